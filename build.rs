@@ -10,6 +10,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let compiler = Compiler::new(SHADER_DIR, std::env::var_os("OUT_DIR").unwrap());
     compiler.compile_shader("fill.vert.glsl");
     compiler.compile_shader("fill.frag.glsl");
+    compiler.compile_shader("image.frag.glsl");
+    compiler.compile_shader("glyph.frag.glsl");
+    compiler.compile_shader("glyph_subpixel.frag.glsl");
+    compiler.compile_shader("color_glyph.frag.glsl");
     compiler.compile_shader("image_upload_uint.comp.glsl");
 
     Ok(())
@@ -0,0 +1,16 @@
+#version 450
+
+layout(location = 0) in vec4 fragColor;
+layout(location = 1) in vec2 fragUv;
+
+// The sampler uses unnormalized coordinates, so UVs are in texels.
+layout(set = 0, binding = 0) uniform sampler2D atlas;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    // Color glyphs store their own colors, which are only faded by the alpha
+    // of the vertex color.
+    vec4 texel = textureLod(atlas, fragUv, 0.0);
+    outColor = vec4(texel.rgb, texel.a * fragColor.a);
+}
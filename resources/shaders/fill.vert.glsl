@@ -2,17 +2,26 @@
 
 layout(location = 0) in ivec2 position;
 layout(location = 1) in vec4 color;
+// Texel coordinates, normalized by the fragment shader if it samples an image.
+layout(location = 2) in ivec2 uv;
 
 layout(push_constant) uniform VertConstants {
     vec2 scale;
     vec2 translate;
+    // Affine transform applied in window coordinates.
+    vec2 x_axis;
+    vec2 y_axis;
+    vec2 translation;
 };
 
 layout(location = 0) out vec4 fragColor;
+layout(location = 1) out vec2 fragUv;
 
 void main() {
-    gl_Position = vec4(position * scale + translate, 0.0, 1.0);
+    vec2 transformed = x_axis * position.x + y_axis * position.y + translation;
+    gl_Position = vec4(transformed * scale + translate, 0.0, 1.0);
 
     // convert from srgb?
     fragColor = color;
+    fragUv = vec2(uv);
 }
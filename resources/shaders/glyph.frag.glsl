@@ -0,0 +1,16 @@
+#version 450
+
+layout(location = 0) in vec4 fragColor;
+layout(location = 1) in vec2 fragUv;
+
+// The sampler uses unnormalized coordinates, so UVs are in texels.
+layout(set = 0, binding = 0) uniform sampler2D atlas;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    // Glyphs store their coverage in the alpha channel. For subpixel glyphs,
+    // this is the greatest coverage of the three color channels.
+    float coverage = textureLod(atlas, fragUv, 0.0).a;
+    outColor = vec4(fragColor.rgb, fragColor.a * coverage);
+}
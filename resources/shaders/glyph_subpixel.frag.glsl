@@ -0,0 +1,19 @@
+#version 450
+
+layout(location = 0) in vec4 fragColor;
+layout(location = 1) in vec2 fragUv;
+
+// The sampler uses unnormalized coordinates, so UVs are in texels.
+layout(set = 0, binding = 0) uniform sampler2D atlas;
+
+// Blended with dual-source blending: each channel of the framebuffer is mixed
+// with the text color by the matching channel of `outBlend`.
+layout(location = 0, index = 0) out vec4 outColor;
+layout(location = 0, index = 1) out vec4 outBlend;
+
+void main() {
+    // Subpixel glyphs store the coverage of each color channel separately.
+    vec4 coverage = textureLod(atlas, fragUv, 0.0);
+    outColor = vec4(fragColor.rgb, 1.0);
+    outBlend = coverage * fragColor.a;
+}
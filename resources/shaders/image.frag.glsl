@@ -0,0 +1,36 @@
+#version 450
+
+layout(location = 0) in vec4 fragColor;
+layout(location = 1) in vec2 fragUv;
+
+// The sampler uses unnormalized coordinates, so UVs are in texels.
+layout(set = 0, binding = 0) uniform sampler2D image;
+
+// Placed after the vertex stage's constants (see FragmentConstants).
+layout(push_constant) uniform FragConstants {
+    layout(offset = 40) uint tiling;
+};
+
+const uint TILING_CLAMP = 0;
+const uint TILING_REPEAT = 1;
+const uint TILING_MIRROR = 2;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    // Unnormalized samplers only support clamping, so tiling is done here.
+    vec2 size = vec2(textureSize(image, 0));
+    vec2 uv = fragUv;
+
+    if (tiling == TILING_REPEAT) {
+        uv = mod(uv, size);
+    } else if (tiling == TILING_MIRROR) {
+        uv = size - abs(mod(uv, 2.0 * size) - size);
+    }
+
+    // Keep the filter inside the image so that edges don't blend with the
+    // sampler's border color.
+    uv = clamp(uv, vec2(0.5), size - 0.5);
+
+    outColor = textureLod(image, uv, 0.0) * fragColor;
+}
@@ -1,20 +1,53 @@
-use std::collections::HashMap;
+use std::{
+    cell::RefCell, collections::HashMap, future::Future, path::PathBuf, rc::Rc, time::Instant,
+};
 
 use crate::{
     gfx::{
-        geometry::{Extent, Offset, Point, Rect},
-        init_gfx, DrawCommandList, ImageCopy, Swapchain,
+        debug,
+        geometry::{
+            Extent, Offset, PhysicalExtent, PhysicalPoint, PhysicalRect, Point, Px, Rect,
+            ScaleFactor,
+        },
+        init_gfx,
+        pixel_buffer::PixelBuffer,
+        DrawCommandList, GfxDevice, GlyphAntiAliasing, ImageCopy, Swapchain,
     },
     gui::{
-        input::{ButtonState, Input, MouseButton},
-        widgets::{DrawContext, LayoutContext, UpdateContext, Widget},
+        accessibility::{perform_action, AccessTree, Request},
+        crash,
+        gestures::GestureRecognizer,
+        hot_reload::{self, HotReload},
+        images::Images,
+        input::{ButtonState, Event as InputEvent, Input, MouseButton},
+        logging,
+        notifications::{Notifications, Severity, Toast, ToastLayer},
+        performance::PerformanceHud,
+        settings::{SettingValue, Settings},
+        state,
+        tasks::{self, Finished, TaskResult, Tasks},
+        ticker::Ticker,
+        tooltip::TooltipManager,
+        undo::UndoStack,
+        widgets::{
+            animate, bind::sync_state, drag_drop::DragDrop, drop_files, find_ime_area,
+            hit_test::cursor_at, DrawContext, FileDialogCallback, LayoutContext, TimerCallback,
+            UpdateContext, Widget,
+        },
     },
     handle_pool::Handle,
     io::image,
     shell::{
-        event::{Event, Window as WindowEvent},
-        {OsShell, Shell, WindowConfig, WindowId},
+        dialog::DialogId,
+        event::{Event, Key, Window as WindowEvent},
+        menu::{Menu, MenuItemId},
+        placement::WindowPlacement,
+        {
+            Clipboard, Composition, CursorIcon, EventLoopProxy, FullscreenMode, OsShell, Shell,
+            TimerId, TrayIcon, TrayIconId, UserEvent, WindowConfig, WindowId,
+        },
     },
+    text::{FontCollection, GlyphAtlas, TextStyle},
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -23,34 +56,497 @@ pub enum Error {
     Renderer(#[from] crate::gfx::Error),
 }
 
+/// How a window anti-aliases its text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAntiAliasing {
+    /// Follows the system's font smoothing setting.
+    #[default]
+    System,
+    Grayscale,
+    /// Uses subpixel anti-aliasing where the window supports it, falling back
+    /// to grayscale otherwise.
+    Subpixel,
+}
+
+/// Describes a window to open. Extents are in logical pixels, and are scaled
+/// for the monitor that the window opens on.
 pub struct AppWindowConfig<'a> {
     pub title: &'a str,
     pub extent: Option<Extent>,
+    /// The smallest size that the user can resize the window to.
+    pub min_extent: Option<Extent>,
+    /// The largest size that the user can resize the window to.
+    pub max_extent: Option<Extent>,
+    pub resizable: bool,
+    pub always_on_top: bool,
+    /// Leaves the window out of the taskbar, such as for floating palettes.
+    pub tool_window: bool,
+    /// Shows what is behind the window through the parts of it that aren't
+    /// opaque. Transparent windows don't have borders or a title bar.
+    pub transparent: bool,
+    /// The icon shown in the window's title bar and the taskbar, or `None`
+    /// for the platform's default.
+    pub icon: Option<&'a PixelBuffer>,
+    /// The menus shown below the window's title bar. Items selected in them
+    /// are passed to the handler set with [`Application::on_menu_item`].
+    pub menu_bar: Option<&'a Menu>,
     pub widget_tree: Box<dyn Widget>,
+    pub text_anti_aliasing: TextAntiAliasing,
+    /// Shows an overlay with the window's frame rate and drawing statistics
+    /// in the top-left corner, drawn with this style. See
+    /// [`performance`](crate::gui::performance).
+    pub performance_hud: Option<TextStyle>,
+    /// Replaces `widget_tree` with one built from a UI description, which is
+    /// rebuilt whenever the description changes in debug builds. See
+    /// [`hot_reload`].
+    pub hot_reload: Option<HotReload>,
+    /// Saves where the window is in the application's
+    /// [`settings`](crate::gui::settings) under this name when it closes, and
+    /// puts it back there when it opens again.
+    pub remember_placement: Option<String>,
+    /// Waits for the display's vertical blank before showing each frame,
+    /// which caps the window's frame rate at the monitor's refresh rate.
+    /// Windows without vsync draw as fast as they can while they animate,
+    /// such as for previews that measure performance. See
+    /// [`AppContext::set_vsync`].
+    pub vsync: bool,
+}
+
+/// Identifies a window opened with [`AppContext::open_window`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AppWindowId(u64);
+
+/// A handle to the running application that widgets and callbacks can keep to
+/// open and close windows and run background tasks. Clones refer to the same
+/// application. See [`Application::context`].
+#[derive(Clone)]
+pub struct AppContext {
+    inner: Rc<RefCell<ContextState>>,
+}
+
+struct ContextState {
+    next_window: u64,
+    requests: Vec<WindowRequest>,
+    tasks: Tasks,
+    settings: Settings,
+    ticker: Ticker,
+    /// The refresh rates of the monitors that the windows are on, for those
+    /// that are known.
+    refresh_rates: HashMap<AppWindowId, f32>,
+}
+
+/// A copy of a [`TrayIcon`], kept until the request for it is applied.
+struct OwnedTrayIcon {
+    icon: PixelBuffer,
+    tooltip: String,
+    menu: Option<Menu>,
+}
+
+/// A change to the application's windows, which is made once the current
+/// event has been handled.
+enum WindowRequest {
+    Open {
+        id: AppWindowId,
+        title: String,
+        icon: Option<PixelBuffer>,
+        menu_bar: Option<Menu>,
+        /// The rest of the window's configuration, without the title, icon,
+        /// and menu bar that it borrowed.
+        config: Box<AppWindowConfig<'static>>,
+    },
+    Close(AppWindowId),
+    SetTrayIcon {
+        window: AppWindowId,
+        tray: Option<OwnedTrayIcon>,
+    },
+    ReplaceWidgetTree {
+        window: AppWindowId,
+        tree: Box<dyn Widget>,
+    },
+    SetVsync {
+        window: AppWindowId,
+        vsync: bool,
+    },
+    SetTitle {
+        window: AppWindowId,
+        title: String,
+    },
+    /// Changes one of the window's properties through the shell.
+    Update {
+        window: AppWindowId,
+        #[allow(clippy::type_complexity)]
+        update: Box<dyn FnOnce(&dyn Shell, WindowId)>,
+    },
+}
+
+impl AppContext {
+    fn new(proxy: EventLoopProxy) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(ContextState {
+                next_window: 0,
+                requests: Vec::new(),
+                tasks: Tasks::new(proxy),
+                settings: Settings::new(),
+                ticker: Ticker::new(),
+                refresh_rates: HashMap::new(),
+            })),
+        }
+    }
+
+    /// The application's settings, which are saved when the last window
+    /// closes. See [`Settings::load`].
+    #[must_use]
+    pub fn settings(&self) -> Settings {
+        self.inner.borrow().settings.clone()
+    }
+
+    /// The callbacks that run once per frame for animations that aren't
+    /// driven by input. See [`ticker`](crate::gui::ticker).
+    #[must_use]
+    pub fn ticker(&self) -> Ticker {
+        self.inner.borrow().ticker.clone()
+    }
+
+    /// Runs `task` on a background thread, then calls `on_done` with its
+    /// result on the UI thread. The callback can change observable
+    /// [`state`] to show the result, which updates the widgets bound to it.
+    /// `on_done` isn't called if the task panics. See [`tasks`].
+    pub fn spawn<T: Send + 'static>(
+        &self,
+        task: impl FnOnce() -> T + Send + 'static,
+        on_done: impl FnOnce(T) + 'static,
+    ) {
+        self.inner.borrow_mut().tasks.spawn(
+            move || Box::new(task()) as TaskResult,
+            Box::new(move |result: TaskResult| on_done(*result.downcast::<T>().unwrap())),
+        );
+    }
+
+    /// Runs `future` to completion on a background thread, then calls
+    /// `on_done` with its output on the UI thread, as with [`Self::spawn`].
+    pub fn spawn_future<T: Send + 'static>(
+        &self,
+        future: impl Future<Output = T> + Send + 'static,
+        on_done: impl FnOnce(T) + 'static,
+    ) {
+        self.spawn(move || tasks::block_on(future), on_done);
+    }
+
+    /// Passes the result of a task that finished to its callback.
+    fn finish_task(&self, finished: Finished) {
+        // The callback may spawn more tasks, so the state isn't borrowed
+        // while it runs.
+        let done = self.inner.borrow_mut().tasks.finish(finished);
+        if let Some((on_done, result)) = done {
+            on_done(result);
+        }
+    }
+
+    /// Opens a window with its own widget tree once the current event has
+    /// been handled, or once the application starts running.
+    pub fn open_window(&self, config: AppWindowConfig) -> AppWindowId {
+        let mut state = self.inner.borrow_mut();
+        let id = AppWindowId(state.next_window);
+        state.next_window += 1;
+
+        state.requests.push(WindowRequest::Open {
+            id,
+            title: config.title.to_owned(),
+            icon: config.icon.cloned(),
+            menu_bar: config.menu_bar.cloned(),
+            config: Box::new(AppWindowConfig {
+                title: "",
+                extent: config.extent,
+                min_extent: config.min_extent,
+                max_extent: config.max_extent,
+                resizable: config.resizable,
+                always_on_top: config.always_on_top,
+                tool_window: config.tool_window,
+                transparent: config.transparent,
+                icon: None,
+                menu_bar: None,
+                widget_tree: config.widget_tree,
+                text_anti_aliasing: config.text_anti_aliasing,
+                performance_hud: config.performance_hud,
+                hot_reload: config.hot_reload,
+                remember_placement: config.remember_placement,
+                vsync: config.vsync,
+            }),
+        });
+
+        id
+    }
+
+    /// Closes the window once the current event has been handled. The
+    /// application exits once every window has been closed.
+    pub fn close_window(&self, window: AppWindowId) {
+        self.inner
+            .borrow_mut()
+            .requests
+            .push(WindowRequest::Close(window));
+    }
+
+    /// Shows an icon for the window in the notification area of the taskbar,
+    /// or removes it if `tray` is `None`. While the window has a tray icon,
+    /// minimizing the window hides it from the taskbar, and clicking the icon
+    /// restores it. Items selected in the icon's menu are passed to the
+    /// handler set with [`Application::on_menu_item`].
+    pub fn set_tray_icon(&self, window: AppWindowId, tray: Option<&TrayIcon>) {
+        let tray = tray.map(|tray| OwnedTrayIcon {
+            icon: tray.icon.clone(),
+            tooltip: tray.tooltip.to_owned(),
+            menu: tray.menu.cloned(),
+        });
+
+        self.inner
+            .borrow_mut()
+            .requests
+            .push(WindowRequest::SetTrayIcon { window, tray });
+    }
+
+    /// Replaces the window's widget tree once the current event has been
+    /// handled, such as to load a new version of it during development. The
+    /// window and its swapchain are kept, but the old tree's timers are
+    /// stopped and the focus is cleared.
+    pub fn replace_widget_tree(&self, window: AppWindowId, tree: Box<dyn Widget>) {
+        self.inner
+            .borrow_mut()
+            .requests
+            .push(WindowRequest::ReplaceWidgetTree { window, tree });
+    }
+
+    /// Changes the text shown in the window's title bar, such as to mark a
+    /// document with unsaved changes. See [`Shell::set_title`].
+    pub fn set_title(&self, window: AppWindowId, title: &str) {
+        self.inner
+            .borrow_mut()
+            .requests
+            .push(WindowRequest::SetTitle {
+                window,
+                title: title.to_owned(),
+            });
+    }
+
+    /// Resizes the area inside the window's borders and title bar to
+    /// `extent` in logical pixels. See [`Shell::set_extent`].
+    pub fn set_extent(&self, window: AppWindowId, extent: Extent) {
+        self.update(window, move |shell, id| {
+            shell.set_extent(id, shell.scale_factor(id).extent_to_physical(extent))
+        });
+    }
+
+    /// Moves the window's top-left corner to `position` on the screen. This is
+    /// in physical pixels, since each monitor may have a different scale. See
+    /// [`Shell::set_position`].
+    pub fn set_position(&self, window: AppWindowId, position: PhysicalPoint) {
+        self.update(window, move |shell, id| shell.set_position(id, position));
+    }
+
+    /// Replaces the window's menu bar, such as to check or disable its
+    /// items, or removes it if `menu` is `None`. See [`Shell::set_menu_bar`].
+    pub fn set_menu_bar(&self, window: AppWindowId, menu: Option<&Menu>) {
+        let menu = menu.cloned();
+        self.update(window, move |shell, id| {
+            shell.set_menu_bar(id, menu.as_ref())
+        });
+    }
+
+    /// Turns vsync on or off for the window, taking effect when it is next
+    /// drawn. See [`AppWindowConfig::vsync`].
+    pub fn set_vsync(&self, window: AppWindowId, vsync: bool) {
+        self.inner
+            .borrow_mut()
+            .requests
+            .push(WindowRequest::SetVsync { window, vsync });
+    }
+
+    /// How many times per second the monitor that the window is on refreshes,
+    /// or `None` if it isn't known or the window hasn't opened yet. This is
+    /// updated when the window moves between monitors. See
+    /// [`Shell::refresh_rate`].
+    #[must_use]
+    pub fn refresh_rate(&self, window: AppWindowId) -> Option<f32> {
+        self.inner.borrow().refresh_rates.get(&window).copied()
+    }
+
+    /// Looks up the refresh rate of the window's monitor again.
+    fn update_refresh_rate(&self, window: &AppWindow, shell: &dyn Shell) {
+        let mut state = self.inner.borrow_mut();
+        match shell.refresh_rate(window.id) {
+            Some(rate) => state.refresh_rates.insert(window.app_id, rate),
+            None => state.refresh_rates.remove(&window.app_id),
+        };
+    }
+
+    pub fn minimize(&self, window: AppWindowId) {
+        self.update(window, |shell, id| shell.minimize(id));
+    }
+
+    pub fn maximize(&self, window: AppWindowId) {
+        self.update(window, |shell, id| shell.maximize(id));
+    }
+
+    pub fn restore(&self, window: AppWindowId) {
+        self.update(window, |shell, id| shell.restore(id));
+    }
+
+    /// Changes the window once the current event has been handled. Does
+    /// nothing if the window has been closed by then.
+    fn update(&self, window: AppWindowId, update: impl FnOnce(&dyn Shell, WindowId) + 'static) {
+        self.inner
+            .borrow_mut()
+            .requests
+            .push(WindowRequest::Update {
+                window,
+                update: Box::new(update),
+            });
+    }
+
+    fn take_requests(&self) -> Vec<WindowRequest> {
+        std::mem::take(&mut self.inner.borrow_mut().requests)
+    }
+}
+
+pub struct Application {
+    shell: OsShell,
+    fonts: Rc<RefCell<FontCollection>>,
+    notifications: Notifications,
+    images: Images,
+    undo_stack: UndoStack,
+    context: AppContext,
+    user_event_handler: Option<Box<dyn FnMut(UserEvent)>>,
+    menu_item_handler: Option<Box<dyn FnMut(AppWindowId, MenuItemId)>>,
 }
 
-#[derive(Default)]
-pub struct Application {}
+impl Default for Application {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Application {
+    /// Creates an application with the system's fonts available.
+    ///
+    /// ## Panics
+    ///
+    /// Only one application may be created for the lifetime of the program.
     #[must_use]
     pub fn new() -> Self {
-        Self::default()
+        let mut fonts = FontCollection::new();
+        fonts.load_system_fonts();
+
+        let shell = OsShell::initialize();
+        let context = AppContext::new(shell.event_loop_proxy());
+
+        Self {
+            shell,
+            fonts: Rc::new(RefCell::new(fonts)),
+            notifications: Notifications::new(),
+            images: Images::new(),
+            undo_stack: UndoStack::new(),
+            context,
+            user_event_handler: None,
+            menu_item_handler: None,
+        }
+    }
+
+    /// The fonts used to lay out and draw text in every window. Widgets that
+    /// draw text need a font from this collection.
+    #[must_use]
+    pub fn fonts(&self) -> Rc<RefCell<FontCollection>> {
+        self.fonts.clone()
+    }
+
+    /// The store of images drawn by widgets, which uploads them to the
+    /// graphics device between frames.
+    #[must_use]
+    pub fn images(&self) -> Images {
+        self.images.clone()
+    }
+
+    /// The queue of toasts shown in every window. Widgets that need to show
+    /// toasts can be given a clone of it.
+    #[must_use]
+    pub fn notifications(&self) -> Notifications {
+        self.notifications.clone()
+    }
+
+    /// The application's undo history. Control+Z undoes the last command
+    /// pushed onto it, and Control+Y or Control+Shift+Z redoes it, unless the
+    /// focused widget handles those keys itself, as text widgets do with
+    /// their own edits. See [`undo`](crate::gui::undo).
+    #[must_use]
+    pub fn undo_stack(&self) -> UndoStack {
+        self.undo_stack.clone()
+    }
+
+    /// A handle that widgets and callbacks can open and close windows and run
+    /// background tasks with while the application is running.
+    #[must_use]
+    pub fn context(&self) -> AppContext {
+        self.context.clone()
+    }
+
+    /// The system clipboard, for callbacks that copy values or paste them.
+    #[must_use]
+    pub fn clipboard(&self) -> Clipboard {
+        self.shell.clipboard()
+    }
+
+    /// Creates a proxy that worker threads can send events to the application
+    /// with, which are passed to the handler set with
+    /// [`Application::on_user_event`].
+    #[must_use]
+    pub fn event_loop_proxy(&self) -> EventLoopProxy {
+        self.shell.event_loop_proxy()
+    }
+
+    /// Handles the events sent with an [`EventLoopProxy`] on the UI thread,
+    /// such as the results of background tasks. Windows are updated
+    /// afterwards, so the handler can change observable
+    /// [`state`](crate::gui::state) to show the results.
+    pub fn on_user_event(&mut self, handler: impl FnMut(UserEvent) + 'static) {
+        self.user_event_handler = Some(Box::new(handler));
+    }
+
+    /// Handles the items that the user selects in the menus of the
+    /// application's windows, such as their menu bars and tray icons.
+    pub fn on_menu_item(&mut self, handler: impl FnMut(AppWindowId, MenuItemId) + 'static) {
+        self.menu_item_handler = Some(Box::new(handler));
+    }
+
+    /// Writes a report to a file in `directory` if the application panics,
+    /// and shows the user an error dialog that says where it is. The report
+    /// describes the panic along with the windows' recent events and widgets.
+    /// See [`crash`].
+    pub fn report_crashes(&mut self, directory: impl Into<PathBuf>) {
+        crash::install(directory.into());
     }
 
     #[allow(clippy::too_many_lines)]
     pub fn run(&mut self, configs: Vec<AppWindowConfig>) {
-        let shell = OsShell::initialize();
+        let shell = &self.shell;
         let gfx = init_gfx().unwrap();
 
         let mut draw_commands = DrawCommandList::new();
 
+        let fonts = self.fonts.clone();
+        let notifications = self.notifications.clone();
+        let undo_stack = self.undo_stack.clone();
+        let drag_drop = DragDrop::new();
+        let images = self.images.clone();
+        let mut user_event_handler = self.user_event_handler.take();
+        let mut menu_item_handler = self.menu_item_handler.take();
+        let mut glyph_atlas = GlyphAtlas::new(gfx.as_ref()).unwrap();
+
         // TODO(straivers): for efficiency, we really should find a way to bind
         // AppWindow to the HWND directly.
         let mut windows = HashMap::<WindowId, AppWindow>::new();
 
         let image_buffer = image::decode_png(&std::fs::read("test.png").unwrap()).unwrap();
-        let image = gfx.create_image(image_buffer.extent()).unwrap();
+        let image = gfx
+            .create_image(image_buffer.extent(), image_buffer.layout())
+            .unwrap();
         gfx.copy_pixels(
             image_buffer.view(),
             image,
@@ -62,27 +558,24 @@ impl Application {
         .unwrap();
 
         for config in configs {
-            let window_id = shell
-                .create_window(&WindowConfig {
-                    title: config.title,
-                    extent: config.extent,
-                })
-                .unwrap();
-
-            let swapchain = gfx.create_swapchain(shell.hwnd(window_id)).unwrap();
-
-            windows.insert(
-                window_id,
-                AppWindow {
-                    swapchain,
-                    extent: Extent::zero(),
-                    input: Input::default(),
-                    widget_tree: config.widget_tree,
-                    needs_repaint: true,
-                },
-            );
+            self.context.open_window(config);
         }
 
+        let context = self.context.clone();
+        let ticker = context.ticker();
+        // The window whose frames the ticker is called with, so that it is
+        // called once per frame however many windows there are.
+        let mut ticker_window = None;
+        apply_window_requests(
+            &context,
+            shell,
+            gfx.as_ref(),
+            &mut windows,
+            &notifications,
+            &drag_drop,
+            &fonts,
+        );
+
         shell.run_event_loop(move |event, shell, control| {
             control.wait();
 
@@ -96,92 +589,496 @@ impl Application {
                         )
                     });
 
+                    // Drawing says nothing about what led up to a crash.
+                    if !matches!(event, WindowEvent::Repaint) {
+                        crash::record_event(window.app_id.0, &event);
+                    }
+
                     match event {
                         WindowEvent::Init { inner_extent } => {
-                            window.extent = inner_extent;
-                            window.needs_repaint = true;
+                            // The swapchain was created at the window's size.
+                            window.resize(inner_extent);
+                            window.swapchain_extent = inner_extent;
+
+                            // Restoring the placement resizes the window,
+                            // which is reported as usual.
+                            let placement = window.placement_key.as_ref().and_then(|key| {
+                                WindowPlacement::from_value(&context.settings().value(key)?)
+                            });
+                            if let Some(placement) = placement {
+                                shell.restore_placement(window_id, &placement);
+                            }
+                            context.update_refresh_rate(window, shell);
+
                             shell.show_window(window_id);
                         }
                         WindowEvent::Destroyed => {
                             let window = windows.remove(&window_id).unwrap();
                             gfx.destroy_swapchain(window.swapchain).unwrap();
+                            crash::forget_window(window.app_id.0);
+                            context
+                                .inner
+                                .borrow_mut()
+                                .refresh_rates
+                                .remove(&window.app_id);
                             std::mem::drop(window);
-                            control.exit();
+
+                            if windows.is_empty() {
+                                // There's nowhere left to show the error.
+                                if let Err(error) = context.settings().save() {
+                                    eprintln!("{error}");
+                                }
+                                control.exit();
+                            }
                         }
                         WindowEvent::CloseRequested => {
+                            window.save_placement(shell, &context.settings());
                             shell.destroy_window(window_id);
                         }
                         WindowEvent::Resized { inner_extent } => {
-                            window.extent = inner_extent;
-                            gfx.resize_swapchain(window.swapchain, inner_extent)
-                                .unwrap();
-                            window.needs_repaint = true;
+                            // The swapchain is resized when the window is next
+                            // drawn, so that a drag that resizes the window
+                            // several times between frames resizes it once.
+                            window.resize(inner_extent);
+
+                            // Windows with tray icons are minimized to the
+                            // tray instead of the taskbar.
+                            if window.extent.area() == 0 && window.tray_icon.is_some() {
+                                shell.hide_window(window_id);
+                            }
+                        }
+                        WindowEvent::TrayIconClicked { .. } => {
+                            if window.extent.area() == 0 {
+                                shell.restore(window_id);
+                            }
+                        }
+                        WindowEvent::MenuItemSelected { item } => {
+                            if let Some(handler) = menu_item_handler.as_mut() {
+                                handler(window.app_id, item);
+                            }
+                        }
+                        WindowEvent::ScaleFactorChanged { dpi } => {
+                            // Every widget in the window is laid out again at
+                            // the new scale, and the size limits are kept the
+                            // same in logical pixels.
+                            let scale_factor = ScaleFactor::from_dpi(dpi);
+                            window.layout_context.set_scale_factor(scale_factor);
+                            window.resize(window.physical_extent);
+                            let (min, max) = window.size_limits;
+                            if min.is_some() || max.is_some() {
+                                shell.set_size_limits(
+                                    window_id,
+                                    min.map(|min| scale_factor.extent_to_physical(min)),
+                                    max.map(|max| scale_factor.extent_to_physical(max)),
+                                );
+                            }
+                            // The window has most likely moved to another
+                            // monitor.
+                            context.update_refresh_rate(window, shell);
+                        }
+                        WindowEvent::SizeMoveStarted => {
+                            let damage = window.tooltips.dismiss();
+                            window.damage = window.damage.union(&damage);
+                        }
+                        WindowEvent::SizeMoveEnded => {
+                            // Make sure that the frame left behind by the drag
+                            // is complete.
+                            window.damage = Rect::new(Point::zero(), window.extent);
+                            context.update_refresh_rate(window, shell);
                         }
                         WindowEvent::CursorMoved { position } => {
+                            let position = window.to_logical(position);
                             window.input.update_cursor_position(position);
-                            window.needs_repaint |= UpdateContext::new(&window.input)
-                                .begin(window.widget_tree.as_mut());
+                            window.update(shell);
                         }
                         WindowEvent::Repaint => {
-                            if window.needs_repaint {
-                                LayoutContext::default()
+                            let started = Instant::now();
+
+                            if ticker.is_active() && ticker_window == Some(window_id) {
+                                ticker.tick(started);
+
+                                // Presenting waits for the display to refresh,
+                                // so the smallest possible frame is drawn to
+                                // keep the ticker in step with the display
+                                // when nothing else changed.
+                                if window.damage.is_empty() {
+                                    window.damage = Rect::new(
+                                        Point::zero(),
+                                        Extent {
+                                            width: Px(1),
+                                            height: Px(1),
+                                        },
+                                    );
+                                }
+                            }
+
+                            // Catch up with state that changed since the last
+                            // frame, such as from callbacks during the update.
+                            if window.state_generation != state::generation() {
+                                window.state_generation = state::generation();
+                                let damage = sync_state(window.widget_tree.as_mut());
+                                window.damage = window.damage.union(&damage);
+                            }
+
+                            let damage = animate(window.widget_tree.as_mut(), Instant::now());
+                            window.is_animating = !damage.is_empty();
+                            window.damage = window.damage.union(&damage);
+
+                            if let Some(gesture) = window.gestures.tick(Instant::now()) {
+                                window.input.update_gesture(gesture);
+                                window.update(shell);
+                            }
+
+                            let damage = window.tooltips.tick(
+                                &window.layout_context,
+                                window.input.cursor_position(),
+                                window.extent,
+                                Instant::now(),
+                            );
+                            window.damage = window.damage.union(&damage);
+
+                            notifications.tick(Instant::now());
+                            let damage = window.toasts.update(
+                                &notifications,
+                                &window.layout_context,
+                                window.extent,
+                            );
+                            window.damage = window.damage.union(&damage);
+
+                            if let Some(hud) = &mut window.performance_hud {
+                                let damage = hud.update(&window.layout_context, Instant::now());
+                                window.damage = window.damage.union(&damage);
+                            }
+
+                            // The drag preview follows the cursor, and is only
+                            // drawn in the window that the cursor is in.
+                            let preview = if drag_drop.is_over_window(window_id) {
+                                drag_drop.layout_preview(
+                                    &mut window.layout_context,
+                                    window.input.cursor_position(),
+                                )
+                            } else {
+                                Rect::zero()
+                            };
+                            if preview != window.drag_preview {
+                                window.damage =
+                                    window.damage.union(&window.drag_preview).union(&preview);
+                                window.drag_preview = preview;
+                            }
+
+                            // A minimized window has nothing to draw to.
+                            if window.extent.area() == 0 {
+                                return;
+                            }
+
+                            if window.swapchain_extent != window.physical_extent {
+                                gfx.resize_swapchain(window.swapchain, window.physical_extent)
+                                    .unwrap();
+                                window.swapchain_extent = window.physical_extent;
+                                window.damage = Rect::new(Point::zero(), window.extent);
+                            }
+
+                            let exclusive = window.input.fullscreen() == FullscreenMode::Exclusive;
+                            if exclusive != window.swapchain_exclusive {
+                                gfx.set_swapchain_exclusive(window.swapchain, exclusive)
+                                    .unwrap();
+                                window.swapchain_exclusive = exclusive;
+                                window.damage = Rect::new(Point::zero(), window.extent);
+                            }
+
+                            if window.vsync != window.swapchain_vsync {
+                                gfx.set_swapchain_vsync(window.swapchain, window.vsync)
+                                    .unwrap();
+                                window.swapchain_vsync = window.vsync;
+                                window.damage = Rect::new(Point::zero(), window.extent);
+                            }
+
+                            // Nothing changed, so there's nothing to record or
+                            // submit.
+                            if !window.damage.is_empty() {
+                                let resized = window
+                                    .layout_context
                                     .begin(window.widget_tree.as_mut(), window.extent);
+                                window.damage = window.damage.union(&resized);
+                                images.flush(gfx.as_ref()).unwrap();
+                                window.describe(shell);
+
+                                // Keep the input method's windows next to the
+                                // caret, which may have moved in the layout.
+                                let ime_area = window.input.focus().and_then(|focus| {
+                                    find_ime_area(window.widget_tree.as_ref(), focus)
+                                });
+                                if let Some(area) = ime_area {
+                                    if area != window.ime_area {
+                                        let scale_factor = window.layout_context.scale_factor();
+                                        shell.set_ime_area(
+                                            window_id,
+                                            scale_factor.rect_to_physical(area),
+                                        );
+                                        window.ime_area = area;
+                                    }
+                                }
+
+                                // Damage is tracked in logical pixels, but the
+                                // swapchain is drawn in physical ones.
+                                let scale_factor = window.layout_context.scale_factor();
+                                let mut damage = scale_factor
+                                    .rect_to_physical(std::mem::take(&mut window.damage));
+                                let region =
+                                    match gfx.get_next_swapchain_image(window.swapchain, damage) {
+                                        Ok(region) => region,
+                                        // The window changed in a way that wasn't
+                                        // reported as a resize, so the swapchain
+                                        // is recreated and redrawn completely.
+                                        Err(crate::gfx::Error::SwapchainOutOfDate) => {
+                                            gfx.resize_swapchain(
+                                                window.swapchain,
+                                                window.physical_extent,
+                                            )
+                                            .unwrap();
+                                            damage = PhysicalRect::new(
+                                                PhysicalPoint::zero(),
+                                                window.physical_extent,
+                                            );
+                                            gfx.get_next_swapchain_image(window.swapchain, damage)
+                                                .unwrap()
+                                        }
+                                        Err(error) => panic!("{error}"),
+                                    };
+
+                                glyph_atlas.begin_frame(gfx.as_ref()).unwrap();
 
                                 draw_commands.clear();
-                                let mut draw_context = DrawContext::new(&mut draw_commands);
+                                let fonts = fonts.borrow();
+                                let mut draw_context = DrawContext::new(
+                                    &mut draw_commands,
+                                    scale_factor.rect_to_logical(region),
+                                );
+                                draw_context.set_scale_factor(scale_factor);
+                                draw_context.set_text_anti_aliasing(window.text_anti_aliasing);
+                                draw_context.set_text_resources(&fonts, &mut glyph_atlas);
+                                draw_context.set_focus(window.input.focus());
                                 draw_context.draw(window.widget_tree.as_ref());
+                                draw_context.draw_overlays(window.widget_tree.as_mut());
+                                window.tooltips.draw(&mut draw_context);
+                                window.toasts.draw(&mut draw_context);
+                                if !window.drag_preview.is_empty() {
+                                    drag_drop.draw_preview(&mut draw_context);
+                                }
+                                if let Some(hud) = &window.performance_hud {
+                                    hud.draw(&mut draw_context);
+                                }
+
+                                glyph_atlas.flush(gfx.as_ref()).unwrap();
+                                // Cached layers recorded this frame are drawn to
+                                // their images now that their glyphs are ready.
+                                // Layers whose images couldn't be drawn keep
+                                // drawing their children instead.
+                                if let Err(error) = images.flush_renders(gfx.as_ref()) {
+                                    eprintln!("{error}");
+                                }
                                 gfx.draw(window.swapchain.into(), &draw_commands).unwrap();
-                                gfx.present_swapchains(&[window.swapchain]).unwrap();
-                                window.needs_repaint = false;
+
+                                // Presenting waits for the display, so it
+                                // isn't counted as time spent on the frame.
+                                let cpu_time = started.elapsed();
+                                match gfx.present_swapchains(&[(window.swapchain, &[damage])]) {
+                                    Ok(()) => {}
+                                    // The frame was lost, so draw it again once
+                                    // the swapchain has been recreated.
+                                    Err(crate::gfx::Error::SwapchainOutOfDate) => {
+                                        window.swapchain_extent = PhysicalExtent::zero();
+                                        window.damage = Rect::new(Point::zero(), window.extent);
+                                    }
+                                    Err(error) => panic!("{error}"),
+                                }
+
+                                if let Some(hud) = &mut window.performance_hud {
+                                    hud.record_frame(Instant::now(), cpu_time, gfx.frame_stats());
+                                }
+
+                                crash::record_frame_stats(gfx.frame_stats());
+                                crash::record_widget_tree(
+                                    window.app_id.0,
+                                    window.widget_tree.as_ref(),
+                                    started,
+                                );
+
+                                // Glyphs that didn't fit in the atlas will have
+                                // room once it has been compacted.
+                                if !glyph_atlas.is_complete() {
+                                    window.damage = Rect::new(Point::zero(), window.extent);
+                                }
                             }
                         }
                         WindowEvent::LeftMouseButtonPressed => {
                             window
                                 .input
                                 .update_mouse_button(MouseButton::Left, ButtonState::Pressed);
-
-                            window.needs_repaint |= UpdateContext::new(&window.input)
-                                .begin(window.widget_tree.as_mut());
+                            window.update(shell);
                         }
                         WindowEvent::LeftMouseButtonReleased => {
                             window
                                 .input
                                 .update_mouse_button(MouseButton::Left, ButtonState::Released);
-
-                            window.needs_repaint |= UpdateContext::new(&window.input)
-                                .begin(window.widget_tree.as_mut());
+                            window.update(shell);
                         }
                         WindowEvent::RightMouseButtonPressed => {
                             window
                                 .input
                                 .update_mouse_button(MouseButton::Right, ButtonState::Pressed);
-
-                            window.needs_repaint |= UpdateContext::new(&window.input)
-                                .begin(window.widget_tree.as_mut());
+                            window.update(shell);
                         }
                         WindowEvent::RightMouseButtonReleased => {
                             window
                                 .input
                                 .update_mouse_button(MouseButton::Right, ButtonState::Released);
-
-                            window.needs_repaint |= UpdateContext::new(&window.input)
-                                .begin(window.widget_tree.as_mut());
+                            window.update(shell);
                         }
                         WindowEvent::MiddleMouseButtonPressed => {
                             window
                                 .input
                                 .update_mouse_button(MouseButton::Middle, ButtonState::Pressed);
-
-                            window.needs_repaint |= UpdateContext::new(&window.input)
-                                .begin(window.widget_tree.as_mut());
+                            window.update(shell);
                         }
                         WindowEvent::MiddleMouseButtonReleased => {
                             window
                                 .input
                                 .update_mouse_button(MouseButton::Middle, ButtonState::Released);
+                            window.update(shell);
+                        }
+                        WindowEvent::MouseWheel { x, y } => {
+                            window.input.update_wheel(x, y);
+                            window.update(shell);
+                        }
+                        WindowEvent::KeyPressed { key, modifiers } => {
+                            // Redraw the whole window so that the capture
+                            // contains a complete frame.
+                            if cfg!(debug_assertions) && key == Key::F11 {
+                                debug::trigger_capture();
+                                window.damage = Rect::new(Point::zero(), window.extent);
+                            }
+
+                            window.input.update_key(key, modifiers);
+                            let handled = window.update(shell);
+
+                            // Commands that change observable state are
+                            // shown once the widgets catch up with it before
+                            // the next frame.
+                            if !handled && modifiers.control {
+                                match key {
+                                    Key::Z if modifiers.shift => {
+                                        undo_stack.redo();
+                                    }
+                                    Key::Z => {
+                                        undo_stack.undo();
+                                    }
+                                    Key::Y => {
+                                        undo_stack.redo();
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                        WindowEvent::CharacterReceived { character } => {
+                            window.input.update_text(character);
+                            window.update(shell);
+                        }
+                        WindowEvent::Touch {
+                            id,
+                            phase,
+                            position,
+                        } => {
+                            let position = window.to_logical(position);
+                            window.input.update_touch(id, phase, position);
+                            window.update(shell);
+
+                            if let Some(gesture) =
+                                window.gestures.touch(id, phase, position, Instant::now())
+                            {
+                                window.input.update_gesture(gesture);
+                                window.update(shell);
+                            }
+                        }
+                        WindowEvent::RawMouseMotion { x, y } => {
+                            window.input.update_raw_mouse_motion(x, y);
+                            window.update(shell);
+                        }
+                        WindowEvent::CompositionStarted => {
+                            window
+                                .input
+                                .update_composition(Some(Composition::default()));
+                            window.update(shell);
+                        }
+                        WindowEvent::CompositionUpdated => {
+                            let composition = shell.composition(window_id);
+                            window.input.update_composition(Some(composition));
+                            window.update(shell);
+                        }
+                        WindowEvent::CompositionEnded => {
+                            window.input.update_composition(None);
+                            window.update(shell);
+                        }
+                        WindowEvent::FilesDropped { position } => {
+                            let position = window.to_logical(position);
+                            let paths = shell.dropped_files(window_id);
+                            if let Some(damage) =
+                                drop_files(window.widget_tree.as_mut(), &paths, position)
+                            {
+                                window.damage = window.damage.union(&damage);
+                            }
+                        }
+                        WindowEvent::Timer { timer } => {
+                            if let Some((_, reload)) =
+                                window.hot_reload.as_mut().filter(|(id, _)| *id == timer)
+                            {
+                                let result = reload.poll();
+                                let style = reload.error_style();
+                                window.reload_widget_tree(shell, result, style);
+                            } else if let Some(index) =
+                                window.timers.iter().position(|(id, _)| *id == timer)
+                            {
+                                if !(window.timers[index].1)() {
+                                    shell.cancel_timer(window_id, timer);
+                                    let _ = window.timers.swap_remove(index);
+                                }
+                            }
+                        }
+                        WindowEvent::AccessibilityRequested => {
+                            window.handle_access_requests(shell);
+                        }
+                        WindowEvent::FileDialogClosed { dialog } => {
+                            let paths = shell.take_dialog_result(dialog);
+                            if let Some(index) =
+                                window.file_dialogs.iter().position(|(id, _)| *id == dialog)
+                            {
+                                let (_, callback) = window.file_dialogs.swap_remove(index);
+                                callback(&paths);
+                            }
+                        }
+                    }
+                }
+                Event::User => {
+                    for event in shell.take_user_events() {
+                        let event = match event.downcast::<Finished>() {
+                            Ok(finished) => {
+                                context.finish_task(*finished);
+                                continue;
+                            }
+                            Err(event) => event,
+                        };
 
-                            window.needs_repaint |= UpdateContext::new(&window.input)
-                                .begin(window.widget_tree.as_mut());
+                        match event.downcast::<logging::Wake>() {
+                            // Views of the log check it for new messages.
+                            Ok(wake) => {
+                                wake.acknowledge();
+                                state::invalidate();
+                            }
+                            Err(event) => {
+                                if let Some(handler) = user_event_handler.as_mut() {
+                                    handler(event);
+                                }
+                            }
                         }
                     }
                 }
@@ -190,14 +1087,574 @@ impl Application {
                     gfx.flush();
                 }
             }
+
+            apply_window_requests(
+                &context,
+                shell,
+                gfx.as_ref(),
+                &mut windows,
+                &notifications,
+                &drag_drop,
+                &fonts,
+            );
+
+            // The ticker follows the first window that can be drawn to,
+            // preferring ones with vsync so that animations run at the
+            // display's rate, and waits while every window is minimized.
+            let can_tick = |id: &WindowId| {
+                windows
+                    .get(id)
+                    .is_some_and(|window| window.extent.area() != 0)
+            };
+            let has_vsync = |id: &WindowId| windows.get(id).is_some_and(|window| window.vsync);
+            if !ticker.is_active() {
+                ticker_window = None;
+            } else if !ticker_window
+                .as_ref()
+                .is_some_and(|id| can_tick(id) && has_vsync(id))
+            {
+                ticker_window = windows
+                    .keys()
+                    .copied()
+                    .find(|id| can_tick(id) && has_vsync(id))
+                    .or_else(|| ticker_window.filter(can_tick))
+                    .or_else(|| windows.keys().copied().find(can_tick));
+            }
+
+            // Windows are only drawn when they ask to be, so that the event
+            // loop can wait for input while nothing changes.
+            for (window_id, window) in &windows {
+                if window.needs_redraw() || ticker_window == Some(*window_id) {
+                    shell.request_redraw(*window_id);
+                }
+            }
         });
     }
 }
 
 struct AppWindow {
+    /// The ID that the window was opened with, for closing it through the
+    /// [`AppContext`].
+    app_id: AppWindowId,
+    /// The icon shown for the window in the notification area, if any.
+    tray_icon: Option<TrayIconId>,
+    /// The size of the window's client area in logical pixels, which is what
+    /// its widgets are laid out in.
     extent: Extent,
+    /// The size of the window's client area as reported by the shell.
+    physical_extent: PhysicalExtent,
+    /// The size limits that the window was opened with, which are applied
+    /// again whenever its scale factor changes.
+    size_limits: (Option<Extent>, Option<Extent>),
     swapchain: Handle<Swapchain>,
     input: Input,
     widget_tree: Box<dyn Widget>,
-    needs_repaint: bool,
+    /// The region of the window that needs to be redrawn, in logical pixels.
+    /// Empty if the window is up to date.
+    damage: Rect,
+    text_anti_aliasing: GlyphAntiAliasing,
+    tooltips: TooltipManager,
+    toasts: ToastLayer,
+    notifications: Notifications,
+    id: WindowId,
+    /// The drag shared by every window.
+    drag_drop: DragDrop,
+    /// Where the drag preview was last drawn in this window. Empty if it
+    /// isn't in this window.
+    drag_preview: Rect,
+    /// The [`state::generation`] when the widgets were last synchronized with
+    /// observable state.
+    state_generation: u64,
+    /// Whether any widget was animating when the last frame was drawn, in
+    /// which case frames are drawn without waiting for input.
+    is_animating: bool,
+    performance_hud: Option<PerformanceHud>,
+    gestures: GestureRecognizer,
+    /// The size that the swapchain was last resized to, which lags behind
+    /// `physical_extent` until the window is drawn.
+    swapchain_extent: PhysicalExtent,
+    /// Whether the swapchain lets the driver take exclusive control of the
+    /// display, which lags behind the window's fullscreen mode until it is
+    /// drawn.
+    swapchain_exclusive: bool,
+    /// Whether the window waits for vertical blank before showing frames.
+    vsync: bool,
+    /// Whether the swapchain waits for vertical blank, which lags behind
+    /// `vsync` until the window is drawn.
+    swapchain_vsync: bool,
+    /// Lays out the window's widgets at the scale factor of the monitor that
+    /// the window is on. Each window has its own, so that a change of scale
+    /// in one window leaves the cached layouts of the others alone.
+    layout_context: LayoutContext,
+    /// The area last given to [`Shell::set_ime_area`].
+    ime_area: Rect,
+    /// The cursor last given to [`Shell::set_cursor`].
+    cursor: CursorIcon,
+    /// Whether the window has captured the mouse with
+    /// [`Shell::set_mouse_capture`].
+    mouse_captured: bool,
+    /// The file dialogs that the window's widgets are waiting on.
+    file_dialogs: Vec<(DialogId, FileDialogCallback)>,
+    /// The timers that the window's widgets started, until their callbacks
+    /// stop them.
+    timers: Vec<(TimerId, TimerCallback)>,
+    /// The UI description that the widget tree is rebuilt from, and the timer
+    /// that checks it for changes.
+    hot_reload: Option<(TimerId, HotReload)>,
+    /// The name of the setting that the window's placement is saved in.
+    placement_key: Option<String>,
+    title: String,
+    /// The description of the window's widgets last given to the shell for
+    /// assistive technology, which its requests are decoded with. `None`
+    /// until the window is first drawn, or if the shell doesn't support
+    /// assistive technology.
+    access_tree: Option<AccessTree>,
+}
+
+/// Opens and closes the windows requested through the [`AppContext`].
+fn apply_window_requests(
+    context: &AppContext,
+    shell: &dyn Shell,
+    gfx: &dyn GfxDevice,
+    windows: &mut HashMap<WindowId, AppWindow>,
+    notifications: &Notifications,
+    drag_drop: &DragDrop,
+    fonts: &Rc<RefCell<FontCollection>>,
+) {
+    for request in context.take_requests() {
+        match request {
+            WindowRequest::Open {
+                id,
+                title,
+                icon,
+                menu_bar,
+                config,
+            } => {
+                let scale_factor = shell.primary_scale_factor();
+                let window_id = shell
+                    .create_window(&WindowConfig {
+                        title: &title,
+                        extent: config
+                            .extent
+                            .map(|extent| scale_factor.extent_to_physical(extent)),
+                        min_extent: config
+                            .min_extent
+                            .map(|extent| scale_factor.extent_to_physical(extent)),
+                        max_extent: config
+                            .max_extent
+                            .map(|extent| scale_factor.extent_to_physical(extent)),
+                        resizable: config.resizable,
+                        always_on_top: config.always_on_top,
+                        tool_window: config.tool_window,
+                        transparent: config.transparent,
+                        icon: icon.as_ref(),
+                        menu_bar: menu_bar.as_ref(),
+                    })
+                    .unwrap();
+
+                #[cfg(target_os = "windows")]
+                let swapchain = gfx
+                    .create_swapchain(shell.hwnd(window_id), config.transparent)
+                    .unwrap();
+
+                #[cfg(target_os = "linux")]
+                let swapchain = {
+                    let (display, xlib_window) = shell.xlib_window(window_id);
+                    gfx.create_swapchain(display, xlib_window, config.transparent)
+                        .unwrap()
+                };
+
+                let prefers_subpixel = match config.text_anti_aliasing {
+                    TextAntiAliasing::System => shell.prefers_subpixel_text(),
+                    TextAntiAliasing::Grayscale => false,
+                    TextAntiAliasing::Subpixel => true,
+                };
+
+                let text_anti_aliasing =
+                    if prefers_subpixel && gfx.supports_subpixel_text(swapchain).unwrap() {
+                        GlyphAntiAliasing::Subpixel
+                    } else {
+                        GlyphAntiAliasing::Grayscale
+                    };
+
+                let mut layout_context = LayoutContext::new(shell.scale_factor(window_id));
+                layout_context.set_fonts(fonts.clone());
+
+                let mut widget_tree = config.widget_tree;
+                let mut hot_reload = None;
+                if let Some(mut reload) = config.hot_reload {
+                    match reload.poll() {
+                        Some(Ok(tree)) => widget_tree = tree,
+                        Some(Err(error)) => {
+                            if let Some(style) = reload.error_style() {
+                                notifications.notify(reload_error(&error, style));
+                            }
+                        }
+                        None => {}
+                    }
+
+                    // Only debug builds watch for changes.
+                    if cfg!(debug_assertions) {
+                        let timer = shell.set_timer(window_id, hot_reload::POLL_INTERVAL, true);
+                        hot_reload = Some((timer, reload));
+                    }
+                }
+
+                windows.insert(
+                    window_id,
+                    AppWindow {
+                        app_id: id,
+                        tray_icon: None,
+                        swapchain,
+                        extent: Extent::zero(),
+                        physical_extent: PhysicalExtent::zero(),
+                        size_limits: (config.min_extent, config.max_extent),
+                        input: Input::default(),
+                        widget_tree,
+                        damage: Rect::zero(),
+                        text_anti_aliasing,
+                        tooltips: TooltipManager::new(),
+                        toasts: ToastLayer::default(),
+                        notifications: notifications.clone(),
+                        id: window_id,
+                        drag_drop: drag_drop.clone(),
+                        drag_preview: Rect::zero(),
+                        state_generation: state::generation(),
+                        is_animating: false,
+                        performance_hud: config.performance_hud.map(PerformanceHud::new),
+                        gestures: GestureRecognizer::new(),
+                        swapchain_extent: PhysicalExtent::zero(),
+                        swapchain_exclusive: false,
+                        vsync: config.vsync,
+                        // Swapchains are created with vsync.
+                        swapchain_vsync: true,
+                        layout_context,
+                        ime_area: Rect::zero(),
+                        cursor: CursorIcon::Arrow,
+                        mouse_captured: false,
+                        file_dialogs: Vec::new(),
+                        timers: Vec::new(),
+                        hot_reload,
+                        placement_key: config.remember_placement,
+                        title,
+                        access_tree: None,
+                    },
+                );
+            }
+            WindowRequest::Close(id) => {
+                if let Some(window) = windows.values().find(|window| window.app_id == id) {
+                    window.save_placement(shell, &context.settings());
+                    shell.destroy_window(window.id);
+                }
+            }
+            WindowRequest::SetTrayIcon { window, tray } => {
+                if let Some(window) = windows.values_mut().find(|w| w.app_id == window) {
+                    match (tray, window.tray_icon) {
+                        (Some(tray), Some(id)) => shell.update_tray_icon(
+                            id,
+                            &TrayIcon {
+                                icon: &tray.icon,
+                                tooltip: &tray.tooltip,
+                                menu: tray.menu.as_ref(),
+                            },
+                        ),
+                        (Some(tray), None) => {
+                            window.tray_icon = Some(shell.add_tray_icon(
+                                window.id,
+                                &TrayIcon {
+                                    icon: &tray.icon,
+                                    tooltip: &tray.tooltip,
+                                    menu: tray.menu.as_ref(),
+                                },
+                            ));
+                        }
+                        (None, Some(id)) => {
+                            shell.remove_tray_icon(id);
+                            window.tray_icon = None;
+                        }
+                        (None, None) => {}
+                    }
+                }
+            }
+            WindowRequest::ReplaceWidgetTree { window, tree } => {
+                if let Some(window) = windows.values_mut().find(|w| w.app_id == window) {
+                    window.replace_widget_tree(shell, tree);
+                }
+            }
+            WindowRequest::SetVsync { window, vsync } => {
+                if let Some(window) = windows.values_mut().find(|w| w.app_id == window) {
+                    window.vsync = vsync;
+                }
+            }
+            WindowRequest::SetTitle { window, title } => {
+                if let Some(window) = windows.values_mut().find(|w| w.app_id == window) {
+                    shell.set_title(window.id, &title);
+                    window.title = title;
+                    // The title names the root of the accessibility tree.
+                    window.describe(shell);
+                }
+            }
+            WindowRequest::Update { window, update } => {
+                if let Some(window) = windows.values().find(|w| w.app_id == window) {
+                    update(shell, window.id);
+                }
+            }
+        }
+    }
+}
+
+/// A toast describing why a UI description couldn't be loaded.
+fn reload_error(error: &hot_reload::Error, style: TextStyle) -> Toast {
+    Toast::new(Severity::Error, error.to_string(), style)
+}
+
+impl AppWindow {
+    /// Saves where the window is, if it remembers its placement.
+    fn save_placement(&self, shell: &dyn Shell, settings: &Settings) {
+        if let Some(key) = &self.placement_key {
+            settings.set_value(key, shell.window_placement(self.id).to_value());
+        }
+    }
+
+    /// Checks if the window has changed since it was last drawn, or is
+    /// waiting for time to pass, such as for pending tooltips to wait out
+    /// their hover delay, resting fingers to become long presses, toasts to
+    /// expire, and animations to finish.
+    fn needs_redraw(&self) -> bool {
+        // A minimized window has nothing to draw to, and is drawn completely
+        // once it is restored.
+        if self.extent.area() == 0 {
+            return false;
+        }
+
+        !self.damage.is_empty()
+            || self.is_animating
+            || self.tooltips.is_pending()
+            || self.gestures.is_pending()
+            || self.notifications.has_timers()
+            || self.state_generation != state::generation()
+            || self.drag_drop.is_over_window(self.id)
+            || !self.drag_preview.is_empty()
+            || self.swapchain_extent != self.physical_extent
+            || self.swapchain_exclusive != (self.input.fullscreen() == FullscreenMode::Exclusive)
+            || self.swapchain_vsync != self.vsync
+    }
+
+    /// Records the size of the window's client area, and converts it to
+    /// logical pixels for layout. The whole window is redrawn.
+    fn resize(&mut self, inner_extent: PhysicalExtent) {
+        self.physical_extent = inner_extent;
+        self.extent = self
+            .layout_context
+            .scale_factor()
+            .extent_to_logical(inner_extent);
+        self.damage = Rect::new(Point::zero(), self.extent);
+    }
+
+    /// Converts a position reported by the shell to the logical pixels that
+    /// input is handled in.
+    fn to_logical(&self, position: PhysicalPoint) -> Point {
+        self.layout_context
+            .scale_factor()
+            .point_to_logical(position)
+    }
+
+    /// Describes the window's widgets to assistive technology, as they were
+    /// last laid out.
+    fn describe(&mut self, shell: &dyn Shell) {
+        if !shell.features().accessibility || self.extent.area() == 0 {
+            return;
+        }
+
+        let tree = AccessTree::build(self.widget_tree.as_ref(), &self.title);
+        let update = tree.to_update(self.input.focus(), self.layout_context.scale_factor());
+        shell.update_accessibility(self.id, update);
+        self.access_tree = Some(tree);
+    }
+
+    /// Moves the focus or performs the actions that assistive technology
+    /// asked for since the window was last described.
+    fn handle_access_requests(&mut self, shell: &dyn Shell) {
+        let focus = self.input.focus();
+        let mut performed = false;
+        for request in shell.take_access_requests(self.id) {
+            let request = self
+                .access_tree
+                .as_ref()
+                .and_then(|tree| tree.request(&request));
+
+            match request {
+                Some(Request::Focus(target)) => self.input.set_focus(Some(target)),
+                Some(Request::Act(target, action)) => {
+                    performed |= perform_action(self.widget_tree.as_mut(), target, &action);
+                }
+                None => {}
+            }
+        }
+
+        // An action may change widgets other than its target, and the widget
+        // that lost the focus isn't told.
+        if performed || self.input.focus() != focus {
+            self.damage = Rect::new(Point::zero(), self.extent);
+        }
+    }
+
+    /// Replaces the widget tree, keeping the window and its swapchain. The old
+    /// tree's timers and focus don't apply to the new one, so they're dropped.
+    fn replace_widget_tree(&mut self, shell: &dyn Shell, tree: Box<dyn Widget>) {
+        self.widget_tree = tree;
+
+        for (timer, _) in self.timers.drain(..) {
+            shell.cancel_timer(self.id, timer);
+        }
+
+        self.input.set_focus(None);
+        let _ = self.tooltips.dismiss();
+        self.damage = Rect::new(Point::zero(), self.extent);
+    }
+
+    /// Applies the result of polling the window's UI description, keeping the
+    /// current tree if the description has an error.
+    fn reload_widget_tree(
+        &mut self,
+        shell: &dyn Shell,
+        result: Option<Result<Box<dyn Widget>, hot_reload::Error>>,
+        error_style: Option<TextStyle>,
+    ) {
+        match result {
+            Some(Ok(tree)) => self.replace_widget_tree(shell, tree),
+            Some(Err(error)) => {
+                if let Some(style) = error_style {
+                    self.notifications.notify(reload_error(&error, style));
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Passes the latest input event to the widget tree, and records the parts
+    /// of the window that need to be redrawn as a result. Returns `true` if a
+    /// widget marked the event as handled. See [`UpdateContext::set_handled`].
+    fn update(&mut self, shell: &dyn Shell) -> bool {
+        // Clicks on toasts don't reach the widgets beneath them.
+        if let InputEvent::MouseButton {
+            button: MouseButton::Left,
+            state: ButtonState::Pressed,
+        } = self.input.event()
+        {
+            if let Some(id) = self.toasts.toast_at(self.input.cursor_position()) {
+                self.notifications.click(id);
+                return true;
+            }
+        }
+
+        let focus = self.input.focus();
+        let raw_mouse_input = self.input.wants_raw_mouse_input();
+        let cursor_confinement = self.input.cursor_confinement();
+        let cursor_hidden = self.input.is_cursor_hidden();
+        let fullscreen = self.input.fullscreen();
+        if let InputEvent::CursorMove { .. } = self.input.event() {
+            self.drag_drop.set_window(self.id);
+        }
+
+        let mut context = UpdateContext::new(&mut self.input)
+            .with_shell(shell)
+            .with_window_extent(self.extent)
+            .with_drag_drop(&self.drag_drop);
+        let damage = context.begin(self.widget_tree.as_mut());
+        let redraw_window = context.needs_window_redraw();
+        let file_dialogs = context.take_file_dialogs();
+        let timers = context.take_timers();
+        let handled = context.is_handled();
+        self.damage = self.damage.union(&damage);
+
+        // Releasing the button ends the drag, even if it wasn't dropped on a
+        // target.
+        if let InputEvent::MouseButton {
+            button: MouseButton::Left,
+            state: ButtonState::Released,
+        } = self.input.event()
+        {
+            self.drag_drop.cancel();
+        }
+
+        let damage = match self.input.event() {
+            InputEvent::None | InputEvent::RawMouseMotion { .. } => Rect::zero(),
+            InputEvent::CursorMove { position } => {
+                self.tooltips
+                    .hover(self.widget_tree.as_ref(), position, Instant::now())
+            }
+            InputEvent::MouseButton { .. }
+            | InputEvent::Scroll { .. }
+            | InputEvent::KeyPress { .. }
+            | InputEvent::Text { .. }
+            | InputEvent::Composition
+            | InputEvent::Touch { .. }
+            | InputEvent::Gesture { .. } => self.tooltips.dismiss(),
+        };
+        self.damage = self.damage.union(&damage);
+
+        // Pressing or releasing a button can change the cursor without moving
+        // it, such as when a drag ends.
+        if let InputEvent::CursorMove { .. } | InputEvent::MouseButton { .. } = self.input.event() {
+            let cursor = cursor_at(self.widget_tree.as_ref(), self.input.cursor_position());
+            if cursor != self.cursor {
+                shell.set_cursor(self.id, cursor);
+                self.cursor = cursor;
+            }
+        }
+
+        for request in file_dialogs {
+            let dialog = if request.save {
+                shell.save_file_dialog(self.id, &request.dialog)
+            } else {
+                shell.open_file_dialog(self.id, &request.dialog)
+            };
+            self.file_dialogs.push((dialog, request.callback));
+        }
+
+        for request in timers {
+            let timer = shell.set_timer(self.id, request.interval, true);
+            self.timers.push((timer, request.callback));
+        }
+
+        if self.input.wants_raw_mouse_input() != raw_mouse_input {
+            shell.set_raw_mouse_input(self.id, self.input.wants_raw_mouse_input());
+        }
+
+        if self.input.cursor_confinement() != cursor_confinement {
+            let scale_factor = self.layout_context.scale_factor();
+            let area = self
+                .input
+                .cursor_confinement()
+                .map(|area| scale_factor.rect_to_physical(area));
+            shell.confine_cursor(self.id, area);
+        }
+
+        if self.input.is_cursor_hidden() != cursor_hidden {
+            shell.set_cursor_visible(self.id, !self.input.is_cursor_hidden());
+        }
+
+        if self.input.fullscreen() != fullscreen {
+            shell.set_fullscreen(self.id, self.input.fullscreen());
+        }
+
+        // The mouse is captured while any button is held so that drags keep
+        // reporting the cursor after it leaves the window.
+        let mouse_captured = [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+            .into_iter()
+            .any(|button| self.input.mouse_button_state(button).is_pressed());
+        if mouse_captured != self.mouse_captured {
+            shell.set_mouse_capture(self.id, mouse_captured);
+            self.mouse_captured = mouse_captured;
+        }
+
+        // The widget that lost the focus isn't told, so the whole window is
+        // redrawn to update it.
+        if redraw_window || self.input.focus() != focus {
+            self.damage = Rect::new(Point::zero(), self.extent);
+        }
+
+        handled
+    }
 }
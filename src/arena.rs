@@ -0,0 +1,302 @@
+use std::{
+    alloc::{alloc, dealloc, handle_alloc_error, Layout},
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+/// A bump allocator for short-lived allocations that are all freed at once,
+/// such as the scratch buffers used while laying out or drawing a frame.
+///
+/// Allocating from the arena only requires a shared reference, so any number
+/// of allocations may be live at the same time. Resetting the arena requires a
+/// mutable reference, which guarantees that no allocations outlive it. After
+/// the first few frames the arena settles on a single chunk large enough for
+/// the whole frame, so that no further heap allocations are needed.
+///
+/// Note that the arena never runs destructors for values allocated with
+/// [`Self::alloc`]. [`ArenaVec`] drops its elements as usual.
+#[derive(Default)]
+pub struct FrameArena {
+    chunks: RefCell<Vec<Chunk>>,
+    /// The number of bytes used in the last chunk.
+    used: Cell<usize>,
+}
+
+impl FrameArena {
+    /// The size of the first chunk allocated by an empty arena.
+    pub const MIN_CHUNK_SIZE: usize = 4096;
+
+    /// The alignment of every chunk. Allocations with a larger alignment are
+    /// supported, but may waste some space.
+    const CHUNK_ALIGN: usize = 16;
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an arena with space for at least `capacity` bytes before it
+    /// needs to allocate more memory.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        let arena = Self::new();
+        if capacity > 0 {
+            arena.chunks.borrow_mut().push(Chunk::new(capacity));
+        }
+        arena
+    }
+
+    /// The total number of bytes that the arena can hold before it needs to
+    /// allocate another chunk.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.chunks.borrow().iter().map(|c| c.layout.size()).sum()
+    }
+
+    /// Frees every allocation made from the arena. If the arena had to grow
+    /// since the last reset, its chunks are merged into one so that the next
+    /// frame can be served from a single chunk.
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut();
+        if chunks.len() > 1 {
+            let capacity = chunks.iter().map(|c| c.layout.size()).sum();
+            chunks.clear();
+            chunks.push(Chunk::new(capacity));
+        }
+        self.used.set(0);
+    }
+
+    /// Moves `value` into the arena, returning a mutable reference to it. The
+    /// value's destructor will not be run.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, value: T) -> &mut T {
+        let ptr = self.alloc_layout(Layout::new::<T>()).cast::<T>();
+        // SAFETY: The pointer is valid for writes of `T` and is not aliased
+        // until the arena is reset, which requires `&mut self`.
+        unsafe {
+            ptr.as_ptr().write(value);
+            &mut *ptr.as_ptr()
+        }
+    }
+
+    /// Allocates uninitialized memory for `layout`. The memory remains valid
+    /// until the arena is reset or dropped.
+    fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
+        if layout.size() == 0 {
+            // SAFETY: Alignments are never 0.
+            return unsafe { NonNull::new_unchecked(layout.align() as *mut u8) };
+        }
+
+        let mut chunks = self.chunks.borrow_mut();
+
+        if let Some(chunk) = chunks.last() {
+            if let Some(ptr) = chunk.try_alloc(&self.used, layout) {
+                return ptr;
+            }
+        }
+
+        let last_size = chunks.last().map_or(0, |c| c.layout.size());
+        let size = (last_size * 2)
+            .max(layout.size() + layout.align())
+            .max(Self::MIN_CHUNK_SIZE);
+
+        let chunk = Chunk::new(size);
+        self.used.set(0);
+        let ptr = chunk
+            .try_alloc(&self.used, layout)
+            .expect("a new chunk is always large enough for the allocation");
+        chunks.push(chunk);
+        ptr
+    }
+}
+
+/// A single allocation from the global allocator that the arena bumps through.
+/// The memory is accessed only through raw pointers, so handing out references
+/// into a chunk never conflicts with the chunk itself.
+struct Chunk {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+// SAFETY: A chunk owns its allocation, and only the arena that holds it hands
+// out references into it.
+unsafe impl Send for Chunk {}
+
+impl Chunk {
+    fn new(size: usize) -> Self {
+        let layout = Layout::from_size_align(size, FrameArena::CHUNK_ALIGN).unwrap();
+        // SAFETY: The layout has a non-zero size.
+        let ptr = unsafe { alloc(layout) };
+        let ptr = NonNull::new(ptr).unwrap_or_else(|| handle_alloc_error(layout));
+        Self { ptr, layout }
+    }
+
+    fn try_alloc(&self, used: &Cell<usize>, layout: Layout) -> Option<NonNull<u8>> {
+        let base = self.ptr.as_ptr() as usize;
+        let start = (base + used.get()).checked_add(layout.align() - 1)? & !(layout.align() - 1);
+        let end = start.checked_add(layout.size())?;
+
+        if end <= base + self.layout.size() {
+            used.set(end - base);
+            // SAFETY: `start - base` is within the chunk, so the offset pointer
+            // is in bounds and non-null.
+            Some(unsafe { NonNull::new_unchecked(self.ptr.as_ptr().add(start - base)) })
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        // SAFETY: The pointer was allocated with this layout in `Chunk::new`.
+        unsafe { dealloc(self.ptr.as_ptr(), self.layout) };
+    }
+}
+
+/// A growable array backed by a [`FrameArena`]. When it runs out of space, the
+/// elements are copied into a new, larger allocation; the old one is reclaimed
+/// when the arena is reset.
+#[must_use]
+pub struct ArenaVec<'a, T> {
+    arena: &'a FrameArena,
+    ptr: NonNull<T>,
+    len: usize,
+    capacity: usize,
+    phantom: PhantomData<T>,
+}
+
+impl<'a, T> ArenaVec<'a, T> {
+    pub fn new(arena: &'a FrameArena) -> Self {
+        Self::with_capacity(arena, 0)
+    }
+
+    pub fn with_capacity(arena: &'a FrameArena, capacity: usize) -> Self {
+        let ptr = if capacity == 0 {
+            NonNull::dangling()
+        } else {
+            arena
+                .alloc_layout(Layout::array::<T>(capacity).unwrap())
+                .cast()
+        };
+
+        Self {
+            arena,
+            ptr,
+            len: 0,
+            capacity,
+            phantom: PhantomData,
+        }
+    }
+
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.len == self.capacity {
+            self.grow();
+        }
+
+        // SAFETY: `len < capacity`, so the slot is within the allocation.
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            // SAFETY: The element at `len` was initialized and is no longer
+            // considered part of the array.
+            Some(unsafe { self.ptr.as_ptr().add(self.len).read() })
+        }
+    }
+
+    fn grow(&mut self) {
+        let capacity = (self.capacity * 2).max(4);
+        let ptr = self
+            .arena
+            .alloc_layout(Layout::array::<T>(capacity).unwrap())
+            .cast::<T>();
+
+        // SAFETY: The new allocation is distinct from the old one and large
+        // enough to hold `len` elements. The old elements are moved, not
+        // copied, since the old allocation is never read again.
+        unsafe { std::ptr::copy_nonoverlapping(self.ptr.as_ptr(), ptr.as_ptr(), self.len) };
+
+        self.ptr = ptr;
+        self.capacity = capacity;
+    }
+}
+
+impl<'a, T> Deref for ArenaVec<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The first `len` elements are initialized.
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<'a, T> DerefMut for ArenaVec<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: The first `len` elements are initialized.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<'a, T> Drop for ArenaVec<'a, T> {
+    fn drop(&mut self) {
+        // SAFETY: The first `len` elements are initialized and are not used
+        // again.
+        unsafe { std::ptr::drop_in_place(&mut **self) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_arena() {
+        let mut arena = FrameArena::with_capacity(64);
+
+        {
+            let a = arena.alloc(1u8);
+            let b = arena.alloc(2u64);
+            *a += 1;
+            *b += 1;
+            assert_eq!((*a, *b), (2, 3));
+            assert_eq!(b as *mut u64 as usize % std::mem::align_of::<u64>(), 0);
+
+            let mut vec = ArenaVec::new(&arena);
+            for i in 0..100u32 {
+                vec.push(i);
+            }
+            assert_eq!(vec.len(), 100);
+            assert!(vec.iter().copied().eq(0..100));
+            assert_eq!(vec.pop(), Some(99));
+        }
+
+        // The arena grew past its first chunk, so reset should merge them.
+        assert!(arena.chunks.borrow().len() > 1);
+        let capacity = arena.capacity();
+        arena.reset();
+        assert_eq!(arena.chunks.borrow().len(), 1);
+        assert_eq!(arena.capacity(), capacity);
+
+        let rc = std::rc::Rc::new(());
+        {
+            let mut vec = ArenaVec::with_capacity(&arena, 1);
+            vec.push(rc.clone());
+            vec.push(rc.clone());
+            assert_eq!(std::rc::Rc::strong_count(&rc), 3);
+        }
+        assert_eq!(std::rc::Rc::strong_count(&rc), 1);
+    }
+}
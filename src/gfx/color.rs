@@ -1,7 +1,7 @@
 use rand::{distributions::Standard, prelude::Distribution};
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -56,6 +56,101 @@ impl Color {
     pub fn to_array(&self) -> [f32; 4] {
         [self.r, self.g, self.b, self.a]
     }
+
+    /// Converts the color to bytes, as it would be stored in an `RGBA8`
+    /// image.
+    #[must_use]
+    pub fn to_rgba8(&self) -> [u8; 4] {
+        self.to_array().map(to_byte)
+    }
+
+    /// Creates a color from its hue in degrees, and its saturation, value and
+    /// alpha between 0 and 1.
+    #[must_use]
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32, alpha: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let m = value - chroma;
+
+        // Truncation picks the sextant of the color wheel that the hue is in.
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+
+        Self {
+            r: r + m,
+            g: g + m,
+            b: b + m,
+            a: alpha,
+        }
+    }
+
+    /// Finds the hue in degrees, and the saturation and value between 0 and 1
+    /// of the color. The hue of a gray is 0.
+    #[must_use]
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let chroma = max - min;
+
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == self.r {
+            60.0 * ((self.g - self.b) / chroma).rem_euclid(6.0)
+        } else if max == self.g {
+            60.0 * ((self.b - self.r) / chroma + 2.0)
+        } else {
+            60.0 * ((self.r - self.g) / chroma + 4.0)
+        };
+
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+        (hue, saturation, max)
+    }
+
+    /// Formats the color as `#rrggbb`, or `#rrggbbaa` if it isn't opaque.
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        let [r, g, b, a] = self.to_rgba8();
+        if a == u8::MAX {
+            format!("#{r:02x}{g:02x}{b:02x}")
+        } else {
+            format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+        }
+    }
+
+    /// Parses a color written as `rrggbb` or `rrggbbaa` in hexadecimal, with
+    /// or without a leading `#`. Returns `None` if `hex` isn't a color.
+    #[must_use]
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.trim().strip_prefix('#').unwrap_or(hex.trim());
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+            return None;
+        }
+
+        let byte = |i: usize| {
+            u8::from_str_radix(hex.get(i..i + 2)?, 16)
+                .ok()
+                .map(|byte| f32::from(byte) / 255.0)
+        };
+
+        Some(Self {
+            r: byte(0)?,
+            g: byte(2)?,
+            b: byte(4)?,
+            a: if hex.len() == 8 { byte(6)? } else { 1.0 },
+        })
+    }
+}
+
+/// Converts a color channel between 0 and 1 to a byte.
+fn to_byte(channel: f32) -> u8 {
+    (channel.clamp(0.0, 1.0) * 255.0).round() as u8
 }
 
 impl Distribution<Color> for Standard {
@@ -68,3 +163,46 @@ impl Distribution<Color> for Standard {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Color, b: Color) {
+        let close = a
+            .to_array()
+            .iter()
+            .zip(b.to_array())
+            .all(|(a, b)| (a - b).abs() < 1e-3);
+        assert!(close, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn hsv() {
+        assert_close(Color::from_hsv(0.0, 1.0, 1.0, 1.0), Color::RED);
+        assert_close(Color::from_hsv(120.0, 1.0, 1.0, 1.0), Color::GREEN);
+        assert_close(Color::from_hsv(240.0, 1.0, 1.0, 1.0), Color::BLUE);
+        assert_close(Color::from_hsv(-120.0, 1.0, 1.0, 1.0), Color::BLUE);
+        assert_close(Color::from_hsv(90.0, 0.0, 1.0, 1.0), Color::WHITE);
+
+        let color = Color {
+            r: 0.2,
+            g: 0.6,
+            b: 0.4,
+            a: 0.5,
+        };
+        let (h, s, v) = color.to_hsv();
+        assert_close(Color::from_hsv(h, s, v, 0.5), color);
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(Color::RED.to_hex(), "#ff0000");
+        assert_eq!(Color::ZERO.to_hex(), "#00000000");
+
+        assert_close(Color::from_hex("#0000ff").unwrap(), Color::BLUE);
+        assert_close(Color::from_hex("ffffffff").unwrap(), Color::WHITE);
+        assert!(Color::from_hex("#fff").is_none());
+        assert!(Color::from_hex("#gg0000").is_none());
+    }
+}
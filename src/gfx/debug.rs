@@ -0,0 +1,155 @@
+//! Hooks for graphics debuggers.
+//!
+//! When the program is launched from (or injected by) RenderDoc, calling
+//! [`trigger_capture`] records the next frame that fathom draws. A frame begins
+//! with the first call to [`GfxDevice::draw`](super::GfxDevice::draw) after the
+//! capture was requested and ends once the swapchains are presented, so the
+//! capture contains exactly one frame regardless of how many windows are open.
+//!
+//! These functions do nothing if RenderDoc is not loaded.
+
+use std::{
+    ffi::c_void,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use once_cell::sync::OnceCell;
+
+/// The version of the RenderDoc in-application API that we request,
+/// `eRENDERDOC_API_Version_1_1_2`.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+const RENDERDOC_API_VERSION: i32 = 10102;
+
+static RENDERDOC: OnceCell<Option<RenderDoc>> = OnceCell::new();
+static CAPTURE_REQUESTED: AtomicBool = AtomicBool::new(false);
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+
+/// Requests that the next frame be captured. Does nothing if RenderDoc is not
+/// attached to the process.
+pub fn trigger_capture() {
+    if is_capture_available() {
+        CAPTURE_REQUESTED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Checks if RenderDoc is attached to the process.
+#[must_use]
+pub fn is_capture_available() -> bool {
+    renderdoc().is_some()
+}
+
+/// Begins a capture if one was requested and none is in progress. Called by
+/// the backend at the start of each draw.
+pub(crate) fn begin_frame() {
+    if CAPTURE_REQUESTED.swap(false, Ordering::Relaxed) && !CAPTURING.load(Ordering::Relaxed) {
+        if let Some(renderdoc) = renderdoc() {
+            // SAFETY: Null device and window pointers capture every device and
+            // window, which is always valid.
+            unsafe { (renderdoc.api().start_frame_capture)(std::ptr::null(), std::ptr::null()) };
+            CAPTURING.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Ends the capture in progress, if any. Called by the backend after
+/// presenting.
+pub(crate) fn end_frame() {
+    if CAPTURING.swap(false, Ordering::Relaxed) {
+        if let Some(renderdoc) = renderdoc() {
+            // SAFETY: A capture was started with the same arguments.
+            unsafe { (renderdoc.api().end_frame_capture)(std::ptr::null(), std::ptr::null()) };
+        }
+    }
+}
+
+fn renderdoc() -> Option<&'static RenderDoc> {
+    RENDERDOC.get_or_init(RenderDoc::load).as_ref()
+}
+
+type Unused = *const c_void;
+
+/// The signature of `RENDERDOC_GetAPI`.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+type GetApi = unsafe extern "C" fn(version: i32, api: *mut *const c_void) -> i32;
+
+/// The function table of `RENDERDOC_API_1_1_2`. Only the functions that we use
+/// are typed; the rest are placeholders to preserve the layout.
+#[repr(C)]
+struct RenderDocApi {
+    _unused: [Unused; 19],
+    start_frame_capture: unsafe extern "C" fn(device: *const c_void, window: *const c_void),
+    _is_frame_capturing: Unused,
+    end_frame_capture: unsafe extern "C" fn(device: *const c_void, window: *const c_void) -> u32,
+    _trigger_multi_frame_capture: Unused,
+}
+
+struct RenderDoc {
+    api: *const RenderDocApi,
+}
+
+// SAFETY: The API table is owned by RenderDoc, is never modified, and its
+// functions are thread-safe.
+unsafe impl Send for RenderDoc {}
+unsafe impl Sync for RenderDoc {}
+
+impl RenderDoc {
+    fn api(&self) -> &RenderDocApi {
+        // SAFETY: The pointer was checked to be non-null when loaded and lives
+        // as long as the RenderDoc module, which is never unloaded.
+        unsafe { &*self.api }
+    }
+
+    /// Retrieves the API from the RenderDoc module if it has already been
+    /// loaded into the process. We never load it ourselves, since RenderDoc
+    /// must be present before the Vulkan instance is created to hook it.
+    #[cfg(target_os = "windows")]
+    fn load() -> Option<Self> {
+        use windows::{
+            s, w,
+            Win32::System::LibraryLoader::{GetModuleHandleW, GetProcAddress},
+        };
+
+        let module = unsafe { GetModuleHandleW(w!("renderdoc.dll")) }.ok()?;
+        let get_api = unsafe { GetProcAddress(module, s!("RENDERDOC_GetAPI")) }?;
+        // SAFETY: `RENDERDOC_GetAPI` has this signature.
+        let get_api =
+            unsafe { std::mem::transmute::<unsafe extern "system" fn() -> isize, GetApi>(get_api) };
+
+        Self::from_get_api(get_api)
+    }
+
+    /// See the Windows version. `RTLD_NOLOAD` makes `dlopen` fail instead of
+    /// loading the module if it isn't already loaded.
+    #[cfg(target_os = "linux")]
+    fn load() -> Option<Self> {
+        use libloading::os::unix::{Library, RTLD_NOW};
+
+        // SAFETY: The module is already loaded, so no initialization code is
+        // run by opening it again.
+        let library =
+            unsafe { Library::open(Some("librenderdoc.so"), RTLD_NOW | libc::RTLD_NOLOAD) }.ok()?;
+        // SAFETY: `RENDERDOC_GetAPI` has this signature.
+        let get_api = *unsafe { library.get::<GetApi>(b"RENDERDOC_GetAPI\0") }.ok()?;
+
+        // Our reference to the module is kept so that the API stays valid even
+        // if RenderDoc's own reference is released.
+        std::mem::forget(library);
+
+        Self::from_get_api(get_api)
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    fn load() -> Option<Self> {
+        None
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
+    fn from_get_api(get_api: GetApi) -> Option<Self> {
+        let mut api = std::ptr::null();
+        if unsafe { get_api(RENDERDOC_API_VERSION, &mut api) } == 1 && !api.is_null() {
+            Some(Self { api: api.cast() })
+        } else {
+            None
+        }
+    }
+}
@@ -1,6 +1,13 @@
 /// The smallest unit of measurement in the UI. It has the same span as a 16-bit
 /// signed integer (`i16`).
 ///
+/// `Px` is measured in logical pixels, which are independent of the display's
+/// density: one logical pixel spans one physical pixel at a [`ScaleFactor`] of
+/// 1.0 (96 DPI on Windows), and more physical pixels on denser displays.
+/// Layout, input, and drawing all work in logical pixels. Only the shell and
+/// the swapchains of the graphics device deal in [`PhysicalPx`], and
+/// [`ScaleFactor`] converts between the two.
+///
 /// It is important to note that conversions from floats always round towards 0.
 #[repr(transparent)]
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
@@ -106,6 +113,178 @@ impl std::cmp::PartialOrd<i32> for Px {
 
 impl Px {
     pub const MAX: Self = Px(i16::MAX);
+    pub const MIN: Self = Px(i16::MIN);
+
+    /// Returns `None` if the sum overflows.
+    pub fn checked_add(self, other: Px) -> Option<Px> {
+        self.0.checked_add(other.0).map(Px)
+    }
+
+    /// Returns `None` if the difference overflows.
+    pub fn checked_sub(self, other: Px) -> Option<Px> {
+        self.0.checked_sub(other.0).map(Px)
+    }
+
+    /// Adds, clamping to [`Px::MIN`] or [`Px::MAX`] on overflow.
+    #[must_use]
+    pub fn saturating_add(self, other: Px) -> Px {
+        Px(self.0.saturating_add(other.0))
+    }
+
+    /// Subtracts, clamping to [`Px::MIN`] or [`Px::MAX`] on overflow.
+    #[must_use]
+    pub fn saturating_sub(self, other: Px) -> Px {
+        Px(self.0.saturating_sub(other.0))
+    }
+}
+
+/// Another name for [`Px`], for places where the distinction from
+/// [`PhysicalPx`] needs to be made explicit.
+pub type LogicalPx = Px;
+
+/// A device pixel, as used by the shell for window geometry and by the graphics
+/// device for swapchain images. It has the same span as [`Px`], but is a
+/// distinct type so that the two can't be mixed up without going through a
+/// [`ScaleFactor`].
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PhysicalPx(pub i16);
+
+impl From<PhysicalPx> for f32 {
+    fn from(px: PhysicalPx) -> Self {
+        f32::from(px.0)
+    }
+}
+
+impl From<i16> for PhysicalPx {
+    fn from(i: i16) -> Self {
+        PhysicalPx(i)
+    }
+}
+
+impl TryFrom<i32> for PhysicalPx {
+    type Error = <i16 as TryFrom<i32>>::Error;
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        i16::try_from(value).map(PhysicalPx)
+    }
+}
+
+impl std::ops::Add for PhysicalPx {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        PhysicalPx(self.0 + other.0)
+    }
+}
+
+impl std::ops::Sub for PhysicalPx {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        PhysicalPx(self.0 - other.0)
+    }
+}
+
+impl PhysicalPx {
+    pub const MAX: Self = PhysicalPx(i16::MAX);
+    pub const MIN: Self = PhysicalPx(i16::MIN);
+}
+
+/// The number of physical pixels per logical pixel on a display.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct ScaleFactor(pub f32);
+
+impl ScaleFactor {
+    /// The scale factor of a standard density (96 DPI) display.
+    pub const ONE: Self = ScaleFactor(1.0);
+
+    /// Computes the scale factor from a display's dots per inch, taking 96 DPI
+    /// to be a scale of 1.0.
+    #[must_use]
+    pub fn from_dpi(dpi: u32) -> Self {
+        ScaleFactor(dpi as f32 / 96.0)
+    }
+
+    /// Converts to physical pixels, rounding to the nearest pixel.
+    #[must_use]
+    pub fn to_physical(&self, px: LogicalPx) -> PhysicalPx {
+        PhysicalPx((f32::from(px) * self.0).round() as i16)
+    }
+
+    /// Converts to logical pixels, rounding to the nearest pixel.
+    #[must_use]
+    pub fn to_logical(&self, px: PhysicalPx) -> LogicalPx {
+        Px::from((f32::from(px) / self.0).round())
+    }
+
+    /// Converts a point to physical pixels, rounding to the nearest pixel.
+    #[must_use]
+    pub fn point_to_physical(&self, point: Point) -> PhysicalPoint {
+        PhysicalPoint {
+            x: self.to_physical(point.x),
+            y: self.to_physical(point.y),
+        }
+    }
+
+    /// Converts a point to logical pixels, rounding down to the logical pixel
+    /// that it falls in.
+    #[must_use]
+    pub fn point_to_logical(&self, point: PhysicalPoint) -> Point {
+        Point {
+            x: Px::from((f32::from(point.x) / self.0).floor()),
+            y: Px::from((f32::from(point.y) / self.0).floor()),
+        }
+    }
+
+    /// Converts an extent to physical pixels, rounding to the nearest pixel.
+    #[must_use]
+    pub fn extent_to_physical(&self, extent: Extent) -> PhysicalExtent {
+        PhysicalExtent {
+            width: self.to_physical(extent.width),
+            height: self.to_physical(extent.height),
+        }
+    }
+
+    /// Converts an extent to logical pixels, rounding down so that it never
+    /// covers more than the physical extent does.
+    #[must_use]
+    pub fn extent_to_logical(&self, extent: PhysicalExtent) -> Extent {
+        Extent {
+            width: Px::from((f32::from(extent.width) / self.0).floor()),
+            height: Px::from((f32::from(extent.height) / self.0).floor()),
+        }
+    }
+
+    /// Converts a rectangle to the smallest rectangle of physical pixels that
+    /// covers it.
+    #[must_use]
+    pub fn rect_to_physical(&self, rect: Rect) -> PhysicalRect {
+        let rect = RectF::from(rect);
+        let edge = |px: f32, round: fn(f32) -> f32| PhysicalPx(round(px * self.0) as i16);
+        PhysicalRect {
+            top: edge(rect.top, f32::floor),
+            left: edge(rect.left, f32::floor),
+            bottom: edge(rect.bottom, f32::ceil),
+            right: edge(rect.right, f32::ceil),
+        }
+    }
+
+    /// Converts a rectangle to the smallest rectangle of logical pixels that
+    /// covers it.
+    #[must_use]
+    pub fn rect_to_logical(&self, rect: PhysicalRect) -> Rect {
+        RectF {
+            top: f32::from(rect.top) / self.0,
+            left: f32::from(rect.left) / self.0,
+            bottom: f32::from(rect.bottom) / self.0,
+            right: f32::from(rect.right) / self.0,
+        }
+        .round_out()
+    }
+}
+
+impl Default for ScaleFactor {
+    fn default() -> Self {
+        Self::ONE
+    }
 }
 
 /// A 2D point in space. It may be negative (to the left or above the top-left
@@ -133,7 +312,7 @@ impl Point {
 
     #[must_use]
     pub fn within(&self, rect: &Rect) -> bool {
-        rect.contains(*self)
+        rect.contains_point(*self)
     }
 }
 
@@ -194,6 +373,40 @@ impl Offset {
     pub fn zero() -> Self {
         Self::default()
     }
+
+    /// Returns `None` if either component overflows.
+    pub fn checked_add(&self, other: Offset) -> Option<Offset> {
+        Some(Offset {
+            x: self.x.checked_add(other.x)?,
+            y: self.y.checked_add(other.y)?,
+        })
+    }
+
+    /// Returns `None` if either component overflows.
+    pub fn checked_sub(&self, other: Offset) -> Option<Offset> {
+        Some(Offset {
+            x: self.x.checked_sub(other.x)?,
+            y: self.y.checked_sub(other.y)?,
+        })
+    }
+
+    /// Adds each component, clamping to [`Px::MIN`] or [`Px::MAX`] on
+    /// overflow.
+    pub fn saturating_add(&self, other: Offset) -> Offset {
+        Offset {
+            x: self.x.saturating_add(other.x),
+            y: self.y.saturating_add(other.y),
+        }
+    }
+
+    /// Subtracts each component, clamping to [`Px::MIN`] or [`Px::MAX`] on
+    /// overflow.
+    pub fn saturating_sub(&self, other: Offset) -> Offset {
+        Offset {
+            x: self.x.saturating_sub(other.x),
+            y: self.y.saturating_sub(other.y),
+        }
+    }
 }
 
 impl std::ops::Add for Offset {
@@ -239,6 +452,11 @@ pub struct Extent {
 }
 
 impl Extent {
+    pub const MAX: Self = Extent {
+        width: Px::MAX,
+        height: Px::MAX,
+    };
+
     #[must_use]
     pub fn zero() -> Self {
         Self::default()
@@ -248,6 +466,55 @@ impl Extent {
     pub fn area(&self) -> usize {
         self.width.0 as usize * self.height.0 as usize
     }
+
+    /// Returns `None` if either dimension overflows.
+    pub fn checked_add(&self, other: Extent) -> Option<Extent> {
+        Some(Extent {
+            width: self.width.checked_add(other.width)?,
+            height: self.height.checked_add(other.height)?,
+        })
+    }
+
+    /// Returns `None` if either dimension would be negative.
+    pub fn checked_sub(&self, other: Extent) -> Option<Extent> {
+        let width = self.width.checked_sub(other.width)?;
+        let height = self.height.checked_sub(other.height)?;
+        (width >= 0 && height >= 0).then_some(Extent { width, height })
+    }
+
+    /// Adds each dimension, clamping to [`Px::MAX`] on overflow.
+    #[must_use]
+    pub fn saturating_add(&self, other: Extent) -> Extent {
+        Extent {
+            width: self.width.saturating_add(other.width),
+            height: self.height.saturating_add(other.height),
+        }
+    }
+
+    /// Subtracts each dimension, clamping to zero instead of going negative.
+    /// Use this when shrinking an extent that may already be degenerate, such
+    /// as the extent of a minimized window.
+    #[must_use]
+    pub fn saturating_sub(&self, other: Extent) -> Extent {
+        Extent {
+            width: self.width.saturating_sub(other.width).max(Px(0)),
+            height: self.height.saturating_sub(other.height).max(Px(0)),
+        }
+    }
+
+    /// Clamps each dimension to lie between `min` and `max`.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if `min` is larger than `max` in either
+    /// dimension.
+    #[must_use]
+    pub fn clamp(&self, min: Extent, max: Extent) -> Extent {
+        Extent {
+            width: self.width.clamp(min.width, max.width),
+            height: self.height.clamp(min.height, max.height),
+        }
+    }
 }
 
 impl From<Offset> for Extent {
@@ -335,12 +602,99 @@ impl Rect {
         }
     }
 
+    /// Whether or not the rectangle covers no area.
     #[must_use]
-    pub fn contains(&self, point: Point) -> bool {
-        self.left <= point.x
-            && point.x < self.right
-            && self.top <= point.y
-            && point.y <= self.bottom
+    pub fn is_empty(&self) -> bool {
+        self.left >= self.right || self.top >= self.bottom
+    }
+
+    /// Computes the smallest rectangle that contains both `self` and `other`.
+    /// Empty rectangles are ignored.
+    #[must_use]
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            *other
+        } else if other.is_empty() {
+            *self
+        } else {
+            Rect {
+                top: self.top.min(other.top),
+                left: self.left.min(other.left),
+                bottom: self.bottom.max(other.bottom),
+                right: self.right.max(other.right),
+            }
+        }
+    }
+
+    /// Computes the area shared by `self` and `other`. Returns an empty
+    /// rectangle if they do not overlap.
+    #[must_use]
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let rect = Rect {
+            top: self.top.max(other.top),
+            left: self.left.max(other.left),
+            bottom: self.bottom.min(other.bottom),
+            right: self.right.min(other.right),
+        };
+
+        if rect.is_empty() {
+            Rect::zero()
+        } else {
+            rect
+        }
+    }
+
+    /// Whether or not `point` lies within the rectangle. The top and left
+    /// edges are inside the rectangle, while the bottom and right edges are
+    /// not, so that adjacent rectangles never both contain the same point.
+    #[must_use]
+    pub fn contains_point(&self, point: Point) -> bool {
+        self.left <= point.x && point.x < self.right && self.top <= point.y && point.y < self.bottom
+    }
+
+    /// Grows the rectangle by `dx` on the left and right, and by `dy` on the
+    /// top and bottom.
+    #[must_use]
+    pub fn inflate(&self, dx: Px, dy: Px) -> Rect {
+        Rect {
+            top: self.top - dy,
+            left: self.left - dx,
+            bottom: self.bottom + dy,
+            right: self.right + dx,
+        }
+    }
+
+    /// Shrinks the rectangle by `dx` on the left and right, and by `dy` on the
+    /// top and bottom. A rectangle that is shrunk by more than its size
+    /// collapses to a line through its center instead of inverting.
+    #[must_use]
+    pub fn deflate(&self, dx: Px, dy: Px) -> Rect {
+        let (left, right) = if self.width().0 / 2 >= dx.0 {
+            (self.left + dx, self.right - dx)
+        } else {
+            let center = self.left + self.width() / 2;
+            (center, center)
+        };
+
+        let (top, bottom) = if self.height().0 / 2 >= dy.0 {
+            (self.top + dy, self.bottom - dy)
+        } else {
+            let center = self.top + self.height() / 2;
+            (center, center)
+        };
+
+        Rect {
+            top,
+            left,
+            bottom,
+            right,
+        }
+    }
+
+    /// Moves the rectangle by `offset` without changing its size.
+    #[must_use]
+    pub fn translate(&self, offset: Offset) -> Rect {
+        *self + offset
     }
 }
 
@@ -355,3 +709,577 @@ impl std::ops::Add<Offset> for Rect {
         }
     }
 }
+
+impl std::ops::Sub<Offset> for Rect {
+    type Output = Self;
+    fn sub(self, other: Offset) -> Self::Output {
+        Rect {
+            top: self.top - other.y,
+            left: self.left - other.x,
+            bottom: self.bottom - other.y,
+            right: self.right - other.x,
+        }
+    }
+}
+
+/// Distances inward from each edge of a rectangle, such as the borders of a
+/// nine-patch image or the padding around a widget.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Insets {
+    pub top: Px,
+    pub left: Px,
+    pub bottom: Px,
+    pub right: Px,
+}
+
+impl Insets {
+    #[must_use]
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    /// Insets that are the same on every edge.
+    #[must_use]
+    pub fn uniform(px: Px) -> Self {
+        Self {
+            top: px,
+            left: px,
+            bottom: px,
+            right: px,
+        }
+    }
+
+    /// The combined size of the insets along each axis.
+    #[must_use]
+    pub fn extent(&self) -> Extent {
+        Extent {
+            width: self.left + self.right,
+            height: self.top + self.bottom,
+        }
+    }
+}
+
+/// A point in physical pixels, such as the position of the cursor as reported
+/// by the shell. See [`Point`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PhysicalPoint {
+    pub x: PhysicalPx,
+    pub y: PhysicalPx,
+}
+
+impl PhysicalPoint {
+    pub fn new(x: impl Into<PhysicalPx>, y: impl Into<PhysicalPx>) -> Self {
+        Self {
+            x: x.into(),
+            y: y.into(),
+        }
+    }
+
+    #[must_use]
+    pub fn zero() -> Self {
+        Self::default()
+    }
+}
+
+/// An extent in physical pixels, such as the size of a window's client area.
+/// See [`Extent`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PhysicalExtent {
+    pub width: PhysicalPx,
+    pub height: PhysicalPx,
+}
+
+impl PhysicalExtent {
+    pub const MAX: Self = PhysicalExtent {
+        width: PhysicalPx::MAX,
+        height: PhysicalPx::MAX,
+    };
+
+    #[must_use]
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    /// Clamps each dimension to lie between `min` and `max`.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if `min` is larger than `max` in either
+    /// dimension.
+    #[must_use]
+    pub fn clamp(&self, min: PhysicalExtent, max: PhysicalExtent) -> PhysicalExtent {
+        PhysicalExtent {
+            width: self.width.clamp(min.width, max.width),
+            height: self.height.clamp(min.height, max.height),
+        }
+    }
+}
+
+/// A rectangle in physical pixels, such as a monitor's bounds or the damaged
+/// region of a swapchain image. See [`Rect`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PhysicalRect {
+    pub top: PhysicalPx,
+    pub left: PhysicalPx,
+    pub bottom: PhysicalPx,
+    pub right: PhysicalPx,
+}
+
+impl PhysicalRect {
+    #[must_use]
+    pub fn new(point: PhysicalPoint, extent: PhysicalExtent) -> Self {
+        PhysicalRect {
+            top: point.y,
+            left: point.x,
+            bottom: point.y + extent.height,
+            right: point.x + extent.width,
+        }
+    }
+
+    #[must_use]
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn top_left(&self) -> PhysicalPoint {
+        PhysicalPoint {
+            x: self.left,
+            y: self.top,
+        }
+    }
+
+    #[must_use]
+    pub fn width(&self) -> PhysicalPx {
+        self.right - self.left
+    }
+
+    #[must_use]
+    pub fn height(&self) -> PhysicalPx {
+        self.bottom - self.top
+    }
+
+    #[must_use]
+    pub fn extent(&self) -> PhysicalExtent {
+        PhysicalExtent {
+            width: self.width(),
+            height: self.height(),
+        }
+    }
+
+    /// See [`Rect::is_empty`].
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.left >= self.right || self.top >= self.bottom
+    }
+
+    /// See [`Rect::union`].
+    #[must_use]
+    pub fn union(&self, other: &PhysicalRect) -> PhysicalRect {
+        if self.is_empty() {
+            *other
+        } else if other.is_empty() {
+            *self
+        } else {
+            PhysicalRect {
+                top: self.top.min(other.top),
+                left: self.left.min(other.left),
+                bottom: self.bottom.max(other.bottom),
+                right: self.right.max(other.right),
+            }
+        }
+    }
+
+    /// See [`Rect::intersect`].
+    #[must_use]
+    pub fn intersect(&self, other: &PhysicalRect) -> PhysicalRect {
+        let rect = PhysicalRect {
+            top: self.top.max(other.top),
+            left: self.left.max(other.left),
+            bottom: self.bottom.min(other.bottom),
+            right: self.right.min(other.right),
+        };
+
+        if rect.is_empty() {
+            PhysicalRect::zero()
+        } else {
+            rect
+        }
+    }
+
+    /// See [`Rect::contains_point`].
+    #[must_use]
+    pub fn contains_point(&self, point: PhysicalPoint) -> bool {
+        self.left <= point.x && point.x < self.right && self.top <= point.y && point.y < self.bottom
+    }
+}
+
+/// A 2D point with sub-pixel precision. Use this for intermediate values that
+/// would lose precision if rounded at every step, such as animated positions
+/// or text layout, and convert to [`Point`] when the value is final.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PointF {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl PointF {
+    #[must_use]
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    #[must_use]
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    /// Linearly interpolates between `self` (at `t = 0`) and `other` (at `t =
+    /// 1`).
+    #[must_use]
+    pub fn lerp(&self, other: PointF, t: f32) -> PointF {
+        PointF {
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+        }
+    }
+
+    /// Rounds to the nearest whole pixel.
+    #[must_use]
+    pub fn round(&self) -> Point {
+        Point {
+            x: Px::from(self.x.round()),
+            y: Px::from(self.y.round()),
+        }
+    }
+}
+
+impl From<Point> for PointF {
+    fn from(point: Point) -> Self {
+        Self {
+            x: f32::from(point.x),
+            y: f32::from(point.y),
+        }
+    }
+}
+
+impl std::ops::Add<ExtentF> for PointF {
+    type Output = Self;
+    fn add(self, other: ExtentF) -> Self::Output {
+        PointF {
+            x: self.x + other.width,
+            y: self.y + other.height,
+        }
+    }
+}
+
+impl std::ops::Sub for PointF {
+    type Output = ExtentF;
+    fn sub(self, other: PointF) -> Self::Output {
+        ExtentF {
+            width: self.x - other.x,
+            height: self.y - other.y,
+        }
+    }
+}
+
+/// A 2D extent with sub-pixel precision. See [`PointF`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExtentF {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ExtentF {
+    #[must_use]
+    pub fn new(width: f32, height: f32) -> Self {
+        Self { width, height }
+    }
+
+    #[must_use]
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    /// The length of the extent when treated as a vector.
+    #[must_use]
+    pub fn length(&self) -> f32 {
+        self.width.hypot(self.height)
+    }
+
+    /// Rounds to the nearest whole pixel.
+    #[must_use]
+    pub fn round(&self) -> Extent {
+        Extent {
+            width: Px::from(self.width.round()),
+            height: Px::from(self.height.round()),
+        }
+    }
+}
+
+impl From<Extent> for ExtentF {
+    fn from(extent: Extent) -> Self {
+        Self {
+            width: f32::from(extent.width),
+            height: f32::from(extent.height),
+        }
+    }
+}
+
+impl std::ops::Mul<f32> for ExtentF {
+    type Output = Self;
+    fn mul(self, scale: f32) -> Self::Output {
+        ExtentF {
+            width: self.width * scale,
+            height: self.height * scale,
+        }
+    }
+}
+
+/// A 2D rectangle with sub-pixel precision. See [`PointF`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RectF {
+    pub top: f32,
+    pub left: f32,
+    pub bottom: f32,
+    pub right: f32,
+}
+
+impl RectF {
+    #[must_use]
+    pub fn new(point: PointF, extent: ExtentF) -> Self {
+        Self {
+            top: point.y,
+            left: point.x,
+            bottom: point.y + extent.height,
+            right: point.x + extent.width,
+        }
+    }
+
+    #[must_use]
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn top_left(&self) -> PointF {
+        PointF {
+            x: self.left,
+            y: self.top,
+        }
+    }
+
+    #[must_use]
+    pub fn bottom_right(&self) -> PointF {
+        PointF {
+            x: self.right,
+            y: self.bottom,
+        }
+    }
+
+    #[must_use]
+    pub fn width(&self) -> f32 {
+        self.right - self.left
+    }
+
+    #[must_use]
+    pub fn height(&self) -> f32 {
+        self.bottom - self.top
+    }
+
+    #[must_use]
+    pub fn extent(&self) -> ExtentF {
+        ExtentF {
+            width: self.width(),
+            height: self.height(),
+        }
+    }
+
+    /// See [`Rect::contains_point`].
+    #[must_use]
+    pub fn contains_point(&self, point: PointF) -> bool {
+        self.left <= point.x && point.x < self.right && self.top <= point.y && point.y < self.bottom
+    }
+
+    /// Rounds each edge to the nearest whole pixel.
+    #[must_use]
+    pub fn round(&self) -> Rect {
+        Rect {
+            top: Px::from(self.top.round()),
+            left: Px::from(self.left.round()),
+            bottom: Px::from(self.bottom.round()),
+            right: Px::from(self.right.round()),
+        }
+    }
+
+    /// Computes the smallest pixel-aligned rectangle that covers `self`. Use
+    /// this instead of [`Self::round`] when every partially covered pixel
+    /// matters, such as for damage or clip regions.
+    #[must_use]
+    pub fn round_out(&self) -> Rect {
+        Rect {
+            top: Px::from(self.top.floor()),
+            left: Px::from(self.left.floor()),
+            bottom: Px::from(self.bottom.ceil()),
+            right: Px::from(self.right.ceil()),
+        }
+    }
+}
+
+impl From<Rect> for RectF {
+    fn from(rect: Rect) -> Self {
+        Self {
+            top: f32::from(rect.top),
+            left: f32::from(rect.left),
+            bottom: f32::from(rect.bottom),
+            right: f32::from(rect.right),
+        }
+    }
+}
+
+/// A 2D affine transformation, applied to geometry on the GPU when it is drawn.
+///
+/// Points are transformed as `x_axis * x + y_axis * y + translation`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub x_axis: [f32; 2],
+    pub y_axis: [f32; 2],
+    pub translation: [f32; 2],
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        x_axis: [1.0, 0.0],
+        y_axis: [0.0, 1.0],
+        translation: [0.0, 0.0],
+    };
+
+    #[must_use]
+    pub fn translate(x: f32, y: f32) -> Self {
+        Self {
+            translation: [x, y],
+            ..Self::IDENTITY
+        }
+    }
+
+    #[must_use]
+    pub fn scale(x: f32, y: f32) -> Self {
+        Self {
+            x_axis: [x, 0.0],
+            y_axis: [0.0, y],
+            translation: [0.0, 0.0],
+        }
+    }
+
+    /// Rotates clockwise (in window coordinates) by `radians`.
+    #[must_use]
+    pub fn rotate(radians: f32) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Self {
+            x_axis: [cos, sin],
+            y_axis: [-sin, cos],
+            translation: [0.0, 0.0],
+        }
+    }
+
+    /// Returns a transform that applies `self` and then `next`.
+    #[must_use]
+    pub fn then(&self, next: &Transform) -> Self {
+        Self {
+            x_axis: next.apply_vector(self.x_axis),
+            y_axis: next.apply_vector(self.y_axis),
+            translation: next.apply([self.translation[0], self.translation[1]]),
+        }
+    }
+
+    /// Transforms a point.
+    #[must_use]
+    pub fn apply(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let [dx, dy] = self.apply_vector([x, y]);
+        [dx + self.translation[0], dy + self.translation[1]]
+    }
+
+    /// Transforms a point. See [`Self::apply`].
+    #[must_use]
+    pub fn apply_point(&self, point: PointF) -> PointF {
+        let [x, y] = self.apply([point.x, point.y]);
+        PointF { x, y }
+    }
+
+    /// Transforms a direction, ignoring translation.
+    #[must_use]
+    fn apply_vector(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        [
+            self.x_axis[0] * x + self.y_axis[0] * y,
+            self.x_axis[1] * x + self.y_axis[1] * y,
+        ]
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_factor_conversions() {
+        let scale = ScaleFactor::from_dpi(144);
+        assert_eq!(scale, ScaleFactor(1.5));
+
+        assert_eq!(scale.to_physical(Px(3)), PhysicalPx(5));
+        assert_eq!(scale.to_logical(PhysicalPx(5)), Px(3));
+
+        // Points fall in the logical pixel that covers them.
+        assert_eq!(
+            scale.point_to_logical(PhysicalPoint::new(4, -1)),
+            Point::new(2, -1)
+        );
+
+        // Logical extents never cover more than the window.
+        let window = PhysicalExtent {
+            width: PhysicalPx(101),
+            height: PhysicalPx(60),
+        };
+        assert_eq!(
+            scale.extent_to_logical(window),
+            Extent {
+                width: Px(67),
+                height: Px(40),
+            }
+        );
+
+        // Rectangles are rounded out to every pixel that they touch.
+        let rect = Rect::new(
+            Point::new(1, 1),
+            Extent {
+                width: Px(1),
+                height: Px(2),
+            },
+        );
+        let physical = scale.rect_to_physical(rect);
+        assert_eq!(
+            physical,
+            PhysicalRect {
+                top: PhysicalPx(1),
+                left: PhysicalPx(1),
+                bottom: PhysicalPx(5),
+                right: PhysicalPx(3),
+            }
+        );
+        assert_eq!(
+            scale.rect_to_logical(physical),
+            Rect {
+                top: Px(0),
+                left: Px(0),
+                bottom: Px(4),
+                right: Px(2),
+            }
+        );
+    }
+}
@@ -1,18 +1,28 @@
-use crate::handle_pool::Handle;
+use std::time::Duration;
+
+use smallvec::SmallVec;
+
+use crate::{arena::FrameArena, handle_pool::Handle};
 
 use self::{
     color::Color,
-    geometry::{Extent, Offset, Point, Rect},
-    pixel_buffer::{PixelBuffer, PixelBufferView},
+    geometry::{
+        Extent, ExtentF, Insets, Offset, PhysicalExtent, PhysicalRect, Point, PointF, Px, Rect,
+        RectF, Transform,
+    },
+    pixel_buffer::{Layout, PixelBuffer, PixelBufferView},
 };
 
 pub mod color;
+pub mod debug;
 pub mod geometry;
 pub mod pixel_buffer;
+pub mod software;
 mod vulkan;
 
 pub const MAX_SWAPCHAINS: u32 = 32;
-pub const MAX_IMAGES: u32 = 1;
+pub const MAX_IMAGES: u32 = 16;
+pub const MAX_EFFECTS: u32 = 16;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -32,6 +42,18 @@ pub enum Error {
         "the image cannot be copied as described without resampling, but resampling was disabled"
     )]
     MustResampleImage,
+    #[error("the graphics device does not support the requested image layout")]
+    UnsupportedLayout,
+    #[error("the source and destination images have incompatible layouts")]
+    IncompatibleLayout,
+    #[error("the copy region is not aligned to the image's compression blocks")]
+    UnalignedCopy,
+    #[error("the image is owned by another API and cannot be written to")]
+    ReadOnlyImage,
+    #[error("the graphics device does not support importing external images")]
+    ExternalImagesNotSupported,
+    #[error("the effect's shader could not be used to create a pipeline")]
+    InvalidShader,
     #[from(ash::vk::Result)]
     #[error("an unhandled error in the Vulkan backend occurred")]
     VulkanInternal {
@@ -67,17 +89,203 @@ impl From<Handle<Image>> for RenderTarget {
     }
 }
 
+/// A custom fragment shader that can be used to draw rectangles. See
+/// [`GfxDevice::create_effect`] for details.
+pub struct Effect {}
+
+/// Parameters passed to an [`Effect`]'s fragment shader, laid out as
+/// `vec4 uniforms[4]` in GLSL.
+pub type EffectUniforms = [f32; 16];
+
+/// The pixel format of an image imported with [`GfxDevice::import_image`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExternalFormat {
+    Rgba8,
+    Bgra8,
+}
+
+/// An OS-specific handle to memory holding an image created outside of Fathom.
+#[derive(Clone, Copy, Debug)]
+pub enum ExternalHandle {
+    /// An NT handle to a D3D11 texture, as returned by
+    /// `IDXGIResource1::CreateSharedHandle`. The caller retains ownership of
+    /// the handle.
+    #[cfg(target_os = "windows")]
+    D3D11Texture(windows::Win32::Foundation::HANDLE),
+    /// A legacy shared handle to a D3D11 texture, as returned by
+    /// `IDXGIResource::GetSharedHandle`.
+    #[cfg(target_os = "windows")]
+    D3D11TextureKmt(windows::Win32::Foundation::HANDLE),
+    /// A dma-buf file descriptor with a single plane. Ownership of the file
+    /// descriptor is transferred to the graphics device if the import
+    /// succeeds.
+    #[cfg(target_os = "linux")]
+    DmaBuf {
+        fd: std::os::unix::io::RawFd,
+        /// The DRM format modifier describing the tiling of the buffer.
+        modifier: u64,
+        /// The offset of the first pixel from the start of the buffer.
+        offset: u64,
+        /// The number of bytes between the start of each row.
+        stride: u64,
+    },
+}
+
+/// An image that was created outside of Fathom, such as by a hardware video
+/// decoder or another process.
+///
+/// Imported images are read-only. It is the producer's responsibility to make
+/// sure that it is done writing to the image before it is drawn.
+#[derive(Clone, Copy, Debug)]
+pub struct ExternalImage {
+    pub extent: Extent,
+    pub format: ExternalFormat,
+    pub handle: ExternalHandle,
+}
+
+/// A single vertex of the geometry drawn by a [`DrawCommandList`].
 #[repr(C)]
-#[derive(Clone, Copy)]
-pub(self) struct Vertex {
+#[derive(Clone, Copy, Debug)]
+pub struct Vertex {
+    /// The texel of the image that is sampled at this vertex, in pixels from
+    /// the top-left corner of the image. Ignored for untextured geometry.
     pub uv: Point,
+    /// The position of the vertex in window coordinates.
     pub point: Point,
     pub color: Color,
 }
 
+impl Vertex {
+    /// Creates an untextured vertex.
+    #[must_use]
+    pub fn new(point: Point, color: Color) -> Self {
+        Self {
+            uv: Point::zero(),
+            point,
+            color,
+        }
+    }
+
+    /// Creates a vertex that samples the texel at `uv` of the image it is
+    /// drawn with. The sampled color is multiplied by `color`.
+    #[must_use]
+    pub fn textured(point: Point, uv: Point, color: Color) -> Self {
+        Self { uv, point, color }
+    }
+}
+
+/// A triangle drawn by a [`DrawCommandList`]. See
+/// [`DrawCommandList::triangles`].
+#[derive(Clone, Copy, Debug)]
+pub struct Triangle {
+    pub vertices: [Vertex; 3],
+    /// Whether the triangle samples an image, glyph atlas, or effect. The
+    /// colors of its vertices are multiplied by what it samples.
+    pub textured: bool,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum Paint {
-    Fill { color: Color },
+    Fill {
+        color: Color,
+    },
+    /// Fills the shape with an image. `transform` maps coordinates relative
+    /// to the top-left corner of the shape to texel coordinates in the image,
+    /// and `tiling` determines what is drawn outside of the image's bounds.
+    Image {
+        handle: Handle<Image>,
+        tiling: Tiling,
+        transform: Transform,
+    },
+}
+
+/// How the outline of a shape is drawn.
+#[derive(Clone, Copy, Debug)]
+pub struct Stroke {
+    pub color: Color,
+    /// The width of the line, centered on the outline.
+    pub width: f32,
+    /// Draws the line as a sequence of dashes instead of a solid line.
+    pub dash: Option<Dash>,
+}
+
+impl Stroke {
+    #[must_use]
+    pub fn solid(color: Color, width: f32) -> Self {
+        Self {
+            color,
+            width,
+            dash: None,
+        }
+    }
+
+    #[must_use]
+    pub fn dashed(color: Color, width: f32, dash: Dash) -> Self {
+        Self {
+            color,
+            width,
+            dash: Some(dash),
+        }
+    }
+}
+
+/// A repeating pattern of dashes along a stroke, measured in pixels along the
+/// outline.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Dash {
+    /// The length of each dash.
+    pub on: f32,
+    /// The length of the gap between dashes.
+    pub off: f32,
+    /// How far into the pattern the stroke starts. Animating this makes the
+    /// dashes crawl along the outline, as in a selection marquee.
+    pub phase: f32,
+}
+
+impl Dash {
+    /// A dotted pattern of square dots for a stroke of the given width.
+    #[must_use]
+    pub fn dotted(width: f32) -> Self {
+        Self {
+            on: width,
+            off: width,
+            phase: 0.0,
+        }
+    }
+
+    /// The length of one dash and one gap.
+    #[must_use]
+    pub fn period(&self) -> f32 {
+        self.on + self.off
+    }
+}
+
+/// How an image is sampled outside of its bounds.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tiling {
+    /// Repeats the pixels at the edge of the image.
+    #[default]
+    Clamp = 0,
+    /// Repeats the image.
+    Repeat = 1,
+    /// Repeats the image, flipping every other copy so that the edges line
+    /// up.
+    Mirror = 2,
+}
+
+/// How the edges of glyphs are smoothed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GlyphAntiAliasing {
+    /// Each pixel is blended with the background by a single coverage value.
+    /// This looks the same on every display and can be drawn over
+    /// transparent backgrounds.
+    Grayscale,
+    /// Each color channel is blended separately, using the horizontal layout
+    /// of the display's subpixels to triple the effective horizontal
+    /// resolution. This assumes an RGB subpixel layout and an opaque
+    /// background.
+    Subpixel,
 }
 
 pub struct ImageCopy {
@@ -101,6 +309,42 @@ enum Command {
     },
     Image {
         image: Handle<Image>,
+        tiling: Tiling,
+        first_index: u16,
+        num_indices: u16,
+    },
+    /// Sets the transform applied to all following geometry.
+    Transform {
+        transform: Transform,
+    },
+    /// Restricts all following geometry to the area covered by the given
+    /// triangles, in addition to any clips that are already in effect.
+    PushClip {
+        first_index: u16,
+        num_indices: u16,
+    },
+    /// Removes the clip that was pushed with the same geometry.
+    PopClip {
+        first_index: u16,
+        num_indices: u16,
+    },
+    /// Glyphs drawn from an atlas with `draw_glyph`.
+    Glyphs {
+        atlas: Handle<Image>,
+        anti_aliasing: GlyphAntiAliasing,
+        first_index: u16,
+        num_indices: u16,
+    },
+    ColorGlyphs {
+        atlas: Handle<Image>,
+        first_index: u16,
+        num_indices: u16,
+    },
+    Effect {
+        effect: Handle<Effect>,
+        image: Option<Handle<Image>>,
+        /// Index into `DrawCommandList::uniforms`.
+        uniforms: u16,
         first_index: u16,
         num_indices: u16,
     },
@@ -114,11 +358,29 @@ pub struct DrawCommandList {
     pub(self) commands: Vec<Command>,
     pub(self) vertices: Vec<Vertex>,
     pub(self) indices: Vec<u16>,
+    transforms: Vec<Transform>,
+    /// The index ranges of the geometry of each clip currently in effect, so
+    /// that the geometry can be reused when the clip is popped.
+    clips: Vec<(u16, u16)>,
+    /// The opacity of each group currently in effect, already multiplied by
+    /// the opacities of the groups around it.
+    opacities: Vec<f32>,
+    /// The uniforms for each effect draw, stored separately to keep commands
+    /// small.
+    pub(self) uniforms: Vec<EffectUniforms>,
+    /// Memory for data that is only needed while a shape is recorded. See
+    /// [`Self::with_scratch`].
+    scratch: FrameArena,
 }
 
 impl DrawCommandList {
     const MAX_VERTICES: usize = u16::MAX as usize + 1;
     const MAX_INDICES: usize = Self::MAX_VERTICES;
+    const QUAD_INDICES: [u16; 6] = [0, 1, 2, 2, 3, 0];
+    /// The most segments used for each corner of a rounded rectangle. Enough
+    /// that each is at most ~2px long for small radii, capped to keep the mesh
+    /// small for large ones.
+    const MAX_ARC_SEGMENTS: usize = 16;
 
     // We want to batch as many commands as we can, as cheaply as possible in
     // order to reduce the number of draw calls that will be necessary. The
@@ -159,6 +421,137 @@ impl DrawCommandList {
         self.commands.clear();
         self.vertices.clear();
         self.indices.clear();
+        self.transforms.clear();
+        self.clips.clear();
+        self.opacities.clear();
+        self.uniforms.clear();
+    }
+
+    /// Calls `f` with scratch memory for data that is only needed while it
+    /// records a shape, such as points moved into place before they are
+    /// stroked. The memory is freed once `f` returns, and reused by the next
+    /// call, so that recording shapes doesn't allocate once the list has been
+    /// used for a frame or two.
+    pub fn with_scratch<R>(&mut self, f: impl FnOnce(&mut Self, &FrameArena) -> R) -> R {
+        // The arena is moved out of the list so that the list can be recorded
+        // to while the arena is borrowed.
+        let mut scratch = std::mem::take(&mut self.scratch);
+        let result = f(self, &scratch);
+        scratch.reset();
+        self.scratch = scratch;
+        result
+    }
+
+    /// Applies `transform` to all geometry drawn until the matching call to
+    /// [`Self::pop_transform`], after any transforms that are already in
+    /// effect. Transforms are applied by the graphics device, and so do not
+    /// affect scissor rectangles.
+    pub fn push_transform(&mut self, transform: &Transform) {
+        let transform = transform.then(&self.current_transform());
+        self.transforms.push(transform);
+        self.push_command(Command::Transform { transform });
+    }
+
+    /// Restores the transform that was in effect before the last call to
+    /// [`Self::push_transform`].
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if there is no transform to pop.
+    pub fn pop_transform(&mut self) {
+        self.transforms
+            .pop()
+            .expect("pop_transform called without a matching push_transform");
+        self.push_command(Command::Transform {
+            transform: self.current_transform(),
+        });
+    }
+
+    #[must_use]
+    pub fn current_transform(&self) -> Transform {
+        self.transforms.last().copied().unwrap_or_default()
+    }
+
+    /// Fades all geometry drawn until the matching call to
+    /// [`Self::pop_opacity`] by `opacity`, from 0 (invisible) to 1 (as
+    /// drawn), on top of any opacities that are already in effect.
+    ///
+    /// The opacity is applied to the color of each vertex, so shapes within
+    /// the group that overlap are each faded and blended with one another,
+    /// rather than the group being faded as a whole.
+    pub fn push_opacity(&mut self, opacity: f32) {
+        let opacity = opacity.clamp(0.0, 1.0) * self.current_opacity();
+        self.opacities.push(opacity);
+    }
+
+    /// Restores the opacity that was in effect before the last call to
+    /// [`Self::push_opacity`].
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if there is no opacity to pop.
+    pub fn pop_opacity(&mut self) {
+        self.opacities
+            .pop()
+            .expect("pop_opacity called without a matching push_opacity");
+    }
+
+    #[must_use]
+    pub fn current_opacity(&self) -> f32 {
+        self.opacities.last().copied().unwrap_or(1.0)
+    }
+
+    /// Restricts all geometry drawn until the matching call to
+    /// [`Self::pop_clip`] to the area covered by the triangles in `indices`,
+    /// intersected with any clips that are already in effect. Triangles must
+    /// have clockwise winding, and may overlap.
+    ///
+    /// Clips are affected by transforms and scissor rectangles like any other
+    /// geometry, so the scissor rectangle should be the same when the clip is
+    /// pushed and popped.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively, or if an index
+    /// is out of bounds of `vertices`.
+    pub fn push_clip_mesh(&mut self, vertices: &[Vertex], indices: &[u16]) {
+        let (first_index, num_indices) = self.append_geometry(vertices, indices);
+        self.clips.push((first_index, num_indices));
+        self.push_command(Command::PushClip {
+            first_index,
+            num_indices,
+        });
+    }
+
+    /// Restricts all geometry drawn until the matching call to
+    /// [`Self::pop_clip`] to `rect` with corners rounded by `radius`. See
+    /// [`Self::push_clip_mesh`] for details.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
+    pub fn push_clip_rounded_rect(&mut self, rect: Rect, radius: Px) {
+        let (vertices, indices) = Self::rounded_rect_mesh(rect, radius, Color::WHITE);
+        self.push_clip_mesh(&vertices, &indices);
+    }
+
+    /// Removes the clip added by the last call to [`Self::push_clip_mesh`] or
+    /// [`Self::push_clip_rounded_rect`].
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if there is no clip to pop.
+    pub fn pop_clip(&mut self) {
+        let (first_index, num_indices) = self
+            .clips
+            .pop()
+            .expect("pop_clip called without a matching push_clip");
+        self.push_command(Command::PopClip {
+            first_index,
+            num_indices,
+        });
     }
 
     pub fn scissor(&mut self, rect: Rect) {
@@ -172,116 +565,732 @@ impl DrawCommandList {
     /// This function will panic if the number of vertices or indices exceeds
     /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
     pub fn draw_rect(&mut self, rect: Rect, paint: Paint) {
-        const NUM_VERTICES: u16 = 4;
-        const NUM_INDICES: u16 = 6;
+        let corners = [
+            rect.top_left(),
+            rect.top_right(),
+            rect.bottom_right(),
+            rect.bottom_left(),
+        ];
 
-        assert!(Self::MAX_VERTICES >= self.vertices.len() + NUM_VERTICES as usize);
-        assert!(Self::MAX_INDICES >= self.indices.len() + NUM_INDICES as usize);
+        match paint {
+            Paint::Fill { color } => {
+                self.draw_mesh(
+                    &corners.map(|c| Vertex::new(c, color)),
+                    &Self::QUAD_INDICES,
+                    None,
+                );
+            }
+            Paint::Image {
+                handle,
+                tiling,
+                transform,
+            } => {
+                let vertices = corners.map(|corner| {
+                    let local = corner - rect.top_left();
+                    let uv = transform.apply_point(PointF {
+                        x: f32::from(local.x),
+                        y: f32::from(local.y),
+                    });
+                    Vertex::textured(corner, uv.round(), Color::WHITE)
+                });
 
-        let color = match paint {
-            Paint::Fill { color } => color,
-        };
+                self.push_mesh(&vertices, &Self::QUAD_INDICES, Some((handle, tiling)));
+            }
+        }
+    }
 
-        let vertex_offset = self.vertices.len() as u16;
-        self.vertices.extend_from_slice(&[
-            Vertex {
-                uv: Point::zero(),
-                point: rect.top_left(),
-                color,
-            },
-            Vertex {
-                uv: Point::zero(),
-                point: rect.top_right(),
-                color,
-            },
-            Vertex {
-                uv: Point::zero(),
-                point: rect.bottom_right(),
-                color,
+    /// Draws `rect` with corners rounded by `radius`, which is limited to half
+    /// of the rectangle's shorter side.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
+    pub fn draw_rounded_rect(&mut self, rect: Rect, radius: Px, paint: Paint) {
+        match paint {
+            Paint::Fill { color } => {
+                let (vertices, indices) = Self::rounded_rect_mesh(rect, radius, color);
+                self.draw_mesh(&vertices, &indices, None);
+            }
+            Paint::Image { .. } => {
+                self.push_clip_rounded_rect(rect, radius);
+                self.draw_rect(rect, paint);
+                self.pop_clip();
+            }
+        }
+    }
+
+    /// Draws the outline of `rect` with corners rounded by `radius`. See
+    /// [`Self::draw_rounded_rect`] and [`Self::stroke_rect`] for details.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
+    pub fn stroke_rounded_rect(&mut self, rect: Rect, radius: Px, stroke: &Stroke) {
+        let mut outline = Self::rounded_rect_outline(rect, radius);
+        // Square corners repeat the same point for the whole arc.
+        outline.dedup();
+        self.stroke_polyline(&outline, true, stroke);
+    }
+
+    /// Draws a circle centered on `center`.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
+    pub fn draw_circle(&mut self, center: Point, radius: Px, paint: Paint) {
+        self.draw_rounded_rect(Self::circle_bounds(center, radius), radius, paint);
+    }
+
+    /// Draws the outline of a circle centered on `center`.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
+    pub fn stroke_circle(&mut self, center: Point, radius: Px, stroke: &Stroke) {
+        self.stroke_rounded_rect(Self::circle_bounds(center, radius), radius, stroke);
+    }
+
+    fn circle_bounds(center: Point, radius: Px) -> Rect {
+        Rect {
+            left: center.x - radius,
+            top: center.y - radius,
+            right: center.x + radius,
+            bottom: center.y + radius,
+        }
+    }
+
+    /// Traces the outline of `rect` with corners rounded by `radius`,
+    /// clockwise from the start of the top-left corner.
+    fn rounded_rect_outline(
+        rect: Rect,
+        radius: Px,
+    ) -> SmallVec<[PointF; 4 * (Self::MAX_ARC_SEGMENTS + 1)]> {
+        let radius = radius
+            .min(rect.width() / 2)
+            .min(rect.height() / 2)
+            .max(Px(0));
+        let r = f32::from(radius);
+        let segments = ((r * std::f32::consts::FRAC_PI_2 / 2.0).ceil() as usize)
+            .clamp(1, Self::MAX_ARC_SEGMENTS);
+
+        // The centers of the corner arcs, clockwise from the top-left. Each arc
+        // starts at the angle at which the previous one ended.
+        let inner = RectF::from(rect.deflate(radius, radius));
+        let (top_left, bottom_right) = (inner.top_left(), inner.bottom_right());
+        let centers = [
+            top_left,
+            PointF {
+                x: bottom_right.x,
+                y: top_left.y,
             },
-            Vertex {
-                uv: Point::zero(),
-                point: rect.bottom_left(),
-                color,
+            bottom_right,
+            PointF {
+                x: top_left.x,
+                y: bottom_right.y,
             },
-        ]);
+        ];
 
-        let index_offset = self.indices.len() as u16;
-        self.indices.extend_from_slice(&[
-            vertex_offset,
-            vertex_offset + 1,
-            vertex_offset + 2,
-            vertex_offset + 2,
-            vertex_offset + 3,
-            vertex_offset,
-        ]);
-
-        if let Some(Command::Polygon { num_indices, .. }) = &mut self.current {
-            *num_indices += NUM_INDICES;
+        let mut outline = SmallVec::new();
+        for (corner, arc_center) in centers.iter().enumerate() {
+            let start = std::f32::consts::PI * (1.0 + 0.5 * corner as f32);
+            for i in 0..=segments {
+                let angle = start + std::f32::consts::FRAC_PI_2 * (i as f32 / segments as f32);
+                outline.push(PointF {
+                    x: arc_center.x + r * angle.cos(),
+                    y: arc_center.y + r * angle.sin(),
+                });
+            }
+        }
+        outline
+    }
+
+    /// Builds a fan of triangles covering `rect` with corners rounded by
+    /// `radius`.
+    fn rounded_rect_mesh(
+        rect: Rect,
+        radius: Px,
+        color: Color,
+    ) -> (
+        SmallVec<[Vertex; 4 * (Self::MAX_ARC_SEGMENTS + 1) + 1]>,
+        SmallVec<[u16; 3 * 4 * (Self::MAX_ARC_SEGMENTS + 1)]>,
+    ) {
+        let mut vertices = SmallVec::new();
+        let center = PointF::from(rect.top_left()).lerp(PointF::from(rect.bottom_right()), 0.5);
+        vertices.push(Vertex::new(center.round(), color));
+        vertices.extend(
+            Self::rounded_rect_outline(rect, radius)
+                .iter()
+                .map(|point| Vertex::new(point.round(), color)),
+        );
+
+        // Fan out from the center. Angles increase clockwise on screen since
+        // the y axis points down.
+        let num_outer = vertices.len() as u16 - 1;
+        let mut indices = SmallVec::new();
+        for i in 0..num_outer {
+            indices.extend_from_slice(&[0, 1 + i, 1 + (i + 1) % num_outer]);
+        }
+
+        (vertices, indices)
+    }
+
+    /// Draws a straight line from `from` to `to`.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
+    pub fn stroke_line(&mut self, from: PointF, to: PointF, stroke: &Stroke) {
+        self.stroke_polyline(&[from, to], false, stroke);
+    }
+
+    /// Draws the outline of `rect`. The stroke is centered on the edges of the
+    /// rectangle, starting from the top-left corner and proceeding clockwise.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
+    pub fn stroke_rect(&mut self, rect: Rect, stroke: &Stroke) {
+        let rect = RectF::from(rect);
+        let (top_left, bottom_right) = (rect.top_left(), rect.bottom_right());
+        self.stroke_polyline(
+            &[
+                top_left,
+                PointF::new(bottom_right.x, top_left.y),
+                bottom_right,
+                PointF::new(top_left.x, bottom_right.y),
+            ],
+            true,
+            stroke,
+        );
+    }
+
+    /// Draws a line through each of `points` in order, returning to the first
+    /// point if `closed` is true. Dashes continue across corners, and segments
+    /// are extended at corners so that they meet without gaps.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
+    pub fn stroke_polyline(&mut self, points: &[PointF], closed: bool, stroke: &Stroke) {
+        if points.len() < 2 || stroke.width <= 0.0 {
+            return;
+        }
+
+        let num_segments = if closed {
+            points.len()
         } else {
-            self.push_command(Command::Polygon {
-                first_index: index_offset,
-                num_indices: NUM_INDICES,
-            });
+            points.len() - 1
+        };
+
+        // Degenerate patterns are drawn as solid lines.
+        let dash = stroke.dash.filter(|dash| dash.on > 0.0 && dash.off >= 0.0);
+        let half_width = stroke.width / 2.0;
+
+        // The distance along the outline at which each segment starts,
+        // including the phase of the dash pattern.
+        let mut distance = dash.map_or(0.0, |dash| dash.phase);
+
+        for i in 0..num_segments {
+            let start = points[i];
+            let end = points[(i + 1) % points.len()];
+            let length = (end - start).length();
+            if length <= 0.0 {
+                continue;
+            }
+
+            let direction = (end - start) * (1.0 / length);
+
+            // Extend segments into their neighbors so that corners are filled.
+            let extend_start = if closed || i > 0 { half_width } else { 0.0 };
+            let extend_end = if closed || i + 1 < num_segments {
+                half_width
+            } else {
+                0.0
+            };
+
+            if let Some(dash) = dash {
+                let period = dash.period();
+                // The start of the first dash that overlaps this segment.
+                let mut dash_start = -(distance % period);
+                if dash_start > 0.0 {
+                    dash_start -= period;
+                }
+
+                while dash_start < length {
+                    let on_start = dash_start.max(0.0);
+                    let on_end = (dash_start + dash.on).min(length);
+                    if on_end > on_start {
+                        let from = if on_start == 0.0 {
+                            -extend_start
+                        } else {
+                            on_start
+                        };
+                        let to = if on_end == length {
+                            length + extend_end
+                        } else {
+                            on_end
+                        };
+                        self.stroke_segment(
+                            start + direction * from,
+                            start + direction * to,
+                            half_width,
+                            stroke.color,
+                        );
+                    }
+                    dash_start += period;
+                }
+            } else {
+                self.stroke_segment(
+                    start + direction * -extend_start,
+                    start + direction * (length + extend_end),
+                    half_width,
+                    stroke.color,
+                );
+            }
+
+            distance += length;
         }
     }
 
-    /// Draws a rectangle with the specified paint.
+    /// Draws a single straight, solid piece of a stroke as a quad.
+    fn stroke_segment(&mut self, from: PointF, to: PointF, half_width: f32, color: Color) {
+        let delta = to - from;
+        let length = delta.length();
+        if length <= 0.0 {
+            return;
+        }
+
+        // The normal is rotated clockwise (on screen) from the direction of the
+        // line, so these corners are always wound clockwise.
+        let normal = ExtentF::new(-delta.height, delta.width) * (half_width / length);
+        let corners = [
+            from + normal * -1.0,
+            to + normal * -1.0,
+            to + normal,
+            from + normal,
+        ]
+        .map(|corner| Vertex::new(corner.round(), color));
+
+        self.draw_mesh(&corners, &Self::QUAD_INDICES, None);
+    }
+
+    /// Draws the `src_rect` of `image`, in pixels, stretched to fill
+    /// `dst_rect`.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
+    pub fn draw_image(&mut self, image: Handle<Image>, src_rect: Rect, dst_rect: Rect) {
+        self.draw_tinted_image(image, src_rect, dst_rect, Color::WHITE);
+    }
+
+    /// Like [`Self::draw_image`], but multiplies every pixel of the image by
+    /// `tint`.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
+    pub fn draw_tinted_image(
+        &mut self,
+        image: Handle<Image>,
+        src_rect: Rect,
+        dst_rect: Rect,
+        tint: Color,
+    ) {
+        self.draw_mesh(
+            &[
+                Vertex::textured(dst_rect.top_left(), src_rect.top_left(), tint),
+                Vertex::textured(dst_rect.top_right(), src_rect.top_right(), tint),
+                Vertex::textured(dst_rect.bottom_right(), src_rect.bottom_right(), tint),
+                Vertex::textured(dst_rect.bottom_left(), src_rect.bottom_left(), tint),
+            ],
+            &Self::QUAD_INDICES,
+            Some(image),
+        );
+    }
+
+    /// Draws `src_rect` of `image` as a nine-patch stretched over `dst_rect`.
+    /// The image is split into a 3x3 grid by `insets`: the corners are drawn
+    /// at their original size, the edges are stretched along one axis, and
+    /// the center is stretched along both. If `dst_rect` is too small to fit
+    /// the corners, they are shrunk proportionally.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
+    pub fn draw_nine_patch(
+        &mut self,
+        image: Handle<Image>,
+        src_rect: Rect,
+        insets: Insets,
+        dst_rect: Rect,
+    ) {
+        // Shrinks a pair of opposing insets to fit within `size`.
+        fn fit(a: Px, b: Px, size: Px) -> (Px, Px) {
+            let (a32, b32, size32) = (i32::from(a.0), i32::from(b.0), i32::from(size.0));
+            if a32 + b32 <= size32 {
+                (a, b)
+            } else {
+                let a = Px((size32 * a32 / (a32 + b32)) as i16);
+                (a, size - a)
+            }
+        }
+
+        let (left, right) = fit(insets.left, insets.right, dst_rect.width().max(Px(0)));
+        let (top, bottom) = fit(insets.top, insets.bottom, dst_rect.height().max(Px(0)));
+
+        let src_x = [
+            src_rect.left,
+            src_rect.left + insets.left,
+            src_rect.right - insets.right,
+            src_rect.right,
+        ];
+        let src_y = [
+            src_rect.top,
+            src_rect.top + insets.top,
+            src_rect.bottom - insets.bottom,
+            src_rect.bottom,
+        ];
+        let dst_x = [
+            dst_rect.left,
+            dst_rect.left + left,
+            dst_rect.right - right,
+            dst_rect.right,
+        ];
+        let dst_y = [
+            dst_rect.top,
+            dst_rect.top + top,
+            dst_rect.bottom - bottom,
+            dst_rect.bottom,
+        ];
+
+        let mut vertices = [Vertex::new(Point::zero(), Color::WHITE); 16];
+        for (row, (&dy, &sy)) in dst_y.iter().zip(&src_y).enumerate() {
+            for (col, (&dx, &sx)) in dst_x.iter().zip(&src_x).enumerate() {
+                vertices[row * 4 + col] =
+                    Vertex::textured(Point { x: dx, y: dy }, Point { x: sx, y: sy }, Color::WHITE);
+            }
+        }
+
+        let mut indices = [0; 54];
+        for (cell, quad) in indices.chunks_exact_mut(6).enumerate() {
+            let top_left = (cell / 3 * 4 + cell % 3) as u16;
+            for (index, corner) in quad.iter_mut().zip(Self::QUAD_INDICES) {
+                // Maps the corners of a quad (clockwise from the top-left) to
+                // the corners of the cell in the 4x4 grid of vertices.
+                *index = top_left + [0, 1, 5, 4][usize::from(corner)];
+            }
+        }
+
+        self.draw_mesh(&vertices, &indices, Some(image));
+    }
+
+    /// Draws `dst_rect` with a custom fragment shader. If `image` is provided,
+    /// the effect can sample its `src_rect` using the texel coordinates
+    /// passed to the shader.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively, or if more
+    /// than `u16::MAX` effects are drawn.
+    pub fn draw_effect(
+        &mut self,
+        effect: Handle<Effect>,
+        dst_rect: Rect,
+        image: Option<(Handle<Image>, Rect)>,
+        uniforms: &EffectUniforms,
+    ) {
+        let src_rect = image.map_or(dst_rect, |(_, src_rect)| src_rect);
+        let (first_index, num_indices) = self.append_geometry(
+            &[
+                Vertex::textured(dst_rect.top_left(), src_rect.top_left(), Color::WHITE),
+                Vertex::textured(dst_rect.top_right(), src_rect.top_right(), Color::WHITE),
+                Vertex::textured(
+                    dst_rect.bottom_right(),
+                    src_rect.bottom_right(),
+                    Color::WHITE,
+                ),
+                Vertex::textured(dst_rect.bottom_left(), src_rect.bottom_left(), Color::WHITE),
+            ],
+            &Self::QUAD_INDICES,
+        );
+
+        let index = u16::try_from(self.uniforms.len()).expect("too many effects in one frame");
+        self.uniforms.push(*uniforms);
+
+        self.push_command(Command::Effect {
+            effect,
+            image: image.map(|(image, _)| image),
+            uniforms: index,
+            first_index,
+            num_indices,
+        });
+    }
+
+    /// Draws a glyph stored at `src_rect` of `atlas` into `dst_rect`, in
+    /// `color`. Glyph images store coverage instead of color: grayscale
+    /// glyphs in their alpha channel, and subpixel glyphs in each color
+    /// channel, with the greatest of the three in the alpha channel.
+    ///
+    /// Subpixel glyphs are drawn as grayscale if the graphics device does not
+    /// support them; see [`GfxDevice::supports_subpixel_text`].
     ///
     /// ## Panics
     ///
     /// This function will panic if the number of vertices or indices exceeds
     /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
-    pub fn draw_image(&mut self, rect: Rect, image: Handle<Image>, region: Rect, paint: Paint) {
-        const NUM_VERTICES: u16 = 4;
-        const NUM_INDICES: u16 = 6;
+    pub fn draw_glyph(
+        &mut self,
+        atlas: Handle<Image>,
+        src_rect: Rect,
+        dst_rect: Rect,
+        color: Color,
+        anti_aliasing: GlyphAntiAliasing,
+    ) {
+        let (first_index, new_indices) = self.append_geometry(
+            &[
+                Vertex::textured(dst_rect.top_left(), src_rect.top_left(), color),
+                Vertex::textured(dst_rect.top_right(), src_rect.top_right(), color),
+                Vertex::textured(dst_rect.bottom_right(), src_rect.bottom_right(), color),
+                Vertex::textured(dst_rect.bottom_left(), src_rect.bottom_left(), color),
+            ],
+            &Self::QUAD_INDICES,
+        );
 
-        assert!(Self::MAX_VERTICES >= self.vertices.len() + NUM_VERTICES as usize);
-        assert!(Self::MAX_INDICES >= self.indices.len() + NUM_INDICES as usize);
+        match &mut self.current {
+            Some(Command::Glyphs {
+                atlas: current_atlas,
+                anti_aliasing: current_anti_aliasing,
+                num_indices,
+                ..
+            }) if *current_atlas == atlas && *current_anti_aliasing == anti_aliasing => {
+                *num_indices += new_indices;
+            }
+            _ => self.push_command(Command::Glyphs {
+                atlas,
+                anti_aliasing,
+                first_index,
+                num_indices: new_indices,
+            }),
+        }
+    }
 
-        let color = match paint {
-            Paint::Fill { color } => color,
+    /// Draws a color glyph, such as an emoji, stored at `src_rect` of `atlas`
+    /// into `dst_rect`. Unlike [`Self::draw_glyph`], the glyph image stores
+    /// color with unpremultiplied alpha, which is drawn as-is and faded by
+    /// `opacity`.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively.
+    pub fn draw_color_glyph(
+        &mut self,
+        atlas: Handle<Image>,
+        src_rect: Rect,
+        dst_rect: Rect,
+        opacity: f32,
+    ) {
+        let color = Color {
+            a: opacity,
+            ..Color::WHITE
         };
 
+        let (first_index, new_indices) = self.append_geometry(
+            &[
+                Vertex::textured(dst_rect.top_left(), src_rect.top_left(), color),
+                Vertex::textured(dst_rect.top_right(), src_rect.top_right(), color),
+                Vertex::textured(dst_rect.bottom_right(), src_rect.bottom_right(), color),
+                Vertex::textured(dst_rect.bottom_left(), src_rect.bottom_left(), color),
+            ],
+            &Self::QUAD_INDICES,
+        );
+
+        match &mut self.current {
+            Some(Command::ColorGlyphs {
+                atlas: current_atlas,
+                num_indices,
+                ..
+            }) if *current_atlas == atlas => {
+                *num_indices += new_indices;
+            }
+            _ => self.push_command(Command::ColorGlyphs {
+                atlas,
+                first_index,
+                num_indices: new_indices,
+            }),
+        }
+    }
+
+    /// Draws a list of triangles. Every three elements of `indices` describe a
+    /// triangle with clockwise winding, and index into `vertices`. If `image`
+    /// is provided, the triangles are textured using the `uv` of each vertex.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if the number of vertices or indices exceeds
+    /// `Self::MAX_VERTICES` or `Self::MAX_INDICES` respectively, or if an index
+    /// is out of bounds of `vertices`.
+    pub fn draw_mesh(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[u16],
+        image: Option<Handle<Image>>,
+    ) {
+        self.push_mesh(vertices, indices, image.map(|image| (image, Tiling::Clamp)));
+    }
+
+    /// Checks if nothing has been drawn since the list was created or
+    /// cleared.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.current.is_none() && self.commands.is_empty()
+    }
+
+    /// The triangles drawn by the list in the order that they are drawn, for
+    /// inspecting what was drawn without a graphics device. Transforms, clips,
+    /// and scissor rectangles are not applied to them.
+    #[must_use]
+    pub fn triangles(&self) -> Vec<Triangle> {
+        let mut triangles = Vec::new();
+
+        for command in self.commands.iter().chain(self.current.as_ref()) {
+            let (first_index, num_indices, textured) = match *command {
+                Command::Polygon {
+                    first_index,
+                    num_indices,
+                } => (first_index, num_indices, false),
+                Command::Image {
+                    first_index,
+                    num_indices,
+                    ..
+                }
+                | Command::Glyphs {
+                    first_index,
+                    num_indices,
+                    ..
+                }
+                | Command::ColorGlyphs {
+                    first_index,
+                    num_indices,
+                    ..
+                }
+                | Command::Effect {
+                    first_index,
+                    num_indices,
+                    ..
+                } => (first_index, num_indices, true),
+                Command::Scissor { .. }
+                | Command::Transform { .. }
+                | Command::PushClip { .. }
+                | Command::PopClip { .. } => continue,
+            };
+
+            let indices = &self.indices[usize::from(first_index)..][..usize::from(num_indices)];
+            triangles.extend(indices.chunks_exact(3).map(|triangle| Triangle {
+                vertices: [
+                    self.vertices[usize::from(triangle[0])],
+                    self.vertices[usize::from(triangle[1])],
+                    self.vertices[usize::from(triangle[2])],
+                ],
+                textured,
+            }));
+        }
+
+        triangles
+    }
+
+    /// Checks if any command samples `image`, which must not also be the
+    /// image that the list is drawn to.
+    fn reads_image(&self, image: Handle<Image>) -> bool {
+        self.commands
+            .iter()
+            .chain(self.current.as_ref())
+            .any(|command| match *command {
+                Command::Image { image: read, .. }
+                | Command::Glyphs { atlas: read, .. }
+                | Command::ColorGlyphs { atlas: read, .. }
+                | Command::Effect {
+                    image: Some(read), ..
+                } => read == image,
+                Command::Effect { image: None, .. }
+                | Command::Scissor { .. }
+                | Command::Polygon { .. }
+                | Command::Transform { .. }
+                | Command::PushClip { .. }
+                | Command::PopClip { .. } => false,
+            })
+    }
+
+    fn push_mesh(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[u16],
+        image: Option<(Handle<Image>, Tiling)>,
+    ) {
+        let (index_offset, new_indices) = self.append_geometry(vertices, indices);
+
+        // Geometry is only ever appended, so consecutive draws with the same
+        // texture (or lack thereof) can always be merged into one command.
+        match (&mut self.current, image) {
+            (Some(Command::Polygon { num_indices, .. }), None) => *num_indices += new_indices,
+            (
+                Some(Command::Image {
+                    image: current_image,
+                    tiling: current_tiling,
+                    num_indices,
+                    ..
+                }),
+                Some((image, tiling)),
+            ) if *current_image == image && *current_tiling == tiling => {
+                *num_indices += new_indices;
+            }
+            (_, None) => self.push_command(Command::Polygon {
+                first_index: index_offset,
+                num_indices: new_indices,
+            }),
+            (_, Some((image, tiling))) => self.push_command(Command::Image {
+                image,
+                tiling,
+                first_index: index_offset,
+                num_indices: new_indices,
+            }),
+        }
+    }
+
+    /// Copies the geometry into the list, returning the offset and number of
+    /// the new indices.
+    fn append_geometry(&mut self, vertices: &[Vertex], indices: &[u16]) -> (u16, u16) {
+        assert!(Self::MAX_VERTICES >= self.vertices.len() + vertices.len());
+        assert!(Self::MAX_INDICES >= self.indices.len() + indices.len());
+        assert!(indices.iter().all(|&i| usize::from(i) < vertices.len()));
+
         let vertex_offset = self.vertices.len() as u16;
-        self.vertices.extend_from_slice(&[
-            Vertex {
-                uv: region.top_left(),
-                point: rect.top_left(),
-                color,
-            },
-            Vertex {
-                uv: region.top_right(),
-                point: rect.top_right(),
-                color,
-            },
-            Vertex {
-                uv: region.bottom_right(),
-                point: rect.bottom_right(),
-                color,
-            },
-            Vertex {
-                uv: region.bottom_left(),
-                point: rect.bottom_left(),
-                color,
-            },
-        ]);
+        self.vertices.extend_from_slice(vertices);
+
+        let opacity = self.current_opacity();
+        if opacity < 1.0 {
+            for vertex in &mut self.vertices[usize::from(vertex_offset)..] {
+                vertex.color.a *= opacity;
+            }
+        }
 
         let index_offset = self.indices.len() as u16;
-        self.indices.extend_from_slice(&[
-            vertex_offset,
-            vertex_offset + 1,
-            vertex_offset + 2,
-            vertex_offset + 2,
-            vertex_offset + 3,
-            vertex_offset,
-        ]);
-
-        self.push_command(Command::Image {
-            image,
-            first_index: index_offset,
-            num_indices: NUM_INDICES,
-        });
+        self.indices
+            .extend(indices.iter().map(|&i| vertex_offset + i));
+
+        (index_offset, indices.len() as u16)
     }
 
     fn push_command(&mut self, new_command: Command) {
@@ -291,6 +1300,23 @@ impl DrawCommandList {
     }
 }
 
+/// Measurements of the work done by a [`GfxDevice`], for profiling. See
+/// [`GfxDevice::frame_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// The number of draw calls recorded by the last call to
+    /// [`GfxDevice::draw`].
+    pub draw_calls: u32,
+    /// The number of vertices drawn by the last call to [`GfxDevice::draw`].
+    pub vertices: u32,
+    /// How long the graphics processor took to execute the most recent draw
+    /// that has completed, or `None` if the device can't measure it.
+    pub gpu_time: Option<Duration>,
+    /// The number of bytes of device memory used by all images, including
+    /// those created internally such as glyph atlases.
+    pub texture_memory: u64,
+}
+
 ///
 /// Most methods take `&self` instead of `&mut self` for two reasons: so that
 /// the methods can be treated much like one might treat `malloc` (that is,
@@ -298,30 +1324,128 @@ impl DrawCommandList {
 /// backend to expand its buffers at need. The second reason is more absolute,
 /// but certainly could have been worked around in some way.
 ///
-pub trait GfxDevice {
+/// Devices are shared between threads, so that images can be created and
+/// uploaded on worker threads while another thread draws.
+pub trait GfxDevice: Send + Sync {
+    /// Creates a swapchain for drawing to the window. If `transparent` is
+    /// set, the window is blended with what is behind it using the alpha of
+    /// what is drawn, where the platform supports it, and is redrawn in full
+    /// every frame.
     #[cfg(target_os = "windows")]
     fn create_swapchain(
         &self,
         hwnd: windows::Win32::Foundation::HWND,
+        transparent: bool,
+    ) -> Result<Handle<Swapchain>, Error>;
+
+    /// Creates a swapchain for drawing to an X11 window, given the Xlib
+    /// display connection that it was created with. See the Windows version
+    /// for `transparent`.
+    #[cfg(target_os = "linux")]
+    fn create_swapchain(
+        &self,
+        display: *mut std::ffi::c_void,
+        window: std::os::raw::c_ulong,
+        transparent: bool,
     ) -> Result<Handle<Swapchain>, Error>;
 
-    fn resize_swapchain(&self, handle: Handle<Swapchain>, extent: Extent) -> Result<(), Error>;
+    /// Resizes the swapchain's images to `extent`, which should be the size of
+    /// its window's client area in physical pixels.
+    fn resize_swapchain(
+        &self,
+        handle: Handle<Swapchain>,
+        extent: PhysicalExtent,
+    ) -> Result<(), Error>;
+
+    /// Allows or prevents the graphics driver from taking exclusive control of
+    /// the display while the swapchain's window covers it. Only windows in
+    /// [`FullscreenMode::Exclusive`](crate::shell::FullscreenMode::Exclusive)
+    /// should allow it, since switching away from them is slow. The swapchain
+    /// is recreated if this changes, and must be redrawn in full.
+    ///
+    /// Does nothing if the device can't control this.
+    fn set_swapchain_exclusive(
+        &self,
+        handle: Handle<Swapchain>,
+        exclusive: bool,
+    ) -> Result<(), Error>;
+
+    /// Makes presenting wait for the display's vertical blank, which caps the
+    /// frame rate at the refresh rate, or lets images be shown as soon as
+    /// they're ready. Swapchains start with vsync on. The swapchain is
+    /// recreated if this changes, and must be redrawn in full.
+    ///
+    /// Falls back to vsync if the display can't present any other way.
+    fn set_swapchain_vsync(&self, handle: Handle<Swapchain>, vsync: bool) -> Result<(), Error>;
 
     fn destroy_swapchain(&self, handle: Handle<Swapchain>) -> Result<(), Error>;
 
+    /// Acquires the next image in the swapchain for drawing. `damage` is the
+    /// region of the window that changed since the last frame, in physical
+    /// pixels like the image itself.
+    ///
+    /// Returns the region of the image that must be redrawn. This may be
+    /// larger than `damage` since the image may have missed changes that were
+    /// drawn into other images in the swapchain. Only this region will be
+    /// updated by the next call to [`GfxDevice::draw`]; the rest of the image
+    /// is preserved.
+    ///
+    /// Drawing to a swapchain without first calling this method redraws the
+    /// entire image.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::SwapchainOutOfDate`] if the swapchain must be resized
+    /// before it can be drawn to.
+    fn get_next_swapchain_image(
+        &self,
+        handle: Handle<Swapchain>,
+        damage: PhysicalRect,
+    ) -> Result<PhysicalRect, Error>;
+
     /// Presents the next image in each swapchain after waiting for drawing to
     /// those images to complete. The render target handles used to render to
     /// the swapchains will be invalid once this method returns. Retrieve the
     /// next image in a swapchain by calling `get_next_swapchain_image`.
     ///
+    /// Each swapchain is paired with the regions of the image that changed
+    /// since the last present. These are passed on to the compositor where
+    /// supported so that it only needs to update those regions. An empty list
+    /// of regions means that the entire image changed. Regions are hints, and
+    /// may be ignored.
+    ///
     /// ## Synchronization
     ///
     /// This is a synchronizing operation and will block until rendering to the
     /// next image in each swapchain is complete.
-    fn present_swapchains(&self, handles: &[Handle<Swapchain>]) -> Result<(), Error>;
+    fn present_swapchains(
+        &self,
+        swapchains: &[(Handle<Swapchain>, &[PhysicalRect])],
+    ) -> Result<(), Error>;
 
     /// Creates an image that can be used in rendering operations.
-    fn create_image(&self, extent: Extent) -> Result<Handle<Image>, Error>;
+    ///
+    /// Uncompressed layouts are converted into a common internal format when
+    /// pixels are copied into the image. Block-compressed layouts are stored
+    /// as-is, and so can only be copied into from a [`PixelBuffer`] with the
+    /// same layout.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::UnsupportedLayout`] if the layout is block-compressed
+    /// and the graphics device does not support that compression format.
+    fn create_image(&self, extent: Extent, layout: Layout) -> Result<Handle<Image>, Error>;
+
+    /// Imports an image created by another API or process so that it can be
+    /// drawn without copying its contents. The imported image must be
+    /// destroyed with [`GfxDevice::destroy_image`] before the external image
+    /// is released.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::ExternalImagesNotSupported`] if the graphics device
+    /// cannot import memory from the provided handle type.
+    fn import_image(&self, image: &ExternalImage) -> Result<Handle<Image>, Error>;
 
     /// Copies portions of a pixel buffer to a target image for rendering.
     /// Operations involving areas beyond the pixel buffer view _or_ the target
@@ -334,6 +1458,13 @@ pub trait GfxDevice {
     ///
     /// Will return `Error::MustResampleImage` if `op.must_resample()` and
     /// resampling has been disabled with [`Resample::None`].
+    ///
+    /// Copies into imported images will return [`Error::ReadOnlyImage`].
+    ///
+    /// Copies into block-compressed images will return
+    /// [`Error::IncompatibleLayout`] if `src` does not have the same layout as
+    /// the image, and [`Error::UnalignedCopy`] if any operation is not aligned
+    /// to the compression block size.
     fn copy_pixels(
         &self,
         src: PixelBufferView,
@@ -356,6 +1487,42 @@ pub trait GfxDevice {
         ops: &[ImageCopy],
     ) -> Result<(), Error>;
 
+    /// Creates an effect from a SPIR-V fragment shader. The shader receives
+    /// the following inputs, and must write its color to location 0:
+    ///
+    /// ```glsl
+    /// layout(location = 0) in vec4 fragColor;
+    /// // Texel coordinates within the image, or window coordinates if the
+    /// // effect is drawn without an image.
+    /// layout(location = 1) in vec2 fragUv;
+    /// // Only valid if the effect is drawn with an image. The sampler uses
+    /// // unnormalized coordinates.
+    /// layout(set = 0, binding = 0) uniform sampler2D image;
+    /// layout(push_constant) uniform Uniforms {
+    ///     layout(offset = 40) vec4 uniforms[4];
+    /// };
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::InvalidShader`] if the shader could not be compiled
+    /// into a pipeline, and [`Error::TooManyObjects`] if more than
+    /// [`MAX_EFFECTS`] effects exist at once.
+    fn create_effect(&self, fragment_shader: &[u32]) -> Result<Handle<Effect>, Error>;
+
+    /// Destroys an effect. This waits for any draws that use the effect to
+    /// complete.
+    fn destroy_effect(&self, handle: Handle<Effect>) -> Result<(), Error>;
+
+    /// Checks if subpixel glyphs drawn to the swapchain will be blended per
+    /// channel. This requires that the graphics device supports dual-source
+    /// blending and that the swapchain has an opaque, 8-bit RGB format.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::InvalidHandle`] if the swapchain does not exist.
+    fn supports_subpixel_text(&self, handle: Handle<Swapchain>) -> Result<bool, Error>;
+
     /// Deletes the image, freeing any resources that were associated with it.
     ///
     /// ## Errors
@@ -373,16 +1540,130 @@ pub trait GfxDevice {
     fn get_image_pixels(&self, handle: Handle<Image>) -> Result<PixelBuffer, Error>;
 
     /// Draws the provided geometry to the render target. All content that was
-    /// once in the render target will be overwritten.
+    /// once in the render target will be overwritten, except for the parts of
+    /// a swapchain image outside of the region returned by
+    /// [`GfxDevice::get_next_swapchain_image`].
+    ///
+    /// Geometry is placed in the pixels of the render target, which for a
+    /// swapchain are physical pixels. Commands recorded in logical pixels
+    /// should start with a [`Transform::scale`] by the window's
+    /// [`ScaleFactor`](geometry::ScaleFactor), as
+    /// [`DrawContext`](crate::gui::widgets::DrawContext) records.
+    ///
+    /// An image that is drawn to starts out transparent, and can be drawn by
+    /// later command lists like any other image. Only images with
+    /// uncompressed layouts can be drawn to.
     ///
     /// The command list can be reused immediately once this method returns.
+    ///
+    /// ## Errors
+    ///
+    /// Drawing to an image returns [`Error::ResourceInUse`] if the commands
+    /// also read from it, [`Error::ReadOnlyImage`] if it was imported, and
+    /// [`Error::UnsupportedLayout`] if its layout is compressed.
     fn draw(&self, render_target: RenderTarget, commands: &DrawCommandList) -> Result<(), Error>;
 
     /// Flushes all work from the device. This stalls the backend and can hurt
     /// performance.
     fn flush(&self);
+
+    /// Statistics about the most recent draws, such as for a performance
+    /// overlay. The GPU time lags behind the other measurements since it is
+    /// only known once the graphics processor finishes drawing.
+    fn frame_stats(&self) -> FrameStats;
 }
 
 pub fn init_gfx() -> Result<Box<dyn GfxDevice>, Error> {
     Ok(Box::new(self::vulkan::VulkanGfxDevice::new(true)?))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::ArenaVec;
+
+    use super::*;
+
+    #[test]
+    fn scratch_is_reused() {
+        let mut commands = DrawCommandList::new();
+        for _ in 0..3 {
+            commands.with_scratch(|_, scratch| {
+                let mut points = ArenaVec::with_capacity(scratch, 64);
+                points.push(PointF::new(0.0, 0.0));
+            });
+        }
+
+        assert_eq!(commands.scratch.capacity(), FrameArena::MIN_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn dashed_stroke() {
+        let mut commands = DrawCommandList::new();
+        let dash = Dash {
+            on: 2.0,
+            off: 2.0,
+            phase: 0.0,
+        };
+
+        // Dashes at 0..2, 4..6 and 8..10.
+        commands.stroke_line(
+            PointF::new(0.0, 0.0),
+            PointF::new(10.0, 0.0),
+            &Stroke::dashed(Color::WHITE, 2.0, dash),
+        );
+        assert_eq!(commands.vertices.len(), 3 * 4);
+
+        // Starting halfway through the first dash shifts them to 0..1, 3..5 and
+        // 7..9.
+        commands.clear();
+        commands.stroke_line(
+            PointF::new(0.0, 0.0),
+            PointF::new(10.0, 0.0),
+            &Stroke::dashed(Color::WHITE, 2.0, Dash { phase: 1.0, ..dash }),
+        );
+        assert_eq!(commands.vertices.len(), 3 * 4);
+        assert_eq!(commands.vertices[1].point, Point::new(Px(1), Px(-1)));
+
+        // The pattern continues around the corners of a closed outline.
+        commands.clear();
+        commands.stroke_rect(
+            Rect::new(
+                Point::zero(),
+                Extent {
+                    width: Px(4),
+                    height: Px(4),
+                },
+            ),
+            &Stroke::dashed(Color::WHITE, 1.0, Dash::dotted(2.0)),
+        );
+        assert_eq!(commands.vertices.len(), 4 * 4);
+    }
+
+    #[test]
+    fn nested_opacity() {
+        let mut commands = DrawCommandList::new();
+        let rect = Rect::new(
+            Point::zero(),
+            Extent {
+                width: Px(4),
+                height: Px(4),
+            },
+        );
+
+        commands.push_opacity(0.5);
+        commands.draw_rect(rect, Paint::Fill { color: Color::RED });
+        commands.push_opacity(0.5);
+        commands.draw_rect(rect, Paint::Fill { color: Color::RED });
+        commands.pop_opacity();
+        commands.pop_opacity();
+        commands.draw_rect(rect, Paint::Fill { color: Color::RED });
+
+        let alphas: Vec<_> = commands
+            .vertices
+            .iter()
+            .step_by(4)
+            .map(|vertex| vertex.color.a)
+            .collect();
+        assert_eq!(alphas, [0.5, 0.25, 1.0]);
+    }
+}
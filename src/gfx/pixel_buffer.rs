@@ -1,20 +1,70 @@
-use super::geometry::{Extent, Point, Rect};
+use super::geometry::{Extent, Point, Px, Rect};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Layout {
     RGB8,
     RGBA8,
+    /// BC1 (DXT1) block-compressed RGB with 1-bit alpha. Each 4x4 block of
+    /// pixels is stored in 8 bytes.
+    BC1,
+    /// BC7 block-compressed RGBA. Each 4x4 block of pixels is stored in 16
+    /// bytes.
+    BC7,
 }
 
 impl Layout {
+    /// The number of bytes used to store a single pixel.
+    ///
+    /// ## Panics
+    ///
+    /// Block-compressed layouts do not store pixels individually, and will
+    /// panic. Use [`Layout::bytes_per_block`] instead.
     #[must_use]
     pub fn bytes_per_pixel(&self) -> usize {
+        assert!(
+            !self.is_compressed(),
+            "compressed layouts have no per-pixel size"
+        );
+        self.bytes_per_block()
+    }
+
+    /// The number of bytes used to store a single block of pixels. For
+    /// uncompressed layouts, a block is a single pixel.
+    #[must_use]
+    pub fn bytes_per_block(&self) -> usize {
         match self {
             Layout::RGB8 => 3,
             Layout::RGBA8 => 4,
+            Layout::BC1 => 8,
+            Layout::BC7 => 16,
+        }
+    }
+
+    /// The width and height of a block of pixels. This is always 1 for
+    /// uncompressed layouts.
+    #[must_use]
+    pub fn block_size(&self) -> i16 {
+        match self {
+            Layout::RGB8 | Layout::RGBA8 => 1,
+            Layout::BC1 | Layout::BC7 => 4,
         }
     }
+
+    #[must_use]
+    pub fn is_compressed(&self) -> bool {
+        self.block_size() > 1
+    }
+
+    /// Calculates the number of bytes needed to store an image of the given
+    /// extent, rounding partial blocks up to whole blocks.
+    #[must_use]
+    pub fn size_of(&self, extent: Extent) -> usize {
+        let block = self.block_size();
+        let blocks_wide = (extent.width.0 + block - 1) / block;
+        let blocks_high = (extent.height.0 + block - 1) / block;
+        blocks_wide as usize * blocks_high as usize * self.bytes_per_block()
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -25,6 +75,7 @@ pub enum ColorSpace {
 }
 
 #[must_use]
+#[derive(Clone)]
 pub struct PixelBuffer {
     layout: Layout,
     color_space: ColorSpace,
@@ -38,7 +89,7 @@ pub struct PixelBuffer {
 
 impl PixelBuffer {
     pub fn new(layout: Layout, color_space: ColorSpace, extent: Extent, bytes: Box<[u8]>) -> Self {
-        let num_bytes = layout.bytes_per_pixel() * extent.area();
+        let num_bytes = layout.size_of(extent);
         assert_eq!(num_bytes, bytes.len());
 
         Self {
@@ -137,7 +188,8 @@ pub struct Bytes<'a> {
     cursor: usize,
     /// One past the last byte in the span, used as a sentinel.
     last_byte: usize,
-    /// The number of bytes to return in a span.
+    /// The number of bytes to return in a span. For block-compressed layouts,
+    /// a span is one row of blocks.
     span_width: usize,
     /// The distance from the start of one span to the start of the next.
     span_offset: usize,
@@ -145,16 +197,24 @@ pub struct Bytes<'a> {
 
 impl<'a> Bytes<'a> {
     fn new(view: &PixelBufferView<'a>) -> Self {
-        let cursor = (view.region.left.0 as usize
-            + view.region.top.0 as usize * view.region.width().0 as usize)
-            * view.layout().bytes_per_pixel();
+        let layout = view.layout();
+        let block = layout.block_size();
+        let bytes_per_block = layout.bytes_per_block();
+
+        // Work in blocks rather than pixels so that compressed layouts can
+        // share the same iterator. Uncompressed layouts have 1x1 blocks.
+        let to_blocks = |px: Px| ((px.0 + block - 1) / block) as usize;
+        let source_width = to_blocks(view.source.extent.width);
+
+        let cursor = (to_blocks(view.region.left) + to_blocks(view.region.top) * source_width)
+            * bytes_per_block;
 
-        let last_byte = (view.region.right.0 as usize
-            + view.region.bottom.0 as usize * view.region.width().0 as usize)
-            * view.layout().bytes_per_pixel();
+        let last_byte = (to_blocks(view.region.right)
+            + to_blocks(view.region.bottom) * source_width)
+            * bytes_per_block;
 
-        let span_width = view.region.width().0 as usize * view.layout().bytes_per_pixel();
-        let span_offset = view.source.extent.width.0 as usize * view.layout().bytes_per_pixel();
+        let span_width = to_blocks(view.region.width()) * bytes_per_block;
+        let span_offset = source_width * bytes_per_block;
 
         Self {
             pixels: view.source,
@@ -0,0 +1,303 @@
+//! Draws a [`DrawCommandList`] on the CPU, for comparing what widgets draw
+//! with reference images without a graphics device.
+//!
+//! The rasterizer follows the Vulkan backend closely enough for regression
+//! tests, but not exactly: pixels are sampled once at their centers without
+//! anti-aliasing, and blending happens on 8-bit values. Images, glyphs, and
+//! effects live on the graphics device, so they aren't drawn.
+
+use super::{
+    color::Color,
+    geometry::{Extent, Rect, Transform},
+    pixel_buffer::{ColorSpace, Layout, PixelBuffer},
+    Command, DrawCommandList, Vertex,
+};
+
+/// Draws `commands` onto an image of size `extent` filled with `background`,
+/// and returns it as `RGBA8`.
+pub fn rasterize(commands: &DrawCommandList, extent: Extent, background: Color) -> PixelBuffer {
+    let mut target = Target::new(extent, background);
+
+    let mut transform = Transform::IDENTITY;
+    let mut scissor = target.bounds();
+    let mut clip_depth = 0;
+
+    for command in commands.commands.iter().chain(commands.current.as_ref()) {
+        match *command {
+            Command::Scissor { rect } => scissor = rect.intersect(&target.bounds()),
+            Command::Transform {
+                transform: new_transform,
+            } => transform = new_transform,
+            Command::Polygon {
+                first_index,
+                num_indices,
+            } => {
+                for triangle in triangles(commands, first_index, num_indices, &transform) {
+                    target.fill(&triangle, scissor, clip_depth, |pixel, color| {
+                        *pixel = blend(*pixel, color);
+                    });
+                }
+            }
+            Command::PushClip {
+                first_index,
+                num_indices,
+            } => {
+                // Overlapping triangles only add to the clip once, since the
+                // second sees the incremented depth.
+                for triangle in triangles(commands, first_index, num_indices, &transform) {
+                    target.mark(&triangle, scissor, clip_depth, clip_depth + 1);
+                }
+                clip_depth += 1;
+            }
+            Command::PopClip {
+                first_index,
+                num_indices,
+            } => {
+                for triangle in triangles(commands, first_index, num_indices, &transform) {
+                    target.mark(&triangle, scissor, clip_depth, clip_depth - 1);
+                }
+                clip_depth -= 1;
+            }
+            Command::Image { .. }
+            | Command::Glyphs { .. }
+            | Command::ColorGlyphs { .. }
+            | Command::Effect { .. } => {}
+        }
+    }
+
+    target.into_pixel_buffer()
+}
+
+/// The transformed vertices of each triangle drawn by a command.
+fn triangles<'a>(
+    commands: &'a DrawCommandList,
+    first_index: u16,
+    num_indices: u16,
+    transform: &'a Transform,
+) -> impl Iterator<Item = [([f32; 2], Color); 3]> + 'a {
+    let vertex = move |index: u16| {
+        let Vertex { point, color, .. } = commands.vertices[usize::from(index)];
+        (
+            transform.apply([f32::from(point.x), f32::from(point.y)]),
+            color,
+        )
+    };
+
+    commands.indices[usize::from(first_index)..][..usize::from(num_indices)]
+        .chunks_exact(3)
+        .map(move |triangle| {
+            [
+                vertex(triangle[0]),
+                vertex(triangle[1]),
+                vertex(triangle[2]),
+            ]
+        })
+}
+
+/// Blends `color` over `pixel` as the Vulkan backend does.
+fn blend(pixel: [u8; 4], color: Color) -> [u8; 4] {
+    let [r, g, b, a] = pixel.map(|channel| f32::from(channel) / 255.0);
+    let inverse = 1.0 - color.a;
+
+    Color {
+        r: color.r * color.a + r * inverse,
+        g: color.g * color.a + g * inverse,
+        b: color.b * color.a + b * inverse,
+        a: color.a + a * inverse,
+    }
+    .to_rgba8()
+}
+
+/// The pixels being drawn to and the clip depth of each, which is the number
+/// of clips that contain the pixel.
+struct Target {
+    extent: Extent,
+    pixels: Vec<[u8; 4]>,
+    clip_depths: Vec<u32>,
+}
+
+impl Target {
+    fn new(extent: Extent, background: Color) -> Self {
+        let len = extent.width.0.max(0) as usize * extent.height.0.max(0) as usize;
+        Self {
+            extent,
+            pixels: vec![background.to_rgba8(); len],
+            clip_depths: vec![0; len],
+        }
+    }
+
+    fn bounds(&self) -> Rect {
+        Rect::new(super::geometry::Point::zero(), self.extent)
+    }
+
+    /// Calls `shade` with the interpolated color of each pixel in `scissor`
+    /// whose center `triangle` covers, and that is in all of the clips in
+    /// effect.
+    fn fill(
+        &mut self,
+        triangle: &[([f32; 2], Color); 3],
+        scissor: Rect,
+        clip_depth: u32,
+        mut shade: impl FnMut(&mut [u8; 4], Color),
+    ) {
+        let colors = triangle.map(|(_, color)| color);
+        let width = self.extent.width.0 as usize;
+
+        for_each_covered(
+            triangle.map(|(point, _)| point),
+            scissor,
+            |x, y, weights| {
+                let index = y * width + x;
+                if self.clip_depths[index] != clip_depth {
+                    return;
+                }
+
+                let channel = |f: fn(&Color) -> f32| {
+                    weights[0] * f(&colors[0])
+                        + weights[1] * f(&colors[1])
+                        + weights[2] * f(&colors[2])
+                };
+                let color = Color {
+                    r: channel(|c| c.r),
+                    g: channel(|c| c.g),
+                    b: channel(|c| c.b),
+                    a: channel(|c| c.a),
+                };
+
+                shade(&mut self.pixels[index], color);
+            },
+        );
+    }
+
+    /// Changes the clip depth of the pixels that `triangle` covers from
+    /// `from` to `to`.
+    fn mark(&mut self, triangle: &[([f32; 2], Color); 3], scissor: Rect, from: u32, to: u32) {
+        let width = self.extent.width.0 as usize;
+
+        for_each_covered(triangle.map(|(point, _)| point), scissor, |x, y, _| {
+            let depth = &mut self.clip_depths[y * width + x];
+            if *depth == from {
+                *depth = to;
+            }
+        });
+    }
+
+    fn into_pixel_buffer(self) -> PixelBuffer {
+        PixelBuffer::new(
+            Layout::RGBA8,
+            ColorSpace::Linear,
+            self.extent,
+            self.pixels.into_iter().flatten().collect(),
+        )
+    }
+}
+
+/// Calls `f` with each pixel in `bounds` whose center is covered by the
+/// triangle, and the weights of the triangle's vertices at that center.
+///
+/// Pixels on an edge shared by two triangles are covered by exactly one of
+/// them, so that meshes don't blend twice along their seams. Like the GPU's
+/// top-left rule, this is decided by which way the edge points.
+fn for_each_covered(
+    points: [[f32; 2]; 3],
+    bounds: Rect,
+    mut f: impl FnMut(usize, usize, [f32; 3]),
+) {
+    let [mut a, b, mut c] = points;
+
+    let edge = |[x0, y0]: [f32; 2], [x1, y1]: [f32; 2], [x, y]: [f32; 2]| {
+        (x1 - x0) * (y - y0) - (y1 - y0) * (x - x0)
+    };
+
+    // Wind the triangle one way so that the weights are positive inside it,
+    // remembering to swap the weights of the first and last vertices back.
+    let mut area = edge(a, b, c);
+    let swapped = area < 0.0;
+    if area == 0.0 {
+        return;
+    } else if swapped {
+        std::mem::swap(&mut a, &mut c);
+        area = -area;
+    }
+
+    let is_top_left = |[x0, y0]: [f32; 2], [x1, y1]: [f32; 2]| (y0 == y1 && x1 > x0) || y1 < y0;
+    let edges = [(b, c), (c, a), (a, b)];
+    let top_left = edges.map(|(from, to)| is_top_left(from, to));
+
+    let min = |i: usize| a[i].min(b[i]).min(c[i]);
+    let max = |i: usize| a[i].max(b[i]).max(c[i]);
+    let left = (min(0).floor() as i32).max(bounds.left.0.into());
+    let right = (max(0).ceil() as i32).min(bounds.right.0.into());
+    let top = (min(1).floor() as i32).max(bounds.top.0.into());
+    let bottom = (max(1).ceil() as i32).min(bounds.bottom.0.into());
+
+    for y in top..bottom {
+        for x in left..right {
+            let center = [x as f32 + 0.5, y as f32 + 0.5];
+            let weights = edges.map(|(from, to)| edge(from, to, center));
+
+            let covered = weights
+                .iter()
+                .zip(top_left)
+                .all(|(weight, top_left)| *weight > 0.0 || (*weight == 0.0 && top_left));
+
+            if covered {
+                let [wa, wb, wc] = weights.map(|weight| weight / area);
+                let weights = if swapped { [wc, wb, wa] } else { [wa, wb, wc] };
+                f(x as usize, y as usize, weights);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gfx::{
+        geometry::{Point, Px},
+        Paint,
+    };
+
+    use super::*;
+
+    fn pixel(image: &PixelBuffer, x: usize, y: usize) -> [u8; 4] {
+        let width = image.extent().width.0 as usize;
+        let i = (y * width + x) * 4;
+        image.bytes()[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn fill_and_clip() {
+        let extent = Extent {
+            width: Px(8),
+            height: Px(8),
+        };
+        let half = Rect::new(
+            Point::zero(),
+            Extent {
+                width: Px(4),
+                height: Px(8),
+            },
+        );
+
+        let mut commands = DrawCommandList::new();
+        commands.push_clip_rounded_rect(half, Px(0));
+        commands.draw_rect(
+            Rect::new(Point::zero(), extent),
+            Paint::Fill {
+                color: Color {
+                    a: 0.5,
+                    ..Color::RED
+                },
+            },
+        );
+        commands.pop_clip();
+
+        let image = rasterize(&commands, extent, Color::BLACK);
+
+        // Half of the red is blended over the black, along the quad's
+        // diagonal as well.
+        assert_eq!(pixel(&image, 0, 0), [128, 0, 0, 255]);
+        assert_eq!(pixel(&image, 3, 3), [128, 0, 0, 255]);
+        assert_eq!(pixel(&image, 4, 0), [0, 0, 0, 255]);
+    }
+}
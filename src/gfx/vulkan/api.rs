@@ -1,6 +1,9 @@
-use std::ffi::c_char;
+use std::{
+    ffi::{c_char, CStr},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
 
-use ash::vk;
+use ash::{extensions::ext::DebugUtils, vk};
 use smallvec::SmallVec;
 
 use crate::gfx::Error;
@@ -15,6 +18,11 @@ pub struct PhysicalDevice {
     pub graphics_queue_family: u32,
     pub transfer_queue_family: u32,
     pub present_queue_family: u32,
+    /// Whether or not the device can sample from BC-compressed images.
+    pub texture_compression_bc: bool,
+    /// Whether or not fragment shaders can output a second color for use as
+    /// a per-channel blend factor, which subpixel text needs.
+    pub dual_source_blend: bool,
 }
 
 #[derive(Clone, Copy)]
@@ -36,6 +44,10 @@ pub struct Vulkan {
 
     pub physical_device: PhysicalDevice,
 
+    /// The device extensions that were enabled, including every optional
+    /// extension that was found.
+    enabled_device_extensions: SmallVec<[*const c_char; 8]>,
+
     pub pipeline_cache: vk::PipelineCache,
     pub graphics_queue: vk::Queue,
     pub transfer_queue: vk::Queue,
@@ -46,8 +58,23 @@ pub struct Vulkan {
 
     #[cfg(target_os = "windows")]
     pub win32_surface_khr: ash::extensions::khr::Win32Surface,
+
+    #[cfg(target_os = "linux")]
+    pub xlib_surface_khr: ash::extensions::khr::XlibSurface,
+
+    /// Used to label command buffers for graphics debuggers. Only present if
+    /// `VK_EXT_debug_utils` is available.
+    debug_utils: Option<DebugUtils>,
+
+    /// Held while any queue is in use. See [`Self::lock_queues`].
+    queue_lock: Mutex<()>,
 }
 
+// The extension names point to static strings, and everything else is either
+// a handle or a table of function pointers.
+unsafe impl Send for Vulkan {}
+unsafe impl Sync for Vulkan {}
+
 impl Vulkan {
     #[allow(clippy::too_many_lines)]
     pub fn new(
@@ -62,7 +89,7 @@ impl Vulkan {
             .map_err(|_| Error::BackendNotFound)
             .unwrap();
 
-        let instance = {
+        let (instance, debug_utils_enabled) = {
             let instance_layers = has_names(
                 &entry.enumerate_instance_layer_properties()?,
                 |layer| &layer.layer_name,
@@ -97,21 +124,45 @@ impl Vulkan {
                 ..Default::default()
             };
 
-            unsafe { entry.create_instance(&create_info, None) }?
+            let debug_utils_enabled = instance_extensions
+                .iter()
+                // SAFETY: extension names are always null-terminated.
+                .any(|name| unsafe { CStr::from_ptr(*name) } == DebugUtils::name());
+
+            (
+                unsafe { entry.create_instance(&create_info, None) }?,
+                debug_utils_enabled,
+            )
         };
 
+        let debug_utils = debug_utils_enabled.then(|| DebugUtils::new(&entry, &instance));
+
         let surface_khr = ash::extensions::khr::Surface::new(&entry, &instance);
 
         #[cfg(target_os = "windows")]
         let win32_surface_khr = ash::extensions::khr::Win32Surface::new(&entry, &instance);
 
+        #[cfg(target_os = "linux")]
+        let xlib_surface_khr = ash::extensions::khr::XlibSurface::new(&entry, &instance);
+
         let (gpu, device_extensions) = select_gpu(
             &instance,
             required_device_extensions,
             optional_device_extensions,
-            |gpu, queue| unsafe {
+            |gpu, queue| {
                 #[cfg(target_os = "windows")]
-                win32_surface_khr.get_physical_device_win32_presentation_support(gpu, queue)
+                return unsafe {
+                    win32_surface_khr.get_physical_device_win32_presentation_support(gpu, queue)
+                };
+
+                // Xlib can only be asked about presentation with a connection
+                // to the X server, which the device doesn't have. Every queue
+                // that can draw can also present with the drivers we know of.
+                #[cfg(target_os = "linux")]
+                return {
+                    let _ = (gpu, queue);
+                    true
+                };
             },
         )?;
 
@@ -153,6 +204,11 @@ impl Vulkan {
                 features12 = vk::PhysicalDeviceVulkan12Features::default();
                 features12.timeline_semaphore = vk::TRUE;
                 vk::PhysicalDeviceFeatures2::builder()
+                    .features(vk::PhysicalDeviceFeatures {
+                        texture_compression_bc: gpu.texture_compression_bc.into(),
+                        dual_src_blend: gpu.dual_source_blend.into(),
+                        ..Default::default()
+                    })
                     .push_next(&mut features12)
                     .build()
             } else {
@@ -183,14 +239,90 @@ impl Vulkan {
             instance,
             device,
             physical_device: gpu,
+            enabled_device_extensions: device_extensions,
             pipeline_cache,
             graphics_queue,
             transfer_queue,
             present_queue,
             surface_khr,
             swapchain_khr,
+            #[cfg(target_os = "windows")]
             win32_surface_khr,
+            #[cfg(target_os = "linux")]
+            xlib_surface_khr,
+            debug_utils,
+            queue_lock: Mutex::new(()),
+        })
+    }
+
+    /// Opens a labeled region in the command buffer that is shown by graphics
+    /// debuggers. Does nothing if `VK_EXT_debug_utils` is not available.
+    pub fn begin_label(&self, command_buffer: vk::CommandBuffer, label: &CStr) {
+        if let Some(debug_utils) = &self.debug_utils {
+            let label = vk::DebugUtilsLabelEXT::builder().label_name(label);
+            unsafe { debug_utils.cmd_begin_debug_utils_label(command_buffer, &label) };
+        }
+    }
+
+    /// Closes the region opened by the last call to [`Self::begin_label`].
+    pub fn end_label(&self, command_buffer: vk::CommandBuffer) {
+        if let Some(debug_utils) = &self.debug_utils {
+            unsafe { debug_utils.cmd_end_debug_utils_label(command_buffer) };
+        }
+    }
+
+    /// Locks the device's queues so that work can be submitted or presented.
+    /// Vulkan requires queues to be externally synchronized, and the queues
+    /// may be shared with each other, so one lock covers all of them.
+    pub fn lock_queues(&self) -> MutexGuard<'_, ()> {
+        self.queue_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits for all work on the device to complete. This locks the queues,
+    /// since waiting accesses each of them.
+    pub fn wait_idle(&self) -> VkResult<()> {
+        let _queues = self.lock_queues();
+        unsafe { self.device.device_wait_idle() }
+    }
+
+    /// Checks if command buffer labels will be recorded, so that callers can
+    /// skip formatting them otherwise.
+    pub fn has_labels(&self) -> bool {
+        self.debug_utils.is_some()
+    }
+
+    /// Checks if an extension was enabled when the device was created.
+    pub fn is_device_extension_enabled(&self, name: &[c_char]) -> bool {
+        // SAFETY: extension names are always null-terminated.
+        let name = unsafe { CStr::from_ptr(name.as_ptr()) };
+        self.enabled_device_extensions
+            .iter()
+            .any(|enabled| unsafe { CStr::from_ptr(*enabled) } == name)
+    }
+
+    /// Selects a format that can be used for stencil attachments, preferring
+    /// formats without a depth component.
+    pub fn select_stencil_format(&self) -> vk::Format {
+        // The Vulkan spec guarantees that at least one of the combined
+        // depth-stencil formats is supported.
+        [
+            vk::Format::S8_UINT,
+            vk::Format::D24_UNORM_S8_UINT,
+            vk::Format::D32_SFLOAT_S8_UINT,
+        ]
+        .into_iter()
+        .find(|format| {
+            let properties = unsafe {
+                self.instance
+                    .get_physical_device_format_properties(self.physical_device.handle, *format)
+            };
+            properties
+                .optimal_tiling_features
+                .contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
         })
+        .unwrap()
     }
 
     pub fn allocate_buffer(
@@ -230,6 +362,18 @@ impl Vulkan {
         usage: MemoryUsage,
         requirements: vk::MemoryRequirements,
     ) -> VkResult<vk::DeviceMemory> {
+        let create_info = vk::MemoryAllocateInfo {
+            allocation_size: requirements.size,
+            memory_type_index: self.select_memory_type(usage, requirements.memory_type_bits),
+            ..Default::default()
+        };
+
+        unsafe { self.device.allocate_memory(&create_info, None) }
+    }
+
+    /// Selects the best memory type for the usage out of those permitted by
+    /// `memory_type_bits`.
+    pub fn select_memory_type(&self, usage: MemoryUsage, memory_type_bits: u32) -> u32 {
         // Use optimal and backup because Vulkan spec guarantees that a memory
         // type offering a subset of another memory type's flags must go first.
         let (optimal, backup) = match usage {
@@ -247,24 +391,18 @@ impl Vulkan {
         let memory_type_count = self.physical_device.memory_properties.memory_type_count;
         let selection = (0..memory_type_count as usize)
             .find(|i| {
-                let type_ok = requirements.memory_type_bits & (1 << i) != 0;
+                let type_ok = memory_type_bits & (1 << i) != 0;
                 type_ok & memory_types[*i].property_flags.contains(optimal)
             })
             .or_else(|| {
                 (0..memory_type_count as usize).find(|i| {
-                    let type_ok = requirements.memory_type_bits & (1 << i) != 0;
+                    let type_ok = memory_type_bits & (1 << i) != 0;
                     type_ok & memory_types[*i].property_flags.contains(backup)
                 })
             })
             .unwrap();
 
-        let create_info = vk::MemoryAllocateInfo {
-            allocation_size: requirements.size,
-            memory_type_index: selection as u32,
-            ..Default::default()
-        };
-
-        unsafe { self.device.allocate_memory(&create_info, None) }
+        selection as u32
     }
 
     pub fn create_image_view(
@@ -279,7 +417,15 @@ impl Vulkan {
             format,
             components: vk::ComponentMapping::default(),
             subresource_range: vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::COLOR,
+                aspect_mask: match format {
+                    vk::Format::S8_UINT => vk::ImageAspectFlags::STENCIL,
+                    vk::Format::D16_UNORM_S8_UINT
+                    | vk::Format::D24_UNORM_S8_UINT
+                    | vk::Format::D32_SFLOAT_S8_UINT => {
+                        vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+                    }
+                    _ => vk::ImageAspectFlags::COLOR,
+                },
                 base_mip_level: 0,
                 level_count: 1,
                 base_array_layer: 0,
@@ -422,6 +568,7 @@ fn select_gpu(
                 let properties = unsafe { instance.get_physical_device_properties(gpu) };
                 let memory_properties =
                     unsafe { instance.get_physical_device_memory_properties(gpu) };
+                let features = unsafe { instance.get_physical_device_features(gpu) };
 
                 return Ok((
                     PhysicalDevice {
@@ -431,6 +578,8 @@ fn select_gpu(
                         transfer_queue_family: transfer.unwrap_or(graphics),
                         present_queue_family: present,
                         memory_properties,
+                        texture_compression_bc: features.texture_compression_bc == vk::TRUE,
+                        dual_source_blend: features.dual_src_blend == vk::TRUE,
                     },
                     extensions,
                 ));
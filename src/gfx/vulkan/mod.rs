@@ -4,27 +4,37 @@ mod shaders;
 mod texture;
 mod window;
 
-use std::{cell::RefCell, collections::HashMap, ffi::c_char};
+use std::{
+    collections::HashMap,
+    ffi::{c_char, CString},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex, MutexGuard, PoisonError,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
 
 use arrayvec::ArrayVec;
 use ash::vk;
 use smallvec::SmallVec;
 
-use crate::handle_pool::{Handle, HandlePool};
+use crate::handle_pool::{ConcurrentHandlePool, Handle, HandlePool};
 
 use self::{
-    api::Vulkan,
+    api::{MemoryUsage, VkResult, Vulkan},
     geometry::UiGeometryBuffer,
-    shaders::{DefaultRenderPass, Fill},
-    texture::{Staging, Texture},
+    shaders::{DefaultRenderPass, Effect, Fill, FragmentConstants, VertexConstants},
+    texture::{Staging, Texture, STORAGE_FORMAT},
     window::Window,
 };
 
 use super::{
     color::Color,
-    geometry::{Extent, Rect},
-    pixel_buffer::{PixelBuffer, PixelBufferView},
-    DrawCommandList, Error, GfxDevice, ImageCopy, MAX_IMAGES, MAX_SWAPCHAINS,
+    geometry::{Extent, PhysicalExtent, PhysicalPx, PhysicalRect, Point, Px, Rect, Transform},
+    pixel_buffer::{Layout, PixelBuffer, PixelBufferView},
+    DrawCommandList, Error, ExternalImage, FrameStats, GfxDevice, ImageCopy, MAX_EFFECTS,
+    MAX_IMAGES, MAX_SWAPCHAINS,
 };
 
 const fn as_cchar_slice(slice: &[u8]) -> &[c_char] {
@@ -39,33 +49,100 @@ const REQUIRED_INSTANCE_EXTENSIONS: &[&[c_char]] = &[
     as_cchar_slice(b"VK_KHR_surface\0"),
     #[cfg(target_os = "windows")]
     as_cchar_slice(b"VK_KHR_win32_surface\0"),
+    #[cfg(target_os = "linux")]
+    as_cchar_slice(b"VK_KHR_xlib_surface\0"),
 ];
 
-const OPTIONAL_INSTANCE_EXTENSIONS: &[&[c_char]] =
-    &[as_cchar_slice(b"VK_EXT_swapchjain_colorspace\0")];
+const OPTIONAL_INSTANCE_EXTENSIONS: &[&[c_char]] = &[
+    as_cchar_slice(b"VK_EXT_swapchjain_colorspace\0"),
+    // Used to label frames for graphics debuggers.
+    as_cchar_slice(b"VK_EXT_debug_utils\0"),
+    // Needed by `VK_EXT_full_screen_exclusive`.
+    #[cfg(target_os = "windows")]
+    as_cchar_slice(b"VK_KHR_get_surface_capabilities2\0"),
+];
 
 const REQUIRED_DEVICE_EXTENSIONS: &[&[c_char]] = &[as_cchar_slice(b"VK_KHR_swapchain\0")];
 
-const OPTIONAL_DEVICE_EXTENSIONS: &[&[c_char]] = &[];
+const OPTIONAL_DEVICE_EXTENSIONS: &[&[c_char]] = &[
+    INCREMENTAL_PRESENT_EXTENSION,
+    #[cfg(target_os = "windows")]
+    FULL_SCREEN_EXCLUSIVE_EXTENSION,
+    #[cfg(target_os = "windows")]
+    as_cchar_slice(b"VK_KHR_external_memory_win32\0"),
+    #[cfg(target_os = "linux")]
+    as_cchar_slice(b"VK_KHR_external_memory_fd\0"),
+    #[cfg(target_os = "linux")]
+    as_cchar_slice(b"VK_EXT_external_memory_dma_buf\0"),
+    #[cfg(target_os = "linux")]
+    as_cchar_slice(b"VK_EXT_image_drm_format_modifier\0"),
+];
+
+/// Allows the swapchain to be presented with a list of changed regions.
+const INCREMENTAL_PRESENT_EXTENSION: &[c_char] = as_cchar_slice(b"VK_KHR_incremental_present\0");
+
+/// Controls whether the driver may take exclusive control of the display for a
+/// swapchain whose window covers it.
+const FULL_SCREEN_EXCLUSIVE_EXTENSION: &[c_char] =
+    as_cchar_slice(b"VK_EXT_full_screen_exclusive\0");
+
+/// The device extensions that must all be present for external images to be
+/// imported.
+const EXTERNAL_MEMORY_EXTENSIONS: &[&[c_char]] = &[
+    #[cfg(target_os = "windows")]
+    as_cchar_slice(b"VK_KHR_external_memory_win32\0"),
+    #[cfg(target_os = "linux")]
+    as_cchar_slice(b"VK_KHR_external_memory_fd\0"),
+    #[cfg(target_os = "linux")]
+    as_cchar_slice(b"VK_EXT_external_memory_dma_buf\0"),
+    #[cfg(target_os = "linux")]
+    as_cchar_slice(b"VK_EXT_image_drm_format_modifier\0"),
+];
 
 const FRAMES_IN_FLIGHT: usize = 2;
 const PREFERRED_SWAPCHAIN_LENGTH: u32 = 2;
 
 const MAX_TEXTURE_DESCRIPTORS: u32 = MAX_IMAGES * 8;
 
+/// The Vulkan implementation of [`GfxDevice`].
+///
+/// Each piece of state has its own lock so that the device can be shared
+/// between threads. Drawing holds `effects`, `windows` and `image_frames`, in
+/// that order, for its whole duration, so only one target is drawn at a time.
+/// Every other lock is only held briefly, and never while waiting for one of
+/// those three.
 pub struct VulkanGfxDevice {
     api: Vulkan,
 
     sampler: vk::Sampler,
-    descriptor_sets: RefCell<ArrayVec<vk::DescriptorSet, { MAX_TEXTURE_DESCRIPTORS as usize }>>,
+    descriptor_sets: Mutex<ArrayVec<vk::DescriptorSet, { MAX_TEXTURE_DESCRIPTORS as usize }>>,
     descriptor_pool: vk::DescriptorPool,
     descriptor_layout: vk::DescriptorSetLayout,
 
     render_pass: DefaultRenderPass,
-    shaders: RefCell<HashMap<vk::Format, Fill>>,
-    windows: RefCell<HandlePool<Window, super::Swapchain, MAX_SWAPCHAINS>>,
-    images: RefCell<HandlePool<Texture, super::Image, MAX_IMAGES>>,
-    staging: RefCell<Staging>,
+    /// The render passes used to draw to images, which leave them ready to be
+    /// sampled.
+    image_render_pass: DefaultRenderPass,
+    /// The pipelines for each swapchain format, which may still be compiling.
+    shaders: Mutex<HashMap<vk::Format, PendingFill>>,
+    /// The pipelines for drawing to images. These are kept apart from the
+    /// swapchain pipelines since they are built for `image_render_pass`, and
+    /// so can't stand in for them or be stood in for. Only `None` once the
+    /// device is being dropped.
+    image_shader: Mutex<Option<PendingFill>>,
+    /// The per-frame resources used to draw to images, used in alternating
+    /// order as tracked by `image_frame_id`.
+    image_frames: Mutex<Vec<RenderFrame>>,
+    image_frame_id: AtomicUsize,
+    windows: Mutex<HandlePool<Window, super::Swapchain, MAX_SWAPCHAINS>>,
+    images: ConcurrentHandlePool<Texture, super::Image, MAX_IMAGES>,
+    effects: Mutex<HandlePool<Effect, super::Effect, MAX_EFFECTS>>,
+    staging: Mutex<Staging>,
+    /// The number of times that swapchains have been presented, used to label
+    /// frames for graphics debuggers.
+    frame_count: AtomicU64,
+    /// Updated by every draw, and whenever images are created or destroyed.
+    stats: Mutex<FrameStats>,
 }
 
 impl VulkanGfxDevice {
@@ -166,25 +243,200 @@ impl VulkanGfxDevice {
 
         let staging = Staging::new(&api)?;
 
-        let render_pass = DefaultRenderPass::new(&api, vk::Format::B8G8R8A8_SRGB);
+        let render_pass =
+            DefaultRenderPass::new(&api, vk::Format::B8G8R8A8_SRGB, api.select_stencil_format());
+        let image_render_pass =
+            DefaultRenderPass::for_images(&api, STORAGE_FORMAT, render_pass.stencil_format);
+        let image_frames: Vec<_> = (0..FRAMES_IN_FLIGHT)
+            .map(|_| RenderFrame::new(&api))
+            .collect();
+
+        // Start compiling pipelines for the most common swapchain format right
+        // away so that they are likely to be ready by the time the first
+        // window is drawn.
+        let mut shaders = HashMap::with_capacity(1);
+        shaders.insert(
+            vk::Format::B8G8R8A8_SRGB,
+            PendingFill::spawn(&api, render_pass.handle, descriptor_layout),
+        );
+        let image_shader = PendingFill::spawn(&api, image_render_pass.handle, descriptor_layout);
 
         Ok(Self {
             api,
             sampler,
-            descriptor_sets: RefCell::new(descriptor_sets),
+            descriptor_sets: Mutex::new(descriptor_sets),
             descriptor_pool,
             descriptor_layout,
             render_pass,
-            shaders: RefCell::new(HashMap::with_capacity(1)),
-            windows: RefCell::new(HandlePool::preallocate()),
-            images: RefCell::new(HandlePool::preallocate_n(8)),
-            staging: RefCell::new(staging),
+            image_render_pass,
+            shaders: Mutex::new(shaders),
+            image_shader: Mutex::new(Some(image_shader)),
+            image_frames: Mutex::new(image_frames),
+            image_frame_id: AtomicUsize::new(0),
+            windows: Mutex::new(HandlePool::preallocate()),
+            images: ConcurrentHandlePool::new(),
+            effects: Mutex::new(HandlePool::preallocate()),
+            staging: Mutex::new(staging),
+            frame_count: AtomicU64::new(0),
+            stats: Mutex::default(),
         })
     }
 }
 
+impl VulkanGfxDevice {
+    /// Tracks a window whose swapchain was just created, and starts compiling
+    /// pipelines for its format if it is the first window with it.
+    fn add_window(&self, window: Window) -> Result<Handle<super::Swapchain>, Error> {
+        // Pipelines for new formats are compiled in the background. Until they
+        // are ready, the window is drawn with the pipelines of another format.
+        lock(&self.shaders)
+            .entry(window.format())
+            .or_insert_with(|| {
+                PendingFill::spawn(&self.api, self.render_pass.handle, self.descriptor_layout)
+            });
+
+        Ok(lock(&self.windows).insert(window)?)
+    }
+
+    /// Selects the format whose pipelines should be used to draw to an image
+    /// of `format`. If the pipelines for `format` are still compiling, those
+    /// of any other format are used instead, since every format shares the
+    /// same render pass. Only if no pipelines are ready does this block until
+    /// compilation is complete.
+    fn select_shader(&self, format: vk::Format) -> Result<vk::Format, Error> {
+        let mut shaders = lock(&self.shaders);
+
+        if shaders.get_mut(&format).unwrap().poll(false)?.is_some() {
+            return Ok(format);
+        }
+
+        for (other, shader) in shaders.iter_mut() {
+            if shader.poll(false)?.is_some() {
+                return Ok(*other);
+            }
+        }
+
+        match shaders.get_mut(&format).unwrap().poll(true)? {
+            Some(_) => Ok(format),
+            // Compilation failed on a previous call, and the error was
+            // already reported.
+            None => Err(Error::VulkanInternal {
+                error_code: vk::Result::ERROR_INITIALIZATION_FAILED,
+            }),
+        }
+    }
+
+    fn update_stats(&self, f: impl FnOnce(&mut FrameStats)) {
+        f(&mut lock(&self.stats));
+    }
+
+    /// Returns a descriptor set that binds `image` for sampling. Each image
+    /// only needs one descriptor per frame no matter how many times it is
+    /// drawn, so descriptors are shared through `used_textures`. New
+    /// descriptors are added to `frame_descriptors` so that they can be
+    /// reclaimed once the frame is complete.
+    fn texture_descriptor(
+        &self,
+        image: Handle<super::Image>,
+        used_textures: &mut SmallVec<[(Handle<super::Image>, vk::DescriptorSet); 32]>,
+        frame_descriptors: &mut SmallVec<[vk::DescriptorSet; 2]>,
+    ) -> Result<vk::DescriptorSet, Error> {
+        if let Some((_, descriptor)) = used_textures.iter().find(|(handle, _)| *handle == image) {
+            return Ok(*descriptor);
+        }
+
+        let texture = self.images.get(image)?;
+        debug_assert_eq!(texture.image_layout, vk::ImageLayout::READ_ONLY_OPTIMAL);
+
+        let descriptor = lock(&self.descriptor_sets)
+            .pop()
+            .ok_or(Error::TooManyObjects {
+                limit: MAX_TEXTURE_DESCRIPTORS,
+            })?;
+        frame_descriptors.push(descriptor);
+
+        let image_info = [vk::DescriptorImageInfo {
+            sampler: self.sampler,
+            image_view: texture.image_view,
+            image_layout: vk::ImageLayout::READ_ONLY_OPTIMAL,
+        }];
+
+        let write = vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_info);
+
+        unsafe {
+            self.api
+                .device
+                .update_descriptor_sets(&[write.build()], &[]);
+        }
+
+        used_textures.push((image, descriptor));
+        Ok(descriptor)
+    }
+}
+
 impl Drop for VulkanGfxDevice {
     fn drop(&mut self) {
+        self.api.wait_idle().unwrap();
+
+        // Destroy any windows and images that weren't cleaned up by the user.
+        for window in self
+            .windows
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain()
+        {
+            window.destroy(&self.api);
+        }
+
+        // The descriptors are freed along with their pool below.
+        for mut frame in self
+            .image_frames
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain(..)
+        {
+            frame.descriptors.clear();
+            frame.destroy(&self.api);
+        }
+
+        for texture in self.images.drain() {
+            texture.destroy(&self.api);
+        }
+
+        for effect in self
+            .effects
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain()
+        {
+            effect.destroy(&self.api);
+        }
+
+        // Pipelines that are still compiling use the descriptor layout, so
+        // they must finish before it is destroyed.
+        for (_, shader) in self
+            .shaders
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain()
+        {
+            if let Ok(Some(shader)) = shader.wait() {
+                shader.destroy(&self.api);
+            }
+        }
+
+        let image_shader = self
+            .image_shader
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(Ok(Some(shader))) = image_shader.take().map(PendingFill::wait) {
+            shader.destroy(&self.api);
+        }
+
         unsafe {
             self.api
                 .device
@@ -195,68 +447,163 @@ impl Drop for VulkanGfxDevice {
             self.api.device.destroy_sampler(self.sampler, None);
         }
 
-        for (_, shader) in self.shaders.borrow_mut().drain() {
-            shader.destroy(&self.api);
-        }
-
-        self.staging.borrow_mut().destroy(&self.api);
+        self.staging
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .destroy(&self.api);
     }
 }
 
 impl GfxDevice for VulkanGfxDevice {
+    #[cfg(target_os = "windows")]
     fn create_swapchain(
         &self,
         hwnd: windows::Win32::Foundation::HWND,
+        transparent: bool,
     ) -> Result<Handle<super::Swapchain>, Error> {
-        let window = Window::new(&self.api, hwnd)?;
-
-        let mut shaders = self.shaders.borrow_mut();
-        shaders
-            .entry(window.format())
-            .or_insert_with(|| Fill::new(&self.api, self.render_pass.handle).unwrap());
+        let window = Window::new(&self.api, hwnd, transparent)?;
+        self.add_window(window)
+    }
 
-        Ok(self.windows.borrow_mut().insert(window)?)
+    #[cfg(target_os = "linux")]
+    fn create_swapchain(
+        &self,
+        display: *mut std::ffi::c_void,
+        window: std::os::raw::c_ulong,
+        transparent: bool,
+    ) -> Result<Handle<super::Swapchain>, Error> {
+        let window = Window::new(&self.api, display, window, transparent)?;
+        self.add_window(window)
     }
 
     fn resize_swapchain(
         &self,
         handle: Handle<super::Swapchain>,
-        extent: Extent,
+        extent: PhysicalExtent,
     ) -> Result<(), Error> {
-        let mut windows = self.windows.borrow_mut();
+        let mut windows = lock(&self.windows);
         let window = windows.get_mut(handle)?;
-        unsafe { self.api.device.device_wait_idle() }?;
+        self.api.wait_idle()?;
         window.resize(&self.api, extent.into())?;
         Ok(())
     }
 
+    fn set_swapchain_exclusive(
+        &self,
+        handle: Handle<super::Swapchain>,
+        exclusive: bool,
+    ) -> Result<(), Error> {
+        let mut windows = lock(&self.windows);
+        let window = windows.get_mut(handle)?;
+        window.set_exclusive(&self.api, exclusive)?;
+        Ok(())
+    }
+
+    fn set_swapchain_vsync(
+        &self,
+        handle: Handle<super::Swapchain>,
+        vsync: bool,
+    ) -> Result<(), Error> {
+        let mut windows = lock(&self.windows);
+        let window = windows.get_mut(handle)?;
+        window.set_vsync(&self.api, vsync)?;
+        Ok(())
+    }
+
     fn destroy_swapchain(&self, handle: Handle<super::Swapchain>) -> Result<(), Error> {
-        let mut windows = self.windows.borrow_mut();
+        let mut windows = lock(&self.windows);
         let window = windows.remove(handle)?;
-        unsafe { self.api.device.device_wait_idle() }?;
+        self.api.wait_idle()?;
         window.destroy(&self.api);
         Ok(())
     }
 
-    fn present_swapchains(&self, handles: &[Handle<super::Swapchain>]) -> Result<(), Error> {
-        let mut windows = self.windows.borrow_mut();
+    fn get_next_swapchain_image(
+        &self,
+        handle: Handle<super::Swapchain>,
+        damage: PhysicalRect,
+    ) -> Result<PhysicalRect, Error> {
+        let mut windows = lock(&self.windows);
+        let window = windows.get_mut(handle)?;
+        match window.get_next_image(&self.api, target_rect(damage)) {
+            Ok(region) => Ok(physical_rect(region)),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Err(Error::SwapchainOutOfDate),
+            Err(e) => Err(Error::VulkanInternal { error_code: e }),
+        }
+    }
+
+    fn present_swapchains(
+        &self,
+        swapchains: &[(Handle<super::Swapchain>, &[PhysicalRect])],
+    ) -> Result<(), Error> {
+        let mut windows = lock(&self.windows);
+        let incremental = self
+            .api
+            .is_device_extension_enabled(INCREMENTAL_PRESENT_EXTENSION);
 
-        for handle in handles {
+        let mut regions = SmallVec::<[vk::RectLayerKHR; 8]>::new();
+        for (handle, damage) in swapchains {
             let window = windows.get_mut(*handle)?;
-            match window.present(&self.api) {
+
+            regions.clear();
+            if incremental {
+                regions.extend(damage.iter().map(|rect| vk::RectLayerKHR {
+                    offset: vk::Offset2D {
+                        x: i32::from(rect.left.0),
+                        y: i32::from(rect.top.0),
+                    },
+                    extent: vk::Extent2D {
+                        width: u32::try_from(rect.width().0).unwrap_or(0),
+                        height: u32::try_from(rect.height().0).unwrap_or(0),
+                    },
+                    layer: 0,
+                }));
+            }
+
+            match window.present(&self.api, &regions) {
                 Ok(()) => Ok(()),
                 Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Err(Error::SwapchainOutOfDate),
                 Err(e) => Err(Error::VulkanInternal { error_code: e }),
             }?;
         }
+
+        self.frame_count.fetch_add(1, Ordering::Relaxed);
+        super::debug::end_frame();
+
         Ok(())
     }
 
-    fn create_image(&self, extent: Extent) -> Result<Handle<super::Image>, Error> {
-        Ok(self
-            .images
-            .borrow_mut()
-            .insert(Texture::new(&self.api, extent)?)?)
+    fn create_image(&self, extent: Extent, layout: Layout) -> Result<Handle<super::Image>, Error> {
+        if layout.is_compressed() && !self.api.physical_device.texture_compression_bc {
+            return Err(Error::UnsupportedLayout);
+        }
+
+        let texture = Texture::new(&self.api, extent, layout)?;
+        let memory_size = texture.memory_size;
+        let handle = self.images.insert(texture)?;
+
+        self.update_stats(|stats| stats.texture_memory += memory_size);
+        Ok(handle)
+    }
+
+    fn import_image(&self, image: &ExternalImage) -> Result<Handle<super::Image>, Error> {
+        if !EXTERNAL_MEMORY_EXTENSIONS
+            .iter()
+            .all(|name| self.api.is_device_extension_enabled(name))
+        {
+            return Err(Error::ExternalImagesNotSupported);
+        }
+
+        let mut texture = Texture::import(&self.api, image)?;
+
+        // Take ownership of the image from the external API before use.
+        lock(&self.staging).acquire_external(&self.api, &mut texture)?;
+
+        let memory_size = texture.memory_size;
+        let handle = self.images.insert(texture)?;
+
+        self.update_stats(|stats| stats.texture_memory += memory_size);
+        Ok(handle)
     }
 
     fn copy_pixels(
@@ -265,11 +612,33 @@ impl GfxDevice for VulkanGfxDevice {
         dst: Handle<super::Image>,
         ops: &[ImageCopy],
     ) -> Result<(), Error> {
-        let mut images = self.images.borrow_mut();
-        let image = images.get_mut(dst)?;
-        self.staging
-            .borrow_mut()
-            .copy_pixels(&self.api, src, image, ops)?;
+        let mut image = self.images.get_mut(dst)?;
+
+        if image.is_external {
+            return Err(Error::ReadOnlyImage);
+        }
+
+        if image.layout.is_compressed() || src.layout().is_compressed() {
+            if image.layout != src.layout() {
+                return Err(Error::IncompatibleLayout);
+            }
+
+            let block = src.layout().block_size();
+            let is_aligned = |px: Px| px.0 % block == 0;
+            for op in ops {
+                if !(is_aligned(op.src_rect.left)
+                    && is_aligned(op.src_rect.top)
+                    && is_aligned(op.src_rect.width())
+                    && is_aligned(op.src_rect.height())
+                    && is_aligned(op.dst_location.x)
+                    && is_aligned(op.dst_location.y))
+                {
+                    return Err(Error::UnalignedCopy);
+                }
+            }
+        }
+
+        lock(&self.staging).copy_pixels(&self.api, src, &mut image, ops)?;
         Ok(())
     }
 
@@ -282,11 +651,61 @@ impl GfxDevice for VulkanGfxDevice {
         todo!()
     }
 
+    fn create_effect(&self, fragment_shader: &[u32]) -> Result<Handle<super::Effect>, Error> {
+        const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+        if fragment_shader.first() != Some(&SPIRV_MAGIC) {
+            return Err(Error::InvalidShader);
+        }
+
+        // Checked up front to avoid compiling a pipeline that can't be stored.
+        if lock(&self.effects).count() >= MAX_EFFECTS as usize {
+            return Err(Error::TooManyObjects { limit: MAX_EFFECTS });
+        }
+
+        let effect = match Effect::new(
+            &self.api,
+            self.render_pass.handle,
+            self.descriptor_layout,
+            fragment_shader,
+        ) {
+            Ok(effect) => Ok(effect),
+            Err(vk::Result::ERROR_INVALID_SHADER_NV) => Err(Error::InvalidShader),
+            Err(e) => Err(Error::VulkanInternal { error_code: e }),
+        }?;
+
+        Ok(lock(&self.effects).insert(effect)?)
+    }
+
+    fn destroy_effect(&self, handle: Handle<super::Effect>) -> Result<(), Error> {
+        let effect = lock(&self.effects).remove(handle)?;
+        self.api.wait_idle()?;
+        effect.destroy(&self.api);
+        Ok(())
+    }
+
+    fn supports_subpixel_text(&self, handle: Handle<super::Swapchain>) -> Result<bool, Error> {
+        // Swapchains are always created with an opaque composite alpha, so
+        // the format is the only property of the window that matters.
+        let format = lock(&self.windows).get(handle)?.format();
+
+        Ok(self.api.physical_device.dual_source_blend
+            && matches!(
+                format,
+                vk::Format::B8G8R8A8_SRGB
+                    | vk::Format::B8G8R8A8_UNORM
+                    | vk::Format::R8G8B8A8_SRGB
+                    | vk::Format::R8G8B8A8_UNORM
+            ))
+    }
+
     fn destroy_image(&self, handle: Handle<super::Image>) -> Result<(), Error> {
-        let mut images = self.images.borrow_mut();
         // If is_idle() returns an error, remove the texture anyway.
-        let texture = images.remove_if(handle, |t| t.is_idle(&self.api).unwrap_or(true))?;
+        let texture = self
+            .images
+            .remove_if(handle, |t| t.is_idle(&self.api).unwrap_or(true))?;
         if let Some(texture) = texture {
+            self.update_stats(|stats| stats.texture_memory -= texture.memory_size);
             texture.destroy(&self.api);
             Ok(())
         } else {
@@ -308,40 +727,148 @@ impl GfxDevice for VulkanGfxDevice {
         let mut signal_values = SmallVec::<[_; MAX_IMAGES as usize]>::new();
         let mut signal_semaphores = SmallVec::<[_; MAX_IMAGES as usize]>::new();
 
-        let shaders = self.shaders.borrow();
+        let effects = lock(&self.effects);
 
-        let mut windows = self.windows.borrow_mut();
-        let (target, extent, new_framebuffer, shader) = match render_target {
-            super::RenderTarget::Swapchain(handle) => {
-                let window = windows.get_mut(handle)?;
-                window.get_next_image(&self.api).unwrap();
+        let mut windows = lock(&self.windows);
+        let mut image_frames = lock(&self.image_frames);
+        let passes = match render_target {
+            super::RenderTarget::Swapchain(_) => &self.render_pass,
+            super::RenderTarget::Image(_) => &self.image_render_pass,
+        };
 
-                let shader = shaders.get(&window.format()).unwrap();
-                let (image_view, extent, sync, target) = window.render_state();
-                let new_framebuffer = self
-                    .render_pass
-                    .create_framebuffer(&self.api, extent, image_view);
+        let (target, extent, region, render_pass, image_view, shader_format, clear_color) =
+            match render_target {
+                super::RenderTarget::Swapchain(handle) => {
+                    let window = windows.get_mut(handle)?;
+
+                    // Images that were not acquired by `get_next_swapchain_image`
+                    // are redrawn in full.
+                    match window.get_next_image(&self.api, Rect::new(Point::zero(), Extent::MAX)) {
+                        Ok(_) => Ok(()),
+                        Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Err(Error::SwapchainOutOfDate),
+                        Err(e) => Err(Error::VulkanInternal { error_code: e }),
+                    }?;
+
+                    let shader_format = Some(self.select_shader(window.format())?);
+                    let render_pass = if window.is_full_redraw() {
+                        self.render_pass.handle
+                    } else {
+                        self.render_pass.load_handle
+                    };
+
+                    // Transparent windows start from nothing, so that the
+                    // compositor shows what is behind them.
+                    let clear_color = if window.is_transparent() {
+                        Color::ZERO
+                    } else {
+                        Color::BLACK
+                    };
+
+                    let (image_view, extent, region, sync, target) = window.render_state();
+
+                    wait_values.push(0);
+                    wait_semaphores.push(sync.acquire_semaphore);
+                    signal_values.push(0);
+                    signal_semaphores.push(sync.present_semaphore);
+
+                    (
+                        target,
+                        extent,
+                        region,
+                        render_pass,
+                        image_view,
+                        shader_format,
+                        clear_color,
+                    )
+                }
+                super::RenderTarget::Image(handle) => {
+                    // The image can't be sampled while it is being drawn to.
+                    if commands.reads_image(handle) {
+                        return Err(Error::ResourceInUse);
+                    }
+
+                    let mut texture = self.images.get_mut(handle)?;
+                    if texture.is_external {
+                        return Err(Error::ReadOnlyImage);
+                    }
+                    if texture.layout.is_compressed() {
+                        return Err(Error::UnsupportedLayout);
+                    }
+
+                    // No other pipelines are compatible with the image render
+                    // pass, so this blocks until its own are compiled.
+                    let compiled = lock(&self.image_shader)
+                        .as_mut()
+                        .map_or(Ok(false), |shader| {
+                            shader.poll(true).map(|fill| fill.is_some())
+                        })?;
+                    if !compiled {
+                        return Err(Error::VulkanInternal {
+                            error_code: vk::Result::ERROR_INITIALIZATION_FAILED,
+                        });
+                    }
+
+                    // Draws that sample the image, and any pending copy to
+                    // it, must finish before it is drawn over.
+                    wait_values.push(texture.read_count);
+                    wait_semaphores.push(texture.read_semaphore);
+                    if let Some(write_state) = &texture.write_state {
+                        wait_values.push(write_state.counter);
+                        wait_semaphores.push(write_state.semaphore);
+                    }
+
+                    // Drawing is counted as a read, so that the image isn't
+                    // destroyed or drawn over again until the draw completes.
+                    texture.read_count += 1;
+                    signal_values.push(texture.read_count);
+                    signal_semaphores.push(texture.read_semaphore);
+
+                    // The render pass leaves the image ready to be sampled.
+                    texture.image_layout = vk::ImageLayout::READ_ONLY_OPTIMAL;
+
+                    let frame_id = self.image_frame_id.fetch_add(1, Ordering::Relaxed);
+
+                    (
+                        &mut image_frames[frame_id % FRAMES_IN_FLIGHT],
+                        vk::Extent2D::from(texture.extent),
+                        Rect::new(Point::zero(), texture.extent),
+                        self.image_render_pass.handle,
+                        texture.image_view,
+                        // The image pipelines are used instead of those of a
+                        // swapchain format.
+                        None,
+                        // Images start out transparent, so that whatever is
+                        // left undrawn shows what is behind them.
+                        Color::ZERO,
+                    )
+                }
+            };
 
-                wait_values.push(0);
-                wait_semaphores.push(sync.acquire_semaphore);
-                signal_values.push(0);
-                signal_semaphores.push(sync.present_semaphore);
+        let shaders = lock(&self.shaders);
+        let image_shader = lock(&self.image_shader);
+        let shader = match shader_format {
+            Some(format) => shaders[&format].get(),
+            None => image_shader.as_ref().and_then(PendingFill::get),
+        }
+        .expect("only formats with compiled pipelines are selected");
 
-                (target, extent, new_framebuffer, shader)
-            }
-            super::RenderTarget::Image(_) => todo!(),
-        };
+        if let Some(gpu_time) = target.make_ready(&self.api) {
+            self.update_stats(|stats| stats.gpu_time = Some(gpu_time));
+        }
 
-        target.make_ready(&self.api, new_framebuffer);
+        let stencil_view = target
+            .stencil
+            .view(&self.api, passes.stencil_format, extent)?;
+        target.framebuffer = passes.create_framebuffer(&self.api, extent, image_view, stencil_view);
 
-        self.descriptor_sets
-            .borrow_mut()
-            .extend(target.descriptors.drain(..));
+        lock(&self.descriptor_sets).extend(target.descriptors.drain(..));
 
         target
             .geometry
             .copy(&self.api, &commands.vertices, &commands.indices)?;
 
+        super::debug::begin_frame();
+
         unsafe {
             self.api.device.begin_command_buffer(
                 target.command_buffer,
@@ -350,21 +877,37 @@ impl GfxDevice for VulkanGfxDevice {
             )
         }?;
 
+        if self.api.has_labels() {
+            let label = CString::new(format!(
+                "Frame {}",
+                self.frame_count.load(Ordering::Relaxed)
+            ))
+            .unwrap();
+            self.api.begin_label(target.command_buffer, &label);
+        }
+
+        target.begin_timing(&self.api);
+
         unsafe {
             self.api.device.cmd_begin_render_pass(
                 target.command_buffer,
                 &vk::RenderPassBeginInfo::builder()
-                    .render_pass(self.render_pass.handle)
+                    .render_pass(render_pass)
                     .framebuffer(target.framebuffer)
-                    .render_area(vk::Rect2D {
-                        offset: vk::Offset2D::default(),
-                        extent,
-                    })
-                    .clear_values(&[vk::ClearValue {
-                        color: vk::ClearColorValue {
-                            float32: Color::BLACK.to_array(),
+                    .render_area(region.into())
+                    .clear_values(&[
+                        vk::ClearValue {
+                            color: vk::ClearColorValue {
+                                float32: clear_color.to_array(),
+                            },
                         },
-                    }]),
+                        vk::ClearValue {
+                            depth_stencil: vk::ClearDepthStencilValue {
+                                depth: 0.0,
+                                stencil: 0,
+                            },
+                        },
+                    ]),
                 vk::SubpassContents::INLINE,
             );
 
@@ -381,24 +924,30 @@ impl GfxDevice for VulkanGfxDevice {
                 }],
             );
 
-            self.api.device.cmd_set_scissor(
+            self.api
+                .device
+                .cmd_set_scissor(target.command_buffer, 0, &[region.into()]);
+
+            self.api.device.cmd_set_stencil_reference(
                 target.command_buffer,
+                vk::StencilFaceFlags::FRONT_AND_BACK,
                 0,
-                &[vk::Rect2D {
-                    offset: vk::Offset2D::default(),
-                    extent,
-                }],
             );
         }
 
-        let mut used_textures = SmallVec::<[Handle<super::Image>; 32]>::new();
+        // The number of clips in effect. Geometry is only drawn where the
+        // stencil value is equal to this.
+        let mut clip_depth = 0;
+
+        let mut constants = VertexConstants::new(extent, Transform::IDENTITY);
+        let mut used_textures = SmallVec::<[(Handle<super::Image>, vk::DescriptorSet); 32]>::new();
         for command in commands.commands.iter().chain(commands.current.as_ref()) {
             match command {
                 super::Command::Scissor { rect } => unsafe {
                     self.api.device.cmd_set_scissor(
                         target.command_buffer,
                         0,
-                        &[vk::Rect2D::from(*rect)],
+                        &[vk::Rect2D::from(rect.intersect(&region))],
                     )
                 },
                 super::Command::Polygon {
@@ -408,42 +957,151 @@ impl GfxDevice for VulkanGfxDevice {
                     &self.api,
                     *first_index,
                     *num_indices,
-                    extent,
+                    &constants,
                     &target.geometry,
                     target.command_buffer,
                 ),
+                super::Command::Transform { transform } => {
+                    constants = VertexConstants::new(extent, *transform);
+                }
+                super::Command::PushClip {
+                    first_index,
+                    num_indices,
+                }
+                | super::Command::PopClip {
+                    first_index,
+                    num_indices,
+                } => {
+                    let push = matches!(command, super::Command::PushClip { .. });
+
+                    shader.draw_clip(
+                        &self.api,
+                        *first_index,
+                        *num_indices,
+                        push,
+                        &constants,
+                        &target.geometry,
+                        target.command_buffer,
+                    );
+
+                    if push {
+                        clip_depth += 1;
+                    } else {
+                        clip_depth -= 1;
+                    }
+
+                    unsafe {
+                        self.api.device.cmd_set_stencil_reference(
+                            target.command_buffer,
+                            vk::StencilFaceFlags::FRONT_AND_BACK,
+                            clip_depth,
+                        );
+                    }
+                }
                 super::Command::Image {
                     image,
+                    tiling,
+                    first_index,
+                    num_indices,
+                } => {
+                    let descriptor = self.texture_descriptor(
+                        *image,
+                        &mut used_textures,
+                        &mut target.descriptors,
+                    )?;
+
+                    shader.draw_textured(
+                        &self.api,
+                        *first_index,
+                        *num_indices,
+                        &constants,
+                        &FragmentConstants::new(*tiling),
+                        descriptor,
+                        &target.geometry,
+                        target.command_buffer,
+                    );
+                }
+                super::Command::Glyphs {
+                    atlas,
+                    anti_aliasing,
                     first_index,
                     num_indices,
                 } => {
-                    // let textures = self.images.borrow_mut();
-                    // // todo: cleanup if fails
-                    // let texture = textures.get(*image).unwrap();
-
-                    // debug_assert_eq!(texture.image_layout, vk::ImageLayout::READ_ONLY_OPTIMAL);
-                    // let texture_info = vk::DescriptorImageInfo {
-                    //     sampler: self.sampler,
-                    //     image_view: texture.image_view,
-                    //     image_layout: vk::ImageLayout::READ_ONLY_OPTIMAL,
-                    // };
-
-                    // let descriptor = self.descriptor_sets.borrow_mut().pop().unwrap();
-                    // target.descriptors.push(descriptor);
-
-                    // shader.draw_textured(
-                    //     &self.api,
-                    //     *first_index,
-                    //     *num_indices,
-                    //     extent,
-                    //     &texture_info,
-                    //     descriptor,
-                    //     &target.geometry,
-                    //     target.command_buffer,
-                    // );
-
-                    // used_textures.push(*image);
-                    todo!()
+                    let descriptor = self.texture_descriptor(
+                        *atlas,
+                        &mut used_textures,
+                        &mut target.descriptors,
+                    )?;
+
+                    shader.draw_glyphs(
+                        &self.api,
+                        *first_index,
+                        *num_indices,
+                        *anti_aliasing,
+                        &constants,
+                        descriptor,
+                        &target.geometry,
+                        target.command_buffer,
+                    );
+                }
+                super::Command::ColorGlyphs {
+                    atlas,
+                    first_index,
+                    num_indices,
+                } => {
+                    let descriptor = self.texture_descriptor(
+                        *atlas,
+                        &mut used_textures,
+                        &mut target.descriptors,
+                    )?;
+
+                    shader.draw_color_glyphs(
+                        &self.api,
+                        *first_index,
+                        *num_indices,
+                        &constants,
+                        descriptor,
+                        &target.geometry,
+                        target.command_buffer,
+                    );
+                }
+                super::Command::Effect {
+                    effect,
+                    image,
+                    uniforms,
+                    first_index,
+                    num_indices,
+                } => {
+                    let descriptor = image
+                        .map(|image| {
+                            self.texture_descriptor(
+                                image,
+                                &mut used_textures,
+                                &mut target.descriptors,
+                            )
+                        })
+                        .transpose()?;
+
+                    effects.get(*effect)?.draw(
+                        &self.api,
+                        *first_index,
+                        *num_indices,
+                        &constants,
+                        &commands.uniforms[usize::from(*uniforms)],
+                        descriptor,
+                        &target.geometry,
+                        target.command_buffer,
+                    );
+
+                    // Effects use a different pipeline layout, so the clip
+                    // depth must be set again for following draws.
+                    unsafe {
+                        self.api.device.cmd_set_stencil_reference(
+                            target.command_buffer,
+                            vk::StencilFaceFlags::FRONT_AND_BACK,
+                            clip_depth,
+                        );
+                    }
                 }
             }
         }
@@ -451,17 +1109,16 @@ impl GfxDevice for VulkanGfxDevice {
         // todo cleanup on error
         unsafe {
             self.api.device.cmd_end_render_pass(target.command_buffer);
+            target.end_timing(&self.api);
+            self.api.end_label(target.command_buffer);
             self.api
                 .device
                 .end_command_buffer(target.command_buffer)
                 .unwrap();
         }
 
-        used_textures.sort();
-        used_textures.dedup();
-        for texture in used_textures {
-            let mut images = self.images.borrow_mut();
-            let texture = images.get_mut(texture).unwrap();
+        for (texture, _) in used_textures {
+            let mut texture = self.images.get_mut(texture).unwrap();
             texture.read_count += 1;
 
             if let Some(write_state) = &texture.write_state {
@@ -469,9 +1126,7 @@ impl GfxDevice for VulkanGfxDevice {
                 if write_state.is_complete(&self.api)? {
                     // Return the completed write to the staging
                     // manager for reuse.
-                    self.staging
-                        .borrow_mut()
-                        .finish(texture.write_state.take().unwrap());
+                    lock(&self.staging).finish(texture.write_state.take().unwrap());
                 } else {
                     // Make sure that the texture is not being written
                     // to when we start using it (semaphore == count).
@@ -495,6 +1150,15 @@ impl GfxDevice for VulkanGfxDevice {
             ..Default::default()
         };
 
+        // Each wait needs its own stage. Textures are read by fragment shaders,
+        // and images are written as color attachments.
+        let wait_stages = SmallVec::<[_; MAX_IMAGES as usize]>::from_elem(
+            vk::PipelineStageFlags::FRAGMENT_SHADER
+                | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            wait_semaphores.len(),
+        );
+
+        let queues = self.api.lock_queues();
         unsafe {
             self.api.device.queue_submit(
                 self.api.graphics_queue,
@@ -503,27 +1167,117 @@ impl GfxDevice for VulkanGfxDevice {
                     .command_buffers(&[target.command_buffer])
                     .wait_semaphores(&wait_semaphores)
                     .signal_semaphores(&signal_semaphores)
-                    .wait_dst_stage_mask(&[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT])
+                    .wait_dst_stage_mask(&wait_stages)
                     .build()],
                 target.fence,
             )
         }?;
+        drop(queues);
+
+        let draw_calls = commands
+            .commands
+            .iter()
+            .chain(commands.current.as_ref())
+            .filter(|command| {
+                !matches!(
+                    command,
+                    super::Command::Scissor { .. } | super::Command::Transform { .. }
+                )
+            })
+            .count();
+        self.update_stats(|stats| {
+            stats.draw_calls = draw_calls as u32;
+            stats.vertices = commands.vertices.len() as u32;
+        });
 
         Ok(())
     }
 
     fn flush(&self) {
-        unsafe { self.api.device.device_wait_idle() }.unwrap();
+        self.api.wait_idle().unwrap();
+    }
+
+    fn frame_stats(&self) -> FrameStats {
+        *lock(&self.stats)
+    }
+}
+
+/// Locks `mutex`, even if a thread panicked while holding it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The pipelines for a swapchain format, which are compiled on a worker thread
+/// so that creating a window with a new format does not stall rendering.
+struct PendingFill {
+    fill: Option<Fill>,
+    task: Option<JoinHandle<VkResult<Fill>>>,
+}
+
+impl PendingFill {
+    fn spawn(
+        api: &Vulkan,
+        render_pass: vk::RenderPass,
+        descriptor_layout: vk::DescriptorSetLayout,
+    ) -> Self {
+        let device = api.device.clone();
+        let pipeline_cache = api.pipeline_cache;
+        let dual_source_blend = api.physical_device.dual_source_blend;
+        let task = std::thread::spawn(move || {
+            Fill::new(
+                &device,
+                pipeline_cache,
+                render_pass,
+                descriptor_layout,
+                dual_source_blend,
+            )
+        });
+
+        Self {
+            fill: None,
+            task: Some(task),
+        }
+    }
+
+    /// Returns the pipelines if they have been compiled.
+    fn get(&self) -> Option<&Fill> {
+        self.fill.as_ref()
+    }
+
+    /// Checks if compilation has finished, blocking until it does if `wait`
+    /// is true. Errors are only returned once.
+    fn poll(&mut self, wait: bool) -> VkResult<Option<&Fill>> {
+        if let Some(task) = self.task.take() {
+            if wait || task.is_finished() {
+                self.fill = Some(task.join().expect("pipeline compilation panicked")?);
+            } else {
+                self.task = Some(task);
+            }
+        }
+
+        Ok(self.fill.as_ref())
+    }
+
+    /// Blocks until compilation is complete, returning the pipelines.
+    fn wait(mut self) -> VkResult<Option<Fill>> {
+        self.poll(true)?;
+        Ok(self.fill)
     }
 }
 
 pub(self) struct RenderFrame {
     framebuffer: vk::Framebuffer,
+    stencil: StencilBuffer,
     command_pool: vk::CommandPool,
     command_buffer: vk::CommandBuffer,
     geometry: UiGeometryBuffer,
     descriptors: SmallVec<[vk::DescriptorSet; 2]>,
     fence: vk::Fence,
+    /// Timestamps written at the start and end of the frame's commands. Null
+    /// if the device doesn't support timestamps.
+    timestamps: vk::QueryPool,
+    /// Whether the timestamps were written by the last submission.
+    is_timing: bool,
 }
 
 impl RenderFrame {
@@ -541,17 +1295,35 @@ impl RenderFrame {
             unsafe { api.device.create_fence(&create_info, None) }.unwrap()
         };
 
+        let timestamps = if api
+            .physical_device
+            .properties
+            .limits
+            .timestamp_compute_and_graphics
+            == vk::TRUE
+        {
+            let create_info = vk::QueryPoolCreateInfo::builder()
+                .query_type(vk::QueryType::TIMESTAMP)
+                .query_count(2);
+            unsafe { api.device.create_query_pool(&create_info, None) }.unwrap()
+        } else {
+            vk::QueryPool::null()
+        };
+
         Self {
             framebuffer: vk::Framebuffer::null(),
+            stencil: StencilBuffer::default(),
             command_pool,
             command_buffer,
             geometry: UiGeometryBuffer::new(api).unwrap(),
             descriptors: SmallVec::new(),
             fence,
+            timestamps,
+            is_timing: false,
         }
     }
 
-    fn destroy(self, api: &Vulkan) {
+    fn destroy(mut self, api: &Vulkan) {
         unsafe {
             api.device
                 .wait_for_fences(&[self.fence], true, u64::MAX)
@@ -562,13 +1334,21 @@ impl RenderFrame {
             );
 
             api.device.destroy_fence(self.fence, None);
+            api.device.destroy_query_pool(self.timestamps, None);
             api.device.destroy_command_pool(self.command_pool, None);
             api.device.destroy_framebuffer(self.framebuffer, None);
+            self.stencil.destroy(api);
             self.geometry.destroy(api);
         }
     }
 
-    fn make_ready(&mut self, api: &Vulkan, framebuffer: vk::Framebuffer) {
+    /// Waits for the frame's previous submission to complete so that its
+    /// resources can be reused. The framebuffer is destroyed and must be
+    /// replaced before drawing.
+    ///
+    /// Returns how long the graphics processor took to execute the previous
+    /// submission, if it was timed.
+    fn make_ready(&mut self, api: &Vulkan) -> Option<Duration> {
         unsafe {
             api.device
                 .wait_for_fences(&[self.fence], true, u64::MAX)
@@ -579,7 +1359,145 @@ impl RenderFrame {
                 .unwrap();
             api.device.destroy_framebuffer(self.framebuffer, None);
         }
-        self.framebuffer = framebuffer;
+        self.framebuffer = vk::Framebuffer::null();
+
+        if !std::mem::take(&mut self.is_timing) {
+            return None;
+        }
+
+        let mut ticks = [0_u64; 2];
+        unsafe {
+            api.device.get_query_pool_results(
+                self.timestamps,
+                0,
+                2,
+                &mut ticks,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        }
+        .ok()?;
+
+        // The timestamp period is the number of nanoseconds per tick.
+        let period = f64::from(api.physical_device.properties.limits.timestamp_period);
+        let nanos = ticks[1].saturating_sub(ticks[0]) as f64 * period;
+        Some(Duration::from_nanos(nanos as u64))
+    }
+
+    /// Records a timestamp at the start of the frame's commands, if the device
+    /// supports timestamps.
+    fn begin_timing(&mut self, api: &Vulkan) {
+        if self.timestamps == vk::QueryPool::null() {
+            return;
+        }
+
+        unsafe {
+            api.device
+                .cmd_reset_query_pool(self.command_buffer, self.timestamps, 0, 2);
+            api.device.cmd_write_timestamp(
+                self.command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                self.timestamps,
+                0,
+            );
+        }
+        self.is_timing = true;
+    }
+
+    /// Records a timestamp at the end of the frame's commands. Must be paired
+    /// with [`Self::begin_timing`].
+    fn end_timing(&mut self, api: &Vulkan) {
+        if self.is_timing {
+            unsafe {
+                api.device.cmd_write_timestamp(
+                    self.command_buffer,
+                    vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                    self.timestamps,
+                    1,
+                );
+            }
+        }
+    }
+}
+
+/// The stencil attachment used for clipping. It is recreated whenever the
+/// extent of the render target changes.
+#[derive(Default)]
+struct StencilBuffer {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    extent: vk::Extent2D,
+}
+
+impl StencilBuffer {
+    /// Returns a view of the stencil buffer with the given extent, recreating
+    /// it if necessary. The buffer must not be in use by the GPU.
+    fn view(
+        &mut self,
+        api: &Vulkan,
+        format: vk::Format,
+        extent: vk::Extent2D,
+    ) -> VkResult<vk::ImageView> {
+        if self.view != vk::ImageView::null() && self.extent == extent {
+            return Ok(self.view);
+        }
+
+        self.destroy(api);
+
+        let image = {
+            let create_info = vk::ImageCreateInfo {
+                image_type: vk::ImageType::TYPE_2D,
+                format,
+                extent: vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                },
+                mip_levels: 1,
+                array_layers: 1,
+                samples: vk::SampleCountFlags::TYPE_1,
+                tiling: vk::ImageTiling::OPTIMAL,
+                usage: vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                ..Default::default()
+            };
+
+            unsafe { api.device.create_image(&create_info, None) }?
+        };
+
+        let memory = {
+            let requirements = unsafe { api.device.get_image_memory_requirements(image) };
+            api.allocate_memory(MemoryUsage::Static, requirements)
+        };
+
+        let memory = match memory {
+            Ok(memory) => memory,
+            Err(e) => {
+                unsafe { api.device.destroy_image(image, None) };
+                return Err(e);
+            }
+        };
+
+        *self = Self {
+            image,
+            memory,
+            view: vk::ImageView::null(),
+            extent,
+        };
+
+        unsafe { api.device.bind_image_memory(image, memory, 0) }?;
+        self.view = api.create_image_view(image, format)?;
+
+        Ok(self.view)
+    }
+
+    fn destroy(&mut self, api: &Vulkan) {
+        unsafe {
+            api.device.destroy_image_view(self.view, None);
+            api.device.destroy_image(self.image, None);
+            api.device.free_memory(self.memory, None);
+        }
+        *self = Self::default();
     }
 }
 
@@ -608,6 +1526,37 @@ impl From<Extent> for vk::Extent2D {
     }
 }
 
+impl From<PhysicalExtent> for vk::Extent2D {
+    fn from(e: PhysicalExtent) -> Self {
+        Self {
+            width: e.width.0.try_into().unwrap(),
+            height: e.height.0.try_into().unwrap(),
+        }
+    }
+}
+
+/// Converts a region of a swapchain image to the pixels of the render target
+/// that it is drawn as. Render targets are measured in device pixels, so the
+/// coordinates stay the same.
+fn target_rect(rect: PhysicalRect) -> Rect {
+    Rect {
+        top: Px(rect.top.0),
+        left: Px(rect.left.0),
+        bottom: Px(rect.bottom.0),
+        right: Px(rect.right.0),
+    }
+}
+
+/// The reverse of [`target_rect`].
+fn physical_rect(rect: Rect) -> PhysicalRect {
+    PhysicalRect {
+        top: PhysicalPx(rect.top.0),
+        left: PhysicalPx(rect.left.0),
+        bottom: PhysicalPx(rect.bottom.0),
+        right: PhysicalPx(rect.right.0),
+    }
+}
+
 impl From<Rect> for vk::Rect2D {
     fn from(r: Rect) -> Self {
         Self {
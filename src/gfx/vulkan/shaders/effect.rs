@@ -0,0 +1,143 @@
+use ash::vk;
+
+use crate::gfx::{
+    vulkan::{
+        api::{VkResult, Vulkan},
+        geometry::UiGeometryBuffer,
+    },
+    EffectUniforms,
+};
+
+use super::{
+    fill::{Blend, StencilMode},
+    Fill, VertexConstants,
+};
+
+/// A pipeline for a user-provided fragment shader. It uses the same vertex
+/// shader and render pass as [`Fill`], but has its own layout so that the
+/// effect's uniforms can be pushed to the fragment stage.
+pub struct Effect {
+    pub pipeline: vk::Pipeline,
+    pub layout: vk::PipelineLayout,
+}
+
+impl Effect {
+    /// The offset of the effect's uniforms in the push constant block, placed
+    /// immediately after [`VertexConstants`].
+    pub const UNIFORMS_OFFSET: u32 = std::mem::size_of::<VertexConstants>() as u32;
+
+    pub fn new(
+        api: &Vulkan,
+        render_pass: vk::RenderPass,
+        descriptor_layout: vk::DescriptorSetLayout,
+        fragment_shader: &[u32],
+    ) -> VkResult<Self> {
+        let layout = {
+            let ranges = [
+                vk::PushConstantRange::builder()
+                    .offset(0)
+                    .size(std::mem::size_of::<VertexConstants>() as u32)
+                    .stage_flags(vk::ShaderStageFlags::VERTEX)
+                    .build(),
+                vk::PushConstantRange::builder()
+                    .offset(Self::UNIFORMS_OFFSET)
+                    .size(std::mem::size_of::<EffectUniforms>() as u32)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+                    .build(),
+            ];
+
+            let set_layouts = [descriptor_layout];
+
+            let ci = vk::PipelineLayoutCreateInfo::builder()
+                .set_layouts(&set_layouts)
+                .push_constant_ranges(&ranges);
+
+            unsafe { api.device.create_pipeline_layout(&ci, None) }?
+        };
+
+        // SAFETY: Any u32 slice can be viewed as bytes.
+        let code = unsafe {
+            std::slice::from_raw_parts(
+                fragment_shader.as_ptr().cast::<u8>(),
+                std::mem::size_of_val(fragment_shader),
+            )
+        };
+
+        match Fill::create_pipeline(
+            &api.device,
+            api.pipeline_cache,
+            layout,
+            render_pass,
+            code,
+            Blend::None,
+            StencilMode::Test,
+        ) {
+            Ok(pipeline) => Ok(Self { pipeline, layout }),
+            Err(e) => {
+                unsafe { api.device.destroy_pipeline_layout(layout, None) };
+                Err(e)
+            }
+        }
+    }
+
+    pub fn destroy(self, api: &Vulkan) {
+        unsafe {
+            api.device.destroy_pipeline(self.pipeline, None);
+            api.device.destroy_pipeline_layout(self.layout, None);
+        }
+    }
+
+    /// Draws geometry with the effect. If `descriptor` is provided, its image
+    /// is bound for the effect to sample.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        api: &Vulkan,
+        first_index: u16,
+        num_indices: u16,
+        constants: &VertexConstants,
+        uniforms: &EffectUniforms,
+        descriptor: Option<vk::DescriptorSet>,
+        geometry: &UiGeometryBuffer,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        unsafe {
+            api.device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline,
+            );
+
+            if let Some(descriptor) = descriptor {
+                api.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.layout,
+                    0,
+                    &[descriptor],
+                    &[],
+                );
+            }
+
+            api.device.cmd_push_constants(
+                command_buffer,
+                self.layout,
+                vk::ShaderStageFlags::FRAGMENT,
+                Self::UNIFORMS_OFFSET,
+                &std::mem::transmute::<EffectUniforms, [u8; std::mem::size_of::<EffectUniforms>()]>(
+                    *uniforms,
+                ),
+            );
+        }
+
+        Fill::record_draw(
+            api,
+            self.layout,
+            first_index,
+            num_indices,
+            constants,
+            geometry,
+            command_buffer,
+        );
+    }
+}
@@ -1,39 +1,172 @@
 use ash::vk;
 
-use crate::gfx::vulkan::{
-    api::{VkResult, Vulkan},
-    as_cchar_slice,
-    geometry::UiGeometryBuffer,
+use crate::gfx::{
+    vulkan::{
+        api::{VkResult, Vulkan},
+        as_cchar_slice,
+        geometry::UiGeometryBuffer,
+    },
+    GlyphAntiAliasing,
 };
 
-use super::{ScaleTranslate, VERTEX_ATTRIBUTE_DESCRIPTIONS, VERTEX_BINDING_DESCRIPTION};
+use super::{
+    FragmentConstants, VertexConstants, VERTEX_ATTRIBUTE_DESCRIPTIONS, VERTEX_BINDING_DESCRIPTION,
+};
 
 pub struct Fill {
     pub pipeline: vk::Pipeline,
+    /// Multiplies the vertex color by a texel from the image bound to
+    /// descriptor set 0. It shares its layout with `pipeline`.
+    pub textured_pipeline: vk::Pipeline,
+    /// Increments the stencil value under the geometry without drawing to the
+    /// color attachment, adding a level of clipping.
+    pub push_clip_pipeline: vk::Pipeline,
+    /// Decrements the stencil value under the geometry, undoing
+    /// `push_clip_pipeline`.
+    pub pop_clip_pipeline: vk::Pipeline,
+    /// Blends the vertex color over the framebuffer using the coverage stored
+    /// in the alpha channel of the glyph atlas.
+    pub glyph_pipeline: vk::Pipeline,
+    /// Blends each color channel separately using the coverage stored in the
+    /// matching channel of the glyph atlas. Only present if the device
+    /// supports dual-source blending.
+    pub subpixel_glyph_pipeline: Option<vk::Pipeline>,
+    /// Blends color glyphs over the framebuffer, scaling their alpha by the
+    /// vertex color's.
+    pub color_glyph_pipeline: vk::Pipeline,
     pub layout: vk::PipelineLayout,
 }
 
+/// How a pipeline combines its output with the contents of the framebuffer.
+#[derive(Clone, Copy)]
+pub(super) enum Blend {
+    /// Overwrites the framebuffer.
+    None,
+    /// Blends with the framebuffer using the output's alpha.
+    Alpha,
+    /// Blends each channel with the framebuffer using the matching channel of
+    /// the shader's second output. Requires dual-source blending.
+    DualSource,
+}
+
+/// How a pipeline interacts with the stencil attachment. Every pipeline only
+/// touches fragments where the stencil value equals the dynamic stencil
+/// reference, which the renderer sets to the current clip depth.
+#[derive(Clone, Copy)]
+pub(super) enum StencilMode {
+    /// Draws color without modifying the stencil attachment.
+    Test,
+    /// Increments the stencil value without drawing color.
+    Increment,
+    /// Decrements the stencil value without drawing color.
+    Decrement,
+}
+
 impl Fill {
     const SHADER_MAIN: *const i8 = as_cchar_slice(b"main\0").as_ptr();
     const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/fill.vert.spv"));
     const FRAGMENT_SHADER: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/fill.frag.spv"));
+    const TEXTURED_FRAGMENT_SHADER: &[u8] =
+        include_bytes!(concat!(env!("OUT_DIR"), "/image.frag.spv"));
+    const GLYPH_FRAGMENT_SHADER: &[u8] =
+        include_bytes!(concat!(env!("OUT_DIR"), "/glyph.frag.spv"));
+    const SUBPIXEL_GLYPH_FRAGMENT_SHADER: &[u8] =
+        include_bytes!(concat!(env!("OUT_DIR"), "/glyph_subpixel.frag.spv"));
+    const COLOR_GLYPH_FRAGMENT_SHADER: &[u8] =
+        include_bytes!(concat!(env!("OUT_DIR"), "/color_glyph.frag.spv"));
 
-    pub fn new(api: &Vulkan, render_pass: vk::RenderPass) -> VkResult<Self> {
+    /// Creates the pipelines for drawing into `render_pass`. Textured geometry
+    /// reads its image from a descriptor set with `descriptor_layout`. The
+    /// subpixel glyph pipeline is only created if `dual_source_blend` is set.
+    ///
+    /// This only needs the device and pipeline cache so that it can be run on
+    /// a worker thread, since compiling pipelines can take a long time.
+    pub fn new(
+        device: &ash::Device,
+        pipeline_cache: vk::PipelineCache,
+        render_pass: vk::RenderPass,
+        descriptor_layout: vk::DescriptorSetLayout,
+        dual_source_blend: bool,
+    ) -> VkResult<Self> {
         let layout = {
-            let ranges = [vk::PushConstantRange::builder()
-                .offset(0)
-                .size(std::mem::size_of::<ScaleTranslate>() as u32)
-                .stage_flags(vk::ShaderStageFlags::VERTEX)
-                .build()];
+            let ranges = [
+                vk::PushConstantRange::builder()
+                    .offset(0)
+                    .size(std::mem::size_of::<VertexConstants>() as u32)
+                    .stage_flags(vk::ShaderStageFlags::VERTEX)
+                    .build(),
+                vk::PushConstantRange::builder()
+                    .offset(FragmentConstants::OFFSET)
+                    .size(std::mem::size_of::<FragmentConstants>() as u32)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+                    .build(),
+            ];
 
-            let ci = vk::PipelineLayoutCreateInfo::builder().push_constant_ranges(&ranges);
+            let set_layouts = [descriptor_layout];
 
-            unsafe { api.device.create_pipeline_layout(&ci, None) }?
+            let ci = vk::PipelineLayoutCreateInfo::builder()
+                .set_layouts(&set_layouts)
+                .push_constant_ranges(&ranges);
+
+            unsafe { device.create_pipeline_layout(&ci, None) }?
+        };
+
+        let create = |fragment_shader_code, blend, stencil_mode| {
+            Self::create_pipeline(
+                device,
+                pipeline_cache,
+                layout,
+                render_pass,
+                fragment_shader_code,
+                blend,
+                stencil_mode,
+            )
+        };
+
+        let subpixel_glyph_pipeline = if dual_source_blend {
+            Some(create(
+                Self::SUBPIXEL_GLYPH_FRAGMENT_SHADER,
+                Blend::DualSource,
+                StencilMode::Test,
+            )?)
+        } else {
+            None
         };
 
+        Ok(Self {
+            pipeline: create(Self::FRAGMENT_SHADER, Blend::None, StencilMode::Test)?,
+            textured_pipeline: create(
+                Self::TEXTURED_FRAGMENT_SHADER,
+                Blend::None,
+                StencilMode::Test,
+            )?,
+            push_clip_pipeline: create(Self::FRAGMENT_SHADER, Blend::None, StencilMode::Increment)?,
+            pop_clip_pipeline: create(Self::FRAGMENT_SHADER, Blend::None, StencilMode::Decrement)?,
+            glyph_pipeline: create(Self::GLYPH_FRAGMENT_SHADER, Blend::Alpha, StencilMode::Test)?,
+            subpixel_glyph_pipeline,
+            color_glyph_pipeline: create(
+                Self::COLOR_GLYPH_FRAGMENT_SHADER,
+                Blend::Alpha,
+                StencilMode::Test,
+            )?,
+            layout,
+        })
+    }
+
+    /// Creates a pipeline that draws UI geometry with the given fragment shader.
+    #[allow(clippy::too_many_lines)]
+    pub(super) fn create_pipeline(
+        device: &ash::Device,
+        pipeline_cache: vk::PipelineCache,
+        layout: vk::PipelineLayout,
+        render_pass: vk::RenderPass,
+        fragment_shader_code: &[u8],
+        blend: Blend,
+        stencil_mode: StencilMode,
+    ) -> VkResult<vk::Pipeline> {
         let pipeline = {
             let vertex_shader = unsafe {
-                api.device.create_shader_module(
+                device.create_shader_module(
                     &vk::ShaderModuleCreateInfo::builder().code(std::slice::from_raw_parts(
                         Self::VERTEX_SHADER.as_ptr().cast(),
                         Self::VERTEX_SHADER.len() / 4,
@@ -43,10 +176,10 @@ impl Fill {
             };
 
             let fragment_shader = unsafe {
-                api.device.create_shader_module(
+                device.create_shader_module(
                     &vk::ShaderModuleCreateInfo::builder().code(std::slice::from_raw_parts(
-                        Self::FRAGMENT_SHADER.as_ptr().cast(),
-                        Self::FRAGMENT_SHADER.len() / 4,
+                        fragment_shader_code.as_ptr().cast(),
+                        fragment_shader_code.len() / 4,
                     )),
                     None,
                 )?
@@ -66,7 +199,11 @@ impl Fill {
                     .build(),
             ];
 
-            let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+            let dynamic_states = [
+                vk::DynamicState::VIEWPORT,
+                vk::DynamicState::SCISSOR,
+                vk::DynamicState::STENCIL_REFERENCE,
+            ];
 
             let dynamic_state_ci =
                 vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
@@ -97,10 +234,63 @@ impl Fill {
                 .sample_shading_enable(false)
                 .rasterization_samples(vk::SampleCountFlags::TYPE_1);
 
-            let framebuffer_blend_ci = vk::PipelineColorBlendAttachmentState::builder()
-                .color_write_mask(vk::ColorComponentFlags::RGBA)
-                .blend_enable(false)
-                .build();
+            let (stencil_op, color_write_mask) = match stencil_mode {
+                StencilMode::Test => (vk::StencilOp::KEEP, vk::ColorComponentFlags::RGBA),
+                StencilMode::Increment => (
+                    vk::StencilOp::INCREMENT_AND_CLAMP,
+                    vk::ColorComponentFlags::empty(),
+                ),
+                StencilMode::Decrement => (
+                    vk::StencilOp::DECREMENT_AND_CLAMP,
+                    vk::ColorComponentFlags::empty(),
+                ),
+            };
+
+            // Testing for equality means that overlapping triangles in a clip
+            // only modify each fragment once.
+            let stencil_op_state = vk::StencilOpState {
+                fail_op: vk::StencilOp::KEEP,
+                pass_op: stencil_op,
+                depth_fail_op: vk::StencilOp::KEEP,
+                compare_op: vk::CompareOp::EQUAL,
+                compare_mask: 0xFF,
+                write_mask: 0xFF,
+                reference: 0,
+            };
+
+            let depth_stencil_ci = vk::PipelineDepthStencilStateCreateInfo::builder()
+                .depth_test_enable(false)
+                .depth_write_enable(false)
+                .stencil_test_enable(true)
+                .front(stencil_op_state)
+                .back(stencil_op_state);
+
+            let framebuffer_blend_ci = match blend {
+                Blend::None => vk::PipelineColorBlendAttachmentState::builder()
+                    .color_write_mask(color_write_mask)
+                    .blend_enable(false)
+                    .build(),
+                Blend::Alpha => vk::PipelineColorBlendAttachmentState::builder()
+                    .color_write_mask(color_write_mask)
+                    .blend_enable(true)
+                    .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                    .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                    .color_blend_op(vk::BlendOp::ADD)
+                    .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                    .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                    .alpha_blend_op(vk::BlendOp::ADD)
+                    .build(),
+                Blend::DualSource => vk::PipelineColorBlendAttachmentState::builder()
+                    .color_write_mask(color_write_mask)
+                    .blend_enable(true)
+                    .src_color_blend_factor(vk::BlendFactor::SRC1_COLOR)
+                    .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC1_COLOR)
+                    .color_blend_op(vk::BlendOp::ADD)
+                    .src_alpha_blend_factor(vk::BlendFactor::SRC1_ALPHA)
+                    .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC1_ALPHA)
+                    .alpha_blend_op(vk::BlendOp::ADD)
+                    .build(),
+            };
 
             let global_blend_ci = vk::PipelineColorBlendStateCreateInfo::builder()
                 .logic_op_enable(false)
@@ -113,6 +303,7 @@ impl Fill {
                 .viewport_state(&viewport_state_ci)
                 .rasterization_state(&rasterization_ci)
                 .multisample_state(&multisample_ci)
+                .depth_stencil_state(&depth_stencil_ci)
                 .color_blend_state(&global_blend_ci)
                 .dynamic_state(&dynamic_state_ci)
                 .layout(layout)
@@ -125,9 +316,9 @@ impl Fill {
                 unsafe {
                     // Call the function pointer directly to avoid allocating a
                     // 1-element Vec
-                    (api.device.fp_v1_0().create_graphics_pipelines)(
-                        api.device.handle(),
-                        api.pipeline_cache,
+                    (device.fp_v1_0().create_graphics_pipelines)(
+                        device.handle(),
+                        pipeline_cache,
                         1,
                         &pipeline_ci,
                         std::ptr::null(),
@@ -139,19 +330,27 @@ impl Fill {
             };
 
             unsafe {
-                api.device.destroy_shader_module(vertex_shader, None);
-                api.device.destroy_shader_module(fragment_shader, None);
+                device.destroy_shader_module(vertex_shader, None);
+                device.destroy_shader_module(fragment_shader, None);
             }
 
             pipeline
         };
 
-        Ok(Self { pipeline, layout })
+        Ok(pipeline)
     }
 
     pub fn destroy(self, api: &Vulkan) {
         unsafe {
             api.device.destroy_pipeline(self.pipeline, None);
+            api.device.destroy_pipeline(self.textured_pipeline, None);
+            api.device.destroy_pipeline(self.push_clip_pipeline, None);
+            api.device.destroy_pipeline(self.pop_clip_pipeline, None);
+            api.device.destroy_pipeline(self.glyph_pipeline, None);
+            if let Some(pipeline) = self.subpixel_glyph_pipeline {
+                api.device.destroy_pipeline(pipeline, None);
+            }
+            api.device.destroy_pipeline(self.color_glyph_pipeline, None);
             api.device.destroy_pipeline_layout(self.layout, None);
         }
     }
@@ -161,7 +360,7 @@ impl Fill {
         api: &Vulkan,
         first_index: u16,
         num_indices: u16,
-        viewport: vk::Extent2D,
+        constants: &VertexConstants,
         geometry: &UiGeometryBuffer,
         command_buffer: vk::CommandBuffer,
     ) {
@@ -171,7 +370,215 @@ impl Fill {
                 vk::PipelineBindPoint::GRAPHICS,
                 self.pipeline,
             );
+        }
+
+        Self::record_draw(
+            api,
+            self.layout,
+            first_index,
+            num_indices,
+            constants,
+            geometry,
+            command_buffer,
+        );
+    }
+
+    /// Pushes (or pops) a level of clipping covering the geometry. The caller
+    /// is responsible for updating the stencil reference afterwards.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_clip(
+        &self,
+        api: &Vulkan,
+        first_index: u16,
+        num_indices: u16,
+        push: bool,
+        constants: &VertexConstants,
+        geometry: &UiGeometryBuffer,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        unsafe {
+            api.device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                if push {
+                    self.push_clip_pipeline
+                } else {
+                    self.pop_clip_pipeline
+                },
+            );
+        }
+
+        Self::record_draw(
+            api,
+            self.layout,
+            first_index,
+            num_indices,
+            constants,
+            geometry,
+            command_buffer,
+        );
+    }
+
+    /// Draws geometry textured with the image bound to `descriptor`, tiled
+    /// according to `fragment_constants`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_textured(
+        &self,
+        api: &Vulkan,
+        first_index: u16,
+        num_indices: u16,
+        constants: &VertexConstants,
+        fragment_constants: &FragmentConstants,
+        descriptor: vk::DescriptorSet,
+        geometry: &UiGeometryBuffer,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        unsafe {
+            api.device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.textured_pipeline,
+            );
+
+            api.device.cmd_push_constants(
+                command_buffer,
+                self.layout,
+                vk::ShaderStageFlags::FRAGMENT,
+                FragmentConstants::OFFSET,
+                &std::mem::transmute::<
+                    FragmentConstants,
+                    [u8; std::mem::size_of::<FragmentConstants>()],
+                >(*fragment_constants),
+            );
+
+            api.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.layout,
+                0,
+                &[descriptor],
+                &[],
+            );
+        }
+
+        Self::record_draw(
+            api,
+            self.layout,
+            first_index,
+            num_indices,
+            constants,
+            geometry,
+            command_buffer,
+        );
+    }
+
+    /// Draws glyphs from the atlas bound to `descriptor`. Subpixel glyphs are
+    /// drawn in grayscale if the device does not support dual-source blending,
+    /// using the greatest coverage of each texel's color channels.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_glyphs(
+        &self,
+        api: &Vulkan,
+        first_index: u16,
+        num_indices: u16,
+        anti_aliasing: GlyphAntiAliasing,
+        constants: &VertexConstants,
+        descriptor: vk::DescriptorSet,
+        geometry: &UiGeometryBuffer,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        let pipeline = match anti_aliasing {
+            GlyphAntiAliasing::Grayscale => self.glyph_pipeline,
+            GlyphAntiAliasing::Subpixel => {
+                self.subpixel_glyph_pipeline.unwrap_or(self.glyph_pipeline)
+            }
+        };
+
+        self.draw_from_atlas(
+            api,
+            pipeline,
+            first_index,
+            num_indices,
+            constants,
+            descriptor,
+            geometry,
+            command_buffer,
+        );
+    }
+
+    /// Draws color glyphs from the atlas bound to `descriptor`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_color_glyphs(
+        &self,
+        api: &Vulkan,
+        first_index: u16,
+        num_indices: u16,
+        constants: &VertexConstants,
+        descriptor: vk::DescriptorSet,
+        geometry: &UiGeometryBuffer,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        self.draw_from_atlas(
+            api,
+            self.color_glyph_pipeline,
+            first_index,
+            num_indices,
+            constants,
+            descriptor,
+            geometry,
+            command_buffer,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_from_atlas(
+        &self,
+        api: &Vulkan,
+        pipeline: vk::Pipeline,
+        first_index: u16,
+        num_indices: u16,
+        constants: &VertexConstants,
+        descriptor: vk::DescriptorSet,
+        geometry: &UiGeometryBuffer,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        unsafe {
+            api.device
+                .cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
+
+            api.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.layout,
+                0,
+                &[descriptor],
+                &[],
+            );
+        }
 
+        Self::record_draw(
+            api,
+            self.layout,
+            first_index,
+            num_indices,
+            constants,
+            geometry,
+            command_buffer,
+        );
+    }
+
+    /// Binds the geometry and constants and records the draw, assuming that a
+    /// pipeline has already been bound.
+    pub(super) fn record_draw(
+        api: &Vulkan,
+        layout: vk::PipelineLayout,
+        first_index: u16,
+        num_indices: u16,
+        constants: &VertexConstants,
+        geometry: &UiGeometryBuffer,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        unsafe {
             api.device
                 .cmd_bind_vertex_buffers(command_buffer, 0, &[geometry.handle], &[0]);
 
@@ -184,15 +591,13 @@ impl Fill {
 
             api.device.cmd_push_constants(
                 command_buffer,
-                self.layout,
+                layout,
                 vk::ShaderStageFlags::VERTEX,
                 0,
-                &std::mem::transmute::<_, [u8; std::mem::size_of::<ScaleTranslate>()]>(
-                    ScaleTranslate {
-                        scale: [2.0 / viewport.width as f32, 2.0 / viewport.height as f32],
-                        translate: [-1.0, -1.0],
-                    },
-                ),
+                &std::mem::transmute::<
+                    VertexConstants,
+                    [u8; std::mem::size_of::<VertexConstants>()],
+                >(*constants),
             );
 
             api.device.cmd_draw_indexed(
@@ -1,18 +1,55 @@
+mod effect;
 mod fill;
 
+pub use effect::Effect;
 pub use fill::Fill;
 
 use ash::vk;
 
-use crate::gfx::{geometry::Point, Vertex};
+use crate::gfx::{
+    geometry::{Point, Transform},
+    Tiling, Vertex,
+};
 
 use super::api::Vulkan;
 
 #[repr(C)]
 #[derive(Clone, Copy)]
-pub struct ScaleTranslate {
+pub struct VertexConstants {
     scale: [f32; 2],
     translate: [f32; 2],
+    transform: Transform,
+}
+
+impl VertexConstants {
+    /// Maps window coordinates within `viewport` to normalized device
+    /// coordinates after applying `transform`.
+    pub fn new(viewport: vk::Extent2D, transform: Transform) -> Self {
+        Self {
+            scale: [2.0 / viewport.width as f32, 2.0 / viewport.height as f32],
+            translate: [-1.0, -1.0],
+            transform,
+        }
+    }
+}
+
+/// Push constants for the fragment stage of textured geometry. These are
+/// placed immediately after [`VertexConstants`], and the offset must match the
+/// one declared in `image.frag.glsl`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct FragmentConstants {
+    tiling: u32,
+}
+
+impl FragmentConstants {
+    pub const OFFSET: u32 = std::mem::size_of::<VertexConstants>() as u32;
+
+    pub fn new(tiling: Tiling) -> Self {
+        Self {
+            tiling: tiling as u32,
+        }
+    }
 }
 
 pub const VERTEX_BINDING_DESCRIPTION: vk::VertexInputBindingDescription =
@@ -47,48 +84,156 @@ pub const VERTEX_ATTRIBUTE_DESCRIPTIONS: [vk::VertexInputAttributeDescription; 3
 ];
 
 pub struct DefaultRenderPass {
+    /// Clears the render target before drawing.
     pub handle: vk::RenderPass,
+    /// Preserves the render target's previous contents so that only part of
+    /// it can be redrawn. This is compatible with `handle`, so framebuffers
+    /// and pipelines created with one can be used with the other.
+    pub load_handle: vk::RenderPass,
+    /// The format of the stencil attachment used for clipping. Its contents
+    /// are cleared at the start of every render pass.
+    pub stencil_format: vk::Format,
 }
 
 impl DefaultRenderPass {
-    pub fn new(api: &Vulkan, format: vk::Format) -> Self {
-        let attachment_descriptions = [vk::AttachmentDescription {
-            flags: vk::AttachmentDescriptionFlags::empty(),
-            format,
-            samples: vk::SampleCountFlags::TYPE_1,
-            load_op: vk::AttachmentLoadOp::CLEAR,
-            store_op: vk::AttachmentStoreOp::STORE,
-            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
-            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
-            initial_layout: vk::ImageLayout::UNDEFINED,
-            final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+    pub fn new(api: &Vulkan, format: vk::Format, stencil_format: vk::Format) -> Self {
+        Self {
+            handle: Self::create(
+                api,
+                format,
+                stencil_format,
+                vk::AttachmentLoadOp::CLEAR,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::PRESENT_SRC_KHR,
+            ),
+            load_handle: Self::create(
+                api,
+                format,
+                stencil_format,
+                vk::AttachmentLoadOp::LOAD,
+                vk::ImageLayout::PRESENT_SRC_KHR,
+                vk::ImageLayout::PRESENT_SRC_KHR,
+            ),
+            stencil_format,
+        }
+    }
+
+    /// Like [`Self::new`], but for drawing to images that are sampled by later
+    /// draws instead of being presented. The image is left ready to be read
+    /// by fragment shaders.
+    pub fn for_images(api: &Vulkan, format: vk::Format, stencil_format: vk::Format) -> Self {
+        Self {
+            handle: Self::create(
+                api,
+                format,
+                stencil_format,
+                vk::AttachmentLoadOp::CLEAR,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::READ_ONLY_OPTIMAL,
+            ),
+            load_handle: Self::create(
+                api,
+                format,
+                stencil_format,
+                vk::AttachmentLoadOp::LOAD,
+                vk::ImageLayout::READ_ONLY_OPTIMAL,
+                vk::ImageLayout::READ_ONLY_OPTIMAL,
+            ),
+            stencil_format,
+        }
+    }
+
+    fn create(
+        api: &Vulkan,
+        format: vk::Format,
+        stencil_format: vk::Format,
+        load_op: vk::AttachmentLoadOp,
+        initial_layout: vk::ImageLayout,
+        final_layout: vk::ImageLayout,
+    ) -> vk::RenderPass {
+        let attachment_descriptions = [
+            vk::AttachmentDescription {
+                flags: vk::AttachmentDescriptionFlags::empty(),
+                format,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op,
+                store_op: vk::AttachmentStoreOp::STORE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout,
+                final_layout,
+            },
+            vk::AttachmentDescription {
+                flags: vk::AttachmentDescriptionFlags::empty(),
+                format: stencil_format,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::DONT_CARE,
+                store_op: vk::AttachmentStoreOp::DONT_CARE,
+                stencil_load_op: vk::AttachmentLoadOp::CLEAR,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            },
+        ];
+
+        // Bound to locals so that they outlive the builder's raw pointers.
+        let color_references = [vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
         }];
 
+        let stencil_reference = vk::AttachmentReference {
+            attachment: 1,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+
         let subpass_descriptions = [vk::SubpassDescription::builder()
-            .color_attachments(&[vk::AttachmentReference {
-                attachment: 0,
-                layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-            }])
+            .color_attachments(&color_references)
+            .depth_stencil_attachment(&stencil_reference)
             .build()];
 
-        let subpass_dependencies = [vk::SubpassDependency {
-            src_subpass: vk::SUBPASS_EXTERNAL,
-            dst_subpass: 0,
-            src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-            src_access_mask: vk::AccessFlags::NONE,
-            dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-            dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
-            dependency_flags: vk::DependencyFlags::empty(),
-        }];
+        // Images that are sampled must also wait for earlier draws to finish
+        // reading them before they are drawn over, and make what was drawn
+        // visible to later draws that read them.
+        let sampled = final_layout == vk::ImageLayout::READ_ONLY_OPTIMAL;
+        let read_stage = if sampled {
+            vk::PipelineStageFlags::FRAGMENT_SHADER
+        } else {
+            vk::PipelineStageFlags::empty()
+        };
 
+        let subpass_dependencies = [
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS
+                    | read_stage,
+                src_access_mask: vk::AccessFlags::NONE,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+            vk::SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: vk::SUBPASS_EXTERNAL,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_access_mask: vk::AccessFlags::SHADER_READ,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+        ];
+
+        let dependency_count = if sampled { 2 } else { 1 };
         let render_pass_ci = vk::RenderPassCreateInfo::builder()
             .attachments(&attachment_descriptions)
             .subpasses(&subpass_descriptions)
-            .dependencies(&subpass_dependencies);
-
-        let handle = unsafe { api.device.create_render_pass(&render_pass_ci, None) }.unwrap();
+            .dependencies(&subpass_dependencies[..dependency_count]);
 
-        Self { handle }
+        unsafe { api.device.create_render_pass(&render_pass_ci, None) }.unwrap()
     }
 
     pub fn create_framebuffer(
@@ -96,11 +241,13 @@ impl DefaultRenderPass {
         api: &Vulkan,
         extent: vk::Extent2D,
         color_attachment: vk::ImageView,
+        stencil_attachment: vk::ImageView,
     ) -> vk::Framebuffer {
+        let attachments = [color_attachment, stencil_attachment];
         let create_info = vk::FramebufferCreateInfo {
             render_pass: self.handle,
-            attachment_count: 1,
-            p_attachments: &color_attachment,
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
             width: extent.width,
             height: extent.height,
             layers: 1,
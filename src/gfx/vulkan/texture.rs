@@ -2,7 +2,10 @@
 //!
 //! ## Assumptions/Requirements
 //!
-//! - all textures stored in `RGBA_F16_LINEAR` format for simplicity
+//! - all uncompressed textures stored in `RGBA_F16_LINEAR` format for
+//!   simplicity
+//! - block-compressed textures are stored in their original format and
+//!   uploaded with a plain buffer-to-image copy
 //! - must accept all formats used by [`PixelBuffer`] (and convert)
 //! - must permit updating of subtextures in bulk
 //!
@@ -29,7 +32,7 @@ use smallvec::SmallVec;
 use crate::gfx::{
     geometry::{Extent, Offset},
     pixel_buffer::{Layout, PixelBufferView},
-    MAX_IMAGES,
+    ExternalFormat, ExternalHandle, ExternalImage, MAX_IMAGES,
 };
 
 use super::{
@@ -37,13 +40,24 @@ use super::{
     as_cchar_slice,
 };
 
-const STORAGE_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+/// The format of every uncompressed texture, and so also the format that is
+/// drawn to when a texture is used as a render target.
+pub const STORAGE_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
 
 pub struct Texture {
+    /// The layout of the pixels that this texture was created for. All
+    /// uncompressed layouts share the same storage format.
+    pub layout: Layout,
+    /// Whether or not the texture's memory was imported from another API. An
+    /// external texture cannot be written to.
+    pub is_external: bool,
+    pub extent: Extent,
     image: vk::Image,
     pub image_view: vk::ImageView,
     pub image_layout: vk::ImageLayout,
     memory: vk::DeviceMemory,
+    /// The number of bytes of device memory used by the texture.
+    pub memory_size: vk::DeviceSize,
     /// A timeline semaphore used to track read operations. If
     /// `read_semaphore==read_count`, the texture is not currently being read
     /// and can be used for write operations.
@@ -54,12 +68,29 @@ pub struct Texture {
 }
 
 impl Texture {
-    pub fn new(api: &Vulkan, extent: Extent) -> VkResult<Self> {
+    pub fn new(api: &Vulkan, extent: Extent, layout: Layout) -> VkResult<Self> {
+        let (format, usage) = match layout {
+            Layout::RGB8 | Layout::RGBA8 => (
+                STORAGE_FORMAT,
+                vk::ImageUsageFlags::SAMPLED
+                    | vk::ImageUsageFlags::STORAGE
+                    | vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            ),
+            Layout::BC1 => (
+                vk::Format::BC1_RGBA_UNORM_BLOCK,
+                vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+            ),
+            Layout::BC7 => (
+                vk::Format::BC7_UNORM_BLOCK,
+                vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+            ),
+        };
+
         let image = {
             let create_info = vk::ImageCreateInfo {
                 flags: vk::ImageCreateFlags::empty(),
                 image_type: vk::ImageType::TYPE_2D,
-                format: STORAGE_FORMAT,
+                format,
                 extent: vk::Extent3D {
                     width: extent.width.0 as u32,
                     height: extent.height.0 as u32,
@@ -69,7 +100,7 @@ impl Texture {
                 array_layers: 1,
                 samples: vk::SampleCountFlags::TYPE_1,
                 tiling: vk::ImageTiling::OPTIMAL,
-                usage: vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::STORAGE,
+                usage,
                 initial_layout: vk::ImageLayout::UNDEFINED,
                 ..Default::default()
             };
@@ -77,21 +108,201 @@ impl Texture {
             unsafe { api.device.create_image(&create_info, None) }?
         };
 
-        let memory = {
-            let requirements = unsafe { api.device.get_image_memory_requirements(image) };
-            api.allocate_memory(MemoryUsage::Static, requirements)?
-        };
+        let requirements = unsafe { api.device.get_image_memory_requirements(image) };
+        let memory = api.allocate_memory(MemoryUsage::Static, requirements)?;
 
         unsafe { api.device.bind_image_memory(image, memory, 0) }?;
 
-        let image_view = api.create_image_view(image, STORAGE_FORMAT)?;
+        let image_view = api.create_image_view(image, format)?;
         let read_semaphore = api.create_semaphore(true)?;
 
         Ok(Self {
+            layout,
+            is_external: false,
+            extent,
+            image,
+            image_view,
+            image_layout: vk::ImageLayout::UNDEFINED,
+            memory,
+            memory_size: requirements.size,
+            read_semaphore,
+            read_count: 0,
+            write_state: None,
+        })
+    }
+
+    /// Creates a texture backed by memory that was allocated by another API.
+    /// The texture must be acquired with [`Staging::acquire_external`] before
+    /// it can be read from.
+    #[allow(clippy::too_many_lines)]
+    pub fn import(api: &Vulkan, external: &ExternalImage) -> VkResult<Self> {
+        let format = match external.format {
+            ExternalFormat::Rgba8 => vk::Format::R8G8B8A8_UNORM,
+            ExternalFormat::Bgra8 => vk::Format::B8G8R8A8_UNORM,
+        };
+
+        let handle_type = match external.handle {
+            #[cfg(target_os = "windows")]
+            ExternalHandle::D3D11Texture(_) => vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE,
+            #[cfg(target_os = "windows")]
+            ExternalHandle::D3D11TextureKmt(_) => {
+                vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE_KMT
+            }
+            #[cfg(target_os = "linux")]
+            ExternalHandle::DmaBuf { .. } => vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
+        };
+
+        let image = {
+            let external_info = vk::ExternalMemoryImageCreateInfo {
+                handle_types: handle_type,
+                ..Default::default()
+            };
+
+            #[allow(unused_mut)]
+            let mut create_info = vk::ImageCreateInfo {
+                p_next: std::ptr::addr_of!(external_info).cast(),
+                flags: vk::ImageCreateFlags::empty(),
+                image_type: vk::ImageType::TYPE_2D,
+                format,
+                extent: vk::Extent3D {
+                    width: external.extent.width.0 as u32,
+                    height: external.extent.height.0 as u32,
+                    depth: 1,
+                },
+                mip_levels: 1,
+                array_layers: 1,
+                samples: vk::SampleCountFlags::TYPE_1,
+                tiling: vk::ImageTiling::OPTIMAL,
+                usage: vk::ImageUsageFlags::SAMPLED,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                ..Default::default()
+            };
+
+            // On Linux, external images are always dma-bufs, which are laid out
+            // as described by their DRM format modifier.
+            #[cfg(target_os = "linux")]
+            let ExternalHandle::DmaBuf {
+                modifier,
+                offset,
+                stride,
+                ..
+            } = external.handle;
+            #[cfg(target_os = "linux")]
+            let plane_layout = vk::SubresourceLayout {
+                offset,
+                size: 0,
+                row_pitch: stride,
+                array_pitch: 0,
+                depth_pitch: 0,
+            };
+            #[cfg(target_os = "linux")]
+            let modifier_info = vk::ImageDrmFormatModifierExplicitCreateInfoEXT {
+                p_next: create_info.p_next,
+                drm_format_modifier: modifier,
+                drm_format_modifier_plane_count: 1,
+                p_plane_layouts: &plane_layout,
+                ..Default::default()
+            };
+            #[cfg(target_os = "linux")]
+            {
+                create_info.p_next = std::ptr::addr_of!(modifier_info).cast();
+                create_info.tiling = vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT;
+            }
+
+            unsafe { api.device.create_image(&create_info, None) }?
+        };
+
+        let requirements = unsafe { api.device.get_image_memory_requirements(image) };
+        let memory = {
+            // External images must have their own dedicated allocation.
+            let dedicated_info = vk::MemoryDedicatedAllocateInfo {
+                image,
+                ..Default::default()
+            };
+
+            #[cfg(target_os = "windows")]
+            let win32_info;
+            #[cfg(target_os = "linux")]
+            let fd_info;
+
+            let import_info: *const std::ffi::c_void = match external.handle {
+                #[cfg(target_os = "windows")]
+                ExternalHandle::D3D11Texture(handle) | ExternalHandle::D3D11TextureKmt(handle) => {
+                    win32_info = vk::ImportMemoryWin32HandleInfoKHR {
+                        p_next: std::ptr::addr_of!(dedicated_info).cast(),
+                        handle_type,
+                        handle: handle.0 as _,
+                        ..Default::default()
+                    };
+                    std::ptr::addr_of!(win32_info).cast()
+                }
+                #[cfg(target_os = "linux")]
+                ExternalHandle::DmaBuf { fd, .. } => {
+                    fd_info = vk::ImportMemoryFdInfoKHR {
+                        p_next: std::ptr::addr_of!(dedicated_info).cast(),
+                        handle_type,
+                        fd,
+                        ..Default::default()
+                    };
+                    std::ptr::addr_of!(fd_info).cast()
+                }
+            };
+
+            let allocate_info = vk::MemoryAllocateInfo {
+                p_next: import_info,
+                allocation_size: requirements.size,
+                memory_type_index: api
+                    .select_memory_type(MemoryUsage::Static, requirements.memory_type_bits),
+                ..Default::default()
+            };
+
+            match unsafe { api.device.allocate_memory(&allocate_info, None) } {
+                Ok(memory) => memory,
+                Err(e) => {
+                    unsafe { api.device.destroy_image(image, None) };
+                    return Err(e);
+                }
+            }
+        };
+
+        // The image and memory are released if anything after this fails, so
+        // that they aren't leaked.
+        let release = || unsafe {
+            api.device.destroy_image(image, None);
+            api.device.free_memory(memory, None);
+        };
+
+        if let Err(e) = unsafe { api.device.bind_image_memory(image, memory, 0) } {
+            release();
+            return Err(e);
+        }
+
+        let image_view = match api.create_image_view(image, format) {
+            Ok(image_view) => image_view,
+            Err(e) => {
+                release();
+                return Err(e);
+            }
+        };
+
+        let read_semaphore = match api.create_semaphore(true) {
+            Ok(semaphore) => semaphore,
+            Err(e) => {
+                unsafe { api.device.destroy_image_view(image_view, None) };
+                release();
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            layout: Layout::RGBA8,
+            is_external: true,
+            extent: external.extent,
             image,
             image_view,
             image_layout: vk::ImageLayout::UNDEFINED,
             memory,
+            memory_size: requirements.size,
             read_semaphore,
             read_count: 0,
             write_state: None,
@@ -212,6 +423,9 @@ pub struct Staging {
     descriptors: ArrayVec<Descriptor, { Self::MAX_DESCRIPTORS as usize }>,
 }
 
+// The mapped memory is only accessed through `&mut self`.
+unsafe impl Send for Staging {}
+
 impl Staging {
     const MAX_CONCURRENT_IO: u32 = MAX_IMAGES;
     const MAX_DESCRIPTORS: u32 = Self::MAX_CONCURRENT_IO * 4;
@@ -429,6 +643,81 @@ impl Staging {
         self.io_pool.push(state);
     }
 
+    /// Transfers ownership of an imported texture from the external API to
+    /// the graphics queue. Draws using the texture will wait for the transfer
+    /// to complete.
+    pub fn acquire_external(&mut self, api: &Vulkan, dst: &mut Texture) -> VkResult<()> {
+        debug_assert!(dst.is_external);
+
+        let mut write_state = self.alloc_write_state(api)?;
+
+        unsafe {
+            api.device.begin_command_buffer(
+                write_state.command_buffer,
+                &vk::CommandBufferBeginInfo {
+                    flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+                    ..Default::default()
+                },
+            )?;
+
+            api.device.cmd_pipeline_barrier(
+                write_state.command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[vk::ImageMemoryBarrier {
+                    src_access_mask: vk::AccessFlags::NONE,
+                    dst_access_mask: vk::AccessFlags::SHADER_READ,
+                    // The producer leaves the image in the general layout.
+                    // Transitioning from `UNDEFINED` would let the driver
+                    // discard the contents that were imported.
+                    old_layout: vk::ImageLayout::GENERAL,
+                    new_layout: vk::ImageLayout::READ_ONLY_OPTIMAL,
+                    src_queue_family_index: vk::QUEUE_FAMILY_EXTERNAL,
+                    dst_queue_family_index: api.physical_device.graphics_queue_family,
+                    image: dst.image,
+                    subresource_range: vk::ImageSubresourceRange {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        base_mip_level: 0,
+                        level_count: 1,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    },
+                    ..Default::default()
+                }],
+            );
+
+            api.device.end_command_buffer(write_state.command_buffer)?;
+        }
+
+        write_state.counter += 1;
+        let timeline_info = vk::TimelineSemaphoreSubmitInfo {
+            signal_semaphore_value_count: 1,
+            p_signal_semaphore_values: &write_state.counter,
+            ..Default::default()
+        };
+
+        let submit = vk::SubmitInfo {
+            p_next: std::ptr::addr_of!(timeline_info).cast(),
+            signal_semaphore_count: 1,
+            p_signal_semaphores: &write_state.semaphore,
+            command_buffer_count: 1,
+            p_command_buffers: &write_state.command_buffer,
+            ..Default::default()
+        };
+
+        dst.image_layout = vk::ImageLayout::READ_ONLY_OPTIMAL;
+        dst.write_state = Some(write_state);
+
+        let _queues = api.lock_queues();
+        unsafe {
+            api.device
+                .queue_submit(api.graphics_queue, &[submit], vk::Fence::null())
+        }
+    }
+
     #[allow(clippy::too_many_lines)]
     pub fn copy_pixels(
         &mut self,
@@ -437,16 +726,15 @@ impl Staging {
         dst: &mut Texture,
         ops: &[crate::gfx::ImageCopy],
     ) -> VkResult<()> {
-        let mut pixels_to_copy = 0;
+        let mut bytes_to_copy = 0;
         for op in ops {
-            pixels_to_copy += op.src_rect.extent().area();
+            bytes_to_copy += src.layout().size_of(op.src_rect.extent()) as vk::DeviceSize;
         }
 
-        let bytes_to_copy = (pixels_to_copy * src.layout().bytes_per_pixel()) as vk::DeviceSize;
         let (image_buffer, image_memory) = api.allocate_buffer(
             MemoryUsage::Once,
             bytes_to_copy,
-            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_SRC,
         )?;
 
         let mut bytes_written = 0;
@@ -478,12 +766,6 @@ impl Staging {
         }
 
         let mut write_state = self.alloc_write_state(api)?;
-        write_state.descriptors.reserve(ops.len());
-
-        assert!(
-            self.descriptors.len() >= ops.len(),
-            "out of staging descriptors!"
-        );
 
         unsafe {
             api.device.begin_command_buffer(
@@ -493,12 +775,88 @@ impl Staging {
                     ..Default::default()
                 },
             )?;
+        }
 
-            let pipeline = match src.layout() {
-                Layout::RGB8 => self.rgb_pipeline,
-                Layout::RGBA8 => self.rgba_pipeline,
-            };
+        let wait_stage = if src.layout().is_compressed() {
+            Self::record_block_copy(api, write_state.command_buffer, src, image_buffer, dst, ops);
+            vk::PipelineStageFlags::TRANSFER
+        } else {
+            self.record_shader_copy(api, &mut write_state, src, image_buffer, dst, ops)?;
+            vk::PipelineStageFlags::COMPUTE_SHADER
+        };
+
+        unsafe { api.device.end_command_buffer(write_state.command_buffer) }?;
 
+        let mut wait_values = ArrayVec::<_, 2>::new();
+        let mut wait_semaphores = ArrayVec::<_, 2>::new();
+
+        wait_values.push(dst.read_count);
+        wait_semaphores.push(dst.read_semaphore);
+
+        let old = dst.write_state.take();
+
+        if let Some(write) = old {
+            wait_values.push(write.counter);
+            wait_semaphores.push(write.semaphore);
+        }
+
+        write_state.counter += 1;
+        let timeline_info = vk::TimelineSemaphoreSubmitInfo {
+            wait_semaphore_value_count: wait_values.len() as u32,
+            p_wait_semaphore_values: wait_values.as_ptr(),
+            signal_semaphore_value_count: 1,
+            p_signal_semaphore_values: &write_state.counter,
+            ..Default::default()
+        };
+
+        let submit = vk::SubmitInfo {
+            p_next: std::ptr::addr_of!(timeline_info).cast(),
+            wait_semaphore_count: wait_values.len() as u32,
+            p_wait_semaphores: wait_semaphores.as_ptr(),
+            p_signal_semaphores: &write_state.semaphore,
+            p_wait_dst_stage_mask: &wait_stage,
+            command_buffer_count: 1,
+            p_command_buffers: &write_state.command_buffer,
+            ..Default::default()
+        };
+
+        dst.image_layout = vk::ImageLayout::READ_ONLY_OPTIMAL;
+        dst.write_state = Some(write_state);
+
+        let _queues = api.lock_queues();
+        unsafe {
+            api.device
+                .queue_submit(api.graphics_queue, &[submit], vk::Fence::null())
+        }?;
+
+        Ok(())
+    }
+
+    /// Records a compute dispatch per copy operation that converts the source
+    /// pixels into the texture's storage format.
+    fn record_shader_copy(
+        &mut self,
+        api: &Vulkan,
+        write_state: &mut WriteState,
+        src: PixelBufferView,
+        image_buffer: vk::Buffer,
+        dst: &Texture,
+        ops: &[crate::gfx::ImageCopy],
+    ) -> VkResult<()> {
+        write_state.descriptors.reserve(ops.len());
+
+        assert!(
+            self.descriptors.len() >= ops.len(),
+            "out of staging descriptors!"
+        );
+
+        let pipeline = match src.layout() {
+            Layout::RGB8 => self.rgb_pipeline,
+            Layout::RGBA8 => self.rgba_pipeline,
+            Layout::BC1 | Layout::BC7 => unreachable!("compressed layouts are copied directly"),
+        };
+
+        unsafe {
             api.device.cmd_bind_pipeline(
                 write_state.command_buffer,
                 vk::PipelineBindPoint::COMPUTE,
@@ -658,52 +1016,108 @@ impl Staging {
                     ..Default::default()
                 }],
             );
-
-            api.device.end_command_buffer(write_state.command_buffer)?;
-        }
-
-        let mut wait_values = ArrayVec::<_, 2>::new();
-        let mut wait_semaphores = ArrayVec::<_, 2>::new();
-
-        wait_values.push(dst.read_count);
-        wait_semaphores.push(dst.read_semaphore);
-
-        let old = dst.write_state.take();
-
-        if let Some(write) = old {
-            wait_values.push(write.counter);
-            wait_semaphores.push(write.semaphore);
         }
 
-        write_state.counter += 1;
-        let timeline_info = vk::TimelineSemaphoreSubmitInfo {
-            wait_semaphore_value_count: wait_values.len() as u32,
-            p_wait_semaphore_values: wait_values.as_ptr(),
-            signal_semaphore_value_count: 1,
-            p_signal_semaphore_values: &write_state.counter,
-            ..Default::default()
-        };
+        Ok(())
+    }
 
-        let submit = vk::SubmitInfo {
-            p_next: std::ptr::addr_of!(timeline_info).cast(),
-            wait_semaphore_count: wait_values.len() as u32,
-            p_wait_semaphores: wait_semaphores.as_ptr(),
-            p_signal_semaphores: &write_state.semaphore,
-            p_wait_dst_stage_mask: &vk::PipelineStageFlags::COMPUTE_SHADER,
-            command_buffer_count: 1,
-            p_command_buffers: &write_state.command_buffer,
-            ..Default::default()
+    /// Records a buffer-to-image copy per copy operation. Block-compressed
+    /// pixels are already in the texture's storage format, so no conversion
+    /// is needed.
+    fn record_block_copy(
+        api: &Vulkan,
+        command_buffer: vk::CommandBuffer,
+        src: PixelBufferView,
+        image_buffer: vk::Buffer,
+        dst: &Texture,
+        ops: &[crate::gfx::ImageCopy],
+    ) {
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
         };
 
-        dst.image_layout = vk::ImageLayout::READ_ONLY_OPTIMAL;
-        dst.write_state = Some(write_state);
+        let mut regions = SmallVec::<[vk::BufferImageCopy; 4]>::with_capacity(ops.len());
+        let mut buffer_offset = 0;
+        for op in ops {
+            let extent = op.src_rect.extent();
+            regions.push(vk::BufferImageCopy {
+                buffer_offset,
+                // Rows are tightly packed blocks.
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+                image_offset: vk::Offset3D {
+                    x: i32::from(op.dst_location.x.0),
+                    y: i32::from(op.dst_location.y.0),
+                    z: 0,
+                },
+                image_extent: vk::Extent3D {
+                    width: extent.width.0 as u32,
+                    height: extent.height.0 as u32,
+                    depth: 1,
+                },
+            });
+            buffer_offset += src.layout().size_of(extent) as vk::DeviceSize;
+        }
 
         unsafe {
-            api.device
-                .queue_submit(api.graphics_queue, &[submit], vk::Fence::null())
-        }?;
+            api.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::BY_REGION,
+                &[],
+                &[],
+                &[vk::ImageMemoryBarrier {
+                    src_access_mask: vk::AccessFlags::NONE,
+                    dst_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                    old_layout: dst.image_layout,
+                    new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    image: dst.image,
+                    subresource_range,
+                    ..Default::default()
+                }],
+            );
 
-        Ok(())
+            api.device.cmd_copy_buffer_to_image(
+                command_buffer,
+                image_buffer,
+                dst.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &regions,
+            );
+
+            api.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::BY_REGION,
+                &[],
+                &[],
+                &[vk::ImageMemoryBarrier {
+                    src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                    dst_access_mask: vk::AccessFlags::NONE,
+                    old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    new_layout: vk::ImageLayout::READ_ONLY_OPTIMAL,
+                    src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    image: dst.image,
+                    subresource_range,
+                    ..Default::default()
+                }],
+            );
+        }
     }
 
     fn create_rgb8_pipeline(api: &Vulkan, layout: vk::PipelineLayout) -> VkResult<vk::Pipeline> {
@@ -1,9 +1,11 @@
 use ash::vk;
 use smallvec::SmallVec;
 
+use crate::gfx::geometry::{Extent, Point, Px, Rect};
+
 use super::{
     api::{VkResult, Vulkan},
-    RenderFrame, FRAMES_IN_FLIGHT, PREFERRED_SWAPCHAIN_LENGTH,
+    RenderFrame, FRAMES_IN_FLIGHT, FULL_SCREEN_EXCLUSIVE_EXTENSION, PREFERRED_SWAPCHAIN_LENGTH,
 };
 
 pub struct FrameSync {
@@ -28,6 +30,16 @@ pub struct Window {
     /// this check is actually useful, but it was left in just in case.
     current_image: Option<u32>,
 
+    /// The region of the current image that must be redrawn. Set alongside
+    /// `current_image`.
+    current_region: Rect,
+
+    /// The regions of each swapchain image that are out of date, either
+    /// because the image is new or because other images were drawn to since
+    /// it was last presented. This is what lets us redraw only part of an
+    /// image while keeping the rest of its contents.
+    stale: SmallVec<[Rect; PREFERRED_SWAPCHAIN_LENGTH as usize]>,
+
     /// SwapchainImage synchronization objects, used in alternating order as tracked by
     /// `frame_id`.
     frame_sync: [FrameSync; FRAMES_IN_FLIGHT],
@@ -37,7 +49,11 @@ pub struct Window {
 
 impl Window {
     #[cfg(target_os = "windows")]
-    pub fn new(api: &Vulkan, hwnd: windows::Win32::Foundation::HWND) -> VkResult<Self> {
+    pub fn new(
+        api: &Vulkan,
+        hwnd: windows::Win32::Foundation::HWND,
+        transparent: bool,
+    ) -> VkResult<Self> {
         use windows::Win32::{
             Foundation::RECT, System::LibraryLoader::GetModuleHandleW,
             UI::WindowsAndMessaging::GetClientRect,
@@ -63,16 +79,52 @@ impl Window {
             }
         };
 
-        Self::_new(api, surface, extent)
+        Self::_new(api, surface, extent, transparent)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn new(
+        api: &Vulkan,
+        display: *mut std::ffi::c_void,
+        window: std::os::raw::c_ulong,
+        transparent: bool,
+    ) -> VkResult<Self> {
+        let surface_ci = vk::XlibSurfaceCreateInfoKHR::builder()
+            .dpy(display.cast())
+            .window(window);
+
+        let surface = unsafe {
+            api.xlib_surface_khr
+                .create_xlib_surface(&surface_ci, None)?
+        };
+
+        // X11 surfaces always report the size of the window.
+        let extent = unsafe {
+            api.surface_khr
+                .get_physical_device_surface_capabilities(api.physical_device.handle, surface)?
+                .current_extent
+        };
+
+        Self::_new(api, surface, extent, transparent)
     }
 
     /// Platform-independent code for initializing a window. See `new` for the
     /// platform-dependent coe needed to call this method.
-    fn _new(api: &Vulkan, surface: vk::SurfaceKHR, extent: vk::Extent2D) -> VkResult<Self> {
+    fn _new(
+        api: &Vulkan,
+        surface: vk::SurfaceKHR,
+        extent: vk::Extent2D,
+        transparent: bool,
+    ) -> VkResult<Self> {
+        let swapchain = Swapchain::new(api, surface, extent, transparent)?;
+        let stale = swapchain.views.iter().map(|_| swapchain.rect()).collect();
+
         Ok(Self {
-            swapchain: Swapchain::new(api, surface, extent)?,
+            swapchain,
             frame_id: 0,
             current_image: None,
+            current_region: Rect::zero(),
+            stale,
             frame_sync: [
                 FrameSync {
                     acquire_semaphore: api.create_semaphore(false).unwrap(),
@@ -104,12 +156,30 @@ impl Window {
         self.swapchain.format
     }
 
+    /// Returns true if the window is blended with what is behind it.
+    pub fn is_transparent(&self) -> bool {
+        self.swapchain.transparent
+    }
+
+    /// Returns true if the current region covers the entire image, and so the
+    /// image's prior contents can be discarded.
+    pub fn is_full_redraw(&self) -> bool {
+        self.current_region == self.swapchain.rect()
+    }
+
     pub(super) fn render_state(
         &mut self,
-    ) -> (vk::ImageView, vk::Extent2D, &FrameSync, &mut RenderFrame) {
+    ) -> (
+        vk::ImageView,
+        vk::Extent2D,
+        Rect,
+        &FrameSync,
+        &mut RenderFrame,
+    ) {
         (
             self.swapchain.views[self.current_image.unwrap() as usize],
             self.swapchain.extent,
+            self.current_region,
             &self.frame_sync[self.frame_id as usize % FRAMES_IN_FLIGHT],
             &mut self.render_targets[self.frame_id as usize % FRAMES_IN_FLIGHT],
         )
@@ -117,45 +187,148 @@ impl Window {
 
     /// Resize the swapchain and create the necessary per-frame data.
     pub fn resize(&mut self, api: &Vulkan, extent: vk::Extent2D) -> VkResult<()> {
-        unsafe { api.device.device_wait_idle() }?;
+        api.wait_idle()?;
         self.swapchain.resize(api, extent)?;
+
+        let full = self.swapchain.rect();
+        self.stale.clear();
+        self.stale.extend(self.swapchain.views.iter().map(|_| full));
         Ok(())
     }
 
-    pub fn get_next_image(&mut self, api: &Vulkan) -> VkResult<()> {
-        let sync = &self.frame_sync[self.frame_id as usize % self.frame_sync.len()];
+    /// Allows or prevents the driver from taking exclusive control of the
+    /// display, recreating the swapchain if this changes.
+    pub fn set_exclusive(&mut self, api: &Vulkan, exclusive: bool) -> VkResult<()> {
+        if self.swapchain.exclusive == exclusive {
+            return Ok(());
+        }
 
-        let (index, out_of_date) = unsafe {
-            api.swapchain_khr.acquire_next_image(
-                self.swapchain.handle,
-                u64::MAX,
-                sync.acquire_semaphore,
-                vk::Fence::null(),
-            )
-        }?;
+        self.swapchain.exclusive = exclusive;
+        let extent = self.swapchain.extent;
+        self.resize(api, extent)
+    }
 
-        if out_of_date {
-            Err(vk::Result::ERROR_OUT_OF_DATE_KHR)
+    /// Makes presenting wait for vertical blank or not, recreating the
+    /// swapchain if this changes.
+    pub fn set_vsync(&mut self, api: &Vulkan, vsync: bool) -> VkResult<()> {
+        if self.swapchain.vsync == vsync {
+            return Ok(());
+        }
+
+        self.swapchain.vsync = vsync;
+        let extent = self.swapchain.extent;
+        self.resize(api, extent)
+    }
+
+    /// Acquires the next swapchain image. `damage` is the region of the window
+    /// that changed since the last frame. Returns the region of the acquired
+    /// image that must be redrawn, which also includes any damage that was
+    /// drawn to other images since this one was last presented.
+    ///
+    /// If an image has already been acquired, `damage` is added to its region
+    /// instead.
+    pub fn get_next_image(&mut self, api: &Vulkan, damage: Rect) -> VkResult<Rect> {
+        let index = if let Some(index) = self.current_image {
+            index
         } else {
+            let sync = &self.frame_sync[self.frame_id as usize % self.frame_sync.len()];
+
+            let (index, out_of_date) = unsafe {
+                api.swapchain_khr.acquire_next_image(
+                    self.swapchain.handle,
+                    u64::MAX,
+                    sync.acquire_semaphore,
+                    vk::Fence::null(),
+                )
+            }?;
+
+            if out_of_date {
+                return Err(vk::Result::ERROR_OUT_OF_DATE_KHR);
+            }
+
             self.current_image = Some(index);
-            Ok(())
+            self.current_region = Rect::zero();
+            index
+        };
+
+        let damage = damage.intersect(&self.swapchain.rect());
+        for stale in &mut self.stale {
+            *stale = stale.union(&damage);
         }
+
+        let stale = std::mem::take(&mut self.stale[index as usize]);
+        self.current_region = self.current_region.union(&stale);
+
+        // Drawing over the old contents of a transparent image would blend
+        // with them, so it is cleared and redrawn in full instead.
+        if self.swapchain.transparent {
+            self.current_region = self.swapchain.rect();
+        }
+
+        Ok(self.current_region)
     }
 
-    pub fn present(&mut self, api: &Vulkan) -> VkResult<()> {
+    /// Presents the current swapchain image. If `damage` is not empty, it is
+    /// passed to the presentation engine as the set of regions that changed
+    /// since the last present. This requires `VK_KHR_incremental_present`.
+    pub fn present(&mut self, api: &Vulkan, damage: &[vk::RectLayerKHR]) -> VkResult<()> {
         let sync = &self.frame_sync[self.frame_id as usize % self.frame_sync.len()];
 
         if let Some(index) = self.current_image.take() {
+            // Regions must lie within the swapchain image.
+            let extent = self.swapchain.extent;
+            let damage = damage
+                .iter()
+                .filter_map(|rect| {
+                    let x = rect.offset.x.clamp(0, extent.width as i32);
+                    let y = rect.offset.y.clamp(0, extent.height as i32);
+                    let right = (rect.offset.x + rect.extent.width as i32).min(extent.width as i32);
+                    let bottom =
+                        (rect.offset.y + rect.extent.height as i32).min(extent.height as i32);
+
+                    (right > x && bottom > y).then_some(vk::RectLayerKHR {
+                        offset: vk::Offset2D { x, y },
+                        extent: vk::Extent2D {
+                            width: (right - x) as u32,
+                            height: (bottom - y) as u32,
+                        },
+                        layer: 0,
+                    })
+                })
+                .collect::<SmallVec<[_; 8]>>();
+
+            let region = vk::PresentRegionKHR {
+                rectangle_count: damage.len() as u32,
+                p_rectangles: damage.as_ptr(),
+            };
+
+            let regions = vk::PresentRegionsKHR {
+                swapchain_count: 1,
+                p_regions: &region,
+                ..Default::default()
+            };
+
             let mut results = [vk::Result::ERROR_UNKNOWN];
+            let wait_semaphores = [sync.present_semaphore];
+            let swapchains = [self.swapchain.handle];
+            let image_indices = [index];
+            let mut present_info = vk::PresentInfoKHR::builder()
+                .wait_semaphores(&wait_semaphores)
+                .swapchains(&swapchains)
+                .image_indices(&image_indices)
+                .results(&mut results)
+                .build();
+
+            // An empty damage list means that the whole image changed, which
+            // is the default without the extension.
+            if !damage.is_empty() {
+                present_info.p_next = std::ptr::addr_of!(regions).cast();
+            }
+
+            let _queues = api.lock_queues();
             unsafe {
-                api.swapchain_khr.queue_present(
-                    api.present_queue,
-                    &vk::PresentInfoKHR::builder()
-                        .wait_semaphores(&[sync.present_semaphore])
-                        .swapchains(&[self.swapchain.handle])
-                        .image_indices(&[index])
-                        .results(&mut results),
-                )
+                api.swapchain_khr
+                    .queue_present(api.present_queue, &present_info)
             }?;
             results[0].result()?;
             self.frame_id += 1;
@@ -174,17 +347,57 @@ struct Swapchain {
     extent: vk::Extent2D,
     format: vk::Format,
     views: SmallVec<[vk::ImageView; PREFERRED_SWAPCHAIN_LENGTH as usize]>,
+    /// Whether the driver may take exclusive control of the display. Only
+    /// used if `VK_EXT_full_screen_exclusive` is enabled.
+    exclusive: bool,
+    /// Whether presenting waits for vertical blank. If not, the swapchain
+    /// uses whichever faster present mode is available.
+    vsync: bool,
+    /// Whether the images are blended with what is behind the window.
+    transparent: bool,
 }
 
 impl Swapchain {
-    fn new(api: &Vulkan, surface: vk::SurfaceKHR, extent: vk::Extent2D) -> VkResult<Self> {
-        Self::create_swapchain(api, surface, extent, vk::SwapchainKHR::null())
+    /// The bounds of the swapchain's images.
+    fn rect(&self) -> Rect {
+        Rect::new(
+            Point::zero(),
+            Extent {
+                width: Px::try_from(self.extent.width).unwrap_or(Px::MAX),
+                height: Px::try_from(self.extent.height).unwrap_or(Px::MAX),
+            },
+        )
     }
 
-    fn resize(&mut self, api: &Vulkan, extent: vk::Extent2D) -> VkResult<()> {
-        unsafe { api.device.device_wait_idle() }?;
+    fn new(
+        api: &Vulkan,
+        surface: vk::SurfaceKHR,
+        extent: vk::Extent2D,
+        transparent: bool,
+    ) -> VkResult<Self> {
+        Self::create_swapchain(
+            api,
+            surface,
+            extent,
+            vk::SwapchainKHR::null(),
+            false,
+            true,
+            transparent,
+        )
+    }
 
-        let mut new = Self::create_swapchain(api, self.surface, extent, self.handle)?;
+    fn resize(&mut self, api: &Vulkan, extent: vk::Extent2D) -> VkResult<()> {
+        api.wait_idle()?;
+
+        let mut new = Self::create_swapchain(
+            api,
+            self.surface,
+            extent,
+            self.handle,
+            self.exclusive,
+            self.vsync,
+            self.transparent,
+        )?;
         std::mem::swap(&mut new, self);
         new.destroy(api);
 
@@ -206,6 +419,9 @@ impl Swapchain {
         surface: vk::SurfaceKHR,
         #[allow(unused)] extent: vk::Extent2D,
         old_swapchain: vk::SwapchainKHR,
+        exclusive: bool,
+        vsync: bool,
+        transparent: bool,
     ) -> VkResult<Swapchain> {
         let vk::SurfaceFormatKHR {
             format,
@@ -238,8 +454,25 @@ impl Swapchain {
                 .get_physical_device_surface_capabilities(api.physical_device.handle, surface)
         }?;
 
+        // FIFO is the only mode that every device supports. Without vsync,
+        // MAILBOX avoids tearing while still presenting the latest image as
+        // soon as possible.
+        let present_mode = if vsync {
+            vk::PresentModeKHR::FIFO
+        } else {
+            let available = unsafe {
+                api.surface_khr
+                    .get_physical_device_surface_present_modes(api.physical_device.handle, surface)
+            }?;
+
+            [vk::PresentModeKHR::MAILBOX, vk::PresentModeKHR::IMMEDIATE]
+                .into_iter()
+                .find(|mode| available.contains(mode))
+                .unwrap_or(vk::PresentModeKHR::FIFO)
+        };
+
         // Current extent is always defined as the size of the window on win32
-        #[cfg(target_os = "windows")]
+        // and X11
         let image_extent = capabilities.current_extent;
 
         let handle = {
@@ -259,7 +492,30 @@ impl Swapchain {
             let needs_concurrent = api.physical_device.graphics_queue_family
                 != api.physical_device.present_queue_family;
 
-            let create_info = vk::SwapchainCreateInfoKHR {
+            // Drivers may otherwise decide for themselves, such as for
+            // borderless windows that cover the monitor.
+            let exclusive_info = vk::SurfaceFullScreenExclusiveInfoEXT {
+                full_screen_exclusive: if exclusive {
+                    vk::FullScreenExclusiveEXT::ALLOWED
+                } else {
+                    vk::FullScreenExclusiveEXT::DISALLOWED
+                },
+                ..Default::default()
+            };
+
+            // Falls back to an opaque window if the presentation engine can't
+            // blend the images.
+            let composite_alpha = if transparent
+                && capabilities
+                    .supported_composite_alpha
+                    .contains(vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED)
+            {
+                vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED
+            } else {
+                vk::CompositeAlphaFlagsKHR::OPAQUE
+            };
+
+            let mut create_info = vk::SwapchainCreateInfoKHR {
                 surface,
                 min_image_count,
                 image_format: format,
@@ -275,13 +531,17 @@ impl Swapchain {
                 queue_family_index_count: if needs_concurrent { 1 } else { 2 },
                 p_queue_family_indices: concurrent_family_indices.as_ptr(),
                 pre_transform: capabilities.current_transform,
-                composite_alpha: vk::CompositeAlphaFlagsKHR::OPAQUE,
-                present_mode: vk::PresentModeKHR::FIFO,
+                composite_alpha,
+                present_mode,
                 clipped: vk::TRUE,
                 old_swapchain,
                 ..Default::default()
             };
 
+            if api.is_device_extension_enabled(FULL_SCREEN_EXCLUSIVE_EXTENSION) {
+                create_info.p_next = std::ptr::addr_of!(exclusive_info).cast();
+            }
+
             unsafe { api.swapchain_khr.create_swapchain(&create_info, None) }?
         };
 
@@ -300,6 +560,9 @@ impl Swapchain {
             extent: image_extent,
             format,
             views,
+            exclusive,
+            vsync,
+            transparent,
         })
     }
 }
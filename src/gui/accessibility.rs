@@ -0,0 +1,603 @@
+//! Describes widget trees to assistive technology, such as screen readers and
+//! UI automation tools.
+//!
+//! Widgets describe themselves with [`Widget::accessibility`], and
+//! [`AccessTree::build`] collects those descriptions into a tree that mirrors
+//! the widget tree. Widgets that only arrange or decorate other widgets don't
+//! describe themselves, so their children appear in the tree as children of
+//! their closest described ancestor. The tree uses the same model as
+//! platform accessibility APIs (roles, names, values, states and actions) so
+//! that it can be handed to them directly.
+//!
+//! Assistive technology acts on widgets through [`perform_action`]. Widgets
+//! are identified by their [`FocusId`], so only widgets that can take the
+//! keyboard focus can be acted on.
+//!
+//! The application hands each window's tree to the shell as an AccessKit
+//! update built by [`AccessTree::to_update`] after the window is laid out,
+//! and decodes the requests that come back with [`AccessTree::request`].
+//! Only the Windows shell passes them on to the platform, through UI
+//! Automation; see [`ShellFeatures::accessibility`].
+//!
+//! [`ShellFeatures::accessibility`]: crate::shell::ShellFeatures::accessibility
+
+use crate::gfx::geometry::{Rect, ScaleFactor};
+
+use super::{
+    input::FocusId,
+    widgets::{mark_changed, Widget},
+};
+
+/// What kind of control a widget is, which determines how assistive
+/// technology presents it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// The root of the tree.
+    Window,
+    Group,
+    Button,
+    CheckBox,
+    RadioButton,
+    Switch,
+    TextInput,
+    MultilineTextInput,
+    ComboBox,
+    Label,
+}
+
+/// Whether a checkable widget is checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Toggled {
+    False,
+    True,
+    /// Neither checked nor unchecked, such as a checkbox for a group of
+    /// settings that are only partially enabled.
+    Mixed,
+}
+
+/// The kinds of [`Action`] that a widget supports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionKind {
+    Click,
+    SetValue,
+}
+
+/// Something that assistive technology asks a widget to do. Moving the
+/// keyboard focus isn't an action, since it is handled by the window rather
+/// than by the widget.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Does what clicking the widget would do.
+    Click,
+    /// Replaces the widget's value, such as the text of a text input.
+    SetValue(String),
+}
+
+impl Action {
+    #[must_use]
+    pub fn kind(&self) -> ActionKind {
+        match self {
+            Action::Click => ActionKind::Click,
+            Action::SetValue(_) => ActionKind::SetValue,
+        }
+    }
+}
+
+/// Something that assistive technology asked a window to do, decoded with
+/// [`AccessTree::request`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Request {
+    /// Moves the keyboard focus to the widget.
+    Focus(FocusId),
+    /// Performs the action on the widget with [`perform_action`].
+    Act(FocusId, Action),
+}
+
+/// A widget's description of itself. See [`Widget::accessibility`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccessNode {
+    pub role: Role,
+    /// The text that identifies the widget, such as a button's label.
+    pub name: Option<String>,
+    /// The widget's current value, such as the text in a text input.
+    pub value: Option<String>,
+    pub toggled: Option<Toggled>,
+    /// Whether a widget that can be opened, such as a dropdown, is open.
+    pub expanded: Option<bool>,
+    /// Set if the widget can take the keyboard focus. This also identifies
+    /// the widget to [`perform_action`].
+    pub focus: Option<FocusId>,
+    pub actions: Vec<ActionKind>,
+}
+
+impl AccessNode {
+    #[must_use]
+    pub fn new(role: Role) -> Self {
+        Self {
+            role,
+            name: None,
+            value: None,
+            toggled: None,
+            expanded: None,
+            focus: None,
+            actions: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_value(mut self, value: impl Into<String>) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_toggled(mut self, toggled: Toggled) -> Self {
+        self.toggled = Some(toggled);
+        self
+    }
+
+    #[must_use]
+    pub fn with_expanded(mut self, expanded: bool) -> Self {
+        self.expanded = Some(expanded);
+        self
+    }
+
+    #[must_use]
+    pub fn with_focus(mut self, focus: FocusId) -> Self {
+        self.focus = Some(focus);
+        self
+    }
+
+    #[must_use]
+    pub fn with_action(mut self, action: ActionKind) -> Self {
+        self.actions.push(action);
+        self
+    }
+}
+
+/// A described widget and its place in the tree.
+#[derive(Clone, Debug)]
+pub struct TreeNode {
+    pub node: AccessNode,
+    /// The widget's bounds, in window coordinates.
+    pub bounds: Rect,
+    pub parent: Option<usize>,
+    /// The indices of the node's children, in tree order.
+    pub children: Vec<usize>,
+}
+
+/// Set in the AccessKit IDs of widgets that can take the focus, which are
+/// made from their [`FocusId`] so that they stay the same from one update to
+/// the next. Other widgets are identified by their index in the tree.
+const FOCUSABLE: u64 = 1 << 63;
+
+/// A snapshot of the descriptions of every widget in a window. Nodes are
+/// stored in depth-first order, with the root window at index 0.
+#[derive(Clone, Debug)]
+pub struct AccessTree {
+    nodes: Vec<TreeNode>,
+}
+
+impl AccessTree {
+    /// Describes the widget tree with `root` at its root, for a window called
+    /// `title`. The widgets must have been laid out so that their bounds are
+    /// known.
+    #[must_use]
+    pub fn build(root: &dyn Widget, title: &str) -> Self {
+        let mut tree = Self {
+            nodes: vec![TreeNode {
+                node: AccessNode::new(Role::Window).with_name(title),
+                bounds: root.widget_state().rect(),
+                parent: None,
+                children: Vec::new(),
+            }],
+        };
+        tree.add(root, 0);
+        tree
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &TreeNode {
+        &self.nodes[0]
+    }
+
+    /// ## Panics
+    ///
+    /// Panics if `index >= len`.
+    #[must_use]
+    pub fn node(&self, index: usize) -> &TreeNode {
+        &self.nodes[index]
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Always false, since the tree has at least its root.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Finds the node of the widget identified by `focus`.
+    #[must_use]
+    pub fn find(&self, focus: FocusId) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|node| node.node.focus == Some(focus))
+    }
+
+    /// Converts the tree for AccessKit, which passes it on to the platform's
+    /// accessibility API. `focus` is the widget with the keyboard focus, or
+    /// `None` for the window itself. Every node is included, so the update
+    /// replaces the previous tree entirely.
+    ///
+    /// Bounds stay in logical pixels, and the window's node scales them to
+    /// the physical pixels that AccessKit expects by `scale_factor`.
+    #[must_use]
+    pub fn to_update(
+        &self,
+        focus: Option<FocusId>,
+        scale_factor: ScaleFactor,
+    ) -> accesskit::TreeUpdate {
+        let mut classes = accesskit::NodeClassSet::new();
+        let nodes = (0..self.nodes.len())
+            .map(|index| {
+                let mut node = self.to_node(index);
+                if index == 0 && scale_factor != ScaleFactor::ONE {
+                    node.set_transform(accesskit::Affine::scale(f64::from(scale_factor.0)));
+                }
+                (self.node_id(index), node.build(&mut classes))
+            })
+            .collect();
+
+        // Widgets that aren't in the tree can't be focused by assistive
+        // technology either.
+        let focus = focus.and_then(|focus| self.find(focus)).unwrap_or(0);
+
+        accesskit::TreeUpdate {
+            nodes,
+            tree: Some(accesskit::Tree::new(self.node_id(0))),
+            focus: self.node_id(focus),
+        }
+    }
+
+    /// Decodes a request that assistive technology made of a tree converted
+    /// with [`Self::to_update`]. Returns `None` if the target isn't a widget
+    /// that can take the focus, or if widgets don't support the action.
+    #[must_use]
+    pub fn request(&self, request: &accesskit::ActionRequest) -> Option<Request> {
+        let target = (0..self.nodes.len())
+            .find(|index| self.node_id(*index) == request.target)
+            .and_then(|index| self.nodes[index].node.focus)?;
+
+        match (request.action, &request.data) {
+            (accesskit::Action::Focus, _) => Some(Request::Focus(target)),
+            (accesskit::Action::Default, _) => Some(Request::Act(target, Action::Click)),
+            (accesskit::Action::SetValue, Some(accesskit::ActionData::Value(value))) => {
+                Some(Request::Act(target, Action::SetValue(value.to_string())))
+            }
+            _ => None,
+        }
+    }
+
+    fn node_id(&self, index: usize) -> accesskit::NodeId {
+        match self.nodes[index].node.focus {
+            Some(focus) => accesskit::NodeId(FOCUSABLE | focus.to_raw()),
+            None => accesskit::NodeId(index as u64),
+        }
+    }
+
+    fn to_node(&self, index: usize) -> accesskit::NodeBuilder {
+        let TreeNode {
+            node,
+            bounds,
+            children,
+            ..
+        } = &self.nodes[index];
+
+        let mut builder = accesskit::NodeBuilder::new(match node.role {
+            Role::Window => accesskit::Role::Window,
+            Role::Group => accesskit::Role::Group,
+            Role::Button => accesskit::Role::Button,
+            Role::CheckBox => accesskit::Role::CheckBox,
+            Role::RadioButton => accesskit::Role::RadioButton,
+            Role::Switch => accesskit::Role::Switch,
+            Role::TextInput => accesskit::Role::TextInput,
+            Role::MultilineTextInput => accesskit::Role::MultilineTextInput,
+            Role::ComboBox => accesskit::Role::ComboBox,
+            Role::Label => accesskit::Role::StaticText,
+        });
+
+        if let Some(name) = &node.name {
+            builder.set_name(name.as_str());
+        }
+        if let Some(value) = &node.value {
+            builder.set_value(value.as_str());
+        }
+        if let Some(toggled) = node.toggled {
+            builder.set_checked(match toggled {
+                Toggled::False => accesskit::Checked::False,
+                Toggled::True => accesskit::Checked::True,
+                Toggled::Mixed => accesskit::Checked::Mixed,
+            });
+        }
+        if let Some(expanded) = node.expanded {
+            builder.set_expanded(expanded);
+        }
+        if node.focus.is_some() {
+            builder.add_action(accesskit::Action::Focus);
+        }
+        for action in &node.actions {
+            builder.add_action(match action {
+                ActionKind::Click => accesskit::Action::Default,
+                ActionKind::SetValue => accesskit::Action::SetValue,
+            });
+        }
+
+        builder.set_bounds(accesskit::Rect::new(
+            f64::from(bounds.left.0),
+            f64::from(bounds.top.0),
+            f64::from(bounds.right.0),
+            f64::from(bounds.bottom.0),
+        ));
+        builder.set_children(
+            children
+                .iter()
+                .map(|child| self.node_id(*child))
+                .collect::<Vec<_>>(),
+        );
+        builder
+    }
+
+    fn add(&mut self, widget: &dyn Widget, parent: usize) {
+        let parent = match widget.accessibility() {
+            Some(node) => {
+                let index = self.nodes.len();
+                self.nodes.push(TreeNode {
+                    node,
+                    bounds: widget.widget_state().rect(),
+                    parent: Some(parent),
+                    children: Vec::new(),
+                });
+                self.nodes[parent].children.push(index);
+                index
+            }
+            None => parent,
+        };
+
+        widget.for_each_child(&mut |child| self.add(child, parent));
+    }
+}
+
+/// Performs `action` on the widget identified by `target`, if it is in the
+/// tree with `root` at its root and supports the action. Returns `true` if the
+/// action was performed.
+///
+/// An action may change widgets other than its target, such as when selecting
+/// a radio button deselects the others, so the whole window should be redrawn
+/// afterwards.
+pub fn perform_action(root: &mut dyn Widget, target: FocusId, action: &Action) -> bool {
+    let is_target = root
+        .accessibility()
+        .is_some_and(|node| node.focus == Some(target) && node.actions.contains(&action.kind()));
+
+    if is_target {
+        let post_update = root.accept_action(action);
+        let _ = mark_changed(root, post_update);
+        return true;
+    }
+
+    let mut performed = false;
+    root.for_each_child_mut(&mut |child| {
+        performed = performed || perform_action(child, target, action);
+    });
+    performed
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::geometry::{Extent, Offset, Point, Px},
+        gui::widgets::{
+            layout::Column, BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext,
+            WidgetState,
+        },
+    };
+
+    use super::*;
+
+    /// A button that counts how many times it was clicked.
+    struct Counter {
+        widget_state: WidgetState,
+        focus: FocusId,
+        clicks: u32,
+    }
+
+    impl Counter {
+        fn new() -> Self {
+            Self {
+                widget_state: WidgetState::default(),
+                focus: FocusId::new(),
+                clicks: 0,
+            }
+        }
+    }
+
+    impl Widget for Counter {
+        fn widget_state(&self) -> &WidgetState {
+            &self.widget_state
+        }
+
+        fn widget_state_mut(&mut self) -> &mut WidgetState {
+            &mut self.widget_state
+        }
+
+        fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+        fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+        fn accept_update(&mut self, _context: &mut UpdateContext) -> PostUpdate {
+            PostUpdate::NoChange
+        }
+
+        fn accept_layout(&mut self, _: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+            constraints.max_fit(Extent {
+                width: Px(20),
+                height: Px(10),
+            })
+        }
+
+        fn accept_draw(&self, _canvas: &mut DrawContext, _extent: Extent) {}
+
+        fn accessibility(&self) -> Option<AccessNode> {
+            Some(
+                AccessNode::new(Role::Button)
+                    .with_name("Count")
+                    .with_value(self.clicks.to_string())
+                    .with_focus(self.focus)
+                    .with_action(ActionKind::Click),
+            )
+        }
+
+        fn accept_action(&mut self, action: &Action) -> PostUpdate {
+            if *action == Action::Click {
+                self.clicks += 1;
+            }
+            PostUpdate::NeedsRedraw
+        }
+    }
+
+    #[test]
+    fn describe_and_act() {
+        let mut column = Column::new();
+        column.add(Counter::new());
+        column.add(Counter::new());
+
+        let mut context = LayoutContext::default();
+        let window = Extent {
+            width: Px(100),
+            height: Px(100),
+        };
+        let _ = context.begin(&mut column, window);
+
+        // The column only arranges the buttons, so they belong to the window.
+        let tree = AccessTree::build(&column, "Counters");
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.root().node.name.as_deref(), Some("Counters"));
+        assert_eq!(tree.root().children, [1, 2]);
+        assert_eq!(tree.node(2).parent, Some(0));
+        assert_eq!(
+            tree.node(2).bounds.top_left() - Point::zero(),
+            Offset {
+                x: Px(0),
+                y: Px(14),
+            }
+        );
+
+        let second = tree.node(2).node.focus.unwrap();
+        assert_eq!(tree.find(second), Some(2));
+
+        assert!(perform_action(&mut column, second, &Action::Click));
+        let tree = AccessTree::build(&column, "Counters");
+        assert_eq!(tree.node(1).node.value.as_deref(), Some("0"));
+        assert_eq!(tree.node(2).node.value.as_deref(), Some("1"));
+
+        // Unsupported actions are ignored.
+        assert!(!perform_action(
+            &mut column,
+            second,
+            &Action::SetValue("5".into())
+        ));
+    }
+
+    #[test]
+    fn convert_for_accesskit() {
+        let mut column = Column::new();
+        column.add(Counter::new());
+        column.add(Counter::new());
+
+        let mut context = LayoutContext::default();
+        let window = Extent {
+            width: Px(100),
+            height: Px(100),
+        };
+        let _ = context.begin(&mut column, window);
+
+        let tree = AccessTree::build(&column, "Counters");
+        let second = tree.node(2).node.focus.unwrap();
+        let update = tree.to_update(Some(second), ScaleFactor::ONE);
+
+        let root = update.tree.as_ref().unwrap().root;
+        let (_, window) = update.nodes.iter().find(|(id, _)| *id == root).unwrap();
+        assert_eq!(window.role(), accesskit::Role::Window);
+        assert_eq!(window.name(), Some("Counters"));
+        assert_eq!(window.children().len(), 2);
+
+        // The focused button keeps its ID from one update to the next.
+        let target = window.children()[1];
+        assert_eq!(update.focus, target);
+        assert_eq!(
+            AccessTree::build(&column, "Counters")
+                .to_update(None, ScaleFactor::ONE)
+                .nodes[2]
+                .0,
+            target
+        );
+
+        let (_, button) = update.nodes.iter().find(|(id, _)| *id == target).unwrap();
+        assert_eq!(button.role(), accesskit::Role::Button);
+        assert!(button.supports_action(accesskit::Action::Default));
+        assert_eq!(button.bounds().unwrap().y0, 14.0);
+
+        // Bounds are scaled to physical pixels by the window's node.
+        let scaled = tree.to_update(None, ScaleFactor(2.0));
+        assert_eq!(
+            scaled.nodes[0].1.transform(),
+            Some(&accesskit::Affine::scale(2.0))
+        );
+        assert_eq!(scaled.nodes[2].1.bounds(), button.bounds());
+
+        let request = |action, data| accesskit::ActionRequest {
+            action,
+            target,
+            data,
+        };
+        assert_eq!(
+            tree.request(&request(accesskit::Action::Default, None)),
+            Some(Request::Act(second, Action::Click))
+        );
+        assert_eq!(
+            tree.request(&request(accesskit::Action::Focus, None)),
+            Some(Request::Focus(second))
+        );
+        assert_eq!(
+            tree.request(&request(
+                accesskit::Action::SetValue,
+                Some(accesskit::ActionData::Value("5".into()))
+            )),
+            Some(Request::Act(second, Action::SetValue("5".into())))
+        );
+        assert_eq!(
+            tree.request(&request(accesskit::Action::ScrollIntoView, None)),
+            None
+        );
+
+        // The window can't be acted on.
+        assert_eq!(
+            tree.request(&accesskit::ActionRequest {
+                action: accesskit::Action::Default,
+                target: root,
+                data: None,
+            }),
+            None
+        );
+    }
+}
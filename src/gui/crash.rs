@@ -0,0 +1,321 @@
+//! Crash reports.
+//!
+//! Once installed with
+//! [`Application::report_crashes`](crate::application::Application::report_crashes),
+//! a panic on the UI thread writes a report to a file and tells the user about
+//! it in a native error dialog before the process exits, instead of the
+//! application just disappearing. The report has the panic's message and
+//! where it happened, along with what the application was doing beforehand:
+//!
+//! - The most recent events that each window received.
+//! - The graphics device's statistics for the last frame.
+//! - An outline of each window's widget tree, as it was when the window was
+//!   last drawn. This is updated at most once per [`SUMMARY_INTERVAL`], since
+//!   the tree can't be looked at while it is being changed.
+//!
+//! Panics on other threads, such as in background tasks, are left to the
+//! default hook since they don't take down the application.
+
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock, TryLockError,
+    },
+    thread::ThreadId,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{gfx::FrameStats, shell};
+
+use super::widgets::Widget;
+
+/// How many of the most recent events are kept for the report.
+pub const EVENT_LIMIT: usize = 32;
+
+/// The least time between outlines of a window's widget tree.
+pub const SUMMARY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the application was doing, in case it crashes.
+struct Record {
+    /// Where reports are written.
+    directory: Option<PathBuf>,
+    events: VecDeque<String>,
+    frame_stats: Option<FrameStats>,
+    /// The outline of each window's widget tree, and when it was made.
+    trees: Vec<(u64, Instant, String)>,
+}
+
+/// Set once a hook has been installed, so that nothing is recorded for
+/// applications that don't report crashes.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// The thread that the application runs on.
+static THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+static RECORD: Mutex<Record> = Mutex::new(Record {
+    directory: None,
+    events: VecDeque::new(),
+    frame_stats: None,
+    trees: Vec::new(),
+});
+
+/// Writes reports for panics on the current thread to `directory`, after the
+/// hook that was installed before prints the panic as usual.
+pub(crate) fn install(directory: PathBuf) {
+    let _ = THREAD.set(std::thread::current().id());
+    lock().directory = Some(directory);
+
+    if INSTALLED.swap(true, Ordering::AcqRel) {
+        // The hook is already installed, and uses the new directory.
+        return;
+    }
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+
+        if THREAD.get() != Some(&std::thread::current().id()) {
+            return;
+        }
+
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(message) => (*message).to_owned(),
+            None => match info.payload().downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "Box<dyn Any>".to_owned(),
+            },
+        };
+        let location = info.location().map_or_else(
+            || "an unknown location".to_owned(),
+            |location| {
+                format!(
+                    "{}:{}:{}",
+                    location.file(),
+                    location.line(),
+                    location.column()
+                )
+            },
+        );
+
+        // The record is still locked if recording is what panicked.
+        let record = match RECORD.try_lock() {
+            Ok(record) => Some(record),
+            Err(TryLockError::Poisoned(error)) => Some(error.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        };
+
+        let directory = record
+            .as_ref()
+            .and_then(|record| record.directory.clone())
+            .unwrap_or_else(std::env::temp_dir);
+        let report = report(record.as_deref(), &message, &location);
+        drop(record);
+
+        let saved = match write_report(&directory, &report) {
+            Ok(path) => format!("A crash report was saved to {}.", path.display()),
+            Err(error) => format!("The crash report could not be saved: {error}"),
+        };
+
+        shell::show_error_dialog(
+            "Application Error",
+            &format!(
+                "The application has stopped working because of an error.\n\n{message}\n\n{saved}"
+            ),
+        );
+    }));
+}
+
+/// Remembers an event that a window received.
+pub(crate) fn record_event(window: u64, event: &dyn std::fmt::Debug) {
+    if !INSTALLED.load(Ordering::Acquire) {
+        return;
+    }
+
+    let mut record = lock();
+    if record.events.len() == EVENT_LIMIT {
+        record.events.pop_front();
+    }
+    record
+        .events
+        .push_back(format!("window {window}: {event:?}"));
+}
+
+pub(crate) fn record_frame_stats(stats: FrameStats) {
+    if INSTALLED.load(Ordering::Acquire) {
+        lock().frame_stats = Some(stats);
+    }
+}
+
+/// Outlines a window's widget tree, unless it was done less than
+/// [`SUMMARY_INTERVAL`] before `now`.
+pub(crate) fn record_widget_tree(window: u64, root: &dyn Widget, now: Instant) {
+    if !INSTALLED.load(Ordering::Acquire) {
+        return;
+    }
+
+    let mut record = lock();
+    let index = record.trees.iter().position(|(id, _, _)| *id == window);
+    match index {
+        Some(index) => {
+            let (_, last, summary) = &mut record.trees[index];
+            if now.saturating_duration_since(*last) >= SUMMARY_INTERVAL {
+                *last = now;
+                *summary = summarize(root);
+            }
+        }
+        None => record.trees.push((window, now, summarize(root))),
+    }
+}
+
+/// Forgets a window that was closed.
+pub(crate) fn forget_window(window: u64) {
+    if INSTALLED.load(Ordering::Acquire) {
+        lock().trees.retain(|(id, _, _)| *id != window);
+    }
+}
+
+/// Locks the record, even if a panic poisoned it.
+fn lock() -> std::sync::MutexGuard<'static, Record> {
+    RECORD
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Describes each widget in the tree on its own line, indented under its
+/// parent, with its accessible role and name if it has them and where it is
+/// in the window.
+fn summarize(root: &dyn Widget) -> String {
+    fn visit(widget: &dyn Widget, depth: usize, out: &mut String) {
+        let _ = write!(out, "{:width$}", "", width = depth * 2);
+        match widget.accessibility() {
+            Some(node) => {
+                let _ = write!(out, "{:?}", node.role);
+                if let Some(name) = node.name {
+                    let _ = write!(out, " {name:?}");
+                }
+            }
+            None => out.push_str("Widget"),
+        }
+
+        let rect = widget.widget_state().rect();
+        let _ = writeln!(
+            out,
+            " at ({}, {}) to ({}, {})",
+            rect.left.0, rect.top.0, rect.right.0, rect.bottom.0
+        );
+
+        widget.for_each_child(&mut |child| visit(child, depth + 1, out));
+    }
+
+    let mut out = String::new();
+    visit(root, 0, &mut out);
+    out
+}
+
+fn report(record: Option<&Record>, message: &str, location: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "The application panicked at {location}:\n{message}\n");
+
+    let record = match record {
+        Some(record) => record,
+        None => {
+            out.push_str("Nothing else is known, since the panic happened while recording.\n");
+            return out;
+        }
+    };
+
+    match &record.frame_stats {
+        Some(stats) => {
+            let _ = writeln!(out, "Last frame: {stats:?}\n");
+        }
+        None => out.push_str("No frames were drawn.\n\n"),
+    }
+
+    out.push_str("Recent events, oldest first:\n");
+    for event in &record.events {
+        let _ = writeln!(out, "  {event}");
+    }
+
+    for (window, _, summary) in &record.trees {
+        let _ = writeln!(out, "\nWidgets in window {window}:");
+        for line in summary.lines() {
+            let _ = writeln!(out, "  {line}");
+        }
+    }
+
+    out
+}
+
+/// Writes the report to a new file in `directory`, named after the time.
+fn write_report(directory: &Path, report: &str) -> std::io::Result<PathBuf> {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    std::fs::create_dir_all(directory)?;
+    let path = directory.join(format!("crash-{time}.txt"));
+    std::fs::write(&path, report)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::{
+            color::Color,
+            geometry::{Extent, Px},
+        },
+        gui::{
+            testing::TestHarness,
+            widgets::{debug::Fill, layout::Column},
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn summarize_tree() {
+        let column = Column::new()
+            .with_child(Fill::new(Color::RED))
+            .with_child(Fill::new(Color::BLUE));
+        let mut harness = TestHarness::new(
+            column,
+            Extent {
+                width: Px(10),
+                height: Px(20),
+            },
+        );
+        let _ = harness.frame();
+
+        let summary = summarize(harness.root());
+        let lines = summary.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Widget at (0, 0)"));
+        assert!(lines[1].starts_with("  Widget"));
+        assert!(lines[2].starts_with("  Widget"));
+    }
+
+    #[test]
+    fn report_record() {
+        let mut record = Record {
+            directory: None,
+            events: VecDeque::new(),
+            frame_stats: Some(FrameStats::default()),
+            trees: vec![(
+                3,
+                Instant::now(),
+                "Button \"OK\" at (0, 0) to (4, 4)\n".to_owned(),
+            )],
+        };
+        record.events.push_back("window 3: Repaint".to_owned());
+
+        let report = report(Some(&record), "oops", "src/main.rs:1:2");
+        assert!(report.starts_with("The application panicked at src/main.rs:1:2:\noops\n"));
+        assert!(report.contains("\n  window 3: Repaint\n"));
+        assert!(report.contains("\nWidgets in window 3:\n  Button \"OK\" at (0, 0) to (4, 4)\n"));
+    }
+}
@@ -0,0 +1,312 @@
+//! Recognizes gestures such as taps and pinches from touch input.
+//!
+//! Each window has a [`GestureRecognizer`] that watches the touches reported
+//! by the shell and turns them into [`Gesture`]s, which are passed to the
+//! widgets as [`Event::Gesture`](super::input::Event::Gesture) after the touch
+//! itself. One finger taps, presses, or pans, and two fingers pinch. Touches
+//! beyond the second are ignored until every finger has been lifted.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    gfx::geometry::{Offset, Point, Px},
+    shell::event::TouchPhase,
+};
+
+/// How long a finger has to rest on the screen before it becomes a long
+/// press.
+pub const LONG_PRESS_DELAY: Duration = Duration::from_millis(500);
+
+/// How far a finger can move before a tap or long press becomes a pan, in
+/// pixels. Fingers are never entirely still.
+const SLOP: f32 = 10.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    /// A finger touched the screen and was lifted without moving.
+    Tap { position: Point },
+    /// A finger rested on the screen for [`LONG_PRESS_DELAY`] without moving.
+    LongPress { position: Point },
+    /// A finger moved by `delta` since the last pan, to `position`.
+    Pan { position: Point, delta: Offset },
+    /// Two fingers moved apart or together. `distance` is the distance between
+    /// them, and `previous` is what it was at the last pinch.
+    Pinch {
+        center: Point,
+        distance: Px,
+        previous: Px,
+    },
+}
+
+impl Gesture {
+    /// How much a pinch zooms in by, where values above 1 zoom in. Returns
+    /// `None` for other gestures.
+    #[must_use]
+    pub fn scale(&self) -> Option<f32> {
+        match self {
+            Gesture::Pinch {
+                distance, previous, ..
+            } if previous.0 > 0 => Some(f32::from(*distance) / f32::from(*previous)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Touch {
+    id: u32,
+    start: Point,
+    position: Point,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Idle,
+    /// One finger is down and hasn't moved, so it may still be a tap or a
+    /// long press.
+    Pending {
+        since: Instant,
+    },
+    Panning,
+    Pinching,
+    /// The gesture has ended, but fingers are still down.
+    Done,
+}
+
+/// Turns touches into gestures for one window.
+pub struct GestureRecognizer {
+    touches: Vec<Touch>,
+    mode: Mode,
+    long_press_delay: Duration,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GestureRecognizer {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            touches: Vec::new(),
+            mode: Mode::Idle,
+            long_press_delay: LONG_PRESS_DELAY,
+        }
+    }
+
+    #[must_use]
+    pub fn with_long_press_delay(mut self, delay: Duration) -> Self {
+        self.long_press_delay = delay;
+        self
+    }
+
+    /// Checks if a finger is resting on the screen, in which case
+    /// [`Self::tick`] needs to be called until it becomes a long press.
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        matches!(self.mode, Mode::Pending { .. })
+    }
+
+    /// Records a touch reported by the shell at `now`, returning the gesture
+    /// that it completes or continues, if any.
+    pub fn touch(
+        &mut self,
+        id: u32,
+        phase: TouchPhase,
+        position: Point,
+        now: Instant,
+    ) -> Option<Gesture> {
+        match phase {
+            TouchPhase::Started => {
+                self.touches.push(Touch {
+                    id,
+                    start: position,
+                    position,
+                });
+                self.mode = match (self.touches.len(), self.mode) {
+                    (1, _) => Mode::Pending { since: now },
+                    (2, Mode::Pending { .. } | Mode::Panning) => Mode::Pinching,
+                    _ => Mode::Done,
+                };
+                None
+            }
+            TouchPhase::Moved => {
+                let index = self.touches.iter().position(|touch| touch.id == id)?;
+                let before = self.touches[index].position;
+                self.touches[index].position = position;
+
+                match self.mode {
+                    Mode::Pending { .. } => {
+                        let start = self.touches[index].start;
+                        if distance(start, position) > SLOP {
+                            self.mode = Mode::Panning;
+                            Some(Gesture::Pan {
+                                position,
+                                delta: position - start,
+                            })
+                        } else {
+                            None
+                        }
+                    }
+                    Mode::Panning if position != before => Some(Gesture::Pan {
+                        position,
+                        delta: position - before,
+                    }),
+                    Mode::Pinching => {
+                        let other = self.touches[1 - index].position;
+                        let previous = Px::from(distance(before, other).round());
+                        let current = Px::from(distance(position, other).round());
+                        if current == previous {
+                            return None;
+                        }
+                        Some(Gesture::Pinch {
+                            center: Point::new(
+                                Px::from((f32::from(position.x) + f32::from(other.x)) / 2.0),
+                                Px::from((f32::from(position.y) + f32::from(other.y)) / 2.0),
+                            ),
+                            distance: current,
+                            previous,
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let index = self.touches.iter().position(|touch| touch.id == id)?;
+                let touch = self.touches.remove(index);
+
+                let gesture = match self.mode {
+                    Mode::Pending { .. } if phase == TouchPhase::Ended => Some(Gesture::Tap {
+                        position: touch.position,
+                    }),
+                    _ => None,
+                };
+
+                self.mode = if self.touches.is_empty() {
+                    Mode::Idle
+                } else {
+                    Mode::Done
+                };
+                gesture
+            }
+        }
+    }
+
+    /// Turns a finger that has been resting on the screen for long enough
+    /// into a long press.
+    pub fn tick(&mut self, now: Instant) -> Option<Gesture> {
+        match self.mode {
+            Mode::Pending { since } if now.duration_since(since) >= self.long_press_delay => {
+                self.mode = Mode::Done;
+                Some(Gesture::LongPress {
+                    position: self.touches[0].position,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    let Offset { x, y } = b - a;
+    f32::from(x).hypot(f32::from(y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: i16, y: i16) -> Point {
+        Point::new(Px(x), Px(y))
+    }
+
+    #[test]
+    fn tap_and_long_press() {
+        let mut gestures = GestureRecognizer::new();
+        let start = Instant::now();
+
+        // Small movements don't prevent a tap.
+        assert_eq!(
+            gestures.touch(1, TouchPhase::Started, point(10, 10), start),
+            None
+        );
+        assert_eq!(
+            gestures.touch(1, TouchPhase::Moved, point(13, 12), start),
+            None
+        );
+        assert_eq!(
+            gestures.touch(1, TouchPhase::Ended, point(13, 12), start),
+            Some(Gesture::Tap {
+                position: point(13, 12)
+            })
+        );
+
+        let _ = gestures.touch(2, TouchPhase::Started, point(10, 10), start);
+        assert!(gestures.is_pending());
+        assert_eq!(gestures.tick(start + LONG_PRESS_DELAY / 2), None);
+        assert_eq!(
+            gestures.tick(start + LONG_PRESS_DELAY),
+            Some(Gesture::LongPress {
+                position: point(10, 10)
+            })
+        );
+
+        // The press isn't also a tap.
+        assert!(!gestures.is_pending());
+        assert_eq!(
+            gestures.touch(2, TouchPhase::Ended, point(10, 10), start),
+            None
+        );
+    }
+
+    #[test]
+    fn pan_and_pinch() {
+        let mut gestures = GestureRecognizer::new();
+        let now = Instant::now();
+
+        let _ = gestures.touch(1, TouchPhase::Started, point(0, 0), now);
+        assert_eq!(
+            gestures.touch(1, TouchPhase::Moved, point(0, 20), now),
+            Some(Gesture::Pan {
+                position: point(0, 20),
+                delta: Offset {
+                    x: Px(0),
+                    y: Px(20)
+                },
+            })
+        );
+        assert_eq!(
+            gestures.touch(1, TouchPhase::Moved, point(5, 25), now),
+            Some(Gesture::Pan {
+                position: point(5, 25),
+                delta: Offset { x: Px(5), y: Px(5) },
+            })
+        );
+
+        // A second finger turns the pan into a pinch.
+        let _ = gestures.touch(2, TouchPhase::Started, point(5, 125), now);
+        let pinch = gestures.touch(2, TouchPhase::Moved, point(5, 225), now);
+        assert_eq!(
+            pinch,
+            Some(Gesture::Pinch {
+                center: point(5, 125),
+                distance: Px(200),
+                previous: Px(100),
+            })
+        );
+        assert_eq!(pinch.and_then(|pinch| pinch.scale()), Some(2.0));
+
+        // Lifting one finger ends the pinch without starting a pan.
+        let _ = gestures.touch(2, TouchPhase::Ended, point(5, 225), now);
+        assert_eq!(
+            gestures.touch(1, TouchPhase::Moved, point(50, 50), now),
+            None
+        );
+        assert_eq!(
+            gestures.touch(1, TouchPhase::Cancelled, point(50, 50), now),
+            None
+        );
+    }
+}
@@ -0,0 +1,369 @@
+//! Rebuilds a window's widget tree from a UI description on disk whenever the
+//! file changes, so that layouts can be tweaked without restarting the
+//! application.
+//!
+//! The description uses the same nodes as the [`ui!`](crate::ui) macro, with
+//! colors written in hexadecimal and widgets built by the application referred
+//! to by name:
+//!
+//! Widgets from [plugins](super::plugins) can be used by name too, once the
+//! registry is given to [`HotReload::with_plugins`].
+//!
+//! ```text
+//! // The editor is registered with `HotReload::with_widget("editor", ...)`.
+//! split_x [
+//!     column [ widget(editor) ],
+//!     tabs [ fill(#ff0000), fill(#0000ff80) ],
+//! ]
+//! ```
+//!
+//! Give the [`HotReload`] to a window through
+//! [`AppWindowConfig::hot_reload`](crate::application::AppWindowConfig). In
+//! debug builds, the window checks the file a few times a second and replaces
+//! its widget tree when the file changes, keeping the window itself and its
+//! swapchain. Release builds load the description once, when the window
+//! opens. If the description has an error, the previous tree is kept.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+use crate::{gfx::color::Color, text::TextStyle};
+
+use super::{
+    plugins::{PluginRegistry, Properties},
+    widgets::{
+        debug::Fill,
+        layout::{Center, Column},
+        split_panel::{Axis, SplitPanel},
+        tabbed_panel::TabbedPanel,
+        Widget,
+    },
+};
+
+/// How often a window checks whether its UI description has changed.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("could not read the UI description: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("line {line}: {message}")]
+    Syntax { line: usize, message: String },
+}
+
+/// Builds a widget tree from a UI description file. See the
+/// [module documentation](self) for the format.
+#[must_use]
+pub struct HotReload {
+    path: PathBuf,
+    #[allow(clippy::type_complexity)]
+    widgets: HashMap<String, Box<dyn Fn() -> Box<dyn Widget>>>,
+    plugins: Option<Rc<PluginRegistry>>,
+    error_style: Option<TextStyle>,
+    /// When the file was last modified as of the last poll, or `None` if it
+    /// couldn't be read.
+    modified: Option<SystemTime>,
+    /// Whether the file has been loaded at least once.
+    loaded: bool,
+}
+
+impl HotReload {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            widgets: HashMap::new(),
+            plugins: None,
+            error_style: None,
+            modified: None,
+            loaded: false,
+        }
+    }
+
+    /// Lets the description place a widget built by `build` with
+    /// `widget(name)`. The widget is built again every time the description
+    /// is reloaded.
+    pub fn with_widget(
+        mut self,
+        name: impl Into<String>,
+        build: impl Fn() -> Box<dyn Widget> + 'static,
+    ) -> Self {
+        self.widgets.insert(name.into(), Box::new(build));
+        self
+    }
+
+    /// Lets the description place the widgets registered by plugins with
+    /// `widget(name)`. Widgets registered with [`Self::with_widget`] take
+    /// precedence.
+    pub fn with_plugins(mut self, plugins: Rc<PluginRegistry>) -> Self {
+        self.plugins = Some(plugins);
+        self
+    }
+
+    /// Shows errors in the description as toasts in this style. Without one,
+    /// errors only keep the previous tree.
+    pub fn with_error_style(mut self, style: TextStyle) -> Self {
+        self.error_style = Some(style);
+        self
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[must_use]
+    pub fn error_style(&self) -> Option<TextStyle> {
+        self.error_style
+    }
+
+    /// Loads the description again if it changed since the last poll, or if
+    /// it hasn't been loaded yet. Returns `None` if it didn't change.
+    pub fn poll(&mut self) -> Option<Result<Box<dyn Widget>, Error>> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        if self.loaded && modified == self.modified {
+            return None;
+        }
+
+        self.loaded = true;
+        self.modified = modified;
+
+        Some(
+            std::fs::read_to_string(&self.path)
+                .map_err(Error::from)
+                .and_then(|source| self.build(&source)),
+        )
+    }
+
+    /// Builds a widget tree from a description.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::Syntax`] with the line of the first error if the
+    /// description isn't a single, valid widget tree.
+    pub fn build(&self, source: &str) -> Result<Box<dyn Widget>, Error> {
+        let mut parser = Parser {
+            source,
+            position: 0,
+            widgets: &self.widgets,
+            plugins: self.plugins.as_deref(),
+        };
+
+        let tree = parser.node()?;
+        parser.skip_whitespace();
+        if parser.position < source.len() {
+            return Err(parser.error("expected the end of the description"));
+        }
+
+        Ok(tree)
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    /// The byte offset of the next character to parse.
+    position: usize,
+    #[allow(clippy::type_complexity)]
+    widgets: &'a HashMap<String, Box<dyn Fn() -> Box<dyn Widget>>>,
+    plugins: Option<&'a PluginRegistry>,
+}
+
+impl<'a> Parser<'a> {
+    fn node(&mut self) -> Result<Box<dyn Widget>, Error> {
+        let kind = self.identifier()?;
+        match kind {
+            "column" => Ok(Box::new(Column::with_children(self.children()?))),
+            "center" => {
+                let mut children = self.children()?;
+                match children.pop() {
+                    Some(child) if children.is_empty() => Ok(Box::new(Center::new(child))),
+                    _ => Err(self.error("`center` takes exactly one child")),
+                }
+            }
+            "split_x" => Ok(Box::new(SplitPanel::with_children(
+                Axis::X,
+                self.children()?,
+            ))),
+            "split_y" => Ok(Box::new(SplitPanel::with_children(
+                Axis::Y,
+                self.children()?,
+            ))),
+            "tabs" => Ok(Box::new(TabbedPanel::with_children(self.children()?))),
+            "fill" => {
+                let argument = self.argument()?;
+                match Color::from_hex(argument) {
+                    Some(color) => Ok(Box::new(Fill::new(color))),
+                    None => Err(self.error(format!("`{argument}` is not a color"))),
+                }
+            }
+            "widget" => {
+                let name = self.argument()?;
+                match (self.widgets.get(name), self.plugins) {
+                    (Some(build), _) => Ok(build()),
+                    (None, Some(plugins)) => plugins
+                        .create_widget(name, &Properties::new())
+                        .map_err(|error| self.error(error.to_string())),
+                    (None, None) => Err(self.error(format!("no widget is registered as `{name}`"))),
+                }
+            }
+            _ => Err(self.error(format!("unknown widget `{kind}`"))),
+        }
+    }
+
+    /// Parses a list of nodes in square brackets, separated by commas.
+    fn children(&mut self) -> Result<Vec<Box<dyn Widget>>, Error> {
+        self.expect('[')?;
+
+        let mut children = Vec::new();
+        loop {
+            if self.eat(']') {
+                return Ok(children);
+            }
+
+            children.push(self.node()?);
+
+            if !self.eat(',') {
+                self.expect(']')?;
+                return Ok(children);
+            }
+        }
+    }
+
+    /// Parses the text between parentheses.
+    fn argument(&mut self) -> Result<&'a str, Error> {
+        self.expect('(')?;
+        let rest = &self.source[self.position..];
+        match rest.find(')') {
+            Some(end) => {
+                self.position += end + 1;
+                Ok(rest[..end].trim())
+            }
+            None => Err(self.error("expected `)`")),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<&'a str, Error> {
+        self.skip_whitespace();
+        let rest = &self.source[self.position..];
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+
+        if len == 0 {
+            return Err(self.error("expected a widget"));
+        }
+
+        self.position += len;
+        Ok(&rest[..len])
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{c}`")))
+        }
+    }
+
+    /// Skips past `c` if it's the next character that isn't whitespace.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.source[self.position..].starts_with(c) {
+            self.position += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skips whitespace and `//` comments.
+    fn skip_whitespace(&mut self) {
+        loop {
+            let rest = &self.source[self.position..];
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+
+            if trimmed.starts_with("//") {
+                self.position += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::Syntax {
+            line: self.source[..self.position].lines().count().max(1),
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(widget: &mut dyn Widget) -> usize {
+        let mut total = 1;
+        widget.for_each_child_mut(&mut |child| total += count(child));
+        total
+    }
+
+    #[test]
+    fn build_tree() {
+        let reload =
+            HotReload::new("unused.ui").with_widget("white", || Box::new(Fill::new(Color::WHITE)));
+
+        let mut tree = reload
+            .build(
+                "// A comment.
+                split_x [
+                    column [ fill(#ff0000), fill(#0000ff80) ],
+                    split_y [
+                        tabs [ fill(#ffffff), fill(#000000), ],
+                        center [ widget(white) ],
+                    ],
+                ]",
+            )
+            .unwrap();
+
+        assert_eq!(count(tree.as_mut()), 10);
+    }
+
+    #[test]
+    fn report_errors() {
+        let reload = HotReload::new("unused.ui");
+
+        let line = |source: &str| match reload.build(source) {
+            Err(Error::Syntax { line, .. }) => line,
+            _ => panic!("expected a syntax error in {source:?}"),
+        };
+
+        assert_eq!(line("column [\n  fill(red)\n]"), 2);
+        assert_eq!(line("column [ widget(editor) ]"), 1);
+        assert_eq!(line("center [ fill(#ffffff), fill(#000000) ]"), 1);
+        assert_eq!(line("column [\n\n  button []\n]"), 3);
+        assert_eq!(line("column [] column []"), 1);
+    }
+
+    #[test]
+    fn poll_for_changes() {
+        let path =
+            std::env::temp_dir().join(format!("fathom-hot-reload-{}.ui", std::process::id()));
+        std::fs::write(&path, "fill(#ff0000)").unwrap();
+
+        let mut reload = HotReload::new(&path);
+        assert!(matches!(reload.poll(), Some(Ok(_))));
+        assert!(reload.poll().is_none());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(reload.poll(), Some(Err(Error::Io(_)))));
+        assert!(reload.poll().is_none());
+    }
+}
@@ -0,0 +1,189 @@
+//! Images shared between widgets and the graphics device.
+//!
+//! Widgets don't have access to the graphics device, so images that they
+//! draw are registered with an [`Images`] store instead. Pixel buffers are
+//! queued for upload, and images that are no longer needed are queued for
+//! destruction. The application carries out both before drawing each frame by
+//! calling [`Images::flush`].
+//!
+//! Command lists can also be queued to be drawn to new images. These are drawn
+//! by [`Images::flush_renders`] once the frame has been recorded, so that the
+//! glyphs that they draw are in the glyph atlas.
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{
+    gfx::{
+        geometry::{Extent, Offset, Point, Rect},
+        pixel_buffer::{Layout, PixelBuffer},
+        DrawCommandList, Error, GfxDevice, Image, ImageCopy, RenderTarget,
+    },
+    handle_pool::Handle,
+};
+
+/// Identifies an image registered with an [`Images`] store.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ImageId(u64);
+
+#[derive(Default)]
+struct Store {
+    next_id: u64,
+    /// Pixel buffers waiting to be uploaded.
+    pending: Vec<(ImageId, PixelBuffer)>,
+    /// Command lists waiting to be drawn to images of the given size.
+    renders: Vec<(ImageId, Extent, DrawCommandList)>,
+    uploaded: HashMap<ImageId, Handle<Image>>,
+    /// Images waiting to be destroyed.
+    released: Vec<Handle<Image>>,
+}
+
+impl Store {
+    fn next_id(&mut self) -> ImageId {
+        let id = ImageId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+}
+
+/// A shared store of images that are uploaded and destroyed between frames.
+/// Clones refer to the same store.
+#[derive(Clone, Default)]
+pub struct Images {
+    store: Rc<RefCell<Store>>,
+}
+
+impl Images {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues `pixels` to be uploaded before the next frame is drawn.
+    pub fn upload(&self, pixels: PixelBuffer) -> ImageId {
+        let mut store = self.store.borrow_mut();
+        let id = store.next_id();
+        store.pending.push((id, pixels));
+        id
+    }
+
+    /// Queues `commands` to be drawn to a new image of `extent` when
+    /// [`Self::flush_renders`] is next called. See [`GfxDevice::draw`].
+    pub fn render(&self, extent: Extent, commands: DrawCommandList) -> ImageId {
+        let mut store = self.store.borrow_mut();
+        let id = store.next_id();
+        store.renders.push((id, extent, commands));
+        id
+    }
+
+    /// Takes ownership of an image that has already been created, so that it
+    /// is destroyed when it is released.
+    pub fn adopt(&self, handle: Handle<Image>) -> ImageId {
+        let mut store = self.store.borrow_mut();
+        let id = store.next_id();
+        store.uploaded.insert(id, handle);
+        id
+    }
+
+    /// The image identified by `id`. Returns `None` if it hasn't been
+    /// uploaded or drawn yet, couldn't be drawn, or has been released.
+    #[must_use]
+    pub fn handle(&self, id: ImageId) -> Option<Handle<Image>> {
+        self.store.borrow().uploaded.get(&id).copied()
+    }
+
+    /// Queues the image identified by `id` to be destroyed before the next
+    /// frame is drawn. If it hasn't been uploaded yet, it never will be.
+    pub fn release(&self, id: ImageId) {
+        let mut store = self.store.borrow_mut();
+        if let Some(handle) = store.uploaded.remove(&id) {
+            store.released.push(handle);
+        } else {
+            store.pending.retain(|(pending, _)| *pending != id);
+            store.renders.retain(|(pending, _, _)| *pending != id);
+        }
+    }
+
+    /// Destroys the released images and uploads the pending ones.
+    ///
+    /// This must be called between frames, once the previous frame has been
+    /// presented, so that the released images aren't still in use.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an image could not be created or destroyed. Images
+    /// that were not uploaded stay pending.
+    pub fn flush(&self, gfx: &dyn GfxDevice) -> Result<(), Error> {
+        let mut store = self.store.borrow_mut();
+
+        for handle in store.released.drain(..) {
+            gfx.destroy_image(handle)?;
+        }
+
+        while let Some((id, pixels)) = store.pending.pop() {
+            let handle = match gfx.create_image(pixels.extent(), pixels.layout()) {
+                Ok(handle) => handle,
+                Err(error) => {
+                    store.pending.push((id, pixels));
+                    return Err(error);
+                }
+            };
+
+            store.uploaded.insert(id, handle);
+            gfx.copy_pixels(
+                pixels.view(),
+                handle,
+                &[ImageCopy {
+                    src_rect: Rect::new(Point::zero(), pixels.extent()),
+                    dst_location: Offset::zero(),
+                }],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Draws the command lists queued by [`Self::render`] to new images.
+    ///
+    /// This must be called after the glyph atlas has been flushed for the
+    /// frame in which the command lists were recorded, so that the glyphs they
+    /// draw are in the atlas.
+    ///
+    /// ## Errors
+    ///
+    /// Returns the first error if an image could not be created or drawn to.
+    /// The other lists are still drawn. Lists that could not be drawn are
+    /// dropped, so their images are never created and whoever queued them
+    /// has to draw without them.
+    pub fn flush_renders(&self, gfx: &dyn GfxDevice) -> Result<(), Error> {
+        let mut store = self.store.borrow_mut();
+        let mut result = Ok(());
+
+        while let Some((id, extent, commands)) = store.renders.pop() {
+            match Self::draw_to_image(gfx, extent, &commands) {
+                Ok(handle) => {
+                    store.uploaded.insert(id, handle);
+                }
+                Err(error) => {
+                    if result.is_ok() {
+                        result = Err(error);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    fn draw_to_image(
+        gfx: &dyn GfxDevice,
+        extent: Extent,
+        commands: &DrawCommandList,
+    ) -> Result<Handle<Image>, Error> {
+        let handle = gfx.create_image(extent, Layout::RGBA8)?;
+        if let Err(error) = gfx.draw(RenderTarget::Image(handle), commands) {
+            gfx.destroy_image(handle)?;
+            return Err(error);
+        }
+        Ok(handle)
+    }
+}
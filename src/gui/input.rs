@@ -1,4 +1,16 @@
-use crate::gfx::geometry::Point;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{
+    gfx::geometry::{Offset, Point, Px, Rect},
+    gui::gestures::Gesture,
+    shell::{
+        event::{Key, Modifiers, TouchPhase, WHEEL_DELTA},
+        Composition, FullscreenMode,
+    },
+};
+
+/// How far one notch of the mouse wheel scrolls, in pixels.
+const SCROLL_PER_NOTCH: i32 = 48;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u8)]
@@ -59,6 +71,68 @@ pub enum Event {
         button: MouseButton,
         state: ButtonState,
     },
+    /// The user asked to scroll by `delta` pixels. Positive values move
+    /// toward the end of the content, down and to the right. `precise` is
+    /// `true` if the delta came from a device that scrolls smoothly, such as a
+    /// trackpad, rather than a wheel that turns in notches.
+    Scroll {
+        delta: Offset,
+        precise: bool,
+    },
+    KeyPress {
+        key: Key,
+        modifiers: Modifiers,
+    },
+    /// A character was typed. See [`crate::shell::event::Window::CharacterReceived`].
+    Text {
+        character: char,
+    },
+    /// An input method started, changed, or stopped composing text. See
+    /// [`Input::composition`].
+    Composition,
+    /// See [`crate::shell::event::Window::Touch`].
+    Touch {
+        id: u32,
+        phase: TouchPhase,
+        position: Point,
+    },
+    /// A gesture recognized from the touches before it. See
+    /// [`gestures`](crate::gui::gestures).
+    Gesture {
+        gesture: Gesture,
+    },
+    /// See [`crate::shell::event::Window::RawMouseMotion`]. Only reported
+    /// while a widget has asked for it with [`Input::set_raw_mouse_input`].
+    RawMouseMotion {
+        x: i32,
+        y: i32,
+    },
+}
+
+/// Identifies a widget that can receive keyboard input. Only the widget with
+/// the keyboard focus should respond to key presses and text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FocusId(u64);
+
+impl FocusId {
+    /// Creates a new identifier, distinct from every other.
+    #[must_use]
+    pub fn new() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// The identifier as a number, which is never reused while the program
+    /// runs.
+    pub(crate) fn to_raw(self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for FocusId {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
@@ -69,7 +143,16 @@ pub struct Input {
     tick: Tick,
     cursor_position: (Point, Tick),
     mouse_buttons: [(ButtonState, Tick); 3],
+    /// The parts of a pixel that the mouse wheel has scrolled by that weren't
+    /// reported yet, in the units of [`WHEEL_DELTA`].
+    wheel_remainder: (i32, i32),
     event: Event,
+    focus: Option<FocusId>,
+    composition: Option<Composition>,
+    raw_mouse_input: bool,
+    cursor_confinement: Option<Rect>,
+    cursor_hidden: bool,
+    fullscreen: FullscreenMode,
 }
 
 impl Input {
@@ -112,8 +195,136 @@ impl Input {
         self.mouse_buttons[button as usize].0
     }
 
+    /// Pressing a mouse button also clears the keyboard focus, so that the
+    /// widget under the cursor can take it when the event is handled.
     pub fn update_mouse_button(&mut self, button: MouseButton, state: ButtonState) {
         self.mouse_buttons[button as usize] = (state, self.tick);
         self.event = Event::MouseButton { button, state };
+
+        if state.is_pressed() {
+            self.focus = None;
+        }
+    }
+
+    /// Records a turn of the mouse wheel, in the units of
+    /// [`crate::shell::event::Window::MouseWheel`]. Distances that don't make
+    /// up a whole pixel are carried over to the next turn, so that devices
+    /// that report very small distances still scroll.
+    pub fn update_wheel(&mut self, x: i16, y: i16) {
+        let pixels = |notches: i16, remainder: &mut i32| {
+            let units = i32::from(notches) * SCROLL_PER_NOTCH + *remainder;
+            *remainder = units % i32::from(WHEEL_DELTA);
+            let distance = units / i32::from(WHEEL_DELTA);
+            Px::try_from(distance).unwrap_or(if distance < 0 { Px::MIN } else { Px::MAX })
+        };
+
+        // Turning the wheel away from the user scrolls up, toward the start.
+        let (mut remainder_x, mut remainder_y) = self.wheel_remainder;
+        let delta = Offset {
+            x: pixels(x, &mut remainder_x),
+            y: pixels(y.saturating_neg(), &mut remainder_y),
+        };
+        self.wheel_remainder = (remainder_x, remainder_y);
+
+        self.event = Event::Scroll {
+            delta,
+            precise: x % WHEEL_DELTA != 0 || y % WHEEL_DELTA != 0,
+        };
+    }
+
+    pub fn update_key(&mut self, key: Key, modifiers: Modifiers) {
+        self.event = Event::KeyPress { key, modifiers };
+    }
+
+    pub fn update_text(&mut self, character: char) {
+        self.event = Event::Text { character };
+    }
+
+    pub fn update_touch(&mut self, id: u32, phase: TouchPhase, position: Point) {
+        self.event = Event::Touch {
+            id,
+            phase,
+            position,
+        };
+    }
+
+    pub fn update_gesture(&mut self, gesture: Gesture) {
+        self.event = Event::Gesture { gesture };
+    }
+
+    pub fn update_raw_mouse_motion(&mut self, x: i32, y: i32) {
+        self.event = Event::RawMouseMotion { x, y };
+    }
+
+    /// Checks if a widget has asked for [`Event::RawMouseMotion`] events.
+    #[must_use]
+    pub fn wants_raw_mouse_input(&self) -> bool {
+        self.raw_mouse_input
+    }
+
+    /// Asks for [`Event::RawMouseMotion`] events, such as while dragging
+    /// something that needs more precision than the cursor gives. Turn them
+    /// off again once they aren't needed.
+    pub fn set_raw_mouse_input(&mut self, enabled: bool) {
+        self.raw_mouse_input = enabled;
+    }
+
+    /// The area of the window that a widget has confined the cursor to, if
+    /// any.
+    #[must_use]
+    pub fn cursor_confinement(&self) -> Option<Rect> {
+        self.cursor_confinement
+    }
+
+    /// Keeps the cursor within `area` of the window, in absolute coordinates,
+    /// or lets it move freely again if `area` is `None`.
+    pub fn confine_cursor(&mut self, area: Option<Rect>) {
+        self.cursor_confinement = area;
+    }
+
+    /// Checks if a widget has hidden the cursor.
+    #[must_use]
+    pub fn is_cursor_hidden(&self) -> bool {
+        self.cursor_hidden
+    }
+
+    /// Shows or hides the cursor while the mouse is over the window, such as
+    /// while the mouse rotates a view instead of pointing at something.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_hidden = !visible;
+    }
+
+    /// How the window covers the screen, as last set by a widget.
+    #[must_use]
+    pub fn fullscreen(&self) -> FullscreenMode {
+        self.fullscreen
+    }
+
+    /// Makes the window fullscreen or windowed.
+    pub fn set_fullscreen(&mut self, mode: FullscreenMode) {
+        self.fullscreen = mode;
+    }
+
+    /// The text that an input method is composing, or `None` if it isn't
+    /// composing. The composed text isn't part of the text being edited until
+    /// it is committed, which is reported as [`Event::Text`] events.
+    #[must_use]
+    pub fn composition(&self) -> Option<&Composition> {
+        self.composition.as_ref()
+    }
+
+    pub fn update_composition(&mut self, composition: Option<Composition>) {
+        self.composition = composition;
+        self.event = Event::Composition;
+    }
+
+    /// The widget that has the keyboard focus, if any.
+    #[must_use]
+    pub fn focus(&self) -> Option<FocusId> {
+        self.focus
+    }
+
+    pub fn set_focus(&mut self, focus: Option<FocusId>) {
+        self.focus = focus;
     }
 }
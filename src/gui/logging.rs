@@ -0,0 +1,349 @@
+//! Logs that can be shown inside the application.
+//!
+//! A [`Log`] keeps the most recent messages written to it from any thread, for
+//! a [`LogView`](super::widgets::log_view::LogView) to show. This lets
+//! deployed applications be debugged without a terminal attached to them.
+//!
+//! Messages written from other threads are shown once the window is next
+//! drawn. Give the log an [`EventLoopProxy`] with
+//! [`Log::with_event_loop_proxy`] to wake the application when they arrive,
+//! even if nothing else is happening:
+//!
+//! ```ignore
+//! let log = Log::new().with_event_loop_proxy(app.event_loop_proxy());
+//! let view = LogView::new(log.clone(), style);
+//! ```
+//!
+//! With the `tracing` feature, a log is also a [`tracing_subscriber::Layer`]
+//! that records every event passed to it:
+//!
+//! ```ignore
+//! tracing_subscriber::registry().with(log.clone()).init();
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::shell::EventLoopProxy;
+
+/// How many messages a log keeps unless told otherwise. The oldest messages
+/// are forgotten first.
+pub const DEFAULT_LIMIT: usize = 1000;
+
+/// How important a message is, from most to least.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+            Self::Trace => "TRACE",
+        })
+    }
+}
+
+/// A message written to a [`Log`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    /// The time since the log was created.
+    pub time: Duration,
+    pub level: Level,
+    /// Where the message came from, usually the path of the module that
+    /// wrote it.
+    pub target: String,
+    pub message: String,
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>10.3} {:<5} {}: {}",
+            self.time.as_secs_f64(),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+/// Chooses which messages are shown.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogFilter {
+    /// The least important level shown.
+    pub level: Level,
+    /// Only shows messages whose target starts with this, such as a crate's
+    /// name, or every message if it is `None`.
+    pub target: Option<String>,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            level: Level::Info,
+            target: None,
+        }
+    }
+}
+
+impl LogFilter {
+    #[must_use]
+    pub fn matches(&self, record: &Record) -> bool {
+        record.level <= self.level
+            && self
+                .target
+                .as_ref()
+                .is_none_or(|target| record.target.starts_with(target.as_str()))
+    }
+}
+
+/// Sent to the event loop when messages are written to a log that has an
+/// [`EventLoopProxy`].
+pub(crate) struct Wake(Log);
+
+impl Wake {
+    /// Lets the log wake the event loop again for the next message.
+    pub fn acknowledge(self) {
+        self.0.lock().wake_pending = false;
+    }
+}
+
+struct Inner {
+    start: Instant,
+    records: VecDeque<Record>,
+    limit: usize,
+    /// Changes whenever a message is written or the log is cleared.
+    version: u64,
+    proxy: Option<EventLoopProxy>,
+    /// Set while a [`Wake`] is waiting to be handled, so that a burst of
+    /// messages only wakes the event loop once.
+    wake_pending: bool,
+}
+
+/// The most recent messages written by the application. Clones refer to the
+/// same messages, and can be sent to other threads.
+#[derive(Clone)]
+pub struct Log {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Default for Log {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Log {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_limit(DEFAULT_LIMIT)
+    }
+
+    /// Creates a log that keeps at most `limit` messages.
+    #[must_use]
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                start: Instant::now(),
+                records: VecDeque::new(),
+                limit,
+                version: 0,
+                proxy: None,
+                wake_pending: false,
+            })),
+        }
+    }
+
+    /// Wakes the event loop through `proxy` when a message is written, so that
+    /// views of the log are updated without waiting for something else to
+    /// redraw the window.
+    #[must_use]
+    pub fn with_event_loop_proxy(self, proxy: EventLoopProxy) -> Self {
+        self.lock().proxy = Some(proxy);
+        self
+    }
+
+    /// Writes a message, forgetting the oldest one if the log is full.
+    pub fn push(&self, level: Level, target: &str, message: impl Into<String>) {
+        let mut inner = self.lock();
+        let record = Record {
+            time: inner.start.elapsed(),
+            level,
+            target: target.to_owned(),
+            message: message.into(),
+        };
+
+        if inner.records.len() >= inner.limit {
+            inner.records.pop_front();
+        }
+        if inner.limit > 0 {
+            inner.records.push_back(record);
+        }
+        inner.version += 1;
+
+        if !inner.wake_pending {
+            // Sending fails once the application has exited, when nothing is
+            // left to show the message.
+            let sent = inner
+                .proxy
+                .as_ref()
+                .is_some_and(|proxy| proxy.send(Wake(self.clone())).is_ok());
+            inner.wake_pending = sent;
+        }
+    }
+
+    /// Identifies the messages in the log. This is different after every
+    /// change.
+    #[must_use]
+    pub fn version(&self) -> u64 {
+        self.lock().version
+    }
+
+    /// Copies the messages that `filter` matches, oldest first.
+    #[must_use]
+    pub fn records(&self, filter: &LogFilter) -> Vec<Record> {
+        self.lock()
+            .records
+            .iter()
+            .filter(|record| filter.matches(record))
+            .cloned()
+            .collect()
+    }
+
+    /// Forgets every message.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.records.clear();
+        inner.version += 1;
+    }
+
+    /// Locks the log, even if a thread panicked while writing to it.
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl From<tracing::Level> for Level {
+    fn from(level: tracing::Level) -> Self {
+        match level {
+            tracing::Level::ERROR => Self::Error,
+            tracing::Level::WARN => Self::Warn,
+            tracing::Level::INFO => Self::Info,
+            tracing::Level::DEBUG => Self::Debug,
+            _ => Self::Trace,
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for Log {
+    fn on_event(
+        &self,
+        event: &tracing::Event<'_>,
+        _context: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        /// Writes the event's message, followed by its other fields as
+        /// `name=value` pairs.
+        #[derive(Default)]
+        struct Message {
+            message: String,
+            fields: String,
+        }
+
+        impl tracing::field::Visit for Message {
+            fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+                if field.name() == "message" {
+                    self.message.push_str(value);
+                } else {
+                    self.record_debug(field, &value);
+                }
+            }
+
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+                use std::fmt::Write;
+
+                if field.name() == "message" {
+                    let _ = write!(self.message, "{value:?}");
+                } else {
+                    let _ = write!(self.fields, " {}={value:?}", field.name());
+                }
+            }
+        }
+
+        let mut message = Message::default();
+        event.record(&mut message);
+        message.message.push_str(&message.fields);
+
+        let metadata = event.metadata();
+        self.push(
+            Level::from(*metadata.level()),
+            metadata.target(),
+            message.message,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_records() {
+        let log = Log::new();
+        log.push(Level::Error, "app::net", "connection lost");
+        log.push(Level::Debug, "app::net", "retrying");
+        log.push(Level::Info, "fathom::gfx", "device created");
+
+        let messages = |filter: &LogFilter| {
+            log.records(filter)
+                .into_iter()
+                .map(|record| record.message)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            messages(&LogFilter::default()),
+            ["connection lost", "device created"]
+        );
+        assert_eq!(
+            messages(&LogFilter {
+                level: Level::Trace,
+                target: Some("app".to_owned()),
+            }),
+            ["connection lost", "retrying"]
+        );
+    }
+
+    #[test]
+    fn forget_old_records() {
+        let log = Log::with_limit(2);
+        let version = log.version();
+        for i in 0..3 {
+            log.push(Level::Info, "app", i.to_string());
+        }
+        assert_ne!(log.version(), version);
+
+        let records = log.records(&LogFilter::default());
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].message, "1");
+        assert!(records[1].to_string().ends_with(" INFO  app: 2"));
+
+        log.clear();
+        assert!(log.records(&LogFilter::default()).is_empty());
+    }
+}
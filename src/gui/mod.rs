@@ -1,2 +1,22 @@
+pub mod accessibility;
+pub mod crash;
+pub mod gestures;
+pub mod hot_reload;
+pub mod images;
 pub mod input;
+pub mod logging;
+pub mod notifications;
+pub mod performance;
+pub mod plugins;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod settings;
+pub mod state;
+pub mod tasks;
+pub mod testing;
+pub mod ticker;
+pub mod tooltip;
+pub mod undo;
 pub mod widgets;
+
+pub use widgets::hit_test::hit_test;
@@ -0,0 +1,414 @@
+//! Transient notifications, or toasts, shown in the corner of every window.
+//!
+//! Toasts are queued through a [`Notifications`] handle, which the application
+//! shares with every window. Each window shows the oldest few toasts stacked
+//! up from its bottom-right corner, above everything else in the window.
+//! Toasts disappear once their duration has passed, and clicking one runs its
+//! action and dismisses it.
+
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    text::{ShapedLine, TextStyle},
+};
+
+use super::widgets::{DrawContext, LayoutContext};
+
+/// How long a toast is shown for unless another duration is given.
+pub const DEFAULT_DURATION: Duration = Duration::from_secs(5);
+
+/// The most toasts shown at once. The rest wait until one of them is
+/// dismissed.
+const MAX_VISIBLE: usize = 4;
+
+const TOAST_WIDTH: Px = Px(280);
+
+/// The space between toasts, and between the toasts and the window's edges.
+const MARGIN: Px = Px(12);
+
+/// The space between the edge of a toast and its text.
+const PADDING: Px = Px(8);
+
+/// The width of the colored bar along the left edge of a toast.
+const SEVERITY_BAR: Px = Px(4);
+
+const BACKGROUND: Color = Color::WHITE;
+
+const BORDER: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+/// How important a toast is, which determines its color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn color(self) -> Color {
+        match self {
+            Self::Info => Color {
+                r: 0.0,
+                g: 0.4,
+                b: 0.9,
+                a: 1.0,
+            },
+            Self::Success => Color {
+                r: 0.1,
+                g: 0.6,
+                b: 0.2,
+                a: 1.0,
+            },
+            Self::Warning => Color {
+                r: 0.9,
+                g: 0.6,
+                b: 0.0,
+                a: 1.0,
+            },
+            Self::Error => Color {
+                r: 0.8,
+                g: 0.1,
+                b: 0.1,
+                a: 1.0,
+            },
+        }
+    }
+}
+
+/// Identifies a toast that has been queued.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ToastId(u64);
+
+/// A message shown briefly in the corner of every window.
+#[must_use]
+pub struct Toast {
+    pub severity: Severity,
+    pub text: String,
+    pub style: TextStyle,
+    /// How long the toast is shown before it is dismissed. If `None`, it stays
+    /// until it is clicked.
+    pub duration: Option<Duration>,
+    on_click: Option<Box<dyn FnMut()>>,
+}
+
+impl Toast {
+    pub fn new(severity: Severity, text: impl Into<String>, style: TextStyle) -> Self {
+        Self {
+            severity,
+            text: text.into(),
+            style,
+            duration: Some(DEFAULT_DURATION),
+            on_click: None,
+        }
+    }
+
+    pub fn with_duration(mut self, duration: Option<Duration>) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Calls `callback` when the toast is clicked, before it is dismissed.
+    pub fn with_on_click(mut self, callback: impl FnMut() + 'static) -> Self {
+        self.on_click = Some(Box::new(callback));
+        self
+    }
+}
+
+struct Entry {
+    id: ToastId,
+    toast: Toast,
+    /// When the toast will be dismissed. Set when it is first shown, so that
+    /// queued toasts get their full duration.
+    expires: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Queue {
+    entries: Vec<Entry>,
+    next_id: u64,
+    /// Incremented whenever the visible toasts change, so that windows know
+    /// when to redraw them.
+    generation: u64,
+}
+
+impl Queue {
+    fn visible(&self) -> &[Entry] {
+        &self.entries[..self.entries.len().min(MAX_VISIBLE)]
+    }
+}
+
+/// A shared queue of toasts. Clones refer to the same queue.
+#[derive(Clone, Default)]
+pub struct Notifications {
+    queue: Rc<RefCell<Queue>>,
+}
+
+impl Notifications {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `toast` to the queue. It is shown once there is room for it.
+    pub fn notify(&self, toast: Toast) -> ToastId {
+        let mut queue = self.queue.borrow_mut();
+        let id = ToastId(queue.next_id);
+        queue.next_id += 1;
+        queue.entries.push(Entry {
+            id,
+            toast,
+            expires: None,
+        });
+        queue.generation += 1;
+        id
+    }
+
+    /// Removes the toast identified by `id`, whether or not it has been shown.
+    pub fn dismiss(&self, id: ToastId) {
+        let mut queue = self.queue.borrow_mut();
+        let len = queue.entries.len();
+        queue.entries.retain(|entry| entry.id != id);
+        if queue.entries.len() != len {
+            queue.generation += 1;
+        }
+    }
+
+    /// Calls the click callback of the toast identified by `id`, then
+    /// dismisses it.
+    pub fn click(&self, id: ToastId) {
+        let entry = {
+            let mut queue = self.queue.borrow_mut();
+            let index = match queue.entries.iter().position(|entry| entry.id == id) {
+                Some(index) => index,
+                None => return,
+            };
+            queue.generation += 1;
+            queue.entries.remove(index)
+        };
+
+        // The queue isn't borrowed while the callback runs, so that it can
+        // queue other toasts.
+        if let Some(mut on_click) = entry.toast.on_click {
+            on_click();
+        }
+    }
+
+    /// Starts the timers of newly shown toasts, and dismisses the ones whose
+    /// time has run out.
+    pub fn tick(&self, now: Instant) {
+        let mut queue = self.queue.borrow_mut();
+        let queue = &mut *queue;
+
+        let visible = queue.entries.len().min(MAX_VISIBLE);
+        for entry in &mut queue.entries[..visible] {
+            if entry.expires.is_none() {
+                entry.expires = entry.toast.duration.map(|duration| now + duration);
+            }
+        }
+
+        let len = queue.entries.len();
+        queue
+            .entries
+            .retain(|entry| !matches!(entry.expires, Some(expires) if expires <= now));
+        if queue.entries.len() != len {
+            queue.generation += 1;
+        }
+    }
+
+    /// Checks if any toast will be dismissed once its time runs out, in which
+    /// case [`Self::tick`] needs to be called until it is.
+    #[must_use]
+    pub fn has_timers(&self) -> bool {
+        self.queue
+            .borrow()
+            .visible()
+            .iter()
+            .any(|entry| entry.toast.duration.is_some())
+    }
+
+    fn generation(&self) -> u64 {
+        self.queue.borrow().generation
+    }
+}
+
+struct ShownToast {
+    id: ToastId,
+    /// The bounds of the toast, in absolute coordinates.
+    rect: Rect,
+    lines: Vec<(ShapedLine, Px)>,
+    size: f32,
+    color: Color,
+}
+
+/// The toasts shown in one window.
+#[derive(Default)]
+pub struct ToastLayer {
+    /// The generation of the queue that the toasts were laid out for.
+    generation: Option<u64>,
+    window: Extent,
+    shown: Vec<ShownToast>,
+}
+
+impl ToastLayer {
+    /// Lays out the toasts again if they have changed since the last call.
+    /// Returns the region of the window that needs to be redrawn, which is
+    /// empty if nothing changed.
+    pub fn update(
+        &mut self,
+        notifications: &Notifications,
+        context: &LayoutContext,
+        window: Extent,
+    ) -> Rect {
+        let generation = notifications.generation();
+        if self.generation == Some(generation) && self.window == window {
+            return Rect::zero();
+        }
+
+        let before = self.rect();
+        let queue = notifications.queue.borrow();
+        let text_width = f32::from((TOAST_WIDTH - SEVERITY_BAR - PADDING - PADDING).0);
+
+        let mut heights = Vec::new();
+        self.shown = queue
+            .visible()
+            .iter()
+            .map(|entry| {
+                let toast = &entry.toast;
+                let metrics = context.measure_text(&toast.text, &toast.style, Some(text_width));
+                heights.push(Px::from(metrics.height.ceil()) + PADDING + PADDING);
+
+                ShownToast {
+                    id: entry.id,
+                    rect: Rect::zero(),
+                    lines: metrics
+                        .lines
+                        .into_iter()
+                        .map(|line| (line.shaped, Px::from(line.baseline.round())))
+                        .collect(),
+                    size: toast.style.size,
+                    color: toast.severity.color(),
+                }
+            })
+            .collect();
+
+        for (shown, rect) in self.shown.iter_mut().zip(stack_toasts(&heights, window)) {
+            shown.rect = rect;
+        }
+
+        self.generation = Some(generation);
+        self.window = window;
+        before.union(&self.rect())
+    }
+
+    /// Finds the toast at `point`, in absolute coordinates.
+    #[must_use]
+    pub fn toast_at(&self, point: Point) -> Option<ToastId> {
+        self.shown
+            .iter()
+            .find(|shown| shown.rect.contains_point(point))
+            .map(|shown| shown.id)
+    }
+
+    /// Draws the toasts. Call this after everything else in the window so that
+    /// they are drawn above it.
+    pub fn draw(&self, canvas: &mut DrawContext) {
+        for shown in &self.shown {
+            let rect = shown.rect;
+            canvas.draw_rect(rect, &Paint::Fill { color: BACKGROUND });
+            canvas.draw_rect(
+                Rect {
+                    right: rect.left + SEVERITY_BAR,
+                    ..rect
+                },
+                &Paint::Fill { color: shown.color },
+            );
+            canvas.stroke_rect(rect, &Stroke::solid(BORDER, 1.0));
+
+            for (line, baseline) in &shown.lines {
+                canvas.draw_text(
+                    line,
+                    Point::new(
+                        rect.left + SEVERITY_BAR + PADDING,
+                        rect.top + PADDING + *baseline,
+                    ),
+                    shown.size,
+                    Color::BLACK,
+                );
+            }
+        }
+    }
+
+    fn rect(&self) -> Rect {
+        self.shown
+            .iter()
+            .fold(Rect::zero(), |rect, shown| rect.union(&shown.rect))
+    }
+}
+
+/// Stacks toasts of the given heights up from the bottom-right corner of a
+/// window of size `window`, oldest at the bottom.
+fn stack_toasts(heights: &[Px], window: Extent) -> Vec<Rect> {
+    let right = window.width - MARGIN;
+    let left = (right - TOAST_WIDTH).max(Px(0));
+    let mut bottom = window.height - MARGIN;
+
+    heights
+        .iter()
+        .map(|&height| {
+            let rect = Rect {
+                left,
+                top: bottom - height,
+                right,
+                bottom,
+            };
+            bottom = rect.top - MARGIN;
+            rect
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stack() {
+        let window = Extent {
+            width: Px(400),
+            height: Px(300),
+        };
+
+        assert_eq!(
+            stack_toasts(&[Px(30), Px(50)], window),
+            [
+                Rect {
+                    left: Px(108),
+                    top: Px(258),
+                    right: Px(388),
+                    bottom: Px(288),
+                },
+                Rect {
+                    left: Px(108),
+                    top: Px(196),
+                    right: Px(388),
+                    bottom: Px(246),
+                },
+            ]
+        );
+    }
+}
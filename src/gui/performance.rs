@@ -0,0 +1,260 @@
+//! An overlay in the corner of a window that shows how quickly it is drawn,
+//! for finding performance problems.
+//!
+//! The overlay is enabled per window with
+//! [`AppWindowConfig::performance_hud`](crate::application::AppWindowConfig).
+//! It shows the number of frames drawn in the last second, the time spent on
+//! the CPU preparing the last frame, and the measurements reported by the
+//! graphics device (see [`FrameStats`]). Windows are only drawn when something
+//! changes, so the frame rate is that of the redraws and not of the display.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Point, Px, Rect},
+        FrameStats, Paint,
+    },
+    text::{ShapedLine, TextStyle},
+};
+
+use super::widgets::{DrawContext, LayoutContext};
+
+/// How often the text is updated. Updating the text redraws the window, so
+/// this also limits how many frames the overlay adds by itself.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The space between the overlay and the window's edges.
+const MARGIN: Px = Px(8);
+
+/// The space between the edge of the overlay and its text.
+const PADDING: Px = Px(6);
+
+const BACKGROUND: Color = Color {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.75,
+};
+
+const TEXT: Color = Color {
+    r: 0.4,
+    g: 1.0,
+    b: 0.4,
+    a: 1.0,
+};
+
+/// The performance overlay for one window.
+pub struct PerformanceHud {
+    style: TextStyle,
+    frame_rate: FrameRate,
+    /// The time spent on the CPU preparing the last frame.
+    cpu_time: Duration,
+    stats: FrameStats,
+    /// When the text was last updated.
+    refreshed: Option<Instant>,
+    text: Vec<String>,
+    lines: Vec<(ShapedLine, Px)>,
+    /// The bounds of the overlay, in absolute coordinates.
+    rect: Rect,
+}
+
+impl PerformanceHud {
+    #[must_use]
+    pub fn new(style: TextStyle) -> Self {
+        Self {
+            style,
+            frame_rate: FrameRate::default(),
+            cpu_time: Duration::ZERO,
+            stats: FrameStats::default(),
+            refreshed: None,
+            text: Vec::new(),
+            lines: Vec::new(),
+            rect: Rect::zero(),
+        }
+    }
+
+    /// Records a frame that finished drawing at `now`, after `cpu_time` was
+    /// spent preparing it. `stats` are the graphics device's measurements
+    /// after the frame was submitted.
+    pub fn record_frame(&mut self, now: Instant, cpu_time: Duration, stats: FrameStats) {
+        self.frame_rate.record(now);
+        self.cpu_time = cpu_time;
+        self.stats = stats;
+    }
+
+    /// Updates the text if it hasn't been updated recently. Returns the region
+    /// of the window that needs to be redrawn, which is empty if nothing
+    /// changed.
+    pub fn update(&mut self, context: &LayoutContext, now: Instant) -> Rect {
+        if self
+            .refreshed
+            .is_some_and(|refreshed| now.saturating_duration_since(refreshed) < REFRESH_INTERVAL)
+        {
+            return Rect::zero();
+        }
+        self.refreshed = Some(now);
+
+        let fps = self.frame_rate.count(now);
+        let text = describe(fps, self.cpu_time, &self.stats);
+        if text == self.text {
+            return Rect::zero();
+        }
+
+        let mut top = Px(0);
+        let mut width = Px(0);
+        self.lines = text
+            .iter()
+            .filter_map(|line| {
+                let metrics = context.measure_text(line, &self.style, None);
+                let line_top = top;
+                top += Px::from(metrics.line_height.ceil());
+                width = width.max(Px::from(metrics.width.ceil()));
+
+                let line = metrics.lines.into_iter().next()?;
+                Some((line.shaped, line_top + Px::from(line.baseline.round())))
+            })
+            .collect();
+        self.text = text;
+
+        let before = self.rect;
+        self.rect = Rect {
+            left: MARGIN,
+            top: MARGIN,
+            right: MARGIN + width + PADDING + PADDING,
+            bottom: MARGIN + top + PADDING + PADDING,
+        };
+        before.union(&self.rect)
+    }
+
+    /// Draws the overlay. Call this after everything else in the window so
+    /// that it is drawn above it.
+    pub fn draw(&self, canvas: &mut DrawContext) {
+        if self.lines.is_empty() {
+            return;
+        }
+
+        canvas.draw_rect(self.rect, &Paint::Fill { color: BACKGROUND });
+        for (line, baseline) in &self.lines {
+            canvas.draw_text(
+                line,
+                Point::new(
+                    self.rect.left + PADDING,
+                    self.rect.top + PADDING + *baseline,
+                ),
+                self.style.size,
+                TEXT,
+            );
+        }
+    }
+}
+
+/// Counts the frames drawn in the last second.
+#[derive(Default)]
+struct FrameRate {
+    /// When each frame in the last second finished drawing, oldest first.
+    frames: VecDeque<Instant>,
+}
+
+impl FrameRate {
+    fn record(&mut self, now: Instant) {
+        self.frames.push_back(now);
+        self.forget_old_frames(now);
+    }
+
+    /// The number of frames drawn in the second before `now`.
+    fn count(&mut self, now: Instant) -> usize {
+        self.forget_old_frames(now);
+        self.frames.len()
+    }
+
+    fn forget_old_frames(&mut self, now: Instant) {
+        while self
+            .frames
+            .front()
+            .is_some_and(|frame| now.saturating_duration_since(*frame) >= Duration::from_secs(1))
+        {
+            self.frames.pop_front();
+        }
+    }
+}
+
+/// The lines of text shown by the overlay.
+fn describe(fps: usize, cpu_time: Duration, stats: &FrameStats) -> Vec<String> {
+    let millis = |duration: Duration| format!("{:.2} ms", duration.as_secs_f64() * 1000.0);
+
+    vec![
+        format!("{fps} fps"),
+        format!("CPU {}", millis(cpu_time)),
+        match stats.gpu_time {
+            Some(gpu_time) => format!("GPU {}", millis(gpu_time)),
+            None => "GPU n/a".to_string(),
+        },
+        format!("{} draws, {} vertices", stats.draw_calls, stats.vertices),
+        format!("{} textures", format_bytes(stats.texture_memory)),
+    ]
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_rate() {
+        let mut frame_rate = FrameRate::default();
+
+        let start = Instant::now();
+        for i in 0..30 {
+            frame_rate.record(start + Duration::from_millis(i * 50));
+        }
+
+        // Frames drawn more than a second ago aren't counted.
+        let end = start + Duration::from_millis(29 * 50);
+        assert_eq!(frame_rate.count(end), 20);
+        assert_eq!(frame_rate.count(end + Duration::from_secs(1)), 0);
+    }
+
+    #[test]
+    fn text() {
+        let stats = FrameStats {
+            draw_calls: 12,
+            vertices: 3456,
+            gpu_time: None,
+            texture_memory: 3 * 1024 * 1024 / 2,
+        };
+
+        assert_eq!(
+            describe(60, Duration::from_micros(1250), &stats),
+            [
+                "60 fps",
+                "CPU 1.25 ms",
+                "GPU n/a",
+                "12 draws, 3456 vertices",
+                "1.5 MiB textures",
+            ]
+        );
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(2048), "2.0 KiB");
+    }
+}
@@ -0,0 +1,707 @@
+//! Widgets and drawing contributed by other crates, found by name at runtime.
+//!
+//! A crate contributes to the UI by implementing [`Plugin`] and handing it to
+//! a [`PluginRegistry`]. When the plugin is loaded, it says which version of
+//! this interface it was built against and which [`Capability`]s it wants,
+//! and the registry agrees to the ones that it can provide. The plugin then
+//! registers [`WidgetFactory`]s and [`DrawPrimitive`]s under names that the
+//! application, or a [hot-reloaded](super::hot_reload) UI description, can
+//! look up without knowing the plugin's types.
+//!
+//! ```ignore
+//! let mut registry = PluginRegistry::new();
+//! registry.load(Box::new(charts::Plugin))?;
+//! let chart = registry.create_widget("charts.bar", &Properties::new().with("bars", 12))?;
+//! ```
+//!
+//! ## Versioning
+//!
+//! The interface and each capability are versioned separately. Minor versions
+//! only add to what came before, so a plugin built against `1.2` works with a
+//! host at `1.3`, but not the other way around. Different major versions are
+//! never compatible.
+
+use std::{collections::HashMap, fmt, rc::Rc};
+
+use crate::gfx::{
+    color::Color,
+    geometry::{Extent, Point, Rect},
+};
+
+use super::{
+    input::Event,
+    widgets::{
+        BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext, Widget, WidgetState,
+    },
+};
+
+/// The version of the plugin interface that this build of fathom provides.
+pub const API_VERSION: Version = Version::new(1, 0);
+
+/// The capabilities that this build of fathom provides, and their versions.
+pub const HOST_CAPABILITIES: &[(Capability, Version)] = &[
+    (Capability::Widgets, Version::new(1, 0)),
+    (Capability::DrawPrimitives, Version::new(1, 0)),
+];
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("plugin `{plugin}` needs plugin API {required}, but {API_VERSION} is available")]
+    IncompatibleApi { plugin: String, required: Version },
+    #[error("plugin `{plugin}` needs {capability:?} {required}, which is not available")]
+    MissingCapability {
+        plugin: String,
+        capability: Capability,
+        required: Version,
+    },
+    #[error("a plugin named `{0}` is already loaded")]
+    DuplicatePlugin(String),
+    #[error("registering {0:?} needs the capability, which the plugin did not ask for")]
+    NotNegotiated(Capability),
+    #[error("`{0}` is already registered")]
+    DuplicateName(String),
+    #[error("no plugin provides `{0}`")]
+    Unknown(String),
+    #[error("`{name}` could not be created: {message}")]
+    InvalidProperties { name: String, message: String },
+}
+
+/// A version of the plugin interface or one of its capabilities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl Version {
+    #[must_use]
+    pub const fn new(major: u16, minor: u16) -> Self {
+        Self { major, minor }
+    }
+
+    /// Checks if something that provides this version can be used by
+    /// something that needs `required`.
+    #[must_use]
+    pub fn satisfies(self, required: Version) -> bool {
+        self.major == required.major && self.minor >= required.minor
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Something that a plugin can contribute, which it has to ask for before
+/// it can register anything of that kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Registering [`WidgetFactory`]s.
+    Widgets,
+    /// Registering [`DrawPrimitive`]s.
+    DrawPrimitives,
+}
+
+/// A capability that a plugin asks for when it is loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CapabilityRequest {
+    pub capability: Capability,
+    /// The oldest version of the capability that the plugin works with.
+    pub version: Version,
+    /// Whether the plugin fails to load without the capability. Plugins can
+    /// check for optional capabilities in [`Plugin::register`].
+    pub required: bool,
+}
+
+/// The capabilities that the registry agreed to provide to a plugin, and the
+/// version of each.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    granted: Vec<(Capability, Version)>,
+}
+
+impl Capabilities {
+    #[must_use]
+    pub fn contains(&self, capability: Capability) -> bool {
+        self.version(capability).is_some()
+    }
+
+    /// The version of the capability provided by the host, if it was granted.
+    #[must_use]
+    pub fn version(&self, capability: Capability) -> Option<Version> {
+        self.granted
+            .iter()
+            .find(|(granted, _)| *granted == capability)
+            .map(|(_, version)| *version)
+    }
+}
+
+/// A value passed to a widget factory or draw primitive.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Color(Color),
+}
+
+macro_rules! value_from {
+    ($($ty:ty => $variant:ident),*) => {
+        $(impl From<$ty> for Value {
+            fn from(value: $ty) -> Self {
+                Self::$variant(value.into())
+            }
+        })*
+    };
+}
+
+value_from!(bool => Bool, i64 => Int, i32 => Int, f64 => Float, f32 => Float, String => String, &str => String, Color => Color);
+
+/// Named values that configure a widget or draw primitive. Their meaning is up
+/// to the plugin.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Properties {
+    values: HashMap<String, Value>,
+}
+
+impl Properties {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.values.insert(name.into(), value.into());
+        self
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    #[must_use]
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.get(name) {
+            Some(Value::Bool(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Gets an integer, or a float rounded to the nearest integer.
+    #[must_use]
+    pub fn int(&self, name: &str) -> Option<i64> {
+        match self.get(name) {
+            Some(Value::Int(value)) => Some(*value),
+            Some(Value::Float(value)) => Some(value.round() as i64),
+            _ => None,
+        }
+    }
+
+    /// Gets a float, or an integer converted to a float.
+    #[must_use]
+    pub fn float(&self, name: &str) -> Option<f64> {
+        match self.get(name) {
+            Some(Value::Float(value)) => Some(*value),
+            Some(Value::Int(value)) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn string(&self, name: &str) -> Option<&str> {
+        match self.get(name) {
+            Some(Value::String(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn color(&self, name: &str) -> Option<Color> {
+        match self.get(name) {
+            Some(Value::Color(value)) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Creates widgets of one kind.
+pub trait WidgetFactory {
+    /// Creates a widget configured by `properties`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a message describing the problem if `properties` don't
+    /// describe a valid widget.
+    fn create(&self, properties: &Properties) -> Result<Box<dyn Widget>, String>;
+}
+
+impl<F: Fn(&Properties) -> Result<Box<dyn Widget>, String>> WidgetFactory for F {
+    fn create(&self, properties: &Properties) -> Result<Box<dyn Widget>, String> {
+        self(properties)
+    }
+}
+
+/// Draws something into a rectangle, such as a custom chart or decoration.
+pub trait DrawPrimitive {
+    fn draw(&self, canvas: &mut DrawContext, rect: Rect, properties: &Properties);
+}
+
+impl<F: Fn(&mut DrawContext, Rect, &Properties)> DrawPrimitive for F {
+    fn draw(&self, canvas: &mut DrawContext, rect: Rect, properties: &Properties) {
+        self(canvas, rect, properties);
+    }
+}
+
+/// The entry point of a crate that contributes to the UI. See the
+/// [module documentation](self).
+pub trait Plugin {
+    /// Identifies the plugin. Names should be unique, such as the name of the
+    /// crate.
+    fn name(&self) -> &str;
+
+    /// The version of the plugin interface that the plugin was built against,
+    /// which is usually [`API_VERSION`] at the time.
+    fn api_version(&self) -> Version;
+
+    /// The capabilities that the plugin wants.
+    fn capabilities(&self) -> Vec<CapabilityRequest>;
+
+    /// Registers the plugin's contributions with the capabilities that were
+    /// granted.
+    ///
+    /// ## Errors
+    ///
+    /// Passes on the errors from the registrar, after which the plugin isn't
+    /// loaded.
+    fn register(&self, registrar: &mut Registrar, granted: &Capabilities) -> Result<(), Error>;
+}
+
+/// Collects a plugin's contributions while it is being loaded.
+pub struct Registrar {
+    granted: Capabilities,
+    widgets: Vec<(String, Rc<dyn WidgetFactory>)>,
+    primitives: Vec<(String, Rc<dyn DrawPrimitive>)>,
+}
+
+impl Registrar {
+    /// Registers a factory for widgets named `name`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the plugin wasn't granted
+    /// [`Capability::Widgets`].
+    pub fn register_widget(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl WidgetFactory + 'static,
+    ) -> Result<(), Error> {
+        self.check(Capability::Widgets)?;
+        self.widgets.push((name.into(), Rc::new(factory)));
+        Ok(())
+    }
+
+    /// Registers a primitive named `name`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the plugin wasn't granted
+    /// [`Capability::DrawPrimitives`].
+    pub fn register_primitive(
+        &mut self,
+        name: impl Into<String>,
+        primitive: impl DrawPrimitive + 'static,
+    ) -> Result<(), Error> {
+        self.check(Capability::DrawPrimitives)?;
+        self.primitives.push((name.into(), Rc::new(primitive)));
+        Ok(())
+    }
+
+    fn check(&self, capability: Capability) -> Result<(), Error> {
+        if self.granted.contains(capability) {
+            Ok(())
+        } else {
+            Err(Error::NotNegotiated(capability))
+        }
+    }
+}
+
+/// A plugin that has been loaded, and the capabilities that it was granted.
+pub struct LoadedPlugin {
+    pub name: String,
+    pub api_version: Version,
+    pub capabilities: Capabilities,
+}
+
+/// The plugins that have been loaded, and what they contributed.
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<LoadedPlugin>,
+    widgets: HashMap<String, Rc<dyn WidgetFactory>>,
+    primitives: HashMap<String, Rc<dyn DrawPrimitive>>,
+}
+
+impl PluginRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Negotiates with the plugin and registers its contributions. Nothing is
+    /// registered if loading fails.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the plugin needs a newer or different version of
+    /// the interface or a required capability, if it registers something that
+    /// it wasn't granted, or if it reuses a name that is already registered.
+    pub fn load(&mut self, plugin: Box<dyn Plugin>) -> Result<(), Error> {
+        let name = plugin.name().to_owned();
+        if self.plugins.iter().any(|loaded| loaded.name == name) {
+            return Err(Error::DuplicatePlugin(name));
+        }
+
+        let api_version = plugin.api_version();
+        if !API_VERSION.satisfies(api_version) {
+            return Err(Error::IncompatibleApi {
+                plugin: name,
+                required: api_version,
+            });
+        }
+
+        let capabilities = negotiate(&name, &plugin.capabilities())?;
+
+        let mut registrar = Registrar {
+            granted: capabilities.clone(),
+            widgets: Vec::new(),
+            primitives: Vec::new(),
+        };
+        plugin.register(&mut registrar, &capabilities)?;
+
+        // Check every name before adding any, so that a failed load leaves
+        // the registry as it was.
+        for (i, (name, _)) in registrar.widgets.iter().enumerate() {
+            if self.widgets.contains_key(name)
+                || registrar.widgets[..i].iter().any(|(n, _)| n == name)
+            {
+                return Err(Error::DuplicateName(name.clone()));
+            }
+        }
+        for (i, (name, _)) in registrar.primitives.iter().enumerate() {
+            if self.primitives.contains_key(name)
+                || registrar.primitives[..i].iter().any(|(n, _)| n == name)
+            {
+                return Err(Error::DuplicateName(name.clone()));
+            }
+        }
+
+        self.widgets.extend(registrar.widgets);
+        self.primitives.extend(registrar.primitives);
+        self.plugins.push(LoadedPlugin {
+            name,
+            api_version,
+            capabilities,
+        });
+
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn plugins(&self) -> &[LoadedPlugin] {
+        &self.plugins
+    }
+
+    /// The names of the registered widgets, in no particular order.
+    pub fn widget_names(&self) -> impl Iterator<Item = &str> {
+        self.widgets.keys().map(String::as_str)
+    }
+
+    /// The names of the registered primitives, in no particular order.
+    pub fn primitive_names(&self) -> impl Iterator<Item = &str> {
+        self.primitives.keys().map(String::as_str)
+    }
+
+    /// Creates a widget with the factory registered as `name`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no factory is registered as `name`, or if the
+    /// factory rejects `properties`.
+    pub fn create_widget(
+        &self,
+        name: &str,
+        properties: &Properties,
+    ) -> Result<Box<dyn Widget>, Error> {
+        let factory = self
+            .widgets
+            .get(name)
+            .ok_or_else(|| Error::Unknown(name.to_owned()))?;
+
+        factory
+            .create(properties)
+            .map_err(|message| Error::InvalidProperties {
+                name: name.to_owned(),
+                message,
+            })
+    }
+
+    /// Creates a widget that fills its space by drawing the primitive
+    /// registered as `name`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if no primitive is registered as `name`.
+    pub fn create_primitive(
+        &self,
+        name: &str,
+        properties: Properties,
+    ) -> Result<PrimitiveWidget, Error> {
+        match self.primitives.get(name) {
+            Some(primitive) => Ok(PrimitiveWidget {
+                widget_state: WidgetState::default(),
+                primitive: primitive.clone(),
+                properties,
+            }),
+            None => Err(Error::Unknown(name.to_owned())),
+        }
+    }
+}
+
+/// Grants the capabilities that the host can provide. Missing optional
+/// capabilities are left out.
+fn negotiate(plugin: &str, requests: &[CapabilityRequest]) -> Result<Capabilities, Error> {
+    let mut granted = Vec::new();
+
+    for request in requests {
+        let offered = HOST_CAPABILITIES
+            .iter()
+            .find(|(capability, version)| {
+                *capability == request.capability && version.satisfies(request.version)
+            })
+            .map(|(_, version)| *version);
+
+        match offered {
+            Some(version) => granted.push((request.capability, version)),
+            None if request.required => {
+                return Err(Error::MissingCapability {
+                    plugin: plugin.to_owned(),
+                    capability: request.capability,
+                    required: request.version,
+                })
+            }
+            None => {}
+        }
+    }
+
+    Ok(Capabilities { granted })
+}
+
+/// Draws a [`DrawPrimitive`] over all of the space that it is given. Created
+/// with [`PluginRegistry::create_primitive`].
+#[must_use]
+pub struct PrimitiveWidget {
+    widget_state: WidgetState,
+    primitive: Rc<dyn DrawPrimitive>,
+    properties: Properties,
+}
+
+impl PrimitiveWidget {
+    #[must_use]
+    pub fn properties(&self) -> &Properties {
+        &self.properties
+    }
+}
+
+impl Widget for PrimitiveWidget {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        match context.event() {
+            Event::None
+            | Event::CursorMove { .. }
+            | Event::MouseButton { .. }
+            | Event::Scroll { .. }
+            | Event::KeyPress { .. }
+            | Event::Text { .. }
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => PostUpdate::NoChange,
+        }
+    }
+
+    fn accept_layout(
+        &mut self,
+        _context: &mut LayoutContext,
+        constraints: BoxConstraint,
+    ) -> Extent {
+        constraints.max()
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        self.primitive
+            .draw(canvas, Rect::new(Point::zero(), extent), &self.properties);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::{geometry::Px, Paint},
+        gui::{testing::TestHarness, widgets::debug::Fill},
+    };
+
+    use super::*;
+
+    struct TestPlugin {
+        name: &'static str,
+        api_version: Version,
+        capabilities: Vec<CapabilityRequest>,
+    }
+
+    impl TestPlugin {
+        fn new(name: &'static str) -> Self {
+            Self {
+                name,
+                api_version: API_VERSION,
+                capabilities: vec![
+                    CapabilityRequest {
+                        capability: Capability::Widgets,
+                        version: Version::new(1, 0),
+                        required: true,
+                    },
+                    CapabilityRequest {
+                        capability: Capability::DrawPrimitives,
+                        version: Version::new(1, 0),
+                        required: false,
+                    },
+                ],
+            }
+        }
+    }
+
+    impl Plugin for TestPlugin {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn api_version(&self) -> Version {
+            self.api_version
+        }
+
+        fn capabilities(&self) -> Vec<CapabilityRequest> {
+            self.capabilities.clone()
+        }
+
+        fn register(&self, registrar: &mut Registrar, granted: &Capabilities) -> Result<(), Error> {
+            registrar.register_widget("test.fill", |properties: &Properties| {
+                let color = properties.color("color").ok_or("missing color")?;
+                Ok(Box::new(Fill::new(color)) as Box<dyn Widget>)
+            })?;
+
+            if granted.contains(Capability::DrawPrimitives) {
+                registrar.register_primitive(
+                    "test.rect",
+                    |canvas: &mut DrawContext, rect: Rect, properties: &Properties| {
+                        let color = properties.color("color").unwrap_or(Color::BLACK);
+                        canvas.draw_rect(rect, &Paint::Fill { color });
+                    },
+                )?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn load_plugins() {
+        let mut registry = PluginRegistry::new();
+        registry.load(Box::new(TestPlugin::new("test"))).unwrap();
+        assert_eq!(registry.plugins().len(), 1);
+
+        let properties = Properties::new().with("color", Color::RED);
+        let fill = registry.create_widget("test.fill", &properties).unwrap();
+        let extent = Extent {
+            width: Px(10),
+            height: Px(10),
+        };
+        let mut harness = TestHarness::new(fill, extent);
+        let _ = harness.frame();
+        assert_eq!(harness.color_at(Point::new(5, 5)), Some(Color::RED));
+
+        let rect = registry.create_primitive("test.rect", properties).unwrap();
+        let mut harness = TestHarness::new(rect, extent);
+        let _ = harness.frame();
+        assert_eq!(harness.color_at(Point::new(5, 5)), Some(Color::RED));
+
+        assert_eq!(
+            registry
+                .create_widget("test.fill", &Properties::new())
+                .err(),
+            Some(Error::InvalidProperties {
+                name: "test.fill".to_owned(),
+                message: "missing color".to_owned(),
+            })
+        );
+        assert_eq!(
+            registry.create_widget("other", &Properties::new()).err(),
+            Some(Error::Unknown("other".to_owned()))
+        );
+    }
+
+    #[test]
+    fn negotiate_versions() {
+        let mut registry = PluginRegistry::new();
+
+        let mut newer = TestPlugin::new("newer");
+        newer.api_version = Version::new(API_VERSION.major, API_VERSION.minor + 1);
+        assert!(matches!(
+            registry.load(Box::new(newer)),
+            Err(Error::IncompatibleApi { .. })
+        ));
+
+        let mut missing = TestPlugin::new("missing");
+        missing.capabilities[0].version = Version::new(2, 0);
+        assert!(matches!(
+            registry.load(Box::new(missing)),
+            Err(Error::MissingCapability {
+                capability: Capability::Widgets,
+                ..
+            })
+        ));
+
+        // Without the optional capability, the plugin loads without its
+        // primitive.
+        let mut optional = TestPlugin::new("optional");
+        optional.capabilities[1].version = Version::new(1, 9);
+        registry.load(Box::new(optional)).unwrap();
+        assert!(!registry.plugins()[0]
+            .capabilities
+            .contains(Capability::DrawPrimitives));
+        assert_eq!(registry.primitive_names().count(), 0);
+
+        // Names can't be registered twice, and nothing from a failed load is
+        // kept.
+        assert_eq!(
+            registry.load(Box::new(TestPlugin::new("again"))),
+            Err(Error::DuplicateName("test.fill".to_owned()))
+        );
+        assert_eq!(registry.plugins().len(), 1);
+        assert_eq!(
+            registry.load(Box::new(TestPlugin::new("optional"))),
+            Err(Error::DuplicatePlugin("optional".to_owned()))
+        );
+    }
+}
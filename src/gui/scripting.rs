@@ -0,0 +1,455 @@
+//! Panels built and driven by [Rhai](https://rhai.rs) scripts, so that tools
+//! built on fathom can let their users script parts of the interface.
+//!
+//! A script evaluates to the widget tree of a [`ScriptPanel`]. It builds
+//! widgets with the functions below, keeps state in observable values, and
+//! reacts to input with closures:
+//!
+//! ```text
+//! let dark = state(false);
+//!
+//! column([
+//!     toggle("Dark mode", false, |on| dark.value = on),
+//!     bind(dark, |on| if on { fill("#202020") } else { fill("#f0f0f0") }),
+//! ])
+//! ```
+//!
+//! | Function                            | Widget                                 |
+//! |-------------------------------------|----------------------------------------|
+//! | `column([..])`                      | [`Column`]                             |
+//! | `center(child)`                     | [`Center`]                             |
+//! | `padding(px, child)`                | [`Padding`] with the same inset on every side |
+//! | `split_x([..])`, `split_y([..])`    | [`SplitPanel`]                         |
+//! | `tabs([..])`                        | [`TabbedPanel`]                        |
+//! | `fill(color)`                       | [`Fill`] with a color in hexadecimal   |
+//! | `checkbox(label, checked, \|checked\| ..)` | [`Checkbox`]                    |
+//! | `toggle(label, on, \|on\| ..)`      | [`Switch`]                             |
+//! | `text_input(text, \|text\| ..)`     | [`TextInput`]                          |
+//! | `clickable(child, \|\| ..)`         | [`Interactive`], called when clicked   |
+//! | `bind(state, \|value\| ..)`         | Rebuilds its child whenever `state` changes |
+//!
+//! `state(value)` creates an [`Observable`] that is read and written through
+//! its `value` property. Each widget can only be placed in the tree once.
+//!
+//! Scripts can't reach the file system or the rest of the application, and
+//! each call into a script is limited to [`MAX_OPERATIONS`] so that a script
+//! that never ends doesn't hang the window. Errors in callbacks are kept until
+//! they are taken with [`ScriptPanel::take_errors`].
+//!
+//! Scripting is only available with the `scripting` feature.
+
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FnPtr, FuncArgs, AST};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Offset, Px},
+    },
+    gui::state::Observable,
+    text::TextStyle,
+};
+
+use super::widgets::{
+    bind::Bind,
+    debug::Fill,
+    interactive::Interactive,
+    layout::{Center, Column, Padding},
+    split_panel::{Axis, SplitPanel},
+    tabbed_panel::TabbedPanel,
+    text_input::TextInput,
+    toggle::{CheckState, Checkbox, Switch},
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// The most operations that a script may perform in a single call, such as
+/// to build the panel or to handle an event.
+pub const MAX_OPERATIONS: u64 = 1_000_000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("the script could not be parsed: {0}")]
+    Parse(#[from] rhai::ParseError),
+    #[error("the script failed: {0}")]
+    Runtime(#[from] Box<EvalAltResult>),
+    #[error("the script returned {0} instead of a widget")]
+    NotAWidget(String),
+}
+
+/// A widget built by a script, which is moved into the tree when it is given
+/// to a container.
+#[derive(Clone)]
+struct ScriptWidget(Rc<RefCell<Option<Box<dyn Widget>>>>);
+
+impl ScriptWidget {
+    fn new(widget: impl Widget + 'static) -> Self {
+        Self(Rc::new(RefCell::new(Some(Box::new(widget)))))
+    }
+
+    fn take(&self) -> ScriptResult<Box<dyn Widget>> {
+        self.0
+            .borrow_mut()
+            .take()
+            .ok_or_else(|| "the widget is already in the tree".into())
+    }
+}
+
+/// An observable value created by a script.
+#[derive(Clone)]
+struct ScriptState(Observable<Dynamic>);
+
+/// The engine that runs a panel's script, shared with the callbacks of the
+/// widgets that it built.
+struct Runtime {
+    engine: Engine,
+    ast: RefCell<AST>,
+    errors: RefCell<Vec<Error>>,
+}
+
+impl Runtime {
+    /// Calls a closure from the script, recording any error that it raises.
+    fn call(&self, f: &FnPtr, args: impl FuncArgs) -> Option<Dynamic> {
+        match f.call::<Dynamic>(&self.engine, &self.ast.borrow(), args) {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.errors.borrow_mut().push(Error::Runtime(error));
+                None
+            }
+        }
+    }
+}
+
+/// Calls `f` through the runtime if the panel still exists.
+fn call(runtime: &Weak<Runtime>, f: &FnPtr, args: impl FuncArgs) -> Option<Dynamic> {
+    runtime.upgrade().and_then(|runtime| runtime.call(f, args))
+}
+
+/// A widget tree built by a script. See the [module documentation](self) for
+/// what scripts can do.
+///
+/// The panel is laid out and drawn as if its tree weren't wrapped.
+#[must_use]
+pub struct ScriptPanel {
+    widget_state: WidgetState,
+    child: Box<dyn Widget>,
+    runtime: Rc<Runtime>,
+}
+
+impl ScriptPanel {
+    /// Runs `source` to build the panel. Text in the panel is drawn in
+    /// `style`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the script can't be parsed, fails, or doesn't
+    /// evaluate to a widget.
+    pub fn new(source: &str, style: TextStyle) -> Result<Self, Error> {
+        Self::build(source, Some(style))
+    }
+
+    /// Runs `source` to build the panel. Without a style, scripts that build
+    /// widgets with text fail.
+    fn build(source: &str, style: Option<TextStyle>) -> Result<Self, Error> {
+        let runtime = Rc::new_cyclic(|runtime: &Weak<Runtime>| {
+            let mut engine = Engine::new();
+            engine.set_max_operations(MAX_OPERATIONS);
+            register_api(&mut engine, runtime, style);
+
+            Runtime {
+                engine,
+                ast: RefCell::new(AST::empty()),
+                errors: RefCell::new(Vec::new()),
+            }
+        });
+
+        *runtime.ast.borrow_mut() = runtime.engine.compile(source)?;
+
+        let result = runtime.engine.eval_ast::<Dynamic>(&runtime.ast.borrow())?;
+        let type_name = result.type_name();
+        let child = match result.try_cast::<ScriptWidget>() {
+            Some(widget) => widget.take()?,
+            None => return Err(Error::NotAWidget(type_name.to_owned())),
+        };
+
+        Ok(Self {
+            widget_state: WidgetState::default(),
+            child,
+            runtime,
+        })
+    }
+
+    /// Takes the errors raised by the script's callbacks since this was last
+    /// called.
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut *self.runtime.errors.borrow_mut())
+    }
+}
+
+impl Widget for ScriptPanel {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(self.child.as_ref());
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(self.child.as_mut());
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(self.child.as_mut());
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = context.layout(self.child.as_mut(), constraints);
+        context.position_widget(self.child.as_mut(), Offset::zero(), extent);
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(self.child.as_ref());
+    }
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Moves the widgets in a script array into the tree.
+fn children(array: Array) -> ScriptResult<Vec<Box<dyn Widget>>> {
+    array
+        .into_iter()
+        .map(|item| {
+            let type_name = item.type_name();
+            match item.try_cast::<ScriptWidget>() {
+                Some(widget) => widget.take(),
+                None => Err(format!("expected a widget, but got {type_name}").into()),
+            }
+        })
+        .collect()
+}
+
+/// Registers the functions that scripts build widgets with. Callbacks refer to
+/// the runtime weakly, since it owns the engine that owns these functions.
+#[allow(clippy::too_many_lines)]
+fn register_api(engine: &mut Engine, runtime: &Weak<Runtime>, style: Option<TextStyle>) {
+    engine.register_type_with_name::<ScriptWidget>("Widget");
+    engine.register_type_with_name::<ScriptState>("State");
+
+    engine.register_fn("state", |value: Dynamic| {
+        ScriptState(Observable::new(value))
+    });
+    engine.register_get_set(
+        "value",
+        |state: &mut ScriptState| state.0.get().clone(),
+        |state: &mut ScriptState, value: Dynamic| state.0.set(value),
+    );
+
+    engine.register_fn("column", |items: Array| -> ScriptResult<ScriptWidget> {
+        Ok(ScriptWidget::new(Column::with_children(children(items)?)))
+    });
+    engine.register_fn(
+        "center",
+        |child: ScriptWidget| -> ScriptResult<ScriptWidget> {
+            Ok(ScriptWidget::new(Center::new(child.take()?)))
+        },
+    );
+    engine.register_fn(
+        "padding",
+        |px: i64, child: ScriptWidget| -> ScriptResult<ScriptWidget> {
+            let px = Px(px.clamp(0, i64::from(Px::MAX.0)) as i16);
+            Ok(ScriptWidget::new(Padding::uniform(px, child.take()?)))
+        },
+    );
+    engine.register_fn("split_x", |items: Array| -> ScriptResult<ScriptWidget> {
+        Ok(ScriptWidget::new(SplitPanel::with_children(
+            Axis::X,
+            children(items)?,
+        )))
+    });
+    engine.register_fn("split_y", |items: Array| -> ScriptResult<ScriptWidget> {
+        Ok(ScriptWidget::new(SplitPanel::with_children(
+            Axis::Y,
+            children(items)?,
+        )))
+    });
+    engine.register_fn("tabs", |items: Array| -> ScriptResult<ScriptWidget> {
+        Ok(ScriptWidget::new(TabbedPanel::with_children(children(
+            items,
+        )?)))
+    });
+    engine.register_fn("fill", |hex: &str| -> ScriptResult<ScriptWidget> {
+        match Color::from_hex(hex) {
+            Some(color) => Ok(ScriptWidget::new(Fill::new(color))),
+            None => Err(format!("`{hex}` is not a color").into()),
+        }
+    });
+
+    let text_style = move || -> ScriptResult<TextStyle> {
+        style.ok_or_else(|| "this panel has no text style".into())
+    };
+
+    let weak = runtime.clone();
+    engine.register_fn(
+        "checkbox",
+        move |label: &str, checked: bool, on_change: FnPtr| -> ScriptResult<ScriptWidget> {
+            let runtime = weak.clone();
+            let state = if checked {
+                CheckState::Checked
+            } else {
+                CheckState::Unchecked
+            };
+
+            Ok(ScriptWidget::new(
+                Checkbox::new(label, text_style()?)
+                    .with_state(state)
+                    .with_on_change(move |state| {
+                        let _ = call(&runtime, &on_change, (state.is_checked(),));
+                    }),
+            ))
+        },
+    );
+
+    let weak = runtime.clone();
+    engine.register_fn(
+        "toggle",
+        move |label: &str, on: bool, on_change: FnPtr| -> ScriptResult<ScriptWidget> {
+            let runtime = weak.clone();
+            Ok(ScriptWidget::new(
+                Switch::new(label, text_style()?)
+                    .with_on(on)
+                    .with_on_change(move |on| {
+                        let _ = call(&runtime, &on_change, (on,));
+                    }),
+            ))
+        },
+    );
+
+    let weak = runtime.clone();
+    engine.register_fn(
+        "text_input",
+        move |text: &str, on_change: FnPtr| -> ScriptResult<ScriptWidget> {
+            let runtime = weak.clone();
+            Ok(ScriptWidget::new(
+                TextInput::new(text_style()?)
+                    .with_text(text)
+                    .with_on_change(move |text| {
+                        let _ = call(&runtime, &on_change, (text.to_owned(),));
+                    }),
+            ))
+        },
+    );
+
+    let weak = runtime.clone();
+    engine.register_fn(
+        "clickable",
+        move |child: ScriptWidget, on_click: FnPtr| -> ScriptResult<ScriptWidget> {
+            let runtime = weak.clone();
+            Ok(ScriptWidget::new(
+                Interactive::new(child.take()?).with_on_click(move |_| {
+                    let _ = call(&runtime, &on_click, ());
+                }),
+            ))
+        },
+    );
+
+    let weak = runtime.clone();
+    engine.register_fn("bind", move |state: ScriptState, build: FnPtr| {
+        let runtime = weak.clone();
+        let placeholder: Box<dyn Widget> = Box::new(Fill::new(Color::ZERO));
+
+        // The child is rebuilt from scratch, so the old one is kept if the
+        // script fails.
+        ScriptWidget::new(Bind::new(&state.0, placeholder, move |value, child| {
+            let widget = call(&runtime, &build, (value.clone(),))
+                .and_then(|result| result.try_cast::<ScriptWidget>())
+                .and_then(|widget| widget.take().ok());
+            if let Some(widget) = widget {
+                *child = widget;
+            }
+        }))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::geometry::Point,
+        gui::{state, testing::TestHarness},
+    };
+
+    use super::*;
+
+    fn extent() -> Extent {
+        Extent {
+            width: Px(100),
+            height: Px(100),
+        }
+    }
+
+    #[test]
+    fn build_panel() {
+        let panel = ScriptPanel::build(r##"center(fill("#ff0000"))"##, None).unwrap();
+        let mut harness = TestHarness::new(panel, extent());
+        let _ = harness.frame();
+        assert_eq!(harness.color_at(Point::new(50, 50)), Some(Color::RED));
+
+        assert!(matches!(
+            ScriptPanel::build("42", None),
+            Err(Error::NotAWidget(_))
+        ));
+        assert!(matches!(
+            ScriptPanel::build("column([1])", None),
+            Err(Error::Runtime(_))
+        ));
+        assert!(matches!(
+            ScriptPanel::build(r#"toggle("On", true, |on| ())"#, None),
+            Err(Error::Runtime(_))
+        ));
+    }
+
+    #[test]
+    fn callbacks_change_state() {
+        let panel = ScriptPanel::build(
+            r##"
+            let red = state(true);
+            clickable(
+                bind(red, |red| if red { fill("#ff0000") } else { fill("#0000ff") }),
+                || red.value = !red.value,
+            )
+            "##,
+            None,
+        )
+        .unwrap();
+
+        let mut harness = TestHarness::new(panel, extent());
+        let _ = harness.frame();
+        assert_eq!(harness.color_at(Point::new(50, 50)), Some(Color::RED));
+
+        let generation = state::generation();
+        harness.click(Point::new(50, 50));
+        assert_ne!(state::generation(), generation);
+
+        let _ = harness.frame();
+        assert_eq!(harness.color_at(Point::new(50, 50)), Some(Color::BLUE));
+        assert!(harness.root_mut().take_errors().is_empty());
+    }
+}
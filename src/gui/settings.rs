@@ -0,0 +1,675 @@
+//! Application settings that persist between runs.
+//!
+//! Settings are read and written through typed [`Key`]s, each with a default
+//! that is used until the setting is first changed:
+//!
+//! ```ignore
+//! const FONT_SIZE: Key<f32> = Key::new("editor.font_size", 16.0);
+//!
+//! let settings = app.context().settings();
+//! settings.load("settings.json")?;
+//! settings.set(&FONT_SIZE, 18.0);
+//! ```
+//!
+//! Settings are kept in memory until [`Settings::save`] is called, which the
+//! application does when its last window closes. The file is a JSON object
+//! with one member for each setting, so it can be edited by hand. Values that
+//! can't be read as the key's type are ignored in favor of the default.
+//!
+//! Widgets that show a setting can be bound to it through
+//! [`Settings::observe`], and windows can remember where they were placed
+//! through [`AppWindowConfig::remember_placement`](crate::application::AppWindowConfig).
+
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write,
+    path::PathBuf,
+    rc::Rc,
+};
+
+use crate::{gfx::color::Color, shell::placement::WindowPlacement};
+
+use super::state::Observable;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("could not access the settings file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("the settings file is not valid at byte {offset}: {message}")]
+    Syntax { offset: usize, message: String },
+}
+
+/// A setting as it is stored.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+/// A type that can be stored as a setting.
+pub trait SettingValue: Clone + Sized + 'static {
+    fn to_value(&self) -> Value;
+
+    /// Reads the setting back, or returns `None` if `value` doesn't hold this
+    /// type.
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl SettingValue for bool {
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl SettingValue for i64 {
+    fn to_value(&self) -> Value {
+        Value::Int(*self)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl SettingValue for f64 {
+    fn to_value(&self) -> Value {
+        Value::Float(*self)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        // Whole numbers are written without a decimal point.
+        match value {
+            Value::Float(value) => Some(*value),
+            Value::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+}
+
+impl SettingValue for f32 {
+    fn to_value(&self) -> Value {
+        Value::Float(f64::from(*self))
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        f64::from_value(value).map(|value| value as f32)
+    }
+}
+
+impl SettingValue for String {
+    fn to_value(&self) -> Value {
+        Value::String(self.clone())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl SettingValue for Color {
+    fn to_value(&self) -> Value {
+        Value::String(self.to_hex())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(hex) => Color::from_hex(hex),
+            _ => None,
+        }
+    }
+}
+
+impl SettingValue for WindowPlacement {
+    fn to_value(&self) -> Value {
+        Value::String(self.to_string())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Names a setting of type `T` and gives its default.
+#[derive(Clone, Debug)]
+pub struct Key<T> {
+    name: Cow<'static, str>,
+    default: T,
+}
+
+impl<T> Key<T> {
+    pub const fn new(name: &'static str, default: T) -> Self {
+        Self {
+            name: Cow::Borrowed(name),
+            default,
+        }
+    }
+
+    /// Creates a key with a name chosen at runtime, such as one for each
+    /// window.
+    pub fn named(name: impl Into<String>, default: T) -> Self {
+        Self {
+            name: Cow::Owned(name.into()),
+            default,
+        }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+type Observer = Box<dyn FnMut(Option<&Value>)>;
+
+#[derive(Default)]
+struct Inner {
+    path: Option<PathBuf>,
+    values: BTreeMap<String, Value>,
+    observers: HashMap<String, Vec<Observer>>,
+    /// Whether anything changed since the settings were last loaded or
+    /// saved.
+    changed: bool,
+}
+
+/// The application's settings. Clones refer to the same settings. See
+/// [`AppContext::settings`](crate::application::AppContext::settings).
+#[derive(Clone, Default)]
+pub struct Settings {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Settings {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the settings from `path`, replacing any that were already set,
+    /// and saves them there from now on. A file that doesn't exist yet is
+    /// treated as empty.
+    ///
+    /// ## Errors
+    ///
+    /// Fails if the file can't be read or isn't a JSON object of settings.
+    /// The settings are unchanged in that case and aren't saved to `path`, so
+    /// that the file isn't overwritten.
+    pub fn load(&self, path: impl Into<PathBuf>) -> Result<(), Error> {
+        let path = path.into();
+        let values = match std::fs::read_to_string(&path) {
+            Ok(text) => parse(&text)?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(error) => return Err(error.into()),
+        };
+
+        let old = {
+            let mut inner = self.inner.borrow_mut();
+            inner.path = Some(path);
+            inner.changed = false;
+            std::mem::replace(&mut inner.values, values)
+        };
+
+        // Tell the observers of every setting that differs from before.
+        let names = {
+            let inner = self.inner.borrow();
+            old.keys()
+                .chain(inner.values.keys())
+                .filter(|name| old.get(*name) != inner.values.get(*name))
+                .cloned()
+                .collect::<BTreeSet<_>>()
+        };
+        for name in names {
+            self.notify(&name);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the settings to the file they were loaded from, if anything
+    /// changed. Does nothing if they weren't loaded from a file.
+    ///
+    /// ## Errors
+    ///
+    /// Fails if the file can't be written.
+    pub fn save(&self) -> Result<(), Error> {
+        let mut inner = self.inner.borrow_mut();
+        match &inner.path {
+            Some(path) if inner.changed => {
+                std::fs::write(path, write(&inner.values))?;
+                inner.changed = false;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    #[must_use]
+    pub fn path(&self) -> Option<PathBuf> {
+        self.inner.borrow().path.clone()
+    }
+
+    /// Reads a setting, or its default if it hasn't been set.
+    #[must_use]
+    pub fn get<T: SettingValue>(&self, key: &Key<T>) -> T {
+        self.inner
+            .borrow()
+            .values
+            .get(key.name())
+            .and_then(T::from_value)
+            .unwrap_or_else(|| key.default.clone())
+    }
+
+    /// Changes a setting, and tells its observers if it changed.
+    pub fn set<T: SettingValue>(&self, key: &Key<T>, value: T) {
+        self.set_value(key.name(), value.to_value());
+    }
+
+    /// Reads a setting as it is stored, or returns `None` if it hasn't been
+    /// set.
+    #[must_use]
+    pub fn value(&self, name: &str) -> Option<Value> {
+        self.inner.borrow().values.get(name).cloned()
+    }
+
+    /// Changes a setting as it is stored. See [`Self::set`].
+    pub fn set_value(&self, name: &str, value: Value) {
+        {
+            let mut inner = self.inner.borrow_mut();
+            if inner.values.get(name) == Some(&value) {
+                return;
+            }
+            inner.values.insert(name.to_owned(), value);
+            inner.changed = true;
+        }
+        self.notify(name);
+    }
+
+    /// Returns a setting to its default.
+    pub fn reset<T: SettingValue>(&self, key: &Key<T>) {
+        let removed = {
+            let mut inner = self.inner.borrow_mut();
+            let removed = inner.values.remove(key.name()).is_some();
+            inner.changed |= removed;
+            removed
+        };
+
+        if removed {
+            self.notify(key.name());
+        }
+    }
+
+    /// Calls `callback` with the new value whenever the setting changes,
+    /// including when it is reset to its default or loaded from a file.
+    pub fn on_change<T: SettingValue>(&self, key: &Key<T>, mut callback: impl FnMut(T) + 'static) {
+        let default = key.default.clone();
+        self.inner
+            .borrow_mut()
+            .observers
+            .entry(key.name().to_owned())
+            .or_default()
+            .push(Box::new(move |value| {
+                callback(
+                    value
+                        .and_then(T::from_value)
+                        .unwrap_or_else(|| default.clone()),
+                );
+            }));
+    }
+
+    /// Creates an observable that follows the setting, which widgets can be
+    /// bound to with [`Bind`](super::widgets::bind::Bind). Changing the
+    /// observable doesn't change the setting.
+    pub fn observe<T: SettingValue>(&self, key: &Key<T>) -> Observable<T> {
+        let observable = Observable::new(self.get(key));
+        let target = observable.clone();
+        self.on_change(key, move |value| target.set(value));
+        observable
+    }
+
+    fn notify(&self, name: &str) {
+        // The observers are taken out while they run so that they can read
+        // and change settings themselves.
+        let mut observers = match self.inner.borrow_mut().observers.remove(name) {
+            Some(observers) => observers,
+            None => return,
+        };
+
+        let value = self.inner.borrow().values.get(name).cloned();
+        for observer in &mut observers {
+            observer(value.as_ref());
+        }
+
+        let mut inner = self.inner.borrow_mut();
+        let added = inner.observers.insert(name.to_owned(), observers);
+        if let Some(added) = added {
+            inner.observers.get_mut(name).unwrap().extend(added);
+        }
+    }
+}
+
+/// Writes the settings as a JSON object, one member to a line.
+fn write(values: &BTreeMap<String, Value>) -> String {
+    let mut out = String::from("{\n");
+
+    for (i, (name, value)) in values.iter().enumerate() {
+        out.push_str("    ");
+        write_string(&mut out, name);
+        out.push_str(": ");
+        match value {
+            Value::Bool(value) => write!(out, "{value}").unwrap(),
+            Value::Int(value) => write!(out, "{value}").unwrap(),
+            // Debug formatting keeps the decimal point on whole numbers, so
+            // that they're read back as floats.
+            Value::Float(value) if value.is_finite() => write!(out, "{value:?}").unwrap(),
+            // JSON has no infinities or NaN.
+            Value::Float(_) => out.push_str("0.0"),
+            Value::String(value) => write_string(&mut out, value),
+        }
+        out.push_str(if i + 1 < values.len() { ",\n" } else { "\n" });
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Reads a JSON object whose members are all booleans, numbers, or strings.
+fn parse(text: &str) -> Result<BTreeMap<String, Value>, Error> {
+    let mut parser = Parser { text, position: 0 };
+    let mut values = BTreeMap::new();
+
+    parser.expect('{')?;
+    if !parser.eat('}') {
+        loop {
+            let name = parser.string()?;
+            parser.expect(':')?;
+            let value = parser.value()?;
+            values.insert(name, value);
+
+            if !parser.eat(',') {
+                parser.expect('}')?;
+                break;
+            }
+        }
+    }
+
+    parser.skip_whitespace();
+    if parser.position < text.len() {
+        return Err(parser.error("expected the end of the file"));
+    }
+
+    Ok(values)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    /// The byte offset of the next character to parse.
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self) -> Result<Value, Error> {
+        self.skip_whitespace();
+        let rest = &self.text[self.position..];
+
+        if rest.starts_with('"') {
+            Ok(Value::String(self.string()?))
+        } else if rest.starts_with("true") {
+            self.position += 4;
+            Ok(Value::Bool(true))
+        } else if rest.starts_with("false") {
+            self.position += 5;
+            Ok(Value::Bool(false))
+        } else {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+                .unwrap_or(rest.len());
+            let number = &rest[..len];
+            let value = if number.contains(['.', 'e', 'E']) {
+                number.parse().ok().map(Value::Float)
+            } else {
+                number.parse().ok().map(Value::Int)
+            };
+
+            match value {
+                Some(value) => {
+                    self.position += len;
+                    Ok(value)
+                }
+                None => Err(self.error("expected a boolean, number, or string")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect('"')?;
+
+        let mut value = String::new();
+        let mut chars = self.text[self.position..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.position += i + 1;
+                    return Ok(value);
+                }
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, c)| c) {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => {
+                            let unit = |chars: &mut std::str::CharIndices| {
+                                let hex =
+                                    chars.by_ref().take(4).map(|(_, c)| c).collect::<String>();
+                                u32::from_str_radix(&hex, 16).ok()
+                            };
+                            // Characters outside the Basic Multilingual Plane
+                            // are escaped as a UTF-16 surrogate pair.
+                            let code = match unit(&mut chars) {
+                                Some(high @ 0xD800..=0xDBFF) => {
+                                    let low = match (chars.next(), chars.next()) {
+                                        (Some((_, '\\')), Some((_, 'u'))) => unit(&mut chars),
+                                        _ => None,
+                                    };
+                                    low.filter(|low| (0xDC00..=0xDFFF).contains(low))
+                                        .map(|low| {
+                                            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                                        })
+                                }
+                                code => code,
+                            };
+                            match code.and_then(char::from_u32) {
+                                Some(c) => c,
+                                None => {
+                                    self.position += i;
+                                    return Err(self.error("invalid unicode escape"));
+                                }
+                            }
+                        }
+                        _ => {
+                            self.position += i;
+                            return Err(self.error("invalid escape"));
+                        }
+                    };
+                    value.push(escaped);
+                }
+                c => value.push(c),
+            }
+        }
+
+        self.position = self.text.len();
+        Err(self.error("unterminated string"))
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{c}`")))
+        }
+    }
+
+    /// Skips past `c` if it's the next character that isn't whitespace.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.text[self.position..].starts_with(c) {
+            self.position += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::Syntax {
+            offset: self.position,
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: Key<f32> = Key::new("editor.font_size", 16.0);
+    const NAME: Key<String> = Key::new("user.name", String::new());
+    const WRAP: Key<bool> = Key::new("editor.wrap", true);
+
+    #[test]
+    fn read_and_write() {
+        let path =
+            std::env::temp_dir().join(format!("fathom-settings-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let settings = Settings::new();
+        settings.load(&path).unwrap();
+        assert_eq!(settings.get(&SIZE), 16.0);
+
+        settings.set(&SIZE, 18.0);
+        settings.set(&NAME, "Ada \"Countess\" Lovelace\n".to_string());
+        settings.set(&WRAP, false);
+        settings.set(&Key::named("window.main", 0), 3_i64);
+        settings.save().unwrap();
+
+        let loaded = Settings::new();
+        loaded.load(&path).unwrap();
+        assert_eq!(loaded.get(&SIZE), 18.0);
+        assert_eq!(loaded.get(&NAME), "Ada \"Countess\" Lovelace\n");
+        assert!(!loaded.get(&WRAP));
+        assert_eq!(loaded.get(&Key::named("window.main", 0_i64)), 3);
+
+        // Values of the wrong type fall back to the default.
+        assert_eq!(loaded.get(&Key::new("user.name", 1_i64)), 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn notify_changes() {
+        let settings = Settings::new();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+
+        let log = seen.clone();
+        settings.on_change(&SIZE, move |size| log.borrow_mut().push(size));
+        let observed = settings.observe(&SIZE);
+
+        settings.set(&SIZE, 12.0);
+        settings.set(&SIZE, 12.0);
+        settings.set(&WRAP, false);
+        settings.reset(&SIZE);
+
+        assert_eq!(*seen.borrow(), [12.0, 16.0]);
+        assert_eq!(*observed.get(), 16.0);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let offset = |text: &str| match parse(text) {
+            Err(Error::Syntax { offset, .. }) => offset,
+            _ => panic!("expected a syntax error in {text:?}"),
+        };
+
+        assert!(parse("{}").unwrap().is_empty());
+        assert_eq!(offset(""), 0);
+        assert_eq!(offset("{\"a\": [1]}"), 6);
+        assert_eq!(offset("{\"a\": 1,}"), 8);
+        assert_eq!(offset("{\"a\": \"b}"), 9);
+        assert_eq!(offset("{} {}"), 3);
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        let values = parse(r#"{"a": "\u00e9\uD83D\uDE00"}"#).unwrap();
+        assert_eq!(values["a"], Value::String("\u{e9}\u{1F600}".to_string()));
+
+        // A surrogate must be followed by the other half of its pair.
+        assert!(parse(r#"{"a": "\uD83D"}"#).is_err());
+        assert!(parse(r#"{"a": "\uD83DA"}"#).is_err());
+        assert!(parse(r#"{"a": "\uDE00"}"#).is_err());
+    }
+
+    #[test]
+    fn keep_unreadable_file() {
+        let path = std::env::temp_dir().join(format!(
+            "fathom-settings-invalid-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(&path, "{ not json").unwrap();
+
+        let settings = Settings::new();
+        assert!(settings.load(&path).is_err());
+        assert_eq!(settings.path(), None);
+
+        // Saving doesn't replace the file that couldn't be read.
+        settings.set(&SIZE, 18.0);
+        settings.save().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{ not json");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
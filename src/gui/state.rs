@@ -0,0 +1,128 @@
+//! Observable application state.
+//!
+//! An [`Observable`] holds a value that widgets can be bound to with
+//! [`Bind`](super::widgets::bind::Bind). Whenever a value changes, the
+//! application finds the widgets bound to it before drawing the next frame and
+//! updates them, then lays out and redraws whatever they changed. Code that
+//! changes the state doesn't need to know which widgets show it.
+
+use std::{
+    cell::{Cell, Ref, RefCell},
+    rc::Rc,
+};
+
+thread_local! {
+    /// Incremented every time an observable value changes.
+    static GENERATION: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Counts the changes made to observable values on this thread. If this is
+/// the same as when it was last checked, nothing has changed since then.
+#[must_use]
+pub fn generation() -> u64 {
+    GENERATION.with(Cell::get)
+}
+
+/// Makes widgets check for changes to state that isn't observable, such as a
+/// [`Log`](super::logging::Log) written to by other threads, as if an
+/// observable value had changed.
+pub(crate) fn invalidate() {
+    next_generation();
+}
+
+fn next_generation() -> u64 {
+    GENERATION.with(|generation| {
+        generation.set(generation.get() + 1);
+        generation.get()
+    })
+}
+
+struct Inner<T> {
+    value: RefCell<T>,
+    /// The generation in which the value last changed.
+    version: Cell<u64>,
+}
+
+/// A shared value that tracks when it changes. Clones refer to the same
+/// value.
+pub struct Observable<T> {
+    inner: Rc<Inner<T>>,
+}
+
+impl<T> Observable<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                value: RefCell::new(value),
+                version: Cell::new(next_generation()),
+            }),
+        }
+    }
+
+    /// Borrows the value.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the value is being changed by [`Observable::update`].
+    pub fn get(&self) -> Ref<'_, T> {
+        self.inner.value.borrow()
+    }
+
+    /// Replaces the value.
+    pub fn set(&self, value: T) {
+        self.update(|current| *current = value);
+    }
+
+    /// Changes the value in place.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the value is borrowed, including from within `f`.
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        f(&mut self.inner.value.borrow_mut());
+        self.inner.version.set(next_generation());
+    }
+
+    /// Identifies the last change to the value. This is different after every
+    /// change.
+    #[must_use]
+    pub fn version(&self) -> u64 {
+        self.inner.version.get()
+    }
+
+    /// Checks if `other` refers to the same value.
+    #[must_use]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl<T> Clone for Observable<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn track_changes() {
+        let value = Observable::new(1);
+        let shared = value.clone();
+        let (version, start) = (value.version(), generation());
+
+        shared.set(2);
+        assert_eq!(*value.get(), 2);
+        assert_ne!(value.version(), version);
+        assert_ne!(generation(), start);
+
+        let version = value.version();
+        value.update(|value| *value += 1);
+        assert_eq!(*shared.get(), 3);
+        assert_ne!(value.version(), version);
+    }
+}
@@ -0,0 +1,209 @@
+//! Background tasks.
+//!
+//! Work that would hold up the frame loop, such as reading files or waiting on
+//! the network, can be run on a pool of worker threads with
+//! [`AppContext::spawn`](crate::application::AppContext::spawn). When a task
+//! finishes, its result is sent back to the UI thread through the event loop
+//! and passed to a callback there. The callback can change observable
+//! [`state`](super::state), and the widgets bound to it are updated and
+//! redrawn before the next frame.
+//!
+//! Futures can be run too, with
+//! [`AppContext::spawn_future`](crate::application::AppContext::spawn_future).
+//! Each one is polled on a worker thread that sleeps while it waits to be
+//! woken, so this suits futures that don't depend on a runtime of their own.
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    future::Future,
+    num::NonZeroUsize,
+    panic::AssertUnwindSafe,
+    pin::pin,
+    sync::{mpsc, Arc, Mutex},
+    task::{Context, Poll, Wake, Waker},
+    thread::Thread,
+};
+
+use crate::shell::EventLoopProxy;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+pub(crate) type TaskResult = Box<dyn Any + Send>;
+
+pub(crate) type Completion = Box<dyn FnOnce(TaskResult)>;
+
+/// Sent to the event loop when a task finishes.
+pub(crate) struct Finished {
+    id: u64,
+    /// The value that the task returned, or `None` if it panicked.
+    result: Option<TaskResult>,
+}
+
+/// A fixed number of worker threads that run jobs in the order they were
+/// given.
+struct Pool {
+    sender: mpsc::Sender<Job>,
+}
+
+impl Pool {
+    fn new(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..threads {
+            let receiver = receiver.clone();
+            std::thread::Builder::new()
+                .name(format!("fathom-worker-{i}"))
+                .spawn(move || loop {
+                    // The lock is released before the job runs, so that the
+                    // other workers can take jobs in the meantime.
+                    let job = receiver.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => job(),
+                        // The pool was dropped.
+                        Err(_) => break,
+                    }
+                })
+                .unwrap();
+        }
+
+        Self { sender }
+    }
+
+    fn execute(&self, job: Job) {
+        // The workers only stop once the sender is dropped.
+        self.sender.send(job).unwrap();
+    }
+}
+
+/// The tasks spawned through an application's context, and the callbacks
+/// waiting on them.
+pub(crate) struct Tasks {
+    proxy: EventLoopProxy,
+    /// Started with the first task, so that applications that don't use
+    /// tasks don't have idle threads.
+    pool: Option<Pool>,
+    next_id: u64,
+    pending: HashMap<u64, Completion>,
+}
+
+impl Tasks {
+    pub fn new(proxy: EventLoopProxy) -> Self {
+        Self {
+            proxy,
+            pool: None,
+            next_id: 0,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Runs `task` on a worker thread, and keeps `on_done` until its result is
+    /// passed to [`Self::finish`].
+    pub fn spawn(
+        &mut self,
+        task: impl FnOnce() -> TaskResult + Send + 'static,
+        on_done: Completion,
+    ) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, on_done);
+
+        let proxy = self.proxy.clone();
+        let pool = self.pool.get_or_insert_with(|| {
+            Pool::new(std::thread::available_parallelism().map_or(4, NonZeroUsize::get))
+        });
+
+        pool.execute(Box::new(move || {
+            // A task that panics only loses its own result.
+            let result = std::panic::catch_unwind(AssertUnwindSafe(task)).ok();
+            // Sending fails if the application has exited, in which case
+            // nobody is waiting for the result.
+            let _ = proxy.send(Finished { id, result });
+        }));
+    }
+
+    /// Takes the callback for a task that finished, along with the task's
+    /// result. Returns `None` if the task panicked, since the callback is
+    /// never called in that case.
+    pub fn finish(&mut self, finished: Finished) -> Option<(Completion, TaskResult)> {
+        let on_done = self.pending.remove(&finished.id)?;
+        finished.result.map(|result| (on_done, result))
+    }
+}
+
+/// Polls `future` on the current thread until it completes, sleeping whenever
+/// it is waiting to be woken.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            // Parking may wake spuriously, in which case the future is polled
+            // again and stays pending.
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_jobs() {
+        let pool = Pool::new(3);
+        let (sender, receiver) = mpsc::channel();
+
+        for i in 0..10 {
+            let sender = sender.clone();
+            pool.execute(Box::new(move || sender.send(i * i).unwrap()));
+        }
+
+        let mut results = receiver.iter().take(10).collect::<Vec<_>>();
+        results.sort_unstable();
+        assert_eq!(results, [0, 1, 4, 9, 16, 25, 36, 49, 64, 81]);
+    }
+
+    #[test]
+    fn block_on_future() {
+        /// Completes once another thread has woken it.
+        struct WokenLater {
+            done: Arc<Mutex<bool>>,
+        }
+
+        impl Future for WokenLater {
+            type Output = u32;
+
+            fn poll(self: std::pin::Pin<&mut Self>, context: &mut Context) -> Poll<u32> {
+                if *self.done.lock().unwrap() {
+                    return Poll::Ready(42);
+                }
+
+                let done = self.done.clone();
+                let waker = context.waker().clone();
+                std::thread::spawn(move || {
+                    *done.lock().unwrap() = true;
+                    waker.wake();
+                });
+                Poll::Pending
+            }
+        }
+
+        let future = WokenLater {
+            done: Arc::new(Mutex::new(false)),
+        };
+        assert_eq!(block_on(future), 42);
+    }
+}
@@ -0,0 +1,681 @@
+//! Drives a widget tree without a window, for testing widgets.
+//!
+//! A [`TestHarness`] owns a widget tree and does what the application does
+//! for a window: it passes input to the widgets, lays them out, and draws them
+//! into a [`DrawCommandList`]. Time only passes when the test advances it, so
+//! animations and timers are deterministic.
+//!
+//! ```ignore
+//! let mut harness = TestHarness::new(Fill::new(Color::RED), extent);
+//! harness.click(Point::new(5, 5));
+//! harness.frame();
+//! assert_eq!(harness.color_at(Point::new(5, 5)), Some(Color::RED));
+//! ```
+//!
+//! Text is measured with the harness's fonts (see
+//! [`TestHarness::with_fonts`]), but isn't drawn since drawing it needs a
+//! glyph atlas on a graphics device.
+//!
+//! ## Golden Images
+//!
+//! [`TestHarness::render`] draws the window with the
+//! [software rasterizer](crate::gfx::software), and [`assert_matches_golden`]
+//! compares the result with a reference image stored next to the tests. Set
+//! the `FATHOM_UPDATE_GOLDEN` environment variable to write the reference
+//! images instead of comparing against them, after checking that the widgets
+//! look as they should.
+//!
+//! ```ignore
+//! let image = harness.render(Color::BLACK);
+//! assert_matches_golden(&image, "tests/golden/split_panel.png", Tolerance::EXACT);
+//! ```
+
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Point, Rect, ScaleFactor},
+        pixel_buffer::{Layout, PixelBuffer},
+        software, DrawCommandList,
+    },
+    io::image::{decode_png, encode_png},
+    shell::event::{Key, Modifiers},
+    text::FontCollection,
+};
+
+use super::{
+    hit_test,
+    input::{ButtonState, FocusId, Input, MouseButton},
+    state,
+    widgets::{
+        animate, bind::sync_state, drag_drop::DragDrop, drop_files, DrawContext, FileDialogRequest,
+        LayoutContext, TimerRequest, UpdateContext, Widget,
+    },
+};
+
+/// A timer started by a widget, which fires when the harness's clock passes
+/// its deadline.
+struct Timer {
+    deadline: Instant,
+    request: TimerRequest,
+}
+
+/// Owns a widget tree and drives it as a window would, without one. See the
+/// [module documentation](self) for details.
+#[must_use]
+pub struct TestHarness<W: Widget> {
+    root: W,
+    extent: Extent,
+    input: Input,
+    layout_context: LayoutContext,
+    drag_drop: DragDrop,
+    draw_commands: DrawCommandList,
+    /// The region of the window that needs to be redrawn, accumulated until
+    /// the next frame.
+    damage: Rect,
+    /// The region of the window that was redrawn by the last frame.
+    last_damage: Rect,
+    /// The time as far as the widgets are concerned, which only changes with
+    /// [`Self::advance`].
+    now: Instant,
+    /// The [`state::generation`] when the widgets were last synchronized
+    /// with observable state.
+    state_generation: u64,
+    timers: Vec<Timer>,
+    file_dialogs: Vec<FileDialogRequest>,
+}
+
+impl<W: Widget> TestHarness<W> {
+    /// Creates a harness for `root` in a window of size `extent`, and lays
+    /// it out.
+    pub fn new(root: W, extent: Extent) -> Self {
+        let mut harness = Self {
+            root,
+            extent,
+            input: Input::default(),
+            layout_context: LayoutContext::new(ScaleFactor::ONE),
+            drag_drop: DragDrop::new(),
+            draw_commands: DrawCommandList::new(),
+            damage: Rect::new(Point::zero(), extent),
+            last_damage: Rect::zero(),
+            now: Instant::now(),
+            state_generation: state::generation(),
+            timers: Vec::new(),
+            file_dialogs: Vec::new(),
+        };
+        harness.layout();
+        harness
+    }
+
+    /// Measures text with `fonts`, and lays the widgets out again.
+    pub fn with_fonts(mut self, fonts: Rc<RefCell<FontCollection>>) -> Self {
+        self.layout_context.set_fonts(fonts);
+        self.layout();
+        self
+    }
+
+    /// Lays the widgets out at `scale_factor` instead of at 1.
+    pub fn with_scale_factor(mut self, scale_factor: ScaleFactor) -> Self {
+        self.layout_context.set_scale_factor(scale_factor);
+        self.layout();
+        self
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &W {
+        &self.root
+    }
+
+    /// The root widget, for changing it between events. Changes that affect
+    /// the widget's layout or appearance should be marked on its
+    /// [`WidgetState`](super::widgets::WidgetState) as they would be in a
+    /// window.
+    pub fn root_mut(&mut self) -> &mut W {
+        &mut self.root
+    }
+
+    #[must_use]
+    pub fn extent(&self) -> Extent {
+        self.extent
+    }
+
+    /// The input state that the widgets see, such as where the cursor is and
+    /// which widget has the keyboard focus.
+    #[must_use]
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+
+    /// The widget that has the keyboard focus, if any.
+    #[must_use]
+    pub fn focus(&self) -> Option<FocusId> {
+        self.input.focus()
+    }
+
+    /// The time as far as the widgets are concerned.
+    #[must_use]
+    pub fn now(&self) -> Instant {
+        self.now
+    }
+
+    /// The region of the window that needs to be redrawn since the last
+    /// frame.
+    #[must_use]
+    pub fn damage(&self) -> Rect {
+        self.damage
+    }
+
+    /// The region of the window that the last frame redrew, or an empty
+    /// rectangle if nothing changed.
+    #[must_use]
+    pub fn last_damage(&self) -> Rect {
+        self.last_damage
+    }
+
+    /// The commands recorded by the last frame that drew anything.
+    pub fn draw_commands(&self) -> &DrawCommandList {
+        &self.draw_commands
+    }
+
+    /// The widgets under `point` as of the last layout, from the topmost to
+    /// the root. See [`hit_test`].
+    #[must_use]
+    pub fn widgets_at(&self, point: Point) -> Vec<&dyn Widget> {
+        hit_test(&self.root, point)
+    }
+
+    /// The color drawn at the pixel whose top-left corner is `point` by the
+    /// last frame, or `None` if nothing untextured was drawn there. This is
+    /// the color of the first vertex of the last untextured triangle to cover
+    /// the pixel's center, so it doesn't account for gradients, blending,
+    /// clips, or transforms.
+    #[must_use]
+    pub fn color_at(&self, point: Point) -> Option<Color> {
+        let x = f32::from(point.x) + 0.5;
+        let y = f32::from(point.y) + 0.5;
+
+        self.draw_commands
+            .triangles()
+            .iter()
+            .rev()
+            .filter(|triangle| !triangle.textured)
+            .find(|triangle| {
+                let [a, b, c] = triangle
+                    .vertices
+                    .map(|vertex| (f32::from(vertex.point.x), f32::from(vertex.point.y)));
+
+                // The point is inside if it is on the same side of every edge,
+                // whichever way the triangle winds.
+                let edge = |(x0, y0): (f32, f32), (x1, y1): (f32, f32)| {
+                    (x1 - x0) * (y - y0) - (y1 - y0) * (x - x0)
+                };
+                let sides = [edge(a, b), edge(b, c), edge(c, a)];
+                sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0)
+            })
+            .map(|triangle| triangle.vertices[0].color)
+    }
+
+    /// Takes the file dialogs that widgets asked to show. The test can answer
+    /// them by calling their callbacks.
+    pub fn take_file_dialogs(&mut self) -> Vec<FileDialogRequest> {
+        std::mem::take(&mut self.file_dialogs)
+    }
+
+    /// The number of timers that widgets have started and not yet stopped.
+    #[must_use]
+    pub fn active_timers(&self) -> usize {
+        self.timers.len()
+    }
+
+    /// Resizes the window, and lays the widgets out again.
+    pub fn resize(&mut self, extent: Extent) {
+        self.extent = extent;
+        self.damage = Rect::new(Point::zero(), extent);
+        self.layout();
+    }
+
+    pub fn move_cursor(&mut self, position: Point) {
+        self.input.update_cursor_position(position);
+        self.update();
+    }
+
+    pub fn press(&mut self, button: MouseButton) {
+        self.input.update_mouse_button(button, ButtonState::Pressed);
+        self.update();
+    }
+
+    pub fn release(&mut self, button: MouseButton) {
+        self.input
+            .update_mouse_button(button, ButtonState::Released);
+        self.update();
+    }
+
+    /// Moves the cursor to `position`, and presses and releases the left
+    /// mouse button there.
+    pub fn click(&mut self, position: Point) {
+        self.move_cursor(position);
+        self.press(MouseButton::Left);
+        self.release(MouseButton::Left);
+    }
+
+    /// Presses the left mouse button at `from`, moves the cursor to `to` in
+    /// `steps` equal movements, and releases the button there.
+    pub fn drag(&mut self, from: Point, to: Point, steps: u16) {
+        self.move_cursor(from);
+        self.press(MouseButton::Left);
+
+        let steps = i32::from(steps.max(1));
+        let lerp = |from: i16, to: i16, step: i32| {
+            let from = i32::from(from);
+            (from + (i32::from(to) - from) * step / steps) as i16
+        };
+        for step in 1..=steps {
+            self.move_cursor(Point::new(
+                lerp(from.x.0, to.x.0, step),
+                lerp(from.y.0, to.y.0, step),
+            ));
+        }
+
+        self.release(MouseButton::Left);
+    }
+
+    /// Turns the mouse wheel, in the units of
+    /// [`crate::shell::event::Window::MouseWheel`].
+    pub fn scroll(&mut self, x: i16, y: i16) {
+        self.input.update_wheel(x, y);
+        self.update();
+    }
+
+    pub fn key(&mut self, key: Key, modifiers: Modifiers) {
+        self.input.update_key(key, modifiers);
+        self.update();
+    }
+
+    /// Types each character of `text` in turn.
+    pub fn type_text(&mut self, text: &str) {
+        for character in text.chars() {
+            self.input.update_text(character);
+            self.update();
+        }
+    }
+
+    /// Drops files on the window at `position`.
+    pub fn drop_files(&mut self, paths: &[std::path::PathBuf], position: Point) {
+        if let Some(damage) = drop_files(&mut self.root, paths, position) {
+            self.damage = self.damage.union(&damage);
+        }
+    }
+
+    /// Changes the input state directly, such as for events without a method
+    /// of their own, and passes the resulting event to the widgets.
+    pub fn send(&mut self, change: impl FnOnce(&mut Input)) {
+        change(&mut self.input);
+        self.update();
+    }
+
+    /// Lays out the widgets that need it, returning the region of the window
+    /// that changed size. Frames do this before drawing.
+    pub fn layout(&mut self) -> Rect {
+        let damage = self.layout_context.begin(&mut self.root, self.extent);
+        self.damage = self.damage.union(&damage);
+        damage
+    }
+
+    /// Draws a frame as the application would: catches the widgets up with
+    /// observable state and animations, lays them out, and draws the parts of
+    /// the window that changed. Returns the region that was redrawn.
+    pub fn frame(&mut self) -> Rect {
+        if self.state_generation != state::generation() {
+            self.state_generation = state::generation();
+            let damage = sync_state(&mut self.root);
+            self.damage = self.damage.union(&damage);
+        }
+
+        let damage = animate(&mut self.root, self.now);
+        self.damage = self.damage.union(&damage);
+
+        self.layout();
+
+        let damage = std::mem::take(&mut self.damage);
+        self.last_damage = damage;
+        if damage.is_empty() {
+            return damage;
+        }
+
+        self.draw_commands.clear();
+        let mut context = DrawContext::new(&mut self.draw_commands, damage);
+        context.set_focus(self.input.focus());
+        context.draw(&self.root);
+        context.draw_overlays(&mut self.root);
+
+        damage
+    }
+
+    /// Draws the whole window over `background` on the CPU and returns the
+    /// image, for comparing with a reference image. This doesn't change what
+    /// was drawn by the last frame.
+    pub fn render(&mut self, background: Color) -> PixelBuffer {
+        let _ = self.layout();
+
+        let window = Rect::new(Point::zero(), self.extent);
+        let mut commands = DrawCommandList::new();
+        let mut context = DrawContext::new(&mut commands, window);
+        context.set_focus(self.input.focus());
+        context.draw(&self.root);
+        context.draw_overlays(&mut self.root);
+
+        software::rasterize(&commands, self.extent, background)
+    }
+
+    /// Moves the clock forward by `duration`, firing the timers that come
+    /// due, and then draws a frame.
+    pub fn advance(&mut self, duration: Duration) -> Rect {
+        let end = self.now + duration;
+
+        // Timers fire in the order that they come due, as many times as they
+        // would have in the time that passed.
+        while let Some(index) = self
+            .timers
+            .iter()
+            .enumerate()
+            .filter(|(_, timer)| timer.deadline <= end)
+            .min_by_key(|(_, timer)| timer.deadline)
+            .map(|(index, _)| index)
+        {
+            let timer = &mut self.timers[index];
+            self.now = timer.deadline;
+            timer.deadline += timer.request.interval.max(Duration::from_millis(1));
+            if !(timer.request.callback)() {
+                let _ = self.timers.swap_remove(index);
+            }
+        }
+
+        self.now = end;
+        self.frame()
+    }
+
+    /// Passes the latest input event to the widgets, and records what they
+    /// asked for.
+    fn update(&mut self) {
+        let focus = self.input.focus();
+
+        let mut context = UpdateContext::new(&mut self.input)
+            .with_window_extent(self.extent)
+            .with_drag_drop(&self.drag_drop);
+        let damage = context.begin(&mut self.root);
+        let redraw_window = context.needs_window_redraw();
+        self.file_dialogs.extend(context.take_file_dialogs());
+        let timers = context.take_timers();
+
+        self.damage = self.damage.union(&damage);
+        if redraw_window || self.input.focus() != focus {
+            self.damage = Rect::new(Point::zero(), self.extent);
+        }
+
+        self.timers.extend(timers.into_iter().map(|request| Timer {
+            deadline: self.now + request.interval,
+            request,
+        }));
+    }
+}
+
+/// How far an image may differ from its reference image and still match.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tolerance {
+    /// The largest difference allowed in any channel of a pixel.
+    pub channel: u8,
+    /// The number of pixels that may differ by more than `channel`.
+    pub pixels: usize,
+}
+
+impl Tolerance {
+    /// Every pixel must match exactly.
+    pub const EXACT: Self = Self {
+        channel: 0,
+        pixels: 0,
+    };
+}
+
+/// Why an image doesn't match its reference image.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Mismatch {
+    #[error("the image is {actual:?}, but the reference image is {expected:?}")]
+    Extent { actual: Extent, expected: Extent },
+    #[error("the image has the {actual:?} layout, but the reference image has {expected:?}")]
+    Layout { actual: Layout, expected: Layout },
+    #[error("{count} pixels differ by up to {max_difference}, starting at {first:?}")]
+    Pixels {
+        count: usize,
+        max_difference: u8,
+        first: Point,
+    },
+}
+
+/// Compares two uncompressed images pixel by pixel.
+///
+/// ## Errors
+///
+/// Returns how the images differ if they have different sizes or layouts, or
+/// more pixels differ than `tolerance` allows.
+///
+/// ## Panics
+///
+/// Panics if the images are block-compressed.
+pub fn compare_images(
+    actual: &PixelBuffer,
+    expected: &PixelBuffer,
+    tolerance: Tolerance,
+) -> Result<(), Mismatch> {
+    if actual.extent() != expected.extent() {
+        return Err(Mismatch::Extent {
+            actual: actual.extent(),
+            expected: expected.extent(),
+        });
+    }
+
+    if actual.layout() != expected.layout() {
+        return Err(Mismatch::Layout {
+            actual: actual.layout(),
+            expected: expected.layout(),
+        });
+    }
+
+    let bytes_per_pixel = actual.layout().bytes_per_pixel();
+    let width = actual.extent().width.0 as usize;
+
+    let mut count = 0;
+    let mut max_difference = 0;
+    let mut first = None;
+
+    let pixels = actual.bytes().chunks_exact(bytes_per_pixel);
+    let expected_pixels = expected.bytes().chunks_exact(bytes_per_pixel);
+    for (index, (a, b)) in pixels.zip(expected_pixels).enumerate() {
+        let difference = a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0);
+
+        if difference > tolerance.channel {
+            count += 1;
+            max_difference = max_difference.max(difference);
+            first.get_or_insert(Point::new((index % width) as i16, (index / width) as i16));
+        }
+    }
+
+    match first {
+        Some(first) if count > tolerance.pixels => Err(Mismatch::Pixels {
+            count,
+            max_difference,
+            first,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Compares `image` with the PNG reference image at `path`, which is relative
+/// to the crate being tested.
+///
+/// If the reference image doesn't exist or the `FATHOM_UPDATE_GOLDEN`
+/// environment variable is set, `image` is written to `path` instead.
+///
+/// ## Panics
+///
+/// Panics if the images don't match, after writing `image` next to the
+/// reference image with the extension `.actual.png` so that the two can be
+/// compared by eye. Also panics if either image can't be read or written.
+pub fn assert_matches_golden(image: &PixelBuffer, path: impl AsRef<Path>, tolerance: Tolerance) {
+    let path = golden_path(path.as_ref());
+
+    if !path.exists() || std::env::var_os("FATHOM_UPDATE_GOLDEN").is_some() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("failed to create golden image directory");
+        }
+        write_png(image, &path);
+        return;
+    }
+
+    let bytes = std::fs::read(&path).expect("failed to read golden image");
+    let expected = decode_png(&bytes).expect("failed to decode golden image");
+
+    if let Err(mismatch) = compare_images(image, &expected, tolerance) {
+        let actual_path = path.with_extension("actual.png");
+        write_png(image, &actual_path);
+        panic!(
+            "image does not match {}: {mismatch} (see {})",
+            path.display(),
+            actual_path.display()
+        );
+    }
+}
+
+/// Resolves a relative path against the directory of the crate being tested,
+/// since tests may run from anywhere.
+fn golden_path(path: &Path) -> PathBuf {
+    match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(root) if path.is_relative() => Path::new(&root).join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn write_png(image: &PixelBuffer, path: &Path) {
+    let bytes = encode_png(image).expect("failed to encode image");
+    std::fs::write(path, bytes).expect("failed to write image");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::geometry::{Px, Rect},
+        gui::widgets::{
+            debug::Fill,
+            split_panel::{Axis, SplitPanel},
+        },
+    };
+
+    use super::*;
+
+    fn split_panel() -> TestHarness<SplitPanel<Fill>> {
+        TestHarness::new(
+            SplitPanel::with_children(Axis::X, vec![Fill::new(Color::RED), Fill::new(Color::BLUE)]),
+            Extent {
+                width: Px(104),
+                height: Px(100),
+            },
+        )
+    }
+
+    #[test]
+    fn draw_frames() {
+        let mut harness = split_panel();
+        assert_eq!(harness.frame(), Rect::new(Point::zero(), harness.extent()));
+        assert_eq!(harness.color_at(Point::new(10, 10)), Some(Color::RED));
+        assert_eq!(harness.color_at(Point::new(90, 10)), Some(Color::BLUE));
+        assert_eq!(harness.color_at(Point::new(200, 10)), None);
+
+        // Nothing changed, so nothing is drawn.
+        assert!(harness.frame().is_empty());
+        assert_eq!(harness.widgets_at(Point::new(90, 10)).len(), 2);
+    }
+
+    #[test]
+    fn drag_divider() {
+        let mut harness = split_panel();
+        let _ = harness.frame();
+
+        harness.drag(Point::new(51, 10), Point::new(71, 10), 4);
+        let _ = harness.frame();
+
+        let first = harness.root().child(0).unwrap().widget_state().rect();
+        assert_eq!(first.width(), Px(70));
+        assert_eq!(harness.color_at(Point::new(60, 10)), Some(Color::RED));
+    }
+
+    #[test]
+    fn fire_timers() {
+        let ticks = Rc::new(RefCell::new(0));
+        let count = ticks.clone();
+        let mut harness = split_panel();
+        harness.timers.push(Timer {
+            deadline: harness.now() + Duration::from_millis(10),
+            request: TimerRequest {
+                interval: Duration::from_millis(10),
+                callback: Box::new(move || {
+                    *count.borrow_mut() += 1;
+                    *count.borrow() < 3
+                }),
+            },
+        });
+
+        let _ = harness.advance(Duration::from_millis(25));
+        assert_eq!(*ticks.borrow(), 2);
+        let _ = harness.advance(Duration::from_millis(100));
+        assert_eq!(*ticks.borrow(), 3);
+        assert_eq!(harness.active_timers(), 0);
+    }
+
+    #[test]
+    fn compare_with_tolerance() {
+        let mut harness = split_panel();
+        let image = harness.render(Color::BLACK);
+        assert_eq!(image.extent(), harness.extent());
+        assert_eq!(compare_images(&image, &image, Tolerance::EXACT), Ok(()));
+
+        let mut bytes = image.bytes().to_vec();
+        bytes[0] -= 2;
+        let changed = PixelBuffer::new(
+            image.layout(),
+            image.color_space(),
+            image.extent(),
+            bytes.into_boxed_slice(),
+        );
+
+        assert_eq!(
+            compare_images(&changed, &image, Tolerance::EXACT),
+            Err(Mismatch::Pixels {
+                count: 1,
+                max_difference: 2,
+                first: Point::zero(),
+            })
+        );
+        let tolerance = Tolerance {
+            channel: 2,
+            pixels: 0,
+        };
+        assert_eq!(compare_images(&changed, &image, tolerance), Ok(()));
+    }
+
+    #[test]
+    fn golden_split_panel() {
+        let mut harness = split_panel();
+        let image = harness.render(Color::BLACK);
+        assert_matches_golden(&image, "tests/golden/split_panel.png", Tolerance::EXACT);
+    }
+}
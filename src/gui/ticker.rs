@@ -0,0 +1,211 @@
+//! Per-frame callbacks for animations.
+//!
+//! A callback added to the [`Ticker`] is called once for every frame that the
+//! application draws, for as long as it returns `true`. While any callback is
+//! registered, the application draws frames without waiting for input, paced
+//! by the display's refresh rate. Once the last one finishes, the event loop
+//! goes back to waiting, so an idle application doesn't use the CPU.
+//!
+//! Callbacks typically change observable [`state`](super::state), which the
+//! widgets bound to it show in the same frame. Widgets that animate
+//! themselves can use
+//! [`Widget::accept_animation_frame`](super::widgets::Widget::accept_animation_frame)
+//! instead.
+
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// Identifies a callback added with [`Ticker::add`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TickId(u64);
+
+/// The timing of a frame, as given to ticker callbacks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tick {
+    /// When the frame is being drawn.
+    pub now: Instant,
+    /// The time since the last frame, or zero for the callback's first frame.
+    pub delta: Duration,
+    /// The time since the callback's first frame.
+    pub elapsed: Duration,
+}
+
+type Callback = Box<dyn FnMut(&Tick) -> bool>;
+
+struct Entry {
+    id: TickId,
+    callback: Callback,
+    /// When the callback was first called, or `None` if it hasn't been yet.
+    started: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Inner {
+    next_id: u64,
+    entries: Vec<Entry>,
+    /// Callbacks removed while the entries were taken out to be called.
+    removed: Vec<TickId>,
+    last_tick: Option<Instant>,
+}
+
+/// Calls registered callbacks once per frame. Clones refer to the same
+/// callbacks. See [`AppContext::ticker`](crate::application::AppContext::ticker).
+#[derive(Clone, Default)]
+pub struct Ticker {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Ticker {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `callback` once per frame, starting with the next one, until it
+    /// returns `false` or is removed.
+    pub fn add(&self, callback: impl FnMut(&Tick) -> bool + 'static) -> TickId {
+        let mut inner = self.inner.borrow_mut();
+        let id = TickId(inner.next_id);
+        inner.next_id += 1;
+        inner.entries.push(Entry {
+            id,
+            callback: Box::new(callback),
+            started: None,
+        });
+        id
+    }
+
+    /// Stops calling a callback. Does nothing if it has already finished.
+    pub fn remove(&self, id: TickId) {
+        let mut inner = self.inner.borrow_mut();
+        inner.entries.retain(|entry| entry.id != id);
+        inner.removed.push(id);
+    }
+
+    /// Checks if any callback is waiting for the next frame.
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        !self.inner.borrow().entries.is_empty()
+    }
+
+    /// Calls every callback for a frame drawn at `now`. The application does
+    /// this once per frame.
+    pub fn tick(&self, now: Instant) {
+        let (entries, last_tick) = {
+            let mut inner = self.inner.borrow_mut();
+            inner.removed.clear();
+            let last_tick = inner.last_tick.replace(now);
+            (std::mem::take(&mut inner.entries), last_tick)
+        };
+
+        // The last tick is forgotten whenever the ticker goes idle, so that
+        // the pause isn't reported as one long frame.
+        let delta = last_tick.map_or(Duration::ZERO, |last| now.saturating_duration_since(last));
+
+        // The callbacks are called without borrowing the ticker, so that
+        // they can add and remove callbacks.
+        let mut kept = Vec::with_capacity(entries.len());
+        for mut entry in entries {
+            if self.inner.borrow().removed.contains(&entry.id) {
+                continue;
+            }
+
+            let started = *entry.started.get_or_insert(now);
+            let tick = Tick {
+                now,
+                delta: if started == now {
+                    Duration::ZERO
+                } else {
+                    delta
+                },
+                elapsed: now.saturating_duration_since(started),
+            };
+
+            if (entry.callback)(&tick) {
+                kept.push(entry);
+            }
+        }
+
+        let mut inner = self.inner.borrow_mut();
+        kept.retain(|entry| !inner.removed.contains(&entry.id));
+        // Callbacks added during the tick start with the next one.
+        kept.append(&mut inner.entries);
+        inner.entries = kept;
+
+        if inner.entries.is_empty() {
+            inner.last_tick = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_each_frame() {
+        let ticker = Ticker::new();
+        let ticks = Rc::new(RefCell::new(Vec::new()));
+
+        let log = ticks.clone();
+        ticker.add(move |tick| {
+            log.borrow_mut().push((tick.delta, tick.elapsed));
+            tick.elapsed < Duration::from_millis(32)
+        });
+        assert!(ticker.is_active());
+
+        let start = Instant::now();
+        for frame in 0..5 {
+            ticker.tick(start + Duration::from_millis(16) * frame);
+        }
+
+        let frame = Duration::from_millis(16);
+        assert_eq!(
+            *ticks.borrow(),
+            [
+                (Duration::ZERO, Duration::ZERO),
+                (frame, frame),
+                (frame, frame * 2)
+            ]
+        );
+        assert!(!ticker.is_active());
+    }
+
+    #[test]
+    fn add_and_remove_during_tick() {
+        let ticker = Ticker::new();
+        let count = Rc::new(RefCell::new(0));
+
+        let first = {
+            let count = count.clone();
+            ticker.add(move |_| {
+                *count.borrow_mut() += 1;
+                true
+            })
+        };
+
+        let inner = ticker.clone();
+        let added = count.clone();
+        ticker.add(move |_| {
+            // Removes the first callback, and adds one that starts next
+            // frame.
+            inner.remove(first);
+            let added = added.clone();
+            inner.add(move |_| {
+                *added.borrow_mut() += 10;
+                false
+            });
+            false
+        });
+
+        let now = Instant::now();
+        ticker.tick(now);
+        assert_eq!(*count.borrow(), 1);
+        ticker.tick(now + Duration::from_millis(16));
+        assert_eq!(*count.borrow(), 11);
+        assert!(!ticker.is_active());
+    }
+}
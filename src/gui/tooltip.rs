@@ -0,0 +1,390 @@
+//! Tooltips shown after the cursor rests on a widget.
+//!
+//! Widgets declare their tooltip through [`Widget::tooltip`], or by being
+//! wrapped in a [`WithTooltip`]. Each window has a [`TooltipManager`] that
+//! watches the cursor, and shows the tooltip of the innermost widget under it
+//! once the cursor has stayed there for the hover delay. Tooltips are drawn
+//! above the widget tree and its overlays, and are placed so that they stay
+//! inside the window.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Offset, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    text::{ShapedLine, TextStyle},
+};
+
+use super::widgets::{
+    hit_test::hit_test, BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext,
+    Widget, WidgetState,
+};
+
+/// How long the cursor has to rest on a widget before its tooltip is shown.
+pub const DEFAULT_HOVER_DELAY: Duration = Duration::from_millis(500);
+
+/// The space between the edge of a tooltip and its text.
+const PADDING: Px = Px(4);
+
+/// How far below and to the right of the cursor a tooltip is placed.
+const CURSOR_OFFSET: Offset = Offset {
+    x: Px(12),
+    y: Px(16),
+};
+
+/// Tooltips wider than this are wrapped onto several lines.
+const MAX_WIDTH: f32 = 320.0;
+
+const BACKGROUND: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 0.9,
+    a: 1.0,
+};
+
+const BORDER: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+/// The content of a tooltip.
+#[derive(Clone, Debug)]
+pub struct Tooltip {
+    pub text: String,
+    pub style: TextStyle,
+    pub color: Color,
+}
+
+impl Tooltip {
+    pub fn new(text: impl Into<String>, style: TextStyle) -> Self {
+        Self {
+            text: text.into(),
+            style,
+            color: Color::BLACK,
+        }
+    }
+}
+
+/// A tooltip that has been laid out and is being shown.
+struct Shown {
+    /// The bounds of the tooltip, in absolute coordinates.
+    rect: Rect,
+    lines: Vec<(ShapedLine, Px)>,
+    size: f32,
+    color: Color,
+}
+
+enum State {
+    /// The cursor isn't over a widget with a tooltip.
+    Idle,
+    /// The cursor is over a widget with a tooltip, which will be shown once
+    /// the hover delay has passed.
+    Pending {
+        tooltip: Tooltip,
+        anchor: Rect,
+        since: Instant,
+    },
+    Shown {
+        tooltip: Tooltip,
+        anchor: Rect,
+        shown: Shown,
+    },
+    /// The tooltip was hidden by a click or key press, and won't be shown
+    /// again until the cursor leaves the widget.
+    Dismissed { tooltip: Tooltip, anchor: Rect },
+}
+
+/// Tracks the widget under the cursor and shows its tooltip after a delay.
+#[must_use]
+pub struct TooltipManager {
+    delay: Duration,
+    state: State,
+}
+
+impl Default for TooltipManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TooltipManager {
+    pub fn new() -> Self {
+        Self {
+            delay: DEFAULT_HOVER_DELAY,
+            state: State::Idle,
+        }
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Checks if a tooltip is waiting for the hover delay to pass, in which
+    /// case [`Self::tick`] needs to be called until it is shown.
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        matches!(self.state, State::Pending { .. })
+    }
+
+    /// Finds the tooltip under the cursor after it moves to `cursor`. Returns
+    /// the region of the window that needs to be redrawn because a tooltip was
+    /// hidden, which is empty if nothing changed.
+    pub fn hover(&mut self, root: &dyn Widget, cursor: Point, now: Instant) -> Rect {
+        let target = find_tooltip(root, cursor);
+
+        // Moving within the same widget leaves the tooltip as it is.
+        let current = match &self.state {
+            State::Idle => None,
+            State::Pending {
+                tooltip, anchor, ..
+            }
+            | State::Shown {
+                tooltip, anchor, ..
+            }
+            | State::Dismissed { tooltip, anchor } => Some((tooltip, *anchor)),
+        };
+        if let (Some((tooltip, anchor)), Some((target, target_anchor))) = (current, &target) {
+            if tooltip.text == target.text && anchor == *target_anchor {
+                return Rect::zero();
+            }
+        }
+
+        let damage = self.shown_rect();
+        self.state = match target {
+            Some((tooltip, anchor)) => State::Pending {
+                tooltip,
+                anchor,
+                since: now,
+            },
+            None => State::Idle,
+        };
+        damage
+    }
+
+    /// Hides the tooltip until the cursor moves to another widget. Call this
+    /// when the user clicks, scrolls, or types. Returns the region of the
+    /// window that needs to be redrawn.
+    pub fn dismiss(&mut self) -> Rect {
+        let damage = self.shown_rect();
+        self.state = match std::mem::replace(&mut self.state, State::Idle) {
+            State::Idle => State::Idle,
+            State::Pending {
+                tooltip, anchor, ..
+            }
+            | State::Shown {
+                tooltip, anchor, ..
+            }
+            | State::Dismissed { tooltip, anchor } => State::Dismissed { tooltip, anchor },
+        };
+        damage
+    }
+
+    /// Shows the pending tooltip if the hover delay has passed, placing it
+    /// near `cursor` within a window of size `window`. Returns the region of
+    /// the window that needs to be redrawn.
+    pub fn tick(
+        &mut self,
+        context: &LayoutContext,
+        cursor: Point,
+        window: Extent,
+        now: Instant,
+    ) -> Rect {
+        let (tooltip, anchor) = match &self.state {
+            State::Pending {
+                tooltip,
+                anchor,
+                since,
+            } if now.duration_since(*since) >= self.delay => (tooltip.clone(), *anchor),
+            _ => return Rect::zero(),
+        };
+
+        let metrics = context.measure_text(&tooltip.text, &tooltip.style, Some(MAX_WIDTH));
+        let extent = Extent {
+            width: Px::from(metrics.width.ceil()) + PADDING + PADDING,
+            height: Px::from(metrics.height.ceil()) + PADDING + PADDING,
+        };
+        let rect = place(cursor, extent, window);
+
+        let lines = metrics
+            .lines
+            .into_iter()
+            .map(|line| (line.shaped, Px::from(line.baseline.round())))
+            .collect();
+
+        self.state = State::Shown {
+            shown: Shown {
+                rect,
+                lines,
+                size: tooltip.style.size,
+                color: tooltip.color,
+            },
+            tooltip,
+            anchor,
+        };
+        rect
+    }
+
+    /// Draws the tooltip, if one is shown. Call this after drawing the widget
+    /// tree and its overlays so that the tooltip is drawn above them.
+    pub fn draw(&self, canvas: &mut DrawContext) {
+        let shown = match &self.state {
+            State::Shown { shown, .. } => shown,
+            _ => return,
+        };
+
+        canvas.draw_rect(shown.rect, &Paint::Fill { color: BACKGROUND });
+        canvas.stroke_rect(shown.rect, &Stroke::solid(BORDER, 1.0));
+
+        for (line, baseline) in &shown.lines {
+            canvas.draw_text(
+                line,
+                Point::new(
+                    shown.rect.left + PADDING,
+                    shown.rect.top + PADDING + *baseline,
+                ),
+                shown.size,
+                shown.color,
+            );
+        }
+    }
+
+    fn shown_rect(&self) -> Rect {
+        match &self.state {
+            State::Shown { shown, .. } => shown.rect,
+            _ => Rect::zero(),
+        }
+    }
+}
+
+/// Finds the tooltip of the innermost widget at `point`, along with that
+/// widget's bounds.
+fn find_tooltip(root: &dyn Widget, point: Point) -> Option<(Tooltip, Rect)> {
+    hit_test(root, point).into_iter().find_map(|widget| {
+        widget
+            .tooltip()
+            .map(|tooltip| (tooltip.clone(), widget.widget_state().rect()))
+    })
+}
+
+/// Places a tooltip of size `extent` below and to the right of `cursor`,
+/// moving it so that it stays inside a window of size `window`. If there isn't
+/// room below the cursor, it is placed above it instead.
+fn place(cursor: Point, extent: Extent, window: Extent) -> Rect {
+    let right = window.width.saturating_sub(extent.width).max(Px(0));
+    let left = (cursor.x + CURSOR_OFFSET.x).min(right).max(Px(0));
+
+    let below = cursor.y + CURSOR_OFFSET.y;
+    let top = if below + extent.height <= window.height {
+        below
+    } else {
+        (cursor.y - extent.height).max(Px(0))
+    };
+
+    Rect::new(Point::new(left, top), extent)
+}
+
+/// Shows a tooltip for its child.
+#[must_use]
+pub struct WithTooltip<W: Widget> {
+    widget_state: WidgetState,
+    pub tooltip: Tooltip,
+    pub child: W,
+}
+
+impl<W: Widget> WithTooltip<W> {
+    pub fn new(tooltip: Tooltip, child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            tooltip,
+            child,
+        }
+    }
+}
+
+impl<W: Widget> Widget for WithTooltip<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(&mut self.child);
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = context.layout(&mut self.child, constraints);
+        context.position_widget(&mut self.child, Offset::zero(), extent);
+        extent
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+
+    fn tooltip(&self) -> Option<&Tooltip> {
+        Some(&self.tooltip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn place_tooltip() {
+        let window = Extent {
+            width: Px(200),
+            height: Px(100),
+        };
+        let extent = Extent {
+            width: Px(50),
+            height: Px(20),
+        };
+
+        // Below and to the right of the cursor when there's room.
+        assert_eq!(
+            place(Point::new(10, 10), extent, window),
+            Rect::new(Point::new(22, 26), extent)
+        );
+
+        // Pushed left at the right edge of the window.
+        assert_eq!(
+            place(Point::new(190, 10), extent, window),
+            Rect::new(Point::new(150, 26), extent)
+        );
+
+        // Above the cursor at the bottom of the window.
+        assert_eq!(
+            place(Point::new(10, 90), extent, window),
+            Rect::new(Point::new(22, 70), extent)
+        );
+
+        // Kept inside a window that is too small for it.
+        let small = Extent {
+            width: Px(30),
+            height: Px(10),
+        };
+        assert_eq!(
+            place(Point::new(5, 5), extent, small),
+            Rect::new(Point::zero(), extent)
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! Undo and redo.
+//!
+//! Changes that can be undone are recorded as [`Command`]s on an
+//! [`UndoStack`] after they have been made. The application has one stack,
+//! which Control+Z undoes and Control+Y or Control+Shift+Z redoes when the
+//! focused widget doesn't handle those keys itself. See
+//! [`Application::undo_stack`](crate::application::Application::undo_stack).
+//!
+//! Text widgets record their edits on a stack of their own unless they're
+//! given one to share, so that typing in a text input is undone before
+//! anything else. Commands made in quick succession can merge, so that
+//! undoing a word typed one character at a time undoes the whole word.
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// How soon a command must follow the last one to be merged into it.
+pub const MERGE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many commands a stack keeps unless told otherwise. The oldest commands
+/// are forgotten first.
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// A change that has been made and can be undone.
+pub trait Command {
+    /// Reverses the change.
+    fn undo(&mut self);
+
+    /// Makes the change again after it was undone.
+    fn redo(&mut self);
+
+    /// Tries to combine `next`, which was made right after this command, into
+    /// this one so that they are undone together. `next` can be downcast to
+    /// find out what it is. Returns `false` if the commands can't be merged,
+    /// which is the default.
+    fn merge(&mut self, next: &dyn Any) -> bool {
+        let _ = next;
+        false
+    }
+}
+
+struct Entry {
+    command: Box<dyn Command>,
+    /// When the command was last pushed or merged into.
+    time: Instant,
+}
+
+struct Inner {
+    undo: Vec<Entry>,
+    redo: Vec<Box<dyn Command>>,
+    limit: usize,
+    /// Whether the next command may be merged into the one on top of the
+    /// stack. Cleared by undoing, redoing, and [`UndoStack::seal`].
+    can_merge: bool,
+}
+
+/// A history of commands that can be undone and redone. Clones refer to the
+/// same history.
+#[derive(Clone)]
+pub struct UndoStack {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Default for UndoStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UndoStack {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_limit(DEFAULT_LIMIT)
+    }
+
+    /// Creates a stack that keeps at most `limit` commands.
+    #[must_use]
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(Inner {
+                undo: Vec::new(),
+                redo: Vec::new(),
+                limit,
+                can_merge: false,
+            })),
+        }
+    }
+
+    /// Records a change that has just been made. Anything that was undone can
+    /// no longer be redone.
+    pub fn push<C: Command + 'static>(&self, command: C) {
+        self.push_at(command, Instant::now());
+    }
+
+    /// Records a change that was made at `now`, merging it into the previous
+    /// command if that was made within [`MERGE_INTERVAL`] and agrees to it.
+    pub fn push_at<C: Command + 'static>(&self, command: C, now: Instant) {
+        let mut inner = self.inner.borrow_mut();
+        inner.redo.clear();
+
+        let can_merge = inner.can_merge;
+        if let Some(top) = inner.undo.last_mut() {
+            if can_merge
+                && now.saturating_duration_since(top.time) <= MERGE_INTERVAL
+                && top.command.merge(&command)
+            {
+                top.time = now;
+                return;
+            }
+        }
+
+        inner.undo.push(Entry {
+            command: Box::new(command),
+            time: now,
+        });
+        inner.can_merge = true;
+
+        if inner.undo.len() > inner.limit {
+            let excess = inner.undo.len() - inner.limit;
+            let _ = inner.undo.drain(..excess);
+        }
+    }
+
+    /// Undoes the most recent command. Returns `false` if there was nothing to
+    /// undo.
+    pub fn undo(&self) -> bool {
+        let entry = {
+            let mut inner = self.inner.borrow_mut();
+            inner.can_merge = false;
+            inner.undo.pop()
+        };
+
+        match entry {
+            Some(mut entry) => {
+                // The stack isn't borrowed while the command runs, in case it
+                // looks at the stack.
+                entry.command.undo();
+                self.inner.borrow_mut().redo.push(entry.command);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redoes the most recently undone command. Returns `false` if there was
+    /// nothing to redo.
+    pub fn redo(&self) -> bool {
+        let command = {
+            let mut inner = self.inner.borrow_mut();
+            inner.can_merge = false;
+            inner.redo.pop()
+        };
+
+        match command {
+            Some(mut command) => {
+                command.redo();
+                self.inner.borrow_mut().undo.push(Entry {
+                    command,
+                    time: Instant::now(),
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[must_use]
+    pub fn can_undo(&self) -> bool {
+        !self.inner.borrow().undo.is_empty()
+    }
+
+    #[must_use]
+    pub fn can_redo(&self) -> bool {
+        !self.inner.borrow().redo.is_empty()
+    }
+
+    /// Keeps the next command from being merged into the last one, such as
+    /// after the caret moves between edits.
+    pub fn seal(&self) {
+        self.inner.borrow_mut().can_merge = false;
+    }
+
+    /// Forgets every command.
+    pub fn clear(&self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.undo.clear();
+        inner.redo.clear();
+        inner.can_merge = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Adds to a shared number, merging with other additions.
+    struct Add {
+        total: Rc<RefCell<i32>>,
+        amount: i32,
+    }
+
+    impl Add {
+        fn push(stack: &UndoStack, total: &Rc<RefCell<i32>>, amount: i32, now: Instant) {
+            *total.borrow_mut() += amount;
+            stack.push_at(
+                Add {
+                    total: total.clone(),
+                    amount,
+                },
+                now,
+            );
+        }
+    }
+
+    impl Command for Add {
+        fn undo(&mut self) {
+            *self.total.borrow_mut() -= self.amount;
+        }
+
+        fn redo(&mut self) {
+            *self.total.borrow_mut() += self.amount;
+        }
+
+        fn merge(&mut self, next: &dyn Any) -> bool {
+            match next.downcast_ref::<Self>() {
+                Some(next) => {
+                    self.amount += next.amount;
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let stack = UndoStack::new();
+        let total = Rc::new(RefCell::new(0));
+        let now = Instant::now();
+
+        Add::push(&stack, &total, 1, now);
+        stack.seal();
+        Add::push(&stack, &total, 2, now);
+        assert_eq!(*total.borrow(), 3);
+
+        assert!(stack.undo());
+        assert_eq!(*total.borrow(), 1);
+        assert!(stack.redo());
+        assert_eq!(*total.borrow(), 3);
+        assert!(!stack.redo());
+
+        assert!(stack.undo());
+        assert!(stack.undo());
+        assert!(!stack.undo());
+        assert_eq!(*total.borrow(), 0);
+
+        // A new command can't be redone past.
+        assert!(stack.can_redo());
+        Add::push(&stack, &total, 5, now);
+        assert!(!stack.can_redo());
+    }
+
+    #[test]
+    fn merge_rapid_commands() {
+        let stack = UndoStack::new();
+        let total = Rc::new(RefCell::new(0));
+        let now = Instant::now();
+
+        Add::push(&stack, &total, 1, now);
+        Add::push(&stack, &total, 1, now + MERGE_INTERVAL / 2);
+        Add::push(&stack, &total, 1, now + MERGE_INTERVAL);
+        Add::push(&stack, &total, 1, now + MERGE_INTERVAL * 3);
+        assert_eq!(*total.borrow(), 4);
+
+        assert!(stack.undo());
+        assert_eq!(*total.borrow(), 3);
+        assert!(stack.undo());
+        assert_eq!(*total.borrow(), 0);
+    }
+
+    #[test]
+    fn forget_old_commands() {
+        let stack = UndoStack::with_limit(2);
+        let total = Rc::new(RefCell::new(0));
+
+        for amount in [1, 2, 3] {
+            Add::push(&stack, &total, amount, Instant::now());
+            stack.seal();
+        }
+
+        assert!(stack.undo());
+        assert!(stack.undo());
+        assert!(!stack.undo());
+        assert_eq!(*total.borrow(), 1);
+    }
+}
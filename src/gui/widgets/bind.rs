@@ -0,0 +1,148 @@
+use crate::{
+    gfx::geometry::{Extent, Offset, Px, Rect},
+    gui::state::Observable,
+};
+
+use super::{
+    mark_changed, BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate,
+    UpdateContext, Widget, WidgetState,
+};
+
+type ApplyFn<T, W> = Box<dyn FnMut(&T, &mut W)>;
+
+/// Keeps its child in sync with an [`Observable`] value, by calling a function
+/// that applies the value to the child whenever it changes.
+///
+/// The child is laid out and redrawn after every change. The child is laid out
+/// and drawn as if it weren't wrapped.
+#[must_use]
+pub struct Bind<T, W: Widget> {
+    widget_state: WidgetState,
+    pub child: W,
+    source: Observable<T>,
+    /// The version of the value that was last applied to the child.
+    version: u64,
+    apply: ApplyFn<T, W>,
+}
+
+impl<T, W: Widget> Bind<T, W> {
+    /// Binds `child` to `source`. `apply` is called immediately, and then
+    /// again every time the value changes.
+    pub fn new(
+        source: &Observable<T>,
+        mut child: W,
+        mut apply: impl FnMut(&T, &mut W) + 'static,
+    ) -> Self {
+        apply(&source.get(), &mut child);
+        Self {
+            widget_state: WidgetState::default(),
+            child,
+            source: source.clone(),
+            version: source.version(),
+            apply: Box::new(apply),
+        }
+    }
+}
+
+impl<T, W: Widget> Widget for Bind<T, W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(&mut self.child);
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = context.layout(&mut self.child, constraints);
+        context.position_widget(&mut self.child, Offset::zero(), extent);
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+
+    fn accept_state_change(&mut self) -> PostUpdate {
+        if self.source.version() == self.version {
+            return PostUpdate::NoChange;
+        }
+
+        self.version = self.source.version();
+        (self.apply)(&self.source.get(), &mut self.child);
+
+        // The child can't tell that it was changed, so its cached layout is
+        // discarded here.
+        self.child.widget_state_mut().set_needs_layout();
+        PostUpdate::NeedsLayout
+    }
+}
+
+/// Brings every widget in the tree with `root` at its root up to date with
+/// the observable values that it's bound to. Returns the region of the window
+/// that needs to be redrawn, which will be empty if nothing changed.
+///
+/// See [`Widget::accept_state_change`].
+#[must_use]
+pub fn sync_state(root: &mut dyn Widget) -> Rect {
+    let post_update = root.accept_state_change();
+    let mut damage = mark_changed(root, post_update);
+
+    root.for_each_child_mut(&mut |child| damage = damage.union(&sync_state(child)));
+    damage
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::{color::Color, geometry::Px},
+        gui::widgets::debug::Fill,
+    };
+
+    use super::*;
+
+    #[test]
+    fn apply_changes() {
+        let red = Observable::new(0.0);
+        let mut widget = Bind::new(&red, Fill::new(Color::BLACK), |red, fill| {
+            fill.color.r = *red;
+        });
+        widget.widget_state.set_layout(
+            Offset::zero(),
+            Extent {
+                width: Px(10),
+                height: Px(10),
+            },
+        );
+
+        assert!(sync_state(&mut widget).is_empty());
+
+        red.set(0.5);
+        assert!(!sync_state(&mut widget).is_empty());
+        assert!(widget.widget_state.needs_layout());
+        assert!((widget.child.color.r - 0.5).abs() < f32::EPSILON);
+
+        assert!(sync_state(&mut widget).is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+use std::cell::Cell;
+
+use crate::{
+    gfx::{
+        geometry::{Extent, Offset, Point, Px, Rect},
+        DrawCommandList,
+    },
+    gui::images::{ImageId, Images},
+};
+
+use super::{
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// Draws its child to an image, and then draws only the image until the child
+/// changes. This saves recording the child's commands every frame, for
+/// panels that are expensive to draw but rarely change.
+///
+/// The image is drawn by the [`Images`] store once the frame that recorded the
+/// child has been drawn, so the child is drawn as usual until then. The same
+/// goes for when the image couldn't be drawn, such as when the graphics device
+/// has run out of images. The image is drawn again whenever the child or its
+/// descendants ask to be redrawn or laid out during an update. Changes made
+/// outside of an update, such as by animations, must be marked with
+/// [`Self::invalidate`].
+///
+/// The child is cut off at the layer's bounds. Translucent parts of the child
+/// are blended with the empty image before being blended with what is behind
+/// the layer, so the child should paint an opaque background to look the
+/// same once cached. In return, the layer's opacity fades the image as a
+/// whole.
+#[must_use]
+pub struct CacheLayer<W: Widget> {
+    widget_state: WidgetState,
+    images: Images,
+    /// The image that the child was last drawn to, which may not have been
+    /// drawn by `images` yet.
+    image: Cell<Option<ImageId>>,
+    /// Whether the child has changed since it was drawn to `image`.
+    is_stale: Cell<bool>,
+    child: W,
+}
+
+impl<W: Widget> CacheLayer<W> {
+    /// Creates a layer that caches `child` in an image drawn by `images`.
+    pub fn new(images: &Images, child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            images: images.clone(),
+            image: Cell::new(None),
+            is_stale: Cell::new(true),
+            child,
+        }
+    }
+
+    #[must_use]
+    pub fn child(&self) -> &W {
+        &self.child
+    }
+
+    /// The child, for changing it between events. The cached image is drawn
+    /// again the next time that the layer is drawn.
+    pub fn child_mut(&mut self) -> &mut W {
+        self.invalidate();
+        &mut self.child
+    }
+
+    /// Marks the cached image as out of date, so that the child is drawn to a
+    /// new one. The layer is not redrawn, so the caller must request a redraw
+    /// of its bounds.
+    pub fn invalidate(&mut self) {
+        self.is_stale.set(true);
+    }
+}
+
+impl<W: Widget> Drop for CacheLayer<W> {
+    fn drop(&mut self) {
+        if let Some(id) = self.image.take() {
+            self.images.release(id);
+        }
+    }
+}
+
+impl<W: Widget> Widget for CacheLayer<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        if !context.update_with_damage(&mut self.child).is_empty() {
+            self.invalidate();
+        }
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        // Layouts are cached, so this only runs when something in the child
+        // changed or the layer's size may have.
+        self.invalidate();
+        let extent = context.layout(&mut self.child, constraints);
+        context.position_widget(&mut self.child, Offset::zero(), extent);
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        let bounds = Rect::new(Point::zero(), extent);
+
+        // The image has a pixel for every physical pixel that it covers, so
+        // that it looks the same as drawing the child directly.
+        let physical = canvas.scale_factor().extent_to_physical(extent);
+        let image_extent = Extent {
+            width: Px(physical.width.0),
+            height: Px(physical.height.0),
+        };
+
+        // A descendant showing an overlay is updated without going through
+        // the layer, so it is drawn as usual until the overlay closes.
+        if has_overlay(&self.child) {
+            self.is_stale.set(true);
+        } else if self.is_stale.replace(false) {
+            if let Some(id) = self.image.take() {
+                self.images.release(id);
+            }
+
+            if image_extent.area() > 0 {
+                let mut commands = DrawCommandList::new();
+                canvas.record(&self.child, &mut commands);
+                self.image
+                    .set(Some(self.images.render(image_extent, commands)));
+            }
+        } else if let Some(handle) = self.image.get().and_then(|id| self.images.handle(id)) {
+            canvas.draw_image(handle, Rect::new(Point::zero(), image_extent), bounds);
+            return;
+        }
+
+        canvas.push_clip_rounded_rect(bounds, Px(0));
+        canvas.draw(&self.child);
+        canvas.pop_clip();
+    }
+}
+
+/// Checks if `widget` or any of its descendants is showing an overlay.
+fn has_overlay(widget: &dyn Widget) -> bool {
+    let mut found = widget.has_overlay();
+    widget.for_each_child(&mut |child| found = found || has_overlay(child));
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::color::Color,
+        gui::{testing::TestHarness, widgets::debug::Fill},
+    };
+
+    use super::*;
+
+    #[test]
+    fn redrawn_when_child_changes() {
+        let images = Images::new();
+        let extent = Extent {
+            width: Px(10),
+            height: Px(10),
+        };
+        let mut harness = TestHarness::new(CacheLayer::new(&images, Fill::new(Color::RED)), extent);
+
+        // The child is drawn as usual until its image has been drawn.
+        harness.frame();
+        assert_eq!(harness.color_at(Point::new(5, 5)), Some(Color::RED));
+        let first = harness.root().image.get();
+        assert!(first.is_some());
+        assert!(!harness.root().is_stale.get());
+
+        // Clicking changes the color of the fill, so it is drawn to a new
+        // image.
+        harness.click(Point::new(5, 5));
+        assert!(harness.root().is_stale.get());
+        harness.frame();
+        let color = harness.root().child().color;
+        assert_eq!(harness.color_at(Point::new(5, 5)), Some(color));
+        assert_ne!(harness.root().image.get(), first);
+        assert!(!harness.root().is_stale.get());
+    }
+}
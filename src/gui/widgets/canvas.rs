@@ -0,0 +1,202 @@
+use crate::{
+    gfx::geometry::{Extent, Point, Rect},
+    gui::input::Event,
+};
+
+use super::{
+    BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext, Widget, WidgetState,
+};
+
+type DrawFn<S> = Box<dyn Fn(&S, &mut DrawContext, Extent)>;
+type EventFn<S> = Box<dyn FnMut(&mut S, &CanvasEvent) -> bool>;
+
+/// An event received by a [`CanvasWidget`], with positions relative to the
+/// top-left corner of the canvas.
+#[derive(Clone, Copy, Debug)]
+pub struct CanvasEvent {
+    pub event: Event,
+    pub cursor: Point,
+    pub extent: Extent,
+}
+
+impl CanvasEvent {
+    /// Checks if the cursor is over the canvas.
+    #[must_use]
+    pub fn is_inside(&self) -> bool {
+        Rect::new(Point::zero(), self.extent).contains_point(self.cursor)
+    }
+}
+
+/// Draws whatever a function draws, so that an application can draw its own
+/// content without writing a widget.
+///
+/// The canvas fills the space that it is given. Every time it is drawn, it
+/// calls its draw function with the [`DrawContext`] and its size, with the
+/// origin at its top-left corner. It can also be given a function that is
+/// called with every event it receives, and returns `true` if the canvas needs
+/// to be drawn again.
+///
+/// Both functions share a state value owned by the canvas, which the event
+/// function can change and the draw function shows:
+///
+/// ```ignore
+/// CanvasWidget::with_state(Vec::new(), |points: &Vec<PointF>, canvas, _| {
+///     canvas.stroke_polyline(points, false, &Stroke::solid(Color::BLACK, 2.0));
+/// })
+/// .with_on_event(|points, event| match event.event {
+///     Event::CursorMove { .. } if event.is_inside() => {
+///         points.push(event.cursor.into());
+///         true
+///     }
+///     _ => false,
+/// })
+/// ```
+#[must_use]
+pub struct CanvasWidget<S = ()> {
+    widget_state: WidgetState,
+    state: S,
+    draw: DrawFn<S>,
+    on_event: Option<EventFn<S>>,
+}
+
+impl CanvasWidget {
+    /// Creates a canvas that draws with `draw`.
+    pub fn new(draw: impl Fn(&mut DrawContext, Extent) + 'static) -> Self {
+        Self::with_state((), move |(), canvas, extent| draw(canvas, extent))
+    }
+}
+
+impl<S: 'static> CanvasWidget<S> {
+    /// Creates a canvas that draws `state` with `draw`.
+    pub fn with_state(state: S, draw: impl Fn(&S, &mut DrawContext, Extent) + 'static) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            state,
+            draw: Box::new(draw),
+            on_event: None,
+        }
+    }
+
+    /// Calls `callback` with every event that the canvas receives. The canvas
+    /// is drawn again if it returns `true`.
+    pub fn with_on_event(
+        mut self,
+        callback: impl FnMut(&mut S, &CanvasEvent) -> bool + 'static,
+    ) -> Self {
+        self.on_event = Some(Box::new(callback));
+        self
+    }
+
+    #[must_use]
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Changes the state, and draws the canvas again.
+    pub fn state_mut(&mut self) -> &mut S {
+        self.widget_state.set_needs_layout();
+        &mut self.state
+    }
+}
+
+impl<S: 'static> Widget for CanvasWidget<S> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        let on_event = match &mut self.on_event {
+            Some(on_event) => on_event,
+            None => return PostUpdate::NoChange,
+        };
+
+        let origin = self.widget_state.origin();
+        let relative = |position: Point| Point::zero() + (position - origin);
+        let event = match context.event() {
+            Event::CursorMove { position } => Event::CursorMove {
+                position: relative(position),
+            },
+            Event::Touch {
+                id,
+                phase,
+                position,
+            } => Event::Touch {
+                id,
+                phase,
+                position: relative(position),
+            },
+            event => event,
+        };
+
+        let event = CanvasEvent {
+            event,
+            cursor: relative(context.cursor_position()),
+            extent: self.widget_state.extent(),
+        };
+        if on_event(&mut self.state, &event) {
+            PostUpdate::NeedsRedraw
+        } else {
+            PostUpdate::NoChange
+        }
+    }
+
+    fn accept_layout(&mut self, _: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        constraints.max
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        (self.draw)(&self.state, canvas, extent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::{color::Color, geometry::Px, Paint},
+        gui::{input::MouseButton, testing::TestHarness},
+    };
+
+    use super::*;
+
+    #[test]
+    fn draw_and_handle_events() {
+        let canvas = CanvasWidget::with_state(Color::RED, |color, canvas, extent| {
+            canvas.draw_rect(
+                Rect::new(Point::zero(), extent),
+                &Paint::Fill { color: *color },
+            );
+        })
+        .with_on_event(|color, event| match event.event {
+            Event::MouseButton { button, state }
+                if button == MouseButton::Left && state.is_pressed() && event.is_inside() =>
+            {
+                *color = Color::BLUE;
+                true
+            }
+            _ => false,
+        });
+
+        let mut harness = TestHarness::new(
+            canvas,
+            Extent {
+                width: Px(20),
+                height: Px(20),
+            },
+        );
+        let _ = harness.frame();
+        assert_eq!(harness.color_at(Point::new(10, 10)), Some(Color::RED));
+
+        harness.click(Point::new(10, 10));
+        let _ = harness.frame();
+        assert_eq!(harness.color_at(Point::new(10, 10)), Some(Color::BLUE));
+        assert_eq!(*harness.root().state(), Color::BLUE);
+    }
+}
@@ -0,0 +1,717 @@
+use std::{any::Any, ops::Range};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::input::Event,
+    text::{ShapedLine, TextStyle},
+};
+
+use super::{
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// How many columns a tab moves to.
+pub const TAB_WIDTH: usize = 4;
+
+/// The space between the edges of the view or the gutter and their text.
+const PADDING: Px = Px(4);
+
+const BACKGROUND: Color = Color::WHITE;
+
+const GUTTER: Color = Color {
+    r: 0.94,
+    g: 0.94,
+    b: 0.94,
+    a: 1.0,
+};
+
+const LINE_NUMBER: Color = Color {
+    r: 0.55,
+    g: 0.55,
+    b: 0.55,
+    a: 1.0,
+};
+
+const BORDER: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+/// A part of a line that is drawn in its own color.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    /// The byte range of the span in the line.
+    pub range: Range<usize>,
+    pub color: Color,
+}
+
+/// Colors the text in a [`CodeView`].
+///
+/// Lines are highlighted on their own as they come into view, so constructs
+/// that span lines, such as block comments, can't be recognized. Any function
+/// from a line to its spans is a highlighter.
+pub trait Highlighter {
+    /// Splits a line into spans, in order and without overlapping. Text that
+    /// isn't in a span is drawn in the view's color. Tabs in the line have
+    /// already been replaced with spaces.
+    fn highlight(&self, line: &str) -> Vec<Span>;
+}
+
+impl<F: Fn(&str) -> Vec<Span>> Highlighter for F {
+    fn highlight(&self, line: &str) -> Vec<Span> {
+        self(line)
+    }
+}
+
+/// A simple highlighter for C-like languages that colors keywords, strings,
+/// numbers, and line comments.
+#[derive(Clone, Debug)]
+pub struct Keywords {
+    keywords: Vec<String>,
+    line_comment: Option<String>,
+    pub keyword_color: Color,
+    pub string_color: Color,
+    pub number_color: Color,
+    pub comment_color: Color,
+}
+
+impl Keywords {
+    pub fn new<S: Into<String>>(keywords: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            keywords: keywords.into_iter().map(Into::into).collect(),
+            line_comment: None,
+            keyword_color: Color {
+                r: 0.0,
+                g: 0.2,
+                b: 0.7,
+                a: 1.0,
+            },
+            string_color: Color {
+                r: 0.6,
+                g: 0.1,
+                b: 0.1,
+                a: 1.0,
+            },
+            number_color: Color {
+                r: 0.0,
+                g: 0.5,
+                b: 0.5,
+                a: 1.0,
+            },
+            comment_color: Color {
+                r: 0.2,
+                g: 0.5,
+                b: 0.2,
+                a: 1.0,
+            },
+        }
+    }
+
+    /// Highlights Rust's keywords and comments.
+    pub fn rust() -> Self {
+        Self::new([
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ])
+        .with_line_comment("//")
+    }
+
+    /// Colors everything from `marker` to the end of the line as a comment.
+    pub fn with_line_comment(mut self, marker: impl Into<String>) -> Self {
+        self.line_comment = Some(marker.into());
+        self
+    }
+}
+
+impl Highlighter for Keywords {
+    fn highlight(&self, line: &str) -> Vec<Span> {
+        let mut spans = Vec::new();
+        let mut chars = line.char_indices().peekable();
+
+        while let Some((start, c)) = chars.next() {
+            if let Some(marker) = &self.line_comment {
+                if line[start..].starts_with(marker.as_str()) {
+                    spans.push(Span {
+                        range: start..line.len(),
+                        color: self.comment_color,
+                    });
+                    break;
+                }
+            }
+
+            // Finds the end of the token that starts with `c`.
+            let mut end_while = |f: &dyn Fn(char) -> bool| loop {
+                match chars.peek() {
+                    Some(&(_, c)) if f(c) => {
+                        chars.next();
+                    }
+                    Some(&(end, _)) => break end,
+                    None => break line.len(),
+                }
+            };
+
+            if c == '"' || c == '\'' {
+                let quote = c;
+                let mut escaped = false;
+                let mut end = line.len();
+                for (i, c) in chars.by_ref() {
+                    if escaped {
+                        escaped = false;
+                    } else if c == '\\' {
+                        escaped = true;
+                    } else if c == quote {
+                        end = i + c.len_utf8();
+                        break;
+                    }
+                }
+                spans.push(Span {
+                    range: start..end,
+                    color: self.string_color,
+                });
+            } else if c.is_ascii_digit() {
+                let end = end_while(&|c| c.is_alphanumeric() || c == '_' || c == '.');
+                spans.push(Span {
+                    range: start..end,
+                    color: self.number_color,
+                });
+            } else if c.is_alphabetic() || c == '_' {
+                let end = end_while(&|c| c.is_alphanumeric() || c == '_');
+                if self.keywords.iter().any(|k| *k == line[start..end]) {
+                    spans.push(Span {
+                        range: start..end,
+                        color: self.keyword_color,
+                    });
+                }
+            }
+        }
+
+        spans
+    }
+}
+
+/// A piece of a visible line, shaped in one color.
+struct Segment {
+    /// The column that the piece starts at.
+    column: usize,
+    line: ShapedLine,
+    color: Color,
+}
+
+/// Shows text in a monospaced font, with line numbers and optional syntax
+/// highlighting.
+///
+/// Only the lines that can be seen are highlighted, laid out, and drawn, and
+/// only the columns of those lines that can be seen, so very long text and
+/// very long lines cost no more than what fits in the view. Columns are as
+/// wide as the font's `0`, and tabs are replaced with spaces up to the next
+/// multiple of [`TAB_WIDTH`] columns.
+///
+/// The view scrolls a whole line or column at a time.
+#[must_use]
+pub struct CodeView {
+    widget_state: WidgetState,
+    style: TextStyle,
+    pub color: Color,
+    lines: Vec<String>,
+    /// The number of columns in the longest line.
+    columns: usize,
+    highlighter: Option<Box<dyn Highlighter>>,
+    line_numbers: bool,
+    /// The first line and column shown.
+    top: usize,
+    left: usize,
+    /// The part of a scroll too small to move by a line or column yet.
+    remainder: (Px, Px),
+    // Computed during layout.
+    row_height: Px,
+    ascent: Px,
+    column_width: f32,
+    gutter_width: Px,
+    numbers: Vec<(ShapedLine, Px)>,
+    rows: Vec<Vec<Segment>>,
+}
+
+impl CodeView {
+    pub fn new(text: &str, style: TextStyle) -> Self {
+        let mut view = Self {
+            widget_state: WidgetState::default(),
+            style,
+            color: Color::BLACK,
+            lines: Vec::new(),
+            columns: 0,
+            highlighter: None,
+            line_numbers: true,
+            top: 0,
+            left: 0,
+            remainder: (Px(0), Px(0)),
+            row_height: Px(0),
+            ascent: Px(0),
+            column_width: 0.0,
+            gutter_width: Px(0),
+            numbers: Vec::new(),
+            rows: Vec::new(),
+        };
+        view.set_text(text);
+        view
+    }
+
+    pub fn with_highlighter(mut self, highlighter: impl Highlighter + 'static) -> Self {
+        self.highlighter = Some(Box::new(highlighter));
+        self
+    }
+
+    /// Shows or hides the line numbers, which are shown by default.
+    pub fn with_line_numbers(mut self, line_numbers: bool) -> Self {
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    /// Replaces the text, keeping the view scrolled to the same line if it is
+    /// still there.
+    pub fn set_text(&mut self, text: &str) {
+        self.lines = text.lines().map(expand_tabs).collect();
+        self.columns = 0;
+        for line in &self.lines {
+            self.columns = self.columns.max(line.chars().count());
+        }
+        self.top = self.top.min(self.lines.len().saturating_sub(1));
+        self.left = self.left.min(self.columns.saturating_sub(1));
+        self.widget_state.set_needs_layout();
+    }
+
+    /// Adds lines to the end of the text, such as new lines of a log.
+    pub fn push_text(&mut self, text: &str) {
+        for line in text.lines().map(expand_tabs) {
+            self.columns = self.columns.max(line.chars().count());
+            self.lines.push(line);
+        }
+        self.widget_state.set_needs_layout();
+    }
+
+    #[must_use]
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The first line that is shown, counting from 0.
+    #[must_use]
+    pub fn top_line(&self) -> usize {
+        self.top
+    }
+
+    /// Scrolls so that `line` is shown, moving as little as possible.
+    pub fn scroll_to_line(&mut self, line: usize) {
+        let rows = self.visible_rows();
+        if line < self.top {
+            self.top = line;
+        } else if rows > 0 && line >= self.top + rows {
+            self.top = line + 1 - rows;
+        }
+        self.top = self.top.min(self.max_top());
+        self.widget_state.set_needs_layout();
+    }
+
+    /// The number of lines that fit in the view.
+    fn visible_rows(&self) -> usize {
+        let height = self.widget_state.extent().height - PADDING - PADDING;
+        if self.row_height > 0 && height > 0 {
+            (height.0 / self.row_height.0) as usize
+        } else {
+            0
+        }
+    }
+
+    /// The number of columns that fit to the right of the gutter.
+    fn visible_columns(&self) -> usize {
+        let width = self.widget_state.extent().width - self.gutter_width - PADDING - PADDING;
+        if self.column_width > 0.0 && width > 0 {
+            (f32::from(width) / self.column_width) as usize
+        } else {
+            0
+        }
+    }
+
+    /// How far down the view can be scrolled while the last line is shown.
+    fn max_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.visible_rows().max(1))
+    }
+
+    fn max_left(&self) -> usize {
+        self.columns.saturating_sub(self.visible_columns().max(1))
+    }
+}
+
+/// The state of a [`CodeView`] that is carried over when the widget tree is
+/// rebuilt.
+struct SavedState {
+    top: usize,
+    left: usize,
+}
+
+impl Widget for CodeView {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        match context.event() {
+            Event::Scroll { delta, .. }
+                if self
+                    .widget_state
+                    .rect()
+                    .contains_point(context.cursor_position()) =>
+            {
+                let column_width = Px::from(self.column_width.round().max(1.0));
+                let (top, y) = scroll_steps(
+                    self.top,
+                    self.remainder.1,
+                    delta.y,
+                    self.row_height,
+                    self.max_top(),
+                );
+                let (left, x) = scroll_steps(
+                    self.left,
+                    self.remainder.0,
+                    delta.x,
+                    column_width,
+                    self.max_left(),
+                );
+                self.remainder = (x, y);
+
+                if (top, left) == (self.top, self.left) {
+                    PostUpdate::NoChange
+                } else {
+                    self.top = top;
+                    self.left = left;
+                    PostUpdate::NeedsLayout
+                }
+            }
+            _ => PostUpdate::NoChange,
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = constraints.max;
+
+        let metrics = context.measure_text("0", &self.style, None);
+        self.row_height = Px::from(metrics.line_height.ceil());
+        self.ascent = Px::from(metrics.ascent.round());
+        self.column_width = metrics.width;
+
+        let digits = self.lines.len().max(1).to_string().len();
+        self.gutter_width = if self.line_numbers {
+            Px::from(digits as f32 * self.column_width).saturating_add(PADDING + PADDING)
+        } else {
+            Px(0)
+        };
+
+        let rows = self.visible_rows() + 1;
+        let columns = self.visible_columns() + 1;
+        self.top = self.top.min(self.max_top());
+        self.left = self.left.min(self.max_left());
+        let visible = self.top..self.lines.len().min(self.top + rows);
+
+        let shape = |text: &str| {
+            context
+                .measure_text(text, &self.style, None)
+                .lines
+                .swap_remove(0)
+        };
+
+        self.numbers.clear();
+        if self.line_numbers {
+            for index in visible.clone() {
+                let number = shape(&(index + 1).to_string());
+                let width = Px::from(number.width.ceil());
+                self.numbers.push((number.shaped, width));
+            }
+        }
+
+        self.rows.clear();
+        for line in &self.lines[visible] {
+            let spans = match &self.highlighter {
+                Some(highlighter) => highlighter.highlight(line),
+                None => Vec::new(),
+            };
+
+            let row = segments(line, &spans, self.left..self.left + columns)
+                .into_iter()
+                .map(|(column, range, color)| Segment {
+                    column,
+                    line: shape(&line[range]).shaped,
+                    color: color.unwrap_or(self.color),
+                })
+                .collect();
+            self.rows.push(row);
+        }
+
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, _: Px) -> Px {
+        match size {
+            IntrinsicSize::Min => Px(0),
+            IntrinsicSize::Max => {
+                let digits = self.lines.len().max(1).to_string().len();
+                let metrics = context.measure_text("0", &self.style, None);
+                let numbers = if self.line_numbers { digits } else { 0 };
+                let width = (self.columns + numbers) as f32 * metrics.width;
+                Px::from(width.ceil().min(f32::from(i16::MAX))).saturating_add(PADDING * 4.0)
+            }
+        }
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, _: Px) -> Px {
+        match size {
+            IntrinsicSize::Min => Px(0),
+            IntrinsicSize::Max => {
+                let metrics = context.measure_text("0", &self.style, None);
+                let height = self.lines.len() as f32 * metrics.line_height.ceil();
+                Px::from(height.min(f32::from(i16::MAX))).saturating_add(PADDING + PADDING)
+            }
+        }
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        let bounds = Rect::new(Point::zero(), extent);
+        canvas.draw_rect(bounds, &Paint::Fill { color: BACKGROUND });
+
+        if self.line_numbers {
+            canvas.draw_rect(
+                Rect {
+                    right: self.gutter_width,
+                    ..bounds
+                },
+                &Paint::Fill { color: GUTTER },
+            );
+        }
+
+        canvas.push_clip_rounded_rect(
+            Rect {
+                top: PADDING,
+                bottom: extent.height - PADDING,
+                ..bounds
+            },
+            Px(0),
+        );
+
+        for (i, (number, width)) in self.numbers.iter().enumerate() {
+            let top = PADDING + self.row_height * i as f32;
+            canvas.draw_text(
+                number,
+                Point::new(self.gutter_width - PADDING - *width, top + self.ascent),
+                self.style.size,
+                LINE_NUMBER,
+            );
+        }
+
+        canvas.push_clip_rounded_rect(
+            Rect {
+                left: self.gutter_width + PADDING,
+                top: PADDING,
+                right: extent.width - PADDING,
+                bottom: extent.height - PADDING,
+            },
+            Px(0),
+        );
+        let left = self.gutter_width + PADDING;
+        for (i, row) in self.rows.iter().enumerate() {
+            let top = PADDING + self.row_height * i as f32;
+            for segment in row {
+                let x = (segment.column - self.left) as f32 * self.column_width;
+                canvas.draw_text(
+                    &segment.line,
+                    Point::new(left + Px::from(x.round()), top + self.ascent),
+                    self.style.size,
+                    segment.color,
+                );
+            }
+        }
+        canvas.pop_clip();
+        canvas.pop_clip();
+
+        canvas.stroke_rect(bounds, &Stroke::solid(BORDER, 1.0));
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(SavedState {
+            top: self.top,
+            left: self.left,
+        }))
+    }
+
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        if let Ok(state) = state.downcast::<SavedState>() {
+            // Clamped to the text during layout.
+            self.top = state.top;
+            self.left = state.left;
+        }
+    }
+}
+
+/// Replaces the tabs in `line` with spaces up to the next tab stop.
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = TAB_WIDTH - column % TAB_WIDTH;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    expanded
+}
+
+/// Splits the part of `line` in `columns` into pieces of one color, returning
+/// the column that each starts at, its byte range in `line`, and its color if
+/// it is in a span. Whitespace between pieces is left out.
+fn segments(
+    line: &str,
+    spans: &[Span],
+    columns: Range<usize>,
+) -> Vec<(usize, Range<usize>, Option<Color>)> {
+    // The byte offset of each column in view, and of the end of the last.
+    let offsets = line
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(line.len()))
+        .enumerate()
+        .skip(columns.start)
+        .take(columns.len() + 1)
+        .collect::<Vec<_>>();
+    let (start, end) = match (offsets.first(), offsets.last()) {
+        (Some(&(_, start)), Some(&(_, end))) if start < end => (start, end),
+        _ => return Vec::new(),
+    };
+    let column_of = |offset: usize| {
+        offsets
+            .iter()
+            .find(|&&(_, o)| o >= offset)
+            .map_or(columns.end, |&(column, _)| column)
+    };
+
+    let mut pieces = Vec::new();
+    let mut push = |range: Range<usize>, color: Option<Color>| {
+        let range = range.start.max(start)..range.end.min(end);
+        let text = &line[range.clone()];
+        let leading = text.len() - text.trim_start().len();
+        let range = range.start + leading..range.start + text.trim_end().len();
+        if !range.is_empty() {
+            pieces.push((column_of(range.start), range, color));
+        }
+    };
+
+    let mut offset = start;
+    for span in spans {
+        if span.range.end <= start || span.range.start >= end {
+            continue;
+        }
+        if span.range.start > offset {
+            push(offset..span.range.start, None);
+        }
+        push(span.range.clone(), Some(span.color));
+        offset = span.range.end;
+    }
+    if offset < end {
+        push(offset..end, None);
+    }
+
+    pieces
+}
+
+/// Scrolls by `delta` pixels from `position`, moving a whole `step` at a time
+/// and carrying over what is left in `remainder`. Returns the new position,
+/// which is at most `max`, and the new remainder.
+fn scroll_steps(position: usize, remainder: Px, delta: Px, step: Px, max: usize) -> (usize, Px) {
+    if step <= 0 {
+        return (position, Px(0));
+    }
+
+    let total = i32::from(remainder.0) + i32::from(delta.0);
+    let steps = total / i32::from(step.0);
+    let remainder = Px((total % i32::from(step.0)) as i16);
+
+    let position = if steps < 0 {
+        position.saturating_sub(steps.unsigned_abs() as usize)
+    } else {
+        position.saturating_add(steps as usize).min(max)
+    };
+
+    // Nothing is carried past either end.
+    if (position == 0 && remainder < 0) || (position == max && remainder > 0) {
+        (position, Px(0))
+    } else {
+        (position, remainder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_keywords() {
+        let rust = Keywords::rust();
+        let line = r#"let s = "a \" b"; // 42"#;
+        let spans = rust.highlight(line);
+        let ranges = spans
+            .iter()
+            .map(|s| &line[s.range.clone()])
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, ["let", r#""a \" b""#, "// 42"]);
+        assert_eq!(spans[2].color, rust.comment_color);
+
+        let spans = rust.highlight("x = 1.5 + letter");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].range, 4..7);
+    }
+
+    #[test]
+    fn split_into_segments() {
+        let color = Color::RED;
+        let spans = [Span { range: 4..7, color }];
+        let line = "let abc = 1";
+
+        assert_eq!(
+            segments(line, &spans, 0..100),
+            [(0, 0..3, None), (4, 4..7, Some(color)), (8, 8..11, None)]
+        );
+        // Only the columns in view are kept.
+        assert_eq!(
+            segments(line, &spans, 5..9),
+            [(5, 5..7, Some(color)), (8, 8..9, None)]
+        );
+        assert_eq!(segments(line, &spans, 20..30), []);
+
+        assert_eq!(expand_tabs("\tab\tc"), "    ab  c");
+    }
+
+    #[test]
+    fn scroll_by_steps() {
+        assert_eq!(scroll_steps(0, Px(0), Px(25), Px(10), 5), (2, Px(5)));
+        assert_eq!(scroll_steps(2, Px(5), Px(-20), Px(10), 5), (1, Px(-5)));
+        assert_eq!(scroll_steps(0, Px(0), Px(-25), Px(10), 5), (0, Px(0)));
+        assert_eq!(scroll_steps(4, Px(0), Px(100), Px(10), 5), (5, Px(0)));
+    }
+}
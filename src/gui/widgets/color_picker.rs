@@ -0,0 +1,593 @@
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Offset, Point, Px, Rect},
+        Paint, Stroke, Vertex,
+    },
+    gui::input::{ButtonState, Event, MouseButton},
+    text::{ShapedLine, TextStyle},
+};
+
+use super::{
+    text_input::TextInput, BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext,
+    Widget, WidgetState,
+};
+
+/// The diameter of the hue wheel.
+const WHEEL_SIZE: Px = Px(160);
+
+/// The width of the ring of hues around the saturation-value square.
+const RING_WIDTH: Px = Px(16);
+
+/// The number of segments that the hue ring is drawn with.
+const RING_SEGMENTS: u16 = 48;
+
+/// The number of cells along each side of the mesh that the saturation-value
+/// square is drawn with.
+const SQUARE_CELLS: u16 = 8;
+
+/// The space between the wheel and the fields, and between rows of fields.
+const GAP: Px = Px(8);
+
+const FIELD_WIDTH: Px = Px(80);
+
+const SWATCH_HEIGHT: Px = Px(24);
+
+const MARKER_RADIUS: Px = Px(5);
+
+const BORDER: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+const LABELS: [&str; 5] = ["R", "G", "B", "A", "Hex"];
+
+/// The index of the hex field in `ColorPicker::fields`.
+const HEX: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Drag {
+    Hue,
+    SaturationValue,
+}
+
+/// Picks a color using a hue wheel around a saturation-value square, or by
+/// typing its channels or hex code.
+///
+/// The red, green, blue and alpha fields take values from 0 to 255, and the
+/// hex field takes `rrggbb` or `rrggbbaa` with an optional `#`. The color
+/// changes as soon as a field holds a valid value.
+///
+/// If an eyedropper is provided with [`Self::with_eyedropper`], a button is
+/// shown that calls it to pick a color from elsewhere, such as the screen.
+#[must_use]
+pub struct ColorPicker {
+    widget_state: WidgetState,
+    style: TextStyle,
+    color: Color,
+    /// The hue and saturation are kept separately from the color so that they
+    /// aren't lost when the color is black or gray.
+    hue: f32,
+    saturation: f32,
+    fields: Vec<TextInput>,
+    /// The text that each field held after the last update, to detect edits.
+    field_text: Vec<String>,
+    drag: Option<Drag>,
+    on_change: Option<Box<dyn FnMut(Color)>>,
+    eyedropper: Option<Box<dyn FnMut() -> Option<Color>>>,
+    // Computed during layout.
+    labels: Vec<ShapedLine>,
+    label_width: Px,
+    ascent: Px,
+    row_height: Px,
+}
+
+impl ColorPicker {
+    pub fn new(style: TextStyle) -> Self {
+        let mut picker = Self {
+            widget_state: WidgetState::default(),
+            style,
+            color: Color::WHITE,
+            hue: 0.0,
+            saturation: 0.0,
+            fields: LABELS.iter().map(|_| TextInput::new(style)).collect(),
+            field_text: vec![String::new(); LABELS.len()],
+            drag: None,
+            on_change: None,
+            eyedropper: None,
+            labels: Vec::new(),
+            label_width: Px(0),
+            ascent: Px(0),
+            row_height: Px(0),
+        };
+        picker.sync_fields(None);
+        picker
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.set_color(color);
+        self
+    }
+
+    /// Calls `callback` with the new color whenever the user changes it.
+    pub fn with_on_change(mut self, callback: impl FnMut(Color) + 'static) -> Self {
+        self.on_change = Some(Box::new(callback));
+        self
+    }
+
+    /// Shows an eyedropper button that calls `callback` when clicked. If it
+    /// returns a color, that color is picked.
+    pub fn with_eyedropper(mut self, callback: impl FnMut() -> Option<Color> + 'static) -> Self {
+        self.eyedropper = Some(Box::new(callback));
+        self
+    }
+
+    #[must_use]
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Changes the color without calling the change callback.
+    pub fn set_color(&mut self, color: Color) {
+        self.set_rgba(color);
+        self.sync_fields(None);
+        self.widget_state.set_needs_layout();
+    }
+
+    /// Sets the color from its red, green, blue and alpha channels, keeping
+    /// the hue and saturation if they can't be recovered from it.
+    fn set_rgba(&mut self, color: Color) {
+        let (hue, saturation, value) = color.to_hsv();
+        if value > 0.0 {
+            if saturation > 0.0 {
+                self.hue = hue;
+            }
+            self.saturation = saturation;
+        }
+        self.color = color;
+    }
+
+    fn set_hsv(&mut self, hue: f32, saturation: f32, value: f32) {
+        self.hue = hue;
+        self.saturation = saturation;
+        self.color = Color::from_hsv(hue, saturation, value, self.color.a);
+        self.sync_fields(None);
+    }
+
+    fn value(&self) -> f32 {
+        self.color.r.max(self.color.g).max(self.color.b)
+    }
+
+    /// Shows the current color in every field except `except`, which is being
+    /// edited.
+    fn sync_fields(&mut self, except: Option<usize>) {
+        let [r, g, b, a] = self.color.to_array().map(channel_text);
+        let text = [r, g, b, a, self.color.to_hex()];
+
+        for (i, text) in text.into_iter().enumerate() {
+            if Some(i) != except {
+                self.fields[i].set_text(&text);
+                self.field_text[i] = text;
+            }
+        }
+    }
+
+    /// Picks up edits made to the fields during the last update. Returns true
+    /// if the color changed.
+    fn apply_field_edits(&mut self) -> bool {
+        let edited = (0..self.fields.len()).find(|&i| self.fields[i].text() != self.field_text[i]);
+        let edited = match edited {
+            Some(edited) => edited,
+            None => return false,
+        };
+
+        let text = self.fields[edited].text().to_string();
+        let color = if edited == HEX {
+            Color::from_hex(&text)
+        } else {
+            parse_channel(&text).map(|channel| {
+                let mut channels = self.color.to_array();
+                channels[edited] = channel;
+                let [r, g, b, a] = channels;
+                Color { r, g, b, a }
+            })
+        };
+        self.field_text[edited] = text;
+
+        match color {
+            Some(color) => {
+                self.set_rgba(color);
+                self.sync_fields(Some(edited));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn notify(&mut self) {
+        if let Some(on_change) = &mut self.on_change {
+            on_change(self.color);
+        }
+    }
+
+    fn wheel_center() -> Point {
+        Point::new(WHEEL_SIZE / 2, WHEEL_SIZE / 2)
+    }
+
+    /// The bounds of the saturation-value square, which fits inside the ring.
+    fn square() -> Rect {
+        let inner = f32::from((WHEEL_SIZE / 2 - RING_WIDTH - Px(4)).0);
+        let half = Px::from((inner / std::f32::consts::SQRT_2).floor());
+        let center = Self::wheel_center();
+        Rect {
+            left: center.x - half,
+            top: center.y - half,
+            right: center.x + half,
+            bottom: center.y + half,
+        }
+    }
+
+    /// The left edge of the swatch and fields.
+    fn fields_left() -> Px {
+        WHEEL_SIZE + GAP
+    }
+
+    fn swatch(&self) -> Rect {
+        let left = Self::fields_left();
+        let right = left + self.label_width + GAP + FIELD_WIDTH;
+        let right = if self.eyedropper.is_some() {
+            right - SWATCH_HEIGHT - GAP
+        } else {
+            right
+        };
+        Rect {
+            left,
+            top: Px(0),
+            right,
+            bottom: SWATCH_HEIGHT,
+        }
+    }
+
+    fn eyedropper_button(&self) -> Option<Rect> {
+        self.eyedropper.as_ref()?;
+        let left = self.swatch().right + GAP;
+        Some(Rect {
+            left,
+            top: Px(0),
+            right: left + SWATCH_HEIGHT,
+            bottom: SWATCH_HEIGHT,
+        })
+    }
+
+    /// Picks the hue or saturation and value at `point`, relative to the
+    /// picker.
+    fn drag_to(&mut self, drag: Drag, point: Point) {
+        match drag {
+            Drag::Hue => {
+                let offset = point - Self::wheel_center();
+                self.set_hsv(hue_at(offset), self.saturation, self.value());
+            }
+            Drag::SaturationValue => {
+                let (saturation, value) = saturation_value_at(Self::square(), point);
+                self.set_hsv(self.hue, saturation, value);
+            }
+        }
+    }
+
+    fn draw_wheel(&self, canvas: &mut DrawContext) {
+        let center = Self::wheel_center();
+        let outer = f32::from((WHEEL_SIZE / 2).0);
+        let inner = outer - f32::from(RING_WIDTH.0);
+
+        let mut vertices = Vec::with_capacity(usize::from(RING_SEGMENTS + 1) * 2);
+        let mut indices = Vec::with_capacity(usize::from(RING_SEGMENTS) * 6);
+        for i in 0..=RING_SEGMENTS {
+            let hue = 360.0 * f32::from(i) / f32::from(RING_SEGMENTS);
+            let color = Color::from_hsv(hue, 1.0, 1.0, 1.0);
+            let (sin, cos) = hue.to_radians().sin_cos();
+            let point = |radius: f32| {
+                Point::new(
+                    f32::from(center.x.0) + cos * radius,
+                    f32::from(center.y.0) - sin * radius,
+                )
+            };
+            vertices.push(Vertex::new(point(outer), color));
+            vertices.push(Vertex::new(point(inner), color));
+
+            if i > 0 {
+                let j = i * 2;
+                indices.extend_from_slice(&[j - 2, j, j - 1, j - 1, j, j + 1]);
+            }
+        }
+        canvas.draw_mesh(&vertices, &indices);
+
+        let square = Self::square();
+        let mut vertices = Vec::with_capacity(usize::from(SQUARE_CELLS + 1).pow(2));
+        let mut indices = Vec::with_capacity(usize::from(SQUARE_CELLS).pow(2) * 6);
+        let cells = f32::from(SQUARE_CELLS);
+        for row in 0..=SQUARE_CELLS {
+            for column in 0..=SQUARE_CELLS {
+                let saturation = f32::from(column) / cells;
+                let value = 1.0 - f32::from(row) / cells;
+                let point = Point::new(
+                    square.left + square.width() * saturation,
+                    square.top + square.height() * (1.0 - value),
+                );
+                vertices.push(Vertex::new(
+                    point,
+                    Color::from_hsv(self.hue, saturation, value, 1.0),
+                ));
+
+                if row > 0 && column > 0 {
+                    let stride = SQUARE_CELLS + 1;
+                    let bottom_right = row * stride + column;
+                    let top_right = bottom_right - stride;
+                    indices.extend_from_slice(&[
+                        top_right - 1,
+                        top_right,
+                        bottom_right - 1,
+                        bottom_right - 1,
+                        top_right,
+                        bottom_right,
+                    ]);
+                }
+            }
+        }
+        canvas.draw_mesh(&vertices, &indices);
+
+        // Markers for the current hue and saturation-value.
+        let (sin, cos) = self.hue.to_radians().sin_cos();
+        let radius = outer - f32::from(RING_WIDTH.0) / 2.0;
+        let hue_marker = Point::new(
+            f32::from(center.x.0) + cos * radius,
+            f32::from(center.y.0) - sin * radius,
+        );
+        let sv_marker = Point::new(
+            square.left + square.width() * self.saturation,
+            square.top + square.height() * (1.0 - self.value()),
+        );
+        for marker in [hue_marker, sv_marker] {
+            canvas.stroke_circle(marker, MARKER_RADIUS, &Stroke::solid(Color::BLACK, 2.0));
+            canvas.stroke_circle(marker, MARKER_RADIUS, &Stroke::solid(Color::WHITE, 1.0));
+        }
+    }
+}
+
+impl Widget for ColorPicker {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        for field in &self.fields {
+            f(field);
+        }
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        for field in &mut self.fields {
+            f(field);
+        }
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        for field in &mut self.fields {
+            context.update(field);
+        }
+
+        if self.apply_field_edits() {
+            self.notify();
+            return PostUpdate::NeedsLayout;
+        }
+
+        let origin = self.widget_state.origin();
+        let cursor = Point::zero() + (context.cursor_position() - origin);
+
+        match context.event() {
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Pressed,
+            } => {
+                let distance = {
+                    let offset = cursor - Self::wheel_center();
+                    f32::from(offset.x.0).hypot(f32::from(offset.y.0))
+                };
+                let outer = f32::from((WHEEL_SIZE / 2).0);
+
+                if Self::square().contains_point(cursor) {
+                    self.drag = Some(Drag::SaturationValue);
+                } else if distance <= outer && distance >= outer - f32::from(RING_WIDTH.0) {
+                    self.drag = Some(Drag::Hue);
+                } else if self
+                    .eyedropper_button()
+                    .is_some_and(|button| button.contains_point(cursor))
+                {
+                    let picked = self.eyedropper.as_mut().and_then(|eyedropper| eyedropper());
+                    if let Some(color) = picked {
+                        self.set_color(color);
+                        self.notify();
+                        return PostUpdate::NeedsLayout;
+                    }
+                    return PostUpdate::NoChange;
+                } else {
+                    return PostUpdate::NoChange;
+                }
+
+                if let Some(drag) = self.drag {
+                    self.drag_to(drag, cursor);
+                    self.notify();
+                }
+                PostUpdate::NeedsLayout
+            }
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Released,
+            } => {
+                self.drag = None;
+                PostUpdate::NoChange
+            }
+            Event::CursorMove { .. } => match self.drag {
+                Some(drag) => {
+                    self.drag_to(drag, cursor);
+                    self.notify();
+                    PostUpdate::NeedsLayout
+                }
+                None => PostUpdate::NoChange,
+            },
+            _ => PostUpdate::NoChange,
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        self.labels.clear();
+        self.label_width = Px(0);
+        for label in LABELS {
+            let mut metrics = context.measure_text(label, &self.style, None);
+            self.label_width = self.label_width.max(Px::from(metrics.width.ceil()));
+            self.ascent = Px::from(metrics.ascent.round());
+            self.labels.push(metrics.lines.swap_remove(0).shaped);
+        }
+
+        let field_constraints = BoxConstraint {
+            min: Extent::zero(),
+            max: Extent {
+                width: FIELD_WIDTH,
+                height: constraints.max.height,
+            },
+        };
+        let left = Self::fields_left() + self.label_width + GAP;
+        let mut top = SWATCH_HEIGHT + GAP;
+        for field in &mut self.fields {
+            let extent = context.layout(field, field_constraints);
+            context.position_widget(field, Offset { x: left, y: top }, extent);
+            self.row_height = extent.height;
+            top += extent.height + GAP;
+        }
+
+        constraints.max_fit(Extent {
+            width: left + FIELD_WIDTH,
+            height: WHEEL_SIZE.max(top - GAP),
+        })
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        self.draw_wheel(canvas);
+
+        let swatch = self.swatch();
+        canvas.draw_rect(swatch, &Paint::Fill { color: self.color });
+        canvas.stroke_rect(swatch, &Stroke::solid(BORDER, 1.0));
+
+        if let Some(button) = self.eyedropper_button() {
+            // A pipette: a diagonal stem with a bulb at the top.
+            canvas.stroke_rect(button, &Stroke::solid(BORDER, 1.0));
+            let inset = button.deflate(Px(6), Px(6));
+            let stroke = Stroke::solid(Color::BLACK, 1.5);
+            canvas.stroke_line(
+                Point::new(inset.left, inset.bottom),
+                Point::new(inset.right - Px(3), inset.top + Px(3)),
+                &stroke,
+            );
+            canvas.draw_circle(
+                Point::new(inset.right - Px(3), inset.top + Px(3)),
+                Px(3),
+                &Paint::Fill {
+                    color: Color::BLACK,
+                },
+            );
+        }
+
+        let mut top = SWATCH_HEIGHT + GAP;
+        for (label, field) in self.labels.iter().zip(&self.fields) {
+            let baseline = top + (self.row_height - self.ascent) / 2 + self.ascent - Px(2);
+            canvas.draw_text(
+                label,
+                Point::new(Self::fields_left(), baseline),
+                self.style.size,
+                Color::BLACK,
+            );
+            canvas.draw(field);
+            top += self.row_height + GAP;
+        }
+    }
+}
+
+/// Formats a color channel as a number from 0 to 255.
+fn channel_text(channel: f32) -> String {
+    format!("{}", (channel.clamp(0.0, 1.0) * 255.0).round())
+}
+
+/// Parses a color channel written as a number from 0 to 255.
+fn parse_channel(text: &str) -> Option<f32> {
+    text.trim()
+        .parse::<u8>()
+        .ok()
+        .map(|channel| f32::from(channel) / 255.0)
+}
+
+/// Finds the hue at `offset` from the center of the wheel. Red is to the
+/// right, and hues increase counter-clockwise.
+fn hue_at(offset: Offset) -> f32 {
+    let angle = f32::atan2(-f32::from(offset.y.0), f32::from(offset.x.0)).to_degrees();
+    angle.rem_euclid(360.0)
+}
+
+/// Finds the saturation and value at `point` in `square`. Saturation increases
+/// to the right and value increases upward.
+fn saturation_value_at(square: Rect, point: Point) -> (f32, f32) {
+    let fraction = |from: Px, to: Px, at: Px| {
+        let length = f32::from((to - from).0);
+        if length <= 0.0 {
+            0.0
+        } else {
+            (f32::from((at - from).0) / length).clamp(0.0, 1.0)
+        }
+    };
+
+    (
+        fraction(square.left, square.right, point.x),
+        1.0 - fraction(square.top, square.bottom, point.y),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pick_from_wheel() {
+        let offset = |x: i16, y: i16| Offset { x: Px(x), y: Px(y) };
+        assert!((hue_at(offset(10, 0)) - 0.0).abs() < 1e-3);
+        assert!((hue_at(offset(0, -10)) - 90.0).abs() < 1e-3);
+        assert!((hue_at(offset(-10, 0)) - 180.0).abs() < 1e-3);
+        assert!((hue_at(offset(0, 10)) - 270.0).abs() < 1e-3);
+
+        let square = Rect {
+            left: Px(0),
+            top: Px(0),
+            right: Px(100),
+            bottom: Px(100),
+        };
+        assert_eq!(saturation_value_at(square, Point::new(0, 0)), (0.0, 1.0));
+        assert_eq!(saturation_value_at(square, Point::new(50, 75)), (0.5, 0.25));
+        assert_eq!(
+            saturation_value_at(square, Point::new(200, -20)),
+            (1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn channel_fields() {
+        assert_eq!(channel_text(1.0), "255");
+        assert_eq!(channel_text(0.5), "128");
+        assert_eq!(parse_channel(" 255 "), Some(1.0));
+        assert_eq!(parse_channel("0"), Some(0.0));
+        assert_eq!(parse_channel("256"), None);
+        assert_eq!(parse_channel("red"), None);
+    }
+}
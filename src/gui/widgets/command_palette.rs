@@ -0,0 +1,595 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Offset, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::input::{ButtonState, Event, MouseButton},
+    shell::{
+        event::{Key, Modifiers},
+        menu::{Accelerator, Menu, MenuItem, MenuItemId},
+    },
+    text::{ShapedLine, TextStyle},
+};
+
+use super::{
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// The widest that the palette gets.
+const MAX_WIDTH: Px = Px(480);
+
+/// The space between the palette and the edges of the window.
+const MARGIN: Px = Px(48);
+
+/// The space around the text of the query and of each command.
+const PADDING: Px = Px(6);
+
+/// The most commands shown at once. The list scrolls to show the rest.
+const MAX_ROWS: usize = 10;
+
+const BACKGROUND: Color = Color {
+    r: 0.97,
+    g: 0.97,
+    b: 0.97,
+    a: 1.0,
+};
+
+const BORDER: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+const HIGHLIGHT: Color = Color {
+    r: 0.6,
+    g: 0.8,
+    b: 1.0,
+    a: 1.0,
+};
+
+const HINT: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+/// Shown in place of the query until something is typed.
+const PLACEHOLDER: &str = "Type a command";
+
+/// A command that can be run from a [`CommandPalette`].
+struct PaletteCommand {
+    label: String,
+    accelerator: Option<Accelerator>,
+    action: Box<dyn FnMut()>,
+}
+
+/// Lists commands above its child when a shortcut is pressed, and runs the one
+/// that the user chooses.
+///
+/// Ctrl+Shift+P opens the palette by default. Typing filters the commands to
+/// those whose labels contain the typed characters in order, best matches
+/// first, so that "ops" finds "Open Settings". Up and Down move the
+/// highlight, and Enter or clicking runs the highlighted command. Escape or
+/// clicking outside of the palette closes it.
+///
+/// Commands are added one at a time with [`Self::with_command`], or taken from
+/// a menu bar with [`Self::with_menu`] so that everything in the menus can be
+/// found by name. Each command's accelerator is shown beside it, but handling
+/// it is up to the application, as the shell does for menu bars.
+#[must_use]
+pub struct CommandPalette<W: Widget> {
+    widget_state: WidgetState,
+    pub child: W,
+    style: TextStyle,
+    pub color: Color,
+    commands: Vec<PaletteCommand>,
+    shortcut: Accelerator,
+    open: bool,
+    query: String,
+    /// The indices of the commands that match the query, best first.
+    matches: Vec<usize>,
+    /// The position in `matches` of the highlighted command.
+    highlighted: usize,
+    /// The position in `matches` of the first command shown.
+    first: usize,
+    window_extent: Extent,
+    /// The bounds of the palette, in absolute coordinates.
+    rect: Rect,
+    // Computed during layout.
+    row_height: Px,
+    ascent: Px,
+    query_line: Option<ShapedLine>,
+    rows: Vec<(ShapedLine, Option<(ShapedLine, Px)>)>,
+}
+
+impl<W: Widget> CommandPalette<W> {
+    pub fn new(style: TextStyle, child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            child,
+            style,
+            color: Color::BLACK,
+            commands: Vec::new(),
+            shortcut: Accelerator::new(
+                Key::P,
+                Modifiers {
+                    shift: true,
+                    control: true,
+                    alt: false,
+                },
+            ),
+            open: false,
+            query: String::new(),
+            matches: Vec::new(),
+            highlighted: 0,
+            first: 0,
+            window_extent: Extent::zero(),
+            rect: Rect::zero(),
+            row_height: Px(0),
+            ascent: Px(0),
+            query_line: None,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Adds a command that calls `action` when it is chosen.
+    pub fn with_command(
+        mut self,
+        label: impl Into<String>,
+        accelerator: Option<Accelerator>,
+        action: impl FnMut() + 'static,
+    ) -> Self {
+        self.commands.push(PaletteCommand {
+            label: label.into(),
+            accelerator,
+            action: Box::new(action),
+        });
+        self
+    }
+
+    /// Adds every enabled item in `menu` and its submenus, labeled with the
+    /// submenus that lead to it, such as "File: Save". Choosing one calls
+    /// `on_select` with its id, just as the menu bar reports it to
+    /// [`Application::on_menu_item`](crate::application::Application::on_menu_item).
+    pub fn with_menu(mut self, menu: &Menu, on_select: impl FnMut(MenuItemId) + 'static) -> Self {
+        let on_select: Rc<RefCell<dyn FnMut(MenuItemId)>> = Rc::new(RefCell::new(on_select));
+        self.add_menu(menu, "", &on_select);
+        self
+    }
+
+    fn add_menu(
+        &mut self,
+        menu: &Menu,
+        prefix: &str,
+        on_select: &Rc<RefCell<dyn FnMut(MenuItemId)>>,
+    ) {
+        for item in &menu.items {
+            match item {
+                MenuItem::Action {
+                    id,
+                    label,
+                    enabled: true,
+                    accelerator,
+                    ..
+                } => {
+                    let id = *id;
+                    let on_select = on_select.clone();
+                    self.commands.push(PaletteCommand {
+                        label: format!("{prefix}{}", strip_mnemonics(label)),
+                        accelerator: *accelerator,
+                        action: Box::new(move || (on_select.borrow_mut())(id)),
+                    });
+                }
+                MenuItem::Submenu { label, menu } => {
+                    let prefix = format!("{prefix}{}: ", strip_mnemonics(label));
+                    self.add_menu(menu, &prefix, on_select);
+                }
+                MenuItem::Action { .. } | MenuItem::Separator => {}
+            }
+        }
+    }
+
+    /// Changes the keys that open the palette from Ctrl+Shift+P.
+    pub fn with_shortcut(mut self, shortcut: Accelerator) -> Self {
+        self.shortcut = shortcut;
+        self
+    }
+
+    /// Checks if the palette is open.
+    #[must_use]
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Finds the commands that match the query, and highlights the best one.
+    fn search(&mut self) {
+        let mut scored = self
+            .commands
+            .iter()
+            .enumerate()
+            .filter_map(|(i, command)| Some((fuzzy_score(&self.query, &command.label)?, i)))
+            .collect::<Vec<_>>();
+        // Commands that match equally well keep the order they were added in.
+        scored.sort_by_key(|&(score, i)| (std::cmp::Reverse(score), i));
+
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.highlighted = 0;
+        self.first = 0;
+        self.widget_state.set_needs_layout();
+    }
+
+    /// Places the palette near the top of the window, big enough for the
+    /// query and the commands shown.
+    fn palette_rect(&self) -> Rect {
+        let rows = self.matches.len().min(MAX_ROWS) + 1;
+        let row_height = self.row_height + PADDING + PADDING;
+        let width = MAX_WIDTH.min(self.window_extent.width.saturating_sub(MARGIN + MARGIN));
+        let extent = Extent {
+            width,
+            height: row_height * rows as f32 + Px(2),
+        };
+
+        Rect::new(
+            Point::new((self.window_extent.width - width) / 2, MARGIN),
+            extent,
+        )
+    }
+
+    /// The position in `matches` of the command shown at `point`.
+    fn match_at(&self, point: Point) -> Option<usize> {
+        if !self.rect.contains_point(point) {
+            return None;
+        }
+
+        let row_height = self.row_height + PADDING + PADDING;
+        let y = point.y - self.rect.top - Px(1) - row_height;
+        if y < 0 || row_height <= 0 {
+            return None;
+        }
+
+        let index = self.first + usize::try_from(y.0 / row_height.0).ok()?;
+        (index < self.matches.len().min(self.first + MAX_ROWS)).then_some(index)
+    }
+
+    /// Moves the highlight to `index`, scrolling the list to show it.
+    fn highlight(&mut self, index: usize) {
+        self.highlighted = index;
+        if index < self.first {
+            self.first = index;
+        } else if index >= self.first + MAX_ROWS {
+            self.first = index + 1 - MAX_ROWS;
+        }
+    }
+
+    fn close(&mut self) {
+        self.open = false;
+        self.query.clear();
+        self.widget_state.set_needs_layout();
+    }
+
+    /// Closes the palette and runs the command at `index` in `matches`.
+    fn run(&mut self, index: usize) {
+        let command = match self.matches.get(index) {
+            Some(&command) => command,
+            None => return,
+        };
+
+        self.close();
+        (self.commands[command].action)();
+    }
+
+    /// Handles the events received while the palette is open. The palette
+    /// receives every event while it is open.
+    fn update_palette(&mut self, context: &mut UpdateContext) {
+        let before = self.rect;
+
+        match context.event() {
+            Event::None
+            | Event::Scroll { .. }
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => return,
+            Event::CursorMove { position } => match self.match_at(position) {
+                Some(index) if index != self.highlighted => self.highlighted = index,
+                _ => return,
+            },
+            Event::MouseButton { button, state } => {
+                if state != ButtonState::Pressed {
+                    return;
+                }
+
+                match self.match_at(context.cursor_position()) {
+                    Some(index) if button == MouseButton::Left => self.run(index),
+                    Some(_) => return,
+                    None if self.rect.contains_point(context.cursor_position()) => return,
+                    None => self.close(),
+                }
+            }
+            Event::Text { character } => {
+                if character.is_control() {
+                    return;
+                }
+                self.query.push(character);
+                self.search();
+            }
+            Event::KeyPress { key, .. } => {
+                let last = self.matches.len().saturating_sub(1);
+                match key {
+                    Key::Up => self.highlight(self.highlighted.saturating_sub(1)),
+                    Key::Down => self.highlight((self.highlighted + 1).min(last)),
+                    Key::PageUp => self.highlight(self.highlighted.saturating_sub(MAX_ROWS)),
+                    Key::PageDown => self.highlight((self.highlighted + MAX_ROWS).min(last)),
+                    Key::Backspace => {
+                        if self.query.pop().is_none() {
+                            return;
+                        }
+                        self.search();
+                    }
+                    Key::Enter => self.run(self.highlighted),
+                    Key::Escape => self.close(),
+                    _ => return,
+                }
+                context.set_handled();
+            }
+        }
+
+        if self.open {
+            self.rect = self.palette_rect();
+        }
+        context.redraw(before);
+        context.redraw(self.rect);
+        self.widget_state.set_needs_layout();
+    }
+}
+
+impl<W: Widget> Widget for CommandPalette<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        if self.open {
+            self.update_palette(context);
+            return PostUpdate::NoChange;
+        }
+
+        // The shortcut isn't passed on, so that it doesn't also type into a
+        // focused text input.
+        match context.event() {
+            Event::KeyPress { key, modifiers }
+                if key == self.shortcut.key && modifiers == self.shortcut.modifiers =>
+            {
+                self.open = true;
+                self.window_extent = context.window_extent();
+                self.search();
+                self.rect = self.palette_rect();
+                context.redraw(self.rect);
+                context.set_handled();
+                PostUpdate::NoChange
+            }
+            _ => {
+                context.update(&mut self.child);
+                PostUpdate::NoChange
+            }
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let shape = |text: &str| {
+            let mut metrics = context.measure_text(text, &self.style, None);
+            let width = Px::from(metrics.width.ceil());
+            (metrics.lines.swap_remove(0).shaped, width)
+        };
+
+        let metrics = context.measure_text("", &self.style, None);
+        self.row_height = Px::from(metrics.line_height.ceil());
+        self.ascent = Px::from(metrics.ascent.round());
+
+        if self.open {
+            let query = if self.query.is_empty() {
+                PLACEHOLDER
+            } else {
+                &self.query
+            };
+            self.query_line = Some(shape(query).0);
+
+            let shown = self.first..self.matches.len().min(self.first + MAX_ROWS);
+            self.rows = self.matches[shown]
+                .iter()
+                .map(|&i| {
+                    let command = &self.commands[i];
+                    let accelerator = command
+                        .accelerator
+                        .map(|accelerator| shape(&accelerator.to_string()));
+                    (shape(&command.label).0, accelerator)
+                })
+                .collect();
+        } else {
+            self.query_line = None;
+            self.rows.clear();
+        }
+
+        let extent = context.layout(&mut self.child, constraints);
+        context.position_widget(&mut self.child, Offset::zero(), extent);
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+
+    fn has_overlay(&self) -> bool {
+        self.open
+    }
+
+    fn accept_draw_overlay(&self, canvas: &mut DrawContext) {
+        let origin = self.widget_state.origin() - Point::zero();
+        let rect = self.rect - origin;
+        let row_height = self.row_height + PADDING + PADDING;
+
+        canvas.draw_rect(rect, &Paint::Fill { color: BACKGROUND });
+        canvas.push_clip_rounded_rect(rect, Px(0));
+
+        let mut top = rect.top + Px(1);
+        if let Some(query) = &self.query_line {
+            let color = if self.query.is_empty() {
+                HINT
+            } else {
+                self.color
+            };
+            canvas.draw_text(
+                query,
+                Point::new(rect.left + PADDING, top + PADDING + self.ascent),
+                self.style.size,
+                color,
+            );
+        }
+        top += row_height;
+        canvas.stroke_line(
+            Point::new(rect.left, top),
+            Point::new(rect.right, top),
+            &Stroke::solid(BORDER, 1.0),
+        );
+
+        for (i, (label, accelerator)) in self.rows.iter().enumerate() {
+            let row_rect = Rect {
+                top,
+                bottom: top + row_height,
+                ..rect
+            };
+            if self.first + i == self.highlighted {
+                canvas.draw_rect(row_rect, &Paint::Fill { color: HIGHLIGHT });
+            }
+
+            let baseline = top + PADDING + self.ascent;
+            canvas.draw_text(
+                label,
+                Point::new(rect.left + PADDING, baseline),
+                self.style.size,
+                self.color,
+            );
+            if let Some((accelerator, width)) = accelerator {
+                canvas.draw_text(
+                    accelerator,
+                    Point::new(rect.right - PADDING - *width, baseline),
+                    self.style.size,
+                    HINT,
+                );
+            }
+
+            top += row_height;
+        }
+
+        canvas.pop_clip();
+        canvas.stroke_rect(rect, &Stroke::solid(BORDER, 1.0));
+    }
+}
+
+/// Removes the `&`s that mark the mnemonics in a menu label, leaving `&&` as
+/// a single `&`. See [`Menu`].
+fn strip_mnemonics(label: &str) -> String {
+    let mut stripped = String::with_capacity(label.len());
+    let mut chars = label.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => stripped.extend(chars.next()),
+            c => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+/// Scores how well `label` matches `query`, or returns `None` if the
+/// characters of `query` don't all appear in `label` in order. Case is
+/// ignored. Characters that follow each other or start words in `label` score
+/// higher, and an empty query matches everything equally.
+fn fuzzy_score(query: &str, label: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut label_chars = label.chars().flat_map(char::to_lowercase).enumerate();
+    let mut previous_match: Option<usize> = None;
+    let mut previous_char = ' ';
+
+    for q in query.chars().flat_map(char::to_lowercase) {
+        loop {
+            let (position, c) = label_chars.next()?;
+            let before = std::mem::replace(&mut previous_char, c);
+
+            if c != q {
+                continue;
+            }
+
+            score += 1;
+            if previous_match.is_some_and(|previous| previous + 1 == position) {
+                score += 4;
+            }
+            if !before.is_alphanumeric() {
+                score += 3;
+            }
+            previous_match = Some(position);
+            break;
+        }
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matching() {
+        assert_eq!(fuzzy_score("", "Anything"), Some(0));
+        assert_eq!(fuzzy_score("xyz", "Open File"), None);
+        assert_eq!(fuzzy_score("fo", "Open File"), None);
+
+        // Word starts and runs of characters score higher than scattered
+        // matches.
+        let initials = fuzzy_score("of", "Open File").unwrap();
+        let scattered = fuzzy_score("ne", "Open File").unwrap();
+        assert!(initials > scattered);
+
+        let run = fuzzy_score("file", "Open File").unwrap();
+        let spread = fuzzy_score("file", "Find in all files").unwrap();
+        assert!(run > fuzzy_score("fl", "Open File").unwrap());
+        assert!(spread > 0);
+        assert_eq!(
+            fuzzy_score("OPEN", "open file"),
+            fuzzy_score("open", "Open File")
+        );
+    }
+
+    #[test]
+    fn menu_labels() {
+        assert_eq!(strip_mnemonics("&Open"), "Open");
+        assert_eq!(strip_mnemonics("Save &As..."), "Save As...");
+        assert_eq!(strip_mnemonics("Fish && Chips"), "Fish & Chips");
+    }
+}
@@ -0,0 +1,798 @@
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Offset, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::input::{ButtonState, Event, MouseButton},
+    shell::event::Key,
+    text::{ShapedLine, TextStyle},
+};
+
+use super::{
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// The space above and below the text of a menu item.
+const PADDING: Px = Px(4);
+
+/// The space before an item's label and after its shortcut, which also holds
+/// the arrow of a submenu.
+const INDENT: Px = Px(20);
+
+/// The smallest space between an item's label and its shortcut.
+const SHORTCUT_GAP: Px = Px(24);
+
+const SEPARATOR_HEIGHT: Px = Px(9);
+
+const BACKGROUND: Color = Color {
+    r: 0.97,
+    g: 0.97,
+    b: 0.97,
+    a: 1.0,
+};
+
+const BORDER: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+const HIGHLIGHT: Color = Color {
+    r: 0.6,
+    g: 0.8,
+    b: 1.0,
+    a: 1.0,
+};
+
+const DISABLED: Color = Color {
+    r: 0.6,
+    g: 0.6,
+    b: 0.6,
+    a: 1.0,
+};
+
+/// An item in a menu that does something when chosen.
+#[must_use]
+pub struct MenuAction {
+    /// Passed to the menu's callback when the action is chosen.
+    pub id: u32,
+    pub label: String,
+    /// The key combination that does the same thing as the action, such as
+    /// "Ctrl+C". This is only shown in the menu; handling the keys is up to
+    /// the application.
+    pub shortcut: Option<String>,
+    /// Disabled actions are drawn grayed out and can't be chosen.
+    pub enabled: bool,
+}
+
+impl MenuAction {
+    pub fn new(id: u32, label: impl Into<String>) -> Self {
+        Self {
+            id,
+            label: label.into(),
+            shortcut: None,
+            enabled: true,
+        }
+    }
+
+    pub fn with_shortcut(mut self, shortcut: impl Into<String>) -> Self {
+        self.shortcut = Some(shortcut.into());
+        self
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+}
+
+pub enum MenuItem {
+    Action(MenuAction),
+    /// A line between groups of related items.
+    Separator,
+    /// An item that opens another menu beside the one it is in.
+    Submenu {
+        label: String,
+        menu: MenuModel,
+    },
+}
+
+impl MenuItem {
+    /// Checks if the item can be highlighted and chosen.
+    #[must_use]
+    pub fn is_selectable(&self) -> bool {
+        match self {
+            Self::Action(action) => action.enabled,
+            Self::Separator => false,
+            Self::Submenu { .. } => true,
+        }
+    }
+}
+
+/// The items in a menu.
+#[must_use]
+#[derive(Default)]
+pub struct MenuModel {
+    pub items: Vec<MenuItem>,
+}
+
+impl MenuModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_action(mut self, action: MenuAction) -> Self {
+        self.items.push(MenuItem::Action(action));
+        self
+    }
+
+    pub fn with_separator(mut self) -> Self {
+        self.items.push(MenuItem::Separator);
+        self
+    }
+
+    pub fn with_submenu(mut self, label: impl Into<String>, menu: MenuModel) -> Self {
+        self.items.push(MenuItem::Submenu {
+            label: label.into(),
+            menu,
+        });
+        self
+    }
+
+    /// Follows `path`, the indices of submenu items, from this menu to one of
+    /// its submenus.
+    fn submenu(&self, path: &[usize]) -> &MenuModel {
+        path.iter().fold(self, |menu, &i| match &menu.items[i] {
+            MenuItem::Submenu { menu, .. } => menu,
+            _ => unreachable!("menu paths only contain submenus"),
+        })
+    }
+}
+
+/// The measurements of a menu, computed during layout and mirroring the
+/// structure of its [`MenuModel`].
+#[derive(Default)]
+struct MenuLayout {
+    extent: Extent,
+    rows: Vec<RowLayout>,
+}
+
+impl MenuLayout {
+    fn submenu(&self, path: &[usize]) -> &MenuLayout {
+        path.iter().fold(self, |layout, &i| {
+            layout.rows[i]
+                .submenu
+                .as_ref()
+                .expect("menu paths only contain submenus")
+        })
+    }
+}
+
+struct RowLayout {
+    /// The distance from the top of the menu to the top of the row.
+    top: Px,
+    height: Px,
+    label: Option<ShapedLine>,
+    shortcut: Option<(ShapedLine, Px)>,
+    submenu: Option<MenuLayout>,
+}
+
+/// A menu that is currently open.
+struct OpenMenu {
+    /// The indices of the submenu items leading from the root menu to this
+    /// one.
+    path: Vec<usize>,
+    /// The bounds of the menu, in absolute coordinates.
+    rect: Rect,
+    highlighted: Option<usize>,
+}
+
+/// Shows a menu when its child is right-clicked.
+///
+/// While the menu is open, hovering over an item highlights it and hovering
+/// over a submenu opens it. Up and Down move the highlight, Right opens the
+/// highlighted submenu, Left closes the innermost submenu, and Enter or Space
+/// chooses the highlighted item. Escape closes the innermost menu, and
+/// choosing an action or clicking outside of the menus closes all of them.
+#[must_use]
+pub struct ContextMenu<W: Widget> {
+    widget_state: WidgetState,
+    pub child: W,
+    menu: MenuModel,
+    style: TextStyle,
+    pub color: Color,
+    on_select: Option<Box<dyn FnMut(u32)>>,
+    /// The open menus, from the root menu to the innermost submenu.
+    open: Vec<OpenMenu>,
+    window_extent: Extent,
+    // Computed during layout.
+    layout: MenuLayout,
+    ascent: Px,
+}
+
+impl<W: Widget> ContextMenu<W> {
+    pub fn new(menu: MenuModel, style: TextStyle, child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            child,
+            menu,
+            style,
+            color: Color::BLACK,
+            on_select: None,
+            open: Vec::new(),
+            window_extent: Extent::zero(),
+            layout: MenuLayout::default(),
+            ascent: Px(0),
+        }
+    }
+
+    /// Calls `callback` with the id of the action whenever the user chooses
+    /// one.
+    pub fn with_on_select(mut self, callback: impl FnMut(u32) + 'static) -> Self {
+        self.on_select = Some(Box::new(callback));
+        self
+    }
+
+    pub fn menu(&self) -> &MenuModel {
+        &self.menu
+    }
+
+    /// Replaces the menu, closing it if it is open.
+    pub fn set_menu(&mut self, menu: MenuModel) {
+        self.menu = menu;
+        self.open.clear();
+        self.widget_state.set_needs_layout();
+    }
+
+    /// Checks if the menu is open.
+    #[must_use]
+    pub fn is_open(&self) -> bool {
+        !self.open.is_empty()
+    }
+
+    /// The area covered by every open menu, in absolute coordinates.
+    fn open_rect(&self) -> Rect {
+        self.open
+            .iter()
+            .fold(Rect::zero(), |rect, menu| rect.union(&menu.rect))
+    }
+
+    /// Finds the innermost open menu containing `point`, and the row of that
+    /// menu at `point` if there is one.
+    fn menu_at(&self, point: Point) -> Option<(usize, Option<usize>)> {
+        let level = self
+            .open
+            .iter()
+            .rposition(|menu| menu.rect.contains_point(point))?;
+
+        let menu = &self.open[level];
+        let y = point.y - menu.rect.top;
+        let row = self
+            .layout
+            .submenu(&menu.path)
+            .rows
+            .iter()
+            .position(|row| y >= row.top && y < row.top + row.height);
+        Some((level, row))
+    }
+
+    /// Opens the submenu at `row` of the menu at `level`, closing any menus
+    /// opened from it before.
+    fn open_submenu(&mut self, level: usize, row: usize, highlight_first: bool) {
+        self.open.truncate(level + 1);
+        let parent = &self.open[level];
+
+        let mut path = parent.path.clone();
+        path.push(row);
+
+        let row_layout = &self.layout.submenu(&parent.path).rows[row];
+        let item_rect = Rect {
+            top: parent.rect.top + row_layout.top,
+            bottom: parent.rect.top + row_layout.top + row_layout.height,
+            ..parent.rect
+        };
+        let extent = self.layout.submenu(&path).extent;
+        let rect = place_submenu(item_rect, extent, self.window_extent);
+
+        let highlighted = if highlight_first {
+            next_selectable(&self.selectable(&path), None, true)
+        } else {
+            None
+        };
+
+        self.open.push(OpenMenu {
+            path,
+            rect,
+            highlighted,
+        });
+    }
+
+    /// Which of the items in the menu at `path` can be highlighted.
+    fn selectable(&self, path: &[usize]) -> Vec<bool> {
+        self.menu
+            .submenu(path)
+            .items
+            .iter()
+            .map(MenuItem::is_selectable)
+            .collect()
+    }
+
+    /// Chooses the item at `row` of the menu at `level`, either opening it if
+    /// it's a submenu or closing every menu and calling the callback if it's
+    /// an action.
+    fn activate(&mut self, level: usize, row: usize, from_keyboard: bool) {
+        let path = &self.open[level].path;
+        match &self.menu.submenu(path).items[row] {
+            MenuItem::Action(action) if action.enabled => {
+                let id = action.id;
+                self.open.clear();
+                if let Some(on_select) = &mut self.on_select {
+                    on_select(id);
+                }
+            }
+            MenuItem::Submenu { .. } => self.open_submenu(level, row, from_keyboard),
+            MenuItem::Action(_) | MenuItem::Separator => {}
+        }
+    }
+
+    /// Handles the events received while the menu is open. The menu receives
+    /// every event while it is open.
+    fn update_menu(&mut self, context: &mut UpdateContext) {
+        let before = self.open_rect();
+
+        match context.event() {
+            Event::None
+            | Event::Scroll { .. }
+            | Event::Text { .. }
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => return,
+            Event::CursorMove { position } => {
+                let (level, row) = match self.menu_at(position) {
+                    Some(found) => found,
+                    None => return,
+                };
+
+                let row = row.filter(|&row| {
+                    self.menu.submenu(&self.open[level].path).items[row].is_selectable()
+                });
+                // Nothing changes while the cursor stays on the same item,
+                // including a submenu item whose submenu is already open.
+                let submenu_open = match (row, self.open.get(level + 1)) {
+                    (Some(row), Some(submenu)) => submenu.path.last() == Some(&row),
+                    (None, next) => next.is_none(),
+                    _ => false,
+                };
+                if self.open[level].highlighted == row && submenu_open {
+                    return;
+                }
+
+                self.open.truncate(level + 1);
+                self.open[level].highlighted = row;
+                if let Some(row) = row {
+                    if let MenuItem::Submenu { .. } =
+                        &self.menu.submenu(&self.open[level].path).items[row]
+                    {
+                        self.open_submenu(level, row, false);
+                    }
+                }
+            }
+            Event::MouseButton { button, state } => {
+                if state != ButtonState::Pressed {
+                    return;
+                }
+
+                match self.menu_at(context.cursor_position()) {
+                    Some((level, Some(row))) if button == MouseButton::Left => {
+                        self.activate(level, row, false);
+                    }
+                    Some(_) => return,
+                    None => self.open.clear(),
+                }
+            }
+            Event::KeyPress { key, .. } => {
+                let level = self.open.len() - 1;
+                let path = self.open[level].path.clone();
+                let selectable = self.selectable(&path);
+                let highlighted = self.open[level].highlighted;
+
+                match key {
+                    Key::Up | Key::Down => {
+                        self.open[level].highlighted =
+                            next_selectable(&selectable, highlighted, key == Key::Down);
+                    }
+                    Key::Home => {
+                        self.open[level].highlighted = next_selectable(&selectable, None, true);
+                    }
+                    Key::End => {
+                        self.open[level].highlighted = next_selectable(&selectable, None, false);
+                    }
+                    Key::Right => {
+                        if let Some(row) = highlighted {
+                            if let MenuItem::Submenu { .. } = &self.menu.submenu(&path).items[row] {
+                                self.open_submenu(level, row, true);
+                            }
+                        }
+                    }
+                    Key::Left if level > 0 => {
+                        self.open.pop();
+                    }
+                    Key::Escape => {
+                        self.open.pop();
+                    }
+                    Key::Enter | Key::Space => {
+                        if let Some(row) = highlighted {
+                            self.activate(level, row, true);
+                        }
+                    }
+                    Key::Tab => self.open.clear(),
+                    _ => return,
+                }
+            }
+        }
+
+        context.redraw(before);
+        context.redraw(self.open_rect());
+    }
+}
+
+impl<W: Widget> Widget for ContextMenu<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        if self.is_open() {
+            self.update_menu(context);
+            return PostUpdate::NoChange;
+        }
+
+        context.update(&mut self.child);
+
+        if let Event::MouseButton {
+            button: MouseButton::Right,
+            state: ButtonState::Pressed,
+        } = context.event()
+        {
+            let cursor = context.cursor_position();
+            if self.widget_state.rect().contains_point(cursor) && !self.menu.items.is_empty() {
+                self.window_extent = context.window_extent();
+                let rect = place_menu(cursor, self.layout.extent, self.window_extent);
+                self.open.push(OpenMenu {
+                    path: Vec::new(),
+                    rect,
+                    highlighted: None,
+                });
+                context.redraw(rect);
+            }
+        }
+
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let metrics = context.measure_text("", &self.style, None);
+        self.ascent = Px::from(metrics.ascent.round());
+        let row_height = Px::from(metrics.line_height.ceil()) + PADDING + PADDING;
+        self.layout = measure_menu(context, &self.menu, &self.style, row_height);
+
+        let extent = context.layout(&mut self.child, constraints);
+        context.position_widget(&mut self.child, Offset::zero(), extent);
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+
+    fn has_overlay(&self) -> bool {
+        self.is_open()
+    }
+
+    fn accept_draw_overlay(&self, canvas: &mut DrawContext) {
+        let origin = self.widget_state.origin() - Point::zero();
+
+        for open in &self.open {
+            let rect = open.rect - origin;
+            let model = self.menu.submenu(&open.path);
+            let layout = self.layout.submenu(&open.path);
+
+            canvas.draw_rect(rect, &Paint::Fill { color: BACKGROUND });
+
+            for (i, (item, row)) in model.items.iter().zip(&layout.rows).enumerate() {
+                let top = rect.top + row.top;
+                let row_rect = Rect {
+                    top,
+                    bottom: top + row.height,
+                    ..rect
+                };
+
+                if open.highlighted == Some(i) {
+                    canvas.draw_rect(row_rect, &Paint::Fill { color: HIGHLIGHT });
+                }
+
+                let color = if item.is_selectable() {
+                    self.color
+                } else {
+                    DISABLED
+                };
+                let baseline = top + PADDING + self.ascent;
+
+                if let Some(label) = &row.label {
+                    canvas.draw_text(
+                        label,
+                        Point::new(rect.left + INDENT, baseline),
+                        self.style.size,
+                        color,
+                    );
+                }
+
+                if let Some((shortcut, width)) = &row.shortcut {
+                    canvas.draw_text(
+                        shortcut,
+                        Point::new(rect.right - INDENT - *width, baseline),
+                        self.style.size,
+                        color,
+                    );
+                }
+
+                match item {
+                    MenuItem::Separator => {
+                        let y = top + row.height / 2;
+                        canvas.stroke_line(
+                            Point::new(rect.left + PADDING, y),
+                            Point::new(rect.right - PADDING, y),
+                            &Stroke::solid(DISABLED, 1.0),
+                        );
+                    }
+                    MenuItem::Submenu { .. } => {
+                        // A rightward arrow in the indent after the label.
+                        let size = row.height / 3;
+                        let x = rect.right - INDENT / 2 - size / 4;
+                        let y = top + row.height / 2;
+                        let stroke = Stroke::solid(color, 1.5);
+                        canvas.stroke_line(
+                            Point::new(x - size / 4, y - size / 2),
+                            Point::new(x + size / 4, y),
+                            &stroke,
+                        );
+                        canvas.stroke_line(
+                            Point::new(x + size / 4, y),
+                            Point::new(x - size / 4, y + size / 2),
+                            &stroke,
+                        );
+                    }
+                    MenuItem::Action(_) => {}
+                }
+            }
+
+            canvas.stroke_rect(rect, &Stroke::solid(BORDER, 1.0));
+        }
+    }
+}
+
+/// Measures every item in `menu` and its submenus.
+fn measure_menu(
+    context: &LayoutContext,
+    menu: &MenuModel,
+    style: &TextStyle,
+    row_height: Px,
+) -> MenuLayout {
+    let mut shape = |text: &str| {
+        let mut metrics = context.measure_text(text, style, None);
+        let width = Px::from(metrics.width.ceil());
+        (metrics.lines.swap_remove(0).shaped, width)
+    };
+
+    let mut rows = Vec::with_capacity(menu.items.len());
+    let mut label_width = Px(0);
+    let mut shortcut_width = Px(0);
+    let mut top = Px(1);
+
+    for item in &menu.items {
+        let (label, shortcut, submenu) = match item {
+            MenuItem::Action(action) => (
+                Some(shape(&action.label)),
+                action.shortcut.as_deref().map(&mut shape),
+                None,
+            ),
+            MenuItem::Separator => (None, None, None),
+            MenuItem::Submenu { label, menu } => (
+                Some(shape(label)),
+                None,
+                Some(measure_menu(context, menu, style, row_height)),
+            ),
+        };
+
+        if let Some((_, width)) = &label {
+            label_width = label_width.max(*width);
+        }
+        if let Some((_, width)) = &shortcut {
+            shortcut_width = shortcut_width.max(*width);
+        }
+
+        let height = match item {
+            MenuItem::Separator => SEPARATOR_HEIGHT,
+            _ => row_height,
+        };
+        rows.push(RowLayout {
+            top,
+            height,
+            label: label.map(|(line, _)| line),
+            shortcut,
+            submenu,
+        });
+        top += height;
+    }
+
+    let gap = if shortcut_width > 0 {
+        SHORTCUT_GAP
+    } else {
+        Px(0)
+    };
+
+    MenuLayout {
+        extent: Extent {
+            width: INDENT + label_width + gap + shortcut_width + INDENT,
+            height: top + Px(1),
+        },
+        rows,
+    }
+}
+
+/// Finds the next item that can be highlighted after `from`, or before it if
+/// `forward` is false, wrapping around at the ends of the menu. If `from` is
+/// `None`, the search starts from the first or last item.
+fn next_selectable(selectable: &[bool], from: Option<usize>, forward: bool) -> Option<usize> {
+    let count = selectable.len();
+    (1..=count)
+        .map(|step| match (from, forward) {
+            (Some(from), true) => (from + step) % count,
+            (Some(from), false) => (from + count - step % count) % count,
+            (None, true) => step - 1,
+            (None, false) => count - step,
+        })
+        .find(|&i| selectable[i])
+}
+
+/// Places a menu of size `extent` with its top-left corner at `cursor`. If it
+/// doesn't fit in a window of size `window`, it opens to the left of or above
+/// the cursor instead, and is then moved to stay inside the window.
+fn place_menu(cursor: Point, extent: Extent, window: Extent) -> Rect {
+    let left = if cursor.x + extent.width <= window.width {
+        cursor.x
+    } else {
+        cursor.x - extent.width
+    };
+    let top = if cursor.y + extent.height <= window.height {
+        cursor.y
+    } else {
+        cursor.y - extent.height
+    };
+
+    Rect::new(
+        clamp_to_window(Point::new(left, top), extent, window),
+        extent,
+    )
+}
+
+/// Places a submenu of size `extent` beside the item that opened it, whose
+/// bounds are `item`. The submenu opens to the right of the item unless it
+/// doesn't fit in a window of size `window`, in which case it opens to the
+/// left.
+fn place_submenu(item: Rect, extent: Extent, window: Extent) -> Rect {
+    let left = if item.right + extent.width <= window.width {
+        item.right
+    } else {
+        item.left - extent.width
+    };
+
+    // Line up the first item of the submenu with the item that opened it.
+    let top = item.top - Px(1);
+
+    Rect::new(
+        clamp_to_window(Point::new(left, top), extent, window),
+        extent,
+    )
+}
+
+/// Moves `origin` so that a rectangle of size `extent` there is inside a
+/// window of size `window`, keeping its top-left corner visible if it is too
+/// big to fit.
+fn clamp_to_window(origin: Point, extent: Extent, window: Extent) -> Point {
+    let x = origin
+        .x
+        .min(window.width.saturating_sub(extent.width))
+        .max(Px(0));
+    let y = origin
+        .y
+        .min(window.height.saturating_sub(extent.height))
+        .max(Px(0));
+    Point::new(x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyboard_navigation() {
+        let selectable = [true, false, true, true, false];
+
+        assert_eq!(next_selectable(&selectable, None, true), Some(0));
+        assert_eq!(next_selectable(&selectable, None, false), Some(3));
+        assert_eq!(next_selectable(&selectable, Some(0), true), Some(2));
+        assert_eq!(next_selectable(&selectable, Some(3), true), Some(0));
+        assert_eq!(next_selectable(&selectable, Some(0), false), Some(3));
+        assert_eq!(next_selectable(&selectable, Some(2), false), Some(0));
+
+        assert_eq!(next_selectable(&[false, false], None, true), None);
+        assert_eq!(next_selectable(&[], None, true), None);
+    }
+
+    #[test]
+    fn menu_placement() {
+        let window = Extent {
+            width: Px(200),
+            height: Px(100),
+        };
+        let extent = Extent {
+            width: Px(50),
+            height: Px(40),
+        };
+
+        assert_eq!(
+            place_menu(Point::new(10, 10), extent, window),
+            Rect::new(Point::new(10, 10), extent)
+        );
+        assert_eq!(
+            place_menu(Point::new(180, 90), extent, window),
+            Rect::new(Point::new(130, 50), extent)
+        );
+
+        let item = Rect {
+            left: Px(10),
+            top: Px(20),
+            right: Px(60),
+            bottom: Px(40),
+        };
+        assert_eq!(
+            place_submenu(item, extent, window),
+            Rect::new(Point::new(60, 19), extent)
+        );
+
+        let item = Rect {
+            left: Px(140),
+            right: Px(190),
+            ..item
+        };
+        assert_eq!(
+            place_submenu(item, extent, window),
+            Rect::new(Point::new(90, 19), extent)
+        );
+    }
+}
@@ -38,11 +38,21 @@ impl Widget for Fill {
         &mut self.widget_state
     }
 
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
     fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
 
     fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
         match context.event() {
-            Event::None | Event::CursorMove { .. } => PostUpdate::NoChange,
+            Event::None
+            | Event::CursorMove { .. }
+            | Event::Scroll { .. }
+            | Event::KeyPress { .. }
+            | Event::Text { .. }
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => PostUpdate::NoChange,
             Event::MouseButton { button, state } => {
                 if button.is_left() && state.is_pressed() {
                     self.color = random();
@@ -96,6 +106,8 @@ impl Widget for FillImage {
         &mut self.widget_state
     }
 
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
     fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
 
     fn accept_update(&mut self, _context: &mut UpdateContext) -> PostUpdate {
@@ -112,12 +124,9 @@ impl Widget for FillImage {
 
     fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
         canvas.draw_image(
-            Rect::new(Point::zero(), extent),
             self.image,
             Rect::new(Point::zero(), self.image_extent),
-            &Paint::Fill {
-                color: Color::WHITE,
-            },
+            Rect::new(Point::zero(), extent),
         );
     }
 }
@@ -0,0 +1,1087 @@
+//! A docking area whose panels the user can rearrange by dragging their tabs.
+//!
+//! The panels of a [`DockArea`] are arranged in a tree of [`SplitPanel`]s,
+//! with a [`TabbedPanel`] at each leaf. Dragging a tab out of its tab bar
+//! detaches the panel so that it floats above the others. While a floating
+//! panel is dragged by its title bar over a tabbed panel, the part of it that
+//! the panel would be docked into is highlighted: the middle adds the panel
+//! as a tab, and the edges split the tabbed panel to make room for it.
+//! Releasing the panel anywhere else leaves it floating.
+//!
+//! The arrangement can be saved as a [`DockLayout`], which converts to and
+//! from text, and restored later with [`DockArea::new`].
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Offset, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::input::{ButtonState, Event, MouseButton},
+};
+
+use super::{
+    split_panel::{Axis, SplitPanel},
+    tabbed_panel::TabbedPanel,
+    BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext, Widget, WidgetState,
+};
+
+type ChangeFn = Box<dyn FnMut(&DockLayout)>;
+
+/// How far a tab has to be dragged before its panel is detached.
+const DETACH_THRESHOLD: Px = Px(8);
+
+/// The height of the bar along the top of a floating panel, which it is
+/// dragged by.
+const TITLE_BAR_HEIGHT: Px = Px(16);
+
+/// The size of a newly detached panel.
+const FLOATING_EXTENT: Extent = Extent {
+    width: Px(240),
+    height: Px(180),
+};
+
+/// Where the cursor holds a newly detached panel, relative to its top-left
+/// corner.
+const FLOATING_GRAB: Offset = Offset {
+    x: Px(16),
+    y: Px(8),
+};
+
+const TITLE_BAR: Color = Color {
+    r: 0.3,
+    g: 0.3,
+    b: 0.35,
+    a: 1.0,
+};
+
+const BORDER: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+const DROP_ZONE: Color = Color {
+    r: 0.0,
+    g: 0.47,
+    b: 0.84,
+    a: 0.3,
+};
+
+const DROP_ZONE_BORDER: Color = Color {
+    r: 0.0,
+    g: 0.47,
+    b: 0.84,
+    a: 1.0,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DockError {
+    #[error("the dock layout could not be parsed: {0}")]
+    Parse(String),
+    #[error("the dock layout refers to the unknown panel {0:?}")]
+    UnknownPanel(String),
+    #[error("the panel {0:?} appears more than once")]
+    DuplicatePanel(String),
+    #[error("the panel identifier {0:?} is empty or contains whitespace or parentheses")]
+    InvalidPanelId(String),
+}
+
+/// The arrangement of the panels in a [`DockArea`], identified by name.
+///
+/// Layouts convert to and from text, such as:
+///
+/// ```text
+/// (dock (split x 0.25 0.75 (tabs 0 files) (tabs 1 editor preview)) (float log 40 40 240 180))
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DockLayout {
+    /// The docked panels, if any.
+    pub root: Option<DockLayoutNode>,
+    pub floating: Vec<FloatingLayout>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DockLayoutNode {
+    /// Panes side by side, with the fraction of the space that each takes.
+    Split {
+        axis: Axis,
+        ratios: Vec<f32>,
+        children: Vec<DockLayoutNode>,
+    },
+    /// Panels in tabs, with the index of the one that is shown.
+    Tabs { panels: Vec<String>, active: usize },
+}
+
+/// A panel floating above the docked panels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FloatingLayout {
+    pub panel: String,
+    /// The bounds of the panel, including its title bar, relative to the dock
+    /// area.
+    pub rect: Rect,
+}
+
+impl fmt::Display for DockLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(dock ")?;
+        match &self.root {
+            Some(root) => write!(f, "{root}")?,
+            None => write!(f, "none")?,
+        }
+        for floating in &self.floating {
+            let rect = floating.rect;
+            write!(
+                f,
+                " (float {} {} {} {} {})",
+                floating.panel,
+                rect.left.0,
+                rect.top.0,
+                rect.width().0,
+                rect.height().0
+            )?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for DockLayoutNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Split {
+                axis,
+                ratios,
+                children,
+            } => {
+                let axis = match axis {
+                    Axis::X => "x",
+                    Axis::Y => "y",
+                };
+                write!(f, "(split {axis}")?;
+                for ratio in ratios {
+                    write!(f, " {ratio}")?;
+                }
+                for child in children {
+                    write!(f, " {child}")?;
+                }
+                write!(f, ")")
+            }
+            Self::Tabs { panels, active } => {
+                write!(f, "(tabs {active}")?;
+                for panel in panels {
+                    write!(f, " {panel}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl FromStr for DockLayout {
+    type Err = DockError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spaced = s.replace('(', " ( ").replace(')', " ) ");
+        let mut parser = Parser {
+            tokens: spaced.split_whitespace().peekable(),
+        };
+
+        parser.expect("(")?;
+        parser.expect("dock")?;
+        let root = if parser.peek() == Some("none") {
+            parser.next()?;
+            None
+        } else {
+            Some(parser.node()?)
+        };
+
+        let mut floating = Vec::new();
+        while parser.peek() == Some("(") {
+            parser.expect("(")?;
+            parser.expect("float")?;
+            let panel = parser.next()?.to_string();
+            let left: i16 = parser.number()?;
+            let top: i16 = parser.number()?;
+            let width = parser.number()?;
+            let height = parser.number()?;
+            parser.expect(")")?;
+            floating.push(FloatingLayout {
+                panel,
+                rect: Rect::new(
+                    Point::new(left, top),
+                    Extent {
+                        width: Px(width),
+                        height: Px(height),
+                    },
+                ),
+            });
+        }
+
+        parser.expect(")")?;
+        match parser.tokens.next() {
+            Some(token) => Err(DockError::Parse(format!(
+                "unexpected {token:?} after the end"
+            ))),
+            None => Ok(Self { root, floating }),
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: std::iter::Peekable<std::str::SplitWhitespace<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Option<&'a str> {
+        self.tokens.peek().copied()
+    }
+
+    fn next(&mut self) -> Result<&'a str, DockError> {
+        self.tokens
+            .next()
+            .ok_or_else(|| DockError::Parse("unexpected end".to_string()))
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), DockError> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(DockError::Parse(format!(
+                "expected {expected:?} but found {token:?}"
+            ))),
+        }
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, DockError> {
+        let token = self.next()?;
+        token
+            .parse()
+            .map_err(|_| DockError::Parse(format!("expected a number but found {token:?}")))
+    }
+
+    fn node(&mut self) -> Result<DockLayoutNode, DockError> {
+        self.expect("(")?;
+        let node = match self.next()? {
+            "split" => {
+                let axis = match self.next()? {
+                    "x" => Axis::X,
+                    "y" => Axis::Y,
+                    token => {
+                        return Err(DockError::Parse(format!(
+                            "expected an axis but found {token:?}"
+                        )))
+                    }
+                };
+
+                let mut ratios = Vec::new();
+                while self
+                    .peek()
+                    .is_some_and(|token| token != "(" && token != ")")
+                {
+                    ratios.push(self.number()?);
+                }
+
+                let mut children = Vec::new();
+                while self.peek() == Some("(") {
+                    children.push(self.node()?);
+                }
+
+                DockLayoutNode::Split {
+                    axis,
+                    ratios,
+                    children,
+                }
+            }
+            "tabs" => {
+                let active = self.number()?;
+                let mut panels = Vec::new();
+                while self.peek().is_some_and(|token| token != ")") {
+                    panels.push(self.next()?.to_string());
+                }
+                DockLayoutNode::Tabs { panels, active }
+            }
+            token => {
+                return Err(DockError::Parse(format!(
+                    "expected a split or tabs but found {token:?}"
+                )))
+            }
+        };
+        self.expect(")")?;
+        Ok(node)
+    }
+}
+
+/// Where a panel is docked into a tabbed panel when dropped on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropZone {
+    /// Adds the panel as a tab.
+    Center,
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl DropZone {
+    /// Finds the zone of `rect` that `point` is in. The middle half of the
+    /// rectangle is the center, and the rest belongs to the nearest edge.
+    fn at(rect: Rect, point: Point) -> Option<Self> {
+        if !rect.contains_point(point) || rect.is_empty() {
+            return None;
+        }
+
+        let x = f32::from(point.x - rect.left) / f32::from(rect.width());
+        let y = f32::from(point.y - rect.top) / f32::from(rect.height());
+        if (0.25..0.75).contains(&x) && (0.25..0.75).contains(&y) {
+            return Some(Self::Center);
+        }
+
+        let edges = [
+            (x, Self::Left),
+            (1.0 - x, Self::Right),
+            (y, Self::Top),
+            (1.0 - y, Self::Bottom),
+        ];
+        edges
+            .into_iter()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, zone)| zone)
+    }
+
+    /// The part of `rect` that a panel dropped in the zone would take.
+    fn rect(self, rect: Rect) -> Rect {
+        let mid_x = rect.left + rect.width() / 2;
+        let mid_y = rect.top + rect.height() / 2;
+        match self {
+            Self::Center => rect,
+            Self::Left => Rect {
+                right: mid_x,
+                ..rect
+            },
+            Self::Right => Rect {
+                left: mid_x,
+                ..rect
+            },
+            Self::Top => Rect {
+                bottom: mid_y,
+                ..rect
+            },
+            Self::Bottom => Rect { top: mid_y, ..rect },
+        }
+    }
+}
+
+/// A panel's content, and the name that identifies it in layouts.
+struct DockPanel {
+    widget_state: WidgetState,
+    id: String,
+    content: Box<dyn Widget>,
+}
+
+impl Widget for DockPanel {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(self.content.as_ref());
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(self.content.as_mut());
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(self.content.as_mut());
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = context.layout(self.content.as_mut(), constraints);
+        context.position_widget(self.content.as_mut(), Offset::zero(), extent);
+        extent
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(self.content.as_ref());
+    }
+}
+
+/// A node in the tree of docked panels.
+enum DockNode {
+    Split(SplitPanel<DockNode>),
+    Tabs(TabbedPanel<DockPanel>),
+}
+
+impl DockNode {
+    fn tabs(panel: DockPanel) -> Self {
+        Self::Tabs(TabbedPanel::with_children(vec![panel]))
+    }
+
+    fn as_widget(&self) -> &dyn Widget {
+        match self {
+            Self::Split(split) => split,
+            Self::Tabs(tabs) => tabs,
+        }
+    }
+
+    fn as_widget_mut(&mut self) -> &mut dyn Widget {
+        match self {
+            Self::Split(split) => split,
+            Self::Tabs(tabs) => tabs,
+        }
+    }
+
+    /// The node at the end of `path`, which lists the index of the child to
+    /// descend into at each split.
+    fn descendant_mut(&mut self, path: &[usize]) -> &mut Self {
+        match path.split_first() {
+            None => self,
+            Some((index, rest)) => match self {
+                Self::Split(split) => split
+                    .child_mut(*index)
+                    .expect("dock paths should be valid")
+                    .descendant_mut(rest),
+                Self::Tabs(_) => panic!("dock paths should end at tabs"),
+            },
+        }
+    }
+
+    /// Finds the path to the tabbed panel at `point`, in absolute
+    /// coordinates, along with the panel.
+    fn tabs_at(&self, point: Point, path: &mut Vec<usize>) -> Option<&TabbedPanel<DockPanel>> {
+        if !self.as_widget().widget_state().rect().contains_point(point) {
+            return None;
+        }
+
+        match self {
+            Self::Tabs(tabs) => Some(tabs),
+            Self::Split(split) => (0..split.len()).find_map(|index| {
+                path.push(index);
+                let found = split.child(index)?.tabs_at(point, path);
+                if found.is_none() {
+                    path.pop();
+                }
+                found
+            }),
+        }
+    }
+
+    fn to_layout(&self) -> DockLayoutNode {
+        match self {
+            Self::Split(split) => DockLayoutNode::Split {
+                axis: split.axis(),
+                ratios: split.ratios().to_vec(),
+                children: (0..split.len())
+                    .filter_map(|index| split.child(index))
+                    .map(Self::to_layout)
+                    .collect(),
+            },
+            Self::Tabs(tabs) => DockLayoutNode::Tabs {
+                panels: (0..tabs.len())
+                    .filter_map(|index| tabs.tab(index))
+                    .map(|panel| panel.id.clone())
+                    .collect(),
+                active: tabs.active(),
+            },
+        }
+    }
+
+    fn from_layout(
+        layout: &DockLayoutNode,
+        panels: &mut HashMap<String, Box<dyn Widget>>,
+    ) -> Result<Self, DockError> {
+        match layout {
+            DockLayoutNode::Split {
+                axis,
+                ratios,
+                children,
+            } => {
+                let children = children
+                    .iter()
+                    .map(|child| Self::from_layout(child, panels))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Self::Split(
+                    SplitPanel::with_children(*axis, children).with_ratios(ratios),
+                ))
+            }
+            DockLayoutNode::Tabs {
+                panels: ids,
+                active,
+            } => {
+                let tabs = ids
+                    .iter()
+                    .map(|id| take_panel(panels, id))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut tabs = TabbedPanel::with_children(tabs);
+                tabs.set_active(*active);
+                Ok(Self::Tabs(tabs))
+            }
+        }
+    }
+}
+
+impl Widget for DockNode {
+    fn widget_state(&self) -> &WidgetState {
+        self.as_widget().widget_state()
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        self.as_widget_mut().widget_state_mut()
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        self.as_widget().for_each_child(f);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        self.as_widget_mut().for_each_child_mut(f);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        self.as_widget_mut().accept_update(context)
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        self.as_widget_mut().accept_layout(context, constraints)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        self.as_widget().accept_draw(canvas, extent);
+    }
+}
+
+fn take_panel(
+    panels: &mut HashMap<String, Box<dyn Widget>>,
+    id: &str,
+) -> Result<DockPanel, DockError> {
+    match panels.remove(id) {
+        Some(content) => Ok(DockPanel {
+            widget_state: WidgetState::default(),
+            id: id.to_string(),
+            content,
+        }),
+        None => Err(DockError::UnknownPanel(id.to_string())),
+    }
+}
+
+struct Floating {
+    panel: DockPanel,
+    /// The bounds of the panel, including its title bar, relative to the dock
+    /// area.
+    rect: Rect,
+}
+
+impl Floating {
+    fn title_bar(&self) -> Rect {
+        Rect {
+            bottom: self.rect.top + TITLE_BAR_HEIGHT,
+            ..self.rect
+        }
+    }
+}
+
+/// An area of panels that the user can rearrange by dragging them. See the
+/// [module documentation](self).
+#[must_use]
+pub struct DockArea {
+    widget_state: WidgetState,
+    root: Option<DockNode>,
+    /// The floating panels, from bottom to top.
+    floating: Vec<Floating>,
+    /// The tab that the left mouse button was pressed on, identified by the
+    /// path to its tabbed panel and its index, and where it was pressed.
+    pressed_tab: Option<(Vec<usize>, usize, Point)>,
+    /// The distance from the top-left corner of the topmost floating panel to
+    /// the cursor, while the panel is being dragged.
+    drag: Option<Offset>,
+    /// Where the dragged panel would be docked if it were dropped, and the
+    /// area to highlight, relative to the dock area.
+    target: Option<(Vec<usize>, DropZone, Rect)>,
+    on_change: Option<ChangeFn>,
+}
+
+impl DockArea {
+    /// Arranges `panels`, identified by their names, according to `layout`.
+    /// Panels that the layout doesn't mention float above the rest.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a panel's name is empty or contains whitespace or
+    /// parentheses, if two panels have the same name, or if the layout refers
+    /// to a panel that isn't given or refers to a panel more than once.
+    pub fn new(
+        layout: &DockLayout,
+        panels: Vec<(String, Box<dyn Widget>)>,
+    ) -> Result<Self, DockError> {
+        let mut order = Vec::with_capacity(panels.len());
+        let mut by_id = HashMap::with_capacity(panels.len());
+        for (id, content) in panels {
+            if id.is_empty() || id.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+                return Err(DockError::InvalidPanelId(id));
+            }
+            if by_id.insert(id.clone(), content).is_some() {
+                return Err(DockError::DuplicatePanel(id));
+            }
+            order.push(id);
+        }
+
+        // A panel that was given but is no longer available has already been
+        // placed, so the layout refers to it twice.
+        let duplicates = |error| match error {
+            DockError::UnknownPanel(id) if order.contains(&id) => DockError::DuplicatePanel(id),
+            error => error,
+        };
+
+        let root = match &layout.root {
+            Some(root) => Some(DockNode::from_layout(root, &mut by_id).map_err(duplicates)?),
+            None => None,
+        };
+
+        let mut floating = Vec::new();
+        for saved in &layout.floating {
+            floating.push(Floating {
+                panel: take_panel(&mut by_id, &saved.panel).map_err(duplicates)?,
+                rect: saved.rect,
+            });
+        }
+
+        // Panels that aren't in the layout are cascaded from the top-left.
+        for id in &order {
+            if let Ok(panel) = take_panel(&mut by_id, id) {
+                let step = Px(i16::try_from(floating.len() * 24).unwrap_or(0));
+                floating.push(Floating {
+                    panel,
+                    rect: Rect::new(Point::new(step, step), FLOATING_EXTENT),
+                });
+            }
+        }
+
+        Ok(Self {
+            widget_state: WidgetState::default(),
+            root,
+            floating,
+            pressed_tab: None,
+            drag: None,
+            target: None,
+            on_change: None,
+        })
+    }
+
+    /// Calls `callback` with the new layout whenever the user rearranges the
+    /// panels, so that it can be saved.
+    pub fn with_on_change(mut self, callback: impl FnMut(&DockLayout) + 'static) -> Self {
+        self.on_change = Some(Box::new(callback));
+        self
+    }
+
+    /// The current arrangement of the panels.
+    #[must_use]
+    pub fn layout(&self) -> DockLayout {
+        DockLayout {
+            root: self.root.as_ref().map(DockNode::to_layout),
+            floating: self
+                .floating
+                .iter()
+                .map(|floating| FloatingLayout {
+                    panel: floating.panel.id.clone(),
+                    rect: floating.rect,
+                })
+                .collect(),
+        }
+    }
+
+    fn changed(&mut self) {
+        if self.on_change.is_some() {
+            let layout = self.layout();
+            if let Some(on_change) = &mut self.on_change {
+                on_change(&layout);
+            }
+        }
+        self.widget_state.set_needs_layout();
+    }
+
+    /// Removes the tab at `index` from the tabbed panel at the end of `path`,
+    /// removing the tabbed panel if it was the last tab and collapsing splits
+    /// left with a single pane.
+    fn undock(&mut self, path: &[usize], index: usize) -> DockPanel {
+        let root = self.root.as_mut().expect("there should be docked panels");
+        let tabs = match root.descendant_mut(path) {
+            DockNode::Tabs(tabs) => tabs,
+            DockNode::Split(_) => panic!("dock paths should end at tabs"),
+        };
+
+        let panel = tabs.remove_tab(index);
+        if !tabs.is_empty() {
+            return panel;
+        }
+
+        match path.split_last() {
+            None => self.root = None,
+            Some((last, parent_path)) => {
+                let parent = root.descendant_mut(parent_path);
+                if let DockNode::Split(split) = parent {
+                    let _ = split.remove_child(*last);
+                    if split.len() == 1 {
+                        *parent = split.remove_child(0);
+                    }
+                }
+            }
+        }
+
+        panel
+    }
+
+    /// Docks `panel` into the tabbed panel at the end of `path`.
+    fn dock(&mut self, panel: DockPanel, path: &[usize], zone: DropZone) {
+        let root = match &mut self.root {
+            Some(root) => root,
+            None => {
+                self.root = Some(DockNode::tabs(panel));
+                return;
+            }
+        };
+
+        let node = root.descendant_mut(path);
+        match (zone, &mut *node) {
+            (DropZone::Center, DockNode::Tabs(tabs)) => {
+                tabs.insert_tab(tabs.len(), panel);
+                tabs.set_active(tabs.len() - 1);
+            }
+            _ => {
+                let axis = match zone {
+                    DropZone::Left | DropZone::Right | DropZone::Center => Axis::X,
+                    DropZone::Top | DropZone::Bottom => Axis::Y,
+                };
+                let existing =
+                    std::mem::replace(node, DockNode::Tabs(TabbedPanel::with_children(Vec::new())));
+                let children = match zone {
+                    DropZone::Left | DropZone::Top => vec![DockNode::tabs(panel), existing],
+                    _ => vec![existing, DockNode::tabs(panel)],
+                };
+                *node = DockNode::Split(SplitPanel::with_children(axis, children));
+            }
+        }
+    }
+
+    /// Finds where the dragged panel would be docked if dropped at `cursor`,
+    /// in absolute coordinates.
+    fn find_target(&self, cursor: Point) -> Option<(Vec<usize>, DropZone, Rect)> {
+        let origin = self.widget_state.origin();
+        let mut path = Vec::new();
+        match &self.root {
+            Some(root) => {
+                let tabs = root.tabs_at(cursor, &mut path)?;
+                let rect = tabs.widget_state().rect();
+                let zone = DropZone::at(rect, cursor)?;
+                Some((path, zone, zone.rect(rect) - (origin - Point::zero())))
+            }
+            // With nothing docked, the whole area is a target.
+            None => {
+                let rect = self.widget_state.rect();
+                rect.contains_point(cursor).then(|| {
+                    let local = rect - (origin - Point::zero());
+                    (path, DropZone::Center, local)
+                })
+            }
+        }
+    }
+
+    /// Finds the topmost floating panel at `point`, relative to the area.
+    fn floating_at(&self, point: Point) -> Option<usize> {
+        self.floating
+            .iter()
+            .rposition(|floating| floating.rect.contains_point(point))
+    }
+}
+
+impl Widget for DockArea {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        if let Some(root) = &self.root {
+            f(root);
+        }
+        for floating in &self.floating {
+            f(&floating.panel);
+        }
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        if let Some(root) = &mut self.root {
+            f(root);
+        }
+        for floating in &mut self.floating {
+            f(&mut floating.panel);
+        }
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        let cursor = context.cursor_position();
+        let local = Point::zero() + (cursor - self.widget_state.origin());
+
+        match context.event() {
+            Event::CursorMove { .. } => {
+                if let Some(grab) = self.drag {
+                    if let Some(floating) = self.floating.last_mut() {
+                        floating.rect = Rect::new(local - grab, floating.rect.extent());
+                    }
+                    self.target = self.find_target(cursor);
+                    return PostUpdate::NeedsLayout;
+                }
+
+                if let Some((path, index, start)) = self.pressed_tab.take() {
+                    let moved = cursor - start;
+                    if moved.x.0.abs() < DETACH_THRESHOLD.0 && moved.y.0.abs() < DETACH_THRESHOLD.0
+                    {
+                        self.pressed_tab = Some((path, index, start));
+                    } else {
+                        let panel = self.undock(&path, index);
+                        self.floating.push(Floating {
+                            panel,
+                            rect: Rect::new(local - FLOATING_GRAB, FLOATING_EXTENT),
+                        });
+                        self.drag = Some(FLOATING_GRAB);
+                        self.target = self.find_target(cursor);
+                        context.redraw_window();
+                        return PostUpdate::NeedsLayout;
+                    }
+                }
+            }
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Pressed,
+            } => {
+                if let Some(index) = self.floating_at(local) {
+                    // Clicking a floating panel brings it to the top.
+                    let floating = self.floating.remove(index);
+                    let on_title_bar = floating.title_bar().contains_point(local);
+                    let grab = local - floating.rect.top_left();
+                    self.floating.push(floating);
+
+                    if on_title_bar {
+                        self.drag = Some(grab);
+                        self.target = self.find_target(cursor);
+                        return PostUpdate::NeedsLayout;
+                    }
+
+                    if index + 1 != self.floating.len() {
+                        context.redraw(self.widget_state.rect());
+                    }
+                } else if let Some(root) = &self.root {
+                    let mut path = Vec::new();
+                    if let Some(tab) = root
+                        .tabs_at(cursor, &mut path)
+                        .and_then(|tabs| tabs.tab_at(cursor))
+                    {
+                        self.pressed_tab = Some((path, tab, cursor));
+                    }
+                }
+            }
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Released,
+            } => {
+                self.pressed_tab = None;
+                if self.drag.take().is_some() {
+                    if let Some((path, zone, _)) = self.target.take() {
+                        if let Some(floating) = self.floating.pop() {
+                            self.dock(floating.panel, &path, zone);
+                        }
+                    }
+                    self.changed();
+                    context.redraw_window();
+                    return PostUpdate::NeedsLayout;
+                }
+            }
+            _ => {}
+        }
+
+        if self.drag.is_some() {
+            return PostUpdate::NoChange;
+        }
+
+        // Mouse events go to the topmost panel under the cursor, and keyboard
+        // events go to every panel since any of them may have the focus.
+        match context.event() {
+            Event::KeyPress { .. }
+            | Event::Text { .. }
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => {
+                if let Some(root) = &mut self.root {
+                    context.update(root);
+                }
+                for floating in &mut self.floating {
+                    context.update(&mut floating.panel);
+                }
+            }
+            _ => match self.floating_at(local) {
+                Some(index) => context.update(&mut self.floating[index].panel),
+                None => {
+                    if let Some(root) = &mut self.root {
+                        context.update(root);
+                    }
+                }
+            },
+        }
+
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = constraints.max;
+
+        if let Some(root) = &mut self.root {
+            let root_extent = context.layout(root, BoxConstraint::exact(extent));
+            context.position_widget(root, Offset::zero(), root_extent);
+        }
+
+        for floating in &mut self.floating {
+            let content = Extent {
+                width: floating.rect.width(),
+                height: floating.rect.height().saturating_sub(TITLE_BAR_HEIGHT),
+            };
+            let content = context.layout(&mut floating.panel, BoxConstraint::exact(content));
+            let offset = floating.rect.top_left() - Point::zero()
+                + Offset {
+                    x: Px(0),
+                    y: TITLE_BAR_HEIGHT,
+                };
+            context.position_widget(&mut floating.panel, offset, content);
+        }
+
+        extent
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        if let Some(root) = &self.root {
+            canvas.draw(root);
+        }
+
+        let highlight = |canvas: &mut DrawContext,
+                         target: &Option<(Vec<usize>, DropZone, Rect)>| {
+            if let Some((_, _, rect)) = target {
+                canvas.draw_rect(*rect, &Paint::Fill { color: DROP_ZONE });
+                canvas.stroke_rect(*rect, &Stroke::solid(DROP_ZONE_BORDER, 2.0));
+            }
+        };
+
+        // The panel being dragged is drawn above the highlighted drop zone.
+        let dragged = usize::from(self.drag.is_some());
+        let (below, above) = self
+            .floating
+            .split_at(self.floating.len().saturating_sub(dragged));
+        for floating in below {
+            draw_floating(canvas, floating);
+        }
+        highlight(canvas, &self.target);
+        for floating in above {
+            draw_floating(canvas, floating);
+        }
+    }
+}
+
+fn draw_floating(canvas: &mut DrawContext, floating: &Floating) {
+    canvas.draw_rect(floating.title_bar(), &Paint::Fill { color: TITLE_BAR });
+    canvas.draw(&floating.panel);
+    canvas.stroke_rect(floating.rect, &Stroke::solid(BORDER, 1.0));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gui::widgets::debug::Fill;
+
+    use super::*;
+
+    fn panels(ids: &[&str]) -> Vec<(String, Box<dyn Widget>)> {
+        ids.iter()
+            .map(|id| {
+                let content: Box<dyn Widget> = Box::new(Fill::new(Color::WHITE));
+                (id.to_string(), content)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn layout_text_round_trip() {
+        let text = "(dock (split x 0.25 0.75 (tabs 0 files) (tabs 1 editor preview)) \
+                    (float log 40 40 240 180))";
+        let layout: DockLayout = text.parse().unwrap();
+        assert_eq!(
+            layout.to_string(),
+            text.split_whitespace().collect::<Vec<_>>().join(" ")
+        );
+        assert_eq!(layout.to_string().parse::<DockLayout>().unwrap(), layout);
+
+        assert!("(dock (tabs 0 a)".parse::<DockLayout>().is_err());
+        assert!("(dock (grid 0 a))".parse::<DockLayout>().is_err());
+    }
+
+    #[test]
+    fn rearrange() {
+        let layout: DockLayout = "(dock (split x 0.5 0.5 (tabs 0 a b) (tabs 0 c)))"
+            .parse()
+            .unwrap();
+        let mut area = DockArea::new(&layout, panels(&["a", "b", "c", "d"])).unwrap();
+
+        // Panels missing from the layout float.
+        assert_eq!(area.layout().floating.len(), 1);
+
+        // Removing the last tab of a pane collapses the split.
+        let c = area.undock(&[1], 0);
+        assert_eq!(area.layout().root.unwrap().to_string(), "(tabs 0 a b)");
+
+        area.dock(c, &[], DropZone::Bottom);
+        let b = area.undock(&[0], 1);
+        area.dock(b, &[1], DropZone::Center);
+        assert_eq!(
+            area.layout().root.unwrap().to_string(),
+            "(split y 0.5 0.5 (tabs 0 a) (tabs 1 c b))"
+        );
+
+        assert!(matches!(
+            DockArea::new(&layout, panels(&["a", "b"])),
+            Err(DockError::UnknownPanel(_))
+        ));
+        assert!(matches!(
+            DockArea::new(&layout, panels(&["a", "a"])),
+            Err(DockError::DuplicatePanel(_))
+        ));
+    }
+
+    #[test]
+    fn drop_zones() {
+        let rect = Rect::new(
+            Point::zero(),
+            Extent {
+                width: Px(100),
+                height: Px(100),
+            },
+        );
+        assert_eq!(
+            DropZone::at(rect, Point::new(50, 50)),
+            Some(DropZone::Center)
+        );
+        assert_eq!(DropZone::at(rect, Point::new(5, 40)), Some(DropZone::Left));
+        assert_eq!(
+            DropZone::at(rect, Point::new(60, 95)),
+            Some(DropZone::Bottom)
+        );
+        assert_eq!(DropZone::at(rect, Point::new(200, 50)), None);
+        assert_eq!(DropZone::Right.rect(rect).left, Px(50));
+    }
+}
@@ -0,0 +1,591 @@
+//! Dragging values between widgets, including widgets in other windows.
+//!
+//! A drag starts from a [`DragSource`] once the user presses the left mouse
+//! button on it and moves the cursor a few pixels. The source produces a
+//! payload, which can be any value, and optionally a preview widget that
+//! follows the cursor. While the drag lasts, every [`DropTarget`] under the
+//! cursor that accepts the payload is told when the cursor enters it, moves
+//! over it, and leaves it. Releasing the button over such a target drops the
+//! payload on it, and releasing it anywhere else cancels the drag.
+//!
+//! The drag is tracked by a [`DragDrop`] that the application shares with
+//! every window, so a drag that starts in one window can end in another.
+//!
+//! Files dragged from other programs are dropped on a [`FileDropTarget`]
+//! instead, since they don't pass over the widgets before they are dropped.
+
+use std::{any::Any, cell::RefCell, path::PathBuf, rc::Rc};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Offset, Point, Px, Rect},
+        Stroke,
+    },
+    gui::input::{ButtonState, Event, MouseButton},
+    shell::WindowId,
+};
+
+use super::{
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// How far the cursor has to move with the button held before a drag starts,
+/// so that clicks that move slightly aren't taken as drags.
+const DRAG_THRESHOLD: Px = Px(4);
+
+/// The outline drawn around a drop target while a payload it accepts is over
+/// it.
+const HIGHLIGHT: Color = Color {
+    r: 0.0,
+    g: 0.47,
+    b: 0.84,
+    a: 1.0,
+};
+
+type PayloadFn = Box<dyn FnMut() -> Option<Box<dyn Any>>>;
+type PreviewFn = Box<dyn FnMut() -> Box<dyn Widget>>;
+type AcceptsFn = Box<dyn FnMut(&dyn Any) -> bool>;
+type EnterFn = Box<dyn FnMut(&dyn Any)>;
+type OverFn = Box<dyn FnMut(&dyn Any, Point)>;
+type DropFn = Box<dyn FnMut(Box<dyn Any>, Point)>;
+type FileDropFn = Box<dyn FnMut(&[PathBuf], Point)>;
+
+struct Drag {
+    payload: Box<dyn Any>,
+    /// The widget drawn at the cursor, and its size.
+    preview: Option<(Box<dyn Widget>, Extent)>,
+    /// The window that the cursor was last seen in.
+    window: Option<WindowId>,
+}
+
+/// The drag in progress, if any. Clones refer to the same drag.
+#[derive(Clone, Default)]
+pub struct DragDrop {
+    drag: Rc<RefCell<Option<Drag>>>,
+}
+
+impl DragDrop {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts dragging `payload`, replacing any drag in progress. If given,
+    /// `preview` is drawn at the cursor with the given size until the drag
+    /// ends.
+    pub fn start(&self, payload: Box<dyn Any>, preview: Option<(Box<dyn Widget>, Extent)>) {
+        *self.drag.borrow_mut() = Some(Drag {
+            payload,
+            preview,
+            window: None,
+        });
+    }
+
+    #[must_use]
+    pub fn is_dragging(&self) -> bool {
+        self.drag.borrow().is_some()
+    }
+
+    /// Calls `f` with the payload being dragged. Returns `None` if there is no
+    /// drag in progress.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `f` starts, ends, or inspects the drag.
+    pub fn with_payload<R>(&self, f: impl FnOnce(&dyn Any) -> R) -> Option<R> {
+        self.drag
+            .borrow()
+            .as_ref()
+            .map(|drag| f(drag.payload.as_ref()))
+    }
+
+    /// Ends the drag, returning its payload.
+    pub fn take(&self) -> Option<Box<dyn Any>> {
+        self.drag.borrow_mut().take().map(|drag| drag.payload)
+    }
+
+    /// Ends the drag without dropping its payload. Returns `true` if there was
+    /// a drag in progress.
+    pub fn cancel(&self) -> bool {
+        self.take().is_some()
+    }
+
+    /// Records that the cursor is in `window`, so that the preview is drawn
+    /// there.
+    pub fn set_window(&self, window: WindowId) {
+        if let Some(drag) = self.drag.borrow_mut().as_mut() {
+            drag.window = Some(window);
+        }
+    }
+
+    /// Checks if the preview should be drawn in `window`.
+    #[must_use]
+    pub fn is_over_window(&self, window: WindowId) -> bool {
+        self.drag
+            .borrow()
+            .as_ref()
+            .is_some_and(|drag| drag.window == Some(window))
+    }
+
+    /// Lays out the preview so that its top-left corner is at `cursor`, in
+    /// absolute coordinates. Returns the area that it covers, which is empty
+    /// if there's no preview.
+    pub fn layout_preview(&self, context: &mut LayoutContext, cursor: Point) -> Rect {
+        let mut drag = self.drag.borrow_mut();
+        let (preview, extent) = match drag.as_mut().and_then(|drag| drag.preview.as_mut()) {
+            Some(preview) => preview,
+            None => return Rect::zero(),
+        };
+
+        let extent = context.layout(preview.as_mut(), BoxConstraint::exact(*extent));
+        let state = preview.widget_state_mut();
+        state.set_layout(cursor - Point::zero(), extent);
+        state.set_origin(cursor);
+        LayoutContext::update_origins(preview.as_mut());
+        preview.widget_state().rect()
+    }
+
+    /// Draws the preview where it was last laid out. Call this after drawing
+    /// everything else in the window so that it's on top.
+    pub fn draw_preview(&self, canvas: &mut DrawContext) {
+        if let Some((preview, _)) = self.drag.borrow().as_ref().and_then(|d| d.preview.as_ref()) {
+            canvas.draw(preview.as_ref());
+        }
+    }
+}
+
+/// Lets the user drag a value out of its child.
+///
+/// The child is laid out and drawn as if it weren't wrapped.
+#[must_use]
+pub struct DragSource<W: Widget> {
+    widget_state: WidgetState,
+    pub child: W,
+    /// Where the left mouse button was pressed on the widget, until the drag
+    /// starts or the button is released.
+    pressed_at: Option<Point>,
+    payload: PayloadFn,
+    preview: Option<(Extent, PreviewFn)>,
+}
+
+impl<W: Widget> DragSource<W> {
+    /// Calls `payload` to get the value to drag when the user starts dragging
+    /// the widget. If it returns `None`, nothing is dragged.
+    pub fn new(child: W, payload: impl FnMut() -> Option<Box<dyn Any>> + 'static) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            child,
+            pressed_at: None,
+            payload: Box::new(payload),
+            preview: None,
+        }
+    }
+
+    /// Calls `preview` when a drag starts to create a widget of size `extent`
+    /// that follows the cursor until the drag ends.
+    pub fn with_preview(
+        mut self,
+        extent: Extent,
+        preview: impl FnMut() -> Box<dyn Widget> + 'static,
+    ) -> Self {
+        self.preview = Some((extent, Box::new(preview)));
+        self
+    }
+}
+
+impl<W: Widget> Widget for DragSource<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(&mut self.child);
+
+        let cursor = context.cursor_position();
+        match context.event() {
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Pressed,
+            } if self.widget_state.rect().contains_point(cursor) => {
+                self.pressed_at = Some(cursor);
+            }
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Released,
+            } => {
+                self.pressed_at = None;
+            }
+            Event::CursorMove { .. } => {
+                if let Some(start) = self.pressed_at {
+                    let moved = cursor - start;
+                    if moved.x.0.abs() >= DRAG_THRESHOLD.0 || moved.y.0.abs() >= DRAG_THRESHOLD.0 {
+                        self.pressed_at = None;
+                        if let Some(payload) = (self.payload)() {
+                            let preview = self
+                                .preview
+                                .as_mut()
+                                .map(|(extent, preview)| (preview(), *extent));
+                            context.drag_drop().start(payload, preview);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = context.layout(&mut self.child, constraints);
+        context.position_widget(&mut self.child, Offset::zero(), extent);
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+}
+
+/// Lets the user drop dragged values onto its child. The child is outlined
+/// while a value that the target accepts is dragged over it.
+///
+/// The child is laid out and drawn as if it weren't wrapped.
+#[must_use]
+pub struct DropTarget<W: Widget> {
+    widget_state: WidgetState,
+    pub child: W,
+    /// Whether a payload that the target accepts is over it.
+    is_over: bool,
+    accepts: AcceptsFn,
+    on_enter: Option<EnterFn>,
+    on_over: Option<OverFn>,
+    on_leave: Option<Box<dyn FnMut()>>,
+    on_drop: DropFn,
+}
+
+impl<W: Widget> DropTarget<W> {
+    /// Calls `on_drop` with the payload and the cursor position, in absolute
+    /// coordinates, when a payload is dropped on the widget.
+    pub fn new(child: W, on_drop: impl FnMut(Box<dyn Any>, Point) + 'static) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            child,
+            is_over: false,
+            accepts: Box::new(|_| true),
+            on_enter: None,
+            on_over: None,
+            on_leave: None,
+            on_drop: Box::new(on_drop),
+        }
+    }
+
+    /// Only accepts payloads for which `accepts` returns `true`. Payloads that
+    /// aren't accepted are ignored. Every payload is accepted by default.
+    pub fn with_accepts(mut self, accepts: impl FnMut(&dyn Any) -> bool + 'static) -> Self {
+        self.accepts = Box::new(accepts);
+        self
+    }
+
+    /// Calls `callback` when an accepted payload is dragged onto the widget.
+    pub fn with_on_enter(mut self, callback: impl FnMut(&dyn Any) + 'static) -> Self {
+        self.on_enter = Some(Box::new(callback));
+        self
+    }
+
+    /// Calls `callback` with the payload and the cursor position, in absolute
+    /// coordinates, whenever an accepted payload moves over the widget.
+    pub fn with_on_over(mut self, callback: impl FnMut(&dyn Any, Point) + 'static) -> Self {
+        self.on_over = Some(Box::new(callback));
+        self
+    }
+
+    /// Calls `callback` when an accepted payload is dragged off of the widget,
+    /// or the drag is cancelled while over it.
+    pub fn with_on_leave(mut self, callback: impl FnMut() + 'static) -> Self {
+        self.on_leave = Some(Box::new(callback));
+        self
+    }
+
+    /// Checks if a payload that the widget accepts is being dragged over it.
+    #[must_use]
+    pub fn is_over(&self) -> bool {
+        self.is_over
+    }
+}
+
+impl<W: Widget> Widget for DropTarget<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(&mut self.child);
+
+        let drag_drop = context.drag_drop().clone();
+        let cursor = context.cursor_position();
+        let is_over = self.widget_state.rect().contains_point(cursor)
+            && drag_drop
+                .with_payload(|payload| (self.accepts)(payload))
+                .unwrap_or(false);
+
+        let was_over = self.is_over;
+        self.is_over = is_over;
+
+        if is_over && !was_over {
+            if let Some(on_enter) = &mut self.on_enter {
+                drag_drop.with_payload(|payload| on_enter(payload));
+            }
+        } else if was_over && !is_over {
+            if let Some(on_leave) = &mut self.on_leave {
+                on_leave();
+            }
+        }
+
+        match context.event() {
+            Event::CursorMove { .. } if is_over => {
+                if let Some(on_over) = &mut self.on_over {
+                    drag_drop.with_payload(|payload| on_over(payload, cursor));
+                }
+            }
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Released,
+            } if is_over => {
+                self.is_over = false;
+                if let Some(payload) = drag_drop.take() {
+                    (self.on_drop)(payload, cursor);
+                }
+            }
+            _ => {}
+        }
+
+        if self.is_over == was_over {
+            PostUpdate::NoChange
+        } else {
+            PostUpdate::NeedsRedraw
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = context.layout(&mut self.child, constraints);
+        context.position_widget(&mut self.child, Offset::zero(), extent);
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        canvas.draw(&self.child);
+
+        if self.is_over {
+            canvas.stroke_rect(
+                Rect::new(Point::zero(), extent).deflate(Px(1), Px(1)),
+                &Stroke::solid(HIGHLIGHT, 2.0),
+            );
+        }
+    }
+}
+
+/// Lets the user drop files from other programs onto its child. If several
+/// targets are under the files, the topmost one gets them.
+///
+/// The child is laid out and drawn as if it weren't wrapped.
+#[must_use]
+pub struct FileDropTarget<W: Widget> {
+    widget_state: WidgetState,
+    pub child: W,
+    on_drop: FileDropFn,
+}
+
+impl<W: Widget> FileDropTarget<W> {
+    /// Calls `on_drop` with the paths of the files and the position they were
+    /// dropped at, in absolute coordinates, when files are dropped on the
+    /// widget.
+    pub fn new(child: W, on_drop: impl FnMut(&[PathBuf], Point) + 'static) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            child,
+            on_drop: Box::new(on_drop),
+        }
+    }
+}
+
+impl<W: Widget> Widget for FileDropTarget<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(&mut self.child);
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = context.layout(&mut self.child, constraints);
+        context.position_widget(&mut self.child, Offset::zero(), extent);
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+
+    fn accept_file_drop(&mut self, paths: &[PathBuf], position: Point) -> Option<PostUpdate> {
+        (self.on_drop)(paths, position);
+        Some(PostUpdate::NoChange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gui::{
+        input::Input,
+        widgets::{debug::Fill, drop_files},
+    };
+
+    use super::*;
+
+    #[test]
+    fn drag_onto_target() {
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let log = dropped.clone();
+
+        let extent = Extent {
+            width: Px(10),
+            height: Px(10),
+        };
+        let mut source = DragSource::new(Fill::new(Color::RED), || Some(Box::new(7_u32)));
+        source.widget_state.set_layout(Offset::zero(), extent);
+        let mut target = DropTarget::new(Fill::new(Color::BLUE), move |payload, _| {
+            log.borrow_mut()
+                .extend(payload.downcast::<u32>().ok().map(|n| *n));
+        })
+        .with_accepts(|payload| payload.is::<u32>());
+        target.widget_state.set_layout(Offset::zero(), extent);
+        target.widget_state.set_origin(Point::new(20, 0));
+
+        let drag_drop = DragDrop::new();
+        let mut input = Input::default();
+        let mut send = |input: &mut Input| {
+            let mut context = UpdateContext::new(input).with_drag_drop(&drag_drop);
+            context.update(&mut source);
+            context.update(&mut target);
+        };
+
+        input.update_cursor_position(Point::new(5, 5));
+        send(&mut input);
+        input.update_mouse_button(MouseButton::Left, ButtonState::Pressed);
+        send(&mut input);
+
+        // Small movements don't start a drag.
+        input.update_cursor_position(Point::new(6, 5));
+        send(&mut input);
+        assert!(!drag_drop.is_dragging());
+
+        input.update_cursor_position(Point::new(25, 5));
+        send(&mut input);
+        assert!(drag_drop.is_dragging());
+
+        input.update_cursor_position(Point::new(26, 5));
+        send(&mut input);
+        input.update_mouse_button(MouseButton::Left, ButtonState::Released);
+        send(&mut input);
+
+        assert!(!drag_drop.is_dragging());
+        assert_eq!(*dropped.borrow(), [7]);
+    }
+
+    #[test]
+    fn drop_files_on_topmost_target() {
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let (outer_log, inner_log) = (dropped.clone(), dropped.clone());
+
+        let inner = FileDropTarget::new(Fill::new(Color::RED), move |paths, _| {
+            inner_log.borrow_mut().push(("inner", paths.len()));
+        });
+        let mut outer = FileDropTarget::new(inner, move |paths, _| {
+            outer_log.borrow_mut().push(("outer", paths.len()));
+        });
+        outer.widget_state.set_layout(
+            Offset::zero(),
+            Extent {
+                width: Px(20),
+                height: Px(20),
+            },
+        );
+        outer.child.widget_state.set_layout(
+            Offset::zero(),
+            Extent {
+                width: Px(10),
+                height: Px(10),
+            },
+        );
+
+        let paths = [PathBuf::from("a.txt"), PathBuf::from("b.txt")];
+        assert!(drop_files(&mut outer, &paths, Point::new(5, 5)).is_some());
+        assert!(drop_files(&mut outer, &paths, Point::new(15, 15)).is_some());
+        assert!(drop_files(&mut outer, &paths, Point::new(50, 50)).is_none());
+        assert_eq!(*dropped.borrow(), [("inner", 2), ("outer", 2)]);
+    }
+}
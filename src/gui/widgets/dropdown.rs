@@ -0,0 +1,661 @@
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::{
+        accessibility::{AccessNode, Action, ActionKind, Role},
+        input::{ButtonState, Event, FocusId, MouseButton},
+    },
+    shell::event::Key,
+    text::{ShapedLine, TextStyle},
+};
+
+use super::{
+    virtual_list::{self, ScrollPosition},
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// The space between the edge of the dropdown or an option and its text.
+const PADDING: Px = Px(4);
+
+/// The most options shown in the popup at once. The rest are reached by
+/// scrolling.
+const MAX_VISIBLE_OPTIONS: usize = 8;
+
+const BACKGROUND: Color = Color::WHITE;
+
+const BORDER: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+const FOCUSED_BORDER: Color = Color {
+    r: 0.0,
+    g: 0.4,
+    b: 0.9,
+    a: 1.0,
+};
+
+const HIGHLIGHT: Color = Color {
+    r: 0.6,
+    g: 0.8,
+    b: 1.0,
+    a: 1.0,
+};
+
+const FILTER: Color = Color {
+    r: 0.0,
+    g: 0.4,
+    b: 0.9,
+    a: 1.0,
+};
+
+/// The list of options shown while the dropdown is open.
+struct Popup {
+    /// The text typed since the popup opened. Only options that contain it
+    /// are shown, ignoring case.
+    filter: String,
+    /// The indices of the options that match the filter.
+    matches: Vec<usize>,
+    /// The position in `matches` of the option that Enter would choose.
+    highlighted: Option<usize>,
+    /// How far the options are scrolled up.
+    scroll: ScrollPosition,
+}
+
+/// A button showing the chosen option that opens a list of the other options
+/// when clicked.
+///
+/// While the dropdown has the keyboard focus, Up and Down change the chosen
+/// option and Space, Enter, or Alt+Down open the list. While the list is open,
+/// typing filters it to the options containing the typed text, Up and Down
+/// move the highlight, and Enter chooses the highlighted option. Escape or a
+/// click anywhere outside of the list closes it.
+#[must_use]
+pub struct Dropdown {
+    widget_state: WidgetState,
+    focus: FocusId,
+    style: TextStyle,
+    pub color: Color,
+    options: Vec<String>,
+    selected: Option<usize>,
+    popup: Option<Popup>,
+    on_change: Option<Box<dyn FnMut(usize)>>,
+    // Computed during layout.
+    shaped: Vec<ShapedLine>,
+    filter_shaped: Option<ShapedLine>,
+    ascent: Px,
+    row_height: Px,
+    popup_width: Px,
+}
+
+impl Dropdown {
+    pub fn new(style: TextStyle, options: Vec<String>) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            focus: FocusId::new(),
+            style,
+            color: Color::BLACK,
+            options,
+            selected: None,
+            popup: None,
+            on_change: None,
+            shaped: Vec::new(),
+            filter_shaped: None,
+            ascent: Px(0),
+            row_height: Px(0),
+            popup_width: Px(0),
+        }
+    }
+
+    pub fn with_selected(mut self, selected: Option<usize>) -> Self {
+        self.set_selected(selected);
+        self
+    }
+
+    /// Calls `callback` with the index of the option whenever the user chooses
+    /// one.
+    pub fn with_on_change(mut self, callback: impl FnMut(usize) + 'static) -> Self {
+        self.on_change = Some(Box::new(callback));
+        self
+    }
+
+    #[must_use]
+    pub fn options(&self) -> &[String] {
+        &self.options
+    }
+
+    /// Replaces the options, clearing the choice and closing the list.
+    pub fn set_options(&mut self, options: Vec<String>) {
+        self.options = options;
+        self.selected = None;
+        self.popup = None;
+        self.widget_state.set_needs_layout();
+    }
+
+    /// The index of the chosen option, if any.
+    #[must_use]
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Chooses the option at `selected`, without calling the change callback.
+    pub fn set_selected(&mut self, selected: Option<usize>) {
+        self.selected = selected.filter(|&i| i < self.options.len());
+        self.widget_state.set_needs_layout();
+    }
+
+    /// The identifier used to give the dropdown the keyboard focus.
+    #[must_use]
+    pub fn focus_id(&self) -> FocusId {
+        self.focus
+    }
+
+    fn choose(&mut self, option: usize) {
+        if self.selected != Some(option) {
+            self.selected = Some(option);
+            if let Some(on_change) = &mut self.on_change {
+                on_change(option);
+            }
+        }
+    }
+
+    fn open(&mut self) {
+        let matches = filter_options(&self.options, "");
+        let highlighted = self
+            .selected
+            .and_then(|selected| matches.iter().position(|&i| i == selected))
+            .or_else(|| (!matches.is_empty()).then_some(0));
+
+        let mut popup = Popup {
+            filter: String::new(),
+            matches,
+            highlighted,
+            scroll: ScrollPosition::TOP,
+        };
+        if let Some(highlighted) = highlighted {
+            popup.scroll = virtual_list::scroll_to_row(
+                ScrollPosition::TOP,
+                self.popup_height(popup.matches.len()),
+                self.row_height,
+                highlighted,
+            );
+        }
+        self.popup = Some(popup);
+    }
+
+    /// The height of a popup showing `count` options.
+    fn popup_height(&self, count: usize) -> Px {
+        let rows = count.clamp(1, MAX_VISIBLE_OPTIONS);
+        self.row_height * rows as f32
+    }
+
+    /// The bounds of the popup, relative to the dropdown.
+    fn popup_rect(&self) -> Rect {
+        let extent = self.widget_state.extent();
+        let count = self.popup.as_ref().map_or(0, |popup| popup.matches.len());
+        Rect {
+            left: Px(0),
+            top: extent.height,
+            right: self.popup_width.max(extent.width),
+            bottom: extent.height + self.popup_height(count),
+        }
+    }
+
+    /// The bounds of the popup in absolute coordinates.
+    fn absolute_popup_rect(&self) -> Rect {
+        self.popup_rect() + (self.widget_state.origin() - Point::zero())
+    }
+
+    /// Finds the position in the list of matches of the option at `point`, in
+    /// absolute coordinates.
+    fn option_at(&self, point: Point) -> Option<usize> {
+        let popup = self.popup.as_ref()?;
+        let rect = self.absolute_popup_rect();
+        if !rect.contains_point(point) {
+            return None;
+        }
+
+        virtual_list::row_at(
+            popup.scroll,
+            point.y - rect.top,
+            self.row_height,
+            popup.matches.len(),
+        )
+    }
+
+    /// Handles the events received while the popup is open. The dropdown
+    /// receives every event while it has an overlay.
+    fn update_popup(&mut self, context: &mut UpdateContext) {
+        let popup_rect = self.absolute_popup_rect();
+        let viewport = popup_rect.height();
+        let row_height = self.row_height;
+
+        match context.event() {
+            Event::None
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => {}
+            Event::CursorMove { position } => {
+                let option = self.option_at(position);
+                if let Some(popup) = &mut self.popup {
+                    if option.is_some() && option != popup.highlighted {
+                        popup.highlighted = option;
+                        context.redraw(popup_rect);
+                    }
+                }
+            }
+            Event::MouseButton { button, state } => {
+                if state == ButtonState::Pressed {
+                    let option = self.option_at(context.cursor_position());
+                    if let (Some(option), MouseButton::Left) = (option, button) {
+                        let popup = self.popup.as_ref().unwrap();
+                        let option = popup.matches[option];
+                        self.choose(option);
+                    }
+
+                    // Any click closes the popup, including on the dropdown
+                    // itself. The dropdown keeps the focus unless the click
+                    // was outside of it.
+                    let on_dropdown = self
+                        .widget_state
+                        .rect()
+                        .contains_point(context.cursor_position());
+                    if option.is_some() || on_dropdown {
+                        context.set_focus(Some(self.focus));
+                    }
+                    self.close(context);
+                }
+            }
+            Event::Scroll { delta, .. } => {
+                if popup_rect.contains_point(context.cursor_position()) {
+                    if let Some(popup) = &mut self.popup {
+                        let max =
+                            virtual_list::max_scroll(viewport, row_height, popup.matches.len());
+                        popup.scroll =
+                            virtual_list::scroll_by(popup.scroll, delta.y, row_height, max);
+                        context.redraw(popup_rect);
+                    }
+                }
+            }
+            Event::KeyPress { key, modifiers } => {
+                let popup = self.popup.as_mut().unwrap();
+                let count = popup.matches.len();
+                let page = MAX_VISIBLE_OPTIONS;
+                let last = count.saturating_sub(1);
+
+                let highlighted = match (key, popup.highlighted) {
+                    _ if count == 0 => None,
+                    (Key::Up, Some(i)) => Some(i.saturating_sub(1)),
+                    (Key::Down, Some(i)) => Some((i + 1).min(last)),
+                    (Key::PageUp, Some(i)) => Some(i.saturating_sub(page)),
+                    (Key::PageDown, Some(i)) => Some((i + page).min(last)),
+                    (Key::Home, _) | (Key::Up | Key::Down | Key::PageUp | Key::PageDown, None) => {
+                        Some(0)
+                    }
+                    (Key::End, _) => Some(last),
+                    _ => popup.highlighted,
+                };
+
+                match key {
+                    Key::Enter | Key::Space if popup.filter.is_empty() || key == Key::Enter => {
+                        if let Some(option) = popup.highlighted.map(|i| popup.matches[i]) {
+                            self.choose(option);
+                        }
+                        self.close(context);
+                    }
+                    Key::Escape | Key::Tab => self.close(context),
+                    Key::Up if modifiers.alt => self.close(context),
+                    Key::Backspace => {
+                        popup.filter.pop();
+                        self.refilter();
+                        context.redraw_window();
+                    }
+                    _ => {
+                        if highlighted != popup.highlighted {
+                            popup.highlighted = highlighted;
+                            if let Some(i) = highlighted {
+                                popup.scroll = virtual_list::scroll_to_row(
+                                    popup.scroll,
+                                    viewport,
+                                    row_height,
+                                    i,
+                                );
+                            }
+                            context.redraw(popup_rect);
+                        }
+                    }
+                }
+            }
+            Event::Text { character } => {
+                if let Some(popup) = &mut self.popup {
+                    popup.filter.push(character);
+                }
+                self.refilter();
+
+                // The popup may have shrunk, so the area it covered before
+                // needs to be redrawn along with the new one.
+                context.redraw(popup_rect);
+                context.redraw(self.absolute_popup_rect());
+            }
+        }
+    }
+
+    /// Updates the options shown in the popup after its filter changes.
+    fn refilter(&mut self) {
+        if let Some(popup) = &mut self.popup {
+            popup.matches = filter_options(&self.options, &popup.filter);
+            popup.highlighted = (!popup.matches.is_empty()).then_some(0);
+            popup.scroll = ScrollPosition::TOP;
+        }
+        // The filter is shown in place of the chosen option.
+        self.widget_state.set_needs_layout();
+    }
+
+    fn close(&mut self, context: &mut UpdateContext) {
+        context.redraw(self.absolute_popup_rect());
+        self.popup = None;
+        self.widget_state.set_needs_layout();
+        context.redraw(self.widget_state.rect());
+    }
+
+    fn handle_key(&mut self, context: &mut UpdateContext, key: Key, alt: bool) -> bool {
+        let last = match self.options.len().checked_sub(1) {
+            Some(last) => last,
+            None => return false,
+        };
+
+        let option = match (key, self.selected) {
+            (Key::Down, _) if alt => {
+                self.open();
+                context.redraw(self.absolute_popup_rect());
+                return true;
+            }
+            (Key::Space | Key::Enter, _) => {
+                self.open();
+                context.redraw(self.absolute_popup_rect());
+                return true;
+            }
+            (Key::Up, Some(i)) => i.saturating_sub(1),
+            (Key::Down, Some(i)) => (i + 1).min(last),
+            (Key::Up | Key::Down | Key::Home, _) => 0,
+            (Key::End, _) => last,
+            _ => return false,
+        };
+
+        let changed = self.selected != Some(option);
+        self.choose(option);
+        changed
+    }
+}
+
+impl Widget for Dropdown {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        if self.popup.is_some() {
+            self.update_popup(context);
+            return if self.widget_state.needs_layout() {
+                PostUpdate::NeedsLayout
+            } else {
+                PostUpdate::NoChange
+            };
+        }
+
+        match context.event() {
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Pressed,
+            } if self
+                .widget_state
+                .rect()
+                .contains_point(context.cursor_position()) =>
+            {
+                context.set_focus(Some(self.focus));
+                self.open();
+                context.redraw(self.absolute_popup_rect());
+                PostUpdate::NeedsRedraw
+            }
+            Event::KeyPress { key, modifiers } if context.is_focused(self.focus) => {
+                if self.handle_key(context, key, modifiers.alt) {
+                    PostUpdate::NeedsRedraw
+                } else {
+                    PostUpdate::NoChange
+                }
+            }
+            _ => PostUpdate::NoChange,
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let mut widest = 0.0_f32;
+        let mut line_height = 0.0_f32;
+        let mut ascent = 0.0_f32;
+        self.shaped.clear();
+
+        let metrics = context.measure_text("", &self.style, None);
+        line_height = line_height.max(metrics.line_height);
+        ascent = ascent.max(metrics.ascent);
+
+        for option in &self.options {
+            let mut metrics = context.measure_text(option, &self.style, None);
+            widest = widest.max(metrics.width);
+            self.shaped.push(metrics.lines.swap_remove(0).shaped);
+        }
+
+        self.filter_shaped = self
+            .popup
+            .as_ref()
+            .filter(|popup| !popup.filter.is_empty())
+            .map(|popup| {
+                context
+                    .measure_text(&popup.filter, &self.style, None)
+                    .lines
+                    .swap_remove(0)
+                    .shaped
+            });
+
+        self.ascent = Px::from(ascent.round());
+        self.row_height = Px::from(line_height.ceil()) + PADDING + PADDING;
+
+        // Room for the arrow at the end, which is as wide as the dropdown is
+        // tall.
+        let text_width = Px::from(widest.ceil()) + PADDING + PADDING;
+        self.popup_width = text_width;
+        constraints.max_fit(Extent {
+            width: text_width + self.row_height,
+            height: self.row_height,
+        })
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, _size: IntrinsicSize, _height: Px) -> Px {
+        let widest = self
+            .options
+            .iter()
+            .map(|option| context.measure_text(option, &self.style, None).width)
+            .fold(0.0_f32, f32::max);
+        let row_height = self.intrinsic_height(context, IntrinsicSize::Max, Px::MAX);
+        Px::from(widest.ceil()) + PADDING + PADDING + row_height
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, _size: IntrinsicSize, _width: Px) -> Px {
+        let metrics = context.measure_text("", &self.style, None);
+        Px::from(metrics.line_height.ceil()) + PADDING + PADDING
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        let bounds = Rect::new(Point::zero(), extent);
+        let is_focused = canvas.is_focused(self.focus);
+
+        canvas.draw_rect(bounds, &Paint::Fill { color: BACKGROUND });
+        canvas.stroke_rect(
+            bounds,
+            &Stroke::solid(if is_focused { FOCUSED_BORDER } else { BORDER }, 1.0),
+        );
+
+        // A downward arrow in a square at the end of the dropdown.
+        let size = extent.height;
+        let arrow = Rect {
+            left: extent.width - size,
+            ..bounds
+        }
+        .deflate(size / 3, size / 3 + Px(1));
+        let middle = arrow.left + arrow.width() / 2;
+        let stroke = Stroke::solid(self.color, 1.5);
+        canvas.stroke_line(
+            Point::new(arrow.left, arrow.top),
+            Point::new(middle, arrow.bottom),
+            &stroke,
+        );
+        canvas.stroke_line(
+            Point::new(middle, arrow.bottom),
+            Point::new(arrow.right, arrow.top),
+            &stroke,
+        );
+
+        let (line, color) = match (&self.filter_shaped, self.selected) {
+            (Some(filter), _) => (Some(filter), FILTER),
+            (None, Some(selected)) => (self.shaped.get(selected), self.color),
+            (None, None) => (None, self.color),
+        };
+
+        if let Some(line) = line {
+            canvas.push_clip_rounded_rect(
+                Rect {
+                    right: extent.width - size,
+                    ..bounds
+                },
+                Px(0),
+            );
+            canvas.draw_text(
+                line,
+                Point::new(PADDING, PADDING + self.ascent),
+                self.style.size,
+                color,
+            );
+            canvas.pop_clip();
+        }
+    }
+
+    fn has_overlay(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    fn accept_draw_overlay(&self, canvas: &mut DrawContext) {
+        let popup = match &self.popup {
+            Some(popup) => popup,
+            None => return,
+        };
+
+        let rect = self.popup_rect();
+        canvas.draw_rect(rect, &Paint::Fill { color: BACKGROUND });
+        canvas.push_clip_rounded_rect(rect, Px(0));
+
+        let rows = virtual_list::visible_rows(
+            popup.scroll,
+            rect.height(),
+            self.row_height,
+            popup.matches.len(),
+        );
+        for i in rows {
+            let top = rect.top + virtual_list::row_top(popup.scroll, self.row_height, i);
+            if popup.highlighted == Some(i) {
+                canvas.draw_rect(
+                    Rect {
+                        top,
+                        bottom: top + self.row_height,
+                        ..rect
+                    },
+                    &Paint::Fill { color: HIGHLIGHT },
+                );
+            }
+
+            if let Some(line) = self.shaped.get(popup.matches[i]) {
+                canvas.draw_text(
+                    line,
+                    Point::new(PADDING, top + PADDING + self.ascent),
+                    self.style.size,
+                    self.color,
+                );
+            }
+        }
+
+        canvas.pop_clip();
+        canvas.stroke_rect(rect, &Stroke::solid(BORDER, 1.0));
+    }
+
+    fn accessibility(&self) -> Option<AccessNode> {
+        let mut node = AccessNode::new(Role::ComboBox)
+            .with_expanded(self.popup.is_some())
+            .with_focus(self.focus)
+            .with_action(ActionKind::Click)
+            .with_action(ActionKind::SetValue);
+        if let Some(selected) = self.selected {
+            node = node.with_value(&self.options[selected]);
+        }
+        Some(node)
+    }
+
+    fn accept_action(&mut self, action: &Action) -> PostUpdate {
+        match action {
+            Action::Click => {
+                if self.popup.take().is_none() {
+                    self.open();
+                }
+            }
+            // Values that aren't one of the options are ignored.
+            Action::SetValue(value) => {
+                if let Some(option) = self.options.iter().position(|option| option == value) {
+                    self.choose(option);
+                    self.popup = None;
+                }
+            }
+        }
+        PostUpdate::NeedsLayout
+    }
+}
+
+/// Finds the options that contain `filter`, ignoring case.
+fn filter_options(options: &[String], filter: &str) -> Vec<usize> {
+    let filter = filter.to_lowercase();
+    options
+        .iter()
+        .enumerate()
+        .filter(|(_, option)| option.to_lowercase().contains(&filter))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter() {
+        let options: Vec<String> = ["Apple", "Banana", "Cherry", "Pineapple"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        assert_eq!(filter_options(&options, ""), [0, 1, 2, 3]);
+        assert_eq!(filter_options(&options, "APP"), [0, 3]);
+        assert_eq!(filter_options(&options, "an"), [1]);
+        assert!(filter_options(&options, "kiwi").is_empty());
+    }
+}
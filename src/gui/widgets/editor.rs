@@ -0,0 +1,425 @@
+use std::{any::Any, borrow::Cow, ops::Range};
+
+use crate::{
+    gui::{
+        state::Observable,
+        undo::{Command, UndoStack},
+    },
+    shell::Composition,
+};
+
+/// The text being edited by a text widget and the selection within it, kept
+/// apart from the widgets so that editing doesn't depend on layout. Offsets
+/// are in bytes, and the caret moves one character at a time.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct Editor {
+    pub text: String,
+    pub caret: usize,
+    /// The end of the selection opposite the caret. Nothing is selected when
+    /// this is equal to `caret`.
+    pub anchor: usize,
+    /// The text that an input method is composing, which is shown in place of
+    /// the selection but isn't part of `text` until it is committed.
+    pub composition: Option<Composition>,
+}
+
+impl Editor {
+    pub fn selection(&self) -> Range<usize> {
+        self.caret.min(self.anchor)..self.caret.max(self.anchor)
+    }
+
+    pub fn selected_text(&self) -> &str {
+        &self.text[self.selection()]
+    }
+
+    /// Replaces the text being composed. Returns `true` if anything changed.
+    pub fn set_composition(&mut self, composition: Option<Composition>) -> bool {
+        let changed = self.composition != composition;
+        self.composition = composition;
+        changed
+    }
+
+    /// The text as it is shown, with the text being composed in place of the
+    /// selection.
+    pub fn display_text(&self) -> Cow<'_, str> {
+        match &self.composition {
+            Some(composition) => {
+                let mut text = self.text.clone();
+                text.replace_range(self.selection(), &composition.text);
+                Cow::Owned(text)
+            }
+            None => Cow::Borrowed(&self.text),
+        }
+    }
+
+    /// Where the caret is shown in [`Self::display_text`].
+    pub fn display_caret(&self) -> usize {
+        match &self.composition {
+            Some(composition) => self.selection().start + composition.caret,
+            None => self.caret,
+        }
+    }
+
+    /// The part of [`Self::display_text`] that is being composed, if any.
+    pub fn composition_range(&self) -> Option<Range<usize>> {
+        let start = self.selection().start;
+        self.composition
+            .as_ref()
+            .map(|composition| start..start + composition.text.len())
+    }
+
+    /// Converts an offset in [`Self::display_text`] to one in `text`. Offsets
+    /// within the text being composed become the start of the selection that
+    /// it replaces.
+    pub fn text_offset(&self, display_offset: usize) -> usize {
+        match self.composition_range() {
+            Some(range) if display_offset >= range.end => {
+                display_offset - range.len() + self.selection().len()
+            }
+            Some(range) => display_offset.min(range.start),
+            None => display_offset,
+        }
+    }
+
+    /// Replaces the selection with `text`, placing the caret after it.
+    pub fn insert(&mut self, text: &str) {
+        let selection = self.selection();
+        self.text.replace_range(selection.clone(), text);
+        self.caret = selection.start + text.len();
+        self.anchor = self.caret;
+    }
+
+    /// Moves the caret to `offset`, extending the selection if `extend` is
+    /// set. Returns `true` if anything changed.
+    pub fn move_to(&mut self, offset: usize, extend: bool) -> bool {
+        let before = (self.caret, self.anchor);
+        self.caret = offset;
+        if !extend {
+            self.anchor = offset;
+        }
+        before != (self.caret, self.anchor)
+    }
+
+    pub fn move_left(&mut self, extend: bool, by_word: bool) -> bool {
+        let offset = if by_word {
+            previous_word(&self.text, self.caret)
+        } else if !extend && self.caret != self.anchor {
+            self.selection().start
+        } else {
+            previous_char(&self.text, self.caret)
+        };
+        self.move_to(offset, extend)
+    }
+
+    pub fn move_right(&mut self, extend: bool, by_word: bool) -> bool {
+        let offset = if by_word {
+            next_word(&self.text, self.caret)
+        } else if !extend && self.caret != self.anchor {
+            self.selection().end
+        } else {
+            next_char(&self.text, self.caret)
+        };
+        self.move_to(offset, extend)
+    }
+
+    pub fn select_all(&mut self) -> bool {
+        self.move_to(0, false) | self.move_to(self.text.len(), true)
+    }
+
+    /// Deletes the selection, or the character or word before the caret if
+    /// nothing is selected. Returns `true` if any text was deleted.
+    pub fn delete_backward(&mut self, by_word: bool) -> bool {
+        if self.caret == self.anchor {
+            self.anchor = if by_word {
+                previous_word(&self.text, self.caret)
+            } else {
+                previous_char(&self.text, self.caret)
+            };
+        }
+        self.delete_selection()
+    }
+
+    /// Deletes the selection, or the character or word after the caret if
+    /// nothing is selected. Returns `true` if any text was deleted.
+    pub fn delete_forward(&mut self, by_word: bool) -> bool {
+        if self.caret == self.anchor {
+            self.anchor = if by_word {
+                next_word(&self.text, self.caret)
+            } else {
+                next_char(&self.text, self.caret)
+            };
+        }
+        self.delete_selection()
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        let changed = self.caret != self.anchor;
+        self.insert("");
+        changed
+    }
+}
+
+/// What kind of edit was made to the text, so that edits of the same kind made
+/// in quick succession are undone together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum EditKind {
+    Typing,
+    Deleting,
+    /// Edits such as pasting, which are always undone one at a time.
+    Other,
+}
+
+/// Records a text widget's edits on an [`UndoStack`], and hands back the text
+/// to show when one is undone or redone.
+///
+/// Undoing doesn't go through the widget, since the stack may be shared with
+/// other widgets and undone from anywhere. Instead, the edit sets an
+/// observable that the widget checks in
+/// [`Widget::accept_state_change`](super::Widget::accept_state_change).
+#[derive(Clone)]
+pub(super) struct EditHistory {
+    stack: UndoStack,
+    restore: Observable<Option<Editor>>,
+    /// The version of `restore` when it was last taken.
+    version: u64,
+}
+
+impl EditHistory {
+    pub fn new(stack: UndoStack) -> Self {
+        let restore = Observable::new(None);
+        let version = restore.version();
+        Self {
+            stack,
+            restore,
+            version,
+        }
+    }
+
+    pub fn stack(&self) -> &UndoStack {
+        &self.stack
+    }
+
+    /// Records that the text was changed from `before` to `after`. Does
+    /// nothing if the text is the same.
+    pub fn record(&self, kind: EditKind, before: Editor, after: &Editor) {
+        if before.text == after.text {
+            return;
+        }
+
+        self.stack.push(TextEdit {
+            kind,
+            before: Editor {
+                composition: None,
+                ..before
+            },
+            after: Editor {
+                composition: None,
+                ..after.clone()
+            },
+            restore: self.restore.clone(),
+        });
+    }
+
+    /// Takes the text that an undo or redo changed back to since the last
+    /// call, if any.
+    pub fn take_restored(&mut self) -> Option<Editor> {
+        if self.restore.version() == self.version {
+            return None;
+        }
+
+        self.version = self.restore.version();
+        self.restore.get().clone()
+    }
+}
+
+struct TextEdit {
+    kind: EditKind,
+    before: Editor,
+    after: Editor,
+    restore: Observable<Option<Editor>>,
+}
+
+impl Command for TextEdit {
+    fn undo(&mut self) {
+        self.restore.set(Some(self.before.clone()));
+    }
+
+    fn redo(&mut self) {
+        self.restore.set(Some(self.after.clone()));
+    }
+
+    /// Typing or deleting merges with more of the same that continues from
+    /// where it left off in the same widget.
+    fn merge(&mut self, next: &dyn Any) -> bool {
+        match next.downcast_ref::<Self>() {
+            Some(next)
+                if next.kind == self.kind
+                    && self.kind != EditKind::Other
+                    && next.before == self.after
+                    && next.restore.ptr_eq(&self.restore) =>
+            {
+                self.after = next.after.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+pub(super) fn previous_char(text: &str, offset: usize) -> usize {
+    text[..offset]
+        .char_indices()
+        .next_back()
+        .map_or(0, |(i, _)| i)
+}
+
+pub(super) fn next_char(text: &str, offset: usize) -> usize {
+    text[offset..]
+        .chars()
+        .next()
+        .map_or(offset, |c| offset + c.len_utf8())
+}
+
+/// Finds the start of the word before `offset`, skipping any whitespace
+/// between them.
+pub(super) fn previous_word(text: &str, offset: usize) -> usize {
+    text[..offset]
+        .trim_end()
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8())
+}
+
+/// Finds the start of the word after `offset`, skipping the rest of the
+/// current word and the whitespace after it.
+pub(super) fn next_word(text: &str, offset: usize) -> usize {
+    let rest = &text[offset..];
+    let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    let next = rest[word_end..]
+        .find(|c: char| !c.is_whitespace())
+        .map_or(rest.len(), |i| word_end + i);
+    offset + next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str, caret: usize, anchor: usize) -> Editor {
+        Editor {
+            text: text.to_string(),
+            caret,
+            anchor,
+            composition: None,
+        }
+    }
+
+    #[test]
+    fn edit_text() {
+        let mut e = editor("hello world", 5, 5);
+
+        e.insert(",");
+        assert_eq!(e, editor("hello, world", 6, 6));
+
+        e.move_right(true, true);
+        assert_eq!(e.selection(), 6..7);
+        e.move_right(true, true);
+        assert_eq!(e.selection(), 6..12);
+        e.insert("🌍");
+        assert_eq!(e, editor("hello,🌍", 10, 10));
+
+        // Characters are deleted whole, and words back to their start.
+        assert!(e.delete_backward(false));
+        assert_eq!(e, editor("hello,", 6, 6));
+        assert!(e.delete_backward(true));
+        assert_eq!(e, editor("", 0, 0));
+        assert!(!e.delete_backward(false));
+        assert!(!e.delete_forward(true));
+    }
+
+    #[test]
+    fn move_caret() {
+        let mut e = editor("one two  three", 0, 0);
+
+        assert!(e.move_right(false, true));
+        assert_eq!(e.caret, 4);
+        e.move_right(false, true);
+        assert_eq!(e.caret, 9);
+        e.move_right(false, true);
+        assert_eq!(e.caret, 14);
+        assert!(!e.move_right(false, false));
+
+        e.move_left(false, true);
+        assert_eq!(e.caret, 9);
+        e.move_left(true, false);
+        assert_eq!(e.selection(), 8..9);
+
+        // Moving without extending collapses the selection to its edge.
+        e.move_left(false, false);
+        assert_eq!(e, editor("one two  three", 8, 8));
+
+        assert!(e.select_all());
+        assert_eq!(e.selection(), 0..14);
+        e.move_right(false, false);
+        assert_eq!(e, editor("one two  three", 14, 14));
+    }
+    #[test]
+    fn compose_text() {
+        let mut e = editor("hello world", 6, 11);
+
+        // The composed text replaces the selection, but only for display.
+        assert!(e.set_composition(Some(Composition {
+            text: "世界".to_string(),
+            caret: 3,
+        })));
+        assert_eq!(e.display_text(), "hello 世界");
+        assert_eq!(e.display_caret(), 9);
+        assert_eq!(e.composition_range(), Some(6..12));
+        assert_eq!(e.text, "hello world");
+        assert_eq!(e.text_offset(3), 3);
+        assert_eq!(e.text_offset(9), 6);
+        assert_eq!(e.text_offset(12), 11);
+
+        e.insert("世界");
+        assert!(e.set_composition(None));
+        assert_eq!(e.display_text(), "hello 世界");
+        assert_eq!(e.display_caret(), 12);
+        assert_eq!(e.composition_range(), None);
+    }
+
+    #[test]
+    fn undo_edits() {
+        let mut history = EditHistory::new(UndoStack::new());
+        let mut e = Editor::default();
+
+        for c in ["a", "b", "c"] {
+            let before = e.clone();
+            e.insert(c);
+            history.record(EditKind::Typing, before, &e);
+        }
+
+        let before = e.clone();
+        e.delete_backward(false);
+        history.record(EditKind::Deleting, before, &e);
+
+        // Moving the caret between edits keeps them apart.
+        e.move_to(0, false);
+        let before = e.clone();
+        e.insert("d");
+        history.record(EditKind::Typing, before, &e);
+        assert_eq!(e.text, "dab");
+
+        assert!(history.stack().undo());
+        assert_eq!(history.take_restored(), Some(editor("ab", 0, 0)));
+        assert_eq!(history.take_restored(), None);
+        assert!(history.stack().undo());
+        assert_eq!(history.take_restored(), Some(editor("abc", 3, 3)));
+        assert!(history.stack().undo());
+        assert_eq!(history.take_restored(), Some(editor("", 0, 0)));
+        assert!(!history.stack().undo());
+
+        assert!(history.stack().redo());
+        assert_eq!(history.take_restored(), Some(editor("abc", 3, 3)));
+    }
+}
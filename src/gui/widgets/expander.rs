@@ -0,0 +1,399 @@
+use std::{
+    any::Any,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Offset, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::{
+        accessibility::{AccessNode, Action, ActionKind, Role},
+        input::{ButtonState, Event, FocusId, MouseButton},
+    },
+    shell::event::Key,
+    text::{ShapedLine, TextStyle},
+};
+
+use super::{
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// The space around the title in the header.
+const PADDING: Px = Px(4);
+
+/// How long it takes to open or close the expander.
+const TRANSITION: Duration = Duration::from_millis(150);
+
+const HEADER: Color = Color {
+    r: 0.92,
+    g: 0.92,
+    b: 0.92,
+    a: 1.0,
+};
+
+const HOVERED_HEADER: Color = Color {
+    r: 0.86,
+    g: 0.86,
+    b: 0.86,
+    a: 1.0,
+};
+
+const FOCUSED_BORDER: Color = Color {
+    r: 0.0,
+    g: 0.4,
+    b: 0.9,
+    a: 1.0,
+};
+
+/// A header with a title that shows or hides the content below it when
+/// clicked, for grouping related settings.
+///
+/// The content slides open and closed rather than appearing at once. The
+/// expander is also toggled by pressing Space or Enter while it has the
+/// keyboard focus. Whether it is expanded is kept when the widget tree is
+/// rebuilt (see [`keyed::reconcile`](super::keyed::reconcile)).
+#[must_use]
+pub struct Expander<W: Widget> {
+    widget_state: WidgetState,
+    focus: FocusId,
+    title: String,
+    style: TextStyle,
+    pub color: Color,
+    pub child: W,
+    expanded: bool,
+    /// How much of the content is shown, from 0 when collapsed to 1 when
+    /// expanded.
+    openness: f32,
+    /// When the expander started opening or closing, and how open it was at
+    /// the time.
+    transition: Option<(Instant, f32)>,
+    hovered: bool,
+    on_toggle: Option<Box<dyn FnMut(bool)>>,
+    // Computed during layout.
+    shaped: Option<ShapedLine>,
+    ascent: Px,
+    header_height: Px,
+}
+
+impl<W: Widget> Expander<W> {
+    /// Creates a collapsed expander.
+    pub fn new(title: &str, style: TextStyle, child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            focus: FocusId::new(),
+            title: title.to_string(),
+            style,
+            color: Color::BLACK,
+            child,
+            expanded: false,
+            openness: 0.0,
+            transition: None,
+            hovered: false,
+            on_toggle: None,
+            shaped: None,
+            ascent: Px(0),
+            header_height: Px(0),
+        }
+    }
+
+    /// Starts the expander open or closed, without animating.
+    pub fn with_expanded(mut self, expanded: bool) -> Self {
+        self.jump_to(expanded);
+        self
+    }
+
+    /// Calls `callback` with whether the expander is now expanded whenever the
+    /// user toggles it.
+    pub fn with_on_toggle(mut self, callback: impl FnMut(bool) + 'static) -> Self {
+        self.on_toggle = Some(Box::new(callback));
+        self
+    }
+
+    /// Whether the expander is open or opening.
+    #[must_use]
+    pub fn is_expanded(&self) -> bool {
+        self.expanded
+    }
+
+    /// Opens or closes the expander without calling the toggle callback,
+    /// animating the change.
+    pub fn set_expanded(&mut self, expanded: bool) {
+        if expanded != self.expanded {
+            self.expanded = expanded;
+            self.transition = Some((Instant::now(), self.openness));
+        }
+    }
+
+    /// The identifier used to give the expander the keyboard focus.
+    #[must_use]
+    pub fn focus_id(&self) -> FocusId {
+        self.focus
+    }
+
+    fn jump_to(&mut self, expanded: bool) {
+        self.expanded = expanded;
+        self.openness = if expanded { 1.0 } else { 0.0 };
+        self.transition = None;
+        self.widget_state.set_needs_resize();
+    }
+
+    fn toggle(&mut self) {
+        self.set_expanded(!self.expanded);
+        if let Some(on_toggle) = &mut self.on_toggle {
+            on_toggle(self.expanded);
+        }
+    }
+
+    fn header(&self) -> Rect {
+        Rect {
+            bottom: self.widget_state.rect().top + self.header_height,
+            ..self.widget_state.rect()
+        }
+    }
+}
+
+/// How open an expander is `elapsed` after it started opening or closing from
+/// `from`, or `None` if it has finished.
+fn openness(from: f32, expanded: bool, elapsed: Duration) -> Option<f32> {
+    let to = if expanded { 1.0 } else { 0.0 };
+
+    // The transition is shorter when it starts part of the way there.
+    let duration = TRANSITION.as_secs_f32() * (to - from).abs();
+    if elapsed.as_secs_f32() >= duration {
+        return None;
+    }
+    let t = elapsed.as_secs_f32() / duration;
+
+    // Eases out, starting quickly and slowing down at the end.
+    let eased = 1.0 - (1.0 - t) * (1.0 - t);
+    Some(from + (to - from) * eased)
+}
+
+struct SavedState {
+    expanded: bool,
+}
+
+impl<W: Widget> Widget for Expander<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        let in_header = self.header().contains_point(context.cursor_position());
+
+        match context.event() {
+            Event::CursorMove { .. } if in_header != self.hovered => {
+                self.hovered = in_header;
+                context.redraw(self.header());
+            }
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Pressed,
+            } if in_header => {
+                context.set_focus(Some(self.focus));
+                self.toggle();
+                return PostUpdate::NeedsRedraw;
+            }
+            Event::KeyPress {
+                key: Key::Space | Key::Enter,
+                ..
+            } if context.is_focused(self.focus) => {
+                self.toggle();
+                return PostUpdate::NeedsRedraw;
+            }
+            _ => {}
+        }
+
+        // The content is hidden while the expander is collapsed.
+        if self.openness > 0.0 {
+            context.update(&mut self.child);
+        }
+
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let metrics = context.measure_text(&self.title, &self.style, None);
+        self.ascent = Px::from(metrics.ascent.round());
+        self.header_height = Px::from(metrics.line_height.ceil()) + PADDING + PADDING;
+        self.shaped = metrics.lines.into_iter().next().map(|line| line.shaped);
+
+        // The content is laid out at its full size and clipped to the part
+        // that is shown.
+        let child_extent = context.layout(
+            &mut self.child,
+            BoxConstraint {
+                min: Extent::zero(),
+                max: constraints.max.saturating_sub(Extent {
+                    width: Px(0),
+                    height: self.header_height,
+                }),
+            },
+        );
+        context.position_widget(
+            &mut self.child,
+            Offset {
+                x: Px(0),
+                y: self.header_height,
+            },
+            child_extent,
+        );
+
+        let shown = Px::from((f32::from(child_extent.height) * self.openness).round());
+        constraints.max_fit(Extent {
+            width: constraints.max.width,
+            height: self.header_height + shown,
+        })
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        let metrics = context.measure_text(&self.title, &self.style, None);
+        let header_height = Px::from(metrics.line_height.ceil()) + PADDING + PADDING;
+        let header = header_height + Px::from(metrics.width.ceil()) + PADDING;
+        let content = height.saturating_sub(header_height).max(Px(0));
+        header.max(self.child.intrinsic_width(context, size, content))
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        let metrics = context.measure_text(&self.title, &self.style, None);
+        let header_height = Px::from(metrics.line_height.ceil()) + PADDING + PADDING;
+        let content = self.child.intrinsic_height(context, size, width);
+        header_height + Px::from((f32::from(content) * self.openness).round())
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        let header = Rect::new(
+            Point::zero(),
+            Extent {
+                width: extent.width,
+                height: self.header_height,
+            },
+        );
+        let color = if self.hovered { HOVERED_HEADER } else { HEADER };
+        canvas.draw_rect(header, &Paint::Fill { color });
+        if canvas.is_focused(self.focus) {
+            canvas.stroke_rect(header, &Stroke::solid(FOCUSED_BORDER, 1.0));
+        }
+
+        // A chevron that turns from pointing right to pointing down as the
+        // expander opens.
+        let size = f32::from(self.header_height) / 5.0;
+        let center = (
+            f32::from(PADDING) + size * 1.5,
+            f32::from(self.header_height) / 2.0,
+        );
+        let (sin, cos) = (self.openness * std::f32::consts::FRAC_PI_2).sin_cos();
+        let at = |x: f32, y: f32| {
+            Point::new(
+                Px::from((center.0 + x * cos - y * sin).round()),
+                Px::from((center.1 + x * sin + y * cos).round()),
+            )
+        };
+        let stroke = Stroke::solid(self.color, 1.5);
+        canvas.stroke_line(at(-size / 2.0, -size), at(size / 2.0, 0.0), &stroke);
+        canvas.stroke_line(at(size / 2.0, 0.0), at(-size / 2.0, size), &stroke);
+
+        if let Some(shaped) = &self.shaped {
+            canvas.draw_text(
+                shaped,
+                Point::new(self.header_height, PADDING + self.ascent),
+                self.style.size,
+                self.color,
+            );
+        }
+
+        if extent.height > self.header_height {
+            canvas.push_clip_rounded_rect(
+                Rect {
+                    top: self.header_height,
+                    ..Rect::new(Point::zero(), extent)
+                },
+                Px(0),
+            );
+            canvas.draw(&self.child);
+            canvas.pop_clip();
+        }
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(SavedState {
+            expanded: self.expanded,
+        }))
+    }
+
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        if let Ok(state) = state.downcast::<SavedState>() {
+            self.jump_to(state.expanded);
+        }
+    }
+
+    fn accept_animation_frame(&mut self, now: Instant) -> PostUpdate {
+        let (start, from) = match self.transition {
+            Some(transition) => transition,
+            None => return PostUpdate::NoChange,
+        };
+
+        match openness(from, self.expanded, now.saturating_duration_since(start)) {
+            Some(openness) => self.openness = openness,
+            None => {
+                self.openness = if self.expanded { 1.0 } else { 0.0 };
+                self.transition = None;
+            }
+        }
+        PostUpdate::NeedsResize
+    }
+
+    fn accessibility(&self) -> Option<AccessNode> {
+        Some(
+            AccessNode::new(Role::Button)
+                .with_name(&self.title)
+                .with_expanded(self.expanded)
+                .with_focus(self.focus)
+                .with_action(ActionKind::Click),
+        )
+    }
+
+    fn accept_action(&mut self, action: &Action) -> PostUpdate {
+        if *action == Action::Click {
+            self.toggle();
+        }
+        PostUpdate::NeedsRedraw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transition() {
+        assert_eq!(openness(0.0, true, Duration::ZERO), Some(0.0));
+        assert_eq!(openness(0.0, true, TRANSITION), None);
+
+        let halfway = openness(0.0, true, TRANSITION / 2).unwrap();
+        assert!(halfway > 0.5 && halfway < 1.0);
+        let closing = openness(1.0, false, TRANSITION / 2).unwrap();
+        assert!(closing > 0.0 && closing < 0.5);
+
+        // Reversing part of the way takes less time.
+        assert_eq!(openness(0.5, false, TRANSITION / 2), None);
+        assert!(openness(0.5, false, TRANSITION / 4).is_some());
+        assert_eq!(openness(0.0, false, Duration::ZERO), None);
+    }
+}
@@ -0,0 +1,465 @@
+use crate::{
+    gfx::geometry::{Extent, Offset, Px},
+    gui::input::Event,
+};
+
+use super::{
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// How the size of a row or column of a [`Grid`] is decided.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Track {
+    /// Always the same size.
+    Fixed(Px),
+    /// As large as the largest child that is only in this row or column.
+    Auto,
+    /// A share of the space left over by the other tracks, in proportion to
+    /// the weights of every weighted track.
+    Weight(f32),
+}
+
+/// The cells of a [`Grid`] that a child covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridCell {
+    pub column: usize,
+    pub row: usize,
+    pub column_span: usize,
+    pub row_span: usize,
+}
+
+impl GridCell {
+    /// The single cell at `column` and `row`.
+    #[must_use]
+    pub fn new(column: usize, row: usize) -> Self {
+        Self {
+            column,
+            row,
+            column_span: 1,
+            row_span: 1,
+        }
+    }
+
+    /// Extends the cell over `columns` columns and `rows` rows.
+    #[must_use]
+    pub fn with_span(mut self, columns: usize, rows: usize) -> Self {
+        self.column_span = columns.max(1);
+        self.row_span = rows.max(1);
+        self
+    }
+}
+
+/// Lays out its children in rows and columns, such as for the labels and
+/// fields of a form.
+///
+/// Each child fills the cells that it covers, including the gaps between
+/// them; wrap it in [`Align`](super::layout::Align) to place it within them at
+/// its own size instead. Children that cover more than one row or column don't
+/// affect the size of [`Track::Auto`] tracks, and cells past the last row or
+/// column are not shown.
+#[must_use]
+pub struct Grid<W: Widget> {
+    widget_state: WidgetState,
+    columns: Vec<Track>,
+    rows: Vec<Track>,
+    column_gap: Px,
+    row_gap: Px,
+    children: Vec<(GridCell, W)>,
+    needs_layout: bool,
+}
+
+impl<W: Widget> Grid<W> {
+    pub fn new(columns: Vec<Track>, rows: Vec<Track>) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            columns,
+            rows,
+            column_gap: Px(4),
+            row_gap: Px(4),
+            children: Vec::new(),
+            needs_layout: false,
+        }
+    }
+
+    /// Changes the space between columns and between rows.
+    pub fn with_gaps(mut self, column_gap: Px, row_gap: Px) -> Self {
+        self.column_gap = column_gap;
+        self.row_gap = row_gap;
+        self
+    }
+
+    pub fn with_child(mut self, cell: GridCell, child: W) -> Self {
+        self.children.push((cell, child));
+        self
+    }
+
+    pub fn add(&mut self, cell: GridCell, child: W) {
+        self.children.push((cell, child));
+        self.needs_layout = true;
+    }
+
+    pub fn remove(&mut self, index: usize) -> W {
+        self.needs_layout = true;
+        self.children.remove(index).1
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Finds the largest size of the children that are only in each track
+    /// along one axis, laying them out within `max`.
+    fn measure(
+        &mut self,
+        context: &mut LayoutContext,
+        tracks: &[Track],
+        max: impl Fn(&GridCell) -> Extent,
+        along: impl Fn(&GridCell, Extent) -> Option<(usize, Px)>,
+    ) -> Vec<Px> {
+        let mut sizes = vec![Px(0); tracks.len()];
+        for (cell, child) in &mut self.children {
+            let extent = max(cell);
+            let (index, size) = match along(cell, extent) {
+                Some((index, _)) if tracks.get(index) == Some(&Track::Auto) => {
+                    let measured = context.layout(child, BoxConstraint::loose(extent));
+                    match along(cell, measured) {
+                        Some(size) => size,
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            };
+            sizes[index] = sizes[index].max(size);
+        }
+        sizes
+    }
+}
+
+/// Divides `available` space between `tracks` separated by `gap`, where
+/// `auto` holds the sizes of the children in each automatic track.
+fn track_sizes(tracks: &[Track], available: Px, gap: Px, auto: &[Px]) -> Vec<Px> {
+    let gaps = gap * tracks.len().saturating_sub(1) as f32;
+
+    let mut sizes: Vec<Px> = tracks
+        .iter()
+        .zip(auto.iter().chain(std::iter::repeat(&Px(0))))
+        .map(|(track, auto)| match track {
+            Track::Fixed(size) => *size,
+            Track::Auto => *auto,
+            Track::Weight(_) => Px(0),
+        })
+        .collect();
+
+    let used = sizes
+        .iter()
+        .fold(gaps, |sum, size| sum.saturating_add(*size));
+    let remaining = available.saturating_sub(used).max(Px(0));
+    let total_weight: f32 = tracks
+        .iter()
+        .map(|track| match track {
+            Track::Weight(weight) => weight.max(0.0),
+            _ => 0.0,
+        })
+        .sum();
+
+    if total_weight > 0.0 {
+        // Each weighted track ends where the running total of weights says it
+        // should, so that rounding doesn't leave any space unused.
+        let mut weight_so_far = 0.0;
+        let mut start = Px(0);
+        for (size, track) in sizes.iter_mut().zip(tracks) {
+            if let Track::Weight(weight) = track {
+                weight_so_far += weight.max(0.0);
+                let end = Px::from((f32::from(remaining) * weight_so_far / total_weight).round());
+                *size = end - start;
+                start = end;
+            }
+        }
+    }
+
+    sizes
+}
+
+/// The size of `tracks` separated by `gap` when each is as large as its
+/// largest child, given as the index of its track and its size. Weighted
+/// tracks are sized like automatic ones, since they have no size of their
+/// own.
+fn intrinsic_total(tracks: &[Track], gap: Px, children: impl Iterator<Item = (usize, Px)>) -> Px {
+    let mut sizes: Vec<Px> = tracks
+        .iter()
+        .map(|track| match track {
+            Track::Fixed(size) => *size,
+            Track::Auto | Track::Weight(_) => Px(0),
+        })
+        .collect();
+
+    for (index, size) in children {
+        if let Some(Track::Auto | Track::Weight(_)) = tracks.get(index) {
+            sizes[index] = sizes[index].max(size);
+        }
+    }
+
+    span_size(&sizes, gap, 0, sizes.len()).unwrap_or(Px(0))
+}
+
+/// The offsets of tracks with `sizes` separated by `gap`.
+fn track_offsets(sizes: &[Px], gap: Px) -> Vec<Px> {
+    let mut offset = Px(0);
+    sizes
+        .iter()
+        .map(|size| {
+            let start = offset;
+            offset = offset.saturating_add(*size).saturating_add(gap);
+            start
+        })
+        .collect()
+}
+
+/// The size of the tracks from `start` to `start + span`, and the gaps between
+/// them, or `None` if they're past the end.
+fn span_size(sizes: &[Px], gap: Px, start: usize, span: usize) -> Option<Px> {
+    let spanned = sizes.get(start..(start + span).min(sizes.len()))?;
+    if spanned.is_empty() {
+        return None;
+    }
+    let gaps = gap * (spanned.len() - 1) as f32;
+    Some(
+        spanned
+            .iter()
+            .fold(gaps, |sum, size| sum.saturating_add(*size)),
+    )
+}
+
+impl<W: Widget> Widget for Grid<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        for (_, child) in &self.children {
+            f(child);
+        }
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        for (_, child) in &mut self.children {
+            f(child);
+        }
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        match context.event() {
+            Event::KeyPress { .. }
+            | Event::Text { .. }
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. }
+            | Event::None => {
+                for (_, child) in &mut self.children {
+                    context.update(child);
+                }
+            }
+            Event::CursorMove { .. } | Event::MouseButton { .. } | Event::Scroll { .. } => {
+                for (_, child) in &mut self.children {
+                    if context.cursor_position().within(&context.bound_of(child)) {
+                        context.update(child);
+                    }
+                }
+            }
+        }
+
+        if self.needs_layout {
+            self.needs_layout = false;
+            PostUpdate::NeedsLayout
+        } else {
+            PostUpdate::NoChange
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let available = constraints.max;
+
+        // Columns are sized first so that automatic rows can be measured at
+        // the width they'll have.
+        let columns = self.columns.clone();
+        let auto = self.measure(
+            context,
+            &columns,
+            |_| available,
+            |cell, extent| (cell.column_span == 1).then_some((cell.column, extent.width)),
+        );
+        let widths = track_sizes(&columns, available.width, self.column_gap, &auto);
+
+        let rows = self.rows.clone();
+        let column_gap = self.column_gap;
+        let auto = self.measure(
+            context,
+            &rows,
+            |cell| Extent {
+                width: span_size(&widths, column_gap, cell.column, cell.column_span)
+                    .unwrap_or(Px(0)),
+                height: available.height,
+            },
+            |cell, extent| (cell.row_span == 1).then_some((cell.row, extent.height)),
+        );
+        let heights = track_sizes(&rows, available.height, self.row_gap, &auto);
+
+        let lefts = track_offsets(&widths, self.column_gap);
+        let tops = track_offsets(&heights, self.row_gap);
+        for (cell, child) in &mut self.children {
+            let extent = match (
+                span_size(&widths, self.column_gap, cell.column, cell.column_span),
+                span_size(&heights, self.row_gap, cell.row, cell.row_span),
+            ) {
+                (Some(width), Some(height)) => Extent { width, height },
+                _ => Extent::zero(),
+            };
+            let offset = Offset {
+                x: lefts.get(cell.column).copied().unwrap_or(Px(0)),
+                y: tops.get(cell.row).copied().unwrap_or(Px(0)),
+            };
+
+            let _ = context.layout(child, BoxConstraint::exact(extent));
+            context.position_widget(child, offset, extent);
+        }
+
+        constraints.max_fit(Extent {
+            width: span_size(&widths, self.column_gap, 0, widths.len()).unwrap_or(Px(0)),
+            height: span_size(&heights, self.row_gap, 0, heights.len()).unwrap_or(Px(0)),
+        })
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, _height: Px) -> Px {
+        intrinsic_total(
+            &self.columns,
+            self.column_gap,
+            self.children
+                .iter()
+                .filter(|(cell, _)| cell.column_span == 1)
+                .map(|(cell, child)| {
+                    let width = child.intrinsic_width(context, size, Px::MAX);
+                    (cell.column, width)
+                }),
+        )
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, _width: Px) -> Px {
+        intrinsic_total(
+            &self.rows,
+            self.row_gap,
+            self.children
+                .iter()
+                .filter(|(cell, _)| cell.row_span == 1)
+                .map(|(cell, child)| {
+                    let height = child.intrinsic_height(context, size, Px::MAX);
+                    (cell.row, height)
+                }),
+        )
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw_children(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::color::Color,
+        gui::widgets::{debug::Fill, layout::SizedBox},
+    };
+
+    use super::*;
+
+    #[test]
+    fn divide_tracks() {
+        let tracks = [
+            Track::Fixed(Px(20)),
+            Track::Auto,
+            Track::Weight(1.0),
+            Track::Weight(2.0),
+        ];
+        let sizes = track_sizes(&tracks, Px(200), Px(10), &[Px(0), Px(35)]);
+        assert_eq!(sizes, [Px(20), Px(35), Px(38), Px(77)]);
+        assert_eq!(
+            track_offsets(&sizes, Px(10)),
+            [Px(0), Px(30), Px(75), Px(123)]
+        );
+        assert_eq!(span_size(&sizes, Px(10), 2, 2), Some(Px(125)));
+        assert_eq!(span_size(&sizes, Px(10), 4, 1), None);
+
+        // Weighted tracks shrink to nothing before the others do.
+        let sizes = track_sizes(&tracks, Px(50), Px(10), &[Px(0), Px(35)]);
+        assert_eq!(sizes, [Px(20), Px(35), Px(0), Px(0)]);
+    }
+
+    #[test]
+    fn form_layout() {
+        let label = |width| -> Box<dyn Widget> {
+            Box::new(SizedBox::new(
+                Extent {
+                    width: Px(width),
+                    height: Px(12),
+                },
+                Fill::new(Color::BLACK),
+            ))
+        };
+
+        let mut grid = Grid::new(vec![Track::Auto, Track::Weight(1.0)], vec![Track::Auto; 3])
+            .with_gaps(Px(8), Px(4))
+            .with_child(GridCell::new(0, 0), label(30))
+            .with_child(GridCell::new(0, 1), label(50))
+            .with_child(GridCell::new(1, 0), label(10))
+            .with_child(GridCell::new(0, 2).with_span(2, 1), label(10));
+
+        let mut context = LayoutContext::default();
+        let extent = context.layout(
+            &mut grid,
+            BoxConstraint::loose(Extent {
+                width: Px(200),
+                height: Px(100),
+            }),
+        );
+        assert_eq!(
+            extent,
+            Extent {
+                width: Px(200),
+                height: Px(44),
+            }
+        );
+
+        let rects: Vec<_> = grid
+            .children
+            .iter()
+            .map(|(_, child)| (child.widget_state().offset(), child.widget_state().extent()))
+            .collect();
+        assert_eq!(
+            rects[2].0,
+            Offset {
+                x: Px(58),
+                y: Px(0)
+            }
+        );
+        assert_eq!(rects[2].1.width, Px(142));
+        assert_eq!(
+            rects[3].0,
+            Offset {
+                x: Px(0),
+                y: Px(32)
+            }
+        );
+        assert_eq!(rects[3].1.width, Px(200));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{gfx::geometry::Point, shell::CursorIcon};
+
+use super::{paint_order, Widget};
+
+/// Finds the widgets in the tree with `root` at its root whose bounds contain
+/// `point`, in absolute coordinates, as of the last layout.
+///
+/// The widgets are ordered from top to bottom, the reverse of the order they
+/// are drawn in (see [`paint_order`]), so the first is the one that appears
+/// under the point and the last is `root`. Widgets are only found if their
+/// parent contains the point, since parts of a widget outside of its parent's
+/// bounds may be clipped. Disabled widgets and their descendants are never
+/// found.
+#[must_use]
+pub fn hit_test(root: &dyn Widget, point: Point) -> Vec<&dyn Widget> {
+    fn visit<'a>(widget: &'a dyn Widget, point: Point, hits: &mut Vec<&'a dyn Widget>) {
+        let state = widget.widget_state();
+        if state.is_disabled() || !state.rect().contains_point(point) {
+            return;
+        }
+
+        for child in paint_order(widget).into_iter().rev() {
+            visit(child, point, hits);
+        }
+
+        hits.push(widget);
+    }
+
+    let mut hits = Vec::new();
+    visit(root, point, &mut hits);
+    hits
+}
+
+/// Finds the cursor to show when the mouse is at `point`, in absolute
+/// coordinates, over the tree with `root` at its root. This is the cursor of
+/// the topmost widget at the point that wants one (see [`Widget::cursor`]), or
+/// the arrow if none do.
+#[must_use]
+pub fn cursor_at(root: &dyn Widget, point: Point) -> CursorIcon {
+    hit_test(root, point)
+        .into_iter()
+        .find_map(|widget| widget.cursor(point))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::{
+            color::Color,
+            geometry::{Extent, Offset, Px, ScaleFactor},
+        },
+        gui::widgets::{
+            debug::Fill,
+            layout::Column,
+            split_panel::{Axis, SplitPanel},
+            LayoutContext, WidgetState,
+        },
+    };
+
+    use super::*;
+
+    fn place(state: &mut WidgetState, x: i16, y: i16, size: i16) {
+        state.set_layout(
+            Offset { x: Px(x), y: Px(y) },
+            Extent {
+                width: Px(size),
+                height: Px(size),
+            },
+        );
+        state.set_origin(Point::new(x, y));
+    }
+
+    #[test]
+    fn topmost_first() {
+        let mut root = Column::with_children(vec![
+            Fill::new(Color::RED),
+            Fill::new(Color::BLUE),
+            Fill::new(Color::WHITE),
+        ]);
+        place(root.widget_state_mut(), 0, 0, 100);
+
+        // The second and third children overlap.
+        let mut index = 0;
+        root.for_each_child_mut(&mut |child| {
+            let x = [50, 0, 5][index];
+            place(child.widget_state_mut(), x, 0, 10);
+            index += 1;
+        });
+
+        assert_eq!(hit_test(&root, Point::new(6, 6)).len(), 3);
+        assert_eq!(hit_test(&root, Point::new(2, 2)).len(), 2);
+        assert_eq!(hit_test(&root, Point::new(90, 90)).len(), 1);
+        assert!(hit_test(&root, Point::new(200, 200)).is_empty());
+
+        let hits = hit_test(&root, Point::new(6, 6));
+        assert_eq!(hits[0].widget_state().rect().left, Px(5));
+        assert_eq!(hits[1].widget_state().rect().left, Px(0));
+
+        // Disabled widgets let the point through to the widgets below them.
+        // Disabling a widget doesn't change its layout.
+        let mut index = 0;
+        root.for_each_child_mut(&mut |child| {
+            child.widget_state_mut().set_disabled(index == 2);
+            assert!(!child.widget_state().needs_layout());
+            index += 1;
+        });
+        let hits = hit_test(&root, Point::new(6, 6));
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].widget_state().rect().left, Px(0));
+    }
+
+    #[test]
+    fn cursor_over_divider() {
+        let mut root =
+            SplitPanel::with_children(Axis::X, vec![Fill::new(Color::RED), Fill::new(Color::BLUE)]);
+        let extent = Extent {
+            width: Px(104),
+            height: Px(100),
+        };
+        let _ = LayoutContext::new(ScaleFactor::ONE).begin(&mut root, extent);
+
+        // The divider lies between the two 50 pixel panes.
+        assert_eq!(
+            cursor_at(&root, Point::new(51, 10)),
+            CursorIcon::ResizeHorizontal
+        );
+        assert_eq!(cursor_at(&root, Point::new(10, 10)), CursorIcon::Arrow);
+        assert_eq!(cursor_at(&root, Point::new(200, 10)), CursorIcon::Arrow);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{
+    gfx::{
+        geometry::{Extent, Point, Px, Rect},
+        pixel_buffer::PixelBuffer,
+        Image as GpuImage,
+    },
+    gui::images::{ImageId, Images},
+    handle_pool::Handle,
+};
+
+use super::{
+    BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext, Widget, WidgetState,
+};
+
+/// How an image is scaled to fill the bounds of an [`Image`] widget.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Fit {
+    /// Scales the image to fit inside the bounds without changing its aspect
+    /// ratio, centered with space left on two sides if the aspect ratios
+    /// differ.
+    #[default]
+    Contain,
+    /// Scales the image to cover the bounds without changing its aspect ratio,
+    /// centered with the parts that don't fit cut off.
+    Cover,
+    /// Stretches the image to the bounds.
+    Fill,
+}
+
+/// Draws an image. The image is destroyed when the widget is dropped.
+#[must_use]
+pub struct Image {
+    widget_state: WidgetState,
+    images: Images,
+    id: ImageId,
+    image_extent: Extent,
+    fit: Fit,
+}
+
+impl Image {
+    /// Creates a widget that draws `pixels`, once they have been uploaded by
+    /// `images`.
+    pub fn from_pixels(images: &Images, pixels: PixelBuffer) -> Self {
+        let image_extent = pixels.extent();
+        let id = images.upload(pixels);
+        Self::with_id(images, id, image_extent)
+    }
+
+    /// Creates a widget that draws an image that has already been created.
+    /// The widget takes ownership of the image, and releases it to `images`
+    /// when dropped.
+    pub fn from_handle(images: &Images, handle: Handle<GpuImage>, image_extent: Extent) -> Self {
+        let id = images.adopt(handle);
+        Self::with_id(images, id, image_extent)
+    }
+
+    fn with_id(images: &Images, id: ImageId, image_extent: Extent) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            images: images.clone(),
+            id,
+            image_extent,
+            fit: Fit::default(),
+        }
+    }
+
+    pub fn with_fit(mut self, fit: Fit) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    #[must_use]
+    pub fn fit(&self) -> Fit {
+        self.fit
+    }
+
+    pub fn set_fit(&mut self, fit: Fit) {
+        self.fit = fit;
+        self.widget_state.set_needs_layout();
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        self.images.release(self.id);
+    }
+}
+
+impl Widget for Image {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, _context: &mut UpdateContext) -> PostUpdate {
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(
+        &mut self,
+        _context: &mut LayoutContext,
+        constraints: BoxConstraint,
+    ) -> Extent {
+        constraints.max_fit(self.image_extent)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        // Nothing is drawn until the image has been uploaded.
+        if let Some(handle) = self.images.handle(self.id) {
+            let (src_rect, dst_rect) = fit_image(self.fit, self.image_extent, extent);
+            canvas.draw_image(handle, src_rect, dst_rect);
+        }
+    }
+}
+
+/// Finds the part of an image of size `image` to draw, and where to draw it
+/// within bounds of size `bounds`, to scale it according to `fit`.
+fn fit_image(fit: Fit, image: Extent, bounds: Extent) -> (Rect, Rect) {
+    let full_image = Rect::new(Point::zero(), image);
+    let full_bounds = Rect::new(Point::zero(), bounds);
+    if image.width <= 0 || image.height <= 0 || fit == Fit::Fill {
+        return (full_image, full_bounds);
+    }
+
+    let scale_x = f32::from(bounds.width.0) / f32::from(image.width.0);
+    let scale_y = f32::from(bounds.height.0) / f32::from(image.height.0);
+
+    // Centers a length of `inner` within a length of `outer`.
+    let center = |outer: Px, inner: Px| (outer - inner) / 2;
+
+    if fit == Fit::Contain {
+        let scale = scale_x.min(scale_y);
+        let extent = Extent {
+            width: Px::from((f32::from(image.width.0) * scale).round()),
+            height: Px::from((f32::from(image.height.0) * scale).round()),
+        };
+        let origin = Point::new(
+            center(bounds.width, extent.width),
+            center(bounds.height, extent.height),
+        );
+        (full_image, Rect::new(origin, extent))
+    } else {
+        let scale = scale_x.max(scale_y);
+        let extent = Extent {
+            width: Px::from((f32::from(bounds.width.0) / scale).round()),
+            height: Px::from((f32::from(bounds.height.0) / scale).round()),
+        };
+        let origin = Point::new(
+            center(image.width, extent.width),
+            center(image.height, extent.height),
+        );
+        (Rect::new(origin, extent), full_bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_modes() {
+        let image = Extent {
+            width: Px(200),
+            height: Px(100),
+        };
+        let bounds = Extent {
+            width: Px(100),
+            height: Px(100),
+        };
+        let rect = |left, top, right, bottom| Rect {
+            left: Px(left),
+            top: Px(top),
+            right: Px(right),
+            bottom: Px(bottom),
+        };
+
+        assert_eq!(
+            fit_image(Fit::Contain, image, bounds),
+            (rect(0, 0, 200, 100), rect(0, 25, 100, 75))
+        );
+        assert_eq!(
+            fit_image(Fit::Cover, image, bounds),
+            (rect(50, 0, 150, 100), rect(0, 0, 100, 100))
+        );
+        assert_eq!(
+            fit_image(Fit::Fill, image, bounds),
+            (rect(0, 0, 200, 100), rect(0, 0, 100, 100))
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{
+    gfx::geometry::{Extent, Offset, Px},
+    gui::input::{ButtonState, Event, MouseButton},
+};
+
+use super::{
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// Reports what the mouse does to its child through callbacks, so that any
+/// widget can respond to clicks and hovering without a custom [`Widget`]
+/// implementation.
+///
+/// The child receives every event first, and is laid out and drawn as if it
+/// weren't wrapped.
+#[must_use]
+pub struct Interactive<W: Widget> {
+    widget_state: WidgetState,
+    pub child: W,
+    hovered: bool,
+    /// The button that was pressed over the widget, if it hasn't been released
+    /// since.
+    pressed: Option<MouseButton>,
+    on_click: Option<Box<dyn FnMut(MouseButton)>>,
+    on_hover: Option<Box<dyn FnMut(bool)>>,
+    on_press: Option<Box<dyn FnMut(MouseButton)>>,
+    on_release: Option<Box<dyn FnMut(MouseButton)>>,
+}
+
+impl<W: Widget> Interactive<W> {
+    pub fn new(child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            child,
+            hovered: false,
+            pressed: None,
+            on_click: None,
+            on_hover: None,
+            on_press: None,
+            on_release: None,
+        }
+    }
+
+    /// Calls `callback` when a mouse button is pressed and released over the
+    /// widget.
+    pub fn with_on_click(mut self, callback: impl FnMut(MouseButton) + 'static) -> Self {
+        self.on_click = Some(Box::new(callback));
+        self
+    }
+
+    /// Calls `callback` with `true` when the cursor moves onto the widget, and
+    /// with `false` when it moves off of it.
+    pub fn with_on_hover(mut self, callback: impl FnMut(bool) + 'static) -> Self {
+        self.on_hover = Some(Box::new(callback));
+        self
+    }
+
+    /// Calls `callback` when a mouse button is pressed over the widget.
+    pub fn with_on_press(mut self, callback: impl FnMut(MouseButton) + 'static) -> Self {
+        self.on_press = Some(Box::new(callback));
+        self
+    }
+
+    /// Calls `callback` when a mouse button that was pressed over the widget
+    /// is released, wherever the cursor is.
+    pub fn with_on_release(mut self, callback: impl FnMut(MouseButton) + 'static) -> Self {
+        self.on_release = Some(Box::new(callback));
+        self
+    }
+
+    /// Checks if the cursor is over the widget.
+    #[must_use]
+    pub fn is_hovered(&self) -> bool {
+        self.hovered
+    }
+
+    /// Checks if a mouse button was pressed over the widget and is still held.
+    #[must_use]
+    pub fn is_pressed(&self) -> bool {
+        self.pressed.is_some()
+    }
+}
+
+impl<W: Widget> Widget for Interactive<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(&mut self.child);
+
+        let is_over = self
+            .widget_state
+            .rect()
+            .contains_point(context.cursor_position());
+
+        match context.event() {
+            Event::CursorMove { .. } if is_over != self.hovered => {
+                self.hovered = is_over;
+                if let Some(on_hover) = &mut self.on_hover {
+                    on_hover(is_over);
+                }
+            }
+            Event::MouseButton {
+                button,
+                state: ButtonState::Pressed,
+            } if is_over && self.pressed.is_none() => {
+                self.pressed = Some(button);
+                if let Some(on_press) = &mut self.on_press {
+                    on_press(button);
+                }
+            }
+            Event::MouseButton {
+                button,
+                state: ButtonState::Released,
+            } if self.pressed == Some(button) => {
+                self.pressed = None;
+                if let Some(on_release) = &mut self.on_release {
+                    on_release(button);
+                }
+                if is_over {
+                    if let Some(on_click) = &mut self.on_click {
+                        on_click(button);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = context.layout(&mut self.child, constraints);
+        context.position_widget(&mut self.child, Offset::zero(), extent);
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{
+        gfx::{
+            color::Color,
+            geometry::{Point, Px},
+        },
+        gui::{input::Input, widgets::debug::Fill},
+    };
+
+    use super::*;
+
+    #[test]
+    fn click_and_hover() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let (click_log, hover_log) = (log.clone(), log.clone());
+
+        let mut widget = Interactive::new(Fill::new(Color::WHITE))
+            .with_on_click(move |button| click_log.borrow_mut().push(format!("{button:?}")))
+            .with_on_hover(move |hovered| hover_log.borrow_mut().push(format!("{hovered}")));
+        widget.widget_state.set_layout(
+            Offset::zero(),
+            Extent {
+                width: Px(10),
+                height: Px(10),
+            },
+        );
+
+        let mut input = Input::default();
+        let send = |input: &mut Input, widget: &mut Interactive<Fill>| {
+            let mut context = UpdateContext::new(input);
+            context.update(widget);
+        };
+
+        input.update_cursor_position(Point::new(5, 5));
+        send(&mut input, &mut widget);
+        input.update_mouse_button(MouseButton::Left, ButtonState::Pressed);
+        send(&mut input, &mut widget);
+        input.update_mouse_button(MouseButton::Left, ButtonState::Released);
+        send(&mut input, &mut widget);
+
+        // Releasing outside of the widget doesn't click it.
+        input.update_mouse_button(MouseButton::Left, ButtonState::Pressed);
+        send(&mut input, &mut widget);
+        input.update_cursor_position(Point::new(20, 5));
+        send(&mut input, &mut widget);
+        input.update_mouse_button(MouseButton::Left, ButtonState::Released);
+        send(&mut input, &mut widget);
+
+        assert_eq!(*log.borrow(), ["true", "Left", "false"]);
+    }
+}
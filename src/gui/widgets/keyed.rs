@@ -0,0 +1,258 @@
+//! Stable keys for widgets, and the reconciliation pass that carries widget
+//! state across a rebuild of the widget tree.
+//!
+//! Rebuilding a tree after the data it shows changes would otherwise reset
+//! everything the user did to it, such as scrolling, selecting, or typing.
+//! [`reconcile`] copies that state from the widgets in the old tree to their
+//! counterparts in the new one. Widgets are matched by position, except for
+//! those wrapped in [`Keyed`], which are matched by key wherever they are in
+//! the tree.
+
+use std::{
+    any::Any,
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use crate::gfx::geometry::{Extent, Offset, Px};
+
+use super::{
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// Identifies a widget across rebuilds of the widget tree. Keys should be
+/// unique within a tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WidgetKey(u64);
+
+impl From<u64> for WidgetKey {
+    fn from(key: u64) -> Self {
+        Self(key)
+    }
+}
+
+impl From<&str> for WidgetKey {
+    fn from(key: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        Self(hasher.finish())
+    }
+}
+
+/// Gives its child a key, so that the child's state is restored to the widget
+/// with the same key when the tree is rebuilt, even if it moved.
+#[must_use]
+pub struct Keyed<W: Widget> {
+    widget_state: WidgetState,
+    key: WidgetKey,
+    pub child: W,
+}
+
+impl<W: Widget> Keyed<W> {
+    pub fn new(key: impl Into<WidgetKey>, child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            key: key.into(),
+            child,
+        }
+    }
+}
+
+impl<W: Widget> Widget for Keyed<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(&mut self.child);
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = context.layout(&mut self.child, constraints);
+        context.position_widget(&mut self.child, Offset::zero(), extent);
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+
+    fn key(&self) -> Option<WidgetKey> {
+        Some(self.key)
+    }
+}
+
+/// Copies the state of the widgets in `old` to the matching widgets in `new`,
+/// which is a rebuilt version of the same tree. See [`Widget::save_state`].
+///
+/// Widgets are first matched by their position among their parent's children,
+/// starting from the roots. Then, the widgets under each [`Keyed`] in `new`
+/// are given the state of those under the `Keyed` with the same key in `old`,
+/// matched by their order within it. Since keyed state is restored last, it
+/// takes precedence.
+///
+/// The keyboard focus is kept when the widget that had it is matched, since
+/// widgets save their focus identifiers as part of their state.
+pub fn reconcile(old: &mut dyn Widget, new: &mut dyn Widget) {
+    reconcile_by_position(old, new);
+
+    let mut saved = HashMap::new();
+    save_keyed(old, &mut saved);
+    restore_keyed(new, &mut saved);
+}
+
+fn reconcile_by_position(old: &mut dyn Widget, new: &mut dyn Widget) {
+    if let Some(state) = old.save_state() {
+        new.restore_state(state);
+    }
+
+    let mut old_children = Vec::new();
+    old.for_each_child_mut(&mut |child| old_children.push(child));
+
+    let mut old_children = old_children.into_iter();
+    new.for_each_child_mut(&mut |child| {
+        if let Some(old_child) = old_children.next() {
+            reconcile_by_position(old_child, child);
+        }
+    });
+}
+
+type SavedStates = Vec<Option<Box<dyn Any>>>;
+
+/// Saves the state of every widget under each keyed widget in the tree.
+fn save_keyed(widget: &mut dyn Widget, saved: &mut HashMap<WidgetKey, SavedStates>) {
+    if let Some(key) = widget.key() {
+        let mut states = Vec::new();
+        save_all(widget, &mut states);
+        saved.insert(key, states);
+    }
+
+    widget.for_each_child_mut(&mut |child| save_keyed(child, saved));
+}
+
+fn save_all(widget: &mut dyn Widget, states: &mut SavedStates) {
+    states.push(widget.save_state());
+    widget.for_each_child_mut(&mut |child| save_all(child, states));
+}
+
+fn restore_keyed(widget: &mut dyn Widget, saved: &mut HashMap<WidgetKey, SavedStates>) {
+    if let Some(states) = widget.key().and_then(|key| saved.remove(&key)) {
+        restore_all(widget, &mut states.into_iter());
+    }
+
+    widget.for_each_child_mut(&mut |child| restore_keyed(child, saved));
+}
+
+fn restore_all(widget: &mut dyn Widget, states: &mut impl Iterator<Item = Option<Box<dyn Any>>>) {
+    if let Some(Some(state)) = states.next() {
+        widget.restore_state(state);
+    }
+    widget.for_each_child_mut(&mut |child| restore_all(child, states));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gui::widgets::layout::Column;
+
+    use super::*;
+
+    /// A widget whose only state is a number.
+    #[derive(Default)]
+    struct Counter {
+        widget_state: WidgetState,
+        count: u32,
+    }
+
+    impl Widget for Counter {
+        fn widget_state(&self) -> &WidgetState {
+            &self.widget_state
+        }
+
+        fn widget_state_mut(&mut self) -> &mut WidgetState {
+            &mut self.widget_state
+        }
+
+        fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+        fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+        fn accept_update(&mut self, _context: &mut UpdateContext) -> PostUpdate {
+            PostUpdate::NoChange
+        }
+
+        fn accept_layout(&mut self, _: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+            constraints.max
+        }
+
+        fn accept_draw(&self, _canvas: &mut DrawContext, _extent: Extent) {}
+
+        fn save_state(&self) -> Option<Box<dyn Any>> {
+            Some(Box::new(self.count))
+        }
+
+        fn restore_state(&mut self, state: Box<dyn Any>) {
+            if let Ok(count) = state.downcast::<u32>() {
+                self.count = *count;
+            }
+        }
+    }
+
+    fn counter(count: u32) -> Counter {
+        Counter {
+            count,
+            ..Counter::default()
+        }
+    }
+
+    fn counts(tree: &mut dyn Widget) -> Vec<u32> {
+        let mut states = Vec::new();
+        save_all(tree, &mut states);
+        states
+            .into_iter()
+            .flatten()
+            .filter_map(|state| state.downcast::<u32>().ok().map(|count| *count))
+            .collect()
+    }
+
+    #[test]
+    fn restore_by_position_and_key() {
+        let mut old: Column<Box<dyn Widget>> = Column::with_children(vec![
+            Box::new(counter(1)),
+            Box::new(Keyed::new("a", counter(2))),
+            Box::new(Keyed::new("b", counter(3))),
+        ]);
+
+        // The keyed widgets swap places, and a new widget is added.
+        let mut new: Column<Box<dyn Widget>> = Column::with_children(vec![
+            Box::new(counter(0)),
+            Box::new(Keyed::new("b", counter(0))),
+            Box::new(Keyed::new("a", counter(0))),
+            Box::new(counter(0)),
+        ]);
+
+        reconcile(&mut old, &mut new);
+        assert_eq!(counts(&mut new), [1, 3, 2, 0]);
+    }
+}
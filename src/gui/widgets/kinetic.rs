@@ -0,0 +1,280 @@
+//! Scrolling physics for one axis of a scrolled view: easing toward where the
+//! mouse wheel was turned to, momentum after a trackpad or finger lets go, and
+//! a spring that pulls the content back after it was pulled past its ends.
+//!
+//! Positions are kept in fractions of a pixel, so that slow movements don't
+//! stall on rounding. Time only moves forward through [`Kinetic::advance`],
+//! which is called with the time of each frame.
+
+use std::time::{Duration, Instant};
+
+/// How quickly a wheel scroll eases toward where it's going. After this long,
+/// about two thirds of the distance has been covered.
+const WHEEL_TIME_CONSTANT: f32 = 0.05;
+
+/// How quickly content pulled past its ends springs back.
+const SPRING_TIME_CONSTANT: f32 = 0.08;
+
+/// How quickly momentum slows down, as a fraction lost per second.
+const FRICTION: f32 = 4.0;
+
+/// How quickly momentum slows down once it has carried the content past its
+/// ends, so that it bounces back instead of flying off.
+const OVERSCROLL_FRICTION: f32 = 30.0;
+
+/// The slowest that momentum keeps moving, in pixels per second.
+const MIN_VELOCITY: f32 = 10.0;
+
+/// How far the content can be pulled past either of its ends, in pixels.
+const MAX_OVERSCROLL: f32 = 80.0;
+
+/// How long after the last precise scroll the drag is taken to have ended, so
+/// that momentum takes over. Trackpads don't report when the fingers lift.
+const RELEASE_DELAY: Duration = Duration::from_millis(50);
+
+/// The longest frame that is simulated at once, so that a stalled frame
+/// doesn't throw the content a long way.
+const MAX_FRAME: f32 = 0.1;
+
+/// The scroll position along one axis, and how it is moving.
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct Kinetic {
+    position: f32,
+    /// Where a wheel scroll is easing toward, if one is.
+    target: Option<f32>,
+    /// In pixels per second.
+    velocity: f32,
+    /// Whether the content is following a trackpad or finger.
+    dragging: bool,
+    /// The distance dragged since the last frame.
+    dragged: f32,
+    /// The time since the last drag, while dragging.
+    since_drag: Duration,
+    /// When the last frame was, or `None` if the axis was at rest.
+    last_frame: Option<Instant>,
+}
+
+impl Kinetic {
+    /// The scroll position, which may be outside of `0..=max` while the
+    /// content is pulled past its ends.
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+
+    /// Checks if the position will change without any more input.
+    pub fn is_moving(&self) -> bool {
+        self.dragging || self.target.is_some() || self.velocity != 0.0
+    }
+
+    /// Moves straight to `position`, stopping any movement.
+    pub fn jump(&mut self, position: f32) {
+        *self = Self {
+            position,
+            ..Self::default()
+        };
+    }
+
+    /// Starts easing `delta` pixels further along than where the last wheel
+    /// scroll was going, staying within `0..=max`.
+    pub fn wheel(&mut self, delta: f32, max: f32) {
+        let from = self.target.unwrap_or_else(|| self.position.clamp(0.0, max));
+        self.target = Some((from + delta).clamp(0.0, max));
+        self.velocity = 0.0;
+        self.dragging = false;
+    }
+
+    /// Moves `delta` pixels along, following a trackpad or finger. The
+    /// content resists being pulled past its ends.
+    pub fn drag(&mut self, delta: f32, max: f32) {
+        let outward =
+            (self.position <= 0.0 && delta < 0.0) || (self.position >= max && delta > 0.0);
+        let delta = if outward {
+            let overscroll = overscroll(self.position, max).abs();
+            delta * 0.5 * (1.0 - overscroll / MAX_OVERSCROLL).max(0.0)
+        } else {
+            delta
+        };
+
+        self.position = (self.position + delta).clamp(-MAX_OVERSCROLL, max + MAX_OVERSCROLL);
+        self.target = None;
+        self.dragging = true;
+        self.dragged += delta;
+        self.since_drag = Duration::ZERO;
+    }
+
+    /// Simulates movement up to a frame at `now`, in content that can be
+    /// scrolled by `max` pixels. Returns `true` if the position changed.
+    pub fn advance(&mut self, now: Instant, max: f32) -> bool {
+        let before = self.position;
+        let elapsed = self
+            .last_frame
+            .replace(now)
+            .map_or(Duration::ZERO, |last| now.saturating_duration_since(last));
+        let dt = elapsed.as_secs_f32().min(MAX_FRAME);
+
+        if self.dragging {
+            // Velocity is measured from how far each frame was dragged, and
+            // smoothed so that one uneven frame doesn't decide it.
+            if self.dragged != 0.0 {
+                if dt > 0.0 {
+                    self.velocity = (self.velocity + self.dragged / dt) / 2.0;
+                }
+                self.dragged = 0.0;
+                self.since_drag = Duration::ZERO;
+            } else {
+                self.since_drag += elapsed;
+                if self.since_drag >= RELEASE_DELAY {
+                    self.dragging = false;
+                }
+            }
+
+            if self.dragging {
+                return false;
+            }
+        }
+
+        if let Some(target) = self.target {
+            self.position += (target - self.position) * (1.0 - (-dt / WHEEL_TIME_CONSTANT).exp());
+            if (target - self.position).abs() < 0.5 {
+                self.position = target;
+                self.target = None;
+            }
+        } else if self.velocity != 0.0 {
+            self.position += self.velocity * dt;
+            let friction = if overscroll(self.position, max) == 0.0 {
+                FRICTION
+            } else {
+                OVERSCROLL_FRICTION
+            };
+            self.velocity *= (-friction * dt).exp();
+
+            let limited = self.position.clamp(-MAX_OVERSCROLL, max + MAX_OVERSCROLL);
+            if limited != self.position || self.velocity.abs() < MIN_VELOCITY {
+                self.position = limited;
+                self.velocity = 0.0;
+            }
+        } else {
+            let overscroll = overscroll(self.position, max);
+            if overscroll != 0.0 {
+                let bound = self.position - overscroll;
+                let remaining = overscroll * (-dt / SPRING_TIME_CONSTANT).exp();
+                self.position = if remaining.abs() < 0.5 {
+                    bound
+                } else {
+                    bound + remaining
+                };
+            }
+        }
+
+        // The last frame is forgotten once the axis comes to rest, so that
+        // the pause isn't taken as one long frame when it moves again.
+        if !self.is_moving() && overscroll(self.position, max) == 0.0 {
+            self.last_frame = None;
+        }
+
+        self.position != before
+    }
+}
+
+/// How far `position` is past the ends of `0..=max`: negative before the
+/// start, positive after the end, and zero within it.
+fn overscroll(position: f32, max: f32) -> f32 {
+    if position < 0.0 {
+        position
+    } else if position > max {
+        position - max
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    /// Advances `kinetic` frame by frame until it comes to rest, returning
+    /// the number of frames that took.
+    fn settle(kinetic: &mut Kinetic, start: Instant, max: f32) -> u32 {
+        let mut frames = 0;
+        while kinetic.is_moving() || overscroll(kinetic.position(), max) != 0.0 {
+            frames += 1;
+            assert!(frames < 1000, "never came to rest");
+            kinetic.advance(start + FRAME * frames, max);
+        }
+        frames
+    }
+
+    #[test]
+    fn wheel_eases_to_target() {
+        let mut kinetic = Kinetic::default();
+        let start = Instant::now();
+
+        kinetic.wheel(48.0, 100.0);
+        kinetic.wheel(48.0, 100.0);
+        kinetic.advance(start, 100.0);
+        kinetic.advance(start + FRAME, 100.0);
+        assert!(kinetic.position() > 0.0 && kinetic.position() < 96.0);
+
+        settle(&mut kinetic, start, 100.0);
+        assert_eq!(kinetic.position(), 96.0);
+
+        // Wheel scrolls stop at the end of the content.
+        kinetic.wheel(48.0, 100.0);
+        settle(&mut kinetic, start, 100.0);
+        assert_eq!(kinetic.position(), 100.0);
+    }
+
+    #[test]
+    fn momentum_after_drag() {
+        let mut kinetic = Kinetic::default();
+        let start = Instant::now();
+
+        let mut now = start;
+        for _ in 0..5 {
+            kinetic.drag(8.0, 1000.0);
+            kinetic.advance(now, 1000.0);
+            now += FRAME;
+        }
+        assert_eq!(kinetic.position(), 40.0);
+
+        // The content keeps going after the drag stops, then slows to a halt.
+        settle(&mut kinetic, now, 1000.0);
+        assert!(kinetic.position() > 100.0);
+        assert!(!kinetic.is_moving());
+    }
+
+    #[test]
+    fn overscroll_springs_back() {
+        let mut kinetic = Kinetic::default();
+        let start = Instant::now();
+
+        // Pulling past the start is resisted.
+        kinetic.drag(-20.0, 100.0);
+        assert_eq!(kinetic.position(), -10.0);
+        for _ in 0..100 {
+            kinetic.drag(-20.0, 100.0);
+        }
+        assert!(kinetic.position() >= -MAX_OVERSCROLL);
+
+        // Pulling back in is not.
+        let position = kinetic.position();
+        kinetic.drag(5.0, 100.0);
+        assert_eq!(kinetic.position(), position + 5.0);
+
+        kinetic.advance(start, 100.0);
+        settle(&mut kinetic, start, 100.0);
+        assert_eq!(kinetic.position(), 0.0);
+    }
+
+    #[test]
+    fn jump_stops_movement() {
+        let mut kinetic = Kinetic::default();
+        kinetic.wheel(48.0, 100.0);
+        kinetic.jump(20.0);
+        assert!(!kinetic.is_moving());
+        assert!(!kinetic.advance(Instant::now(), 100.0));
+        assert_eq!(kinetic.position(), 20.0);
+    }
+}
@@ -0,0 +1,472 @@
+use std::{any::Any, ops::Range};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::{
+        accessibility::{AccessNode, Role},
+        input::{ButtonState, Event, FocusId, MouseButton},
+    },
+    shell::{
+        event::{Key, Modifiers},
+        CursorIcon,
+    },
+    text::{RichLayout, RichText, TextStyle},
+};
+
+use super::{
+    editor::{next_char, next_word, previous_char, previous_word},
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+type LinkFn = Box<dyn FnMut(&str)>;
+
+const LINK: Color = Color {
+    r: 0.0,
+    g: 0.3,
+    b: 0.8,
+    a: 1.0,
+};
+
+const SELECTION: Color = Color {
+    r: 0.6,
+    g: 0.8,
+    b: 1.0,
+    a: 1.0,
+};
+
+/// Text that can't be edited, wrapped to the width of the widget.
+///
+/// The text can be plain, or [`RichText`] whose spans each have their own
+/// font, size, weight, color, or link. Links are underlined and drawn in
+/// [`Self::link_color`] unless their span has a color, and clicking one calls
+/// the function given to [`Self::with_on_link`] with where it links to.
+///
+/// Labels made selectable with [`Self::with_selectable`] take the keyboard
+/// focus when clicked, and their text can be selected by dragging the mouse
+/// across it, or with Shift and the arrow keys, Home, and End. Control+A
+/// selects all of the text, and Control+C copies the selection to the system
+/// clipboard.
+#[must_use]
+pub struct Label {
+    widget_state: WidgetState,
+    text: RichText,
+    style: TextStyle,
+    pub color: Color,
+    pub link_color: Color,
+    on_link: Option<LinkFn>,
+    /// The span of the link that the mouse was pressed on.
+    pressed_link: Option<usize>,
+    selectable: bool,
+    focus: FocusId,
+    /// The end of the selection that moves. Nothing is selected when this is
+    /// equal to `anchor`.
+    caret: usize,
+    anchor: usize,
+    is_selecting: bool,
+    // Computed during layout.
+    layout: RichLayout,
+}
+
+impl Label {
+    pub fn new(text: impl Into<RichText>, style: TextStyle) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            text: text.into(),
+            style,
+            color: Color::BLACK,
+            link_color: LINK,
+            on_link: None,
+            pressed_link: None,
+            selectable: false,
+            focus: FocusId::new(),
+            caret: 0,
+            anchor: 0,
+            is_selecting: false,
+            layout: RichLayout::default(),
+        }
+    }
+
+    /// Lets the text be selected and copied.
+    pub fn with_selectable(mut self, selectable: bool) -> Self {
+        self.selectable = selectable;
+        self
+    }
+
+    /// Calls `callback` with where a link leads when it is clicked.
+    pub fn with_on_link(mut self, callback: impl FnMut(&str) + 'static) -> Self {
+        self.on_link = Some(Box::new(callback));
+        self
+    }
+
+    #[must_use]
+    pub fn text(&self) -> &RichText {
+        &self.text
+    }
+
+    pub fn set_text(&mut self, text: impl Into<RichText>) {
+        self.text = text.into();
+        self.pressed_link = None;
+        self.caret = 0;
+        self.anchor = 0;
+        self.is_selecting = false;
+        self.widget_state.set_needs_resize();
+    }
+
+    /// The identifier used to give a selectable label the keyboard focus.
+    #[must_use]
+    pub fn focus_id(&self) -> FocusId {
+        self.focus
+    }
+
+    /// The byte range of the selected text.
+    #[must_use]
+    pub fn selection(&self) -> Range<usize> {
+        self.caret.min(self.anchor)..self.caret.max(self.anchor)
+    }
+
+    #[must_use]
+    pub fn selected_text(&self) -> &str {
+        &self.text.text()[self.selection()]
+    }
+
+    /// Lays out the text, giving empty text the height of one line.
+    fn layout_text(&self, context: &LayoutContext, max_width: Option<f32>) -> RichLayout {
+        let mut layout = context.layout_rich_text(&self.text, &self.style, max_width);
+        if self.text.text().is_empty() {
+            layout.height = context.measure_text("", &self.style, None).line_height;
+        }
+        layout
+    }
+
+    /// Finds the span of the link at `point`, relative to the widget.
+    fn link_at(&self, point: Point) -> Option<usize> {
+        let (x, y) = (f32::from(point.x), f32::from(point.y));
+        let line = self
+            .layout
+            .lines
+            .iter()
+            .find(|line| line.top <= y && y < line.top + line.height)?;
+        let run = line
+            .runs
+            .iter()
+            .find(|run| run.x <= x && x < run.x + run.width)?;
+
+        self.text.spans()[run.span]
+            .attributes
+            .link
+            .is_some()
+            .then_some(run.span)
+    }
+
+    /// Finds the caret closest to `point`, in absolute coordinates.
+    fn offset_at(&self, point: Point) -> usize {
+        let origin = self.widget_state.origin();
+        let x = f32::from(point.x - origin.x);
+        let y = f32::from(point.y - origin.y);
+        self.layout
+            .line_at(y)
+            .and_then(|line| self.layout.caret_at(line, x))
+            .map_or(0, |caret| caret.offset)
+    }
+
+    /// Moves the caret to `offset`, extending the selection if `extend` is
+    /// set. Returns `true` if anything changed.
+    fn move_to(&mut self, offset: usize, extend: bool) -> bool {
+        let before = (self.caret, self.anchor);
+        self.caret = offset;
+        if !extend {
+            self.anchor = offset;
+        }
+        before != (self.caret, self.anchor)
+    }
+
+    /// Finds the caret closest to the caret's horizontal position on the line
+    /// `lines` away from it. Moving past the first or last line moves to the
+    /// start or end of the text.
+    fn vertical_offset(&self, lines: isize) -> usize {
+        let line = match self.layout.line_of(self.caret) {
+            Some(line) => line,
+            None => return self.caret,
+        };
+
+        let x = self.layout.lines[line].caret_x(self.caret);
+        let target = line as isize + lines;
+        if target < 0 {
+            0
+        } else if target as usize >= self.layout.lines.len() {
+            self.text.text().len()
+        } else {
+            self.layout
+                .caret_at(target as usize, x)
+                .map_or(self.caret, |caret| caret.offset)
+        }
+    }
+
+    fn handle_key(&mut self, context: &mut UpdateContext, key: Key, modifiers: Modifiers) -> bool {
+        let Modifiers { shift, control, .. } = modifiers;
+        let text = self.text.text();
+        let line = self
+            .layout
+            .line_of(self.caret)
+            .map(|line| self.layout.lines[line].range.clone());
+
+        // Without Shift, the selection collapses toward the key's direction.
+        let offset = match key {
+            Key::Left if control => previous_word(text, self.caret),
+            Key::Left if !shift && self.caret != self.anchor => self.selection().start,
+            Key::Left => previous_char(text, self.caret),
+            Key::Right if control => next_word(text, self.caret),
+            Key::Right if !shift && self.caret != self.anchor => self.selection().end,
+            Key::Right => next_char(text, self.caret),
+            Key::Up => self.vertical_offset(-1),
+            Key::Down => self.vertical_offset(1),
+            Key::Home if control => 0,
+            Key::End if control => text.len(),
+            Key::Home => line.map_or(0, |line| line.start),
+            Key::End => line.map_or(text.len(), |line| line.end),
+            Key::A if control => {
+                let end = text.len();
+                return self.move_to(0, false) | self.move_to(end, true);
+            }
+            Key::C if control => {
+                if !self.selected_text().is_empty() {
+                    context.set_clipboard_text(self.selected_text());
+                    context.set_handled();
+                }
+                return false;
+            }
+            Key::Escape => {
+                context.set_focus(None);
+                return true;
+            }
+            _ => return false,
+        };
+
+        self.move_to(offset, shift)
+    }
+
+    /// Handles a press or release of the left mouse button.
+    fn click(&mut self, context: &mut UpdateContext, state: ButtonState) -> PostUpdate {
+        let origin = self.widget_state.origin();
+        let is_inside = self
+            .widget_state
+            .rect()
+            .contains_point(context.cursor_position());
+        let link = is_inside
+            .then(|| self.link_at(Point::zero() + (context.cursor_position() - origin)))
+            .flatten();
+
+        match state {
+            ButtonState::Pressed => {
+                self.pressed_link = link;
+                if link.is_some() {
+                    context.set_handled();
+                } else if self.selectable && is_inside {
+                    context.set_focus(Some(self.focus));
+                    let offset = self.offset_at(context.cursor_position());
+                    self.move_to(offset, false);
+                    self.is_selecting = true;
+                    return PostUpdate::NeedsRedraw;
+                }
+            }
+            ButtonState::Released => {
+                self.is_selecting = false;
+
+                // Links are followed if the mouse is released on the link it
+                // was pressed on.
+                let pressed = self.pressed_link.take();
+                let target = match link {
+                    Some(span) if pressed == Some(span) => {
+                        self.text.spans()[span].attributes.link.as_deref()
+                    }
+                    _ => None,
+                };
+                if let (Some(on_link), Some(target)) = (&mut self.on_link, target) {
+                    on_link(target);
+                    context.set_handled();
+                }
+            }
+        }
+
+        PostUpdate::NoChange
+    }
+}
+
+/// The state of a selectable [`Label`] that is carried over when the widget
+/// tree is rebuilt.
+struct SavedState {
+    focus: FocusId,
+    caret: usize,
+    anchor: usize,
+}
+
+impl Widget for Label {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        match context.event() {
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state,
+            } => self.click(context, state),
+            Event::CursorMove { position }
+                if self.is_selecting
+                    && context.is_focused(self.focus)
+                    && context.mouse_button_state(MouseButton::Left).is_pressed() =>
+            {
+                let offset = self.offset_at(position);
+                if self.move_to(offset, true) {
+                    PostUpdate::NeedsRedraw
+                } else {
+                    PostUpdate::NoChange
+                }
+            }
+            Event::KeyPress { key, modifiers }
+                if self.selectable && context.is_focused(self.focus) =>
+            {
+                if self.handle_key(context, key, modifiers) {
+                    PostUpdate::NeedsRedraw
+                } else {
+                    PostUpdate::NoChange
+                }
+            }
+            _ => PostUpdate::NoChange,
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        self.layout = self.layout_text(context, Some(f32::from(constraints.max().width)));
+        constraints.max_fit(Extent {
+            width: Px::from(self.layout.width.ceil()),
+            height: Px::from(self.layout.height.ceil()),
+        })
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, _height: Px) -> Px {
+        // The narrowest a label can be is as wide as its widest word.
+        let max_width = match size {
+            IntrinsicSize::Min => Some(0.0),
+            IntrinsicSize::Max => None,
+        };
+        Px::from(self.layout_text(context, max_width).width.ceil())
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, _size: IntrinsicSize, width: Px) -> Px {
+        Px::from(
+            self.layout_text(context, Some(f32::from(width)))
+                .height
+                .ceil(),
+        )
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        let selection = if self.selectable && canvas.is_focused(self.focus) {
+            self.selection()
+        } else {
+            0..0
+        };
+
+        for line in &self.layout.lines {
+            // Selected line breaks are shown as a little extra space after
+            // the end of the line.
+            let start = selection.start.max(line.range.start);
+            let end = selection.end.min(line.range.end);
+            let past_end = selection.end > line.range.end && selection.start <= line.range.end;
+            if start < end || past_end {
+                let a = line.caret_x(start);
+                let b = line.caret_x(end);
+                let extra = if past_end { self.style.size / 4.0 } else { 0.0 };
+                canvas.draw_rect(
+                    Rect {
+                        left: Px::from(a.min(b).round()),
+                        top: Px::from(line.top.round()),
+                        right: Px::from((a.max(b) + extra).round()),
+                        bottom: Px::from((line.top + line.height).round()),
+                    },
+                    &Paint::Fill { color: SELECTION },
+                );
+            }
+
+            let baseline = Px::from(line.baseline.round());
+            for run in &line.runs {
+                let attributes = &self.text.spans()[run.span].attributes;
+                let color = match (attributes.color, &attributes.link) {
+                    (Some(color), _) => color,
+                    (None, Some(_)) => self.link_color,
+                    (None, None) => self.color,
+                };
+
+                let x = Px::from(run.x.round());
+                canvas.draw_text(&run.shaped, Point::new(x, baseline), run.style.size, color);
+
+                if attributes.link.is_some() {
+                    let end = Px::from((run.x + run.width).round());
+                    let y = baseline + Px(2);
+                    canvas.stroke_line(
+                        Point::new(x, y),
+                        Point::new(end, y),
+                        &Stroke::solid(color, 1.0),
+                    );
+                }
+            }
+        }
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        self.selectable.then(|| {
+            Box::new(SavedState {
+                focus: self.focus,
+                caret: self.caret,
+                anchor: self.anchor,
+            }) as Box<dyn Any>
+        })
+    }
+
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        if let Ok(state) = state.downcast::<SavedState>() {
+            self.focus = state.focus;
+            // The selection is dropped if the text has changed under it.
+            let text = self.text.text();
+            if [state.caret, state.anchor]
+                .iter()
+                .all(|&offset| text.is_char_boundary(offset))
+            {
+                self.caret = state.caret;
+                self.anchor = state.anchor;
+            }
+        }
+    }
+
+    fn accessibility(&self) -> Option<AccessNode> {
+        let node = AccessNode::new(Role::Label).with_name(self.text.text());
+        Some(if self.selectable {
+            node.with_focus(self.focus)
+        } else {
+            node
+        })
+    }
+
+    fn cursor(&self, point: Point) -> Option<CursorIcon> {
+        let origin = self.widget_state.origin();
+        match self.link_at(Point::zero() + (point - origin)) {
+            Some(_) => Some(CursorIcon::Hand),
+            None if self.selectable => Some(CursorIcon::Text),
+            None => None,
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use crate::{
-    gfx::geometry::{Extent, Offset, Px},
+    gfx::geometry::{Extent, Insets, Offset, Px},
     gui::input::Event,
 };
 
 use super::{
-    BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext, Widget, WidgetState,
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
 };
 
 #[must_use]
@@ -31,6 +32,10 @@ impl<W: Widget + 'static> Widget for Center<W> {
         &mut self.widget_state
     }
 
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
     fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
         f(&mut self.child);
     }
@@ -43,14 +48,22 @@ impl<W: Widget + 'static> Widget for Center<W> {
     fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
         let child_extent = context.layout(&mut self.child, constraints);
         let child_offset = Offset {
-            x: (constraints.max.width - child_extent.width) / 2,
-            y: (constraints.max.height - child_extent.height) / 2,
+            x: constraints.max.width.saturating_sub(child_extent.width) / 2,
+            y: constraints.max.height.saturating_sub(child_extent.height) / 2,
         };
         context.position_widget(&mut self.child, child_offset, child_extent);
 
         constraints.max
     }
 
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
     fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
         canvas.draw(&self.child);
     }
@@ -114,6 +127,12 @@ impl<W: Widget> Widget for Column<W> {
         &mut self.widget_state
     }
 
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        for child in &self.children {
+            f(child);
+        }
+    }
+
     fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
         for child in &mut self.children {
             f(child);
@@ -132,7 +151,7 @@ impl<W: Widget> Widget for Column<W> {
                     }
                 }
             }
-            Event::MouseButton { .. } => {
+            Event::MouseButton { .. } | Event::Scroll { .. } => {
                 for child in &mut self.children {
                     // If the child handles the event, there's no need to pass
                     // it to the next child.
@@ -141,6 +160,16 @@ impl<W: Widget> Widget for Column<W> {
                     }
                 }
             }
+            Event::KeyPress { .. }
+            | Event::Text { .. }
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => {
+                for child in &mut self.children {
+                    context.update(child);
+                }
+            }
         }
 
         if self.needs_layout {
@@ -161,10 +190,10 @@ impl<W: Widget> Widget for Column<W> {
             // reduce the available height
             let child_constraints = BoxConstraint {
                 min: Extent::zero(),
-                max: Extent {
-                    width: constraints.max.width,
-                    height: constraints.max.height - advancing_y,
-                },
+                max: constraints.max.saturating_sub(Extent {
+                    width: Px(0),
+                    height: advancing_y,
+                }),
             };
 
             let child_extent = context.layout(child, child_constraints);
@@ -178,7 +207,9 @@ impl<W: Widget> Widget for Column<W> {
             );
 
             // advance to the next widget's position
-            advancing_y += child_extent.height + self.spacing;
+            advancing_y = advancing_y
+                .saturating_add(child_extent.height)
+                .saturating_add(self.spacing);
             max_width = max_width.max(child_extent.width);
         }
 
@@ -195,10 +226,23 @@ impl<W: Widget> Widget for Column<W> {
         }
     }
 
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, _height: Px) -> Px {
+        self.children
+            .iter()
+            .map(|child| child.intrinsic_width(context, size, Px::MAX))
+            .max()
+            .unwrap_or(Px(0))
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        let spacing = self.spacing * self.children.len().saturating_sub(1) as f32;
+        self.children.iter().fold(spacing, |height, child| {
+            height.saturating_add(child.intrinsic_height(context, size, width))
+        })
+    }
+
     fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
-        for child in &self.children {
-            canvas.draw(child);
-        }
+        canvas.draw_children(self);
     }
 }
 
@@ -228,6 +272,10 @@ impl<W: Widget> Widget for SizedBox<W> {
         &mut self.widget_state
     }
 
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
     fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
         f(&mut self.child);
     }
@@ -247,7 +295,709 @@ impl<W: Widget> Widget for SizedBox<W> {
         self.extent
     }
 
+    fn intrinsic_width(&self, _context: &LayoutContext, _size: IntrinsicSize, _height: Px) -> Px {
+        self.extent.width
+    }
+
+    fn intrinsic_height(&self, _context: &LayoutContext, _size: IntrinsicSize, _width: Px) -> Px {
+        self.extent.height
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+}
+
+/// Leaves space around a widget, such as to keep it from touching the edges of
+/// its parent or its siblings.
+#[must_use]
+pub struct Padding<W: Widget> {
+    widget_state: WidgetState,
+    pub insets: Insets,
+    pub child: W,
+}
+
+impl<W: Widget> Padding<W> {
+    pub fn new(insets: Insets, child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            insets,
+            child,
+        }
+    }
+
+    /// Leaves the same amount of space on every side of the child.
+    pub fn uniform(px: Px, child: W) -> Self {
+        Self::new(Insets::uniform(px), child)
+    }
+}
+
+impl<W: Widget> Widget for Padding<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(&mut self.child);
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let child_extent = context.layout(&mut self.child, constraints.deflate(self.insets));
+        let child_offset = Offset {
+            x: self.insets.left,
+            y: self.insets.top,
+        };
+        context.position_widget(&mut self.child, child_offset, child_extent);
+
+        constraints.max_fit(child_extent.saturating_add(self.insets.extent()))
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        let insets = self.insets.extent();
+        let height = height.saturating_sub(insets.height).max(Px(0));
+        self.child
+            .intrinsic_width(context, size, height)
+            .saturating_add(insets.width)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        let insets = self.insets.extent();
+        let width = width.saturating_sub(insets.width).max(Px(0));
+        self.child
+            .intrinsic_height(context, size, width)
+            .saturating_add(insets.height)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+}
+
+/// Where a widget is placed within the space available to it, as fractions of
+/// the space left over along each axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Alignment {
+    /// From 0 at the left to 1 at the right.
+    pub x: f32,
+    /// From 0 at the top to 1 at the bottom.
+    pub y: f32,
+}
+
+impl Alignment {
+    pub const TOP_LEFT: Self = Self { x: 0.0, y: 0.0 };
+    pub const TOP: Self = Self { x: 0.5, y: 0.0 };
+    pub const TOP_RIGHT: Self = Self { x: 1.0, y: 0.0 };
+    pub const LEFT: Self = Self { x: 0.0, y: 0.5 };
+    pub const CENTER: Self = Self { x: 0.5, y: 0.5 };
+    pub const RIGHT: Self = Self { x: 1.0, y: 0.5 };
+    pub const BOTTOM_LEFT: Self = Self { x: 0.0, y: 1.0 };
+    pub const BOTTOM: Self = Self { x: 0.5, y: 1.0 };
+    pub const BOTTOM_RIGHT: Self = Self { x: 1.0, y: 1.0 };
+
+    /// The offset of something of size `inner` aligned within `outer`.
+    pub fn offset(&self, inner: Extent, outer: Extent) -> Offset {
+        let space = outer.saturating_sub(inner);
+        Offset {
+            x: Px::from((f32::from(space.width) * self.x).round()),
+            y: Px::from((f32::from(space.height) * self.y).round()),
+        }
+    }
+}
+
+/// Places a widget at its preferred size within all of the space available to
+/// it. [`Center`] is the same as aligning to [`Alignment::CENTER`].
+#[must_use]
+pub struct Align<W: Widget> {
+    widget_state: WidgetState,
+    pub alignment: Alignment,
+    pub child: W,
+}
+
+impl<W: Widget> Align<W> {
+    pub fn new(alignment: Alignment, child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            alignment,
+            child,
+        }
+    }
+}
+
+impl<W: Widget> Widget for Align<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(&mut self.child);
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let child_extent = context.layout(&mut self.child, BoxConstraint::loose(constraints.max));
+        let child_offset = self.alignment.offset(child_extent, constraints.max);
+        context.position_widget(&mut self.child, child_offset, child_extent);
+
+        constraints.max
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+}
+
+/// Adds constraints to the size of a widget, such as a minimum width for a
+/// button or a maximum height for a list. The constraints are narrowed to fit
+/// within those given by the parent.
+#[must_use]
+pub struct ConstrainedBox<W: Widget> {
+    widget_state: WidgetState,
+    pub constraints: BoxConstraint,
+    pub child: W,
+}
+
+impl<W: Widget> ConstrainedBox<W> {
+    pub fn new(constraints: BoxConstraint, child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            constraints,
+            child,
+        }
+    }
+}
+
+impl<W: Widget> Widget for ConstrainedBox<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(&mut self.child);
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let child_constraints = constraints.constrain(self.constraints);
+        let child_extent = context.layout(&mut self.child, child_constraints);
+        let child_extent = child_constraints.max_fit(child_extent);
+        context.position_widget(&mut self.child, Offset::zero(), child_extent);
+
+        child_extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child
+            .intrinsic_width(context, size, height)
+            .clamp(self.constraints.min.width, self.constraints.max.width)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child
+            .intrinsic_height(context, size, width)
+            .clamp(self.constraints.min.height, self.constraints.max.height)
+    }
+
     fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
         canvas.draw(&self.child);
     }
 }
+
+/// Sizes a widget to the width of its content rather than to the space given
+/// to it, such as a button that hugs its label in a stretched layout. See
+/// [`Widget::intrinsic_width`].
+#[must_use]
+pub struct IntrinsicWidth<W: Widget> {
+    widget_state: WidgetState,
+    pub child: W,
+}
+
+impl<W: Widget> IntrinsicWidth<W> {
+    pub fn new(child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            child,
+        }
+    }
+}
+
+impl<W: Widget> Widget for IntrinsicWidth<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(&mut self.child);
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let width = self
+            .child
+            .intrinsic_width(context, IntrinsicSize::Max, constraints.max.height)
+            .clamp(constraints.min.width, constraints.max.width);
+        let child_constraints = BoxConstraint {
+            min: Extent {
+                width,
+                height: constraints.min.height,
+            },
+            max: Extent {
+                width,
+                height: constraints.max.height,
+            },
+        };
+
+        let child_extent = context.layout(&mut self.child, child_constraints);
+        let child_extent = child_constraints.max_fit(child_extent);
+        context.position_widget(&mut self.child, Offset::zero(), child_extent);
+        child_extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, _size: IntrinsicSize, height: Px) -> Px {
+        self.child
+            .intrinsic_width(context, IntrinsicSize::Max, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+}
+
+/// Where a child of a [`Stack`] is placed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StackPosition {
+    /// Aligned within the stack.
+    Aligned(Alignment),
+    /// At an offset from the top-left corner of the stack. Positioned children
+    /// don't affect the size of the stack.
+    At(Offset),
+}
+
+impl From<Alignment> for StackPosition {
+    fn from(alignment: Alignment) -> Self {
+        Self::Aligned(alignment)
+    }
+}
+
+impl From<Offset> for StackPosition {
+    fn from(offset: Offset) -> Self {
+        Self::At(offset)
+    }
+}
+
+/// Layers its children on top of each other, such as for a badge on an icon
+/// or a background behind some content.
+///
+/// Later children are drawn above earlier ones, unless their z-indices say
+/// otherwise (see [`Widget::z_index`]). The stack is as large as its largest
+/// aligned child, and every child is laid out at its own size within that of
+/// the stack.
+#[must_use]
+pub struct Stack<W: Widget> {
+    widget_state: WidgetState,
+    children: Vec<(StackPosition, W)>,
+    needs_layout: bool,
+}
+
+impl<W: Widget> Stack<W> {
+    pub fn new() -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            children: Vec::new(),
+            needs_layout: false,
+        }
+    }
+
+    pub fn with_child(mut self, position: impl Into<StackPosition>, child: W) -> Self {
+        self.children.push((position.into(), child));
+        self
+    }
+
+    pub fn add(&mut self, position: impl Into<StackPosition>, child: W) {
+        self.children.push((position.into(), child));
+        self.needs_layout = true;
+    }
+
+    pub fn remove(&mut self, index: usize) -> W {
+        self.needs_layout = true;
+        self.children.remove(index).1
+    }
+
+    /// Moves the child at `index`, such as to follow the cursor.
+    pub fn set_position(&mut self, index: usize, position: impl Into<StackPosition>) {
+        self.children[index].0 = position.into();
+        self.needs_layout = true;
+    }
+}
+
+impl<W: Widget> Default for Stack<W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Widget> Widget for Stack<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        for (_, child) in &self.children {
+            f(child);
+        }
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        for (_, child) in &mut self.children {
+            f(child);
+        }
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        match context.event() {
+            Event::KeyPress { .. }
+            | Event::Text { .. }
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. }
+            | Event::None => {
+                for (_, child) in &mut self.children {
+                    context.update(child);
+                }
+            }
+            Event::CursorMove { .. } | Event::MouseButton { .. } | Event::Scroll { .. } => {
+                for (_, child) in &mut self.children {
+                    if context.cursor_position().within(&context.bound_of(child)) {
+                        context.update(child);
+                    }
+                }
+            }
+        }
+
+        if self.needs_layout {
+            self.needs_layout = false;
+            PostUpdate::NeedsLayout
+        } else {
+            PostUpdate::NoChange
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let mut extents = Vec::with_capacity(self.children.len());
+        let mut largest = Extent::zero();
+        for (position, child) in &mut self.children {
+            let max = match position {
+                StackPosition::Aligned(_) => constraints.max,
+                StackPosition::At(offset) => constraints.max.saturating_sub(Extent {
+                    width: offset.x,
+                    height: offset.y,
+                }),
+            };
+            let extent = context.layout(child, BoxConstraint::loose(max));
+            if let StackPosition::Aligned(_) = position {
+                largest = Extent {
+                    width: largest.width.max(extent.width),
+                    height: largest.height.max(extent.height),
+                };
+            }
+            extents.push(extent);
+        }
+
+        let extent = constraints.max_fit(largest);
+        for ((position, child), child_extent) in self.children.iter_mut().zip(extents) {
+            let offset = match position {
+                StackPosition::Aligned(alignment) => alignment.offset(child_extent, extent),
+                StackPosition::At(offset) => *offset,
+            };
+            context.position_widget(child, offset, child_extent);
+        }
+
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.children
+            .iter()
+            .filter(|(position, _)| matches!(position, StackPosition::Aligned(_)))
+            .map(|(_, child)| child.intrinsic_width(context, size, height))
+            .max()
+            .unwrap_or(Px(0))
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.children
+            .iter()
+            .filter(|(position, _)| matches!(position, StackPosition::Aligned(_)))
+            .map(|(_, child)| child.intrinsic_height(context, size, width))
+            .max()
+            .unwrap_or(Px(0))
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw_children(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::{color::Color, geometry::ScaleFactor},
+        gui::widgets::debug::Fill,
+    };
+
+    use super::*;
+
+    fn extent(width: i16, height: i16) -> Extent {
+        Extent {
+            width: Px(width),
+            height: Px(height),
+        }
+    }
+
+    #[test]
+    fn combinators() {
+        let mut context = LayoutContext::default();
+        let constraints = BoxConstraint::exact(extent(100, 100));
+
+        // A box at most 40x20 in the bottom right, 10px from the edges.
+        let mut tree = Padding::uniform(
+            Px(10),
+            Align::new(
+                Alignment::BOTTOM_RIGHT,
+                ConstrainedBox::new(
+                    BoxConstraint::new(extent(10, 10), extent(40, 20)),
+                    Fill::new(Color::RED),
+                ),
+            ),
+        );
+
+        assert_eq!(context.layout(&mut tree, constraints), extent(100, 100));
+        let align = &tree.child;
+        assert_eq!(
+            align.widget_state().offset(),
+            Offset {
+                x: Px(10),
+                y: Px(10)
+            }
+        );
+        assert_eq!(align.widget_state().extent(), extent(80, 80));
+        let sized = &align.child;
+        assert_eq!(
+            sized.widget_state().offset(),
+            Offset {
+                x: Px(40),
+                y: Px(60)
+            }
+        );
+        assert_eq!(sized.widget_state().extent(), extent(40, 20));
+    }
+
+    #[test]
+    fn stack() {
+        let mut context = LayoutContext::default();
+        let sized = |width, height| SizedBox::new(extent(width, height), Fill::new(Color::RED));
+
+        let mut stack = Stack::new()
+            .with_child(Alignment::CENTER, sized(60, 40))
+            .with_child(Alignment::TOP_RIGHT, sized(10, 10))
+            .with_child(
+                Offset {
+                    x: Px(5),
+                    y: Px(30),
+                },
+                sized(80, 80),
+            );
+
+        // Positioned children don't make the stack any larger.
+        let size = context.layout(&mut stack, BoxConstraint::loose(extent(100, 100)));
+        assert_eq!(size, extent(60, 40));
+
+        let offsets: Vec<_> = stack
+            .children
+            .iter()
+            .map(|(_, child)| child.widget_state().offset())
+            .collect();
+        assert_eq!(offsets[0], Offset::zero());
+        assert_eq!(
+            offsets[1],
+            Offset {
+                x: Px(50),
+                y: Px(0)
+            }
+        );
+        assert_eq!(
+            offsets[2],
+            Offset {
+                x: Px(5),
+                y: Px(30)
+            }
+        );
+    }
+
+    #[test]
+    fn intrinsic_sizes() {
+        let mut context = LayoutContext::default();
+        let sized = |width, height| SizedBox::new(extent(width, height), Fill::new(Color::RED));
+
+        let column = Padding::uniform(
+            Px(5),
+            Column::with_children(vec![sized(30, 10), sized(50, 10)]),
+        );
+        assert_eq!(
+            column.intrinsic_width(&context, IntrinsicSize::Max, Px(100)),
+            Px(60)
+        );
+        assert_eq!(
+            column.intrinsic_height(&context, IntrinsicSize::Min, Px(100)),
+            Px(34)
+        );
+
+        // The background hugs the content instead of stretching.
+        let mut hug = IntrinsicWidth::new(
+            Stack::new()
+                .with_child(
+                    Alignment::CENTER,
+                    Box::new(Fill::new(Color::RED)) as Box<dyn Widget>,
+                )
+                .with_child(Alignment::CENTER, Box::new(sized(30, 10))),
+        );
+        let size = context.layout(&mut hug, BoxConstraint::loose(extent(100, 20)));
+        assert_eq!(size, extent(30, 20));
+    }
+
+    /// Counts how many times it is laid out.
+    struct Counter {
+        widget_state: WidgetState,
+        layouts: std::rc::Rc<std::cell::Cell<u32>>,
+    }
+
+    impl Widget for Counter {
+        fn widget_state(&self) -> &WidgetState {
+            &self.widget_state
+        }
+
+        fn widget_state_mut(&mut self) -> &mut WidgetState {
+            &mut self.widget_state
+        }
+
+        fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+        fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+        fn accept_update(&mut self, _context: &mut UpdateContext) -> PostUpdate {
+            PostUpdate::NoChange
+        }
+
+        fn accept_layout(&mut self, _: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+            self.layouts.set(self.layouts.get() + 1);
+            constraints.max_fit(extent(10, 10))
+        }
+
+        fn accept_draw(&self, _canvas: &mut DrawContext, _extent: Extent) {}
+    }
+
+    #[test]
+    fn layout_cache() {
+        let mut context = LayoutContext::default();
+        let layouts = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = || Counter {
+            widget_state: WidgetState::default(),
+            layouts: layouts.clone(),
+        };
+
+        let mut tree = Padding::uniform(Px(0), Column::new());
+        tree.child.add(counter());
+        tree.child.add(counter());
+
+        let window = extent(100, 100);
+        let _ = context.begin(&mut tree, window);
+        assert_eq!(layouts.get(), 2);
+
+        // Nothing changed.
+        let _ = context.begin(&mut tree, window);
+        assert_eq!(layouts.get(), 2);
+
+        // The column is laid out again, but gives its children the same
+        // constraints as before.
+        tree.child.widget_state.set_needs_layout();
+        let _ = context.begin(&mut tree, window);
+        assert_eq!(layouts.get(), 2);
+
+        tree.child.children[1].widget_state.set_needs_layout();
+        let _ = context.begin(&mut tree, window);
+        assert_eq!(layouts.get(), 3);
+
+        // Different constraints.
+        let _ = context.begin(&mut tree, extent(100, 50));
+        assert_eq!(layouts.get(), 5);
+
+        context.set_scale_factor(ScaleFactor(2.0));
+        let _ = context.layout(&mut tree, BoxConstraint::exact(extent(100, 50)));
+        assert_eq!(layouts.get(), 7);
+    }
+}
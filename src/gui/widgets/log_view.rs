@@ -0,0 +1,336 @@
+use std::{any::Any, ops::Range};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::{
+        input::Event,
+        logging::{Level, Log, LogFilter, Record},
+    },
+    text::{ShapedLine, TextStyle},
+};
+
+use super::{
+    virtual_list::{self, ScrollPosition},
+    BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext, Widget, WidgetState,
+};
+
+/// The space between the edges of the view and its text.
+const PADDING: Px = Px(4);
+
+const BACKGROUND: Color = Color {
+    r: 0.1,
+    g: 0.1,
+    b: 0.1,
+    a: 1.0,
+};
+
+const BORDER: Color = Color {
+    r: 0.3,
+    g: 0.3,
+    b: 0.3,
+    a: 1.0,
+};
+
+/// The color of a message's text.
+fn level_color(level: Level) -> Color {
+    match level {
+        Level::Error => Color {
+            r: 1.0,
+            g: 0.4,
+            b: 0.4,
+            a: 1.0,
+        },
+        Level::Warn => Color {
+            r: 1.0,
+            g: 0.8,
+            b: 0.3,
+            a: 1.0,
+        },
+        Level::Info => Color {
+            r: 0.9,
+            g: 0.9,
+            b: 0.9,
+            a: 1.0,
+        },
+        Level::Debug | Level::Trace => Color {
+            r: 0.6,
+            g: 0.6,
+            b: 0.6,
+            a: 1.0,
+        },
+    }
+}
+
+/// Shows the messages in a [`Log`], one per line, colored by their level.
+///
+/// Only the messages that [`LogFilter`] matches are shown, and only the lines
+/// that can be seen are laid out and drawn. While the view is scrolled to the
+/// bottom, it follows the log so that new messages can be seen as they are
+/// written. Scrolling up stops following, and scrolling back to the bottom
+/// starts again.
+///
+/// The view checks the log for new messages whenever observable state
+/// changes, which a log with an event loop proxy causes as messages arrive.
+/// See [`logging`](crate::gui::logging).
+#[must_use]
+pub struct LogView {
+    widget_state: WidgetState,
+    log: Log,
+    style: TextStyle,
+    filter: LogFilter,
+    /// The messages that the filter matched, as of `version`.
+    records: Vec<Record>,
+    version: u64,
+    /// How far the lines are scrolled up.
+    scroll: ScrollPosition,
+    /// Whether the view stays scrolled to the bottom as messages arrive.
+    follow: bool,
+    // Computed during layout.
+    row_height: Px,
+    ascent: Px,
+    visible: Range<usize>,
+    lines: Vec<ShapedLine>,
+}
+
+impl LogView {
+    pub fn new(log: Log, style: TextStyle) -> Self {
+        let filter = LogFilter::default();
+        Self {
+            widget_state: WidgetState::default(),
+            version: log.version(),
+            records: log.records(&filter),
+            log,
+            style,
+            filter,
+            scroll: ScrollPosition::TOP,
+            follow: true,
+            row_height: Px(0),
+            ascent: Px(0),
+            visible: 0..0,
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn with_filter(mut self, filter: LogFilter) -> Self {
+        self.set_filter(filter);
+        self
+    }
+
+    #[must_use]
+    pub fn filter(&self) -> &LogFilter {
+        &self.filter
+    }
+
+    /// Changes which messages are shown.
+    pub fn set_filter(&mut self, filter: LogFilter) {
+        self.filter = filter;
+        self.reload();
+    }
+
+    /// Checks if the view is following new messages.
+    #[must_use]
+    pub fn is_following(&self) -> bool {
+        self.follow
+    }
+
+    /// Scrolls to the bottom and follows new messages, or stays where it is.
+    pub fn set_following(&mut self, follow: bool) {
+        self.follow = follow;
+        self.widget_state.set_needs_layout();
+    }
+
+    /// Copies the messages from the log again.
+    fn reload(&mut self) {
+        self.version = self.log.version();
+        self.records = self.log.records(&self.filter);
+        self.widget_state.set_needs_layout();
+    }
+
+    fn viewport(&self) -> Px {
+        self.widget_state
+            .extent()
+            .height
+            .saturating_sub(PADDING + PADDING)
+    }
+}
+
+/// The state of a [`LogView`] that is carried over when the widget tree is
+/// rebuilt.
+struct SavedState {
+    filter: LogFilter,
+    scroll: ScrollPosition,
+    follow: bool,
+}
+
+impl Widget for LogView {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        match context.event() {
+            Event::Scroll { delta, .. }
+                if self
+                    .widget_state
+                    .rect()
+                    .contains_point(context.cursor_position()) =>
+            {
+                let max =
+                    virtual_list::max_scroll(self.viewport(), self.row_height, self.records.len());
+                let (scroll, follow) = scroll_by(self.scroll, delta.y, self.row_height, max);
+                self.follow = follow;
+                if scroll == self.scroll {
+                    PostUpdate::NoChange
+                } else {
+                    self.scroll = scroll;
+                    PostUpdate::NeedsLayout
+                }
+            }
+            _ => PostUpdate::NoChange,
+        }
+    }
+
+    fn accept_state_change(&mut self) -> PostUpdate {
+        if self.log.version() == self.version {
+            PostUpdate::NoChange
+        } else {
+            self.reload();
+            PostUpdate::NeedsLayout
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = constraints.max;
+
+        let metrics = context.measure_text("", &self.style, None);
+        self.row_height = Px::from(metrics.line_height.ceil());
+        self.ascent = Px::from(metrics.ascent.round());
+
+        let viewport = extent.height.saturating_sub(PADDING + PADDING);
+        let max = virtual_list::max_scroll(viewport, self.row_height, self.records.len());
+        self.scroll = if self.follow {
+            max
+        } else {
+            self.scroll.min(max)
+        };
+
+        self.visible =
+            virtual_list::visible_rows(self.scroll, viewport, self.row_height, self.records.len());
+        self.lines = self.records[self.visible.clone()]
+            .iter()
+            .map(|record| {
+                // Each message takes one line.
+                let text = record.to_string().replace('\n', " ");
+                context
+                    .measure_text(&text, &self.style, None)
+                    .lines
+                    .swap_remove(0)
+                    .shaped
+            })
+            .collect();
+
+        extent
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        let bounds = Rect::new(Point::zero(), extent);
+        canvas.draw_rect(bounds, &Paint::Fill { color: BACKGROUND });
+
+        canvas.push_clip_rounded_rect(
+            Rect {
+                top: PADDING,
+                bottom: extent.height - PADDING,
+                ..bounds
+            },
+            Px(0),
+        );
+        for (index, line) in self.visible.clone().zip(&self.lines) {
+            let top = PADDING + virtual_list::row_top(self.scroll, self.row_height, index);
+            canvas.draw_text(
+                line,
+                Point::new(PADDING, top + self.ascent),
+                self.style.size,
+                level_color(self.records[index].level),
+            );
+        }
+        canvas.pop_clip();
+
+        canvas.stroke_rect(bounds, &Stroke::solid(BORDER, 1.0));
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(SavedState {
+            filter: self.filter.clone(),
+            scroll: self.scroll,
+            follow: self.follow,
+        }))
+    }
+
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        if let Ok(state) = state.downcast::<SavedState>() {
+            self.set_filter(state.filter);
+            // Clamped to the new messages during layout.
+            self.scroll = state.scroll;
+            self.follow = state.follow;
+        }
+    }
+}
+
+/// Scrolls a view that can be scrolled down as far as `max`, returning where
+/// it ends up and whether it should follow new messages from there.
+fn scroll_by(
+    scroll: ScrollPosition,
+    delta: Px,
+    row_height: Px,
+    max: ScrollPosition,
+) -> (ScrollPosition, bool) {
+    let scroll = virtual_list::scroll_by(scroll, delta, row_height, max);
+    (scroll, scroll == max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follow_at_bottom() {
+        let at = |row, offset| ScrollPosition {
+            row,
+            offset: Px(offset),
+        };
+        let max = at(5, 0);
+        assert_eq!(scroll_by(max, Px(-20), Px(20), max), (at(4, 0), false));
+        assert_eq!(scroll_by(at(4, 0), Px(50), Px(20), max), (max, true));
+        // A log that fits in the view is always followed.
+        let top = ScrollPosition::TOP;
+        assert_eq!(scroll_by(top, Px(-20), Px(20), top), (top, true));
+    }
+
+    #[test]
+    fn follow_past_pixel_range() {
+        // Far more than `i16::MAX` pixels of messages.
+        let count = 100_000;
+        let viewport = Px(300);
+        let max = virtual_list::max_scroll(viewport, Px(16), count);
+        assert_eq!(
+            virtual_list::visible_rows(max, viewport, Px(16), count).end,
+            count
+        );
+
+        let (scroll, follow) = scroll_by(max, Px(-16), Px(16), max);
+        assert!(!follow);
+        assert_eq!(scroll_by(scroll, Px(16), Px(16), max), (max, true));
+    }
+}
@@ -1,17 +1,67 @@
+pub mod bind;
+pub mod cache_layer;
+pub mod canvas;
+pub mod code_view;
+pub mod color_picker;
+pub mod command_palette;
+pub mod context_menu;
 pub mod debug;
+pub mod dock;
+pub mod drag_drop;
+pub mod dropdown;
+mod editor;
+pub mod expander;
+pub mod grid;
+pub mod hit_test;
+pub mod image;
+pub mod interactive;
+pub mod keyed;
+mod kinetic;
+pub mod label;
 pub mod layout;
+pub mod log_view;
+pub mod plot;
+pub mod scroll_view;
 pub mod split_panel;
 pub mod tabbed_panel;
+pub mod table;
+pub mod text_area;
+pub mod text_input;
+pub mod toggle;
+mod ui;
+mod virtual_list;
+pub mod z_index;
+
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use crate::{
+    arena::{ArenaVec, FrameArena},
     gfx::{
-        geometry::{Extent, Offset, Point, Rect},
-        DrawCommandList, Image, Paint,
+        color::Color,
+        geometry::{Extent, Insets, Offset, Point, PointF, Px, Rect, ScaleFactor, Transform},
+        DrawCommandList, Effect, EffectUniforms, GlyphAntiAliasing, Image, Paint, Stroke, Vertex,
     },
     handle_pool::Handle,
+    shell::{dialog::FileDialog, Clipboard, Composition, CursorIcon, FullscreenMode, Shell},
+    text::{
+        layout_rich_text, measure_text, FontCollection, GlyphAtlas, RichLayout, RichText,
+        ShapedLine, TextMetrics, TextStyle,
+    },
 };
 
-use super::input::{Event, Input};
+use self::{drag_drop::DragDrop, keyed::WidgetKey};
+
+use super::{
+    accessibility::{AccessNode, Action},
+    input::{ButtonState, Event, FocusId, Input, MouseButton},
+    tooltip::Tooltip,
+};
 
 #[must_use]
 pub trait Widget {
@@ -19,6 +69,8 @@ pub trait Widget {
 
     fn widget_state_mut(&mut self) -> &mut WidgetState;
 
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget));
+
     fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget));
 
     fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate;
@@ -26,6 +78,129 @@ pub trait Widget {
     fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent;
 
     fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent);
+
+    /// The width that the widget needs to show its content when it is
+    /// `height` tall, computed without laying it out. This lets containers
+    /// size themselves to their content instead of to the space they're given,
+    /// such as with [`layout::IntrinsicWidth`]. Widgets that have no size of
+    /// their own, and so fill whatever space they're given, return zero.
+    fn intrinsic_width(&self, _context: &LayoutContext, _size: IntrinsicSize, _height: Px) -> Px {
+        Px(0)
+    }
+
+    /// The height that the widget needs to show its content when it is
+    /// `width` wide. See [`Self::intrinsic_width`].
+    fn intrinsic_height(&self, _context: &LayoutContext, _size: IntrinsicSize, _width: Px) -> Px {
+        Px(0)
+    }
+
+    /// Checks if the widget is showing an overlay, such as an open popup.
+    ///
+    /// While any widget in the tree shows an overlay, that widget receives
+    /// every input event and no other widget does. This lets it respond to
+    /// clicks outside of its bounds, such as by closing the overlay. If more
+    /// than one widget shows an overlay, the last one in the tree wins.
+    fn has_overlay(&self) -> bool {
+        false
+    }
+
+    /// Draws the widget's overlay above every other widget, after the whole
+    /// tree has been drawn. Coordinates are relative to the widget as for
+    /// [`Self::accept_draw`], but the overlay isn't limited to the widget's
+    /// bounds.
+    ///
+    /// Overlays are not tracked by layout, so widgets must ask for the parts
+    /// of the window that their overlays cover to be redrawn when they change.
+    /// See [`UpdateContext::redraw`].
+    fn accept_draw_overlay(&self, _canvas: &mut DrawContext) {}
+
+    /// The tooltip shown when the cursor rests on the widget, if it has one.
+    /// If several widgets under the cursor have tooltips, the innermost one is
+    /// shown. See [`TooltipManager`](super::tooltip::TooltipManager).
+    fn tooltip(&self) -> Option<&Tooltip> {
+        None
+    }
+
+    /// Where the widget is drawn relative to its siblings. Siblings with higher
+    /// z-indices are drawn above those with lower ones, and are found first by
+    /// [`hit_test::hit_test`]. Siblings with the same z-index are drawn in tree
+    /// order. See [`paint_order`].
+    fn z_index(&self) -> i32 {
+        0
+    }
+
+    /// The key that identifies the widget when a tree is rebuilt. See
+    /// [`keyed::reconcile`].
+    fn key(&self) -> Option<WidgetKey> {
+        None
+    }
+
+    /// Copies the state that the user has changed, such as scroll offsets,
+    /// selections, or text being edited, so that it can be restored to the
+    /// widget that replaces this one when the tree is rebuilt. Returns `None`
+    /// if the widget has no such state.
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        None
+    }
+
+    /// Restores state saved by [`Self::save_state`]. State saved by a
+    /// different kind of widget is ignored.
+    fn restore_state(&mut self, _state: Box<dyn Any>) {}
+
+    /// Called before each frame is drawn if any
+    /// [`Observable`](super::state::Observable) value has changed, so that
+    /// widgets that show one can catch up with it. Unlike
+    /// [`Self::accept_update`], this must not call into the widget's
+    /// children, since every widget in the tree is called. See
+    /// [`bind::sync_state`].
+    fn accept_state_change(&mut self) -> PostUpdate {
+        PostUpdate::NoChange
+    }
+
+    /// Called before each frame is drawn to advance the widget's animations
+    /// to `now`. Frames keep being drawn without waiting for input for as long
+    /// as any widget returns something other than [`PostUpdate::NoChange`].
+    /// As with [`Self::accept_state_change`], this must not call into the
+    /// widget's children. See [`animate`].
+    fn accept_animation_frame(&mut self, _now: Instant) -> PostUpdate {
+        PostUpdate::NoChange
+    }
+
+    /// Describes the widget to assistive technology such as screen readers,
+    /// or returns `None` if the widget only arranges or decorates other
+    /// widgets. See [`accessibility`](super::accessibility).
+    fn accessibility(&self) -> Option<AccessNode> {
+        None
+    }
+
+    /// Performs an action asked for by assistive technology. Only called with
+    /// the kinds of action listed by [`Self::accessibility`]. See
+    /// [`accessibility::perform_action`](super::accessibility::perform_action).
+    fn accept_action(&mut self, _action: &Action) -> PostUpdate {
+        PostUpdate::NoChange
+    }
+
+    /// The area where text is being entered, in absolute coordinates, if the
+    /// widget is the one identified by `focus`. Input methods show their
+    /// candidate window next to it. See [`find_ime_area`].
+    fn ime_area(&self, _focus: FocusId) -> Option<Rect> {
+        None
+    }
+
+    /// The cursor shown when the mouse is at `point`, in absolute coordinates,
+    /// over the widget, if the widget wants one. If several widgets under the
+    /// mouse want one, the innermost one's is shown. See
+    /// [`hit_test::cursor_at`].
+    fn cursor(&self, _point: Point) -> Option<CursorIcon> {
+        None
+    }
+
+    /// Accepts files dropped on the widget from another program at
+    /// `position`, in absolute coordinates. Returns `None` to leave them for
+    /// the widgets below. See [`drop_files`].
+    fn accept_file_drop(&mut self, _paths: &[PathBuf], _position: Point) -> Option<PostUpdate> {
+        None
+    }
 }
 
 /// Implementing [`Widget`] for `Box<dyn Widget>` permits a few nifty
@@ -48,6 +223,11 @@ impl Widget for Box<dyn Widget> {
         self.as_mut().widget_state_mut()
     }
 
+    #[inline]
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        self.as_ref().for_each_child(f);
+    }
+
     #[inline]
     fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
         self.as_mut().for_each_child_mut(f);
@@ -67,6 +247,185 @@ impl Widget for Box<dyn Widget> {
     fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
         self.as_ref().accept_draw(canvas, extent);
     }
+
+    #[inline]
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.as_ref().intrinsic_width(context, size, height)
+    }
+
+    #[inline]
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.as_ref().intrinsic_height(context, size, width)
+    }
+
+    #[inline]
+    fn has_overlay(&self) -> bool {
+        self.as_ref().has_overlay()
+    }
+
+    #[inline]
+    fn accept_draw_overlay(&self, canvas: &mut DrawContext) {
+        self.as_ref().accept_draw_overlay(canvas);
+    }
+
+    #[inline]
+    fn tooltip(&self) -> Option<&Tooltip> {
+        self.as_ref().tooltip()
+    }
+
+    #[inline]
+    fn z_index(&self) -> i32 {
+        self.as_ref().z_index()
+    }
+
+    #[inline]
+    fn key(&self) -> Option<WidgetKey> {
+        self.as_ref().key()
+    }
+
+    #[inline]
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        self.as_ref().save_state()
+    }
+
+    #[inline]
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        self.as_mut().restore_state(state);
+    }
+
+    #[inline]
+    fn accept_state_change(&mut self) -> PostUpdate {
+        self.as_mut().accept_state_change()
+    }
+
+    #[inline]
+    fn accept_animation_frame(&mut self, now: Instant) -> PostUpdate {
+        self.as_mut().accept_animation_frame(now)
+    }
+
+    #[inline]
+    fn accessibility(&self) -> Option<AccessNode> {
+        self.as_ref().accessibility()
+    }
+
+    #[inline]
+    fn accept_action(&mut self, action: &Action) -> PostUpdate {
+        self.as_mut().accept_action(action)
+    }
+
+    #[inline]
+    fn ime_area(&self, focus: FocusId) -> Option<Rect> {
+        self.as_ref().ime_area(focus)
+    }
+
+    #[inline]
+    fn cursor(&self, point: Point) -> Option<CursorIcon> {
+        self.as_ref().cursor(point)
+    }
+
+    #[inline]
+    fn accept_file_drop(&mut self, paths: &[PathBuf], position: Point) -> Option<PostUpdate> {
+        self.as_mut().accept_file_drop(paths, position)
+    }
+}
+
+/// The children of `widget` in the order they are drawn in, from bottom to
+/// top. This is the order of [`Widget::for_each_child`], stably sorted by
+/// [`Widget::z_index`].
+#[must_use]
+pub fn paint_order(widget: &dyn Widget) -> Vec<&dyn Widget> {
+    let mut children = Vec::new();
+    widget.for_each_child(&mut |child| children.push(child));
+    children.sort_by_key(|child| child.z_index());
+    children
+}
+
+/// Advances the animations of every widget in the tree with `root` at its
+/// root to `now`. Returns the region of the window that needs to be redrawn,
+/// which is empty once every animation has finished.
+pub fn animate(root: &mut dyn Widget, now: Instant) -> Rect {
+    let post_update = root.accept_animation_frame(now);
+    let mut damage = mark_changed(root, post_update);
+    root.for_each_child_mut(&mut |child| damage = damage.union(&animate(child, now)));
+    damage
+}
+
+/// Gives files dropped at `position`, in absolute coordinates, to the topmost
+/// widget there that accepts them, in the tree with `root` at its root. See
+/// [`Widget::accept_file_drop`]. Returns the region of the window that needs to
+/// be redrawn, or `None` if no widget accepted the files.
+pub fn drop_files(root: &mut dyn Widget, paths: &[PathBuf], position: Point) -> Option<Rect> {
+    if root.widget_state().is_disabled() || !root.widget_state().rect().contains_point(position) {
+        return None;
+    }
+
+    // Children are visited from top to bottom, as by `hit_test`.
+    let mut accepted = None;
+    {
+        let mut children = Vec::new();
+        root.for_each_child_mut(&mut |child| children.push(child));
+        children.sort_by_key(|child| child.z_index());
+        for child in children.into_iter().rev() {
+            accepted = drop_files(child, paths, position);
+            if accepted.is_some() {
+                break;
+            }
+        }
+    }
+
+    match accepted {
+        Some(damage) => Some(damage),
+        None => {
+            let post_update = root.accept_file_drop(paths, position)?;
+            Some(mark_changed(root, post_update))
+        }
+    }
+}
+
+/// Finds the area where the widget identified by `focus` is entering text, in
+/// the tree with `root` at its root. See [`Widget::ime_area`].
+#[must_use]
+pub fn find_ime_area(root: &dyn Widget, focus: FocusId) -> Option<Rect> {
+    let mut area = root.ime_area(focus);
+    root.for_each_child(&mut |child| {
+        if area.is_none() {
+            area = find_ime_area(child, focus);
+        }
+    });
+    area
+}
+
+/// Flags `widget` for layout as asked for by `post_update`, returning the
+/// region of the window that needs to be redrawn because of it.
+pub(crate) fn mark_changed(widget: &mut dyn Widget, post_update: PostUpdate) -> Rect {
+    match post_update {
+        PostUpdate::NoChange => Rect::zero(),
+        PostUpdate::NeedsRedraw => widget.widget_state().rect(),
+        PostUpdate::NeedsLayout => {
+            // Layout is computed within the widget's existing bounds, so
+            // those are all that need to be redrawn.
+            widget.widget_state_mut().set_needs_layout();
+            widget.widget_state().rect()
+        }
+        PostUpdate::NeedsResize => {
+            // The parent's bounds are redrawn once it has been laid out again.
+            // See `LayoutContext::begin`.
+            widget.widget_state_mut().set_needs_resize();
+            widget.widget_state().rect()
+        }
+    }
+}
+
+/// Which of a widget's intrinsic sizes to compute. See
+/// [`Widget::intrinsic_width`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntrinsicSize {
+    /// The smallest size at which the widget can show its content properly,
+    /// such as the width of the longest word in a paragraph.
+    Min,
+    /// The size beyond which more space doesn't help the widget show its
+    /// content, such as the width of a paragraph on a single line.
+    Max,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -77,22 +436,97 @@ pub enum PostUpdate {
     // The widget needs to have the layout of its children recalculated. The
     // widget will make use of the current layout bounds in this calculation.
     NeedsLayout,
+    // The widget needs a different size, so its parent needs to lay out its
+    // children again. The parent will make use of its current layout bounds.
+    NeedsResize,
 }
 
 #[must_use]
 pub struct UpdateContext<'a> {
-    input: &'a Input,
-    needs_redraw: bool,
+    input: &'a mut Input,
+    /// The shell that owns the window, used to access the clipboard. `None`
+    /// if the widgets are not being updated in a window.
+    shell: Option<&'a dyn Shell>,
+    /// The bounds of every widget that needs to be redrawn.
+    damage: Rect,
+    /// Set when a change affects widgets other than the one that made it, so
+    /// that the whole window is redrawn.
+    redraw_window: bool,
+    /// The size of the window, used to keep overlays inside of it.
+    window_extent: Extent,
+    drag_drop: DragDrop,
+    file_dialogs: Vec<FileDialogRequest>,
+    timers: Vec<TimerRequest>,
+    /// Set when a widget has acted on the event, so that the application
+    /// doesn't act on it too.
+    handled: bool,
+}
+
+pub type FileDialogCallback = Box<dyn FnOnce(&[PathBuf])>;
+
+/// A file dialog that a widget asked to be shown during an update. See
+/// [`UpdateContext::open_file_dialog`].
+pub struct FileDialogRequest {
+    pub dialog: FileDialog,
+    /// Whether the dialog chooses where to save a file, rather than files to
+    /// open.
+    pub save: bool,
+    pub callback: FileDialogCallback,
+}
+
+pub type TimerCallback = Box<dyn FnMut() -> bool>;
+
+/// A timer that a widget started during an update. See
+/// [`UpdateContext::set_timer`].
+pub struct TimerRequest {
+    pub interval: Duration,
+    pub callback: TimerCallback,
 }
 
 impl<'a> UpdateContext<'a> {
-    pub fn new(input: &'a Input) -> Self {
+    pub fn new(input: &'a mut Input) -> Self {
         Self {
             input,
-            needs_redraw: false,
+            shell: None,
+            damage: Rect::zero(),
+            redraw_window: false,
+            window_extent: Extent::zero(),
+            drag_drop: DragDrop::default(),
+            file_dialogs: Vec::new(),
+            timers: Vec::new(),
+            handled: false,
         }
     }
 
+    pub fn with_shell(mut self, shell: &'a dyn Shell) -> Self {
+        self.shell = Some(shell);
+        self
+    }
+
+    pub fn with_window_extent(mut self, extent: Extent) -> Self {
+        self.window_extent = extent;
+        self
+    }
+
+    /// Shares `drag_drop` with the widgets, so that drags can move between
+    /// widget trees. Otherwise, drags are limited to the tree being updated.
+    pub fn with_drag_drop(mut self, drag_drop: &DragDrop) -> Self {
+        self.drag_drop = drag_drop.clone();
+        self
+    }
+
+    /// The drag in progress, if any. See [`drag_drop`].
+    pub fn drag_drop(&self) -> &DragDrop {
+        &self.drag_drop
+    }
+
+    /// The size of the window that the widgets are in. This is zero if it
+    /// hasn't been provided.
+    #[must_use]
+    pub fn window_extent(&self) -> Extent {
+        self.window_extent
+    }
+
     pub fn event(&self) -> Event {
         self.input.event()
     }
@@ -102,28 +536,225 @@ impl<'a> UpdateContext<'a> {
         self.input.cursor_position()
     }
 
+    /// The text that an input method is composing. See [`Input::composition`].
+    #[must_use]
+    pub fn composition(&self) -> Option<&Composition> {
+        self.input.composition()
+    }
+
+    #[must_use]
+    pub fn mouse_button_state(&self, button: MouseButton) -> ButtonState {
+        self.input.mouse_button_state(button)
+    }
+
+    /// Checks if the widget identified by `id` has the keyboard focus.
+    #[must_use]
+    pub fn is_focused(&self, id: FocusId) -> bool {
+        self.input.focus() == Some(id)
+    }
+
+    /// Gives the keyboard focus to the widget identified by `id`, or takes it
+    /// away from every widget if `id` is `None`.
+    pub fn set_focus(&mut self, id: Option<FocusId>) {
+        self.input.set_focus(id);
+    }
+
+    /// Starts or stops raw mouse input. See [`Input::set_raw_mouse_input`].
+    pub fn set_raw_mouse_input(&mut self, enabled: bool) {
+        self.input.set_raw_mouse_input(enabled);
+    }
+
+    /// Confines the cursor to `area`. See [`Input::confine_cursor`].
+    pub fn confine_cursor(&mut self, area: Option<Rect>) {
+        self.input.confine_cursor(area);
+    }
+
+    /// Shows or hides the cursor. See [`Input::set_cursor_visible`].
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.input.set_cursor_visible(visible);
+    }
+
+    /// Makes the window fullscreen or windowed. See [`Shell::set_fullscreen`].
+    pub fn set_fullscreen(&mut self, mode: FullscreenMode) {
+        self.input.set_fullscreen(mode);
+    }
+
+    /// Asks for the whole window to be redrawn, for changes that affect how
+    /// other widgets are drawn.
+    pub fn redraw_window(&mut self) {
+        self.redraw_window = true;
+    }
+
+    /// Marks the event as handled, so that the application doesn't act on it
+    /// as well, such as undoing with Control+Z when a text input already did.
+    pub fn set_handled(&mut self) {
+        self.handled = true;
+    }
+
+    /// Checks if any widget marked the event as handled during the update.
+    #[must_use]
+    pub fn is_handled(&self) -> bool {
+        self.handled
+    }
+
+    /// Checks if any widget asked for the whole window to be redrawn during
+    /// the update.
+    #[must_use]
+    pub fn needs_window_redraw(&self) -> bool {
+        self.redraw_window
+    }
+
+    /// Reads text from the system clipboard. Returns `None` if the clipboard
+    /// does not hold text or there is no shell.
+    #[must_use]
+    pub fn clipboard_text(&self) -> Option<String> {
+        self.clipboard().and_then(|clipboard| clipboard.text())
+    }
+
+    /// Replaces the contents of the system clipboard with `text`. Does nothing
+    /// if there is no shell.
+    pub fn set_clipboard_text(&self, text: &str) {
+        if let Some(clipboard) = self.clipboard() {
+            clipboard.set_text(text);
+        }
+    }
+
+    /// Shows a dialog for choosing files to open once the update is over, and
+    /// calls `callback` with the chosen files when it closes. There are no
+    /// files if the user cancelled the dialog. See
+    /// [`Shell::open_file_dialog`].
+    pub fn open_file_dialog(
+        &mut self,
+        dialog: FileDialog,
+        callback: impl FnOnce(&[PathBuf]) + 'static,
+    ) {
+        self.file_dialogs.push(FileDialogRequest {
+            dialog,
+            save: false,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Shows a dialog for choosing where to save a file once the update is
+    /// over. See [`Self::open_file_dialog`].
+    pub fn save_file_dialog(
+        &mut self,
+        dialog: FileDialog,
+        callback: impl FnOnce(&[PathBuf]) + 'static,
+    ) {
+        self.file_dialogs.push(FileDialogRequest {
+            dialog,
+            save: true,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Takes the file dialogs that widgets asked for during the update, for
+    /// the application to show.
+    pub fn take_file_dialogs(&mut self) -> Vec<FileDialogRequest> {
+        std::mem::take(&mut self.file_dialogs)
+    }
+
+    /// Calls `callback` every `interval` once the update is over, for as long
+    /// as it returns true. Callbacks communicate with widgets through shared
+    /// state, such as to blink a caret or save a document while the user is
+    /// idle. See [`Shell::set_timer`].
+    pub fn set_timer(&mut self, interval: Duration, callback: impl FnMut() -> bool + 'static) {
+        self.timers.push(TimerRequest {
+            interval,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Takes the timers that widgets started during the update, for the
+    /// application to start.
+    pub fn take_timers(&mut self) -> Vec<TimerRequest> {
+        std::mem::take(&mut self.timers)
+    }
+
+    /// The system clipboard, which widgets can keep for callbacks that copy
+    /// something. `None` if there is no shell.
+    #[must_use]
+    pub fn clipboard(&self) -> Option<Clipboard> {
+        self.shell.map(Shell::clipboard)
+    }
+
+    /// Marks `rect`, in absolute coordinates, as needing to be redrawn. This
+    /// is for parts of the window outside of the widget being updated, such as
+    /// its overlay.
+    pub fn redraw(&mut self, rect: Rect) {
+        self.damage = self.damage.union(&rect);
+    }
+
+    /// Updates the widget tree with `root` at its root. Returns the region of
+    /// the window that needs to be redrawn, which will be empty if nothing
+    /// changed.
+    ///
+    /// If a widget in the tree is showing an overlay, only that widget is
+    /// updated. See [`Widget::has_overlay`].
     #[must_use]
-    pub fn begin(&mut self, root: &mut dyn Widget) -> bool {
-        self.update(root);
-        self.needs_redraw
+    pub fn begin(&mut self, root: &mut dyn Widget) -> Rect {
+        match Self::find_overlay(root) {
+            Some(widget) => self.update(widget),
+            None => self.update(root),
+        }
+        self.damage
     }
 
+    /// Finds the last widget in the tree that is showing an overlay.
+    fn find_overlay(widget: &mut dyn Widget) -> Option<&mut dyn Widget> {
+        fn any_child_has_overlay(widget: &mut dyn Widget) -> bool {
+            let mut found = false;
+            widget.for_each_child_mut(&mut |child| {
+                found = found || child.has_overlay() || any_child_has_overlay(child);
+            });
+            found
+        }
+
+        // The children are checked first since they're drawn after their
+        // parent, and so would be on top of its overlay.
+        if !any_child_has_overlay(widget) {
+            return if widget.has_overlay() {
+                Some(widget)
+            } else {
+                None
+            };
+        }
+
+        let mut found = None;
+        widget.for_each_child_mut(&mut |child| {
+            if let Some(overlay) = Self::find_overlay(child) {
+                found = Some(overlay);
+            }
+        });
+        found
+    }
+
+    /// Gives the current event to `widget`, which passes it on to its
+    /// children as it sees fit. Disabled widgets are skipped along with their
+    /// descendants. See [`WidgetState::set_disabled`].
     pub fn update(&mut self, widget: &mut dyn Widget) {
         // Invariant: the all widgets processed by an instance of
         // `UpdateContext` are part of the same tree.
 
-        match widget.accept_update(self) {
-            PostUpdate::NoChange => {
-                // no-op
-            }
-            PostUpdate::NeedsRedraw => {
-                self.needs_redraw = true;
-            }
-            PostUpdate::NeedsLayout => {
-                widget.widget_state_mut().set_needs_layout();
-                self.needs_redraw = true;
-            }
+        if widget.widget_state().is_disabled() {
+            return;
         }
+
+        let post_update = widget.accept_update(self);
+        self.damage = self.damage.union(&mark_changed(widget, post_update));
+    }
+
+    /// Updates `widget` like [`Self::update`], returning the region that it
+    /// and its descendants marked as needing to be redrawn. This is for
+    /// widgets that keep what their children drew, so that they know when it
+    /// is out of date.
+    pub fn update_with_damage(&mut self, widget: &mut dyn Widget) -> Rect {
+        let outer = std::mem::replace(&mut self.damage, Rect::zero());
+        self.update(widget);
+        let damage = self.damage;
+        self.damage = outer.union(&damage);
+        damage
     }
 
     /// Returns the bounds for the given widget that was calculated during the
@@ -136,44 +767,189 @@ impl<'a> UpdateContext<'a> {
     }
 }
 
+thread_local! {
+    /// The last generation given to a [`LayoutContext`].
+    static LAYOUT_GENERATION: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Returns a layout generation that no other [`LayoutContext`] on this thread
+/// has used. Each window has its own context, so this keeps a widget that
+/// moves to another window from reusing a layout from its old one.
+fn next_layout_generation() -> u64 {
+    LAYOUT_GENERATION.with(|generation| {
+        generation.set(generation.get() + 1);
+        generation.get()
+    })
+}
+
 #[must_use]
-#[derive(Default)]
-pub struct LayoutContext {}
+pub struct LayoutContext {
+    scale_factor: ScaleFactor,
+    /// Scratch memory for the duration of a single call to [`Self::begin`].
+    /// Reusing the context across frames avoids allocating on every layout.
+    arena: FrameArena,
+    /// The fonts used to measure text. Shared with the application so that
+    /// fonts loaded elsewhere are available for measurement.
+    fonts: Rc<RefCell<FontCollection>>,
+    /// Changes whenever something that every widget's layout may depend on
+    /// changes, such as the scale factor, so that cached layouts are redone.
+    /// See [`next_layout_generation`].
+    generation: u64,
+}
+
+impl Default for LayoutContext {
+    fn default() -> Self {
+        Self::new(ScaleFactor::ONE)
+    }
+}
 
 impl LayoutContext {
-    /// Computes the layout of a widget tree with `root` at its root.
+    /// Creates a layout context for a window with the given scale factor.
+    pub fn new(scale_factor: ScaleFactor) -> Self {
+        Self {
+            scale_factor,
+            arena: FrameArena::new(),
+            fonts: Rc::default(),
+            generation: next_layout_generation(),
+        }
+    }
+
+    /// Replaces the fonts used to measure text.
+    pub fn set_fonts(&mut self, fonts: Rc<RefCell<FontCollection>>) {
+        self.fonts = fonts;
+        self.generation = next_layout_generation();
+    }
+
+    /// The fonts used to measure text.
+    pub fn fonts(&self) -> Rc<RefCell<FontCollection>> {
+        self.fonts.clone()
+    }
+
+    /// Measures `text` as it would be drawn with `style`, for widgets that
+    /// size themselves to their text. See [`measure_text`] for details.
+    pub fn measure_text(
+        &self,
+        text: &str,
+        style: &TextStyle,
+        max_width: Option<f32>,
+    ) -> TextMetrics {
+        measure_text(&self.fonts.borrow(), text, style, max_width)
+    }
+
+    /// Lays out `text` as it would be drawn within text drawn in `style`. See
+    /// [`layout_rich_text`] for details.
+    pub fn layout_rich_text(
+        &self,
+        text: &RichText,
+        style: &TextStyle,
+        max_width: Option<f32>,
+    ) -> RichLayout {
+        let mut fonts = self.fonts.borrow_mut();
+        let styles: Vec<_> = text
+            .spans()
+            .iter()
+            .map(|span| span.attributes.resolve(&mut fonts, style))
+            .collect();
+        layout_rich_text(&fonts, text, &styles, max_width)
+    }
+
+    /// Changes the scale factor used for subsequent layouts.
+    pub fn set_scale_factor(&mut self, scale_factor: ScaleFactor) {
+        self.scale_factor = scale_factor;
+        self.generation = next_layout_generation();
+    }
+
+    /// The number of physical pixels per logical pixel in the window being
+    /// laid out. Layout is in logical pixels, so most widgets don't need this,
+    /// but those that want to line up with physical pixels, such as to draw
+    /// hairlines, can use it to round to them.
+    #[must_use]
+    pub fn scale_factor(&self) -> ScaleFactor {
+        self.scale_factor
+    }
+
+    /// Computes the layout of a widget tree with `root` at its root. Returns
+    /// the region of the window that needs to be redrawn because widgets
+    /// changed size, beyond what was already reported during the update.
     ///
     /// ## Panics
     ///
     /// This function will panic if `root` is not in fact the root of a widget
     /// tree.
-    pub fn begin(&mut self, root: &mut dyn Widget, window_extent: Extent) {
+    pub fn begin(&mut self, root: &mut dyn Widget, window_extent: Extent) -> Rect {
         assert!(root.widget_state().offset() == Offset::zero());
 
-        if root.widget_state().extent() == window_extent {
-            let mut subtrees_needing_layout = vec![];
-            Self::collect_subtrees_needing_layout(root, &mut subtrees_needing_layout);
+        // A root that asked to be laid out again is laid out as if the window
+        // was resized, since it has no parent to do it.
+        if root.widget_state().extent() == window_extent && !root.widget_state().needs_layout() {
+            // The arena is moved out of the context so that it can be borrowed
+            // while widgets are laid out with `&mut self`.
+            let mut arena = std::mem::take(&mut self.arena);
+            arena.reset();
+
+            let mut damage = Rect::zero();
+            {
+                let mut subtrees_needing_layout = ArenaVec::new(&arena);
+                Self::collect_subtrees_needing_layout(root, &mut subtrees_needing_layout);
 
-            for subtree in subtrees_needing_layout {
-                let constraints = BoxConstraint::exact(subtree.widget_state().extent());
-                let _ = self.layout(subtree, constraints);
+                for subtree in subtrees_needing_layout.iter_mut() {
+                    // The subtree is laid out as its parent last laid it out,
+                    // so that its descendants can reuse their cached layouts.
+                    let state = subtree.widget_state();
+                    let constraints = state
+                        .constraints
+                        .unwrap_or_else(|| BoxConstraint::exact(state.extent()));
+                    let _ = self.layout(*subtree, constraints);
 
-                // Now that we have the subtree's layout, we can update the
-                // origins of its children (and they're more likely to be in
-                // cache here).
-                Self::update_origins(subtree);
+                    // Now that we have the subtree's layout, we can update the
+                    // origins of its children (and they're more likely to be in
+                    // cache here).
+                    Self::update_origins(*subtree);
+
+                    damage = damage.union(&subtree.widget_state().rect());
+                }
             }
+
+            self.arena = arena;
+            damage
         } else {
             // Since this is the root widget, the origin is always 0.
             let _ = self.layout(root, BoxConstraint::exact(window_extent));
             root.widget_state_mut()
                 .set_layout(Offset::zero(), window_extent);
             Self::update_origins(root);
+            root.widget_state().rect()
         }
     }
 
+    /// Lays out `widget` within `constraints`, returning the extent it asks
+    /// for.
+    ///
+    /// The result is cached, so widgets that haven't asked to be laid out
+    /// again since they were last laid out with the same constraints are
+    /// skipped along with all of their descendants. Widgets that change
+    /// anything that their layout depends on must return
+    /// [`PostUpdate::NeedsLayout`] or [`PostUpdate::NeedsResize`], and
+    /// widgets that change their children must also ask for the children to
+    /// be laid out again.
     pub fn layout(&mut self, widget: &mut dyn Widget, constraints: BoxConstraint) -> Extent {
-        widget.accept_layout(self, constraints)
+        let state = widget.widget_state();
+        if let Some(cached) = state.cached_layout {
+            if state.constraints == Some(constraints) && cached.generation == self.generation {
+                return cached.extent;
+            }
+        }
+
+        let extent = widget.accept_layout(self, constraints);
+
+        let state = widget.widget_state_mut();
+        state.status = RenderObjectStatus::Ready;
+        state.constraints = Some(constraints);
+        state.cached_layout = Some(CachedLayout {
+            generation: self.generation,
+            extent,
+        });
+        extent
     }
 
     pub fn position_widget(&mut self, widget: &mut dyn Widget, offset: Offset, extent: Extent) {
@@ -184,7 +960,7 @@ impl LayoutContext {
     /// the update phase.
     fn collect_subtrees_needing_layout<'a>(
         widget: &'a mut dyn Widget,
-        buffer: &mut Vec<&'a mut dyn Widget>,
+        buffer: &mut ArenaVec<&'a mut dyn Widget>,
     ) {
         // The most efficient way to do this is to walk the tree breadth-first and
         // find the nodes that have their status set to NeedsLayout. Their
@@ -200,6 +976,18 @@ impl LayoutContext {
         // relaid anyway so we can return immediately.
         assert!(!widget.widget_state().needs_layout());
 
+        // A child that changed size needs its siblings to be moved, so the
+        // whole widget is laid out again.
+        let mut child_resized = false;
+        widget.for_each_child(&mut |child| {
+            child_resized = child_resized || child.widget_state().needs_resize();
+        });
+        if child_resized {
+            widget.widget_state_mut().set_needs_layout();
+            buffer.push(widget);
+            return;
+        }
+
         widget.for_each_child_mut(&mut |child| {
             if child.widget_state().needs_layout() {
                 buffer.push(child);
@@ -223,29 +1011,240 @@ impl LayoutContext {
 pub struct DrawContext<'a> {
     draw_commands: &'a mut DrawCommandList,
     current_offset: Offset,
+    /// The region of the window being redrawn, in absolute coordinates.
+    region: Rect,
+    text_anti_aliasing: GlyphAntiAliasing,
+    /// The fonts and glyph cache used to draw text. Text is not drawn if
+    /// these have not been provided.
+    text: Option<(&'a FontCollection, &'a mut GlyphAtlas)>,
+    focus: Option<FocusId>,
+    scale_factor: ScaleFactor,
 }
 
 impl<'a> DrawContext<'a> {
-    pub fn new(draw_commands: &'a mut DrawCommandList) -> Self {
+    /// Creates a new draw context that draws the parts of the widget tree that
+    /// overlap `region`. Widgets that lie entirely outside of it are skipped.
+    pub fn new(draw_commands: &'a mut DrawCommandList, region: Rect) -> Self {
         Self {
             draw_commands,
             current_offset: Offset::zero(),
+            region,
+            text_anti_aliasing: GlyphAntiAliasing::Grayscale,
+            text: None,
+            focus: None,
+            scale_factor: ScaleFactor::ONE,
         }
     }
 
+    /// Draws everything that follows at `scale_factor` physical pixels per
+    /// logical pixel, for drawing to a window's swapchain. Call this once,
+    /// before anything is drawn. Text is rasterized for the physical pixels,
+    /// so that it stays sharp.
+    pub fn set_scale_factor(&mut self, scale_factor: ScaleFactor) {
+        self.scale_factor = scale_factor;
+        self.draw_commands
+            .push_transform(&Transform::scale(scale_factor.0, scale_factor.0));
+    }
+
+    /// The number of physical pixels that each logical pixel is drawn to.
+    #[must_use]
+    pub fn scale_factor(&self) -> ScaleFactor {
+        self.scale_factor
+    }
+
+    /// Provides the fonts and glyph cache that text is drawn with.
+    pub fn set_text_resources(&mut self, fonts: &'a FontCollection, atlas: &'a mut GlyphAtlas) {
+        self.text = Some((fonts, atlas));
+    }
+
+    /// Sets the widget that has the keyboard focus, so that it can be drawn
+    /// differently from the others.
+    pub fn set_focus(&mut self, focus: Option<FocusId>) {
+        self.focus = focus;
+    }
+
+    /// Checks if the widget identified by `id` has the keyboard focus.
+    #[must_use]
+    pub fn is_focused(&self, id: FocusId) -> bool {
+        self.focus == Some(id)
+    }
+
+    /// Sets how text is anti-aliased. This is chosen by the window, since
+    /// subpixel anti-aliasing depends on the display and the swapchain.
+    pub fn set_text_anti_aliasing(&mut self, anti_aliasing: GlyphAntiAliasing) {
+        self.text_anti_aliasing = anti_aliasing;
+    }
+
+    #[must_use]
+    pub fn text_anti_aliasing(&self) -> GlyphAntiAliasing {
+        self.text_anti_aliasing
+    }
+
+    /// The region of the window being redrawn, in coordinates relative to the
+    /// widget currently being drawn.
+    #[must_use]
+    pub fn region(&self) -> Rect {
+        self.region - self.current_offset
+    }
+
     pub fn draw(&mut self, widget: &dyn Widget) {
         let widget_state = widget.widget_state();
+        if widget_state.rect().intersect(&self.region).is_empty() {
+            return;
+        }
+
         self.current_offset += widget_state.offset();
 
         // push clip bounds
 
+        // Disabled widgets are faded along with their descendants.
+        let opacity = widget_state.draw_opacity();
+        if opacity < 1.0 {
+            self.draw_commands.push_opacity(opacity);
+        }
+
         widget.accept_draw(self, widget_state.extent());
 
+        if opacity < 1.0 {
+            self.draw_commands.pop_opacity();
+        }
+
         // pop clip bounds
 
         self.current_offset -= widget_state.offset();
     }
 
+    /// Fades everything drawn until the matching call to
+    /// [`Self::pop_opacity`]. See [`DrawCommandList::push_opacity`].
+    pub fn push_opacity(&mut self, opacity: f32) {
+        self.draw_commands.push_opacity(opacity);
+    }
+
+    /// Restores the opacity that was in effect before the last call to
+    /// [`Self::push_opacity`].
+    pub fn pop_opacity(&mut self) {
+        self.draw_commands.pop_opacity();
+    }
+
+    /// Draws `widget` into `commands` instead, with the origin of the widget
+    /// currently being drawn at the origin of `commands` and at the same scale
+    /// factor as this context. All of `widget` is drawn, whether or not it
+    /// overlaps the region being redrawn, for widgets that keep what their
+    /// children drew such as [`CacheLayer`](cache_layer::CacheLayer).
+    ///
+    /// Text is drawn with grayscale anti-aliasing, since subpixel glyphs can
+    /// only be blended with the window.
+    pub fn record(&mut self, widget: &dyn Widget, commands: &mut DrawCommandList) {
+        let mut context = DrawContext {
+            draw_commands: commands,
+            current_offset: self.current_offset,
+            region: widget.widget_state().rect(),
+            text_anti_aliasing: GlyphAntiAliasing::Grayscale,
+            text: self
+                .text
+                .as_mut()
+                .map(|(fonts, atlas)| (&**fonts, &mut **atlas)),
+            focus: self.focus,
+            scale_factor: self.scale_factor,
+        };
+
+        let x = f32::from(self.current_offset.x);
+        let y = f32::from(self.current_offset.y);
+        let scale = self.scale_factor.0;
+        context
+            .draw_commands
+            .push_transform(&Transform::translate(-x, -y).then(&Transform::scale(scale, scale)));
+        context.draw(widget);
+        context.draw_commands.pop_transform();
+    }
+
+    /// Draws the children of `widget` in [`paint_order`], so that children with
+    /// higher z-indices are drawn above their siblings. Containers whose
+    /// children may overlap should draw them with this.
+    pub fn draw_children(&mut self, widget: &dyn Widget) {
+        for child in paint_order(widget) {
+            self.draw(child);
+        }
+    }
+
+    /// Draws the overlays of every widget in the tree with `root` at its root,
+    /// in tree order. Call this after drawing the tree so that the overlays
+    /// are drawn above it. See [`Widget::accept_draw_overlay`].
+    pub fn draw_overlays(&mut self, root: &mut dyn Widget) {
+        if root.has_overlay() {
+            let saved = self.current_offset;
+            self.current_offset = root.widget_state().origin() - Point::zero();
+            root.accept_draw_overlay(self);
+            self.current_offset = saved;
+        }
+
+        root.for_each_child_mut(&mut |child| self.draw_overlays(child));
+    }
+
+    /// Applies `transform` to everything drawn until the matching call to
+    /// [`Self::pop_transform`]. The transform is relative to the origin of the
+    /// widget currently being drawn, so that a widget can be rotated or scaled
+    /// around its own coordinates.
+    ///
+    /// Transforms only affect how widgets are drawn, not their layout or the
+    /// regions that are redrawn when they change.
+    pub fn push_transform(&mut self, transform: &Transform) {
+        let x = f32::from(self.current_offset.x);
+        let y = f32::from(self.current_offset.y);
+        let transform = Transform::translate(-x, -y)
+            .then(transform)
+            .then(&Transform::translate(x, y));
+        self.draw_commands.push_transform(&transform);
+    }
+
+    /// Restores the transform that was in effect before the last call to
+    /// [`Self::push_transform`].
+    pub fn pop_transform(&mut self) {
+        self.draw_commands.pop_transform();
+    }
+
+    /// Clips everything drawn until the matching call to [`Self::pop_clip`] to
+    /// `rect` with corners rounded by `radius`, in relative coordinates.
+    pub fn push_clip_rounded_rect(&mut self, rect: Rect, radius: Px) {
+        let rect = rect + self.current_offset;
+        self.draw_commands.push_clip_rounded_rect(rect, radius);
+    }
+
+    /// Removes the clip added by the last call to
+    /// [`Self::push_clip_rounded_rect`].
+    pub fn pop_clip(&mut self) {
+        self.draw_commands.pop_clip();
+    }
+
+    /// Draws the outline of `rect` at the given relative coordinates.
+    pub fn stroke_rect(&mut self, rect: Rect, stroke: &Stroke) {
+        let rect = rect + self.current_offset;
+        self.draw_commands.stroke_rect(rect, stroke);
+    }
+
+    /// Draws a line between two points at the given relative coordinates.
+    pub fn stroke_line(&mut self, from: Point, to: Point, stroke: &Stroke) {
+        let from = from + self.current_offset;
+        let to = to + self.current_offset;
+        self.draw_commands
+            .stroke_line(from.into(), to.into(), stroke);
+    }
+
+    /// Draws a line through each of `points` at the given relative
+    /// coordinates, returning to the first point if `closed` is true. See
+    /// [`DrawCommandList::stroke_polyline`] for details.
+    pub fn stroke_polyline(&mut self, points: &[PointF], closed: bool, stroke: &Stroke) {
+        let x = f32::from(self.current_offset.x);
+        let y = f32::from(self.current_offset.y);
+        self.draw_commands.with_scratch(|commands, scratch| {
+            let mut moved = ArenaVec::with_capacity(scratch, points.len());
+            for point in points {
+                moved.push(PointF::new(point.x + x, point.y + y));
+            }
+            commands.stroke_polyline(&moved, closed, stroke);
+        });
+    }
+
     /// Draws a colored rectangle at the given relative coordinates.
     pub fn draw_rect(&mut self, rect: Rect, paint: &Paint) {
         // convert the rect into absolute coordinates
@@ -253,20 +1252,143 @@ impl<'a> DrawContext<'a> {
         self.draw_commands.draw_rect(rect, *paint);
     }
 
-    pub fn draw_image(&mut self, rect: Rect, image: Handle<Image>, region: Rect, paint: &Paint) {
+    /// Draws `rect` with corners rounded by `radius` at the given relative
+    /// coordinates.
+    pub fn draw_rounded_rect(&mut self, rect: Rect, radius: Px, paint: &Paint) {
+        let rect = rect + self.current_offset;
+        self.draw_commands.draw_rounded_rect(rect, radius, *paint);
+    }
+
+    /// Draws the outline of `rect` with corners rounded by `radius` at the
+    /// given relative coordinates.
+    pub fn stroke_rounded_rect(&mut self, rect: Rect, radius: Px, stroke: &Stroke) {
         let rect = rect + self.current_offset;
-        self.draw_commands.draw_image(rect, image, region, *paint);
+        self.draw_commands.stroke_rounded_rect(rect, radius, stroke);
+    }
+
+    /// Draws a circle centered on `center` at the given relative coordinates.
+    pub fn draw_circle(&mut self, center: Point, radius: Px, paint: &Paint) {
+        let center = center + self.current_offset;
+        self.draw_commands.draw_circle(center, radius, *paint);
+    }
+
+    /// Draws the outline of a circle centered on `center` at the given
+    /// relative coordinates.
+    pub fn stroke_circle(&mut self, center: Point, radius: Px, stroke: &Stroke) {
+        let center = center + self.current_offset;
+        self.draw_commands.stroke_circle(center, radius, stroke);
+    }
+
+    /// Draws untextured triangles whose vertices are at the given relative
+    /// coordinates, blending the colors of the vertices across each triangle.
+    /// See [`DrawCommandList::draw_mesh`] for details.
+    pub fn draw_mesh(&mut self, vertices: &[Vertex], indices: &[u16]) {
+        let offset = self.current_offset;
+        self.draw_commands.with_scratch(|commands, scratch| {
+            let mut moved = ArenaVec::with_capacity(scratch, vertices.len());
+            for vertex in vertices {
+                moved.push(Vertex {
+                    point: vertex.point + offset,
+                    ..*vertex
+                });
+            }
+            commands.draw_mesh(&moved, indices, None);
+        });
+    }
+
+    /// Draws the `src_rect` of `image`, in pixels, stretched to fill `dst_rect`
+    /// at the given relative coordinates.
+    pub fn draw_image(&mut self, image: Handle<Image>, src_rect: Rect, dst_rect: Rect) {
+        let dst_rect = dst_rect + self.current_offset;
+        self.draw_commands.draw_image(image, src_rect, dst_rect);
+    }
+
+    /// Draws `src_rect` of `image` as a nine-patch stretched over `dst_rect` at
+    /// the given relative coordinates. See
+    /// [`DrawCommandList::draw_nine_patch`] for details.
+    pub fn draw_nine_patch(
+        &mut self,
+        image: Handle<Image>,
+        src_rect: Rect,
+        insets: Insets,
+        dst_rect: Rect,
+    ) {
+        let dst_rect = dst_rect + self.current_offset;
+        self.draw_commands
+            .draw_nine_patch(image, src_rect, insets, dst_rect);
+    }
+
+    /// Like [`Self::draw_image`], but multiplies every pixel of the image by
+    /// `tint`.
+    pub fn draw_tinted_image(
+        &mut self,
+        image: Handle<Image>,
+        src_rect: Rect,
+        dst_rect: Rect,
+        tint: Color,
+    ) {
+        let dst_rect = dst_rect + self.current_offset;
+        self.draw_commands
+            .draw_tinted_image(image, src_rect, dst_rect, tint);
+    }
+
+    /// Draws a line of shaped text with the pen starting at `origin` on the
+    /// baseline, at the given relative coordinates. See
+    /// [`GlyphAtlas::draw_line`] for details.
+    ///
+    /// Nothing is drawn unless text resources have been provided with
+    /// [`Self::set_text_resources`].
+    pub fn draw_text(&mut self, line: &ShapedLine, origin: Point, size: f32, color: Color) {
+        let origin = origin + self.current_offset;
+        if let Some((fonts, atlas)) = &mut self.text {
+            atlas.draw_line(
+                fonts,
+                self.draw_commands,
+                line,
+                origin,
+                size,
+                self.scale_factor,
+                color,
+                self.text_anti_aliasing,
+            );
+        }
+    }
+
+    /// Fills `dst_rect` at the given relative coordinates with `effect`. If
+    /// `image` is provided, the effect may sample its `src_rect`. See
+    /// [`DrawCommandList::draw_effect`] for details.
+    pub fn draw_effect(
+        &mut self,
+        effect: Handle<Effect>,
+        dst_rect: Rect,
+        image: Option<(Handle<Image>, Rect)>,
+        uniforms: &EffectUniforms,
+    ) {
+        let dst_rect = dst_rect + self.current_offset;
+        self.draw_commands
+            .draw_effect(effect, dst_rect, image, uniforms);
     }
 }
 
 #[must_use]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct BoxConstraint {
     min: Extent,
     max: Extent,
 }
 
 impl BoxConstraint {
+    /// Constrains widgets to be at least `min` and at most `max` in size.
+    pub fn new(min: Extent, max: Extent) -> Self {
+        Self {
+            min,
+            max: Extent {
+                width: max.width.max(min.width),
+                height: max.height.max(min.height),
+            },
+        }
+    }
+
     pub fn exact(extent: Extent) -> Self {
         Self {
             min: extent,
@@ -274,12 +1396,47 @@ impl BoxConstraint {
         }
     }
 
+    /// Constrains widgets to be at most `max` in size, and as small as they
+    /// like.
+    pub fn loose(max: Extent) -> Self {
+        Self {
+            min: Extent::zero(),
+            max,
+        }
+    }
+
+    #[must_use]
+    pub fn min(&self) -> Extent {
+        self.min
+    }
+
+    #[must_use]
+    pub fn max(&self) -> Extent {
+        self.max
+    }
+
+    /// Narrows these constraints to `other` as far as they allow.
+    pub fn constrain(&self, other: BoxConstraint) -> Self {
+        Self {
+            min: self.max_fit(other.min),
+            max: self.max_fit(other.max),
+        }
+    }
+
+    /// Shrinks the constraints by `insets`, for the content inside of them.
+    pub fn deflate(&self, insets: Insets) -> Self {
+        Self {
+            min: self.min.saturating_sub(insets.extent()),
+            max: self.max.saturating_sub(insets.extent()),
+        }
+    }
+
     /// Computes the largest extent that fits within the given constraints.
     #[must_use]
     pub fn max_fit(&self, extent: Extent) -> Extent {
         Extent {
             width: self.min.width.max(extent.width.min(self.max.width)),
-            height: self.min.width.max(extent.height.min(self.max.height)),
+            height: self.min.height.max(extent.height.min(self.max.height)),
         }
     }
 }
@@ -289,6 +1446,8 @@ impl BoxConstraint {
 enum RenderObjectStatus {
     #[default]
     NeedsLayout,
+    /// The widget's parent needs to be laid out again.
+    NeedsResize,
     Ready,
 }
 
@@ -300,7 +1459,19 @@ struct Layout {
     extent: Extent,
 }
 
-#[derive(Default)]
+/// The result of the last time a widget was laid out. See
+/// [`LayoutContext::layout`].
+#[derive(Clone, Copy)]
+struct CachedLayout {
+    /// The [`LayoutContext`] generation that the widget was laid out in.
+    generation: u64,
+    extent: Extent,
+}
+
+/// How much a disabled widget is faded, on top of its opacity. See
+/// [`WidgetState::set_disabled`].
+const DISABLED_OPACITY: f32 = 0.4;
+
 pub struct WidgetState {
     /// Determines if the widget needs to be laid out. This is set during the
     /// update phase and is cleared during the layout phase.
@@ -311,17 +1482,96 @@ pub struct WidgetState {
     origin: Point,
 
     layout: Layout,
+
+    /// The constraints that the widget was last laid out with, so that it can
+    /// be laid out again without its parent.
+    constraints: Option<BoxConstraint>,
+
+    /// Discarded when the widget needs to be laid out again.
+    cached_layout: Option<CachedLayout>,
+
+    disabled: bool,
+
+    opacity: f32,
+}
+
+impl Default for WidgetState {
+    fn default() -> Self {
+        Self {
+            status: RenderObjectStatus::default(),
+            origin: Point::zero(),
+            layout: Layout::default(),
+            constraints: None,
+            cached_layout: None,
+            disabled: false,
+            opacity: 1.0,
+        }
+    }
 }
 
 impl WidgetState {
+    /// Checks if the widget and its descendants are disabled.
+    #[must_use]
+    pub fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    /// Disables or enables the widget and all of its descendants. Disabled
+    /// widgets are drawn faded, aren't updated, so they ignore input, and
+    /// aren't found by [`hit_test::hit_test`].
+    ///
+    /// This doesn't change the widget's layout, so the widget is not laid out
+    /// again. The caller must redraw the widget's bounds, such as by returning
+    /// [`PostUpdate::NeedsRedraw`].
+    pub fn set_disabled(&mut self, disabled: bool) {
+        self.disabled = disabled;
+    }
+
+    /// How opaque the widget and its descendants are drawn, from 0 (invisible)
+    /// to 1 (as drawn).
+    #[must_use]
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Fades the widget and its descendants. Opacities multiply, so a widget
+    /// at half opacity inside another at half opacity is drawn at a quarter.
+    /// See [`DrawCommandList::push_opacity`].
+    ///
+    /// As with [`Self::set_disabled`], the caller must redraw the widget's
+    /// bounds.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    /// The opacity that the widget is drawn with, including the fading of
+    /// disabled widgets.
+    fn draw_opacity(&self) -> f32 {
+        if self.disabled {
+            self.opacity * DISABLED_OPACITY
+        } else {
+            self.opacity
+        }
+    }
+
     fn set_needs_layout(&mut self) {
         self.status = RenderObjectStatus::NeedsLayout;
+        self.cached_layout = None;
     }
 
     fn needs_layout(&self) -> bool {
         self.status == RenderObjectStatus::NeedsLayout
     }
 
+    fn set_needs_resize(&mut self) {
+        self.status = RenderObjectStatus::NeedsResize;
+        self.cached_layout = None;
+    }
+
+    fn needs_resize(&self) -> bool {
+        self.status == RenderObjectStatus::NeedsResize
+    }
+
     fn offset(&self) -> Offset {
         self.layout.offset
     }
@@ -334,7 +1584,7 @@ impl WidgetState {
         self.layout.extent
     }
 
-    fn rect(&self) -> Rect {
+    pub(crate) fn rect(&self) -> Rect {
         Rect::new(self.origin(), self.extent())
     }
 
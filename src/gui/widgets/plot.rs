@@ -0,0 +1,734 @@
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Point, PointF, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::input::Event,
+    text::{ShapedLine, TextStyle},
+};
+
+use super::{
+    BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext, Widget, WidgetState,
+};
+
+/// The space between the edges of the plot and its labels.
+const PADDING: Px = Px(6);
+
+/// The least space between ticks on the horizontal and vertical axes.
+const TICK_SPACING: (f32, f32) = (80.0, 40.0);
+
+/// How close the cursor must be to a point to show its tooltip.
+const HOVER_DISTANCE: f32 = 8.0;
+
+/// The radius of the points in a scatter plot.
+const POINT_RADIUS: Px = Px(3);
+
+/// The fraction of the space given to each bar that it fills.
+const BAR_FILL: f32 = 0.8;
+
+const BACKGROUND: Color = Color::WHITE;
+
+const GRID: Color = Color {
+    r: 0.9,
+    g: 0.9,
+    b: 0.9,
+    a: 1.0,
+};
+
+const AXIS: Color = Color {
+    r: 0.4,
+    g: 0.4,
+    b: 0.4,
+    a: 1.0,
+};
+
+const TOOLTIP: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 0.9,
+    a: 1.0,
+};
+
+/// How a [`Series`] is drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeriesKind {
+    /// Joins the points with lines.
+    Line,
+    /// Draws a dot at each point.
+    Scatter,
+    /// Draws a bar from 0 to each point.
+    Bar,
+}
+
+/// A set of points drawn in one color.
+#[derive(Clone, Debug)]
+pub struct Series {
+    /// Shown in the tooltip of each point.
+    pub name: String,
+    pub color: Color,
+    kind: SeriesKind,
+    points: Vec<(f64, f64)>,
+}
+
+impl Series {
+    pub fn new(name: impl Into<String>, kind: SeriesKind, color: Color) -> Self {
+        Self {
+            name: name.into(),
+            color,
+            kind,
+            points: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_points(mut self, points: impl IntoIterator<Item = (f64, f64)>) -> Self {
+        self.points.extend(points);
+        self
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> SeriesKind {
+        self.kind
+    }
+
+    /// The `(x, y)` coordinates of each point, in the order they were added.
+    #[must_use]
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+}
+
+/// The range of values shown along an axis, and the space between its ticks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Scale {
+    min: f64,
+    max: f64,
+    step: f64,
+}
+
+impl Scale {
+    /// Maps `value` to the fraction of the way from `min` to `max` that it is.
+    fn fraction(&self, value: f64) -> f64 {
+        (value - self.min) / (self.max - self.min)
+    }
+}
+
+/// The points of a series, converted to where they are drawn in the plot.
+///
+/// Lines keep at most four points in each column of pixels, the first, last,
+/// highest, and lowest, which look the same when drawn. Scatter plots skip
+/// points that land on the pixel before them. This keeps the cost of drawing a
+/// series proportional to the width of the plot instead of the number of
+/// points, and lets points added later be appended without converting the ones
+/// before them again.
+#[derive(Default)]
+struct Cache {
+    /// Each point and the index of the data point that it came from.
+    points: Vec<(PointF, usize)>,
+    /// The number of data points that have been added.
+    count: usize,
+    /// The pixel column of the last points of a line, and where in `points`
+    /// they start.
+    column: Option<(i32, usize)>,
+    /// The first, highest, lowest, and last points in the column.
+    extremes: [(PointF, usize); 4],
+}
+
+impl Cache {
+    fn push(&mut self, kind: SeriesKind, point: PointF, index: usize) {
+        self.count += 1;
+        match kind {
+            SeriesKind::Line => self.push_line(point, index),
+            SeriesKind::Scatter => {
+                let pixel = |p: PointF| (p.x.round() as i32, p.y.round() as i32);
+                if self
+                    .points
+                    .last()
+                    .is_none_or(|&(last, _)| pixel(last) != pixel(point))
+                {
+                    self.points.push((point, index));
+                }
+            }
+            SeriesKind::Bar => self.points.push((point, index)),
+        }
+    }
+
+    fn push_line(&mut self, point: PointF, index: usize) {
+        let column = point.x.floor() as i32;
+        let start = match self.column {
+            Some((c, start)) if c == column => start,
+            _ => {
+                self.column = Some((column, self.points.len()));
+                self.extremes = [(point, index); 4];
+                self.points.push((point, index));
+                return;
+            }
+        };
+
+        // Positive y points down, so the lowest value is the largest y.
+        let [_, highest, lowest, last] = &mut self.extremes;
+        if point.y < highest.0.y {
+            *highest = (point, index);
+        }
+        if point.y > lowest.0.y {
+            *lowest = (point, index);
+        }
+        *last = (point, index);
+
+        let mut kept = self.extremes;
+        kept.sort_by_key(|&(_, index)| index);
+        self.points.truncate(start);
+        let mut previous = None;
+        for (point, index) in kept {
+            if previous != Some(index) {
+                self.points.push((point, index));
+                previous = Some(index);
+            }
+        }
+    }
+}
+
+/// Plots series of points as lines, scattered dots, or bars, with labeled axes.
+///
+/// The axes fit every point unless their ranges are fixed with
+/// [`Self::with_x_range`] and [`Self::with_y_range`], and are rounded out to
+/// the nearest tick. Hovering over a point shows its series and coordinates.
+///
+/// Points can be added to a plot as they arrive with [`Self::push_points`],
+/// such as from within a [`Bind`](super::bind::Bind):
+///
+/// ```ignore
+/// Bind::new(&samples, plot, |samples, plot| {
+///     let new = &samples[plot.series()[0].points().len()..];
+///     plot.push_points(0, new);
+/// })
+/// ```
+///
+/// Only the new points are converted to where they are drawn, unless they fall
+/// outside of the axes and the axes have to change.
+#[must_use]
+pub struct Plot {
+    widget_state: WidgetState,
+    style: TextStyle,
+    pub color: Color,
+    series: Vec<Series>,
+    x_range: Option<(f64, f64)>,
+    y_range: Option<(f64, f64)>,
+    /// The series and index of the point under the cursor.
+    hover: Option<(usize, usize)>,
+    // Computed during layout.
+    x_scale: Scale,
+    y_scale: Scale,
+    /// Where the points are drawn, relative to the widget.
+    area: Rect,
+    caches: Vec<Cache>,
+    row_height: Px,
+    ascent: Px,
+    x_labels: Vec<(Px, ShapedLine, Px)>,
+    y_labels: Vec<(Px, ShapedLine, Px)>,
+    tooltip: Option<(ShapedLine, Px)>,
+}
+
+impl Plot {
+    pub fn new(style: TextStyle) -> Self {
+        let scale = Scale {
+            min: 0.0,
+            max: 1.0,
+            step: 1.0,
+        };
+        Self {
+            widget_state: WidgetState::default(),
+            style,
+            color: Color::BLACK,
+            series: Vec::new(),
+            x_range: None,
+            y_range: None,
+            hover: None,
+            x_scale: scale,
+            y_scale: scale,
+            area: Rect::zero(),
+            caches: Vec::new(),
+            row_height: Px(0),
+            ascent: Px(0),
+            x_labels: Vec::new(),
+            y_labels: Vec::new(),
+            tooltip: None,
+        }
+    }
+
+    pub fn with_series(mut self, series: Series) -> Self {
+        self.series.push(series);
+        self
+    }
+
+    /// Shows the values from `min` to `max` on the horizontal axis, instead
+    /// of fitting the axis to the points.
+    pub fn with_x_range(mut self, min: f64, max: f64) -> Self {
+        self.x_range = Some((min, max));
+        self
+    }
+
+    /// Shows the values from `min` to `max` on the vertical axis, instead of
+    /// fitting the axis to the points.
+    pub fn with_y_range(mut self, min: f64, max: f64) -> Self {
+        self.y_range = Some((min, max));
+        self
+    }
+
+    #[must_use]
+    pub fn series(&self) -> &[Series] {
+        &self.series
+    }
+
+    /// Replaces the points of the series at `index`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if there is no series at `index`.
+    pub fn set_points(&mut self, index: usize, points: impl IntoIterator<Item = (f64, f64)>) {
+        let series = &mut self.series[index];
+        series.points.clear();
+        series.points.extend(points);
+        if let Some(cache) = self.caches.get_mut(index) {
+            *cache = Cache::default();
+        }
+        self.hover = None;
+        self.widget_state.set_needs_layout();
+    }
+
+    /// Adds points to the end of the series at `index`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if there is no series at `index`.
+    pub fn push_points(&mut self, index: usize, points: &[(f64, f64)]) {
+        if !points.is_empty() {
+            self.series[index].points.extend_from_slice(points);
+            self.widget_state.set_needs_layout();
+        }
+    }
+
+    /// The scales that fit the axes to the points in a plot `area` pixels in
+    /// size.
+    fn scales(&self, area: Extent) -> (Scale, Scale) {
+        let mut x = (f64::INFINITY, f64::NEG_INFINITY);
+        let mut y = (f64::INFINITY, f64::NEG_INFINITY);
+        for series in &self.series {
+            if series.kind == SeriesKind::Bar {
+                y = (y.0.min(0.0), y.1.max(0.0));
+            }
+            for &(px, py) in &series.points {
+                if px.is_finite() && py.is_finite() {
+                    x = (x.0.min(px), x.1.max(px));
+                    y = (y.0.min(py), y.1.max(py));
+                }
+            }
+        }
+
+        let ticks = |length: Px, spacing: f32| (f32::from(length) / spacing).max(1.0) as usize;
+        let x = match self.x_range {
+            Some((min, max)) => fixed_scale(min, max, ticks(area.width, TICK_SPACING.0)),
+            None => nice_scale(x.0, x.1, ticks(area.width, TICK_SPACING.0)),
+        };
+        let y = match self.y_range {
+            Some((min, max)) => fixed_scale(min, max, ticks(area.height, TICK_SPACING.1)),
+            None => nice_scale(y.0, y.1, ticks(area.height, TICK_SPACING.1)),
+        };
+        (x, y)
+    }
+
+    /// Where the point at `(x, y)` is drawn, relative to the widget.
+    fn to_screen(&self, x: f64, y: f64) -> PointF {
+        let left = f64::from(f32::from(self.area.left));
+        let bottom = f64::from(f32::from(self.area.bottom));
+        let width = f64::from(f32::from(self.area.width()));
+        let height = f64::from(f32::from(self.area.height()));
+        PointF::new(
+            (left + self.x_scale.fraction(x) * width) as f32,
+            (bottom - self.y_scale.fraction(y) * height) as f32,
+        )
+    }
+
+    /// The width of each bar in a bar series with `count` points.
+    fn bar_width(&self, count: usize) -> f32 {
+        let width = f32::from(self.area.width()) / count.max(1) as f32;
+        (width * BAR_FILL).max(1.0)
+    }
+
+    /// Finds the point nearest to `cursor`, relative to the widget, if it is
+    /// close enough to show its tooltip.
+    fn point_at(&self, cursor: PointF) -> Option<(usize, usize)> {
+        if !self
+            .area
+            .contains_point(Point::new(Px::from(cursor.x), Px::from(cursor.y)))
+        {
+            return None;
+        }
+
+        let mut nearest = None;
+        let mut nearest_distance = HOVER_DISTANCE;
+        for (s, (series, cache)) in self.series.iter().zip(&self.caches).enumerate() {
+            let bar_width = self.bar_width(series.points.len());
+            for &(point, index) in &cache.points {
+                let distance = match series.kind {
+                    SeriesKind::Bar if (point.x - cursor.x).abs() <= bar_width / 2.0 => 0.0,
+                    SeriesKind::Bar => f32::INFINITY,
+                    SeriesKind::Line | SeriesKind::Scatter => (point - cursor).length(),
+                };
+                if distance <= nearest_distance {
+                    nearest_distance = distance;
+                    nearest = Some((s, index));
+                }
+            }
+        }
+        nearest
+    }
+}
+
+impl Widget for Plot {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        let hover = match context.event() {
+            Event::CursorMove { position } => {
+                let origin = self.widget_state.origin();
+                self.point_at(PointF::new(
+                    f32::from(position.x - origin.x),
+                    f32::from(position.y - origin.y),
+                ))
+            }
+            _ => return PostUpdate::NoChange,
+        };
+
+        if hover == self.hover {
+            PostUpdate::NoChange
+        } else {
+            self.hover = hover;
+            PostUpdate::NeedsLayout
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = constraints.max;
+
+        let metrics = context.measure_text("0", &self.style, None);
+        self.row_height = Px::from(metrics.line_height.ceil());
+        self.ascent = Px::from(metrics.ascent.round());
+
+        let shape = |text: &str| {
+            let mut metrics = context.measure_text(text, &self.style, None);
+            let width = Px::from(metrics.width.ceil());
+            (metrics.lines.swap_remove(0).shaped, width)
+        };
+
+        // The labels on the vertical axis decide how much space is left for
+        // the horizontal axis, so they are measured first.
+        let bottom = extent.height - self.row_height - PADDING - PADDING;
+        let height = bottom - PADDING - self.row_height / 2;
+        let (_, y_scale) = self.scales(Extent {
+            width: extent.width,
+            height,
+        });
+        let y_labels = ticks(&y_scale)
+            .map(|value| (value, shape(&format_tick(value, y_scale.step))))
+            .collect::<Vec<_>>();
+        let label_width = y_labels.iter().map(|(_, (_, w))| *w).max().unwrap_or(Px(0));
+
+        let area = Rect {
+            left: PADDING + label_width + PADDING,
+            top: PADDING + self.row_height / 2,
+            right: extent.width - PADDING - PADDING,
+            bottom,
+        };
+        let (x_scale, y_scale) = self.scales(area.extent());
+
+        if (x_scale, y_scale, area) != (self.x_scale, self.y_scale, self.area)
+            || self.caches.len() != self.series.len()
+        {
+            self.x_scale = x_scale;
+            self.y_scale = y_scale;
+            self.area = area;
+            self.caches.clear();
+            self.caches.resize_with(self.series.len(), Cache::default);
+        }
+
+        for i in 0..self.series.len() {
+            let series = &self.series[i];
+            let start = self.caches[i].count;
+            let new = series.points[start..]
+                .iter()
+                .map(|&(x, y)| self.to_screen(x, y))
+                .collect::<Vec<_>>();
+            for (offset, point) in new.into_iter().enumerate() {
+                self.caches[i].push(series.kind, point, start + offset);
+            }
+        }
+
+        self.y_labels = y_labels
+            .into_iter()
+            .map(|(value, (line, width))| {
+                let y = Px::from(self.to_screen(self.x_scale.min, value).y.round());
+                (y, line, width)
+            })
+            .collect();
+        self.x_labels = ticks(&self.x_scale)
+            .map(|value| {
+                let x = Px::from(self.to_screen(value, self.y_scale.min).x.round());
+                let (line, width) = shape(&format_tick(value, self.x_scale.step));
+                (x, line, width)
+            })
+            .collect();
+
+        self.tooltip = self.hover.and_then(|(s, index)| {
+            let series = self.series.get(s)?;
+            let &(x, y) = series.points.get(index)?;
+            Some(shape(&format!("{}: {x}, {y}", series.name)))
+        });
+
+        extent
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        let bounds = Rect::new(Point::zero(), extent);
+        let area = self.area;
+        canvas.draw_rect(bounds, &Paint::Fill { color: BACKGROUND });
+
+        for (y, line, width) in &self.y_labels {
+            canvas.stroke_line(
+                Point::new(area.left, *y),
+                Point::new(area.right, *y),
+                &Stroke::solid(GRID, 1.0),
+            );
+            canvas.draw_text(
+                line,
+                Point::new(
+                    area.left - PADDING - *width,
+                    *y - self.row_height / 2 + self.ascent,
+                ),
+                self.style.size,
+                self.color,
+            );
+        }
+        for (x, line, width) in &self.x_labels {
+            canvas.stroke_line(
+                Point::new(*x, area.top),
+                Point::new(*x, area.bottom),
+                &Stroke::solid(GRID, 1.0),
+            );
+            canvas.draw_text(
+                line,
+                Point::new(*x - *width / 2, area.bottom + PADDING + self.ascent),
+                self.style.size,
+                self.color,
+            );
+        }
+
+        canvas.push_clip_rounded_rect(area, Px(0));
+        let base = self
+            .to_screen(0.0, 0.0_f64.clamp(self.y_scale.min, self.y_scale.max))
+            .y;
+        for (series, cache) in self.series.iter().zip(&self.caches) {
+            match series.kind {
+                SeriesKind::Line => {
+                    let points = cache.points.iter().map(|(p, _)| *p).collect::<Vec<_>>();
+                    canvas.stroke_polyline(&points, false, &Stroke::solid(series.color, 2.0));
+                }
+                SeriesKind::Scatter => {
+                    for (point, _) in &cache.points {
+                        canvas.draw_circle(
+                            Point::new(Px::from(point.x.round()), Px::from(point.y.round())),
+                            POINT_RADIUS,
+                            &Paint::Fill {
+                                color: series.color,
+                            },
+                        );
+                    }
+                }
+                SeriesKind::Bar => {
+                    let half = self.bar_width(series.points.len()) / 2.0;
+                    for (point, _) in &cache.points {
+                        let rect = Rect {
+                            left: Px::from((point.x - half).round()),
+                            top: Px::from(point.y.min(base).round()),
+                            right: Px::from((point.x + half).round()),
+                            bottom: Px::from(point.y.max(base).round()),
+                        };
+                        canvas.draw_rect(
+                            rect,
+                            &Paint::Fill {
+                                color: series.color,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+        canvas.pop_clip();
+
+        canvas.stroke_line(
+            Point::new(area.left, area.top),
+            Point::new(area.left, area.bottom),
+            &Stroke::solid(AXIS, 1.0),
+        );
+        canvas.stroke_line(
+            Point::new(area.left, area.bottom),
+            Point::new(area.right, area.bottom),
+            &Stroke::solid(AXIS, 1.0),
+        );
+
+        let hovered = self.hover.and_then(|(s, index)| {
+            let &(x, y) = self.series.get(s)?.points.get(index)?;
+            Some((self.series[s].color, self.to_screen(x, y)))
+        });
+        if let (Some((color, point)), Some((line, width))) = (hovered, &self.tooltip) {
+            let point = Point::new(Px::from(point.x.round()), Px::from(point.y.round()));
+            canvas.stroke_circle(point, POINT_RADIUS + Px(2), &Stroke::solid(color, 2.0));
+
+            // The tooltip is shown above and to the right of the point, unless
+            // that would put it outside of the plot.
+            let size = Extent {
+                width: *width + PADDING + PADDING,
+                height: self.row_height + PADDING,
+            };
+            let mut left = point.x + PADDING;
+            if left + size.width > extent.width {
+                left = (point.x - PADDING - size.width).max(Px(0));
+            }
+            let top = (point.y - PADDING - size.height).max(Px(0));
+            let rect = Rect::new(Point::new(left, top), size);
+
+            canvas.draw_rect(rect, &Paint::Fill { color: TOOLTIP });
+            canvas.stroke_rect(rect, &Stroke::solid(AXIS, 1.0));
+            canvas.draw_text(
+                line,
+                Point::new(left + PADDING, top + PADDING / 2 + self.ascent),
+                self.style.size,
+                self.color,
+            );
+        }
+    }
+}
+
+/// Chooses a step of 1, 2, or 5 times a power of 10 that splits the values from
+/// `min` to `max` into about `count` ticks.
+fn tick_step(min: f64, max: f64, count: usize) -> f64 {
+    let raw = (max - min) / count.max(1) as f64;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let nice = match raw / magnitude {
+        f if f <= 1.0 => 1.0,
+        f if f <= 2.0 => 2.0,
+        f if f <= 5.0 => 5.0,
+        _ => 10.0,
+    };
+    nice * magnitude
+}
+
+/// Fits an axis to the values from `min` to `max`, rounded out to whole ticks.
+fn nice_scale(min: f64, max: f64, count: usize) -> Scale {
+    let (min, max) = if !min.is_finite() || !max.is_finite() {
+        (0.0, 1.0)
+    } else if min == max {
+        // A single value is shown in the middle of the axis.
+        let margin = if min == 0.0 { 1.0 } else { min.abs() / 2.0 };
+        (min - margin, max + margin)
+    } else {
+        (min, max)
+    };
+
+    let step = tick_step(min, max, count);
+    Scale {
+        min: (min / step).floor() * step,
+        max: (max / step).ceil() * step,
+        step,
+    }
+}
+
+/// Shows exactly the values from `min` to `max` on an axis.
+fn fixed_scale(min: f64, max: f64, count: usize) -> Scale {
+    if min < max {
+        Scale {
+            min,
+            max,
+            step: tick_step(min, max, count),
+        }
+    } else {
+        nice_scale(min, max, count)
+    }
+}
+
+/// The values of the ticks on an axis.
+fn ticks(scale: &Scale) -> impl Iterator<Item = f64> + '_ {
+    let first = (scale.min / scale.step).ceil() as i64;
+    let last = (scale.max / scale.step + 1e-9).floor() as i64;
+    (first..=last).map(|i| i as f64 * scale.step)
+}
+
+/// Formats a tick's value with as many decimal places as the steps between
+/// ticks need.
+fn format_tick(value: f64, step: f64) -> String {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let value = if value.abs() < step * 1e-9 {
+        0.0
+    } else {
+        value
+    };
+    format!("{value:.decimals$}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choose_ticks() {
+        let scale = nice_scale(0.3, 9.2, 5);
+        assert_eq!((scale.min, scale.max, scale.step), (0.0, 10.0, 2.0));
+        assert_eq!(
+            ticks(&scale).collect::<Vec<_>>(),
+            [0.0, 2.0, 4.0, 6.0, 8.0, 10.0]
+        );
+
+        let scale = nice_scale(5.0, 5.0, 4);
+        assert!(scale.min < 5.0 && scale.max > 5.0);
+
+        let scale = fixed_scale(-1.0, 1.0, 4);
+        assert_eq!((scale.min, scale.max, scale.step), (-1.0, 1.0, 0.5));
+
+        assert_eq!(format_tick(0.25, 0.05), "0.25");
+        assert_eq!(format_tick(1500.0, 500.0), "1500");
+        assert_eq!(format_tick(-1e-17, 0.1), "0.0");
+    }
+
+    #[test]
+    fn decimate_lines() {
+        let mut cache = Cache::default();
+        let ys = [5.0, 1.0, 9.0, 3.0, 4.0];
+        for (i, y) in ys.into_iter().enumerate() {
+            cache.push(SeriesKind::Line, PointF::new(10.0 + i as f32 * 0.1, y), i);
+        }
+        cache.push(SeriesKind::Line, PointF::new(11.0, 2.0), 5);
+
+        // The first, highest, lowest, and last points of the first column are
+        // kept in order, followed by the next column.
+        let indices = cache.points.iter().map(|(_, i)| *i).collect::<Vec<_>>();
+        assert_eq!(indices, [0, 1, 2, 4, 5]);
+        assert_eq!(cache.count, 6);
+
+        let mut cache = Cache::default();
+        cache.push(SeriesKind::Scatter, PointF::new(1.0, 1.0), 0);
+        cache.push(SeriesKind::Scatter, PointF::new(1.2, 0.9), 1);
+        cache.push(SeriesKind::Scatter, PointF::new(3.0, 1.0), 2);
+        assert_eq!(cache.points.len(), 2);
+    }
+}
@@ -0,0 +1,690 @@
+use std::{any::Any, time::Instant};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Offset, Point, Px, Rect},
+        Paint,
+    },
+    gui::{
+        gestures::Gesture,
+        input::{Event, FocusId, MouseButton},
+    },
+    shell::event::Key,
+};
+
+use super::{
+    kinetic::Kinetic, split_panel::Axis, BoxConstraint, DrawContext, LayoutContext, PostUpdate,
+    UpdateContext, Widget, WidgetState,
+};
+
+/// The thickness of the scrollbars, which are drawn over the edges of the
+/// content.
+const SCROLLBAR_WIDTH: Px = Px(8);
+
+/// The shortest that a scrollbar's thumb can be, so that it can still be
+/// grabbed when the content is very long.
+const MIN_THUMB_LENGTH: Px = Px(16);
+
+/// The largest extent that the child can have along a scrolled axis. Layout
+/// coordinates are 16 bits, so this leaves room for the view's own position
+/// in the window.
+const MAX_CONTENT: Px = Px(i16::MAX / 2);
+
+/// How far the arrow keys scroll, in pixels.
+const LINE_SCROLL: Px = Px(40);
+
+const TRACK: Color = Color {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.1,
+};
+
+const THUMB: Color = Color {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.4,
+};
+
+const DRAGGED_THUMB: Color = Color {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.6,
+};
+
+/// A scrollbar thumb being dragged with the mouse.
+#[derive(Clone, Copy, Debug)]
+struct Drag {
+    axis: Axis,
+    /// The distance from the start of the thumb to the cursor when the thumb
+    /// was grabbed.
+    grab: Px,
+}
+
+/// Shows part of a child that may be larger than the view, offset by how far
+/// it has been scrolled.
+///
+/// The child is laid out at least as large as the view, and may grow along
+/// each axis that scrolls. Children that fill all of the space they're given
+/// will be very large, so the child should size itself to its content.
+///
+/// The view scrolls with the mouse wheel, by panning with a finger, by
+/// dragging or clicking its scrollbars, and with the arrow keys, Page Up,
+/// Page Down, Home, and End when it has the keyboard focus. It takes the
+/// focus when clicked, unless a child takes it instead.
+///
+/// Scrolling is smooth unless turned off with
+/// [`Self::with_smooth_scrolling`]: the mouse wheel eases toward where it was
+/// turned to, trackpads and fingers leave the content moving when they let
+/// go, and content pulled past its ends springs back.
+#[must_use]
+pub struct ScrollView<W: Widget + 'static> {
+    widget_state: WidgetState,
+    pub child: W,
+    focus: FocusId,
+    horizontal: bool,
+    vertical: bool,
+    smooth: bool,
+    /// How far the child is scrolled, as a distance from its top-left corner.
+    /// This is [`Self::x`] and [`Self::y`] rounded to whole pixels.
+    scroll: Offset,
+    x: Kinetic,
+    y: Kinetic,
+    /// The extent of the child from the last layout.
+    content: Extent,
+    drag: Option<Drag>,
+}
+
+impl<W: Widget + 'static> ScrollView<W> {
+    /// Creates a view that scrolls `child` vertically.
+    pub fn new(child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            child,
+            focus: FocusId::new(),
+            horizontal: false,
+            vertical: true,
+            smooth: true,
+            scroll: Offset::zero(),
+            x: Kinetic::default(),
+            y: Kinetic::default(),
+            content: Extent::zero(),
+            drag: None,
+        }
+    }
+
+    /// Allows the child to be wider than the view and scrolled horizontally.
+    pub fn with_horizontal(mut self, horizontal: bool) -> Self {
+        self.horizontal = horizontal;
+        self
+    }
+
+    /// Allows the child to be taller than the view and scrolled vertically.
+    pub fn with_vertical(mut self, vertical: bool) -> Self {
+        self.vertical = vertical;
+        self
+    }
+
+    /// Animates scrolling with the mouse wheel, trackpads, and fingers, or
+    /// moves the child by exactly as far as they scroll as soon as they do.
+    pub fn with_smooth_scrolling(mut self, smooth: bool) -> Self {
+        self.smooth = smooth;
+        self
+    }
+
+    /// How far the child is scrolled, as a distance from its top-left corner.
+    /// This may be past the ends of the child while it springs back.
+    pub fn scroll(&self) -> Offset {
+        self.scroll
+    }
+
+    /// Scrolls the child so that `scroll` is at the view's top-left corner,
+    /// as far as the child's extent allows.
+    pub fn set_scroll(&mut self, scroll: Offset) {
+        if self.scroll_to(scroll) {
+            self.widget_state.set_needs_layout();
+        }
+    }
+
+    /// The identifier used to give the view the keyboard focus.
+    #[must_use]
+    pub fn focus_id(&self) -> FocusId {
+        self.focus
+    }
+
+    /// Moves the scroll position to `scroll`, clamped to the child's extent.
+    /// Returns `true` if it changed.
+    fn scroll_to(&mut self, scroll: Offset) -> bool {
+        let viewport = self.widget_state.extent();
+        let clamped = Offset {
+            x: scroll
+                .x
+                .clamp(Px(0), max_scroll(viewport.width, self.content.width)),
+            y: scroll
+                .y
+                .clamp(Px(0), max_scroll(viewport.height, self.content.height)),
+        };
+        let changed = clamped != self.scroll;
+        self.scroll = clamped;
+        self.x.jump(f32::from(clamped.x));
+        self.y.jump(f32::from(clamped.y));
+        changed
+    }
+
+    /// How far the child can be scrolled along each axis.
+    fn max_scroll(&self) -> (f32, f32) {
+        let viewport = self.widget_state.extent();
+        (
+            f32::from(max_scroll(viewport.width, self.content.width)),
+            f32::from(max_scroll(viewport.height, self.content.height)),
+        )
+    }
+
+    /// Checks if the child will keep scrolling without any more input.
+    fn is_moving(&self) -> bool {
+        self.x.is_moving() || self.y.is_moving()
+    }
+
+    /// Rounds the scroll position to whole pixels after the child has been
+    /// scrolled smoothly, and finds what needs to be done about it.
+    fn update_scroll(&mut self) -> PostUpdate {
+        let scroll = Offset {
+            x: Px::from(self.x.position().round()),
+            y: Px::from(self.y.position().round()),
+        };
+
+        if scroll != self.scroll {
+            self.scroll = scroll;
+            PostUpdate::NeedsLayout
+        } else if self.is_moving() {
+            // Keeps frames coming so that the movement carries on.
+            PostUpdate::NeedsRedraw
+        } else {
+            PostUpdate::NoChange
+        }
+    }
+
+    /// Scrolls by `delta` as asked for by the mouse wheel or a trackpad.
+    fn wheel(&mut self, delta: Offset, precise: bool) -> PostUpdate {
+        let delta = Offset {
+            x: if self.horizontal { delta.x } else { Px(0) },
+            y: if self.vertical { delta.y } else { Px(0) },
+        };
+
+        if !self.smooth {
+            return if self.scroll_to(self.scroll.saturating_add(delta)) {
+                PostUpdate::NeedsLayout
+            } else {
+                PostUpdate::NoChange
+            };
+        }
+
+        // Trackpads move the content as they go, while the wheel eases toward
+        // where it was turned to.
+        let (max_x, max_y) = self.max_scroll();
+        if delta.x != 0 {
+            if precise {
+                self.x.drag(f32::from(delta.x), max_x);
+            } else {
+                self.x.wheel(f32::from(delta.x), max_x);
+            }
+        }
+        if delta.y != 0 {
+            if precise {
+                self.y.drag(f32::from(delta.y), max_y);
+            } else {
+                self.y.wheel(f32::from(delta.y), max_y);
+            }
+        }
+
+        self.update_scroll()
+    }
+
+    /// Moves the child by `delta`, following a finger.
+    fn pan(&mut self, delta: Offset) -> PostUpdate {
+        // The content follows the finger, so it scrolls the opposite way to
+        // the mouse wheel.
+        let delta = Offset {
+            x: if self.horizontal { delta.x } else { Px(0) },
+            y: if self.vertical { delta.y } else { Px(0) },
+        };
+
+        if !self.smooth {
+            return if self.scroll_to(self.scroll.saturating_sub(delta)) {
+                PostUpdate::NeedsLayout
+            } else {
+                PostUpdate::NoChange
+            };
+        }
+
+        let (max_x, max_y) = self.max_scroll();
+        if delta.x != 0 {
+            self.x.drag(-f32::from(delta.x), max_x);
+        }
+        if delta.y != 0 {
+            self.y.drag(-f32::from(delta.y), max_y);
+        }
+        self.update_scroll()
+    }
+
+    /// Checks if the child is too large to fit in the view along `axis`.
+    fn overflows(&self, axis: Axis) -> bool {
+        let viewport = self.widget_state.extent();
+        match axis {
+            Axis::X => self.horizontal && self.content.width > viewport.width,
+            Axis::Y => self.vertical && self.content.height > viewport.height,
+        }
+    }
+
+    /// The area that the scrollbar for `axis` moves in, relative to the view.
+    /// Space is left in the corner if both scrollbars are shown.
+    fn track_rect(&self, axis: Axis) -> Rect {
+        let Extent { width, height } = self.widget_state.extent();
+        let corner = |other: Axis| {
+            if self.overflows(other) {
+                SCROLLBAR_WIDTH
+            } else {
+                Px(0)
+            }
+        };
+
+        match axis {
+            Axis::X => Rect {
+                left: Px(0),
+                top: height - SCROLLBAR_WIDTH,
+                right: width - corner(Axis::Y),
+                bottom: height,
+            },
+            Axis::Y => Rect {
+                left: width - SCROLLBAR_WIDTH,
+                top: Px(0),
+                right: width,
+                bottom: height - corner(Axis::X),
+            },
+        }
+    }
+
+    /// The part of the scrollbar for `axis` that can be dragged, relative to
+    /// the view.
+    fn thumb_rect(&self, axis: Axis) -> Rect {
+        let track = self.track_rect(axis);
+        let viewport = self.widget_state.extent();
+        match axis {
+            Axis::X => {
+                let (start, length) = thumb(
+                    track.width(),
+                    viewport.width,
+                    self.content.width,
+                    self.scroll.x.max(Px(0)),
+                );
+                Rect {
+                    left: track.left + start,
+                    right: track.left + start + length,
+                    ..track
+                }
+            }
+            Axis::Y => {
+                let (start, length) = thumb(
+                    track.height(),
+                    viewport.height,
+                    self.content.height,
+                    self.scroll.y.max(Px(0)),
+                );
+                Rect {
+                    top: track.top + start,
+                    bottom: track.top + start + length,
+                    ..track
+                }
+            }
+        }
+    }
+
+    /// Scrolls so that the thumb for `axis` starts `start` pixels along its
+    /// track.
+    fn drag_thumb(&mut self, axis: Axis, start: Px) -> bool {
+        let track = self.track_rect(axis);
+        let viewport = self.widget_state.extent();
+        match axis {
+            Axis::X => {
+                let x = scroll_for_thumb(track.width(), viewport.width, self.content.width, start);
+                self.scroll_to(Offset { x, ..self.scroll })
+            }
+            Axis::Y => {
+                let y =
+                    scroll_for_thumb(track.height(), viewport.height, self.content.height, start);
+                self.scroll_to(Offset { y, ..self.scroll })
+            }
+        }
+    }
+
+    /// Handles a press of the left mouse button at `point`, relative to the
+    /// view. Returns `None` if the press wasn't on a scrollbar.
+    fn press_scrollbar(&mut self, point: Point) -> Option<PostUpdate> {
+        for axis in [Axis::X, Axis::Y] {
+            if !self.overflows(axis) || !self.track_rect(axis).contains_point(point) {
+                continue;
+            }
+
+            let thumb = self.thumb_rect(axis);
+            let viewport = self.widget_state.extent();
+            let (along, thumb_start, page) = match axis {
+                Axis::X => (point.x, thumb.left, viewport.width),
+                Axis::Y => (point.y, thumb.top, viewport.height),
+            };
+
+            if thumb.contains_point(point) {
+                self.drag = Some(Drag {
+                    axis,
+                    grab: along - thumb_start,
+                });
+                return Some(PostUpdate::NeedsRedraw);
+            }
+
+            // Clicking the track pages toward the cursor.
+            let page = if along < thumb_start {
+                Px(0) - page
+            } else {
+                page
+            };
+            let delta = match axis {
+                Axis::X => Offset { x: page, y: Px(0) },
+                Axis::Y => Offset { x: Px(0), y: page },
+            };
+            let changed = self.scroll_to(self.scroll.saturating_add(delta));
+            return Some(if changed {
+                PostUpdate::NeedsLayout
+            } else {
+                PostUpdate::NoChange
+            });
+        }
+
+        None
+    }
+
+    fn handle_key(&mut self, key: Key) -> bool {
+        let viewport = self.widget_state.extent();
+        let (x, y) = match key {
+            Key::Left => (Px(0) - LINE_SCROLL, Px(0)),
+            Key::Right => (LINE_SCROLL, Px(0)),
+            Key::Up => (Px(0), Px(0) - LINE_SCROLL),
+            Key::Down => (Px(0), LINE_SCROLL),
+            Key::PageUp => (Px(0), Px(0) - viewport.height),
+            Key::PageDown => (Px(0), viewport.height),
+            Key::Home => (Px(0), Px::MIN),
+            Key::End => (Px(0), Px::MAX),
+            _ => return false,
+        };
+
+        self.scroll_to(self.scroll.saturating_add(Offset { x, y }))
+    }
+}
+
+/// The state of a [`ScrollView`] that is carried over when the widget tree is
+/// rebuilt.
+struct SavedState {
+    focus: FocusId,
+    scroll: Offset,
+}
+
+impl<W: Widget + 'static> Widget for ScrollView<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        // Scrolling moves the child, which is done by laying it out again.
+        let rect = self.widget_state.rect();
+        let cursor = context.cursor_position();
+
+        match context.event() {
+            Event::None => PostUpdate::NoChange,
+            Event::Scroll { delta, precise } => {
+                if rect.contains_point(cursor) {
+                    self.wheel(delta, precise)
+                } else {
+                    PostUpdate::NoChange
+                }
+            }
+            Event::CursorMove { position } => match self.drag {
+                Some(Drag { axis, grab }) => {
+                    let track = self.track_rect(axis);
+                    let start = match axis {
+                        Axis::X => position.x - rect.left - track.left - grab,
+                        Axis::Y => position.y - rect.top - track.top - grab,
+                    };
+                    if self.drag_thumb(axis, start) {
+                        PostUpdate::NeedsLayout
+                    } else {
+                        PostUpdate::NoChange
+                    }
+                }
+                None => {
+                    if rect.contains_point(position) {
+                        context.update(&mut self.child);
+                    }
+                    PostUpdate::NoChange
+                }
+            },
+            Event::MouseButton { button, state } => {
+                if button == MouseButton::Left && state.is_released() && self.drag.is_some() {
+                    self.drag = None;
+                    return PostUpdate::NeedsRedraw;
+                }
+
+                if !rect.contains_point(cursor) {
+                    return PostUpdate::NoChange;
+                }
+
+                if button == MouseButton::Left && state.is_pressed() {
+                    let point = Point {
+                        x: cursor.x - rect.left,
+                        y: cursor.y - rect.top,
+                    };
+                    if let Some(post_update) = self.press_scrollbar(point) {
+                        context.set_focus(Some(self.focus));
+                        return post_update;
+                    }
+
+                    // The child may take the focus for itself.
+                    context.set_focus(Some(self.focus));
+                }
+
+                context.update(&mut self.child);
+                PostUpdate::NoChange
+            }
+            Event::KeyPress { key, .. } => {
+                if context.is_focused(self.focus) {
+                    if self.handle_key(key) {
+                        PostUpdate::NeedsLayout
+                    } else {
+                        PostUpdate::NoChange
+                    }
+                } else {
+                    context.update(&mut self.child);
+                    PostUpdate::NoChange
+                }
+            }
+            Event::Gesture {
+                gesture: Gesture::Pan { position, delta },
+            } if rect.contains_point(position) => self.pan(delta),
+            Event::Text { .. }
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => {
+                context.update(&mut self.child);
+                PostUpdate::NoChange
+            }
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let viewport = constraints.max;
+        let child_constraints = BoxConstraint {
+            min: viewport,
+            max: Extent {
+                width: if self.horizontal {
+                    MAX_CONTENT
+                } else {
+                    viewport.width
+                },
+                height: if self.vertical {
+                    MAX_CONTENT
+                } else {
+                    viewport.height
+                },
+            },
+        };
+
+        self.content = context.layout(&mut self.child, child_constraints);
+
+        // The child may have shrunk since it was last scrolled. If it is
+        // still moving, it springs back by itself.
+        if !self.is_moving() {
+            let max = Offset {
+                x: max_scroll(viewport.width, self.content.width),
+                y: max_scroll(viewport.height, self.content.height),
+            };
+            if self.scroll.x > max.x || self.scroll.y > max.y {
+                let clamped = Offset {
+                    x: self.scroll.x.min(max.x),
+                    y: self.scroll.y.min(max.y),
+                };
+                self.scroll = clamped;
+                self.x.jump(f32::from(clamped.x));
+                self.y.jump(f32::from(clamped.y));
+            }
+        }
+
+        context.position_widget(&mut self.child, Offset::zero() - self.scroll, self.content);
+
+        viewport
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        canvas.push_clip_rounded_rect(Rect::new(Point::zero(), extent), Px(0));
+        canvas.draw(&self.child);
+        canvas.pop_clip();
+
+        for axis in [Axis::X, Axis::Y] {
+            if self.overflows(axis) {
+                let is_dragged = matches!(self.drag, Some(drag) if drag.axis == axis);
+                canvas.draw_rect(self.track_rect(axis), &Paint::Fill { color: TRACK });
+                canvas.draw_rect(
+                    self.thumb_rect(axis),
+                    &Paint::Fill {
+                        color: if is_dragged { DRAGGED_THUMB } else { THUMB },
+                    },
+                );
+            }
+        }
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(SavedState {
+            focus: self.focus,
+            scroll: self.scroll,
+        }))
+    }
+
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        if let Ok(state) = state.downcast::<SavedState>() {
+            self.focus = state.focus;
+            // Clamped to the new child's extent during layout.
+            self.scroll = state.scroll;
+            self.x.jump(f32::from(state.scroll.x));
+            self.y.jump(f32::from(state.scroll.y));
+            self.widget_state.set_needs_layout();
+        }
+    }
+
+    fn accept_animation_frame(&mut self, now: Instant) -> PostUpdate {
+        let (max_x, max_y) = self.max_scroll();
+        let moved = self.x.advance(now, max_x) | self.y.advance(now, max_y);
+        match self.update_scroll() {
+            PostUpdate::NoChange if moved => PostUpdate::NeedsRedraw,
+            post_update => post_update,
+        }
+    }
+}
+
+/// How far content of length `content` can be scrolled in a view of length
+/// `viewport`.
+fn max_scroll(viewport: Px, content: Px) -> Px {
+    content.saturating_sub(viewport).max(Px(0))
+}
+
+/// Finds the start and length of a scrollbar's thumb within a track of length
+/// `track`. The thumb's length is in proportion to how much of the content is
+/// visible.
+fn thumb(track: Px, viewport: Px, content: Px, scroll: Px) -> (Px, Px) {
+    let max = max_scroll(viewport, content);
+    if max == 0 {
+        return (Px(0), track);
+    }
+
+    let track = f32::from(track);
+    let length = (track * f32::from(viewport) / f32::from(content))
+        .max(f32::from(MIN_THUMB_LENGTH))
+        .min(track);
+    let start = (track - length) * f32::from(scroll) / f32::from(max);
+    (Px::from(start.round()), Px::from(length.round()))
+}
+
+/// The inverse of [`thumb`]: finds the scroll position that puts the start of
+/// the thumb at `start`.
+fn scroll_for_thumb(track: Px, viewport: Px, content: Px, start: Px) -> Px {
+    let max = max_scroll(viewport, content);
+    let (_, length) = thumb(track, viewport, content, Px(0));
+    let range = f32::from(track - length);
+    if range <= 0.0 {
+        return Px(0);
+    }
+
+    let fraction = (f32::from(start) / range).clamp(0.0, 1.0);
+    Px::from((fraction * f32::from(max)).round())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrollbar_thumb() {
+        // Half of the content is visible, so the thumb fills half the track.
+        assert_eq!(thumb(Px(100), Px(100), Px(200), Px(0)), (Px(0), Px(50)));
+        assert_eq!(thumb(Px(100), Px(100), Px(200), Px(100)), (Px(50), Px(50)));
+
+        // Very long content still has a thumb that can be grabbed.
+        assert_eq!(
+            thumb(Px(100), Px(100), Px(10000), Px(0)).1,
+            MIN_THUMB_LENGTH
+        );
+
+        // Content that fits doesn't scroll.
+        assert_eq!(thumb(Px(100), Px(100), Px(50), Px(0)), (Px(0), Px(100)));
+    }
+
+    #[test]
+    fn drag_thumb() {
+        assert_eq!(scroll_for_thumb(Px(100), Px(100), Px(200), Px(25)), Px(50));
+        assert_eq!(scroll_for_thumb(Px(100), Px(100), Px(200), Px(-10)), Px(0));
+        assert_eq!(scroll_for_thumb(Px(100), Px(100), Px(200), Px(80)), Px(100));
+        assert_eq!(scroll_for_thumb(Px(100), Px(100), Px(50), Px(10)), Px(0));
+    }
+}
@@ -1,144 +1,544 @@
+use std::any::Any;
+
 use crate::{
-    gfx::geometry::{Extent, Offset, Px},
-    gui::input::Event,
+    gfx::{
+        color::Color,
+        geometry::{Extent, Offset, Point, Px, Rect},
+        Paint,
+    },
+    gui::input::{ButtonState, Event, MouseButton},
+    shell::CursorIcon,
 };
 
 use super::{
     BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext, Widget, WidgetState,
 };
 
+/// The thickness of the dividers between panes.
+const DIVIDER: Px = Px(4);
+
+const DIVIDER_COLOR: Color = Color {
+    r: 0.8,
+    g: 0.8,
+    b: 0.8,
+    a: 1.0,
+};
+
+/// The color of a divider under the cursor or being dragged.
+const ACTIVE_DIVIDER_COLOR: Color = Color {
+    r: 0.0,
+    g: 0.47,
+    b: 0.84,
+    a: 1.0,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Axis {
     X,
     Y,
 }
 
+impl Axis {
+    /// The component of `point` along the axis.
+    fn of_point(self, point: Point) -> Px {
+        match self {
+            Self::X => point.x,
+            Self::Y => point.y,
+        }
+    }
+
+    /// The cursor for resizing along the axis.
+    fn resize_cursor(self) -> CursorIcon {
+        match self {
+            Self::X => CursorIcon::ResizeHorizontal,
+            Self::Y => CursorIcon::ResizeVertical,
+        }
+    }
+
+    /// The dimension of `extent` along the axis.
+    fn of_extent(self, extent: Extent) -> Px {
+        match self {
+            Self::X => extent.width,
+            Self::Y => extent.height,
+        }
+    }
+}
+
+type ResizeCallback = Box<dyn FnMut(&[f32])>;
+
+/// Lays its children out side by side along an axis, separated by dividers
+/// that the user can drag to resize them.
+///
+/// Each pane takes a fraction of the space, its ratio, which is kept as the
+/// panel is resized. Panes can be limited to a range of sizes, in which case
+/// the other panes make up the difference.
 #[must_use]
 pub struct SplitPanel<W: Widget + 'static> {
     state: WidgetState,
     children: Vec<W>,
     axis: Axis,
+    /// The fraction of the space taken by each pane, which add up to 1.
+    ratios: Vec<f32>,
+    /// The smallest and largest sizes of each pane.
+    limits: Vec<(Px, Px)>,
+    /// The size of each pane from the last layout.
+    sizes: Vec<Px>,
+    /// The divider under the cursor, identified by the pane before it.
+    hovered: Option<usize>,
+    /// The divider being dragged, and the distance from the cursor to the
+    /// start of the divider when the drag began.
+    drag: Option<(usize, Px)>,
+    on_resize: Option<ResizeCallback>,
 }
 
 impl<W: Widget + 'static> SplitPanel<W> {
+    /// Creates a panel that divides its space evenly between `children`.
     pub fn with_children(axis: Axis, children: Vec<W>) -> Self {
+        let count = children.len();
         Self {
             state: WidgetState::default(),
             children,
             axis,
+            ratios: even_ratios(count),
+            limits: vec![(Px(0), Px::MAX); count],
+            sizes: Vec::new(),
+            hovered: None,
+            drag: None,
+            on_resize: None,
         }
     }
-}
 
-impl<W: Widget + 'static> Widget for SplitPanel<W> {
-    fn widget_state(&self) -> &WidgetState {
-        &self.state
+    /// Divides the space according to `ratios`. See [`Self::set_ratios`].
+    pub fn with_ratios(mut self, ratios: &[f32]) -> Self {
+        self.set_ratios(ratios);
+        self
     }
 
-    fn widget_state_mut(&mut self) -> &mut WidgetState {
-        &mut self.state
+    /// Limits the size of the pane at `index`. See [`Self::set_pane_limits`].
+    pub fn with_pane_limits(mut self, index: usize, min: Px, max: Px) -> Self {
+        self.set_pane_limits(index, min, max);
+        self
     }
 
-    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
-        for child in &mut self.children {
-            f(child);
+    /// Calls `callback` with the new ratios when the user finishes dragging a
+    /// divider, so that they can be saved and restored with
+    /// [`Self::with_ratios`].
+    pub fn with_on_resize(mut self, callback: impl FnMut(&[f32]) + 'static) -> Self {
+        self.on_resize = Some(Box::new(callback));
+        self
+    }
+
+    /// The number of child panels.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    #[must_use]
+    pub fn axis(&self) -> Axis {
+        self.axis
+    }
+
+    /// The child at `index`.
+    #[must_use]
+    pub fn child(&self, index: usize) -> Option<&W> {
+        self.children.get(index)
+    }
+
+    /// The child at `index`.
+    pub fn child_mut(&mut self, index: usize) -> Option<&mut W> {
+        self.children.get_mut(index)
+    }
+
+    /// The fraction of the space taken by each pane.
+    #[must_use]
+    pub fn ratios(&self) -> &[f32] {
+        &self.ratios
+    }
+
+    /// Divides the space between the panes according to `ratios`, which are
+    /// scaled so that they add up to 1. Ignored unless there is one
+    /// non-negative ratio for each pane, and they don't add up to 0.
+    pub fn set_ratios(&mut self, ratios: &[f32]) {
+        let total: f32 = ratios.iter().sum();
+        if ratios.len() != self.children.len() || ratios.iter().any(|r| *r < 0.0) || total <= 0.0 {
+            return;
         }
+
+        self.ratios = ratios.iter().map(|ratio| ratio / total).collect();
+        self.state.set_needs_layout();
     }
 
-    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+    /// Keeps the pane at `index` between `min` and `max` in size along the
+    /// split axis, as far as the space allows.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `index >= len`.
+    pub fn set_pane_limits(&mut self, index: usize, min: Px, max: Px) {
+        self.limits[index] = (min, max.max(min));
+        self.state.set_needs_layout();
+    }
+
+    /// Inserts a child at `index`, shifting all children after it along the
+    /// split axis. The panes are resized to share the space evenly.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn insert_child(&mut self, index: usize, child: W) {
+        self.children.insert(index, child);
+        self.limits.insert(index, (Px(0), Px::MAX));
+        self.ratios = even_ratios(self.children.len());
+        self.cancel_drag();
+        self.state.set_needs_layout();
+    }
+
+    /// Removes the child at `index` and returns it so that it can be inserted
+    /// elsewhere in the tree. Its space is shared by the remaining panes in
+    /// proportion to their ratios.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `index >= len`.
+    pub fn remove_child(&mut self, index: usize) -> W {
+        let child = self.children.remove(index);
+        self.limits.remove(index);
+        self.ratios.remove(index);
+        let total: f32 = self.ratios.iter().sum();
+        if total > 0.0 {
+            self.ratios.iter_mut().for_each(|ratio| *ratio /= total);
+        } else {
+            self.ratios = even_ratios(self.children.len());
+        }
+        self.cancel_drag();
+        self.state.set_needs_layout();
+        child
+    }
+
+    fn cancel_drag(&mut self) {
+        self.drag = None;
+        self.hovered = None;
+    }
+
+    /// Where the pane at `index` starts along the axis, relative to the panel.
+    fn pane_start(&self, index: usize) -> Px {
+        self.sizes[..index]
+            .iter()
+            .fold(DIVIDER * index as f32, |start, size| start + *size)
+    }
+
+    /// The bounds of the divider after the pane at `index`, relative to the
+    /// panel.
+    fn divider_rect(&self, index: usize) -> Rect {
+        let start = self.pane_start(index) + self.sizes[index];
+        let extent = self.state.extent();
+        match self.axis {
+            Axis::X => Rect {
+                left: start,
+                right: start + DIVIDER,
+                top: Px(0),
+                bottom: extent.height,
+            },
+            Axis::Y => Rect {
+                left: Px(0),
+                right: extent.width,
+                top: start,
+                bottom: start + DIVIDER,
+            },
+        }
+    }
+
+    /// Finds the divider at `point`, relative to the panel.
+    fn divider_at(&self, point: Point) -> Option<usize> {
+        (0..self.sizes.len().saturating_sub(1))
+            .find(|index| self.divider_rect(*index).contains_point(point))
+    }
+
+    /// Moves the divider being dragged so that it starts at `position` along
+    /// the axis, relative to the panel.
+    fn drag_to(&mut self, index: usize, position: Px) {
+        let start = self.pane_start(index);
+        drag_divider(&mut self.sizes, &self.limits, index, position - start);
+
+        let total = self
+            .sizes
+            .iter()
+            .fold(0.0, |total, size| total + f32::from(*size));
+        if total > 0.0 {
+            self.ratios = self
+                .sizes
+                .iter()
+                .map(|size| f32::from(*size) / total)
+                .collect();
+        }
+    }
+
+    fn update_children(&mut self, context: &mut UpdateContext) {
         match context.event() {
             Event::None => {}
             Event::CursorMove { position } => {
                 for child in &mut self.children {
-                    if context.bound_of(child).contains(position) {
+                    if context.bound_of(child).contains_point(position) {
                         context.update(child);
                         break;
                     }
                 }
             }
-            Event::MouseButton { .. } => {
+            Event::MouseButton { .. } | Event::Scroll { .. } => {
                 // TODO(straivers): handle keyboard focus
                 for child in &mut self.children {
-                    if context.bound_of(child).contains(context.cursor_position()) {
+                    if context
+                        .bound_of(child)
+                        .contains_point(context.cursor_position())
+                    {
                         context.update(child);
                         break;
                     }
                 }
             }
+            Event::KeyPress { .. }
+            | Event::Text { .. }
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => {
+                // Only the focused widget responds to the keyboard, and it
+                // could be in any of the panels.
+                for child in &mut self.children {
+                    context.update(child);
+                }
+            }
         }
+    }
+}
 
-        PostUpdate::NoChange
+impl<W: Widget + 'static> Widget for SplitPanel<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.state
     }
 
-    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
-        match self.axis {
-            Axis::X => {
-                let per_child_width =
-                    constraints.max.width / self.children.len().try_into().unwrap();
-                let mut slack = constraints.max.width % self.children.len().try_into().unwrap();
-                let mut advancing_x = Px(0);
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.state
+    }
 
-                for child in &mut self.children {
-                    let child_constraint = BoxConstraint::exact(Extent {
-                        width: if slack > 0 {
-                            slack -= 1.into();
-                            per_child_width + 1.into()
-                        } else {
-                            per_child_width
-                        },
-                        height: constraints.max.height,
-                    });
-
-                    let child_extent = context.layout(child, child_constraint);
-                    context.position_widget(
-                        child,
-                        Offset {
-                            x: advancing_x,
-                            y: Px(0),
-                        },
-                        child_extent,
-                    );
-                    advancing_x += child_extent.width;
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        for child in &self.children {
+            f(child);
+        }
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        for child in &mut self.children {
+            f(child);
+        }
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        // Relative to the panel, like the dividers.
+        let cursor = Point::zero() + (context.cursor_position() - self.state.origin());
+
+        match context.event() {
+            Event::CursorMove { .. } => {
+                if let Some((index, grab)) = self.drag {
+                    self.drag_to(index, self.axis.of_point(cursor) - grab);
+                    return PostUpdate::NeedsLayout;
                 }
 
-                constraints.max
+                let hovered = self.divider_at(cursor);
+                if hovered != self.hovered {
+                    self.hovered = hovered;
+                    context.redraw(self.state.rect());
+                }
             }
-            Axis::Y => {
-                let per_child_height =
-                    constraints.max.height / self.children.len().try_into().unwrap();
-                let mut slack = constraints.max.height % self.children.len().try_into().unwrap();
-                let mut advancing_y = Px(0);
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Pressed,
+            } => {
+                if let Some(index) = self.divider_at(cursor) {
+                    let start = self.pane_start(index) + self.sizes[index];
+                    self.drag = Some((index, self.axis.of_point(cursor) - start));
+                    return PostUpdate::NeedsRedraw;
+                }
+            }
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Released,
+            } if self.drag.is_some() => {
+                self.drag = None;
+                self.hovered = self.divider_at(cursor);
+                if let Some(on_resize) = &mut self.on_resize {
+                    on_resize(&self.ratios);
+                }
+                return PostUpdate::NeedsRedraw;
+            }
+            _ => {}
+        }
 
-                for child in &mut self.children {
-                    let child_constraint = BoxConstraint::exact(Extent {
+        self.update_children(context);
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        if self.children.is_empty() {
+            return constraints.max;
+        }
+
+        let total = self.axis.of_extent(constraints.max);
+        self.sizes = pane_sizes(total, &self.ratios, &self.limits);
+
+        let mut advancing = Px(0);
+        for (child, size) in self.children.iter_mut().zip(&self.sizes) {
+            let (offset, extent) = match self.axis {
+                Axis::X => (
+                    Offset {
+                        x: advancing,
+                        y: Px(0),
+                    },
+                    Extent {
+                        width: *size,
+                        height: constraints.max.height,
+                    },
+                ),
+                Axis::Y => (
+                    Offset {
+                        x: Px(0),
+                        y: advancing,
+                    },
+                    Extent {
                         width: constraints.max.width,
-                        height: if slack > 0 {
-                            slack -= 1.into();
-                            per_child_height + 1.into()
-                        } else {
-                            per_child_height
-                        },
-                    });
-
-                    let child_extent = context.layout(child, child_constraint);
-                    context.position_widget(
-                        child,
-                        Offset {
-                            x: Px(0),
-                            y: advancing_y,
-                        },
-                        child_extent,
-                    );
-                    advancing_y += child_extent.height;
-                }
+                        height: *size,
+                    },
+                ),
+            };
 
-                constraints.max
-            }
+            let child_extent = context.layout(child, BoxConstraint::exact(extent));
+            context.position_widget(child, offset, child_extent);
+            advancing += *size + DIVIDER;
         }
+
+        constraints.max
     }
 
     fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
-        for child in &self.children {
-            canvas.draw(child);
+        canvas.draw_children(self);
+
+        let active = self.drag.map(|(index, _)| index).or(self.hovered);
+        for index in 0..self.sizes.len().saturating_sub(1) {
+            let color = if active == Some(index) {
+                ACTIVE_DIVIDER_COLOR
+            } else {
+                DIVIDER_COLOR
+            };
+            canvas.draw_rect(self.divider_rect(index), &Paint::Fill { color });
+        }
+    }
+
+    fn cursor(&self, point: Point) -> Option<CursorIcon> {
+        // Dragging keeps the cursor even where the divider can't follow.
+        let point = Point::zero() + (point - self.state.origin());
+        if self.drag.is_some() || self.divider_at(point).is_some() {
+            Some(self.axis.resize_cursor())
+        } else {
+            None
+        }
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(self.ratios.clone()))
+    }
+
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        if let Ok(ratios) = state.downcast::<Vec<f32>>() {
+            self.set_ratios(&ratios);
         }
     }
 }
+
+fn even_ratios(count: usize) -> Vec<f32> {
+    vec![1.0 / count as f32; count]
+}
+
+/// Divides `total`, less the space taken by dividers, between panes according
+/// to their `ratios`, keeping each pane within its `limits` where possible.
+fn pane_sizes(total: Px, ratios: &[f32], limits: &[(Px, Px)]) -> Vec<Px> {
+    let dividers = DIVIDER * ratios.len().saturating_sub(1) as f32;
+    let available = total.saturating_sub(dividers).max(Px(0));
+
+    let mut sizes: Vec<Px> = ratios
+        .iter()
+        .zip(limits)
+        .map(|(ratio, (min, max))| {
+            Px::from((f32::from(available) * ratio).round())
+                .max(*min)
+                .min(*max)
+        })
+        .collect();
+
+    // Rounding and the limits may leave space over, or take too much. Give
+    // the difference to the panes that can take it, starting from the last.
+    let mut difference = available - sizes.iter().fold(Px(0), |sum, size| sum + *size);
+    for (size, (min, max)) in sizes.iter_mut().zip(limits).rev() {
+        let resized = (*size + difference).max(*min).min(*max).max(Px(0));
+        difference -= resized - *size;
+        *size = resized;
+    }
+
+    sizes
+}
+
+/// Resizes the pane at `index` to `size`, taking the space from or giving it
+/// to the pane after it, within the limits of both.
+fn drag_divider(sizes: &mut [Px], limits: &[(Px, Px)], index: usize, size: Px) {
+    let pair = sizes[index] + sizes[index + 1];
+    let (min, max) = limits[index];
+    let (next_min, next_max) = limits[index + 1];
+
+    let lowest = min.max(pair.saturating_sub(next_max)).max(Px(0));
+    let highest = max.min(pair - next_min).min(pair);
+    let size = size.max(lowest).min(highest);
+
+    sizes[index] = size;
+    sizes[index + 1] = pair - size;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn divide_space() {
+        let unlimited = [(Px(0), Px::MAX); 3];
+        let sizes = pane_sizes(Px(308), &even_ratios(3), &unlimited);
+        assert_eq!(sizes, [Px(100), Px(100), Px(100)]);
+
+        // The first pane can't grow past 50, so the others take the rest.
+        let limits = [(Px(0), Px(50)), (Px(0), Px::MAX)];
+        let sizes = pane_sizes(Px(204), &[0.5, 0.5], &limits);
+        assert_eq!(sizes, [Px(50), Px(150)]);
+
+        let limits = [(Px(120), Px::MAX), (Px(0), Px::MAX)];
+        let sizes = pane_sizes(Px(204), &[0.5, 0.5], &limits);
+        assert_eq!(sizes, [Px(120), Px(80)]);
+    }
+
+    #[test]
+    fn drag_within_limits() {
+        let limits = [(Px(20), Px(150)), (Px(30), Px::MAX)];
+
+        let mut sizes = [Px(100), Px(100)];
+        drag_divider(&mut sizes, &limits, 0, Px(60));
+        assert_eq!(sizes, [Px(60), Px(140)]);
+
+        drag_divider(&mut sizes, &limits, 0, Px(5));
+        assert_eq!(sizes, [Px(20), Px(180)]);
+
+        drag_divider(&mut sizes, &limits, 0, Px(190));
+        assert_eq!(sizes, [Px(150), Px(50)]);
+    }
+}
@@ -1,9 +1,11 @@
+use std::any::Any;
+
 use rand::random;
 
 use crate::{
     gfx::{
         color::Color,
-        geometry::{Extent, Offset, Px, Rect},
+        geometry::{Extent, Offset, Point, Px, Rect},
         Paint,
     },
     gui::input::{Event, MouseButton},
@@ -39,6 +41,110 @@ impl<W: Widget> TabbedPanel<W> {
         }
     }
 
+    /// The number of tabs in the panel.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// The index of the tab that is shown.
+    #[must_use]
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Shows the tab at `index`. Does nothing if there is no such tab.
+    pub fn set_active(&mut self, index: usize) {
+        if index < self.children.len() && index != self.active {
+            self.active = index;
+            self.state.set_needs_layout();
+        }
+    }
+
+    /// The widget in the tab at `index`.
+    #[must_use]
+    pub fn tab(&self, index: usize) -> Option<&W> {
+        self.children.get(index).map(|tab| &tab.widget)
+    }
+
+    /// Finds the tab whose header is at `point`, in absolute coordinates.
+    #[must_use]
+    pub fn tab_at(&self, point: Point) -> Option<usize> {
+        let rect = self.state.rect();
+        if !Self::tab_bar_rect(rect).contains_point(point) {
+            return None;
+        }
+
+        let mut advancing_x = rect.left;
+        self.children.iter().position(|child| {
+            advancing_x += child.width;
+            point.x <= advancing_x
+        })
+    }
+
+    /// Inserts a widget as a new tab at `index`, shifting all tabs after it
+    /// to the right. The active tab is preserved.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn insert_tab(&mut self, index: usize, widget: W) {
+        self.children.insert(
+            index,
+            Tab {
+                width: TAB_WIDTH,
+                widget,
+                color: random(),
+            },
+        );
+
+        if index <= self.active && self.children.len() > 1 {
+            self.active += 1;
+        }
+
+        self.state.set_needs_layout();
+    }
+
+    /// Removes the tab at `index` and returns its widget so that it can be
+    /// inserted elsewhere in the tree (e.g. into another panel). If the active
+    /// tab is removed, the tab before it becomes active, or the one after it if
+    /// it was the first tab.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `index >= len`.
+    pub fn remove_tab(&mut self, index: usize) -> W {
+        let tab = self.children.remove(index);
+
+        if index <= self.active {
+            self.active = self.active.saturating_sub(1);
+        }
+
+        self.state.set_needs_layout();
+        tab.widget
+    }
+
+    /// Moves the tab at `from` so that it is at position `to` after the move.
+    /// The active tab follows the move.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if either index is out of bounds.
+    pub fn move_tab(&mut self, from: usize, to: usize) {
+        let was_active = self.active == from;
+        let widget = self.remove_tab(from);
+        self.insert_tab(to, widget);
+
+        if was_active {
+            self.active = to;
+        }
+    }
+
     fn tab_bar_rect(bounds: Rect) -> Rect {
         Rect {
             left: bounds.left,
@@ -58,6 +164,12 @@ impl<W: Widget> TabbedPanel<W> {
     }
 }
 
+/// The state of a [`TabbedPanel`] that is carried over when the widget tree is
+/// rebuilt.
+struct SavedState {
+    active: usize,
+}
+
 impl<W: Widget> Widget for TabbedPanel<W> {
     fn widget_state(&self) -> &WidgetState {
         &self.state
@@ -67,6 +179,12 @@ impl<W: Widget> Widget for TabbedPanel<W> {
         &mut self.state
     }
 
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        for child in &self.children {
+            f(&child.widget);
+        }
+    }
+
     fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
         for child in &mut self.children {
             f(&mut child.widget);
@@ -78,9 +196,20 @@ impl<W: Widget> Widget for TabbedPanel<W> {
 
         match context.event() {
             Event::None => PostUpdate::NoChange,
+            Event::Scroll { .. } => {
+                if Self::content_rect(rect).contains_point(context.cursor_position()) {
+                    if let Some(child) = self.children.get_mut(self.active) {
+                        context.update(&mut child.widget);
+                    }
+                }
+
+                PostUpdate::NoChange
+            }
             Event::CursorMove { position } => {
-                if Self::content_rect(rect).contains(position) {
-                    context.update(&mut self.children[self.active].widget);
+                if Self::content_rect(rect).contains_point(position) {
+                    if let Some(child) = self.children.get_mut(self.active) {
+                        context.update(&mut child.widget);
+                    }
                 }
 
                 PostUpdate::NoChange
@@ -88,49 +217,57 @@ impl<W: Widget> Widget for TabbedPanel<W> {
             Event::MouseButton { button, state } => {
                 let cursor_pos = context.cursor_position();
 
-                if Self::tab_bar_rect(rect).contains(cursor_pos) {
-                    let cursor_x = cursor_pos.x;
-                    let mut advancing_x = rect.left;
-                    for (i, child) in self.children.iter_mut().enumerate() {
-                        advancing_x += child.width;
-                        if cursor_x <= advancing_x {
-                            if button == MouseButton::Left && state.is_pressed() {
-                                self.active = i;
-                                return PostUpdate::NeedsLayout;
-                            }
-
-                            break;
+                if Self::tab_bar_rect(rect).contains_point(cursor_pos) {
+                    match self.tab_at(cursor_pos) {
+                        Some(i) if button == MouseButton::Left && state.is_pressed() => {
+                            self.active = i;
+                            PostUpdate::NeedsLayout
                         }
+                        _ => PostUpdate::NoChange,
+                    }
+                } else if Self::content_rect(rect).contains_point(cursor_pos) {
+                    if let Some(child) = self.children.get_mut(self.active) {
+                        context.update(&mut child.widget);
                     }
-
-                    PostUpdate::NoChange
-                } else if Self::content_rect(rect).contains(cursor_pos) {
-                    context.update(&mut self.children[self.active].widget);
                     PostUpdate::NoChange
                 } else {
                     PostUpdate::NoChange
                 }
             }
+            Event::KeyPress { .. }
+            | Event::Text { .. }
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => {
+                // Hidden tabs can't have the focus, since they can't be
+                // clicked.
+                if let Some(child) = self.children.get_mut(self.active) {
+                    context.update(&mut child.widget);
+                }
+                PostUpdate::NoChange
+            }
         }
     }
 
     fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
-        let child_constraints = BoxConstraint::exact(Extent {
-            width: constraints.max.width,
-            height: constraints.max.height - TAB_BAR_HEIGHT,
-        });
-
-        let child_extent =
-            context.layout(&mut self.children[self.active].widget, child_constraints);
-
-        context.position_widget(
-            &mut self.children[self.active].widget,
-            Offset {
-                x: Px(0),
-                y: TAB_BAR_HEIGHT,
-            },
-            child_extent,
-        );
+        let child_constraints = BoxConstraint::exact(constraints.max.saturating_sub(Extent {
+            width: Px(0),
+            height: TAB_BAR_HEIGHT,
+        }));
+
+        if let Some(child) = self.children.get_mut(self.active) {
+            let child_extent = context.layout(&mut child.widget, child_constraints);
+
+            context.position_widget(
+                &mut child.widget,
+                Offset {
+                    x: Px(0),
+                    y: TAB_BAR_HEIGHT,
+                },
+                child_extent,
+            );
+        }
 
         constraints.max
     }
@@ -151,7 +288,24 @@ impl<W: Widget> Widget for TabbedPanel<W> {
             advancing_x += child.width;
         }
 
-        canvas.draw(&self.children[self.active].widget);
+        if let Some(child) = self.children.get(self.active) {
+            canvas.draw(&child.widget);
+        }
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(SavedState {
+            active: self.active,
+        }))
+    }
+
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        if let Ok(state) = state.downcast::<SavedState>() {
+            if state.active < self.children.len() {
+                self.active = state.active;
+                self.state.set_needs_layout();
+            }
+        }
     }
 }
 
@@ -160,3 +314,57 @@ struct Tab<W: Widget> {
     color: Color,
     widget: W,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::gfx::color::Color;
+    use crate::gui::widgets::debug::Fill;
+
+    use super::*;
+
+    fn fill(r: f32) -> Fill {
+        Fill::new(Color {
+            r,
+            g: 0.0,
+            b: 0.0,
+            a: 1.0,
+        })
+    }
+
+    fn make_panel(n: u8) -> TabbedPanel<Fill> {
+        TabbedPanel::with_children((0..n).map(|i| fill(f32::from(i))).collect())
+    }
+
+    fn order(panel: &TabbedPanel<Fill>) -> Vec<f32> {
+        panel.children.iter().map(|t| t.widget.color.r).collect()
+    }
+
+    #[test]
+    fn tabbed_panel_insert_remove() {
+        let mut panel = make_panel(3);
+        panel.active = 1;
+
+        panel.insert_tab(0, fill(9.0));
+        assert_eq!(order(&panel), [9.0, 0.0, 1.0, 2.0]);
+        assert_eq!(panel.active, 2);
+
+        let removed = panel.remove_tab(2);
+        assert_eq!(removed.color.r, 1.0);
+        assert_eq!(order(&panel), [9.0, 0.0, 2.0]);
+        assert_eq!(panel.active, 1);
+
+        panel.move_tab(2, 0);
+        assert_eq!(order(&panel), [2.0, 9.0, 0.0]);
+        assert_eq!(panel.active, 2);
+
+        let mut other = make_panel(0);
+        while !panel.is_empty() {
+            let widget = panel.remove_tab(0);
+            other.insert_tab(other.len(), widget);
+        }
+
+        assert_eq!(order(&other), [2.0, 9.0, 0.0]);
+        assert_eq!(panel.active, 0);
+        assert!(panel.state.needs_layout());
+    }
+}
@@ -0,0 +1,665 @@
+use std::{any::Any, cmp::Ordering, ops::Range};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::input::{Event, FocusId, MouseButton},
+    shell::{event::Key, CursorIcon},
+    text::{ShapedLine, TextStyle},
+};
+
+use super::{
+    virtual_list::{self, ScrollPosition},
+    BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext, Widget, WidgetState,
+};
+
+/// The space between the edges of a cell and its text.
+const CELL_PADDING: Px = Px(4);
+
+/// How close to the edge between two column headers the cursor must be to
+/// start resizing the column on the left.
+const RESIZE_MARGIN: Px = Px(4);
+
+const MIN_COLUMN_WIDTH: Px = Px(24);
+
+const BACKGROUND: Color = Color::WHITE;
+
+const HEADER_BACKGROUND: Color = Color {
+    r: 0.9,
+    g: 0.9,
+    b: 0.9,
+    a: 1.0,
+};
+
+const GRID: Color = Color {
+    r: 0.8,
+    g: 0.8,
+    b: 0.8,
+    a: 1.0,
+};
+
+const SELECTION: Color = Color {
+    r: 0.6,
+    g: 0.8,
+    b: 1.0,
+    a: 1.0,
+};
+
+/// Compares the text of two cells to sort a column.
+pub type CompareFn = fn(&str, &str) -> Ordering;
+
+/// A column of a [`Table`].
+#[must_use]
+pub struct TableColumn {
+    title: String,
+    width: Px,
+    compare: CompareFn,
+}
+
+impl TableColumn {
+    pub fn new(title: &str, width: Px) -> Self {
+        Self {
+            title: title.to_string(),
+            width: width.max(MIN_COLUMN_WIDTH),
+            compare: str::cmp,
+        }
+    }
+
+    /// Changes how the column is sorted. Columns are sorted by comparing the
+    /// text of their cells by default.
+    pub fn with_compare(mut self, compare: CompareFn) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    #[must_use]
+    pub fn width(&self) -> Px {
+        self.width
+    }
+}
+
+/// A row of text that has been shaped for drawing.
+struct VisibleRow {
+    /// The position of the row in sorted order.
+    index: usize,
+    cells: Vec<ShapedLine>,
+}
+
+type SelectCallback = Box<dyn FnMut(Option<usize>)>;
+
+/// Rows of text arranged in columns, under a header of column titles.
+///
+/// Only the rows that can be seen are laid out and drawn, so the table can
+/// hold many rows. Clicking a column's title sorts the rows by that column,
+/// and clicking it again reverses the order. Columns are resized by dragging
+/// the edges between their titles.
+///
+/// Clicking a row selects it and gives the table the keyboard focus. While
+/// focused, Up, Down, Page Up, Page Down, Home, and End move the selection,
+/// and Ctrl+C copies the selected row with its cells separated by tabs.
+/// Rows are identified by their index in the order they were added, which
+/// doesn't change when the rows are sorted.
+#[must_use]
+pub struct Table {
+    widget_state: WidgetState,
+    focus: FocusId,
+    style: TextStyle,
+    pub color: Color,
+    columns: Vec<TableColumn>,
+    rows: Vec<Vec<String>>,
+    /// The indices of the rows in the order that they are shown.
+    order: Vec<usize>,
+    /// The column that the rows are sorted by and whether they are sorted in
+    /// ascending order.
+    sort: Option<(usize, bool)>,
+    selected: Option<usize>,
+    /// How far the rows are scrolled up.
+    scroll: ScrollPosition,
+    /// The column being resized, and the distance from the cursor to its
+    /// right edge when the drag started.
+    resizing: Option<(usize, Px)>,
+    on_select: Option<SelectCallback>,
+    // Computed during layout.
+    row_height: Px,
+    ascent: Px,
+    headers: Vec<ShapedLine>,
+    visible: Vec<VisibleRow>,
+}
+
+impl Table {
+    pub fn new(style: TextStyle, columns: Vec<TableColumn>) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            focus: FocusId::new(),
+            style,
+            color: Color::BLACK,
+            columns,
+            rows: Vec::new(),
+            order: Vec::new(),
+            sort: None,
+            selected: None,
+            scroll: ScrollPosition::TOP,
+            resizing: None,
+            on_select: None,
+            row_height: Px(0),
+            ascent: Px(0),
+            headers: Vec::new(),
+            visible: Vec::new(),
+        }
+    }
+
+    pub fn with_rows(mut self, rows: Vec<Vec<String>>) -> Self {
+        self.set_rows(rows);
+        self
+    }
+
+    /// Calls `callback` with the selected row whenever the user changes the
+    /// selection.
+    pub fn with_on_select(mut self, callback: impl FnMut(Option<usize>) + 'static) -> Self {
+        self.on_select = Some(Box::new(callback));
+        self
+    }
+
+    /// Replaces every row, clearing the selection. Cells missing from the end
+    /// of a row are left empty.
+    pub fn set_rows(&mut self, rows: Vec<Vec<String>>) {
+        self.rows = rows;
+        self.selected = None;
+        self.sort_rows();
+        self.widget_state.set_needs_layout();
+    }
+
+    /// Adds a row after the others, keeping the rows sorted.
+    pub fn push_row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+        self.sort_rows();
+        self.widget_state.set_needs_layout();
+    }
+
+    #[must_use]
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    pub fn columns(&self) -> &[TableColumn] {
+        &self.columns
+    }
+
+    /// The index of the selected row in the order the rows were added.
+    #[must_use]
+    pub fn selected_row(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Selects the row at `row`, or clears the selection. This does not call
+    /// the select callback.
+    pub fn set_selected_row(&mut self, row: Option<usize>) {
+        self.selected = row.filter(|&row| row < self.rows.len());
+        self.widget_state.set_needs_layout();
+    }
+
+    /// Sorts the rows by `column`, or restores the order the rows were added
+    /// in if `column` is `None`.
+    pub fn sort_by(&mut self, column: Option<usize>, ascending: bool) {
+        self.sort = column.map(|column| (column, ascending));
+        self.sort_rows();
+        self.widget_state.set_needs_layout();
+    }
+
+    fn sort_rows(&mut self) {
+        let sort = self.sort.and_then(|(column, ascending)| {
+            let compare = self.columns.get(column)?.compare;
+            Some((column, compare, ascending))
+        });
+        self.order = sorted_order(&self.rows, sort);
+    }
+
+    /// The height of the rows, excluding the header.
+    fn viewport(&self) -> Px {
+        self.widget_state
+            .extent()
+            .height
+            .saturating_sub(self.row_height)
+    }
+
+    /// Moves the selection to the row shown at `index`, scrolling it into
+    /// view. Returns `true` if the selection changed.
+    fn select(&mut self, index: usize) -> bool {
+        let row = match self.order.get(index) {
+            Some(&row) => row,
+            None => return false,
+        };
+
+        self.scroll =
+            virtual_list::scroll_to_row(self.scroll, self.viewport(), self.row_height, index);
+        if self.selected == Some(row) {
+            return false;
+        }
+
+        self.selected = Some(row);
+        if let Some(on_select) = &mut self.on_select {
+            on_select(self.selected);
+        }
+        true
+    }
+
+    /// The position of the selected row in sorted order.
+    fn selected_index(&self) -> Option<usize> {
+        let selected = self.selected?;
+        self.order.iter().position(|&row| row == selected)
+    }
+
+    /// Finds the column whose right edge is within [`RESIZE_MARGIN`] of `x`,
+    /// relative to the table.
+    fn column_edge_at(&self, x: Px) -> Option<usize> {
+        let mut right = Px(0);
+        for (i, column) in self.columns.iter().enumerate() {
+            right += column.width;
+            if x >= right - RESIZE_MARGIN && x <= right + RESIZE_MARGIN {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    /// Finds the column containing `x`, relative to the table.
+    fn column_at(&self, x: Px) -> Option<usize> {
+        let mut right = Px(0);
+        for (i, column) in self.columns.iter().enumerate() {
+            right += column.width;
+            if x < right {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    fn press(&mut self, context: &mut UpdateContext, point: Point) -> PostUpdate {
+        context.set_focus(Some(self.focus));
+
+        if point.y >= self.row_height {
+            let y = point.y - self.row_height;
+            return match virtual_list::row_at(self.scroll, y, self.row_height, self.order.len()) {
+                Some(index) if self.select(index) => PostUpdate::NeedsLayout,
+                _ => PostUpdate::NoChange,
+            };
+        }
+
+        if let Some(column) = self.column_edge_at(point.x) {
+            let right = self.columns[..=column]
+                .iter()
+                .fold(Px(0), |right, column| right + column.width);
+            self.resizing = Some((column, right - point.x));
+            return PostUpdate::NoChange;
+        }
+
+        match self.column_at(point.x) {
+            Some(column) => {
+                let ascending = !matches!(self.sort, Some((c, true)) if c == column);
+                self.sort_by(Some(column), ascending);
+                PostUpdate::NeedsLayout
+            }
+            None => PostUpdate::NoChange,
+        }
+    }
+
+    /// Draws a row of cells with its top edge at `top`, clipping each cell to
+    /// its column.
+    fn draw_cells(&self, canvas: &mut DrawContext, cells: &[ShapedLine], top: Px) {
+        let mut left = Px(0);
+        for (column, cell) in self.columns.iter().zip(cells) {
+            let cell_rect = Rect {
+                left,
+                top,
+                right: left + column.width,
+                bottom: top + self.row_height,
+            };
+
+            canvas.push_clip_rounded_rect(cell_rect, Px(0));
+            canvas.draw_text(
+                cell,
+                Point::new(left + CELL_PADDING, top + CELL_PADDING + self.ascent),
+                self.style.size,
+                self.color,
+            );
+            canvas.pop_clip();
+
+            left += column.width;
+        }
+    }
+
+    fn handle_key(&mut self, key: Key) -> bool {
+        let count = self.order.len();
+        let page = usize::try_from(self.viewport().0 / self.row_height.0.max(1))
+            .unwrap_or(0)
+            .max(1);
+        let index = match next_index(key, self.selected_index(), count, page) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let scroll = self.scroll;
+        self.select(index) || self.scroll != scroll
+    }
+}
+
+/// The state of a [`Table`] that is carried over when the widget tree is
+/// rebuilt.
+struct SavedState {
+    focus: FocusId,
+    sort: Option<(usize, bool)>,
+    selected: Option<usize>,
+    scroll: ScrollPosition,
+}
+
+impl Widget for Table {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        // Rows are shaped during layout, so anything that changes which rows
+        // are visible needs a layout.
+        let rect = self.widget_state.rect();
+        let cursor = context.cursor_position();
+
+        match context.event() {
+            Event::None
+            | Event::Text { .. }
+            | Event::Composition
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => PostUpdate::NoChange,
+            Event::Scroll { delta, .. } => {
+                if !rect.contains_point(cursor) {
+                    return PostUpdate::NoChange;
+                }
+
+                let max =
+                    virtual_list::max_scroll(self.viewport(), self.row_height, self.order.len());
+                let scroll = virtual_list::scroll_by(self.scroll, delta.y, self.row_height, max);
+                if scroll == self.scroll {
+                    PostUpdate::NoChange
+                } else {
+                    self.scroll = scroll;
+                    PostUpdate::NeedsLayout
+                }
+            }
+            Event::CursorMove { position } => match self.resizing {
+                Some((column, grab)) => {
+                    let left = self.columns[..column]
+                        .iter()
+                        .fold(rect.left, |left, column| left + column.width);
+                    self.columns[column].width = (position.x + grab - left).max(MIN_COLUMN_WIDTH);
+                    PostUpdate::NeedsRedraw
+                }
+                None => PostUpdate::NoChange,
+            },
+            Event::MouseButton { button, state } => {
+                if button != MouseButton::Left {
+                    PostUpdate::NoChange
+                } else if state.is_released() {
+                    self.resizing = None;
+                    PostUpdate::NoChange
+                } else if rect.contains_point(cursor) {
+                    let point = Point {
+                        x: cursor.x - rect.left,
+                        y: cursor.y - rect.top,
+                    };
+                    self.press(context, point)
+                } else {
+                    PostUpdate::NoChange
+                }
+            }
+            Event::KeyPress {
+                key: Key::C,
+                modifiers,
+            } if modifiers.control && context.is_focused(self.focus) => {
+                if let Some(row) = self.selected {
+                    context.set_clipboard_text(&self.rows[row].join("\t"));
+                }
+                PostUpdate::NoChange
+            }
+            Event::KeyPress { key, .. } => {
+                if context.is_focused(self.focus) && self.handle_key(key) {
+                    PostUpdate::NeedsLayout
+                } else {
+                    PostUpdate::NoChange
+                }
+            }
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = constraints.max;
+
+        let header = |text: &str, context: &LayoutContext| {
+            context
+                .measure_text(text, &self.style, None)
+                .lines
+                .swap_remove(0)
+                .shaped
+        };
+
+        let metrics = context.measure_text("", &self.style, None);
+        self.row_height = Px::from(metrics.line_height.ceil()) + CELL_PADDING + CELL_PADDING;
+        self.ascent = Px::from(metrics.ascent.round());
+
+        let mut headers = Vec::with_capacity(self.columns.len());
+        for (i, column) in self.columns.iter().enumerate() {
+            let title = match self.sort {
+                Some((sorted, true)) if sorted == i => format!("{} \u{25B2}", column.title),
+                Some((sorted, false)) if sorted == i => format!("{} \u{25BC}", column.title),
+                _ => column.title.clone(),
+            };
+            headers.push(header(&title, context));
+        }
+        self.headers = headers;
+
+        let viewport = extent.height.saturating_sub(self.row_height);
+        let max = virtual_list::max_scroll(viewport, self.row_height, self.order.len());
+        self.scroll = self.scroll.min(max);
+
+        let visible: Range<usize> =
+            virtual_list::visible_rows(self.scroll, viewport, self.row_height, self.order.len());
+        self.visible = visible
+            .map(|index| {
+                let row = &self.rows[self.order[index]];
+                let cells = (0..self.columns.len())
+                    .map(|column| header(row.get(column).map_or("", String::as_str), context))
+                    .collect();
+                VisibleRow { index, cells }
+            })
+            .collect();
+
+        extent
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        let bounds = Rect::new(Point::zero(), extent);
+        canvas.draw_rect(bounds, &Paint::Fill { color: BACKGROUND });
+        canvas.push_clip_rounded_rect(bounds, Px(0));
+
+        let body_top = self.row_height;
+        let selected = self.selected_index();
+        let grid = Stroke::solid(GRID, 1.0);
+
+        // The rows are clipped below the header so that partly scrolled rows
+        // don't draw over it.
+        canvas.push_clip_rounded_rect(
+            Rect {
+                top: body_top,
+                ..bounds
+            },
+            Px(0),
+        );
+        for row in &self.visible {
+            let top = body_top + virtual_list::row_top(self.scroll, self.row_height, row.index);
+            let row_rect = Rect {
+                left: Px(0),
+                top,
+                right: extent.width,
+                bottom: top + self.row_height,
+            };
+
+            if selected == Some(row.index) {
+                canvas.draw_rect(row_rect, &Paint::Fill { color: SELECTION });
+            }
+
+            self.draw_cells(canvas, &row.cells, top);
+            canvas.stroke_line(
+                Point::new(Px(0), row_rect.bottom),
+                Point::new(extent.width, row_rect.bottom),
+                &grid,
+            );
+        }
+        canvas.pop_clip();
+
+        canvas.draw_rect(
+            Rect {
+                bottom: body_top,
+                ..bounds
+            },
+            &Paint::Fill {
+                color: HEADER_BACKGROUND,
+            },
+        );
+        self.draw_cells(canvas, &self.headers, Px(0));
+
+        let mut right = Px(0);
+        for column in &self.columns {
+            right += column.width;
+            canvas.stroke_line(
+                Point::new(right, Px(0)),
+                Point::new(right, extent.height),
+                &grid,
+            );
+        }
+
+        canvas.pop_clip();
+        canvas.stroke_rect(bounds, &grid);
+    }
+
+    fn cursor(&self, point: Point) -> Option<CursorIcon> {
+        let rect = self.widget_state.rect();
+        let in_header = point.y - rect.top < self.row_height;
+        if self.resizing.is_some()
+            || in_header && self.column_edge_at(point.x - rect.left).is_some()
+        {
+            Some(CursorIcon::ResizeHorizontal)
+        } else {
+            None
+        }
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(SavedState {
+            focus: self.focus,
+            sort: self.sort,
+            selected: self.selected,
+            scroll: self.scroll,
+        }))
+    }
+
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        if let Ok(state) = state.downcast::<SavedState>() {
+            self.focus = state.focus;
+            self.sort = state.sort;
+            self.sort_rows();
+            self.selected = state.selected.filter(|&row| row < self.rows.len());
+            // Clamped to the new rows during layout.
+            self.scroll = state.scroll;
+            self.widget_state.set_needs_layout();
+        }
+    }
+}
+
+/// Finds the order to show `rows` in when sorted by a column, using the
+/// column's comparison and whether to sort in ascending order. Rows keep
+/// the order they were added in if there is no column to sort by.
+fn sorted_order(rows: &[Vec<String>], sort: Option<(usize, CompareFn, bool)>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+
+    if let Some((column, compare, ascending)) = sort {
+        let cell = |row: usize| rows[row].get(column).map_or("", String::as_str);
+        order.sort_by(|&a, &b| {
+            let ordering = compare(cell(a), cell(b));
+            if ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        });
+    }
+
+    order
+}
+
+/// Finds the position that `key` moves the selection to in a table of
+/// `count` rows, where `page` rows fit in the view at once. Returns `None` if
+/// the key doesn't move the selection.
+fn next_index(key: Key, current: Option<usize>, count: usize, page: usize) -> Option<usize> {
+    let last = count.checked_sub(1)?;
+    let index = match (key, current) {
+        (Key::Up, Some(i)) => i.saturating_sub(1),
+        (Key::Down, Some(i)) => (i + 1).min(last),
+        (Key::PageUp, Some(i)) => i.saturating_sub(page),
+        (Key::PageDown, Some(i)) => (i + page).min(last),
+        (Key::Up | Key::Down | Key::PageUp | Key::PageDown | Key::Home, _) => 0,
+        (Key::End, _) => last,
+        _ => return None,
+    };
+    Some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_rows() {
+        let rows: Vec<Vec<String>> = [&["b", "10"][..], &["c", "9"], &["a"], &["d", "5"]]
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect();
+        let by_number: CompareFn = |a, b| {
+            let parse = |s: &str| s.parse::<u32>().unwrap_or(0);
+            parse(a).cmp(&parse(b))
+        };
+
+        assert_eq!(sorted_order(&rows, None), [0, 1, 2, 3]);
+        assert_eq!(sorted_order(&rows, Some((0, str::cmp, true))), [2, 0, 1, 3]);
+        assert_eq!(
+            sorted_order(&rows, Some((0, str::cmp, false))),
+            [3, 1, 0, 2]
+        );
+
+        // Missing cells sort as if they were empty.
+        assert_eq!(
+            sorted_order(&rows, Some((1, by_number, true))),
+            [2, 3, 1, 0]
+        );
+    }
+
+    #[test]
+    fn move_selection() {
+        assert_eq!(next_index(Key::Down, None, 5, 2), Some(0));
+        assert_eq!(next_index(Key::Down, Some(4), 5, 2), Some(4));
+        assert_eq!(next_index(Key::PageDown, Some(1), 5, 2), Some(3));
+        assert_eq!(next_index(Key::PageUp, Some(1), 5, 2), Some(0));
+        assert_eq!(next_index(Key::End, Some(1), 5, 2), Some(4));
+        assert_eq!(next_index(Key::Down, None, 0, 2), None);
+        assert_eq!(next_index(Key::A, Some(1), 5, 2), None);
+    }
+}
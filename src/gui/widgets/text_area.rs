@@ -0,0 +1,761 @@
+use std::any::Any;
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::{
+        accessibility::{AccessNode, Action, ActionKind, Role},
+        input::{Event, FocusId, MouseButton},
+        undo::UndoStack,
+    },
+    shell::{
+        event::{Key, Modifiers},
+        CursorIcon,
+    },
+    text::{LineMetrics, TextMetrics, TextStyle},
+};
+
+use super::{
+    editor::{EditHistory, EditKind, Editor},
+    BoxConstraint, DrawContext, LayoutContext, PostUpdate, UpdateContext, Widget, WidgetState,
+};
+
+/// The space between the edge of the text area and its text.
+const PADDING: Px = Px(4);
+
+const BACKGROUND: Color = Color::WHITE;
+
+const BORDER: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+const FOCUSED_BORDER: Color = Color {
+    r: 0.0,
+    g: 0.4,
+    b: 0.9,
+    a: 1.0,
+};
+
+const SELECTION: Color = Color {
+    r: 0.6,
+    g: 0.8,
+    b: 1.0,
+    a: 1.0,
+};
+
+type Callback = Box<dyn FnMut(&str)>;
+
+/// Multiple lines of editable text, wrapped to the width of the widget.
+///
+/// The text area takes the keyboard focus when clicked. It supports the same
+/// keys as [`TextInput`](super::text_input::TextInput), as well as Up, Down,
+/// Page Up, and Page Down to move between lines, and Enter to break a line.
+/// Home and End move to the ends of the line, or of the whole text with
+/// Control. Edits are undone and redone with the same keys as in a text input,
+/// on a stack of the text area's own unless it's given one with
+/// [`Self::with_undo_stack`]. Text is scrolled vertically to keep the caret in view, and can be
+/// scrolled with the mouse wheel. Text being composed by an input method is
+/// shown underlined at the caret, as in a text input.
+///
+/// A read-only text area can still be selected and copied from, which makes
+/// it suitable for showing logs. See [`Self::append`].
+#[must_use]
+pub struct TextArea {
+    widget_state: WidgetState,
+    focus: FocusId,
+    editor: Editor,
+    history: EditHistory,
+    style: TextStyle,
+    pub color: Color,
+    /// The measurements of the text from the last layout, used to find the
+    /// caret's position.
+    metrics: Option<TextMetrics>,
+    /// How far the text is scrolled up, in pixels.
+    scroll: f32,
+    /// Set when the caret moves, so that the next layout scrolls it into view.
+    /// Cleared so that scrolling with the mouse wheel isn't undone.
+    follow_caret: bool,
+    is_selecting: bool,
+    /// The horizontal position that the caret tries to keep while moving
+    /// between lines, so that it doesn't drift when passing short lines.
+    goal_x: Option<f32>,
+    read_only: bool,
+    on_change: Option<Callback>,
+}
+
+impl TextArea {
+    pub fn new(style: TextStyle) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            focus: FocusId::new(),
+            editor: Editor::default(),
+            history: EditHistory::new(UndoStack::new()),
+            style,
+            color: Color::BLACK,
+            metrics: None,
+            scroll: 0.0,
+            follow_caret: true,
+            is_selecting: false,
+            goal_x: None,
+            read_only: false,
+            on_change: None,
+        }
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.set_text(text);
+        self
+    }
+
+    /// Records edits on `stack` instead of a stack of the text area's own,
+    /// such as to undo them together with changes made elsewhere.
+    pub fn with_undo_stack(mut self, stack: UndoStack) -> Self {
+        self.history = EditHistory::new(stack);
+        self
+    }
+
+    /// Calls `callback` with the new text whenever the user changes it.
+    pub fn with_on_change(mut self, callback: impl FnMut(&str) + 'static) -> Self {
+        self.on_change = Some(Box::new(callback));
+        self
+    }
+
+    /// Prevents the user from changing the text, while still allowing it to
+    /// be selected and copied.
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.editor.text
+    }
+
+    /// Replaces the text and moves the caret to its end. This does not call
+    /// the change callback, and can't be undone.
+    pub fn set_text(&mut self, text: &str) {
+        self.editor = Editor::default();
+        self.editor.insert(&text.replace("\r\n", "\n"));
+        self.follow_caret = true;
+        self.widget_state.set_needs_layout();
+    }
+
+    /// Adds `text` to the end of the text without disturbing the selection.
+    /// If the caret was at the end, it stays there so that the new text is
+    /// scrolled into view. This does not call the change callback, and can't
+    /// be undone.
+    pub fn append(&mut self, text: &str) {
+        let end = self.editor.text.len();
+        let follow = self.editor.caret == end && self.editor.anchor == end;
+        self.editor.text.push_str(&text.replace("\r\n", "\n"));
+        if follow {
+            self.editor.move_to(self.editor.text.len(), false);
+            self.follow_caret = true;
+        }
+        self.widget_state.set_needs_layout();
+    }
+
+    /// The identifier used to give the text area the keyboard focus.
+    #[must_use]
+    pub fn focus_id(&self) -> FocusId {
+        self.focus
+    }
+
+    /// The line holding the caret before `offset`, and the caret's distance
+    /// from the start of that line.
+    fn caret_position(&self, offset: usize) -> Option<(&LineMetrics, f32)> {
+        let metrics = self.metrics.as_ref()?;
+        let line = &metrics.lines[line_of(metrics, offset)];
+        Some((line, caret_x(line, offset)))
+    }
+
+    /// Finds the caret closest to `point`, in absolute coordinates.
+    fn offset_at(&self, point: Point) -> usize {
+        let metrics = match &self.metrics {
+            Some(metrics) => metrics,
+            None => return 0,
+        };
+
+        let origin = self.widget_state.origin();
+        let x = f32::from(point.x - origin.x - PADDING);
+        let y = f32::from(point.y - origin.y - PADDING) + self.scroll;
+        let line = (y / metrics.line_height).floor().max(0.0) as usize;
+
+        let offset = metrics
+            .caret_at(line.min(metrics.lines.len() - 1), x)
+            .map_or(0, |caret| caret.offset);
+        self.editor.text_offset(offset)
+    }
+
+    /// Moves the caret up or down by `lines`, keeping as close as possible to
+    /// the horizontal position it had before moving between lines.
+    fn move_vertically(&mut self, lines: isize, extend: bool, goal_x: Option<f32>) -> bool {
+        let metrics = match &self.metrics {
+            Some(metrics) => metrics,
+            None => return false,
+        };
+
+        let x = goal_x.unwrap_or_else(|| {
+            let line = &metrics.lines[line_of(metrics, self.editor.caret)];
+            caret_x(line, self.editor.caret)
+        });
+        self.goal_x = Some(x);
+
+        let offset = vertical_offset(metrics, self.editor.caret, lines, x);
+        self.editor.move_to(offset, extend)
+    }
+
+    /// The furthest that the text can be scrolled without leaving space after
+    /// its end.
+    fn max_scroll(&self) -> f32 {
+        let visible = f32::from(self.widget_state.rect().height() - PADDING - PADDING);
+        self.metrics
+            .as_ref()
+            .map_or(0.0, |metrics| (metrics.height - visible).max(0.0))
+    }
+
+    /// The number of lines that fit in the text area, used to move by pages.
+    fn page_lines(&self) -> isize {
+        let visible = f32::from(self.widget_state.rect().height() - PADDING - PADDING);
+        self.metrics.as_ref().map_or(1, |metrics| {
+            ((visible / metrics.line_height).floor() as isize).max(1)
+        })
+    }
+
+    fn handle_key(&mut self, context: &mut UpdateContext, key: Key, modifiers: Modifiers) -> bool {
+        let Modifiers { shift, control, .. } = modifiers;
+        let end = self.editor.text.len();
+        let goal_x = self.goal_x.take();
+
+        match key {
+            Key::Left => self.editor.move_left(shift, control),
+            Key::Right => self.editor.move_right(shift, control),
+            Key::Up => self.move_vertically(-1, shift, goal_x),
+            Key::Down => self.move_vertically(1, shift, goal_x),
+            Key::PageUp => self.move_vertically(-self.page_lines(), shift, goal_x),
+            Key::PageDown => self.move_vertically(self.page_lines(), shift, goal_x),
+            Key::Home if control => self.editor.move_to(0, shift),
+            Key::End if control => self.editor.move_to(end, shift),
+            Key::Home => match self.caret_position(self.editor.caret) {
+                Some((line, _)) => self.editor.move_to(line.range.start, shift),
+                None => false,
+            },
+            Key::End => match self.caret_position(self.editor.caret) {
+                Some((line, _)) => self.editor.move_to(line.range.end, shift),
+                None => false,
+            },
+            Key::A if control => self.editor.select_all(),
+            Key::C if control => {
+                if !self.editor.selected_text().is_empty() {
+                    context.set_clipboard_text(self.editor.selected_text());
+                }
+                false
+            }
+            Key::Escape => {
+                context.set_focus(None);
+                true
+            }
+            _ if self.read_only => false,
+            Key::X if control => {
+                if self.editor.selected_text().is_empty() {
+                    return false;
+                }
+                context.set_clipboard_text(self.editor.selected_text());
+                self.edit(EditKind::Other, |editor| editor.delete_backward(false))
+            }
+            Key::V if control => match context.clipboard_text() {
+                Some(text) => self.edit(EditKind::Other, |editor| {
+                    editor.insert(&text.replace("\r\n", "\n"));
+                    true
+                }),
+                None => false,
+            },
+            Key::Z if control => {
+                context.set_handled();
+                if shift {
+                    self.history.stack().redo();
+                } else {
+                    self.history.stack().undo();
+                }
+                self.restore()
+            }
+            Key::Y if control => {
+                context.set_handled();
+                self.history.stack().redo();
+                self.restore()
+            }
+            // Each line break is undone on its own.
+            Key::Enter => self.edit(EditKind::Other, |editor| {
+                editor.insert("\n");
+                true
+            }),
+            Key::Backspace => {
+                self.edit(EditKind::Deleting, |editor| editor.delete_backward(control))
+            }
+            Key::Delete => self.edit(EditKind::Deleting, |editor| editor.delete_forward(control)),
+            _ => false,
+        }
+    }
+
+    /// Changes the text with `edit`, recording the change to be undone.
+    /// `edit` returns `true` if it changed anything.
+    fn edit(&mut self, kind: EditKind, edit: impl FnOnce(&mut Editor) -> bool) -> bool {
+        let before = self.editor.clone();
+        let changed = edit(&mut self.editor);
+        if changed {
+            self.history.record(kind, before, &self.editor);
+        }
+        self.notify_change(changed);
+        changed
+    }
+
+    /// Shows the text that an undo or redo changed back to, if any. Returns
+    /// `true` if the text changed.
+    fn restore(&mut self) -> bool {
+        match self.history.take_restored() {
+            Some(editor) => {
+                self.editor = editor;
+                self.goal_x = None;
+                self.follow_caret = true;
+                self.notify_change(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn notify_change(&mut self, changed: bool) {
+        if changed {
+            if let Some(on_change) = &mut self.on_change {
+                on_change(&self.editor.text);
+            }
+        }
+    }
+}
+
+/// The state of a [`TextArea`] that is carried over when the widget tree is
+/// rebuilt.
+struct SavedState {
+    focus: FocusId,
+    editor: Editor,
+    history: EditHistory,
+    scroll: f32,
+}
+
+impl Widget for TextArea {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        // Every change moves the caret, which may scroll the text, so the text
+        // is laid out again rather than only redrawn.
+        let changed = match context.event() {
+            // Touches and raw mouse motion are also reported as mouse input.
+            Event::None
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => false,
+            Event::Scroll { delta, .. } => {
+                if !self
+                    .widget_state
+                    .rect()
+                    .contains_point(context.cursor_position())
+                {
+                    return PostUpdate::NoChange;
+                }
+
+                let before = self.scroll;
+                self.scroll = (self.scroll + f32::from(delta.y)).clamp(0.0, self.max_scroll());
+                return if self.scroll == before {
+                    PostUpdate::NoChange
+                } else {
+                    PostUpdate::NeedsRedraw
+                };
+            }
+            Event::MouseButton { button, state } => {
+                if !button.is_left() {
+                    false
+                } else if state.is_released() {
+                    self.is_selecting = false;
+                    false
+                } else if self
+                    .widget_state
+                    .rect()
+                    .contains_point(context.cursor_position())
+                {
+                    context.set_focus(Some(self.focus));
+                    let offset = self.offset_at(context.cursor_position());
+                    self.editor.move_to(offset, false);
+                    self.goal_x = None;
+                    self.is_selecting = true;
+                    true
+                } else {
+                    false
+                }
+            }
+            Event::CursorMove { position } => {
+                if self.is_selecting
+                    && context.is_focused(self.focus)
+                    && context.mouse_button_state(MouseButton::Left).is_pressed()
+                {
+                    let offset = self.offset_at(position);
+                    self.editor.move_to(offset, true)
+                } else {
+                    false
+                }
+            }
+            Event::KeyPress { key, modifiers } => {
+                context.is_focused(self.focus) && self.handle_key(context, key, modifiers)
+            }
+            Event::Text { character } => {
+                if context.is_focused(self.focus) && !self.read_only {
+                    self.goal_x = None;
+                    self.edit(EditKind::Typing, |editor| {
+                        editor.insert(character.encode_utf8(&mut [0; 4]));
+                        true
+                    })
+                } else {
+                    false
+                }
+            }
+            Event::Composition => {
+                context.is_focused(self.focus)
+                    && !self.read_only
+                    && self.editor.set_composition(context.composition().cloned())
+            }
+        };
+
+        if changed {
+            self.follow_caret = true;
+            PostUpdate::NeedsLayout
+        } else {
+            PostUpdate::NoChange
+        }
+    }
+
+    fn accept_state_change(&mut self) -> PostUpdate {
+        if self.restore() {
+            PostUpdate::NeedsLayout
+        } else {
+            PostUpdate::NoChange
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = constraints.max;
+        let width = f32::from(extent.width.saturating_sub(PADDING + PADDING));
+        let metrics = context.measure_text(&self.editor.display_text(), &self.style, Some(width));
+
+        // Scroll just far enough to keep the caret's line in view, without
+        // leaving space after the end of the text.
+        let visible = f32::from(extent.height.saturating_sub(PADDING + PADDING));
+        if std::mem::take(&mut self.follow_caret) {
+            let line = &metrics.lines[line_of(&metrics, self.editor.display_caret())];
+            let top = line.baseline - metrics.ascent;
+            let bottom = top + metrics.line_height;
+            if bottom - self.scroll > visible {
+                self.scroll = bottom - visible;
+            } else if top < self.scroll {
+                self.scroll = top;
+            }
+        }
+        self.scroll = self.scroll.min((metrics.height - visible).max(0.0));
+
+        self.metrics = Some(metrics);
+        extent
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        let bounds = Rect::new(Point::zero(), extent);
+        let is_focused = canvas.is_focused(self.focus);
+
+        canvas.draw_rect(bounds, &Paint::Fill { color: BACKGROUND });
+        canvas.stroke_rect(
+            bounds,
+            &Stroke::solid(if is_focused { FOCUSED_BORDER } else { BORDER }, 1.0),
+        );
+
+        let metrics = match &self.metrics {
+            Some(metrics) => metrics,
+            None => return,
+        };
+
+        let content = Rect {
+            left: PADDING,
+            top: PADDING,
+            right: extent.width - PADDING,
+            bottom: extent.height - PADDING,
+        };
+        let left = f32::from(PADDING);
+        let visible = f32::from(content.bottom - content.top);
+        // The text being composed replaces the selection.
+        let selection = match self.editor.composition {
+            Some(_) => 0..0,
+            None => self.editor.selection(),
+        };
+
+        canvas.push_clip_rounded_rect(content, Px(0));
+
+        for line in &metrics.lines {
+            let top = line.baseline - metrics.ascent - self.scroll;
+            if top + metrics.line_height < 0.0 || top > visible {
+                continue;
+            }
+
+            let line_top = PADDING + Px::from(top.round());
+
+            // Selected line breaks are shown as a little extra space after
+            // the end of the line.
+            let start = selection.start.max(line.range.start);
+            let end = selection.end.min(line.range.end);
+            let past_end = selection.end > line.range.end && selection.start <= line.range.end;
+            if is_focused && (start < end || past_end) {
+                let a = caret_x(line, start);
+                let b = caret_x(line, end);
+                let extra = if past_end { self.style.size / 4.0 } else { 0.0 };
+                canvas.draw_rect(
+                    Rect {
+                        left: Px::from((left + a.min(b)).round()),
+                        top: line_top,
+                        right: Px::from((left + a.max(b) + extra).round()),
+                        bottom: line_top + Px::from(metrics.line_height.round()),
+                    },
+                    &Paint::Fill { color: SELECTION },
+                );
+            }
+
+            if let Some(composition) = self.editor.composition_range() {
+                let start = composition.start.max(line.range.start);
+                let end = composition.end.min(line.range.end);
+                if start < end {
+                    let bottom = line_top + Px::from(metrics.line_height.round());
+                    canvas.draw_rect(
+                        Rect {
+                            left: Px::from((left + caret_x(line, start)).round()),
+                            top: bottom - Px(1),
+                            right: Px::from((left + caret_x(line, end)).round()),
+                            bottom,
+                        },
+                        &Paint::Fill { color: self.color },
+                    );
+                }
+            }
+
+            canvas.draw_text(
+                &line.shaped,
+                Point::new(
+                    PADDING,
+                    PADDING + Px::from((line.baseline - self.scroll).round()),
+                ),
+                self.style.size,
+                self.color,
+            );
+        }
+
+        if is_focused && !self.read_only {
+            if let Some((line, x)) = self.caret_position(self.editor.display_caret()) {
+                let caret_left = Px::from((left + x).round());
+                let top =
+                    PADDING + Px::from((line.baseline - metrics.ascent - self.scroll).round());
+                canvas.draw_rect(
+                    Rect {
+                        left: caret_left,
+                        top,
+                        right: caret_left + Px(1),
+                        bottom: top + Px::from(metrics.line_height.round()),
+                    },
+                    &Paint::Fill { color: self.color },
+                );
+            }
+        }
+
+        canvas.pop_clip();
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(SavedState {
+            focus: self.focus,
+            editor: self.editor.clone(),
+            history: self.history.clone(),
+            scroll: self.scroll,
+        }))
+    }
+
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        if let Ok(state) = state.downcast::<SavedState>() {
+            self.focus = state.focus;
+            self.editor = state.editor;
+            self.history = state.history;
+            self.scroll = state.scroll;
+            // Keep the restored scroll position rather than jumping to the caret.
+            self.follow_caret = false;
+            self.widget_state.set_needs_layout();
+        }
+    }
+
+    fn accessibility(&self) -> Option<AccessNode> {
+        let node = AccessNode::new(Role::MultilineTextInput)
+            .with_value(&self.editor.text)
+            .with_focus(self.focus);
+
+        if self.read_only {
+            Some(node)
+        } else {
+            Some(node.with_action(ActionKind::SetValue))
+        }
+    }
+
+    fn accept_action(&mut self, action: &Action) -> PostUpdate {
+        if let Action::SetValue(text) = action {
+            let before = self.editor.clone();
+            self.set_text(text);
+            self.history.record(EditKind::Other, before, &self.editor);
+            self.notify_change(true);
+        }
+        PostUpdate::NeedsLayout
+    }
+
+    fn cursor(&self, _point: Point) -> Option<CursorIcon> {
+        Some(CursorIcon::Text)
+    }
+
+    fn ime_area(&self, focus: FocusId) -> Option<Rect> {
+        if focus != self.focus || self.read_only {
+            return None;
+        }
+
+        let metrics = self.metrics.as_ref()?;
+        let (line, x) = self.caret_position(self.editor.display_caret())?;
+        let origin = self.widget_state.origin();
+        let left = origin.x + PADDING + Px::from(x.round());
+        let top =
+            origin.y + PADDING + Px::from((line.baseline - metrics.ascent - self.scroll).round());
+        Some(Rect {
+            left,
+            top,
+            right: left + Px(1),
+            bottom: top + Px::from(metrics.line_height.round()),
+        })
+    }
+}
+
+/// Finds the line that the caret before `offset` is drawn on. Offsets in the
+/// whitespace that a line was wrapped at belong to the line after it.
+fn line_of(metrics: &TextMetrics, offset: usize) -> usize {
+    metrics
+        .lines
+        .iter()
+        .position(|line| offset <= line.range.end)
+        .unwrap_or(metrics.lines.len() - 1)
+}
+
+/// The distance from the start of `line` to the caret before `offset`, which
+/// is clamped to the line.
+fn caret_x(line: &LineMetrics, offset: usize) -> f32 {
+    line.carets
+        .iter()
+        .rev()
+        .find(|caret| caret.offset <= offset)
+        .or_else(|| line.carets.first())
+        .map_or(0.0, |caret| caret.x)
+}
+
+/// Finds the offset of the caret closest to `x` on the line `lines` away from
+/// the one holding `offset`. Moving past the first or last line moves to the
+/// start or end of the text.
+fn vertical_offset(metrics: &TextMetrics, offset: usize, lines: isize, x: f32) -> usize {
+    let target = line_of(metrics, offset) as isize + lines;
+    if target < 0 {
+        0
+    } else if target as usize >= metrics.lines.len() {
+        metrics.lines.last().map_or(offset, |line| line.range.end)
+    } else {
+        metrics
+            .caret_at(target as usize, x)
+            .map_or(offset, |caret| caret.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::{Caret, ShapedLine};
+
+    /// Lays out `lines` as if every character were 1 pixel wide and every
+    /// line 10 pixels tall.
+    fn metrics(text: &str, lines: &[std::ops::Range<usize>]) -> TextMetrics {
+        let lines: Vec<_> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, range)| LineMetrics {
+                range: range.clone(),
+                width: range.len() as f32,
+                baseline: 8.0 + i as f32 * 10.0,
+                carets: text[range.clone()]
+                    .char_indices()
+                    .map(|(offset, _)| range.start + offset)
+                    .chain(std::iter::once(range.end))
+                    .map(|offset| Caret {
+                        offset,
+                        x: (offset - range.start) as f32,
+                    })
+                    .collect(),
+                shaped: ShapedLine::default(),
+            })
+            .collect();
+
+        TextMetrics {
+            width: lines.iter().map(|line| line.width).fold(0.0, f32::max),
+            height: lines.len() as f32 * 10.0,
+            ascent: 8.0,
+            descent: 2.0,
+            line_height: 10.0,
+            lines,
+        }
+    }
+
+    #[test]
+    fn find_lines() {
+        // "hello world" wrapped after "hello", then a line break.
+        let m = metrics("hello world\nab", &[0..5, 6..11, 12..14]);
+
+        assert_eq!(line_of(&m, 0), 0);
+        assert_eq!(line_of(&m, 5), 0);
+        // The wrapped space belongs to the next line.
+        assert_eq!(line_of(&m, 6), 1);
+        assert_eq!(line_of(&m, 11), 1);
+        assert_eq!(line_of(&m, 14), 2);
+
+        assert_eq!(caret_x(&m.lines[1], 8), 2.0);
+        assert_eq!(caret_x(&m.lines[1], 5), 0.0);
+    }
+
+    #[test]
+    fn move_between_lines() {
+        let m = metrics("hello world\nab", &[0..5, 6..11, 12..14]);
+
+        assert_eq!(vertical_offset(&m, 3, 1, 3.0), 9);
+        // Short lines clamp the caret, but the goal is kept for later lines.
+        assert_eq!(vertical_offset(&m, 9, 1, 3.0), 14);
+        assert_eq!(vertical_offset(&m, 14, -2, 3.0), 3);
+
+        assert_eq!(vertical_offset(&m, 3, -1, 3.0), 0);
+        assert_eq!(vertical_offset(&m, 3, 10, 3.0), 14);
+    }
+}
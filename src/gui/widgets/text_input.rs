@@ -0,0 +1,539 @@
+use std::any::Any;
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::{
+        accessibility::{AccessNode, Action, ActionKind, Role},
+        input::{Event, FocusId, MouseButton},
+        undo::UndoStack,
+    },
+    shell::{
+        event::{Key, Modifiers},
+        CursorIcon,
+    },
+    text::{TextMetrics, TextStyle},
+};
+
+use super::{
+    editor::{EditHistory, EditKind, Editor},
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// The space between the edge of the input and its text.
+const PADDING: Px = Px(4);
+
+const BACKGROUND: Color = Color::WHITE;
+
+const BORDER: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+const FOCUSED_BORDER: Color = Color {
+    r: 0.0,
+    g: 0.4,
+    b: 0.9,
+    a: 1.0,
+};
+
+const SELECTION: Color = Color {
+    r: 0.6,
+    g: 0.8,
+    b: 1.0,
+    a: 1.0,
+};
+
+type Callback = Box<dyn FnMut(&str)>;
+
+/// A single line of editable text.
+///
+/// The input takes the keyboard focus when clicked. While focused, the caret
+/// can be moved with the arrow keys, Home, and End, with Shift extending the
+/// selection and Control moving by words. Control+C, Control+X, and Control+V
+/// copy, cut, and paste through the system clipboard, and Control+Z undoes
+/// edits while Control+Y or Control+Shift+Z redoes them. Text is scrolled
+/// horizontally to keep the caret in view when it doesn't fit. Text being
+/// composed by an input method is shown underlined at the caret until it is
+/// committed.
+///
+/// The input's font must be in the fonts used for layout and drawing.
+#[must_use]
+pub struct TextInput {
+    widget_state: WidgetState,
+    focus: FocusId,
+    editor: Editor,
+    history: EditHistory,
+    style: TextStyle,
+    pub color: Color,
+    /// The measurements of the text from the last layout, used to find the
+    /// caret's position.
+    metrics: Option<TextMetrics>,
+    /// How far the text is scrolled to the left, in pixels.
+    scroll: f32,
+    is_selecting: bool,
+    on_change: Option<Callback>,
+    on_submit: Option<Callback>,
+}
+
+impl TextInput {
+    pub fn new(style: TextStyle) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            focus: FocusId::new(),
+            editor: Editor::default(),
+            history: EditHistory::new(UndoStack::new()),
+            style,
+            color: Color::BLACK,
+            metrics: None,
+            scroll: 0.0,
+            is_selecting: false,
+            on_change: None,
+            on_submit: None,
+        }
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.set_text(text);
+        self
+    }
+
+    /// Records edits on `stack` instead of a stack of the input's own, such as
+    /// to undo them together with changes made elsewhere.
+    pub fn with_undo_stack(mut self, stack: UndoStack) -> Self {
+        self.history = EditHistory::new(stack);
+        self
+    }
+
+    /// Calls `callback` with the new text whenever the user changes it.
+    pub fn with_on_change(mut self, callback: impl FnMut(&str) + 'static) -> Self {
+        self.on_change = Some(Box::new(callback));
+        self
+    }
+
+    /// Calls `callback` with the text when the user presses Enter.
+    pub fn with_on_submit(mut self, callback: impl FnMut(&str) + 'static) -> Self {
+        self.on_submit = Some(Box::new(callback));
+        self
+    }
+
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.editor.text
+    }
+
+    /// Replaces the text and moves the caret to its end. Line breaks are
+    /// replaced with spaces. This does not call the change callback, and can't
+    /// be undone.
+    pub fn set_text(&mut self, text: &str) {
+        self.editor = Editor::default();
+        self.editor.insert(&text.replace(['\r', '\n'], " "));
+        self.widget_state.set_needs_layout();
+    }
+
+    /// The identifier used to give the input the keyboard focus.
+    #[must_use]
+    pub fn focus_id(&self) -> FocusId {
+        self.focus
+    }
+
+    /// The distance from the start of the text to the caret before `offset`.
+    fn caret_x(&self, offset: usize) -> f32 {
+        self.metrics
+            .as_ref()
+            .and_then(|metrics| metrics.lines.first())
+            .and_then(|line| line.carets.iter().find(|caret| caret.offset == offset))
+            .map_or(0.0, |caret| caret.x)
+    }
+
+    /// Finds the caret closest to `point`, in absolute coordinates.
+    fn offset_at(&self, point: Point) -> usize {
+        let x = f32::from(point.x - self.widget_state.origin().x - PADDING) + self.scroll;
+        let offset = self
+            .metrics
+            .as_ref()
+            .and_then(|metrics| metrics.caret_at(0, x))
+            .map_or(0, |caret| caret.offset);
+        self.editor.text_offset(offset)
+    }
+
+    fn handle_key(&mut self, context: &mut UpdateContext, key: Key, modifiers: Modifiers) -> bool {
+        let Modifiers { shift, control, .. } = modifiers;
+        let end = self.editor.text.len();
+
+        match key {
+            Key::Left => self.editor.move_left(shift, control),
+            Key::Right => self.editor.move_right(shift, control),
+            Key::Home => self.editor.move_to(0, shift),
+            Key::End => self.editor.move_to(end, shift),
+            Key::A if control => self.editor.select_all(),
+            Key::C if control => {
+                if !self.editor.selected_text().is_empty() {
+                    context.set_clipboard_text(self.editor.selected_text());
+                }
+                false
+            }
+            Key::X if control => {
+                if self.editor.selected_text().is_empty() {
+                    return false;
+                }
+                context.set_clipboard_text(self.editor.selected_text());
+                self.edit(EditKind::Other, |editor| editor.delete_backward(false))
+            }
+            Key::V if control => match context.clipboard_text() {
+                Some(text) => self.edit(EditKind::Other, |editor| {
+                    editor.insert(&text.replace(['\r', '\n'], " "));
+                    true
+                }),
+                None => false,
+            },
+            Key::Z if control => {
+                context.set_handled();
+                if shift {
+                    self.history.stack().redo();
+                } else {
+                    self.history.stack().undo();
+                }
+                self.restore()
+            }
+            Key::Y if control => {
+                context.set_handled();
+                self.history.stack().redo();
+                self.restore()
+            }
+            Key::Backspace => {
+                self.edit(EditKind::Deleting, |editor| editor.delete_backward(control))
+            }
+            Key::Delete => self.edit(EditKind::Deleting, |editor| editor.delete_forward(control)),
+            Key::Enter => {
+                if let Some(on_submit) = &mut self.on_submit {
+                    on_submit(&self.editor.text);
+                }
+                false
+            }
+            Key::Escape => {
+                context.set_focus(None);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Changes the text with `edit`, recording the change to be undone.
+    /// `edit` returns `true` if it changed anything.
+    fn edit(&mut self, kind: EditKind, edit: impl FnOnce(&mut Editor) -> bool) -> bool {
+        let before = self.editor.clone();
+        let changed = edit(&mut self.editor);
+        if changed {
+            self.history.record(kind, before, &self.editor);
+        }
+        self.notify_change(changed);
+        changed
+    }
+
+    /// Shows the text that an undo or redo changed back to, if any. Returns
+    /// `true` if the text changed.
+    fn restore(&mut self) -> bool {
+        match self.history.take_restored() {
+            Some(editor) => {
+                self.editor = editor;
+                self.notify_change(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn notify_change(&mut self, changed: bool) {
+        if changed {
+            if let Some(on_change) = &mut self.on_change {
+                on_change(&self.editor.text);
+            }
+        }
+    }
+}
+
+/// The state of a [`TextInput`] that is carried over when the widget tree is
+/// rebuilt.
+struct SavedState {
+    focus: FocusId,
+    editor: Editor,
+    history: EditHistory,
+    scroll: f32,
+}
+
+impl Widget for TextInput {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        // Every change moves the caret, which may scroll the text, so the text
+        // is laid out again rather than only redrawn.
+        let changed = match context.event() {
+            // Touches and raw mouse motion are also reported as mouse input.
+            Event::None
+            | Event::Scroll { .. }
+            | Event::Touch { .. }
+            | Event::Gesture { .. }
+            | Event::RawMouseMotion { .. } => false,
+            Event::MouseButton { button, state } => {
+                if !button.is_left() {
+                    false
+                } else if state.is_released() {
+                    self.is_selecting = false;
+                    false
+                } else if self
+                    .widget_state
+                    .rect()
+                    .contains_point(context.cursor_position())
+                {
+                    context.set_focus(Some(self.focus));
+                    let offset = self.offset_at(context.cursor_position());
+                    self.editor.move_to(offset, false);
+                    self.is_selecting = true;
+                    true
+                } else {
+                    false
+                }
+            }
+            Event::CursorMove { position } => {
+                if self.is_selecting
+                    && context.is_focused(self.focus)
+                    && context.mouse_button_state(MouseButton::Left).is_pressed()
+                {
+                    let offset = self.offset_at(position);
+                    self.editor.move_to(offset, true)
+                } else {
+                    false
+                }
+            }
+            Event::KeyPress { key, modifiers } => {
+                context.is_focused(self.focus) && self.handle_key(context, key, modifiers)
+            }
+            Event::Text { character } => {
+                if context.is_focused(self.focus) {
+                    self.edit(EditKind::Typing, |editor| {
+                        editor.insert(character.encode_utf8(&mut [0; 4]));
+                        true
+                    })
+                } else {
+                    false
+                }
+            }
+            Event::Composition => {
+                context.is_focused(self.focus)
+                    && self.editor.set_composition(context.composition().cloned())
+            }
+        };
+
+        if changed {
+            PostUpdate::NeedsLayout
+        } else {
+            PostUpdate::NoChange
+        }
+    }
+
+    fn accept_state_change(&mut self) -> PostUpdate {
+        if self.restore() {
+            PostUpdate::NeedsLayout
+        } else {
+            PostUpdate::NoChange
+        }
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let metrics = context.measure_text(&self.editor.display_text(), &self.style, None);
+
+        let extent = constraints.max_fit(Extent {
+            width: constraints.max.width,
+            height: Px::from(metrics.line_height.ceil()) + PADDING + PADDING,
+        });
+
+        // Scroll just far enough to keep the caret in view, without leaving
+        // space after the end of the text.
+        let visible = f32::from(extent.width.saturating_sub(PADDING + PADDING));
+        let width = metrics.width;
+        self.metrics = Some(metrics);
+        let caret = self.caret_x(self.editor.display_caret());
+        if caret - self.scroll > visible {
+            self.scroll = caret - visible;
+        } else if caret < self.scroll {
+            self.scroll = caret;
+        }
+        self.scroll = self.scroll.min((width - visible).max(0.0));
+
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, _height: Px) -> Px {
+        // The text scrolls when it doesn't fit, so the input works at any
+        // width.
+        let text = match size {
+            IntrinsicSize::Min => Px(0),
+            IntrinsicSize::Max => {
+                let metrics = context.measure_text(&self.editor.text, &self.style, None);
+                Px::from(metrics.width.ceil())
+            }
+        };
+        text + PADDING + PADDING
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, _size: IntrinsicSize, _width: Px) -> Px {
+        let metrics = context.measure_text(&self.editor.text, &self.style, None);
+        Px::from(metrics.line_height.ceil()) + PADDING + PADDING
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, extent: Extent) {
+        let bounds = Rect::new(Point::zero(), extent);
+        let is_focused = canvas.is_focused(self.focus);
+
+        canvas.draw_rect(bounds, &Paint::Fill { color: BACKGROUND });
+        canvas.stroke_rect(
+            bounds,
+            &Stroke::solid(if is_focused { FOCUSED_BORDER } else { BORDER }, 1.0),
+        );
+
+        let metrics = match &self.metrics {
+            Some(metrics) => metrics,
+            None => return,
+        };
+
+        let content = Rect {
+            left: PADDING,
+            top: PADDING,
+            right: extent.width - PADDING,
+            bottom: extent.height - PADDING,
+        };
+        let start = f32::from(PADDING) - self.scroll;
+
+        canvas.push_clip_rounded_rect(content, Px(0));
+
+        // The text being composed replaces the selection.
+        let selection = self.editor.selection();
+        if is_focused && !selection.is_empty() && self.editor.composition.is_none() {
+            let a = start + self.caret_x(selection.start);
+            let b = start + self.caret_x(selection.end);
+            canvas.draw_rect(
+                Rect {
+                    left: Px::from(a.min(b).round()),
+                    right: Px::from(a.max(b).round()),
+                    ..content
+                },
+                &Paint::Fill { color: SELECTION },
+            );
+        }
+
+        canvas.draw_text(
+            &metrics.lines[0].shaped,
+            Point::new(
+                Px::from(start.round()),
+                PADDING + Px::from(metrics.ascent.round()),
+            ),
+            self.style.size,
+            self.color,
+        );
+
+        if let Some(composition) = self.editor.composition_range() {
+            let a = start + self.caret_x(composition.start);
+            let b = start + self.caret_x(composition.end);
+            canvas.draw_rect(
+                Rect {
+                    left: Px::from(a.round()),
+                    top: content.bottom - Px(1),
+                    right: Px::from(b.round()),
+                    bottom: content.bottom,
+                },
+                &Paint::Fill { color: self.color },
+            );
+        }
+
+        if is_focused {
+            let x = Px::from((start + self.caret_x(self.editor.display_caret())).round());
+            canvas.draw_rect(
+                Rect {
+                    left: x,
+                    right: x + Px(1),
+                    ..content
+                },
+                &Paint::Fill { color: self.color },
+            );
+        }
+
+        canvas.pop_clip();
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(SavedState {
+            focus: self.focus,
+            editor: self.editor.clone(),
+            history: self.history.clone(),
+            scroll: self.scroll,
+        }))
+    }
+
+    fn restore_state(&mut self, state: Box<dyn Any>) {
+        if let Ok(state) = state.downcast::<SavedState>() {
+            self.focus = state.focus;
+            self.editor = state.editor;
+            self.history = state.history;
+            self.scroll = state.scroll;
+            self.widget_state.set_needs_layout();
+        }
+    }
+
+    fn accessibility(&self) -> Option<AccessNode> {
+        Some(
+            AccessNode::new(Role::TextInput)
+                .with_value(&self.editor.text)
+                .with_focus(self.focus)
+                .with_action(ActionKind::SetValue),
+        )
+    }
+
+    fn accept_action(&mut self, action: &Action) -> PostUpdate {
+        if let Action::SetValue(text) = action {
+            let before = self.editor.clone();
+            self.set_text(text);
+            self.history.record(EditKind::Other, before, &self.editor);
+            self.notify_change(true);
+        }
+        PostUpdate::NeedsLayout
+    }
+
+    fn cursor(&self, _point: Point) -> Option<CursorIcon> {
+        Some(CursorIcon::Text)
+    }
+
+    fn ime_area(&self, focus: FocusId) -> Option<Rect> {
+        if focus != self.focus {
+            return None;
+        }
+
+        let metrics = self.metrics.as_ref()?;
+        let origin = self.widget_state.origin();
+        let x = self.caret_x(self.editor.display_caret()) - self.scroll;
+        let left = origin.x + PADDING + Px::from(x.round());
+        let top = origin.y + PADDING;
+        Some(Rect {
+            left,
+            top,
+            right: left + Px(1),
+            bottom: top + Px::from(metrics.line_height.ceil()),
+        })
+    }
+}
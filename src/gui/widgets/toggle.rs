@@ -0,0 +1,678 @@
+use std::{cell::Cell, rc::Rc};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Point, Px, Rect},
+        Paint, Stroke,
+    },
+    gui::{
+        accessibility::{AccessNode, Action, ActionKind, Role, Toggled},
+        input::{ButtonState, Event, FocusId, MouseButton},
+    },
+    shell::event::Key,
+    text::{ShapedLine, TextStyle},
+};
+
+use super::{
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// The space between a toggle's indicator and its label.
+const LABEL_GAP: Px = Px(6);
+
+/// The space between an indicator and the ring drawn around it when its
+/// widget has the keyboard focus.
+const FOCUS_RING_GAP: Px = Px(2);
+
+const BACKGROUND: Color = Color::WHITE;
+
+const BORDER: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+const ACCENT: Color = Color {
+    r: 0.0,
+    g: 0.4,
+    b: 0.9,
+    a: 1.0,
+};
+
+const FOCUSED_BORDER: Color = ACCENT;
+
+const SWITCH_OFF: Color = Color {
+    r: 0.7,
+    g: 0.7,
+    b: 0.7,
+    a: 1.0,
+};
+
+/// The state of a [`Checkbox`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CheckState {
+    #[default]
+    Unchecked,
+    Checked,
+    /// Neither checked nor unchecked, such as for a checkbox that controls a
+    /// group of other checkboxes that are only partly checked. The user can't
+    /// choose this state, only leave it.
+    Indeterminate,
+}
+
+impl CheckState {
+    #[must_use]
+    pub fn is_checked(self) -> bool {
+        self == Self::Checked
+    }
+
+    /// The state that clicking the checkbox changes it to.
+    #[must_use]
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Checked => Self::Unchecked,
+            Self::Unchecked | Self::Indeterminate => Self::Checked,
+        }
+    }
+}
+
+/// The height of a toggle's indicator, relative to the height of its label.
+fn indicator_height(line_height: Px) -> Px {
+    Px::from((f32::from(line_height) * 0.8).round())
+}
+
+/// The parts shared by every toggle widget: a label drawn after an indicator,
+/// and the keyboard focus.
+struct Toggle {
+    widget_state: WidgetState,
+    focus: FocusId,
+    label: String,
+    style: TextStyle,
+    /// The width of the indicator as a multiple of its height.
+    aspect: f32,
+    // Computed during layout.
+    shaped: Option<ShapedLine>,
+    ascent: Px,
+    line_height: Px,
+}
+
+impl Toggle {
+    fn new(label: &str, style: TextStyle, aspect: f32) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            focus: FocusId::new(),
+            label: label.to_string(),
+            style,
+            aspect,
+            shaped: None,
+            ascent: Px(0),
+            line_height: Px(0),
+        }
+    }
+
+    /// Checks if the current event activates the toggle, which happens when
+    /// it is clicked or Space is pressed while it has the keyboard focus.
+    /// Clicking also gives it the focus.
+    fn is_activated(&self, context: &mut UpdateContext) -> bool {
+        match context.event() {
+            Event::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Pressed,
+            } => {
+                let clicked = self
+                    .widget_state
+                    .rect()
+                    .contains_point(context.cursor_position());
+                if clicked {
+                    context.set_focus(Some(self.focus));
+                }
+                clicked
+            }
+            Event::KeyPress {
+                key: Key::Space, ..
+            } => context.is_focused(self.focus),
+            _ => false,
+        }
+    }
+
+    /// Describes the toggle to assistive technology, which can click it.
+    fn access_node(&self, role: Role) -> AccessNode {
+        AccessNode::new(role)
+            .with_name(&self.label)
+            .with_focus(self.focus)
+            .with_action(ActionKind::Click)
+    }
+
+    /// The bounds of the indicator, vertically centered on the label.
+    fn indicator(&self) -> Rect {
+        let height = indicator_height(self.line_height);
+        let top = (self.line_height - height) / 2;
+        Rect {
+            left: Px(0),
+            top,
+            right: height * self.aspect,
+            bottom: top + height,
+        }
+    }
+
+    fn layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let metrics = context.measure_text(&self.label, &self.style, None);
+        self.ascent = Px::from(metrics.ascent.round());
+        self.line_height = Px::from(metrics.line_height.ceil());
+
+        let label_width = Px::from(metrics.width.ceil());
+        self.shaped = metrics.lines.into_iter().next().map(|line| line.shaped);
+
+        constraints.max_fit(Extent {
+            width: self.indicator().right + LABEL_GAP + label_width,
+            height: self.line_height,
+        })
+    }
+
+    /// The extent that [`Self::layout`] would fit to the constraints,
+    /// computed without laying out the toggle.
+    fn intrinsic_extent(&self, context: &LayoutContext) -> Extent {
+        let metrics = context.measure_text(&self.label, &self.style, None);
+        let line_height = Px::from(metrics.line_height.ceil());
+        Extent {
+            width: indicator_height(line_height) * self.aspect
+                + LABEL_GAP
+                + Px::from(metrics.width.ceil()),
+            height: line_height,
+        }
+    }
+
+    /// Draws the label and, if the toggle is focused, a ring around the
+    /// indicator with corners rounded by `radius`.
+    fn draw(&self, canvas: &mut DrawContext, radius: Px, color: Color) {
+        let indicator = self.indicator();
+
+        if canvas.is_focused(self.focus) {
+            canvas.stroke_rounded_rect(
+                indicator.inflate(FOCUS_RING_GAP, FOCUS_RING_GAP),
+                radius + FOCUS_RING_GAP,
+                &Stroke::solid(FOCUSED_BORDER, 1.0),
+            );
+        }
+
+        if let Some(shaped) = &self.shaped {
+            canvas.draw_text(
+                shaped,
+                Point::new(indicator.right + LABEL_GAP, self.ascent),
+                self.style.size,
+                color,
+            );
+        }
+    }
+}
+
+/// A box that can be checked and unchecked, followed by a label.
+///
+/// The checkbox is toggled by clicking it, or by pressing Space when it has
+/// the keyboard focus.
+#[must_use]
+pub struct Checkbox {
+    toggle: Toggle,
+    state: CheckState,
+    pub color: Color,
+    on_change: Option<Box<dyn FnMut(CheckState)>>,
+}
+
+impl Checkbox {
+    pub fn new(label: &str, style: TextStyle) -> Self {
+        Self {
+            toggle: Toggle::new(label, style, 1.0),
+            state: CheckState::Unchecked,
+            color: Color::BLACK,
+            on_change: None,
+        }
+    }
+
+    pub fn with_state(mut self, state: CheckState) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Calls `callback` with the new state whenever the user changes it.
+    pub fn with_on_change(mut self, callback: impl FnMut(CheckState) + 'static) -> Self {
+        self.on_change = Some(Box::new(callback));
+        self
+    }
+
+    #[must_use]
+    pub fn state(&self) -> CheckState {
+        self.state
+    }
+
+    /// Changes the state without calling the change callback.
+    pub fn set_state(&mut self, state: CheckState) {
+        self.state = state;
+        self.toggle.widget_state.set_needs_layout();
+    }
+
+    /// The identifier used to give the checkbox the keyboard focus.
+    #[must_use]
+    pub fn focus_id(&self) -> FocusId {
+        self.toggle.focus
+    }
+
+    fn toggle_state(&mut self) {
+        self.state = self.state.toggled();
+        if let Some(on_change) = &mut self.on_change {
+            on_change(self.state);
+        }
+    }
+}
+
+impl Widget for Checkbox {
+    fn widget_state(&self) -> &WidgetState {
+        &self.toggle.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.toggle.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        if !self.toggle.is_activated(context) {
+            return PostUpdate::NoChange;
+        }
+
+        self.toggle_state();
+        PostUpdate::NeedsRedraw
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        self.toggle.layout(context, constraints)
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, _size: IntrinsicSize, _height: Px) -> Px {
+        self.toggle.intrinsic_extent(context).width
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, _size: IntrinsicSize, _width: Px) -> Px {
+        self.toggle.intrinsic_extent(context).height
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        let radius = Px(3);
+        let bounds = self.toggle.indicator();
+        let size = f32::from(bounds.width());
+        let at = |x: f32, y: f32| {
+            Point::new(
+                bounds.left + Px::from((x * size).round()),
+                bounds.top + Px::from((y * size).round()),
+            )
+        };
+
+        match self.state {
+            CheckState::Unchecked => {
+                canvas.draw_rounded_rect(bounds, radius, &Paint::Fill { color: BACKGROUND });
+                canvas.stroke_rounded_rect(bounds, radius, &Stroke::solid(BORDER, 1.0));
+            }
+            CheckState::Checked => {
+                canvas.draw_rounded_rect(bounds, radius, &Paint::Fill { color: ACCENT });
+                let mark = Stroke::solid(Color::WHITE, (size / 8.0).max(1.5));
+                canvas.stroke_line(at(0.22, 0.52), at(0.42, 0.72), &mark);
+                canvas.stroke_line(at(0.42, 0.72), at(0.78, 0.3), &mark);
+            }
+            CheckState::Indeterminate => {
+                canvas.draw_rounded_rect(bounds, radius, &Paint::Fill { color: ACCENT });
+                let top_left = at(0.25, 0.44);
+                let bottom_right = at(0.75, 0.56);
+                canvas.draw_rect(
+                    Rect {
+                        left: top_left.x,
+                        top: top_left.y,
+                        right: bottom_right.x,
+                        bottom: bottom_right.y,
+                    },
+                    &Paint::Fill {
+                        color: Color::WHITE,
+                    },
+                );
+            }
+        }
+
+        self.toggle.draw(canvas, radius, self.color);
+    }
+
+    fn accessibility(&self) -> Option<AccessNode> {
+        let toggled = match self.state {
+            CheckState::Unchecked => Toggled::False,
+            CheckState::Checked => Toggled::True,
+            CheckState::Indeterminate => Toggled::Mixed,
+        };
+        Some(
+            self.toggle
+                .access_node(Role::CheckBox)
+                .with_toggled(toggled),
+        )
+    }
+
+    fn accept_action(&mut self, action: &Action) -> PostUpdate {
+        if *action == Action::Click {
+            self.toggle_state();
+        }
+        PostUpdate::NeedsRedraw
+    }
+}
+
+/// The choice made with a group of [`RadioButton`]s. Clones refer to the same
+/// group, so the group can be kept to read the choice after the buttons have
+/// been added to the widget tree.
+#[derive(Clone, Debug, Default)]
+pub struct RadioGroup {
+    selected: Rc<Cell<Option<usize>>>,
+}
+
+impl RadioGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of the selected button, if any.
+    #[must_use]
+    pub fn selected(&self) -> Option<usize> {
+        self.selected.get()
+    }
+
+    /// Selects the button with `value`, or none of them. The buttons are
+    /// redrawn the next time the window is.
+    pub fn set_selected(&self, value: Option<usize>) {
+        self.selected.set(value);
+    }
+}
+
+/// One of a group of buttons of which only one can be selected, followed by a
+/// label.
+///
+/// A button is selected by clicking it, or by pressing Space when it has the
+/// keyboard focus. Selecting a button deselects the others in its group.
+#[must_use]
+pub struct RadioButton {
+    toggle: Toggle,
+    group: RadioGroup,
+    value: usize,
+    pub color: Color,
+    on_select: Option<Box<dyn FnMut(usize)>>,
+}
+
+impl RadioButton {
+    /// Creates a button in `group` that selects `value` when chosen.
+    pub fn new(group: &RadioGroup, value: usize, label: &str, style: TextStyle) -> Self {
+        Self {
+            toggle: Toggle::new(label, style, 1.0),
+            group: group.clone(),
+            value,
+            color: Color::BLACK,
+            on_select: None,
+        }
+    }
+
+    /// Calls `callback` with the button's value whenever the user selects it.
+    pub fn with_on_select(mut self, callback: impl FnMut(usize) + 'static) -> Self {
+        self.on_select = Some(Box::new(callback));
+        self
+    }
+
+    #[must_use]
+    pub fn is_selected(&self) -> bool {
+        self.group.selected() == Some(self.value)
+    }
+
+    /// The identifier used to give the button the keyboard focus.
+    #[must_use]
+    pub fn focus_id(&self) -> FocusId {
+        self.toggle.focus
+    }
+
+    fn select(&mut self) {
+        self.group.set_selected(Some(self.value));
+        if let Some(on_select) = &mut self.on_select {
+            on_select(self.value);
+        }
+    }
+}
+
+impl Widget for RadioButton {
+    fn widget_state(&self) -> &WidgetState {
+        &self.toggle.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.toggle.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        if !self.toggle.is_activated(context) || self.is_selected() {
+            return PostUpdate::NoChange;
+        }
+
+        self.select();
+
+        // The button that was selected before could be anywhere.
+        context.redraw_window();
+        PostUpdate::NeedsRedraw
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        self.toggle.layout(context, constraints)
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, _size: IntrinsicSize, _height: Px) -> Px {
+        self.toggle.intrinsic_extent(context).width
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, _size: IntrinsicSize, _width: Px) -> Px {
+        self.toggle.intrinsic_extent(context).height
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        let bounds = self.toggle.indicator();
+        let radius = bounds.width() / 2;
+        let center = Point::new(bounds.left + radius, bounds.top + radius);
+
+        if self.is_selected() {
+            canvas.draw_circle(center, radius, &Paint::Fill { color: ACCENT });
+            canvas.draw_circle(
+                center,
+                radius * 0.4,
+                &Paint::Fill {
+                    color: Color::WHITE,
+                },
+            );
+        } else {
+            canvas.draw_circle(center, radius, &Paint::Fill { color: BACKGROUND });
+            canvas.stroke_circle(center, radius, &Stroke::solid(BORDER, 1.0));
+        }
+
+        self.toggle.draw(canvas, radius, self.color);
+    }
+
+    fn accessibility(&self) -> Option<AccessNode> {
+        let toggled = if self.is_selected() {
+            Toggled::True
+        } else {
+            Toggled::False
+        };
+        Some(
+            self.toggle
+                .access_node(Role::RadioButton)
+                .with_toggled(toggled),
+        )
+    }
+
+    fn accept_action(&mut self, action: &Action) -> PostUpdate {
+        if *action == Action::Click && !self.is_selected() {
+            self.select();
+        }
+        PostUpdate::NeedsRedraw
+    }
+}
+
+/// A switch that can be turned on and off, followed by a label.
+///
+/// The switch is flipped by clicking it, or by pressing Space when it has the
+/// keyboard focus.
+#[must_use]
+pub struct Switch {
+    toggle: Toggle,
+    is_on: bool,
+    pub color: Color,
+    on_change: Option<Box<dyn FnMut(bool)>>,
+}
+
+impl Switch {
+    pub fn new(label: &str, style: TextStyle) -> Self {
+        Self {
+            toggle: Toggle::new(label, style, 1.8),
+            is_on: false,
+            color: Color::BLACK,
+            on_change: None,
+        }
+    }
+
+    pub fn with_on(mut self, is_on: bool) -> Self {
+        self.is_on = is_on;
+        self
+    }
+
+    /// Calls `callback` with the new state whenever the user flips the switch.
+    pub fn with_on_change(mut self, callback: impl FnMut(bool) + 'static) -> Self {
+        self.on_change = Some(Box::new(callback));
+        self
+    }
+
+    #[must_use]
+    pub fn is_on(&self) -> bool {
+        self.is_on
+    }
+
+    /// Flips the switch without calling the change callback.
+    pub fn set_on(&mut self, is_on: bool) {
+        self.is_on = is_on;
+        self.toggle.widget_state.set_needs_layout();
+    }
+
+    /// The identifier used to give the switch the keyboard focus.
+    #[must_use]
+    pub fn focus_id(&self) -> FocusId {
+        self.toggle.focus
+    }
+
+    fn flip(&mut self) {
+        self.is_on = !self.is_on;
+        if let Some(on_change) = &mut self.on_change {
+            on_change(self.is_on);
+        }
+    }
+}
+
+impl Widget for Switch {
+    fn widget_state(&self) -> &WidgetState {
+        &self.toggle.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.toggle.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, _: &mut dyn FnMut(&'a dyn Widget)) {}
+
+    fn for_each_child_mut<'a>(&'a mut self, _: &mut dyn FnMut(&'a mut dyn Widget)) {}
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        if !self.toggle.is_activated(context) {
+            return PostUpdate::NoChange;
+        }
+
+        self.flip();
+        PostUpdate::NeedsRedraw
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        self.toggle.layout(context, constraints)
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, _size: IntrinsicSize, _height: Px) -> Px {
+        self.toggle.intrinsic_extent(context).width
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, _size: IntrinsicSize, _width: Px) -> Px {
+        self.toggle.intrinsic_extent(context).height
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        let track = self.toggle.indicator();
+        let radius = track.height() / 2;
+        let color = if self.is_on { ACCENT } else { SWITCH_OFF };
+        canvas.draw_rounded_rect(track, radius, &Paint::Fill { color });
+
+        // The knob sits inside the track, at the end that matches the state.
+        let knob_radius = radius.saturating_sub(Px(2));
+        let x = if self.is_on {
+            track.right - radius
+        } else {
+            track.left + radius
+        };
+        canvas.draw_circle(
+            Point::new(x, track.top + radius),
+            knob_radius,
+            &Paint::Fill {
+                color: Color::WHITE,
+            },
+        );
+
+        self.toggle.draw(canvas, radius, self.color);
+    }
+
+    fn accessibility(&self) -> Option<AccessNode> {
+        let toggled = if self.is_on {
+            Toggled::True
+        } else {
+            Toggled::False
+        };
+        Some(self.toggle.access_node(Role::Switch).with_toggled(toggled))
+    }
+
+    fn accept_action(&mut self, action: &Action) -> PostUpdate {
+        if *action == Action::Click {
+            self.flip();
+        }
+        PostUpdate::NeedsRedraw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_check_state() {
+        assert_eq!(CheckState::Unchecked.toggled(), CheckState::Checked);
+        assert_eq!(CheckState::Checked.toggled(), CheckState::Unchecked);
+        assert_eq!(CheckState::Indeterminate.toggled(), CheckState::Checked);
+    }
+
+    #[test]
+    fn radio_group() {
+        let group = RadioGroup::new();
+        let other = group.clone();
+        assert_eq!(group.selected(), None);
+
+        other.set_selected(Some(2));
+        assert_eq!(group.selected(), Some(2));
+    }
+}
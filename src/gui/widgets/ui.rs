@@ -0,0 +1,244 @@
+//! The [`ui!`](crate::ui) macro, for declaring widget trees without spelling
+//! out every `Box::new(...)` and `Vec` of children.
+
+/// Builds a widget tree, returning it as a `Box<dyn Widget>`.
+///
+/// Each node is the name of a widget followed by its arguments. Containers
+/// take their children in square brackets, separated by commas, and widgets
+/// that wrap a single child take it as their last argument. Every child is
+/// boxed so that they can be of different types.
+///
+/// | Node                                   | Widget                         |
+/// |----------------------------------------|--------------------------------|
+/// | `column [..]`                          | [`Column`](super::layout::Column) |
+/// | `center [child]`                       | [`Center`](super::layout::Center) |
+/// | `split_x [..]`, `split_y [..]`         | [`SplitPanel`](super::split_panel::SplitPanel) laid out horizontally or vertically |
+/// | `tabs [..]`                            | [`TabbedPanel`](super::tabbed_panel::TabbedPanel) |
+/// | `scroll [child]`                       | [`ScrollView`](super::scroll_view::ScrollView) |
+/// | `padding(px, child)`                   | [`Padding`](super::layout::Padding) with the same inset on every side |
+/// | `sized(extent, child)`                 | [`SizedBox`](super::layout::SizedBox) |
+/// | `expander(title, style, child)`        | [`Expander`](super::expander::Expander) |
+/// | `clickable(\|\| .., child)`            | [`Interactive`](super::interactive::Interactive), called when clicked |
+/// | `fill(color)`                          | [`Fill`](super::debug::Fill)   |
+/// | `label(text, style)`                   | [`Label`](super::label::Label) |
+/// | `text_input(style, \|text\| ..)`       | [`TextInput`](super::text_input::TextInput) |
+/// | `text_area(style, \|text\| ..)`        | [`TextArea`](super::text_area::TextArea) |
+/// | `checkbox(label, style, \|state\| ..)` | [`Checkbox`](super::toggle::Checkbox) |
+/// | `switch(label, style, \|on\| ..)`      | [`Switch`](super::toggle::Switch) |
+/// | `dropdown(style, options, \|i\| ..)`   | [`Dropdown`](super::dropdown::Dropdown) |
+/// | `table(style, columns, rows)`          | [`Table`](super::table::Table) |
+/// | `color_picker(style, \|color\| ..)`    | [`ColorPicker`](super::color_picker::ColorPicker) |
+/// | `code_view(text, style)`               | [`CodeView`](super::code_view::CodeView) |
+/// | `log_view(log, style)`                 | [`LogView`](super::log_view::LogView) |
+/// | `plot(style)`                          | [`Plot`](super::plot::Plot)    |
+/// | `widget(expr)`                         | Any widget, built by `expr`    |
+///
+/// The callbacks of the input widgets are optional, and are passed to their
+/// `with_on_change` methods. Widgets that need more configuration than the
+/// node takes, such as a text input with initial text, are built with
+/// `widget(expr)`. There is no button widget; a label in a `clickable` node
+/// serves as one.
+///
+/// ```ignore
+/// let tree = ui! {
+///     split_x [
+///         column [
+///             label("Name", style),
+///             text_input(style, |name| println!("{name}")),
+///             clickable(|| println!("OK"), label("OK", style)),
+///         ],
+///         tabs [ fill(Color::RED), scroll [ widget(image) ] ],
+///     ]
+/// };
+/// ```
+#[macro_export]
+macro_rules! ui {
+    ($kind:ident $body:tt) => {
+        $crate::ui!(@node $kind $body)
+    };
+
+    (@node column [$($kind:ident $body:tt),* $(,)?]) => {
+        $crate::ui!(@boxed $crate::gui::widgets::layout::Column::with_children(
+            $crate::ui!(@children $($kind $body),*)
+        ))
+    };
+    (@node center [$kind:ident $body:tt $(,)?]) => {
+        $crate::ui!(@boxed $crate::gui::widgets::layout::Center::new(
+            $crate::ui!(@node $kind $body)
+        ))
+    };
+    (@node split_x [$($kind:ident $body:tt),* $(,)?]) => {
+        $crate::ui!(@boxed $crate::gui::widgets::split_panel::SplitPanel::with_children(
+            $crate::gui::widgets::split_panel::Axis::X,
+            $crate::ui!(@children $($kind $body),*),
+        ))
+    };
+    (@node split_y [$($kind:ident $body:tt),* $(,)?]) => {
+        $crate::ui!(@boxed $crate::gui::widgets::split_panel::SplitPanel::with_children(
+            $crate::gui::widgets::split_panel::Axis::Y,
+            $crate::ui!(@children $($kind $body),*),
+        ))
+    };
+    (@node tabs [$($kind:ident $body:tt),* $(,)?]) => {
+        $crate::ui!(@boxed $crate::gui::widgets::tabbed_panel::TabbedPanel::with_children(
+            $crate::ui!(@children $($kind $body),*)
+        ))
+    };
+    (@node scroll [$kind:ident $body:tt $(,)?]) => {
+        $crate::ui!(@boxed $crate::gui::widgets::scroll_view::ScrollView::new(
+            $crate::ui!(@node $kind $body)
+        ))
+    };
+    (@node padding($inset:expr, $kind:ident $body:tt $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::layout::Padding::uniform(
+            $inset,
+            $crate::ui!(@node $kind $body),
+        ))
+    };
+    (@node sized($extent:expr, $kind:ident $body:tt $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::layout::SizedBox::new(
+            $extent,
+            $crate::ui!(@node $kind $body),
+        ))
+    };
+    (@node expander($title:expr, $style:expr, $kind:ident $body:tt $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::expander::Expander::new(
+            $title,
+            $style,
+            $crate::ui!(@node $kind $body),
+        ))
+    };
+    (@node clickable($on_click:expr, $kind:ident $body:tt $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::interactive::Interactive::new(
+            $crate::ui!(@node $kind $body)
+        )
+        .with_on_click({
+            #[allow(unused_mut)]
+            let mut on_click = $on_click;
+            move |_| on_click()
+        }))
+    };
+    (@node fill($color:expr $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::debug::Fill::new($color))
+    };
+    (@node label($text:expr, $style:expr $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::label::Label::new($text, $style))
+    };
+    (@node text_input($style:expr $(, $on_change:expr)? $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::text_input::TextInput::new($style)
+            $(.with_on_change($on_change))?)
+    };
+    (@node text_area($style:expr $(, $on_change:expr)? $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::text_area::TextArea::new($style)
+            $(.with_on_change($on_change))?)
+    };
+    (@node checkbox($label:expr, $style:expr $(, $on_change:expr)? $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::toggle::Checkbox::new($label, $style)
+            $(.with_on_change($on_change))?)
+    };
+    (@node switch($label:expr, $style:expr $(, $on_change:expr)? $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::toggle::Switch::new($label, $style)
+            $(.with_on_change($on_change))?)
+    };
+    (@node dropdown($style:expr, $options:expr $(, $on_change:expr)? $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::dropdown::Dropdown::new($style, $options)
+            $(.with_on_change($on_change))?)
+    };
+    (@node table($style:expr, $columns:expr $(, $rows:expr)? $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::table::Table::new($style, $columns)
+            $(.with_rows($rows))?)
+    };
+    (@node color_picker($style:expr $(, $on_change:expr)? $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::color_picker::ColorPicker::new($style)
+            $(.with_on_change($on_change))?)
+    };
+    (@node code_view($text:expr, $style:expr $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::code_view::CodeView::new($text, $style))
+    };
+    (@node log_view($log:expr, $style:expr $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::log_view::LogView::new($log, $style))
+    };
+    (@node plot($style:expr $(,)?)) => {
+        $crate::ui!(@boxed $crate::gui::widgets::plot::Plot::new($style))
+    };
+    (@node widget($widget:expr $(,)?)) => {
+        $crate::ui!(@boxed $widget)
+    };
+
+    (@children $($kind:ident $body:tt),*) => {
+        ::std::vec![$($crate::ui!(@node $kind $body)),*]
+    };
+    (@boxed $widget:expr) => {
+        ::std::boxed::Box::new($widget) as ::std::boxed::Box<dyn $crate::gui::widgets::Widget>
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::{
+            color::Color,
+            geometry::{Extent, Px},
+        },
+        gui::{logging::Log, widgets::table::TableColumn},
+        text::{test_font, FontCollection, TextStyle},
+    };
+
+    use super::super::Widget;
+
+    fn count(widget: &mut dyn Widget) -> usize {
+        let mut total = 1;
+        widget.for_each_child_mut(&mut |child| total += count(child));
+        total
+    }
+
+    #[test]
+    fn nested_tree() {
+        let mut tree = ui! {
+            split_x [
+                column [ fill(Color::RED), fill(Color::BLUE) ],
+                split_y [
+                    tabs [ fill(Color::WHITE), fill(Color::BLACK) ],
+                    center [ fill(Color::RED) ],
+                ],
+                widget(crate::gui::widgets::debug::Fill::new(Color::WHITE)),
+            ]
+        };
+
+        assert_eq!(count(tree.as_mut()), 11);
+    }
+
+    #[test]
+    fn built_in_widgets() {
+        let font = FontCollection::new()
+            .load_bytes(test_font::build("Test", &['a']))
+            .unwrap()[0];
+        let style = TextStyle { font, size: 16.0 };
+        let extent = Extent {
+            width: Px(100),
+            height: Px(100),
+        };
+
+        let mut tree = ui! {
+            column [
+                label("a", style),
+                clickable(|| (), label("OK", style)),
+                text_input(style, |_| ()),
+                text_area(style),
+                checkbox("a", style, |_| ()),
+                switch("a", style),
+                dropdown(style, vec!["a".to_string()], |_| ()),
+                table(style, vec![TableColumn::new("a", Px(40))], vec![vec!["a".to_string()]]),
+                color_picker(style),
+                code_view("a", style),
+                log_view(Log::new(), style),
+                plot(style),
+                expander("a", style, scroll [ padding(Px(4), sized(extent, fill(Color::RED))) ]),
+            ]
+        };
+
+        let mut children = 0;
+        tree.for_each_child_mut(&mut |_| children += 1);
+        assert_eq!(children, 13);
+    }
+}
@@ -0,0 +1,198 @@
+//! Windowing for lists of equally tall rows, so that widgets showing many rows
+//! only lay out and draw the ones that can be seen.
+//!
+//! Scroll positions are kept as a row index and an offset into that row
+//! rather than a distance in pixels, so that lists can be taller than a [`Px`]
+//! can measure.
+
+use std::ops::Range;
+
+use crate::gfx::geometry::Px;
+
+/// How far a list is scrolled: the first row that can be seen, and how many
+/// pixels of it are scrolled out of view. The offset is always less than the
+/// height of a row.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct ScrollPosition {
+    pub row: usize,
+    pub offset: Px,
+}
+
+impl ScrollPosition {
+    pub const TOP: Self = Self {
+        row: 0,
+        offset: Px(0),
+    };
+
+    /// The position `pixels` pixels below the top of the first row.
+    fn from_pixels(pixels: i64, row_height: Px) -> Self {
+        if row_height <= 0 || pixels <= 0 {
+            return Self::TOP;
+        }
+
+        let row_height = i64::from(row_height.0);
+        Self {
+            row: usize::try_from(pixels / row_height).unwrap_or(usize::MAX),
+            offset: Px((pixels % row_height) as i16),
+        }
+    }
+}
+
+/// The rows that are at least partly visible in a view `viewport` pixels tall
+/// that has been scrolled to `scroll`.
+pub(super) fn visible_rows(
+    scroll: ScrollPosition,
+    viewport: Px,
+    row_height: Px,
+    count: usize,
+) -> Range<usize> {
+    if row_height <= 0 || viewport <= 0 {
+        return 0..0;
+    }
+
+    let row_height = i32::from(row_height.0);
+    let bottom = i32::from(scroll.offset.0) + i32::from(viewport.0);
+
+    let first = scroll.row;
+    let last = first.saturating_add(((bottom + row_height - 1) / row_height) as usize);
+    first.min(count)..last.min(count)
+}
+
+/// The distance from the top of the view to the top of `row`, which must be
+/// one of the rows that [`visible_rows`] returned.
+pub(super) fn row_top(scroll: ScrollPosition, row_height: Px, row: usize) -> Px {
+    let rows = row.saturating_sub(scroll.row) as i64;
+    let top = rows * i64::from(row_height.0) - i64::from(scroll.offset.0);
+    Px(top.clamp(i64::from(i16::MIN), i64::from(i16::MAX)) as i16)
+}
+
+/// How far a list of `count` rows can be scrolled without leaving space after
+/// the last row.
+pub(super) fn max_scroll(viewport: Px, row_height: Px, count: usize) -> ScrollPosition {
+    let height = i64::from(row_height.0).saturating_mul(count as i64);
+    ScrollPosition::from_pixels(height - i64::from(viewport.0), row_height)
+}
+
+/// Scrolls by `delta` pixels from `scroll`, staying between the top of the
+/// list and `max`.
+pub(super) fn scroll_by(
+    scroll: ScrollPosition,
+    delta: Px,
+    row_height: Px,
+    max: ScrollPosition,
+) -> ScrollPosition {
+    if row_height <= 0 {
+        return ScrollPosition::TOP;
+    }
+
+    let height = i32::from(row_height.0);
+    let total = i32::from(scroll.offset.0) + i32::from(delta.0);
+    let rows = total.div_euclid(height);
+    let offset = Px(total.rem_euclid(height) as i16);
+
+    let position = if rows < 0 {
+        match scroll.row.checked_sub(rows.unsigned_abs() as usize) {
+            Some(row) => ScrollPosition { row, offset },
+            None => ScrollPosition::TOP,
+        }
+    } else {
+        ScrollPosition {
+            row: scroll.row.saturating_add(rows as usize),
+            offset,
+        }
+    };
+    position.min(max)
+}
+
+/// Adjusts `scroll` so that `row` is entirely visible, moving as little as
+/// possible.
+pub(super) fn scroll_to_row(
+    scroll: ScrollPosition,
+    viewport: Px,
+    row_height: Px,
+    row: usize,
+) -> ScrollPosition {
+    let top = ScrollPosition { row, offset: Px(0) };
+    if top < scroll {
+        return top;
+    }
+
+    // The distance from the top of the view to the bottom of the row.
+    let rows = (row - scroll.row).saturating_add(1) as i64;
+    let bottom = i64::from(row_height.0).saturating_mul(rows) - i64::from(scroll.offset.0);
+    if bottom > i64::from(viewport.0) {
+        max_scroll(viewport, row_height, row.saturating_add(1))
+    } else {
+        scroll
+    }
+}
+
+/// Finds the row at `y` pixels below the top of the view, if there is one.
+pub(super) fn row_at(scroll: ScrollPosition, y: Px, row_height: Px, count: usize) -> Option<usize> {
+    if row_height <= 0 || y < 0 {
+        return None;
+    }
+
+    let rows = (i32::from(scroll.offset.0) + i32::from(y.0)) / i32::from(row_height.0);
+    let row = scroll.row.saturating_add(rows as usize);
+    (row < count).then_some(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(row: usize, offset: i16) -> ScrollPosition {
+        ScrollPosition {
+            row,
+            offset: Px(offset),
+        }
+    }
+
+    #[test]
+    fn find_visible_rows() {
+        assert_eq!(visible_rows(at(0, 0), Px(100), Px(20), 1000), 0..5);
+        assert_eq!(visible_rows(at(0, 10), Px(100), Px(20), 1000), 0..6);
+        assert_eq!(visible_rows(at(49, 10), Px(100), Px(20), 50), 49..50);
+        assert_eq!(visible_rows(at(0, 0), Px(100), Px(20), 0), 0..0);
+
+        assert_eq!(max_scroll(Px(100), Px(20), 10), at(5, 0));
+        assert_eq!(max_scroll(Px(90), Px(20), 10), at(5, 10));
+        assert_eq!(max_scroll(Px(100), Px(20), 2), at(0, 0));
+
+        assert_eq!(row_at(at(0, 10), Px(15), Px(20), 10), Some(1));
+        assert_eq!(row_at(at(0, 0), Px(300), Px(20), 10), None);
+
+        assert_eq!(row_top(at(3, 5), Px(20), 4), Px(15));
+    }
+
+    #[test]
+    fn scroll_by_pixels() {
+        let max = at(50, 0);
+        assert_eq!(scroll_by(at(0, 0), Px(25), Px(20), max), at(1, 5));
+        assert_eq!(scroll_by(at(1, 5), Px(-10), Px(20), max), at(0, 15));
+        assert_eq!(scroll_by(at(1, 5), Px(-100), Px(20), max), at(0, 0));
+        assert_eq!(scroll_by(at(49, 0), Px(100), Px(20), max), max);
+    }
+
+    #[test]
+    fn scroll_rows_into_view() {
+        assert_eq!(scroll_to_row(at(0, 0), Px(100), Px(20), 2), at(0, 0));
+        assert_eq!(scroll_to_row(at(0, 0), Px(100), Px(20), 5), at(1, 0));
+        assert_eq!(scroll_to_row(at(5, 0), Px(100), Px(20), 1), at(1, 0));
+        assert_eq!(scroll_to_row(at(1, 10), Px(100), Px(20), 1), at(1, 0));
+    }
+
+    #[test]
+    fn scroll_past_pixel_range() {
+        // The rows below are far more than `i16::MAX` pixels down the list.
+        let count = 200_000;
+        let row = 100_000;
+        let scroll = scroll_to_row(at(0, 0), Px(100), Px(20), row);
+        assert_eq!(scroll, at(row - 4, 0));
+        assert!(visible_rows(scroll, Px(100), Px(20), count).contains(&row));
+        assert_eq!(row_at(scroll, Px(90), Px(20), count), Some(row));
+
+        assert_eq!(max_scroll(Px(100), Px(20), count), at(count - 5, 0));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::gfx::geometry::{Extent, Offset, Px};
+
+use super::{
+    BoxConstraint, DrawContext, IntrinsicSize, LayoutContext, PostUpdate, UpdateContext, Widget,
+    WidgetState,
+};
+
+/// Draws its child above or below its siblings, regardless of where it is
+/// among them, by giving it a z-index. See [`Widget::z_index`].
+///
+/// The child is laid out as if it weren't wrapped.
+#[must_use]
+pub struct ZIndex<W: Widget> {
+    widget_state: WidgetState,
+    z_index: i32,
+    pub child: W,
+}
+
+impl<W: Widget> ZIndex<W> {
+    pub fn new(z_index: i32, child: W) -> Self {
+        Self {
+            widget_state: WidgetState::default(),
+            z_index,
+            child,
+        }
+    }
+
+    /// Changes the z-index. The parent is not redrawn, so the caller must
+    /// request a redraw of the area that the child overlaps.
+    pub fn set_z_index(&mut self, z_index: i32) {
+        self.z_index = z_index;
+    }
+}
+
+impl<W: Widget> Widget for ZIndex<W> {
+    fn widget_state(&self) -> &WidgetState {
+        &self.widget_state
+    }
+
+    fn widget_state_mut(&mut self) -> &mut WidgetState {
+        &mut self.widget_state
+    }
+
+    fn for_each_child<'a>(&'a self, f: &mut dyn FnMut(&'a dyn Widget)) {
+        f(&self.child);
+    }
+
+    fn for_each_child_mut<'a>(&'a mut self, f: &mut dyn FnMut(&'a mut dyn Widget)) {
+        f(&mut self.child);
+    }
+
+    fn accept_update(&mut self, context: &mut UpdateContext) -> PostUpdate {
+        context.update(&mut self.child);
+        PostUpdate::NoChange
+    }
+
+    fn accept_layout(&mut self, context: &mut LayoutContext, constraints: BoxConstraint) -> Extent {
+        let extent = context.layout(&mut self.child, constraints);
+        context.position_widget(&mut self.child, Offset::zero(), extent);
+        extent
+    }
+
+    fn intrinsic_width(&self, context: &LayoutContext, size: IntrinsicSize, height: Px) -> Px {
+        self.child.intrinsic_width(context, size, height)
+    }
+
+    fn intrinsic_height(&self, context: &LayoutContext, size: IntrinsicSize, width: Px) -> Px {
+        self.child.intrinsic_height(context, size, width)
+    }
+
+    fn accept_draw(&self, canvas: &mut DrawContext, _extent: Extent) {
+        canvas.draw(&self.child);
+    }
+
+    fn z_index(&self) -> i32 {
+        self.z_index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gfx::{
+            color::Color,
+            geometry::{Point, Px},
+        },
+        gui::widgets::{debug::Fill, hit_test::hit_test, layout::Column, paint_order},
+    };
+
+    use super::*;
+
+    #[test]
+    fn raised_above_siblings() {
+        let mut root: Column<Box<dyn Widget>> = Column::with_children(vec![
+            Box::new(ZIndex::new(1, Fill::new(Color::RED))),
+            Box::new(Fill::new(Color::BLUE)),
+            Box::new(ZIndex::new(-1, Fill::new(Color::WHITE))),
+        ]);
+
+        let order: Vec<i32> = paint_order(&root).iter().map(|w| w.z_index()).collect();
+        assert_eq!(order, [-1, 0, 1]);
+
+        // Stack all of the widgets on top of each other.
+        let extent = Extent {
+            width: Px(10),
+            height: Px(10),
+        };
+        root.widget_state_mut().set_layout(Offset::zero(), extent);
+        root.for_each_child_mut(&mut |child| {
+            child.widget_state_mut().set_layout(Offset::zero(), extent);
+            child.for_each_child_mut(&mut |grandchild| {
+                grandchild
+                    .widget_state_mut()
+                    .set_layout(Offset::zero(), extent);
+            });
+        });
+
+        let hits = hit_test(&root, Point::new(5, 5));
+        let order: Vec<i32> = hits.iter().map(|w| w.z_index()).collect();
+        // Each wrapped child is found just before its wrapper.
+        assert_eq!(order, [0, 1, 0, 0, -1, 0]);
+    }
+}
@@ -1,4 +1,9 @@
-use std::{hash::Hash, marker::PhantomData, mem::MaybeUninit};
+use std::{
+    hash::Hash,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    sync::{Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
 
 /// A handle to an element in a `HandlePool`. Note that handles act like weak
 /// references, so elements may be deleted while handles to it still exist. If
@@ -115,7 +120,8 @@ struct Slot<Value, KeyType> {
     /// itself, and marks itself as free (and the index of the next entry in the
     /// free list) when it points away from itself. The slot at the end of the
     /// free list will still point away from itself, so you need to refer to
-    /// `HandlePool::num_free_slots` to determine the end of the list.
+    /// `HandlePool::num_free_slots` to determine the end of the list. Retired
+    /// slots also point away from themselves, but are not part of the list.
     index_and_cycles: Handle<KeyType>,
 
     /// Storage for a value.
@@ -351,28 +357,8 @@ impl<Value, KeyType, const MIN_ELEMENTS: u32> HandlePool<Value, KeyType, MIN_ELE
             .get_mut(usize::from(index))
             .ok_or(Error::InvalidHandle)?;
         if slot.index_and_cycles == handle {
-            let mut value = MaybeUninit::uninit();
-            std::mem::swap(&mut value, &mut slot.value);
-
-            if Self::is_saturated(slot.index_and_cycles) {
-                self.num_retired_slots += 1;
-            } else {
-                Self::increment_cycle(&mut slot.index_and_cycles);
-                Self::set_index(
-                    &mut slot.index_and_cycles,
-                    if self.first_free_slot == index {
-                        index + 1
-                    } else {
-                        self.first_free_slot
-                    },
-                );
-                self.first_free_slot = index;
-                self.num_free_slots += 1;
-            }
-
-            // SAFETY: We have determined that the slot is valid and have
-            // invalidated the handle.
-            Ok(unsafe { value.assume_init() })
+            // SAFETY: We have determined that the slot is valid.
+            Ok(unsafe { self.free_slot(index) })
         } else {
             Err(Error::InvalidHandle)
         }
@@ -396,28 +382,8 @@ impl<Value, KeyType, const MIN_ELEMENTS: u32> HandlePool<Value, KeyType, MIN_ELE
             .ok_or(Error::InvalidHandle)?;
         if slot.index_and_cycles == handle {
             if f(unsafe { slot.value.assume_init_ref() }) {
-                let mut value = MaybeUninit::uninit();
-                std::mem::swap(&mut value, &mut slot.value);
-
-                if Self::is_saturated(slot.index_and_cycles) {
-                    self.num_retired_slots += 1;
-                } else {
-                    Self::increment_cycle(&mut slot.index_and_cycles);
-                    Self::set_index(
-                        &mut slot.index_and_cycles,
-                        if self.first_free_slot == index {
-                            index + 1
-                        } else {
-                            self.first_free_slot
-                        },
-                    );
-                    self.first_free_slot = index;
-                    self.num_free_slots += 1;
-                }
-
-                // SAFETY: We have determined that the slot is valid and have
-                // invalidated the handle.
-                Ok(Some(unsafe { value.assume_init() }))
+                // SAFETY: We have determined that the slot is valid.
+                Ok(Some(unsafe { self.free_slot(index) }))
             } else {
                 Ok(None)
             }
@@ -426,6 +392,117 @@ impl<Value, KeyType, const MIN_ELEMENTS: u32> HandlePool<Value, KeyType, MIN_ELE
         }
     }
 
+    /// Iterates over every element in the pool along with its handle, in no
+    /// particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<KeyType>, &Value)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(i, slot)| usize::from(Self::index_of(slot.index_and_cycles)) == *i)
+            // SAFETY: The slot points to itself, and so holds a value.
+            .map(|(_, slot)| {
+                (slot.index_and_cycles, unsafe {
+                    slot.value.assume_init_ref()
+                })
+            })
+    }
+
+    /// Mutably iterates over every element in the pool along with its handle,
+    /// in no particular order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle<KeyType>, &mut Value)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter(|(i, slot)| usize::from(Self::index_of(slot.index_and_cycles)) == *i)
+            // SAFETY: The slot points to itself, and so holds a value.
+            .map(|(_, slot)| {
+                (slot.index_and_cycles, unsafe {
+                    slot.value.assume_init_mut()
+                })
+            })
+    }
+
+    /// Removes every element for which `f` returns false, invalidating their
+    /// handles. The removed elements are dropped.
+    pub fn retain(&mut self, mut f: impl FnMut(Handle<KeyType>, &mut Value) -> bool) {
+        for i in 0..self.slots.len() {
+            let index = RawIndex(i as u32);
+            if !self.is_occupied(index) {
+                continue;
+            }
+
+            let slot = &mut self.slots[i];
+            // SAFETY: We just checked that the slot holds a value.
+            if !f(slot.index_and_cycles, unsafe {
+                slot.value.assume_init_mut()
+            }) {
+                std::mem::drop(unsafe { self.free_slot(index) });
+            }
+        }
+    }
+
+    /// Removes every element from the pool, invalidating all outstanding
+    /// handles. Slots are recycled (or retired) just as if each element had
+    /// been removed individually.
+    pub fn clear(&mut self) {
+        for _ in self.drain() {}
+    }
+
+    /// Removes every element from the pool and returns them to the caller,
+    /// invalidating all outstanding handles. Use this to dispose of elements
+    /// in bulk, such as when tearing down a graphics device, without keeping
+    /// track of every handle.
+    ///
+    /// All elements are removed from the pool when the iterator is dropped,
+    /// even if it was not fully consumed.
+    pub fn drain(&mut self) -> Drain<'_, Value, KeyType, MIN_ELEMENTS> {
+        Drain {
+            pool: self,
+            next: RawIndex(0),
+        }
+    }
+
+    /// Checks if the slot at `index` holds a value.
+    fn is_occupied(&self, index: RawIndex) -> bool {
+        Self::index_of(self.slots[usize::from(index)].index_and_cycles) == index
+    }
+
+    /// Moves the value out of the slot at `index` and returns the slot to the
+    /// free list, or retires it if it has run out of cycles.
+    ///
+    /// ## Safety
+    ///
+    /// The slot must hold a value.
+    unsafe fn free_slot(&mut self, index: RawIndex) -> Value {
+        let slot = &mut self.slots[usize::from(index)];
+
+        let mut value = MaybeUninit::uninit();
+        std::mem::swap(&mut value, &mut slot.value);
+
+        if Self::is_saturated(slot.index_and_cycles) {
+            // Point away from the slot so that it isn't mistaken for an
+            // occupied slot. Retired slots are never part of the free list.
+            Self::set_index(&mut slot.index_and_cycles, RawIndex(index.0 ^ 1));
+            self.num_retired_slots += 1;
+        } else {
+            Self::increment_cycle(&mut slot.index_and_cycles);
+            Self::set_index(
+                &mut slot.index_and_cycles,
+                if self.first_free_slot == index {
+                    index + 1
+                } else {
+                    self.first_free_slot
+                },
+            );
+            self.first_free_slot = index;
+            self.num_free_slots += 1;
+        }
+
+        // SAFETY: The caller guarantees that the slot held a value, and we
+        // have invalidated the handle.
+        value.assume_init()
+    }
+
     #[inline]
     fn new_handle(index: u32) -> Handle<KeyType> {
         assert!(index < (1 << Self::INDEX_BITS));
@@ -470,6 +547,39 @@ impl<Value, KeyType, const MIN_ELEMENTS: u32> HandlePool<Value, KeyType, MIN_ELE
     }
 }
 
+/// An iterator that removes every element from a [`HandlePool`]. See
+/// [`HandlePool::drain`].
+pub struct Drain<'a, Value, KeyType, const MIN_ELEMENTS: u32> {
+    pool: &'a mut HandlePool<Value, KeyType, MIN_ELEMENTS>,
+    next: RawIndex,
+}
+
+impl<'a, Value, KeyType, const MIN_ELEMENTS: u32> Iterator
+    for Drain<'a, Value, KeyType, MIN_ELEMENTS>
+{
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while usize::from(self.next) < self.pool.slots.len() {
+            let index = self.next;
+            self.next = index + 1;
+
+            if self.pool.is_occupied(index) {
+                // SAFETY: We just checked that the slot holds a value.
+                return Some(unsafe { self.pool.free_slot(index) });
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a, Value, KeyType, const MIN_ELEMENTS: u32> Drop for Drain<'a, Value, KeyType, MIN_ELEMENTS> {
+    fn drop(&mut self) {
+        for _ in self.by_ref() {}
+    }
+}
+
 impl<Value, KeyType, const MIN_ELEMENTS: u32> Default for HandlePool<Value, KeyType, MIN_ELEMENTS> {
     fn default() -> Self {
         Self {
@@ -484,9 +594,9 @@ impl<Value, KeyType, const MIN_ELEMENTS: u32> Default for HandlePool<Value, KeyT
 impl<Value, KeyType, const MIN_ELEMENTS: u32> Drop for HandlePool<Value, KeyType, MIN_ELEMENTS> {
     fn drop(&mut self) {
         for (i, mut slot) in self.slots.drain(..).enumerate() {
-            let (index, generation) = Self::split(slot.index_and_cycles);
+            let (index, _) = Self::split(slot.index_and_cycles);
 
-            if i == index.into() && generation < Self::MAX_CYCLES {
+            if i == index.into() {
                 // SAFETY: As per documentation on `Slot`, we have confirmed
                 // that the slot's index points to itself.
                 unsafe { slot.value.assume_init_drop() };
@@ -495,6 +605,312 @@ impl<Value, KeyType, const MIN_ELEMENTS: u32> Drop for HandlePool<Value, KeyType
     }
 }
 
+/// The contents of a slot in a [`ConcurrentHandlePool`].
+struct SharedSlot<Value, KeyType> {
+    /// The handle that currently refers to the slot. Unlike in [`Slot`], the
+    /// index always points to the slot itself; occupancy is tracked by
+    /// `value`.
+    handle: Handle<KeyType>,
+    value: Option<Value>,
+}
+
+/// Bookkeeping for the unoccupied slots of a [`ConcurrentHandlePool`].
+struct FreeList {
+    /// Slots that have been used before and can be reused.
+    free: Vec<u32>,
+    /// The index of the first slot that has never been used.
+    next_unused: u32,
+    num_retired: u32,
+}
+
+/// A thread-safe variant of [`HandlePool`].
+///
+/// Each slot is guarded by its own lock, so threads only contend with each
+/// other when they access the same element. Looking up an element never takes
+/// a pool-wide lock; only `insert()` and `remove()` briefly lock the list of
+/// free slots.
+///
+/// Handles have the same format as those of a `HandlePool` with the same
+/// `MIN_ELEMENTS`, and slots are retired in the same way. Unlike `HandlePool`,
+/// all slots are allocated up front since the slot array cannot be resized
+/// without locking the whole pool.
+#[must_use]
+pub struct ConcurrentHandlePool<Value, KeyType, const MIN_ELEMENTS: u32> {
+    free_list: Mutex<FreeList>,
+    slots: Box<[RwLock<SharedSlot<Value, KeyType>>]>,
+}
+
+/// A shared reference to an element of a [`ConcurrentHandlePool`]. The element
+/// cannot be removed from the pool while the reference is held.
+pub struct PoolRef<'a, Value, KeyType>(RwLockReadGuard<'a, SharedSlot<Value, KeyType>>);
+
+impl<'a, Value, KeyType> std::ops::Deref for PoolRef<'a, Value, KeyType> {
+    type Target = Value;
+
+    fn deref(&self) -> &Self::Target {
+        // The slot was checked to be occupied when the guard was acquired,
+        // and cannot change while it is held.
+        self.0.value.as_ref().unwrap()
+    }
+}
+
+/// A mutable reference to an element of a [`ConcurrentHandlePool`]. See
+/// [`PoolRef`].
+pub struct PoolRefMut<'a, Value, KeyType>(RwLockWriteGuard<'a, SharedSlot<Value, KeyType>>);
+
+impl<'a, Value, KeyType> std::ops::Deref for PoolRefMut<'a, Value, KeyType> {
+    type Target = Value;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.value.as_ref().unwrap()
+    }
+}
+
+impl<'a, Value, KeyType> std::ops::DerefMut for PoolRefMut<'a, Value, KeyType> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.value.as_mut().unwrap()
+    }
+}
+
+impl<Value, KeyType, const MIN_ELEMENTS: u32> ConcurrentHandlePool<Value, KeyType, MIN_ELEMENTS> {
+    /// The maximum number of slots available to this pool.
+    pub const MAX_ELEMENTS: usize = HandlePool::<Value, KeyType, MIN_ELEMENTS>::MAX_ELEMENTS;
+
+    /// The maximum number of times a slot may be reused before it is
+    /// permanently retired.
+    pub const MAX_CYCLES: u32 = HandlePool::<Value, KeyType, MIN_ELEMENTS>::MAX_CYCLES;
+
+    /// Allocates the memory required to store `MAX_ELEMENTS` slots. Be careful
+    /// when calling with large values of `MIN_ELEMENTS` as it may consume a
+    /// lot of memory.
+    pub fn new() -> Self {
+        Self {
+            free_list: Mutex::new(FreeList {
+                free: Vec::new(),
+                next_unused: 0,
+                num_retired: 0,
+            }),
+            slots: (0..Self::MAX_ELEMENTS)
+                .map(|i| {
+                    RwLock::new(SharedSlot {
+                        handle: HandlePool::<Value, KeyType, MIN_ELEMENTS>::new_handle(i as u32),
+                        value: None,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Retrieves the number of elements in the pool. The count may be out of
+    /// date by the time it is returned if other threads are modifying the
+    /// pool.
+    #[must_use]
+    pub fn count(&self) -> usize {
+        let free_list = self.lock_free_list();
+        free_list.next_unused as usize - free_list.free.len() - free_list.num_retired as usize
+    }
+
+    /// Checks if the handle pool has no elements. See [`Self::count`].
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+
+    /// Checks if the handle is valid.
+    #[must_use]
+    pub fn contains(&self, handle: Handle<KeyType>) -> bool {
+        self.get(handle).is_ok()
+    }
+
+    /// Borrows a reference to the element identified by `handle` if it exists.
+    /// This blocks if another thread holds a mutable reference to the same
+    /// element.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::InvalidHandle`] if the handle is not valid.
+    pub fn get(&self, handle: Handle<KeyType>) -> Result<PoolRef<'_, Value, KeyType>, Error> {
+        let slot = self
+            .slot(handle)?
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        if slot.handle == handle && slot.value.is_some() {
+            Ok(PoolRef(slot))
+        } else {
+            Err(Error::InvalidHandle)
+        }
+    }
+
+    /// Mutably borrows a reference to the element identified by `handle` if it
+    /// exists. This blocks if another thread holds a reference to the same
+    /// element.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::InvalidHandle`] if the handle is not valid.
+    pub fn get_mut(
+        &self,
+        handle: Handle<KeyType>,
+    ) -> Result<PoolRefMut<'_, Value, KeyType>, Error> {
+        let slot = self
+            .slot(handle)?
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        if slot.handle == handle && slot.value.is_some() {
+            Ok(PoolRefMut(slot))
+        } else {
+            Err(Error::InvalidHandle)
+        }
+    }
+
+    /// Inserts an element into the pool, returning a handle to that element.
+    ///
+    /// ## Errors
+    ///
+    /// Inserting a new value may fail if the pool has run out of slots. See
+    /// [`HandlePool::insert`].
+    pub fn insert(&self, value: Value) -> Result<Handle<KeyType>, Error> {
+        let index = {
+            let mut free_list = self.lock_free_list();
+            if let Some(index) = free_list.free.pop() {
+                index
+            } else if (free_list.next_unused as usize) < Self::MAX_ELEMENTS {
+                free_list.next_unused += 1;
+                free_list.next_unused - 1
+            } else if free_list.num_retired as usize == Self::MAX_ELEMENTS {
+                return Err(Error::Exhausted {
+                    capacity: Self::MAX_ELEMENTS,
+                });
+            } else {
+                return Err(Error::TooManyObjects {
+                    num_allocated: free_list.next_unused as usize,
+                    num_retired: free_list.num_retired as usize,
+                    capacity: Self::MAX_ELEMENTS,
+                });
+            }
+        };
+
+        // The slot is not reachable by any valid handle until its value is
+        // set, so no other thread can be waiting on it for long.
+        let mut slot = self.slots[index as usize]
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        debug_assert!(slot.value.is_none());
+        slot.value = Some(value);
+        Ok(slot.handle)
+    }
+
+    /// Removes the element identified by `handle` from the pool if it exists
+    /// and returns it to the caller.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`Error::InvalidHandle`] if the handle is not valid.
+    pub fn remove(&self, handle: Handle<KeyType>) -> Result<Value, Error> {
+        self.remove_if(handle, |_| true)
+            .map(|value| value.expect("predicate always returns true"))
+    }
+
+    /// Removes the element identified by `handle` from the pool if it exists
+    /// and the predicate `f` returns true.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an [`Error::InvalidHandle`] if the handle is not valid.
+    pub fn remove_if(
+        &self,
+        handle: Handle<KeyType>,
+        f: impl Fn(&Value) -> bool,
+    ) -> Result<Option<Value>, Error> {
+        let mut slot = self
+            .slot(handle)?
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        match &slot.value {
+            Some(value) if slot.handle == handle => {
+                if !f(value) {
+                    return Ok(None);
+                }
+            }
+            _ => return Err(Error::InvalidHandle),
+        }
+
+        let value = slot.value.take();
+        let index = HandlePool::<Value, KeyType, MIN_ELEMENTS>::index_of(handle);
+        let saturated = HandlePool::<Value, KeyType, MIN_ELEMENTS>::is_saturated(slot.handle);
+
+        if !saturated {
+            HandlePool::<Value, KeyType, MIN_ELEMENTS>::increment_cycle(&mut slot.handle);
+        }
+
+        // Release the slot before taking the free list lock so that the two
+        // locks are never held at the same time.
+        std::mem::drop(slot);
+
+        let mut free_list = self.lock_free_list();
+        if saturated {
+            free_list.num_retired += 1;
+        } else {
+            free_list.free.push(index.0);
+        }
+
+        Ok(value)
+    }
+
+    /// Removes every element from the pool and returns them to the caller,
+    /// invalidating all outstanding handles. See [`HandlePool::drain`].
+    ///
+    /// Unlike `HandlePool::drain`, elements are only removed as the iterator
+    /// is consumed.
+    pub fn drain(&mut self) -> impl Iterator<Item = Value> + '_ {
+        let free_list = self
+            .free_list
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        self.slots[..free_list.next_unused as usize]
+            .iter_mut()
+            .filter_map(|slot| {
+                let slot = slot.get_mut().unwrap_or_else(PoisonError::into_inner);
+                let value = slot.value.take()?;
+
+                if HandlePool::<Value, KeyType, MIN_ELEMENTS>::is_saturated(slot.handle) {
+                    free_list.num_retired += 1;
+                } else {
+                    HandlePool::<Value, KeyType, MIN_ELEMENTS>::increment_cycle(&mut slot.handle);
+                    free_list
+                        .free
+                        .push(HandlePool::<Value, KeyType, MIN_ELEMENTS>::index_of(slot.handle).0);
+                }
+
+                Some(value)
+            })
+    }
+
+    fn slot(&self, handle: Handle<KeyType>) -> Result<&RwLock<SharedSlot<Value, KeyType>>, Error> {
+        self.slots
+            .get(usize::from(
+                HandlePool::<Value, KeyType, MIN_ELEMENTS>::index_of(handle),
+            ))
+            .ok_or(Error::InvalidHandle)
+    }
+
+    fn lock_free_list(&self) -> MutexGuard<'_, FreeList> {
+        self.free_list
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<Value, KeyType, const MIN_ELEMENTS: u32> Default
+    for ConcurrentHandlePool<Value, KeyType, MIN_ELEMENTS>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -626,4 +1042,116 @@ mod tests {
 
         assert_eq!(drop_counter.get(), COUNT);
     }
+
+    #[test]
+    fn concurrent_handle_pool() {
+        use std::sync::Arc;
+
+        const THREADS: usize = 4;
+        const PER_THREAD: usize = 64;
+
+        let pool = Arc::new(ConcurrentHandlePool::<
+            usize,
+            (),
+            { (THREADS * PER_THREAD) as u32 },
+        >::new());
+
+        let threads: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let pool = pool.clone();
+                std::thread::spawn(move || {
+                    let handles: Vec<_> = (0..PER_THREAD)
+                        .map(|i| pool.insert(t * PER_THREAD + i).unwrap())
+                        .collect();
+
+                    for (i, handle) in handles.iter().enumerate() {
+                        *pool.get_mut(*handle).unwrap() += 1;
+                        assert_eq!(*pool.get(*handle).unwrap(), t * PER_THREAD + i + 1);
+                    }
+
+                    for handle in handles.iter().step_by(2) {
+                        assert!(pool.remove(*handle).is_ok());
+                        assert!(!pool.contains(*handle));
+                    }
+
+                    handles
+                })
+            })
+            .collect();
+
+        let mut remaining = vec![];
+        for thread in threads {
+            remaining.extend(thread.join().unwrap().into_iter().skip(1).step_by(2));
+        }
+
+        assert_eq!(pool.count(), THREADS * PER_THREAD / 2);
+        for handle in &remaining {
+            assert!(pool.contains(*handle));
+        }
+
+        // Freed slots are reused with new handles.
+        let handle = pool.insert(0).unwrap();
+        assert!(!remaining.contains(&handle));
+        assert_eq!(pool.remove_if(handle, |v| *v == 1), Ok(None));
+        assert_eq!(pool.remove_if(handle, |v| *v == 0), Ok(Some(0)));
+        assert_eq!(pool.get(handle).err(), Some(Error::InvalidHandle));
+    }
+
+    #[test]
+    fn handle_pool_drain() {
+        let mut pool = HandlePool::<u32, (), 16>::default();
+
+        let handles: Vec<_> = (0..8).map(|i| pool.insert(i).unwrap()).collect();
+        let _ = pool.remove(handles[3]);
+
+        let mut drained: Vec<_> = pool.drain().collect();
+        drained.sort_unstable();
+        assert_eq!(drained, [0, 1, 2, 4, 5, 6, 7]);
+
+        assert!(pool.is_empty());
+        assert_eq!(pool.num_free_slots, 8);
+        pool_invariants(&pool);
+        for handle in &handles {
+            assert!(!pool.contains(*handle));
+        }
+
+        // Slots are reused after draining, and a partially consumed drain
+        // still removes everything.
+        let a = pool.insert(10).unwrap();
+        let _ = pool.insert(20).unwrap();
+        assert_eq!(pool.drain().take(1).count(), 1);
+        assert!(pool.is_empty());
+        assert!(!pool.contains(a));
+
+        let _ = pool.insert(30).unwrap();
+        pool.clear();
+        assert!(pool.is_empty());
+        pool_invariants(&pool);
+    }
+
+    #[test]
+    fn handle_pool_iter() {
+        let mut pool = HandlePool::<u32, (), 16>::default();
+
+        let handles: Vec<_> = (0..8).map(|i| pool.insert(i).unwrap()).collect();
+        let _ = pool.remove(handles[0]);
+
+        assert_eq!(pool.iter().count(), 7);
+        for (handle, value) in pool.iter() {
+            assert_eq!(handles[*value as usize], handle);
+        }
+
+        for (_, value) in pool.iter_mut() {
+            *value *= 10;
+        }
+
+        pool.retain(|_, value| *value % 20 == 0);
+        pool_invariants(&pool);
+
+        let mut remaining: Vec<_> = pool.iter().map(|(_, v)| *v).collect();
+        remaining.sort_unstable();
+        assert_eq!(remaining, [20, 40, 60]);
+        assert!(!pool.contains(handles[1]));
+        assert!(pool.contains(handles[2]));
+    }
 }
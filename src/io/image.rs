@@ -1,4 +1,4 @@
-use png::{BitDepth, Decoder, DecodingError, Transformations};
+use png::{BitDepth, Decoder, DecodingError, Encoder, EncodingError, Transformations};
 
 use crate::gfx::{
     geometry::{Extent, Px},
@@ -19,6 +19,10 @@ pub enum Error {
     },
     #[error("an unknown error was encountered within the decoder")]
     Unknown(DecodingError),
+    #[error("images with the {0:?} layout cannot be encoded as PNG")]
+    UnsupportedLayout(Layout),
+    #[error("an error was encountered within the encoder")]
+    Encoding(#[from] EncodingError),
 }
 
 /// Decodes a blob containing a PNG-encoded image into a pixel buffer. Animated
@@ -67,3 +71,31 @@ pub fn decode_png(bytes: &[u8]) -> Result<PixelBuffer, Error> {
         image.into_boxed_slice(),
     ))
 }
+
+/// Encodes an uncompressed pixel buffer as a PNG image. The color space is
+/// recorded so that decoding the image with [`decode_png`] round-trips it.
+pub fn encode_png(image: &PixelBuffer) -> Result<Vec<u8>, Error> {
+    let color_type = match image.layout() {
+        Layout::RGB8 => png::ColorType::Rgb,
+        Layout::RGBA8 => png::ColorType::Rgba,
+        layout => return Err(Error::UnsupportedLayout(layout)),
+    };
+
+    let mut bytes = Vec::new();
+    {
+        let extent = image.extent();
+        // The extent of a pixel buffer is never negative.
+        let mut encoder = Encoder::new(&mut bytes, extent.width.0 as u32, extent.height.0 as u32);
+        encoder.set_color(color_type);
+        encoder.set_depth(BitDepth::Eight);
+        if matches!(image.color_space(), ColorSpace::Srgb) {
+            encoder.set_srgb(png::SrgbRenderingIntent::Perceptual);
+        }
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(image.bytes())?;
+        writer.finish()?;
+    }
+
+    Ok(bytes)
+}
@@ -1,6 +1,8 @@
 pub mod application;
+pub mod arena;
 pub mod gfx;
 pub mod gui;
 pub mod handle_pool;
 pub mod io;
 pub mod shell;
+pub mod text;
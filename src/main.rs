@@ -1,41 +1,57 @@
 use fathom::{
-    application::{AppWindowConfig, Application},
-    gfx::color::Color,
-    gui::widgets::{
-        debug::Fill,
-        split_panel::{Axis, SplitPanel},
-        tabbed_panel::TabbedPanel,
-        Widget,
-    },
+    application::{AppWindowConfig, Application, TextAntiAliasing},
+    gfx::{color::Color, geometry::Px},
+    gui::widgets::text_input::TextInput,
+    text::{FontStyle, TextStyle},
+    ui,
 };
 
 fn main() {
-    let tree = SplitPanel::<Box<dyn Widget>>::with_children(
-        Axis::X,
-        vec![
-            Box::new(Fill::new(Color::GREEN)),
-            Box::new(SplitPanel::with_children(
-                Axis::Y,
-                vec![
-                    TabbedPanel::with_children(vec![
-                        Fill::new(Color::RED),
-                        Fill::new(Color::BLUE),
-                        Fill::new(Color::WHITE),
-                    ]),
-                    TabbedPanel::with_children(vec![
-                        Fill::new(Color::RED),
-                        Fill::new(Color::BLUE),
-                        Fill::new(Color::WHITE),
-                    ]),
-                ],
-            )),
-            Box::new(Fill::new(Color::WHITE)),
-        ],
-    );
+    let mut app = Application::new();
 
-    Application::new().run(vec![AppWindowConfig {
+    let family = if cfg!(target_os = "windows") {
+        "Segoe UI"
+    } else {
+        "DejaVu Sans"
+    };
+
+    let font = app
+        .fonts()
+        .borrow_mut()
+        .query(family, FontStyle::REGULAR)
+        .expect("the default font should be installed");
+
+    let input = TextInput::new(TextStyle { font, size: 16.0 })
+        .with_text("Hello, world!")
+        .with_on_submit(|text| println!("submitted: {text}"));
+
+    let tree = ui! {
+        split_x [
+            column [ padding(Px(8), widget(input)) ],
+            split_y [
+                tabs [ fill(Color::RED), fill(Color::BLUE), fill(Color::WHITE) ],
+                tabs [ fill(Color::RED), fill(Color::BLUE), fill(Color::WHITE) ],
+            ],
+            fill(Color::WHITE),
+        ]
+    };
+
+    app.run(vec![AppWindowConfig {
         title: "Window #1",
         extent: None,
-        widget_tree: Box::new(tree),
+        min_extent: None,
+        max_extent: None,
+        resizable: true,
+        always_on_top: false,
+        tool_window: false,
+        transparent: false,
+        icon: None,
+        menu_bar: None,
+        widget_tree: tree,
+        text_anti_aliasing: TextAntiAliasing::System,
+        performance_hud: None,
+        hot_reload: None,
+        remember_placement: None,
+        vsync: true,
     }]);
 }
@@ -0,0 +1,67 @@
+//! Describes the native dialogs that the shell shows for choosing files.
+//!
+//! Dialogs don't block the event loop. Showing one returns a [`DialogId`]
+//! immediately, and [`Window::FileDialogClosed`](super::event::Window::FileDialogClosed)
+//! is sent to the window that owns it once the user has made a choice. The
+//! chosen paths are then read with
+//! [`Shell::take_dialog_result`](super::Shell::take_dialog_result).
+
+/// Identifies a dialog shown by the shell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DialogId(pub(super) u64);
+
+/// A kind of file that a dialog can be limited to, such as images.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileFilter {
+    /// The name shown to the user, such as `"PNG Images"`.
+    pub name: String,
+    /// The extensions of the files to show, without the leading dot.
+    pub extensions: Vec<String>,
+}
+
+/// A dialog for choosing files to open, or where to save a file.
+#[must_use]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileDialog {
+    /// The title of the dialog, or `None` for the platform's default.
+    pub title: Option<String>,
+    /// The kinds of file the user can choose between. The first is selected
+    /// when the dialog opens, and is used to add an extension to saved files
+    /// that don't have one. Every file is shown if this is empty.
+    pub filters: Vec<FileFilter>,
+    /// Whether several files can be chosen at once. Ignored when saving.
+    pub multiple: bool,
+    /// The name that the dialog starts with.
+    pub file_name: Option<String>,
+}
+
+impl FileDialog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /// Adds a kind of file, named `name`, that the user can limit the dialog
+    /// to. See [`Self::filters`].
+    pub fn with_filter(mut self, name: &str, extensions: &[&str]) -> Self {
+        self.filters.push(FileFilter {
+            name: name.to_string(),
+            extensions: extensions.iter().map(ToString::to_string).collect(),
+        });
+        self
+    }
+
+    pub fn with_multiple(mut self, multiple: bool) -> Self {
+        self.multiple = multiple;
+        self
+    }
+
+    pub fn with_file_name(mut self, file_name: &str) -> Self {
+        self.file_name = Some(file_name.to_string());
+        self
+    }
+}
@@ -1,6 +1,10 @@
-use crate::gfx::geometry::{Extent, Point};
+use crate::gfx::geometry::{PhysicalExtent, PhysicalPoint};
 
-use super::WindowId;
+use super::{dialog::DialogId, menu::MenuItemId, TimerId, TrayIconId, WindowId};
+
+/// The distance that the mouse wheel moves for each notch. Wheels without
+/// notches may report smaller distances.
+pub const WHEEL_DELTA: i16 = 120;
 
 /// Events that can be received from the OS event loop.
 ///
@@ -21,6 +25,10 @@ pub enum Event {
     /// been completed. Handle this message to perform any shared post-rendering
     /// operations.
     RepaintComplete,
+    /// Events sent with an [`EventLoopProxy`](super::EventLoopProxy) are
+    /// waiting to be taken with
+    /// [`Shell::take_user_events`](super::Shell::take_user_events).
+    User,
 }
 
 /// Window-specific events that can be received from the OS event loop.
@@ -32,11 +40,33 @@ pub enum Event {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Window {
-    Init { inner_extent: Extent },
+    Init {
+        inner_extent: PhysicalExtent,
+    },
     CloseRequested,
     Destroyed,
-    Resized { inner_extent: Extent },
-    CursorMoved { position: Point },
+    /// The size of the window's client area changed. This is zero while the
+    /// window is minimized.
+    Resized {
+        inner_extent: PhysicalExtent,
+    },
+    /// The window moved to a monitor with a different number of dots per
+    /// inch, or the monitor's scale was changed. Convert it to a scale factor
+    /// with [`ScaleFactor::from_dpi`](crate::gfx::geometry::ScaleFactor::from_dpi).
+    /// The window is resized to keep its contents the same size on the
+    /// monitor, which is reported with [`Window::Resized`] after this event.
+    ScaleFactorChanged {
+        dpi: u32,
+    },
+    /// The user started moving or resizing the window by dragging it. Until
+    /// [`Window::SizeMoveEnded`], the event loop is blocked and events,
+    /// including [`Window::Resized`] and [`Window::Repaint`], are delivered
+    /// as the window changes so that its contents can follow the drag.
+    SizeMoveStarted,
+    SizeMoveEnded,
+    CursorMoved {
+        position: PhysicalPoint,
+    },
     Repaint,
     LeftMouseButtonPressed,
     LeftMouseButtonReleased,
@@ -44,4 +74,158 @@ pub enum Window {
     RightMouseButtonReleased,
     MiddleMouseButtonPressed,
     MiddleMouseButtonReleased,
+    /// The mouse wheel was turned, in multiples of [`WHEEL_DELTA`] per notch.
+    /// Positive `y` is away from the user, and positive `x` is to the right.
+    MouseWheel {
+        x: i16,
+        y: i16,
+    },
+    KeyPressed {
+        key: Key,
+        modifiers: Modifiers,
+    },
+    /// A character was typed, after the keyboard layout, dead keys, and input
+    /// methods have been applied. Control characters are reported as
+    /// [`Window::KeyPressed`] instead.
+    CharacterReceived {
+        character: char,
+    },
+    /// A finger touched, moved on, or was lifted from the screen. `id`
+    /// identifies the finger until it is lifted. Touches are also reported as
+    /// mouse input, for widgets that don't handle them.
+    Touch {
+        id: u32,
+        phase: TouchPhase,
+        position: PhysicalPoint,
+    },
+    /// The mouse moved by (`x`, `y`) in the units of the device, without the
+    /// pointer's acceleration applied. Only reported while raw mouse input is
+    /// enabled with [`Shell::set_raw_mouse_input`](super::Shell::set_raw_mouse_input),
+    /// and in addition to [`Window::CursorMoved`].
+    RawMouseMotion {
+        x: i32,
+        y: i32,
+    },
+    /// An input method started composing text, such as when typing Chinese,
+    /// Japanese, or Korean. Until [`Window::CompositionEnded`], key presses
+    /// are handled by the input method rather than reported as events.
+    CompositionStarted,
+    /// The text being composed changed. Read it with
+    /// [`Shell::composition`](super::Shell::composition).
+    CompositionUpdated,
+    /// The input method stopped composing text. Text that the user committed
+    /// is reported as [`Window::CharacterReceived`] events before this.
+    CompositionEnded,
+    /// A file dialog owned by the window closed. Read the files that the user
+    /// chose with [`Shell::take_dialog_result`](super::Shell::take_dialog_result).
+    FileDialogClosed {
+        dialog: DialogId,
+    },
+    /// A timer started with [`Shell::set_timer`](super::Shell::set_timer)
+    /// fired.
+    Timer {
+        timer: TimerId,
+    },
+    /// Files were dragged from another program and dropped on the window at
+    /// `position`. Read their paths with
+    /// [`Shell::dropped_files`](super::Shell::dropped_files).
+    FilesDropped {
+        position: PhysicalPoint,
+    },
+    /// Assistive technology asked the window to do something, such as to
+    /// click a button or move the focus. Read the requests with
+    /// [`Shell::take_access_requests`](super::Shell::take_access_requests).
+    AccessibilityRequested,
+    /// The user clicked a tray icon that the window owns. See
+    /// [`Shell::add_tray_icon`](super::Shell::add_tray_icon).
+    TrayIconClicked {
+        tray: TrayIconId,
+    },
+    /// The user selected an item in a menu that the window owns.
+    MenuItemSelected {
+        item: MenuItemId,
+    },
+}
+
+/// The stage of a [`Window::Touch`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Ended,
+    /// The touch was interrupted, such as by the system taking it over for
+    /// one of its own gestures. The position of a cancelled touch is
+    /// unreliable.
+    Cancelled,
+}
+
+/// The modifier keys that were held when a key was pressed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+}
+
+/// A key on the keyboard, identified by its position rather than the character
+/// that it produces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Key {
+    Backspace,
+    Tab,
+    Enter,
+    Escape,
+    Space,
+    PageUp,
+    PageDown,
+    End,
+    Home,
+    Left,
+    Up,
+    Right,
+    Down,
+    Insert,
+    Delete,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    /// A key that fathom does not yet recognize.
+    Unknown,
 }
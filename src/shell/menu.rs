@@ -0,0 +1,157 @@
+//! Describes the native menus that the shell shows, such as for tray icons and
+//! menu bars.
+//!
+//! Each item that can be selected is identified by a [`MenuItemId`] chosen by
+//! the program. When the user selects one,
+//! [`Window::MenuItemSelected`](super::event::Window::MenuItemSelected) is sent
+//! to the window that owns the menu.
+//!
+//! An `&` before a letter in a label underlines the letter, and lets the user
+//! select the item by typing it while the menu is open. Use `&&` for an
+//! ampersand.
+
+use std::fmt;
+
+use super::event::{Key, Modifiers};
+
+/// Identifies an item in a menu. Chosen by the program, and reported when the
+/// item is selected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MenuItemId(pub u32);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MenuItem {
+    /// An item that can be selected.
+    Action {
+        id: MenuItemId,
+        label: String,
+        /// Whether the item can be selected. Disabled items are grayed out.
+        enabled: bool,
+        /// Whether the item is shown with a check mark.
+        checked: bool,
+        /// The keys that select the item without opening the menu. Only used
+        /// by menu bars.
+        accelerator: Option<Accelerator>,
+    },
+    /// A line between groups of items.
+    Separator,
+    /// An item that opens another menu beside it.
+    Submenu { label: String, menu: Menu },
+}
+
+/// A combination of keys that selects a menu item, such as `Ctrl+S`. It is
+/// shown beside the item's label.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Accelerator {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl Accelerator {
+    pub fn new(key: Key, modifiers: Modifiers) -> Self {
+        Self { key, modifiers }
+    }
+
+    /// The key while holding down Ctrl.
+    pub fn ctrl(key: Key) -> Self {
+        Self::new(
+            key,
+            Modifiers {
+                control: true,
+                ..Modifiers::default()
+            },
+        )
+    }
+}
+
+impl fmt::Display for Accelerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.control {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+/// A list of items, shown as a popup or as a window's menu bar.
+#[must_use]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Menu {
+    pub items: Vec<MenuItem>,
+}
+
+impl Menu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an item that reports `id` when it is selected.
+    pub fn with_item(mut self, id: MenuItemId, label: &str) -> Self {
+        self.items.push(MenuItem::Action {
+            id,
+            label: label.to_string(),
+            enabled: true,
+            checked: false,
+            accelerator: None,
+        });
+        self
+    }
+
+    /// Adds an item that is shown with a check mark if `checked` is set, such
+    /// as for a setting that can be turned on and off.
+    pub fn with_check_item(mut self, id: MenuItemId, label: &str, checked: bool) -> Self {
+        self.items.push(MenuItem::Action {
+            id,
+            label: label.to_string(),
+            enabled: true,
+            checked,
+            accelerator: None,
+        });
+        self
+    }
+
+    /// Adds an item that is grayed out and can't be selected.
+    pub fn with_disabled_item(mut self, id: MenuItemId, label: &str) -> Self {
+        self.items.push(MenuItem::Action {
+            id,
+            label: label.to_string(),
+            enabled: false,
+            checked: false,
+            accelerator: None,
+        });
+        self
+    }
+
+    /// Lets the most recently added item be selected with `accelerator`
+    /// while its window is active. Ignored if the item isn't one that can be
+    /// selected.
+    pub fn with_accelerator(mut self, accelerator: Accelerator) -> Self {
+        if let Some(MenuItem::Action {
+            accelerator: current,
+            ..
+        }) = self.items.last_mut()
+        {
+            *current = Some(accelerator);
+        }
+        self
+    }
+
+    pub fn with_separator(mut self) -> Self {
+        self.items.push(MenuItem::Separator);
+        self
+    }
+
+    pub fn with_submenu(mut self, label: &str, menu: Menu) -> Self {
+        self.items.push(MenuItem::Submenu {
+            label: label.to_string(),
+            menu,
+        });
+        self
+    }
+}
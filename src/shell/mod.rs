@@ -1,6 +1,18 @@
+pub mod dialog;
 pub mod event;
+pub mod menu;
+pub mod placement;
 
-use crate::gfx::geometry::Extent;
+use std::{any::Any, path::PathBuf, time::Duration};
+
+use crate::gfx::{
+    geometry::{PhysicalExtent, PhysicalPoint, PhysicalRect, ScaleFactor},
+    pixel_buffer::PixelBuffer,
+};
+
+use dialog::{DialogId, FileDialog};
+use menu::Menu;
+use placement::WindowPlacement;
 
 use event::Event;
 
@@ -8,23 +20,189 @@ use event::Event;
 #[path = "win32.rs"]
 mod platform;
 
+#[cfg(target_os = "linux")]
+#[path = "x11.rs"]
+mod platform;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("the operation is not failed because the shell is shutting down")]
     ShuttingDown,
+    #[error("the window system reported an error: {0}")]
+    WindowSystem(String),
 }
 
 pub struct WindowConfig<'a> {
     pub title: &'a str,
-    pub extent: Option<Extent>,
+    pub extent: Option<PhysicalExtent>,
+    /// The smallest size that the user can resize the window to, not
+    /// including its borders and title bar.
+    pub min_extent: Option<PhysicalExtent>,
+    /// The largest size that the user can resize or maximize the window to,
+    /// not including its borders and title bar.
+    pub max_extent: Option<PhysicalExtent>,
+    /// Whether the user can resize or maximize the window.
+    pub resizable: bool,
+    /// Keeps the window above every window that isn't also always on top.
+    pub always_on_top: bool,
+    /// Gives the window a smaller title bar, and leaves it out of the taskbar
+    /// and the task switcher, such as for floating palettes.
+    pub tool_window: bool,
+    /// Shows whatever is behind the window through the parts of it that
+    /// aren't opaque, such as for overlays. Transparent windows don't have
+    /// borders or a title bar.
+    pub transparent: bool,
+    /// The icon shown in the window's title bar and the taskbar, or `None`
+    /// for the platform's default.
+    pub icon: Option<&'a PixelBuffer>,
+    /// The menus shown in a bar below the window's title bar, or `None` for
+    /// no menu bar. Each item in the bar should be a submenu.
+    pub menu_bar: Option<&'a Menu>,
+}
+
+/// Text that an input method is composing, before the user commits it. See
+/// [`event::Window::CompositionStarted`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Composition {
+    pub text: String,
+    /// The position of the caret within `text`, in bytes.
+    pub caret: usize,
+}
+
+/// The image shown for the mouse cursor. See [`Shell::set_cursor`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CursorIcon {
+    #[default]
+    Arrow,
+    /// An I-beam, for text that can be selected or edited.
+    Text,
+    /// A pointing hand, for links.
+    Hand,
+    /// Arrows pointing in every direction, for something that can be moved.
+    Move,
+    /// Arrows pointing left and right, for something that can be resized
+    /// horizontally.
+    ResizeHorizontal,
+    /// Arrows pointing up and down, for something that can be resized
+    /// vertically.
+    ResizeVertical,
+    /// For something that can't be clicked or dropped on.
+    NotAllowed,
+    /// For when the program is busy and ignoring input.
+    Wait,
+}
+
+/// How a window covers the screen. See [`Shell::set_fullscreen`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FullscreenMode {
+    /// An ordinary window with borders and a title bar.
+    #[default]
+    Windowed,
+    /// Covers the monitor that the window is on, without borders. Other
+    /// windows can be shown above it, and switching to them is instant.
+    Borderless,
+    /// Covers the monitor like [`FullscreenMode::Borderless`], but lets the
+    /// graphics driver take exclusive control of the display, which may
+    /// lower latency. Switching to other windows may be slow, and may make
+    /// the screen flicker.
+    Exclusive,
+}
+
+/// The system clipboard. Clones refer to the same clipboard, so they can be
+/// kept by callbacks that offer to copy something. See [`Shell::clipboard`].
+#[derive(Clone)]
+pub struct Clipboard(platform::Clipboard);
+
+impl Clipboard {
+    /// Reads the text on the clipboard. Returns `None` if the clipboard
+    /// doesn't hold any text or couldn't be opened.
+    #[must_use]
+    pub fn text(&self) -> Option<String> {
+        self.0.text()
+    }
+
+    /// Replaces the contents of the clipboard with `text`. Line breaks are
+    /// converted to the platform's convention.
+    pub fn set_text(&self, text: &str) {
+        self.0.set_text(text);
+    }
+}
+
+/// The optional features of the shell, which aren't available on every
+/// platform. Applications can check these to offer another way to do what a
+/// missing feature would have done, such as a file dialog in place of dropping
+/// files. See [`Shell::features`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShellFeatures {
+    /// Whether files can be dropped on windows. See [`Shell::dropped_files`].
+    pub file_drop: bool,
+    /// Whether text can be entered with an input method. See
+    /// [`Shell::composition`] and [`Shell::set_ime_area`].
+    pub input_method: bool,
+    /// Whether relative mouse motion can be reported. See
+    /// [`Shell::set_raw_mouse_input`].
+    pub raw_mouse_input: bool,
+    /// Whether each window is scaled for the monitor that it is on, rather
+    /// than every window sharing one scale. See [`Shell::scale_factor`].
+    pub per_monitor_scale: bool,
+    /// Whether windows are described to assistive technology, such as screen
+    /// readers. See [`Shell::update_accessibility`].
+    pub accessibility: bool,
+}
+
+/// An event sent to the event loop with an [`EventLoopProxy`].
+pub type UserEvent = Box<dyn Any + Send>;
+
+/// Sends events to the event loop from other threads, such as when a
+/// background task finishes. Clones send to the same event loop. See
+/// [`Shell::event_loop_proxy`].
+#[derive(Clone)]
+pub struct EventLoopProxy(platform::EventLoopProxy);
+
+impl EventLoopProxy {
+    /// Queues `event` and wakes the event loop if it is waiting for input,
+    /// which then receives [`Event::User`].
+    ///
+    /// ## Errors
+    ///
+    /// Fails if the event loop has stopped.
+    pub fn send(&self, event: impl Any + Send) -> Result<(), Error> {
+        self.0.send(Box::new(event))
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct WindowId(platform::WindowId);
 
+/// Identifies an icon added with [`Shell::add_tray_icon`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TrayIconId(u64);
+
+/// An icon shown in the notification area of the taskbar. See
+/// [`Shell::add_tray_icon`].
+pub struct TrayIcon<'a> {
+    /// Only `RGB8` and `RGBA8` images are supported, and others are shown as
+    /// a blank icon.
+    pub icon: &'a PixelBuffer,
+    /// Shown when the mouse hovers over the icon. Truncated to 127 UTF-16
+    /// code units.
+    pub tooltip: &'a str,
+    /// Shown when the icon is right-clicked, or `None` for no menu.
+    pub menu: Option<&'a Menu>,
+}
+
+/// Identifies a timer started with [`Shell::set_timer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EventLoopControl {
+    /// Runs the event loop again without waiting for input, and repaints
+    /// every window each time, such as for games that draw continuously.
     Poll,
+    /// Waits for input, timers, or redraw requests before running the event
+    /// loop again, and only repaints the windows that asked to be with
+    /// [`Shell::request_redraw`] or that the system asked to repaint.
     Wait,
     /// Performs a clean exit of the event loop once the callback returns. Any
     /// windows created within the callback will be destroyed (the
@@ -79,6 +257,13 @@ impl OsShell {
     }
 }
 
+/// Shows a message box with an error, and waits for the user to close it.
+/// Unlike the rest of the shell, this doesn't need the shell to be running, so
+/// it can be used from a panic hook.
+pub fn show_error_dialog(title: &str, message: &str) {
+    platform::show_error_dialog(title, message);
+}
+
 impl Shell for OsShell {
     fn create_window(&self, config: &WindowConfig) -> Result<WindowId, Error> {
         self.inner.create_window(config)
@@ -96,14 +281,199 @@ impl Shell for OsShell {
         self.inner.hide_window(window);
     }
 
+    fn request_redraw(&self, window: WindowId) {
+        self.inner.request_redraw(window);
+    }
+
+    fn set_title(&self, window: WindowId, title: &str) {
+        self.inner.set_title(window, title);
+    }
+
+    fn set_extent(&self, window: WindowId, extent: PhysicalExtent) {
+        self.inner.set_extent(window, extent);
+    }
+
+    fn set_position(&self, window: WindowId, position: PhysicalPoint) {
+        self.inner.set_position(window, position);
+    }
+
+    fn minimize(&self, window: WindowId) {
+        self.inner.minimize(window);
+    }
+
+    fn maximize(&self, window: WindowId) {
+        self.inner.maximize(window);
+    }
+
+    fn restore(&self, window: WindowId) {
+        self.inner.restore(window);
+    }
+
+    fn window_placement(&self, window: WindowId) -> WindowPlacement {
+        self.inner.window_placement(window)
+    }
+
+    fn restore_placement(&self, window: WindowId, placement: &WindowPlacement) {
+        self.inner.restore_placement(window, placement);
+    }
+
+    fn set_size_limits(
+        &self,
+        window: WindowId,
+        min: Option<PhysicalExtent>,
+        max: Option<PhysicalExtent>,
+    ) {
+        self.inner.set_size_limits(window, min, max);
+    }
+
+    fn set_resizable(&self, window: WindowId, resizable: bool) {
+        self.inner.set_resizable(window, resizable);
+    }
+
+    fn set_always_on_top(&self, window: WindowId, always_on_top: bool) {
+        self.inner.set_always_on_top(window, always_on_top);
+    }
+
+    fn set_window_icon(&self, window: WindowId, icon: Option<&PixelBuffer>) {
+        self.inner.set_window_icon(window, icon);
+    }
+
+    fn fullscreen(&self, window: WindowId) -> FullscreenMode {
+        self.inner.fullscreen(window)
+    }
+
+    fn set_fullscreen(&self, window: WindowId, mode: FullscreenMode) {
+        self.inner.set_fullscreen(window, mode);
+    }
+
+    fn scale_factor(&self, window: WindowId) -> ScaleFactor {
+        self.inner.scale_factor(window)
+    }
+
+    fn primary_scale_factor(&self) -> ScaleFactor {
+        self.inner.primary_scale_factor()
+    }
+
+    fn refresh_rate(&self, window: WindowId) -> Option<f32> {
+        self.inner.refresh_rate(window)
+    }
+
+    fn prefers_subpixel_text(&self) -> bool {
+        self.inner.prefers_subpixel_text()
+    }
+
+    fn features(&self) -> ShellFeatures {
+        self.inner.features()
+    }
+
+    fn clipboard(&self) -> Clipboard {
+        self.inner.clipboard()
+    }
+
+    fn event_loop_proxy(&self) -> EventLoopProxy {
+        self.inner.event_loop_proxy()
+    }
+
+    fn take_user_events(&self) -> Vec<UserEvent> {
+        self.inner.take_user_events()
+    }
+
+    fn composition(&self, window: WindowId) -> Composition {
+        self.inner.composition(window)
+    }
+
+    fn dropped_files(&self, window: WindowId) -> Vec<PathBuf> {
+        self.inner.dropped_files(window)
+    }
+
+    fn update_accessibility(&self, window: WindowId, update: accesskit::TreeUpdate) {
+        self.inner.update_accessibility(window, update);
+    }
+
+    fn take_access_requests(&self, window: WindowId) -> Vec<accesskit::ActionRequest> {
+        self.inner.take_access_requests(window)
+    }
+
+    fn set_ime_area(&self, window: WindowId, area: PhysicalRect) {
+        self.inner.set_ime_area(window, area);
+    }
+
+    fn set_raw_mouse_input(&self, window: WindowId, enabled: bool) {
+        self.inner.set_raw_mouse_input(window, enabled);
+    }
+
+    fn set_cursor(&self, window: WindowId, icon: CursorIcon) {
+        self.inner.set_cursor(window, icon);
+    }
+
+    fn set_cursor_visible(&self, window: WindowId, visible: bool) {
+        self.inner.set_cursor_visible(window, visible);
+    }
+
+    fn set_mouse_capture(&self, window: WindowId, captured: bool) {
+        self.inner.set_mouse_capture(window, captured);
+    }
+
+    fn confine_cursor(&self, window: WindowId, area: Option<PhysicalRect>) {
+        self.inner.confine_cursor(window, area);
+    }
+
+    fn open_file_dialog(&self, window: WindowId, dialog: &FileDialog) -> DialogId {
+        self.inner.open_file_dialog(window, dialog)
+    }
+
+    fn save_file_dialog(&self, window: WindowId, dialog: &FileDialog) -> DialogId {
+        self.inner.save_file_dialog(window, dialog)
+    }
+
+    fn take_dialog_result(&self, dialog: DialogId) -> Vec<PathBuf> {
+        self.inner.take_dialog_result(dialog)
+    }
+
+    fn set_timer(&self, window: WindowId, duration: Duration, repeating: bool) -> TimerId {
+        self.inner.set_timer(window, duration, repeating)
+    }
+
+    fn cancel_timer(&self, window: WindowId, timer: TimerId) {
+        self.inner.cancel_timer(window, timer);
+    }
+
+    fn add_tray_icon(&self, window: WindowId, icon: &TrayIcon) -> TrayIconId {
+        self.inner.add_tray_icon(window, icon)
+    }
+
+    fn update_tray_icon(&self, tray: TrayIconId, icon: &TrayIcon) {
+        self.inner.update_tray_icon(tray, icon);
+    }
+
+    fn remove_tray_icon(&self, tray: TrayIconId) {
+        self.inner.remove_tray_icon(tray);
+    }
+
+    fn set_menu_bar(&self, window: WindowId, menu: Option<&Menu>) {
+        self.inner.set_menu_bar(window, menu);
+    }
+
     #[cfg(target_os = "windows")]
     fn hwnd(&self, window: WindowId) -> windows::Win32::Foundation::HWND {
         self.inner.hwnd(window)
     }
+
+    #[cfg(target_os = "linux")]
+    fn xlib_window(&self, window: WindowId) -> (*mut std::ffi::c_void, std::os::raw::c_ulong) {
+        self.inner.xlib_window(window)
+    }
 }
 
+/// Window geometry, such as extents, positions, and the areas given to the
+/// input method, is in physical pixels, since that is what the window system
+/// works in. Convert it to and from the logical pixels of layout with
+/// [`Shell::scale_factor`].
 pub trait Shell {
-    /// Creates a new window for the given configuration.
+    /// Creates a new window for the given configuration. Events that the
+    /// window is sent while it is created, such as [`event::Window::Init`],
+    /// are delivered once the event callback returns if it is called from
+    /// within the callback.
     ///
     /// ## Errors
     ///
@@ -121,7 +491,235 @@ pub trait Shell {
     /// Makes the window invisible.
     fn hide_window(&self, window: WindowId);
 
+    /// Sends [`event::Window::Repaint`] to the window the next time the event
+    /// loop runs, without waiting for input. Requests made before then are
+    /// combined into one.
+    fn request_redraw(&self, window: WindowId);
+
+    /// Changes the text shown in the window's title bar and the taskbar.
+    fn set_title(&self, window: WindowId, title: &str);
+
+    /// Resizes the window so that the area inside its borders and title bar
+    /// is `extent`, within its size limits. A minimized or maximized window
+    /// is restored first. Ignored while the window is fullscreen.
+    fn set_extent(&self, window: WindowId, extent: PhysicalExtent);
+
+    /// Moves the window so that the top-left corner of its frame is at
+    /// `position`, in screen coordinates. Ignored while the window is
+    /// fullscreen.
+    fn set_position(&self, window: WindowId, position: PhysicalPoint);
+
+    /// Minimizes the window to the taskbar.
+    fn minimize(&self, window: WindowId);
+
+    /// Maximizes the window to fill the monitor's work area. Ignored while
+    /// the window is fullscreen.
+    fn maximize(&self, window: WindowId);
+
+    /// Restores a minimized or maximized window to its previous size and
+    /// position.
+    fn restore(&self, window: WindowId);
+
+    /// Retrieves where the window is on the screen, so that it can be saved
+    /// and reopened in the same place later with [`Shell::restore_placement`].
+    /// If the window is minimized or fullscreen, this is where it will be
+    /// restored to.
+    fn window_placement(&self, window: WindowId) -> WindowPlacement;
+
+    /// Moves and resizes the window to a placement from
+    /// [`Shell::window_placement`], and maximizes it if it was maximized. If
+    /// the monitor that the window was on has been disconnected or changed
+    /// size, the window is centered on the nearest monitor instead, and kept
+    /// inside of its work area. Fullscreen windows are moved there when they
+    /// leave fullscreen.
+    fn restore_placement(&self, window: WindowId, placement: &WindowPlacement);
+
+    /// Limits the size that the user can resize the window to, not including
+    /// its borders and title bar. The window is resized immediately if it is
+    /// outside of the new limits.
+    fn set_size_limits(
+        &self,
+        window: WindowId,
+        min: Option<PhysicalExtent>,
+        max: Option<PhysicalExtent>,
+    );
+
+    /// Allows or prevents the user from resizing and maximizing the window.
+    /// Has no effect on transparent windows, which don't have borders.
+    fn set_resizable(&self, window: WindowId, resizable: bool);
+
+    /// Keeps the window above every window that isn't also always on top, or
+    /// lets other windows cover it again.
+    fn set_always_on_top(&self, window: WindowId, always_on_top: bool);
+
+    /// Changes the icon shown in the window's title bar and the taskbar, or
+    /// restores the platform's default if `icon` is `None`. Only `RGB8` and
+    /// `RGBA8` images are supported, and others are ignored.
+    fn set_window_icon(&self, window: WindowId, icon: Option<&PixelBuffer>);
+
+    /// How the window covers the screen.
+    fn fullscreen(&self, window: WindowId) -> FullscreenMode;
+
+    /// Makes the window fullscreen or windowed. The window's placement is
+    /// remembered when it becomes fullscreen, and restored when it returns to
+    /// [`FullscreenMode::Windowed`].
+    ///
+    /// The change is applied once the event callback returns, and the window
+    /// is sent [`event::Window::Resized`] if its size changes. Exclusive
+    /// fullscreen also needs the window's swapchain to allow it; see
+    /// [`GfxDevice::set_swapchain_exclusive`](crate::gfx::GfxDevice::set_swapchain_exclusive).
+    fn set_fullscreen(&self, window: WindowId, mode: FullscreenMode);
+
+    /// The number of physical pixels per logical pixel on the monitor that the
+    /// window is on. See [`event::Window::ScaleFactorChanged`].
+    ///
+    /// On X11, every window shares the scale set for the screen as a whole,
+    /// and the scale never changes while the program runs. See
+    /// [`ShellFeatures::per_monitor_scale`].
+    fn scale_factor(&self, window: WindowId) -> ScaleFactor;
+
+    /// The scale factor of the primary monitor, which new windows are created
+    /// on unless they are placed elsewhere. Use it to convert the extents in a
+    /// [`WindowConfig`] from logical pixels.
+    fn primary_scale_factor(&self) -> ScaleFactor;
+
+    /// How many times per second the monitor that the window is on refreshes,
+    /// or `None` if it can't be found out. On X11, this is the refresh rate
+    /// of the screen as a whole.
+    fn refresh_rate(&self, window: WindowId) -> Option<f32>;
+
+    /// Checks if the user has asked for text to be smoothed with subpixel
+    /// anti-aliasing, such as with ClearType on Windows.
+    fn prefers_subpixel_text(&self) -> bool;
+
+    /// The optional features that the shell supports on this platform.
+    fn features(&self) -> ShellFeatures;
+
+    /// The system clipboard.
+    fn clipboard(&self) -> Clipboard;
+
+    /// Creates a proxy that other threads can send events to the event loop
+    /// with.
+    fn event_loop_proxy(&self) -> EventLoopProxy;
+
+    /// Takes the events sent with an [`EventLoopProxy`], in the order that
+    /// they were sent. See [`Event::User`].
+    fn take_user_events(&self) -> Vec<UserEvent>;
+
+    /// Reads the text that the input method is composing for the window. This
+    /// is empty if it isn't composing anything.
+    ///
+    /// Input methods aren't supported on X11, where this is always empty and
+    /// text is only entered through the keyboard. See
+    /// [`ShellFeatures::input_method`].
+    fn composition(&self, window: WindowId) -> Composition;
+
+    /// Reads the paths of the files dropped on the window. Only valid while
+    /// handling [`event::Window::FilesDropped`], and empty otherwise.
+    ///
+    /// Dropping files isn't supported on X11, where windows never receive
+    /// [`event::Window::FilesDropped`]. See [`ShellFeatures::file_drop`].
+    fn dropped_files(&self, window: WindowId) -> Vec<PathBuf>;
+
+    /// Replaces the description of the window's contents that assistive
+    /// technology, such as screen readers, is given. The update must contain
+    /// the whole tree. Requests that assistive technology makes of the window
+    /// are reported with [`event::Window::AccessibilityRequested`].
+    ///
+    /// Assistive technology isn't supported on X11, where this does nothing.
+    /// See [`ShellFeatures::accessibility`].
+    fn update_accessibility(&self, window: WindowId, update: accesskit::TreeUpdate);
+
+    /// Takes the requests that assistive technology made of the window, in
+    /// the order that they were made. See
+    /// [`event::Window::AccessibilityRequested`].
+    ///
+    /// Always empty on X11.
+    fn take_access_requests(&self, window: WindowId) -> Vec<accesskit::ActionRequest>;
+
+    /// Tells the input method where text is being entered in the window, in
+    /// window coordinates, so that it can show its candidate window next to
+    /// it without covering it.
+    ///
+    /// Does nothing on X11. See [`ShellFeatures::input_method`].
+    fn set_ime_area(&self, window: WindowId, area: PhysicalRect);
+
+    /// Starts or stops reporting [`event::Window::RawMouseMotion`] events to
+    /// the window. Only one window receives them at a time, so enabling them
+    /// for one window stops them for any other.
+    ///
+    /// Does nothing on X11, where only [`event::Window::CursorMoved`] is
+    /// reported. See [`ShellFeatures::raw_mouse_input`].
+    fn set_raw_mouse_input(&self, window: WindowId, enabled: bool);
+
+    /// Changes the cursor shown while the mouse is over the window. It stays
+    /// the same until it is changed again.
+    fn set_cursor(&self, window: WindowId, icon: CursorIcon);
+
+    /// Shows or hides the cursor while the mouse is over the window.
+    fn set_cursor_visible(&self, window: WindowId, visible: bool);
+
+    /// Sends mouse input to the window even while the cursor is outside of
+    /// it, until the capture is released or another window takes it. This
+    /// keeps drags working when the cursor moves faster than the window can
+    /// follow it.
+    fn set_mouse_capture(&self, window: WindowId, captured: bool);
+
+    /// Keeps the cursor within `area` of the window, in window coordinates,
+    /// or lets it move freely again if `area` is `None`. Only one window can
+    /// confine the cursor at a time, and the cursor is free while the window
+    /// isn't active.
+    fn confine_cursor(&self, window: WindowId, area: Option<PhysicalRect>);
+
+    /// Shows a dialog owned by the window for choosing files to open. The
+    /// window can't be used until the dialog closes, but the event loop keeps
+    /// running. See [`dialog`].
+    fn open_file_dialog(&self, window: WindowId, dialog: &FileDialog) -> DialogId;
+
+    /// Shows a dialog owned by the window for choosing where to save a file.
+    /// The user is asked before an existing file is chosen. See [`dialog`].
+    fn save_file_dialog(&self, window: WindowId, dialog: &FileDialog) -> DialogId;
+
+    /// Takes the files chosen in a dialog that has closed. This is empty if
+    /// the user cancelled the dialog, or the dialog hasn't closed yet.
+    fn take_dialog_result(&self, dialog: DialogId) -> Vec<PathBuf>;
+
+    /// Sends [`event::Window::Timer`] to the window once `duration` has
+    /// passed, and again every `duration` after that if `repeating` is set.
+    /// Timers fire even while the event loop waits for input, and are
+    /// cancelled when their window is destroyed. The system may round
+    /// `duration` up to a minimum of around 10 milliseconds.
+    fn set_timer(&self, window: WindowId, duration: Duration, repeating: bool) -> TimerId;
+
+    /// Stops the timer from firing again. Does nothing if it already stopped.
+    fn cancel_timer(&self, window: WindowId, timer: TimerId);
+
+    /// Shows an icon in the notification area of the taskbar, such as for
+    /// programs that keep running while their windows are hidden. The window
+    /// is sent [`event::Window::TrayIconClicked`] when the icon is clicked,
+    /// and [`event::Window::MenuItemSelected`] when an item in its menu is
+    /// selected. The icon is removed when the window is destroyed.
+    fn add_tray_icon(&self, window: WindowId, icon: &TrayIcon) -> TrayIconId;
+
+    /// Changes the image, tooltip, and menu of a tray icon.
+    fn update_tray_icon(&self, tray: TrayIconId, icon: &TrayIcon);
+
+    /// Removes a tray icon. Does nothing if it was already removed.
+    fn remove_tray_icon(&self, tray: TrayIconId);
+
+    /// Replaces the window's menu bar, such as to check or disable its items,
+    /// or removes it if `menu` is `None`. The window is sent
+    /// [`event::Window::MenuItemSelected`] when an item is selected, either
+    /// from the menu or with its accelerator. The area inside the window's
+    /// borders shrinks to make room for the bar.
+    fn set_menu_bar(&self, window: WindowId, menu: Option<&Menu>);
+
     /// Retrieves the `HWND` for the window.
     #[cfg(target_os = "windows")]
     fn hwnd(&self, window: WindowId) -> windows::Win32::Foundation::HWND;
+
+    /// Retrieves the Xlib `Display` that the window was created with, and
+    /// the window's ID.
+    #[cfg(target_os = "linux")]
+    fn xlib_window(&self, window: WindowId) -> (*mut std::ffi::c_void, std::os::raw::c_ulong);
 }
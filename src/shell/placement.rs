@@ -0,0 +1,168 @@
+//! Describes where a window is on the screen, so that it can be reopened in
+//! the same place the next time the program runs.
+//!
+//! A [`WindowPlacement`] is read with
+//! [`Shell::window_placement`](super::Shell::window_placement), converts to
+//! and from text for saving, and is applied with
+//! [`Shell::restore_placement`](super::Shell::restore_placement).
+
+use std::{fmt, str::FromStr};
+
+use crate::gfx::geometry::{PhysicalExtent, PhysicalPoint, PhysicalPx, PhysicalRect};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PlacementError {
+    #[error("the window placement could not be parsed: {0}")]
+    Parse(String),
+}
+
+/// Where a window is on the screen, in screen coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowPlacement {
+    /// The top-left corner of the window's frame while it isn't minimized,
+    /// maximized, or fullscreen.
+    pub position: PhysicalPoint,
+    /// The size of the window's frame, including its borders and title bar,
+    /// while it isn't minimized, maximized, or fullscreen.
+    pub extent: PhysicalExtent,
+    pub maximized: bool,
+    /// The bounds of the monitor that the window was on, used to tell if the
+    /// monitor has been disconnected or rearranged since.
+    pub monitor: PhysicalRect,
+}
+
+impl fmt::Display for WindowPlacement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = if self.maximized {
+            "maximized"
+        } else {
+            "normal"
+        };
+
+        write!(
+            f,
+            "(placement {} {} {} {} {state} (monitor {} {} {} {}))",
+            self.position.x.0,
+            self.position.y.0,
+            self.extent.width.0,
+            self.extent.height.0,
+            self.monitor.left.0,
+            self.monitor.top.0,
+            self.monitor.width().0,
+            self.monitor.height().0,
+        )
+    }
+}
+
+impl FromStr for WindowPlacement {
+    type Err = PlacementError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spaced = s.replace('(', " ( ").replace(')', " ) ");
+        let mut tokens = spaced.split_whitespace();
+        let mut next = || {
+            tokens
+                .next()
+                .ok_or_else(|| PlacementError::Parse("unexpected end".to_string()))
+        };
+
+        let expect = |expected: &str, token: &str| {
+            if token == expected {
+                Ok(())
+            } else {
+                Err(PlacementError::Parse(format!(
+                    "expected {expected:?} but found {token:?}"
+                )))
+            }
+        };
+
+        let number = |token: &str| {
+            token
+                .parse::<i16>()
+                .map_err(|_| PlacementError::Parse(format!("{token:?} is not a number")))
+        };
+
+        expect("(", next()?)?;
+        expect("placement", next()?)?;
+        let position = PhysicalPoint::new(number(next()?)?, number(next()?)?);
+        let extent = PhysicalExtent {
+            width: PhysicalPx(number(next()?)?),
+            height: PhysicalPx(number(next()?)?),
+        };
+        let maximized = match next()? {
+            "maximized" => true,
+            "normal" => false,
+            token => {
+                return Err(PlacementError::Parse(format!(
+                    "expected \"maximized\" or \"normal\" but found {token:?}"
+                )))
+            }
+        };
+
+        expect("(", next()?)?;
+        expect("monitor", next()?)?;
+        let monitor_position = PhysicalPoint::new(number(next()?)?, number(next()?)?);
+        let monitor_extent = PhysicalExtent {
+            width: PhysicalPx(number(next()?)?),
+            height: PhysicalPx(number(next()?)?),
+        };
+        expect(")", next()?)?;
+        expect(")", next()?)?;
+
+        match tokens.next() {
+            Some(token) => Err(PlacementError::Parse(format!(
+                "unexpected {token:?} after the end"
+            ))),
+            None => Ok(Self {
+                position,
+                extent,
+                maximized,
+                monitor: PhysicalRect::new(monitor_position, monitor_extent),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let placement = WindowPlacement {
+            position: PhysicalPoint::new(-1800, 40),
+            extent: PhysicalExtent {
+                width: PhysicalPx(1280),
+                height: PhysicalPx(720),
+            },
+            maximized: true,
+            monitor: PhysicalRect::new(
+                PhysicalPoint::new(-1920, 0),
+                PhysicalExtent {
+                    width: PhysicalPx(1920),
+                    height: PhysicalPx(1080),
+                },
+            ),
+        };
+
+        let text = placement.to_string();
+        assert_eq!(
+            text,
+            "(placement -1800 40 1280 720 maximized (monitor -1920 0 1920 1080))"
+        );
+        assert_eq!(text.parse::<WindowPlacement>().unwrap(), placement);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("(placement 0 0 100 100 normal)"
+            .parse::<WindowPlacement>()
+            .is_err());
+        assert!("(placement 0 0 100 100 tiny (monitor 0 0 10 10))"
+            .parse::<WindowPlacement>()
+            .is_err());
+        assert!("(placement 0 0 100 100 normal (monitor 0 0 10 10)) extra"
+            .parse::<WindowPlacement>()
+            .is_err());
+    }
+}
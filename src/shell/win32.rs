@@ -1,35 +1,116 @@
 use std::{
     cell::{Cell, RefCell},
-    rc::Rc,
+    path::PathBuf,
+    ptr::null_mut,
+    rc::{Rc, Weak},
+    sync::{Arc, Mutex},
     thread::ThreadId,
+    time::Duration,
 };
 
 use once_cell::sync::OnceCell;
 use windows::{
-    core::PCWSTR,
+    core::{PCWSTR, PWSTR},
     Win32::{
-        Foundation::{GetLastError, HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM},
-        Graphics::Gdi::{BeginPaint, EndPaint, PAINTSTRUCT},
-        System::LibraryLoader::GetModuleHandleW,
+        Foundation::{
+            GetLastError, BOOL, HANDLE, HINSTANCE, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM,
+        },
+        Globalization::HIMC,
+        Graphics::Dwm::DwmExtendFrameIntoClientArea,
+        Graphics::Gdi::{
+            BeginPaint, ClientToScreen, EndPaint, EnumDisplaySettingsW, GetMonitorInfoW,
+            MonitorFromRect, MonitorFromWindow, ScreenToClient, DEVMODEW, ENUM_CURRENT_SETTINGS,
+            HMONITOR, MONITORINFO, MONITORINFOEXW, MONITOR_DEFAULTTONEAREST, PAINTSTRUCT,
+        },
+        System::Com::{CoInitializeEx, COINIT_APARTMENTTHREADED},
+        System::{
+            DataExchange::{
+                CloseClipboard, EmptyClipboard, GetClipboardData, OpenClipboard, SetClipboardData,
+            },
+            LibraryLoader::GetModuleHandleW,
+            Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE},
+            SystemServices::CF_UNICODETEXT,
+            Threading::GetCurrentThreadId,
+        },
+        UI::Controls::Dialogs::{
+            GetOpenFileNameW, GetSaveFileNameW, OFN_ALLOWMULTISELECT, OFN_EXPLORER,
+            OFN_FILEMUSTEXIST, OFN_NOCHANGEDIR, OFN_OVERWRITEPROMPT, OFN_PATHMUSTEXIST,
+            OPENFILENAMEW,
+        },
+        UI::Controls::MARGINS,
+        UI::HiDpi::{
+            AdjustWindowRectExForDpi, GetDpiForSystem, GetDpiForWindow,
+            SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
+        },
+        UI::Input::Ime::{
+            ImmGetCompositionStringW, ImmGetContext, ImmReleaseContext, ImmSetCandidateWindow,
+            ImmSetCompositionWindow, CANDIDATEFORM, CFS_EXCLUDE, CFS_POINT, COMPOSITIONFORM,
+            GCS_COMPSTR, GCS_CURSORPOS, GCS_RESULTSTR, ISC_SHOWUICOMPOSITIONWINDOW,
+        },
+        UI::Input::KeyboardAndMouse::{
+            GetCapture, GetKeyState, ReleaseCapture, SetCapture, VIRTUAL_KEY, VK_BACK, VK_CONTROL,
+            VK_DELETE, VK_DOWN, VK_END, VK_ESCAPE, VK_F1, VK_F12, VK_HOME, VK_INSERT, VK_LEFT,
+            VK_MENU, VK_NEXT, VK_PRIOR, VK_RETURN, VK_RIGHT, VK_SHIFT, VK_SPACE, VK_TAB, VK_UP,
+        },
+        UI::Input::Pointer::GetPointerType,
+        UI::Input::{
+            GetRawInputData, RegisterRawInputDevices, HRAWINPUT, RAWINPUT, RAWINPUTDEVICE,
+            RAWINPUTDEVICE_FLAGS, RAWINPUTHEADER, RIDEV_REMOVE, RID_INPUT, RIM_TYPEMOUSE,
+        },
+        UI::Shell::{
+            DragAcceptFiles, DragFinish, DragQueryFileW, DragQueryPoint, Shell_NotifyIconW, HDROP,
+            NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NIM_MODIFY, NOTIFYICONDATAW,
+        },
         UI::WindowsAndMessaging::{
-            CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetClientRect,
-            GetMessageW, GetWindowLongPtrW, LoadCursorW, PeekMessageW, PostMessageW,
-            PostQuitMessage, RegisterClassExW, SetWindowLongPtrW, ShowWindow, TranslateMessage,
-            CREATESTRUCTW, CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT, GWLP_USERDATA, IDC_ARROW, MSG,
-            PM_REMOVE, SWP_NOCOPYBITS, SW_HIDE, SW_SHOW, WINDOWPOS, WINDOW_EX_STYLE, WM_CLOSE,
-            WM_CREATE, WM_DESTROY, WM_ERASEBKGND, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN,
-            WM_MBUTTONUP, WM_MOUSEMOVE, WM_PAINT, WM_QUIT, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_USER,
-            WM_WINDOWPOSCHANGED, WM_WINDOWPOSCHANGING, WNDCLASSEXW, WS_OVERLAPPEDWINDOW,
+            AppendMenuW, ClipCursor, CreateIcon, CreateMenu, CreatePopupMenu, CreateWindowExW,
+            DefWindowProcW, DestroyIcon, DestroyMenu, DestroyWindow, DispatchMessageW,
+            GetClientRect, GetCursorPos, GetForegroundWindow, GetMenu, GetMessageW,
+            GetWindowLongPtrW, GetWindowPlacement, IsIconic, IsZoomed, KillTimer, LoadCursorW,
+            MessageBoxW, PeekMessageW, PostMessageW, PostQuitMessage, PostThreadMessageW,
+            RegisterClassExW, SendMessageW, SetCursor, SetForegroundWindow,
+            SetLayeredWindowAttributes, SetMenu, SetTimer, SetWindowLongPtrW, SetWindowPlacement,
+            SetWindowPos, SetWindowTextW, ShowWindow, SystemParametersInfoW, TrackPopupMenu,
+            TranslateMessage, CREATESTRUCTW, CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT,
+            FE_FONTSMOOTHINGCLEARTYPE, GWLP_USERDATA, GWL_EXSTYLE, GWL_STYLE, HCURSOR, HICON,
+            HMENU, HTCLIENT, HWND_NOTOPMOST, HWND_TOP, HWND_TOPMOST, ICON_BIG, ICON_SMALL,
+            IDC_ARROW, IDC_HAND, IDC_IBEAM, IDC_NO, IDC_SIZEALL, IDC_SIZENS, IDC_SIZEWE, IDC_WAIT,
+            LWA_ALPHA, MB_ICONERROR, MB_OK, MB_SETFOREGROUND, MB_TASKMODAL, MF_CHECKED, MF_GRAYED,
+            MF_POPUP, MF_SEPARATOR, MF_STRING, MINMAXINFO, MSG, PM_NOREMOVE, PM_REMOVE,
+            POINTER_INPUT_TYPE, PT_TOUCH, SPI_GETFONTSMOOTHING, SPI_GETFONTSMOOTHINGTYPE,
+            SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOCOPYBITS, SWP_NOMOVE, SWP_NOOWNERZORDER,
+            SWP_NOSIZE, SWP_NOZORDER, SW_HIDE, SW_MAXIMIZE, SW_MINIMIZE, SW_RESTORE, SW_SHOW,
+            SW_SHOWMAXIMIZED, SW_SHOWMINIMIZED, SW_SHOWNORMAL, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+            TPM_RETURNCMD, TPM_RIGHTBUTTON, WA_INACTIVE, WINDOWPLACEMENT, WINDOWPOS,
+            WINDOW_EX_STYLE, WINDOW_STYLE, WM_ACTIVATE, WM_CHAR, WM_CLOSE, WM_COMMAND, WM_CREATE,
+            WM_DESTROY, WM_DPICHANGED, WM_DROPFILES, WM_ENTERSIZEMOVE, WM_ERASEBKGND,
+            WM_EXITSIZEMOVE, WM_GETMINMAXINFO, WM_GETOBJECT, WM_IME_COMPOSITION,
+            WM_IME_ENDCOMPOSITION, WM_IME_SETCONTEXT, WM_IME_STARTCOMPOSITION, WM_INPUT,
+            WM_KEYDOWN, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEHWHEEL,
+            WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_PAINT, WM_POINTERCAPTURECHANGED, WM_POINTERDOWN,
+            WM_POINTERUP, WM_POINTERUPDATE, WM_QUIT, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETCURSOR,
+            WM_SETICON, WM_SYSKEYDOWN, WM_TIMER, WM_USER, WM_WINDOWPOSCHANGED,
+            WM_WINDOWPOSCHANGING, WNDCLASSEXW, WPF_RESTORETOMAXIMIZED, WS_EX_LAYERED,
+            WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_MAXIMIZEBOX, WS_OVERLAPPEDWINDOW, WS_POPUP,
+            WS_THICKFRAME, WS_VISIBLE,
         },
     },
 };
 
 use crate::{
-    gfx::geometry::{Extent, Point, Px},
-    shell::event::{Event, Window as WindowEvent},
+    gfx::{
+        geometry::{Extent, PhysicalExtent, PhysicalPoint, PhysicalPx, PhysicalRect, ScaleFactor},
+        pixel_buffer::{Layout, PixelBuffer},
+    },
+    shell::event::{Event, Key, Modifiers, TouchPhase, Window as WindowEvent},
 };
 
-use super::{Error, EventLoopControl, WindowConfig};
+use super::{
+    dialog::{DialogId, FileDialog, FileFilter},
+    menu::{Accelerator, Menu, MenuItem, MenuItemId},
+    placement::WindowPlacement,
+    Composition, CursorIcon, Error, EventLoopControl, FullscreenMode, TimerId, TrayIcon,
+    TrayIconId, UserEvent, WindowConfig,
+};
 
 /// This message is sent when the user destroys a window (by dropping the
 /// window) instead of calling `DestroyWindow` in order to avoid re-entrancy in
@@ -37,6 +118,33 @@ use super::{Error, EventLoopControl, WindowConfig};
 /// called once execution is outside of the event callback.
 const UM_DESTROY_WINDOW: u32 = WM_USER + 1;
 
+/// This message is posted to a window by the thread running a file dialog that
+/// it owns once the dialog closes, with the dialog's ID in `WPARAM`.
+const UM_FILE_DIALOG_CLOSED: u32 = WM_USER + 2;
+
+/// This message is posted to a window when its size limits, style, or
+/// fullscreen mode change, so that it is resized to fit them and its frame is
+/// redrawn once execution is outside of the event callback.
+const UM_UPDATE_FRAME: u32 = WM_USER + 3;
+
+/// This message is posted to the shell's thread, rather than to a window, by
+/// an `EventLoopProxy` to wake the event loop once it has queued an event.
+const UM_USER_EVENT: u32 = WM_USER + 4;
+
+/// This message is sent to a window by the taskbar when the mouse interacts
+/// with one of its tray icons, with the icon's ID in `WPARAM` and the mouse
+/// message in the low word of `LPARAM`.
+const UM_TRAY_ICON: u32 = WM_USER + 5;
+
+/// This message is posted to a window by its accessibility adapter, which
+/// may run on another thread, once it has queued a request from assistive
+/// technology.
+const UM_ACCESS_REQUEST: u32 = WM_USER + 6;
+
+/// Set in `RAWMOUSE::usFlags` when the mouse reports its absolute position
+/// rather than how far it moved, such as for tablets and remote desktops.
+const MOUSE_MOVE_ABSOLUTE: u16 = 1;
+
 /// The name of Fathom's window classes `"FATHOM_WNDCLASS"` in UTF-16 as an
 /// array of `u16`s.
 const WNDCLASS_NAME: &[u16] = &[
@@ -82,6 +190,15 @@ impl OsShell {
 
         let hinstance = unsafe { GetModuleHandleW(None) }.unwrap();
 
+        // Windows are drawn at the resolution of the monitor that they are
+        // on, rather than being scaled by the system. This fails if the
+        // program's manifest already set the awareness, which is fine.
+        unsafe { SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) };
+
+        // Make sure that the thread has a message queue before any proxies
+        // can post to it.
+        unsafe { PeekMessageW(&mut MSG::default(), None, WM_USER, WM_USER, PM_NOREMOVE) };
+
         let _wndclass_atom = {
             let arrow_cursor = unsafe { LoadCursorW(None, IDC_ARROW) }.unwrap();
 
@@ -101,9 +218,29 @@ impl OsShell {
         Self {
             inner: Rc::new(Inner {
                 hinstance,
-                init_event_buffer: RefCell::new(vec![]),
+                event_buffer: RefCell::new(vec![]),
                 windows: RefCell::new(vec![]),
+                redraw_requests: RefCell::new(vec![]),
+                cursors: RefCell::new(vec![]),
+                hidden_cursors: RefCell::new(vec![]),
+                size_limits: RefCell::new(vec![]),
+                icons: RefCell::new(vec![]),
+                fullscreen: RefCell::new(vec![]),
+                cursor_clip: Cell::new(None),
+                next_dialog: Cell::new(0),
+                next_timer: Cell::new(0),
+                one_shot_timers: RefCell::new(vec![]),
+                next_tray: Cell::new(0),
+                tray_icons: RefCell::new(vec![]),
+                menu_bars: RefCell::new(vec![]),
+                dropped_files: RefCell::new(None),
+                accessibility: RefCell::new(vec![]),
+                uia_init: accesskit_windows::UiaInitMarker::new(),
+                dialog_results: Arc::new(Mutex::new(Vec::new())),
+                thread: unsafe { GetCurrentThreadId() },
+                user_events: Arc::new(Mutex::new(Vec::new())),
                 is_shutting_down: Cell::new(false),
+                high_surrogate: Cell::new(None),
                 event_mode: Cell::new(EventLoopControl::Poll),
                 event_callback: RefCell::new(None),
             }),
@@ -126,7 +263,7 @@ impl OsShell {
         *self.inner.event_callback.borrow_mut() = Some(Box::new(callback));
 
         {
-            let buffered_events = self.inner.init_event_buffer.take();
+            let buffered_events = self.inner.event_buffer.take();
             dispatch(&self.inner, buffered_events);
         }
 
@@ -138,7 +275,7 @@ impl OsShell {
             }
 
             let mut msg = MSG::default();
-            if mode == EventLoopControl::Wait {
+            if mode == EventLoopControl::Wait && self.inner.redraw_requests.borrow().is_empty() {
                 match unsafe { GetMessageW(&mut msg, None, 0, 0).0 } {
                     -1 => panic!("GetMessage failed. Error: {:?}", unsafe { GetLastError() }),
                     0 => break 'evt,
@@ -160,18 +297,34 @@ impl OsShell {
                 }
             }
 
-            dispatch(
-                &self.inner,
-                self.inner
-                    .windows
-                    .borrow()
-                    .iter()
-                    .map(|hwnd| Event::Window {
-                        window_id: (*hwnd).into(),
-                        event: WindowEvent::Repaint,
-                    })
-                    .chain(std::iter::once(Event::RepaintComplete)),
-            );
+            // Events are checked for here rather than when `UM_USER_EVENT` is
+            // received, since messages posted to the thread are lost while a
+            // window is being dragged or a menu is open.
+            if !self.inner.user_events.lock().unwrap().is_empty() {
+                dispatch(&self.inner, std::iter::once(Event::User));
+            }
+
+            // The mode is checked again since the callback may have changed
+            // it while handling messages.
+            let repaint = if self.inner.event_mode.get() == EventLoopControl::Poll {
+                self.inner.redraw_requests.borrow_mut().clear();
+                self.inner.windows.borrow().clone()
+            } else {
+                self.inner.redraw_requests.take()
+            };
+
+            if !repaint.is_empty() {
+                dispatch(
+                    &self.inner,
+                    repaint
+                        .into_iter()
+                        .map(|hwnd| Event::Window {
+                            window_id: hwnd.into(),
+                            event: WindowEvent::Repaint,
+                        })
+                        .chain(std::iter::once(Event::RepaintComplete)),
+                );
+            }
         }
 
         clean_exit(&self.inner);
@@ -195,25 +348,602 @@ impl super::Shell for OsShell {
         self.inner.hide_window(window);
     }
 
+    fn request_redraw(&self, window: super::WindowId) {
+        self.inner.request_redraw(window);
+    }
+
+    fn set_title(&self, window: super::WindowId, title: &str) {
+        self.inner.set_title(window, title);
+    }
+
+    fn set_extent(&self, window: super::WindowId, extent: PhysicalExtent) {
+        self.inner.set_extent(window, extent);
+    }
+
+    fn set_position(&self, window: super::WindowId, position: PhysicalPoint) {
+        self.inner.set_position(window, position);
+    }
+
+    fn minimize(&self, window: super::WindowId) {
+        self.inner.minimize(window);
+    }
+
+    fn maximize(&self, window: super::WindowId) {
+        self.inner.maximize(window);
+    }
+
+    fn restore(&self, window: super::WindowId) {
+        self.inner.restore(window);
+    }
+
+    fn window_placement(&self, window: super::WindowId) -> WindowPlacement {
+        self.inner.window_placement(window)
+    }
+
+    fn restore_placement(&self, window: super::WindowId, placement: &WindowPlacement) {
+        self.inner.restore_placement(window, placement);
+    }
+
+    fn set_size_limits(
+        &self,
+        window: super::WindowId,
+        min: Option<PhysicalExtent>,
+        max: Option<PhysicalExtent>,
+    ) {
+        self.inner.set_size_limits(window, min, max);
+    }
+
+    fn set_resizable(&self, window: super::WindowId, resizable: bool) {
+        self.inner.set_resizable(window, resizable);
+    }
+
+    fn set_always_on_top(&self, window: super::WindowId, always_on_top: bool) {
+        self.inner.set_always_on_top(window, always_on_top);
+    }
+
+    fn set_window_icon(&self, window: super::WindowId, icon: Option<&PixelBuffer>) {
+        self.inner.set_window_icon(window, icon);
+    }
+
+    fn fullscreen(&self, window: super::WindowId) -> FullscreenMode {
+        self.inner.fullscreen(window)
+    }
+
+    fn set_fullscreen(&self, window: super::WindowId, mode: FullscreenMode) {
+        self.inner.set_fullscreen(window, mode);
+    }
+
+    fn scale_factor(&self, window: super::WindowId) -> ScaleFactor {
+        self.inner.scale_factor(window)
+    }
+
+    fn primary_scale_factor(&self) -> ScaleFactor {
+        self.inner.primary_scale_factor()
+    }
+
+    fn refresh_rate(&self, window: super::WindowId) -> Option<f32> {
+        self.inner.refresh_rate(window)
+    }
+
+    fn prefers_subpixel_text(&self) -> bool {
+        self.inner.prefers_subpixel_text()
+    }
+
+    fn features(&self) -> super::ShellFeatures {
+        self.inner.features()
+    }
+
+    fn clipboard(&self) -> super::Clipboard {
+        self.inner.clipboard()
+    }
+
+    fn event_loop_proxy(&self) -> super::EventLoopProxy {
+        self.inner.event_loop_proxy()
+    }
+
+    fn take_user_events(&self) -> Vec<UserEvent> {
+        self.inner.take_user_events()
+    }
+
+    fn composition(&self, window: super::WindowId) -> Composition {
+        self.inner.composition(window)
+    }
+
+    fn dropped_files(&self, window: super::WindowId) -> Vec<PathBuf> {
+        self.inner.dropped_files(window)
+    }
+
+    fn update_accessibility(&self, window: super::WindowId, update: accesskit::TreeUpdate) {
+        self.inner.update_accessibility(window, update);
+    }
+
+    fn take_access_requests(&self, window: super::WindowId) -> Vec<accesskit::ActionRequest> {
+        self.inner.take_access_requests(window)
+    }
+
+    fn set_ime_area(&self, window: super::WindowId, area: PhysicalRect) {
+        self.inner.set_ime_area(window, area);
+    }
+
+    fn set_raw_mouse_input(&self, window: super::WindowId, enabled: bool) {
+        self.inner.set_raw_mouse_input(window, enabled);
+    }
+
+    fn set_cursor(&self, window: super::WindowId, icon: CursorIcon) {
+        self.inner.set_cursor(window, icon);
+    }
+
+    fn set_cursor_visible(&self, window: super::WindowId, visible: bool) {
+        self.inner.set_cursor_visible(window, visible);
+    }
+
+    fn set_mouse_capture(&self, window: super::WindowId, captured: bool) {
+        self.inner.set_mouse_capture(window, captured);
+    }
+
+    fn confine_cursor(&self, window: super::WindowId, area: Option<PhysicalRect>) {
+        self.inner.confine_cursor(window, area);
+    }
+
+    fn open_file_dialog(&self, window: super::WindowId, dialog: &FileDialog) -> DialogId {
+        self.inner.open_file_dialog(window, dialog)
+    }
+
+    fn save_file_dialog(&self, window: super::WindowId, dialog: &FileDialog) -> DialogId {
+        self.inner.save_file_dialog(window, dialog)
+    }
+
+    fn take_dialog_result(&self, dialog: DialogId) -> Vec<PathBuf> {
+        self.inner.take_dialog_result(dialog)
+    }
+
+    fn set_timer(&self, window: super::WindowId, duration: Duration, repeating: bool) -> TimerId {
+        self.inner.set_timer(window, duration, repeating)
+    }
+
+    fn cancel_timer(&self, window: super::WindowId, timer: TimerId) {
+        self.inner.cancel_timer(window, timer);
+    }
+
+    fn add_tray_icon(&self, window: super::WindowId, icon: &TrayIcon) -> TrayIconId {
+        self.inner.add_tray_icon(window, icon)
+    }
+
+    fn update_tray_icon(&self, tray: TrayIconId, icon: &TrayIcon) {
+        self.inner.update_tray_icon(tray, icon);
+    }
+
+    fn remove_tray_icon(&self, tray: TrayIconId) {
+        self.inner.remove_tray_icon(tray);
+    }
+
+    fn set_menu_bar(&self, window: super::WindowId, menu: Option<&Menu>) {
+        self.inner.set_menu_bar(window, menu);
+    }
+
     fn hwnd(&self, window: super::WindowId) -> windows::Win32::Foundation::HWND {
         self.inner.hwnd(window)
     }
 }
 
+#[derive(Clone)]
+pub struct EventLoopProxy {
+    /// The thread running the event loop.
+    thread: u32,
+    events: Arc<Mutex<Vec<UserEvent>>>,
+}
+
+impl EventLoopProxy {
+    pub fn send(&self, event: UserEvent) -> Result<(), Error> {
+        self.events.lock().unwrap().push(event);
+
+        // Posting fails once the thread has exited, and with it the event
+        // loop.
+        if unsafe { PostThreadMessageW(self.thread, UM_USER_EVENT, WPARAM(0), LPARAM(0)) }.as_bool()
+        {
+            Ok(())
+        } else {
+            Err(Error::ShuttingDown)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Clipboard {
+    /// Used to find a window to own the clipboard. Clipboards outlive the
+    /// shell only when they are kept by widgets after the event loop ends.
+    shell: Weak<Inner>,
+}
+
+impl Clipboard {
+    pub fn text(&self) -> Option<String> {
+        unsafe {
+            if !OpenClipboard(HWND::default()).as_bool() {
+                return None;
+            }
+
+            let text = GetClipboardData(CF_UNICODETEXT.0).ok().and_then(|handle| {
+                let data = GlobalLock(handle.0).cast::<u16>();
+                if data.is_null() {
+                    return None;
+                }
+
+                let len = (0..).take_while(|&i| *data.add(i) != 0).count();
+                let text = String::from_utf16_lossy(std::slice::from_raw_parts(data, len));
+                GlobalUnlock(handle.0);
+                Some(text.replace("\r\n", "\n"))
+            });
+
+            CloseClipboard();
+            text
+        }
+    }
+
+    pub fn set_text(&self, text: &str) {
+        let shell = match self.shell.upgrade() {
+            Some(shell) => shell,
+            None => return,
+        };
+
+        // The clipboard must be owned by a window for `SetClipboardData` to
+        // succeed.
+        let owner = match shell.windows.borrow().first() {
+            Some(hwnd) => *hwnd,
+            None => return,
+        };
+
+        let text: Vec<u16> = text
+            .replace("\r\n", "\n")
+            .replace('\n', "\r\n")
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect();
+
+        unsafe {
+            if !OpenClipboard(owner).as_bool() {
+                return;
+            }
+
+            EmptyClipboard();
+
+            let memory = GlobalAlloc(GMEM_MOVEABLE, text.len() * std::mem::size_of::<u16>());
+            let data = GlobalLock(memory).cast::<u16>();
+            if !data.is_null() {
+                std::ptr::copy_nonoverlapping(text.as_ptr(), data, text.len());
+                GlobalUnlock(memory);
+
+                // The clipboard takes ownership of the memory only if the
+                // data was set.
+                if SetClipboardData(CF_UNICODETEXT.0, HANDLE(memory)).is_err() {
+                    GlobalFree(memory);
+                }
+            }
+
+            CloseClipboard();
+        }
+    }
+}
+
 type InnerPtr = *const Inner;
 
+/// What a fullscreen window looked like before it became fullscreen, so that
+/// it can be restored.
+#[derive(Clone, Copy)]
+struct Fullscreen {
+    hwnd: HWND,
+    /// `FullscreenMode::Windowed` while the window waits to be restored.
+    mode: FullscreenMode,
+    placement: WINDOWPLACEMENT,
+    style: WINDOW_STYLE,
+}
+
+/// An icon added with `Shell::add_tray_icon`.
+struct Tray {
+    id: u64,
+    hwnd: HWND,
+    icon: HICON,
+    menu: Option<Menu>,
+}
+
+/// What assistive technology knows of a window, once it has been described
+/// with `Shell::update_accessibility`.
+struct Accessibility {
+    hwnd: HWND,
+    /// The latest tree, until assistive technology first asks for the window
+    /// and the adapter is created from it.
+    tree: Option<accesskit::TreeUpdate>,
+    adapter: Option<Rc<accesskit_windows::Adapter>>,
+    /// The requests that assistive technology made of the window, until they
+    /// are taken. The adapter may make them from another thread.
+    requests: Arc<Mutex<Vec<accesskit::ActionRequest>>>,
+}
+
+/// Queues the requests that assistive technology makes of a window, and wakes
+/// the window to report them.
+struct AccessRequests {
+    hwnd: HWND,
+    requests: Arc<Mutex<Vec<accesskit::ActionRequest>>>,
+}
+
+impl accesskit::ActionHandler for AccessRequests {
+    fn do_action(&mut self, request: accesskit::ActionRequest) {
+        self.requests.lock().unwrap().push(request);
+        unsafe { PostMessageW(self.hwnd, UM_ACCESS_REQUEST, WPARAM(0), LPARAM(0)) };
+    }
+}
+
 pub(super) struct Inner {
     hinstance: HINSTANCE,
-    init_event_buffer: RefCell<Vec<Event>>,
+    /// Events sent while the event callback couldn't receive them, either
+    /// before the event loop started or while the callback was running.
+    event_buffer: RefCell<Vec<Event>>,
     /// A simple array used to keep track of every currently open window.
     windows: RefCell<Vec<HWND>>,
+    /// The windows to repaint the next time the event loop runs.
+    redraw_requests: RefCell<Vec<HWND>>,
+    /// The cursor set for each window with [`super::Shell::set_cursor`].
+    /// Windows without one show the window class's arrow.
+    cursors: RefCell<Vec<(HWND, HCURSOR)>>,
+    /// Windows that hide the cursor while the mouse is over them.
+    hidden_cursors: RefCell<Vec<HWND>>,
+    /// The smallest and largest client extents of each window that has been
+    /// given size limits.
+    size_limits: RefCell<Vec<(HWND, PhysicalExtent, PhysicalExtent)>>,
+    /// The icon created for each window with
+    /// [`super::Shell::set_window_icon`]. It is destroyed when it is replaced
+    /// or the window is destroyed.
+    icons: RefCell<Vec<(HWND, HICON)>>,
+    /// The windows that are fullscreen, or are waiting to be restored.
+    fullscreen: RefCell<Vec<Fullscreen>>,
+    /// The window that confines the cursor and the area it is confined to, in
+    /// window coordinates. The area is converted to screen coordinates again
+    /// whenever the window moves or is activated.
+    cursor_clip: Cell<Option<(HWND, PhysicalRect)>>,
+    next_dialog: Cell<u64>,
+    next_timer: Cell<u64>,
+    /// The timers that stop after firing once. Windows timers repeat until
+    /// they are killed.
+    one_shot_timers: RefCell<Vec<(HWND, usize)>>,
+    next_tray: Cell<u64>,
+    tray_icons: RefCell<Vec<Tray>>,
+    /// The items in each window's menu bar.
+    menu_bars: RefCell<Vec<(HWND, MenuCommands)>>,
+    /// The files dropped on a window, while `Window::FilesDropped` is being
+    /// handled.
+    dropped_files: RefCell<Option<(HWND, Vec<PathBuf>)>>,
+    /// What assistive technology knows of each window that the application
+    /// has described.
+    accessibility: RefCell<Vec<Accessibility>>,
+    /// Shows that UI Automation was started before any window could be asked
+    /// for its accessibility tree.
+    uia_init: accesskit_windows::UiaInitMarker,
+    /// The files chosen in each dialog that has closed, until they are taken.
+    /// Dialogs run on their own threads.
+    #[allow(clippy::type_complexity)]
+    dialog_results: Arc<Mutex<Vec<(u64, Vec<PathBuf>)>>>,
+    /// The thread running the event loop, which proxies post to.
+    thread: u32,
+    /// The events sent by proxies, until they are taken.
+    user_events: Arc<Mutex<Vec<UserEvent>>>,
     is_shutting_down: Cell<bool>,
+    /// The first half of a character outside of the Basic Multilingual Plane,
+    /// which `WM_CHAR` delivers as two UTF-16 code units.
+    high_surrogate: Cell<Option<u16>>,
     event_mode: Cell<EventLoopControl>,
     #[allow(clippy::type_complexity)]
     event_callback:
         RefCell<Option<Box<dyn FnMut(Event, &dyn super::Shell, &mut EventLoopControl)>>>,
 }
 
+impl Inner {
+    /// The window's accessibility adapter, which is created from the latest
+    /// tree when assistive technology first asks for the window. `None` if
+    /// the application hasn't described the window yet.
+    fn access_adapter(&self, hwnd: HWND) -> Option<Rc<accesskit_windows::Adapter>> {
+        let mut accessibility = self.accessibility.borrow_mut();
+        let access = accessibility.iter_mut().find(|a| a.hwnd == hwnd)?;
+
+        if access.adapter.is_none() {
+            let tree = access.tree.take()?;
+            let handler = AccessRequests {
+                hwnd,
+                requests: access.requests.clone(),
+            };
+            let is_focused = unsafe { GetForegroundWindow() } == hwnd;
+            access.adapter = Some(Rc::new(accesskit_windows::Adapter::new(
+                accesskit_windows::HWND(hwnd.0),
+                tree,
+                is_focused,
+                Box::new(handler),
+                self.uia_init,
+            )));
+        }
+
+        access.adapter.clone()
+    }
+
+    /// Shows the menu of a tray icon at the cursor, and waits for the user to
+    /// select an item or dismiss it.
+    fn show_tray_menu(&self, hwnd: HWND, tray: u64) -> Option<MenuItemId> {
+        let menu = self
+            .tray_icons
+            .borrow()
+            .iter()
+            .find(|t| t.id == tray)
+            .and_then(|t| t.menu.clone())?;
+
+        let mut commands = MenuCommands::default();
+        let hmenu = create_menu(&menu, false, &mut commands);
+
+        // The menu only closes when the user clicks away from it if the
+        // window is in the foreground.
+        let mut cursor = POINT::default();
+        let command = unsafe {
+            GetCursorPos(&mut cursor);
+            SetForegroundWindow(hwnd);
+            let command = TrackPopupMenu(
+                hmenu,
+                TPM_RETURNCMD | TPM_RIGHTBUTTON,
+                cursor.x,
+                cursor.y,
+                0,
+                hwnd,
+                std::ptr::null(),
+            );
+            DestroyMenu(hmenu);
+            command.0
+        };
+
+        commands.item(command)
+    }
+
+    /// Finds the item in the window's menu bar for a command from
+    /// `WM_COMMAND`.
+    fn menu_bar_item(&self, hwnd: HWND, command: i32) -> Option<MenuItemId> {
+        self.menu_bars
+            .borrow()
+            .iter()
+            .find(|(h, _)| *h == hwnd)
+            .and_then(|(_, commands)| commands.item(command))
+    }
+
+    /// Finds the item in the window's menu bar that the keys select.
+    fn accelerator(&self, hwnd: HWND, key: Key, modifiers: Modifiers) -> Option<MenuItemId> {
+        let accelerator = Accelerator::new(key, modifiers);
+        self.menu_bars
+            .borrow()
+            .iter()
+            .find(|(h, _)| *h == hwnd)
+            .and_then(|(_, commands)| {
+                commands
+                    .accelerators
+                    .iter()
+                    .find(|(a, _)| *a == accelerator)
+                    .map(|(_, item)| *item)
+            })
+    }
+
+    fn is_fullscreen(&self, hwnd: HWND) -> bool {
+        self.fullscreen.borrow().iter().any(|f| f.hwnd == hwnd)
+    }
+
+    /// The smallest and largest client extents that the window can be resized
+    /// to.
+    fn size_limits(&self, hwnd: HWND) -> (PhysicalExtent, PhysicalExtent) {
+        self.size_limits
+            .borrow()
+            .iter()
+            .find(|(h, _, _)| *h == hwnd)
+            .map_or(
+                (PhysicalExtent::default(), PhysicalExtent::MAX),
+                |(_, min, max)| (*min, *max),
+            )
+    }
+
+    /// Shows a file dialog on its own thread, since the dialog runs its own
+    /// message loop until it closes. Events for other windows are still
+    /// delivered by the event loop, and the dialog posts
+    /// `UM_FILE_DIALOG_CLOSED` to the owner when it closes.
+    fn show_file_dialog(
+        &self,
+        window: super::WindowId,
+        dialog: &FileDialog,
+        save: bool,
+    ) -> DialogId {
+        let id = self.next_dialog.get();
+        self.next_dialog.set(id + 1);
+
+        let owner = window.0.hwnd.0;
+        let results = self.dialog_results.clone();
+        let filter = filter_string(&dialog.filters);
+        let default_extension: Vec<u16> = dialog
+            .filters
+            .first()
+            .and_then(|filter| filter.extensions.first())
+            .map(|extension| extension.encode_utf16().chain(Some(0)).collect())
+            .unwrap_or_default();
+        let title: Option<Vec<u16>> = dialog
+            .title
+            .as_ref()
+            .map(|title| title.encode_utf16().chain(Some(0)).collect());
+        let file_name: Vec<u16> = dialog
+            .file_name
+            .as_deref()
+            .unwrap_or_default()
+            .encode_utf16()
+            .collect();
+        let multiple = dialog.multiple && !save;
+
+        std::thread::spawn(move || {
+            // The dialog uses COM to show the folders, including for the
+            // shell extensions that it loads.
+            let _ = unsafe { CoInitializeEx(std::ptr::null(), COINIT_APARTMENTTHREADED) };
+
+            // Large enough for the paths of many files when several can be
+            // chosen.
+            let mut buffer = vec![0_u16; 32 * 1024];
+            let len = file_name.len().min(buffer.len() - 1);
+            buffer[..len].copy_from_slice(&file_name[..len]);
+
+            let mut flags = OFN_EXPLORER | OFN_NOCHANGEDIR | OFN_PATHMUSTEXIST;
+            if save {
+                flags |= OFN_OVERWRITEPROMPT;
+            } else {
+                flags |= OFN_FILEMUSTEXIST;
+            }
+            if multiple {
+                flags |= OFN_ALLOWMULTISELECT;
+            }
+
+            let pointer_to = |string: &[u16]| {
+                if string.is_empty() {
+                    PCWSTR::null()
+                } else {
+                    PCWSTR(string.as_ptr())
+                }
+            };
+
+            let mut params = OPENFILENAMEW {
+                lStructSize: std::mem::size_of::<OPENFILENAMEW>() as u32,
+                hwndOwner: HWND(owner),
+                lpstrFilter: pointer_to(&filter),
+                lpstrFile: PWSTR(buffer.as_mut_ptr()),
+                nMaxFile: buffer.len() as u32,
+                lpstrTitle: pointer_to(title.as_deref().unwrap_or_default()),
+                lpstrDefExt: pointer_to(&default_extension),
+                Flags: flags,
+                ..Default::default()
+            };
+
+            let chosen = unsafe {
+                if save {
+                    GetSaveFileNameW(&mut params)
+                } else {
+                    GetOpenFileNameW(&mut params)
+                }
+            };
+
+            let paths = if chosen.as_bool() {
+                file_names(&buffer)
+            } else {
+                Vec::new()
+            };
+
+            results.lock().unwrap().push((id, paths));
+            unsafe {
+                PostMessageW(
+                    HWND(owner),
+                    UM_FILE_DIALOG_CLOSED,
+                    WPARAM(id as usize),
+                    LPARAM(0),
+                );
+            }
+        });
+
+        DialogId(id)
+    }
+}
+
 impl super::Shell for Rc<Inner> {
     fn create_window(&self, config: &WindowConfig) -> Result<super::WindowId, Error> {
         let hinstance = if self.is_shutting_down.get() {
@@ -244,12 +974,29 @@ impl super::Shell for Rc<Inner> {
             (CW_USEDEFAULT, CW_USEDEFAULT)
         };
 
+        let mut ex_style = WINDOW_EX_STYLE::default();
+        if config.always_on_top {
+            ex_style |= WS_EX_TOPMOST;
+        }
+        if config.tool_window {
+            ex_style |= WS_EX_TOOLWINDOW;
+        }
+        if config.transparent {
+            ex_style |= WS_EX_LAYERED;
+        }
+
+        let style = if config.transparent {
+            WS_POPUP
+        } else {
+            window_style(config.resizable)
+        };
+
         let hwnd = unsafe {
             CreateWindowExW(
-                WINDOW_EX_STYLE::default(),
+                ex_style,
                 PCWSTR(WNDCLASS_NAME.as_ptr()),
                 PCWSTR(os_title.as_ptr()),
-                WS_OVERLAPPEDWINDOW,
+                style,
                 CW_USEDEFAULT,
                 CW_USEDEFAULT,
                 width,
@@ -262,9 +1009,44 @@ impl super::Shell for Rc<Inner> {
         };
 
         self.windows.borrow_mut().push(hwnd);
-        unsafe { ShowWindow(hwnd, SW_SHOW) };
+        let window = super::WindowId(WindowId { hwnd });
+
+        // Layered windows are invisible until their attributes are set.
+        // Extending the frame over the whole window makes the compositor
+        // blend it with what is behind it using its per-pixel alpha.
+        if config.transparent {
+            unsafe {
+                SetLayeredWindowAttributes(hwnd, 0, 255, LWA_ALPHA);
+                let _ = DwmExtendFrameIntoClientArea(
+                    hwnd,
+                    &MARGINS {
+                        cxLeftWidth: -1,
+                        cxRightWidth: -1,
+                        cyTopHeight: -1,
+                        cyBottomHeight: -1,
+                    },
+                );
+            }
+        }
+
+        if config.min_extent.is_some() || config.max_extent.is_some() {
+            self.set_size_limits(window, config.min_extent, config.max_extent);
+        }
+
+        if config.icon.is_some() {
+            self.set_window_icon(window, config.icon);
+        }
+
+        if config.menu_bar.is_some() {
+            self.set_menu_bar(window, config.menu_bar);
+        }
 
-        Ok(super::WindowId(WindowId { hwnd }))
+        unsafe {
+            DragAcceptFiles(hwnd, true);
+            ShowWindow(hwnd, SW_SHOW);
+        }
+
+        Ok(window)
     }
 
     fn destroy_window(&self, window: super::WindowId) {
@@ -279,45 +1061,1142 @@ impl super::Shell for Rc<Inner> {
         unsafe { ShowWindow(window.0.hwnd, SW_HIDE) };
     }
 
-    fn hwnd(&self, window: super::WindowId) -> windows::Win32::Foundation::HWND {
-        window.0.hwnd
+    fn request_redraw(&self, window: super::WindowId) {
+        let mut requests = self.redraw_requests.borrow_mut();
+        if !requests.contains(&window.0.hwnd) {
+            requests.push(window.0.hwnd);
+        }
     }
-}
 
-#[inline]
-fn dispatch(shell: &Rc<Inner>, events: impl IntoIterator<Item = Event>) {
-    let mut cb = shell.event_callback.borrow_mut();
+    fn add_tray_icon(&self, window: super::WindowId, icon: &TrayIcon) -> TrayIconId {
+        let id = self.next_tray.get();
+        self.next_tray.set(id + 1);
 
-    // If we don't have a callback yet, the event was sent before
-    // run_event_loop() was called. This happens when windows are created before
-    // the event loop is run.
-    if let Some(callback) = cb.as_mut() {
-        let mut ctrl = EventLoopControl::Poll;
-        for event in events {
-            callback(event, shell, &mut ctrl);
-            shell.event_mode.set(ctrl);
+        let tray = Tray {
+            id,
+            hwnd: window.0.hwnd,
+            icon: create_icon(self.hinstance, icon.icon).unwrap_or_default(),
+            menu: icon.menu.cloned(),
+        };
 
-            if ctrl == EventLoopControl::Exit {
-                // std::mem::drop(cb);
-                // clean_exit(shell);
-                unsafe { PostQuitMessage(0) };
+        let data = notify_icon_data(&tray, icon.tooltip);
+        unsafe { Shell_NotifyIconW(NIM_ADD, &data) };
+        self.tray_icons.borrow_mut().push(tray);
+
+        TrayIconId(id)
+    }
+
+    fn update_tray_icon(&self, tray: TrayIconId, icon: &TrayIcon) {
+        let mut tray_icons = self.tray_icons.borrow_mut();
+        if let Some(tray) = tray_icons.iter_mut().find(|t| t.id == tray.0) {
+            let old_icon = tray.icon;
+            tray.icon = create_icon(self.hinstance, icon.icon).unwrap_or_default();
+            tray.menu = icon.menu.cloned();
+
+            let data = notify_icon_data(tray, icon.tooltip);
+            unsafe {
+                Shell_NotifyIconW(NIM_MODIFY, &data);
+                DestroyIcon(old_icon);
             }
         }
-    } else {
-        shell.init_event_buffer.borrow_mut().extend(events);
     }
-}
 
-fn clean_exit(shell: &Rc<Inner>) -> ! {
-    // Cleanly shut down the event loop by disabling window creation and
-    // destroying any windows that remain.
-    shell.is_shutting_down.set(true);
+    fn set_menu_bar(&self, window: super::WindowId, menu: Option<&Menu>) {
+        let hwnd = window.0.hwnd;
+        let mut menu_bars = self.menu_bars.borrow_mut();
+        menu_bars.retain(|(h, _)| *h != hwnd);
+
+        let hmenu = menu.map_or(HMENU::default(), |menu| {
+            let mut commands = MenuCommands::default();
+            let hmenu = create_menu(menu, true, &mut commands);
+            menu_bars.push((hwnd, commands));
+            hmenu
+        });
+
+        // The borrow must end before the menu changes, since that resizes the
+        // window's client area.
+        std::mem::drop(menu_bars);
+
+        // Menus that belong to a window are destroyed with it, but replaced
+        // menus are not.
+        unsafe {
+            let old = GetMenu(hwnd);
+            SetMenu(hwnd, hmenu);
+            if old.0 != 0 {
+                DestroyMenu(old);
+            }
+        }
+    }
 
-    let mut cb = shell.event_callback.borrow_mut();
+    fn remove_tray_icon(&self, tray: TrayIconId) {
+        let mut tray_icons = self.tray_icons.borrow_mut();
+        if let Some(index) = tray_icons.iter().position(|t| t.id == tray.0) {
+            delete_tray_icon(&tray_icons.swap_remove(index));
+        }
+    }
 
-    // If we don't have a callback yet, the event was sent before
-    // run_event_loop() was called. This happens when windows are created before
-    // the event loop is run.
+    fn set_title(&self, window: super::WindowId, title: &str) {
+        let title: Vec<u16> = title.encode_utf16().chain(Some(0)).collect();
+        unsafe { SetWindowTextW(window.0.hwnd, PCWSTR(title.as_ptr())) };
+    }
+
+    fn set_extent(&self, window: super::WindowId, extent: PhysicalExtent) {
+        let hwnd = window.0.hwnd;
+
+        if self.is_fullscreen(hwnd) {
+            return;
+        }
+
+        // The system sizes maximized and minimized windows.
+        if unsafe { IsZoomed(hwnd).as_bool() || IsIconic(hwnd).as_bool() } {
+            unsafe { ShowWindow(hwnd, SW_RESTORE) };
+        }
+
+        let (min, max) = self.size_limits(hwnd);
+        let (width, height) = window_size(hwnd, extent.clamp(min, max));
+
+        unsafe {
+            SetWindowPos(
+                hwnd,
+                None,
+                0,
+                0,
+                width,
+                height,
+                SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE,
+            )
+        };
+    }
+
+    fn set_position(&self, window: super::WindowId, position: PhysicalPoint) {
+        let hwnd = window.0.hwnd;
+
+        if self.is_fullscreen(hwnd) {
+            return;
+        }
+
+        unsafe {
+            SetWindowPos(
+                hwnd,
+                None,
+                position.x.0.into(),
+                position.y.0.into(),
+                0,
+                0,
+                SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+            )
+        };
+    }
+
+    fn minimize(&self, window: super::WindowId) {
+        unsafe { ShowWindow(window.0.hwnd, SW_MINIMIZE) };
+    }
+
+    fn maximize(&self, window: super::WindowId) {
+        if !self.is_fullscreen(window.0.hwnd) {
+            unsafe { ShowWindow(window.0.hwnd, SW_MAXIMIZE) };
+        }
+    }
+
+    fn restore(&self, window: super::WindowId) {
+        unsafe { ShowWindow(window.0.hwnd, SW_RESTORE) };
+    }
+
+    fn window_placement(&self, window: super::WindowId) -> WindowPlacement {
+        let hwnd = window.0.hwnd;
+
+        // Fullscreen windows return to where they were before.
+        let saved = self
+            .fullscreen
+            .borrow()
+            .iter()
+            .find(|f| f.hwnd == hwnd)
+            .map(|f| f.placement);
+
+        let placement = match saved {
+            Some(placement) => placement,
+            None => {
+                let mut placement = WINDOWPLACEMENT {
+                    length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+                    ..Default::default()
+                };
+                unsafe { GetWindowPlacement(hwnd, &mut placement) };
+                placement
+            }
+        };
+
+        let monitor = monitor_info(unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) });
+        let (dx, dy) = workspace_offset(hwnd, &monitor);
+
+        let RECT {
+            left,
+            top,
+            right,
+            bottom,
+        } = placement.rcNormalPosition;
+
+        let maximized = placement.showCmd == SW_SHOWMAXIMIZED
+            || (placement.showCmd == SW_SHOWMINIMIZED
+                && placement.flags.0 & WPF_RESTORETOMAXIMIZED.0 != 0);
+
+        WindowPlacement {
+            position: PhysicalPoint::new((left + dx) as i16, (top + dy) as i16),
+            extent: PhysicalExtent {
+                width: PhysicalPx((right - left) as i16),
+                height: PhysicalPx((bottom - top) as i16),
+            },
+            maximized,
+            monitor: to_rect(monitor.rcMonitor),
+        }
+    }
+
+    fn restore_placement(&self, window: super::WindowId, placement: &WindowPlacement) {
+        let hwnd = window.0.hwnd;
+
+        let mut left = i32::from(placement.position.x.0);
+        let mut top = i32::from(placement.position.y.0);
+        let width = i32::from(placement.extent.width.0);
+        let height = i32::from(placement.extent.height.0);
+
+        let rect = RECT {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        };
+        let monitor = monitor_info(unsafe { MonitorFromRect(&rect, MONITOR_DEFAULTTONEAREST) });
+
+        // The monitor was disconnected or rearranged, so the window may not
+        // be visible where it was.
+        if to_rect(monitor.rcMonitor) != placement.monitor {
+            let work = monitor.rcWork;
+            left = (work.left + (work.right - work.left - width) / 2).max(work.left);
+            top = (work.top + (work.bottom - work.top - height) / 2).max(work.top);
+        }
+
+        let (dx, dy) = workspace_offset(hwnd, &monitor);
+
+        let show_cmd = if placement.maximized {
+            SW_SHOWMAXIMIZED
+        } else {
+            SW_SHOWNORMAL
+        };
+
+        let new_placement = WINDOWPLACEMENT {
+            length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+            showCmd: show_cmd,
+            rcNormalPosition: RECT {
+                left: left - dx,
+                top: top - dy,
+                right: left - dx + width,
+                bottom: top - dy + height,
+            },
+            ..Default::default()
+        };
+
+        if let Some(entry) = self
+            .fullscreen
+            .borrow_mut()
+            .iter_mut()
+            .find(|f| f.hwnd == hwnd)
+        {
+            entry.placement = new_placement;
+            return;
+        }
+
+        unsafe { SetWindowPlacement(hwnd, &new_placement) };
+    }
+
+    fn set_size_limits(
+        &self,
+        window: super::WindowId,
+        min: Option<PhysicalExtent>,
+        max: Option<PhysicalExtent>,
+    ) {
+        let hwnd = window.0.hwnd;
+        let mut limits = self.size_limits.borrow_mut();
+        limits.retain(|(h, _, _)| *h != hwnd);
+
+        if min.is_some() || max.is_some() {
+            limits.push((
+                hwnd,
+                min.unwrap_or_default(),
+                max.unwrap_or(PhysicalExtent::MAX),
+            ));
+        }
+
+        unsafe { PostMessageW(hwnd, UM_UPDATE_FRAME, WPARAM(0), LPARAM(0)) };
+    }
+
+    fn set_resizable(&self, window: super::WindowId, resizable: bool) {
+        let hwnd = window.0.hwnd;
+
+        if ex_style(hwnd).0 & WS_EX_LAYERED.0 != 0 {
+            return;
+        }
+
+        // Fullscreen windows don't have borders, so the style is applied once
+        // the window is restored.
+        if let Some(fullscreen) = self
+            .fullscreen
+            .borrow_mut()
+            .iter_mut()
+            .find(|f| f.hwnd == hwnd)
+        {
+            fullscreen.style = window_style(resizable);
+            return;
+        }
+
+        unsafe {
+            SetWindowLongPtrW(hwnd, GWL_STYLE, window_style(resizable).0 as isize);
+            PostMessageW(hwnd, UM_UPDATE_FRAME, WPARAM(0), LPARAM(0));
+        }
+    }
+
+    fn set_always_on_top(&self, window: super::WindowId, always_on_top: bool) {
+        let insert_after = if always_on_top {
+            HWND_TOPMOST
+        } else {
+            HWND_NOTOPMOST
+        };
+
+        unsafe {
+            SetWindowPos(
+                window.0.hwnd,
+                insert_after,
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+            )
+        };
+    }
+
+    fn fullscreen(&self, window: super::WindowId) -> FullscreenMode {
+        self.fullscreen
+            .borrow()
+            .iter()
+            .find(|f| f.hwnd == window.0.hwnd)
+            .map_or(FullscreenMode::Windowed, |f| f.mode)
+    }
+
+    fn set_fullscreen(&self, window: super::WindowId, mode: FullscreenMode) {
+        let hwnd = window.0.hwnd;
+        let mut fullscreen = self.fullscreen.borrow_mut();
+
+        if let Some(entry) = fullscreen.iter_mut().find(|f| f.hwnd == hwnd) {
+            entry.mode = mode;
+        } else if mode != FullscreenMode::Windowed {
+            let mut placement = WINDOWPLACEMENT {
+                length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+                ..Default::default()
+            };
+
+            unsafe { GetWindowPlacement(hwnd, &mut placement) };
+            let style = WINDOW_STYLE(unsafe { GetWindowLongPtrW(hwnd, GWL_STYLE) } as u32);
+
+            fullscreen.push(Fullscreen {
+                hwnd,
+                mode,
+                placement,
+                style,
+            });
+        } else {
+            return;
+        }
+
+        unsafe { PostMessageW(hwnd, UM_UPDATE_FRAME, WPARAM(0), LPARAM(0)) };
+    }
+
+    fn set_window_icon(&self, window: super::WindowId, icon: Option<&PixelBuffer>) {
+        let hwnd = window.0.hwnd;
+        let hicon = icon
+            .and_then(|icon| create_icon(self.hinstance, icon))
+            .unwrap_or_default();
+
+        // The same icon is used for both sizes, and scaled to fit.
+        unsafe {
+            SendMessageW(hwnd, WM_SETICON, WPARAM(ICON_BIG as usize), LPARAM(hicon.0));
+            SendMessageW(
+                hwnd,
+                WM_SETICON,
+                WPARAM(ICON_SMALL as usize),
+                LPARAM(hicon.0),
+            );
+        }
+
+        let mut icons = self.icons.borrow_mut();
+        if let Some(index) = icons.iter().position(|(h, _)| *h == hwnd) {
+            let (_, old) = icons.swap_remove(index);
+            unsafe { DestroyIcon(old) };
+        }
+
+        if hicon.0 != 0 {
+            icons.push((hwnd, hicon));
+        }
+    }
+
+    fn scale_factor(&self, window: super::WindowId) -> ScaleFactor {
+        ScaleFactor::from_dpi(unsafe { GetDpiForWindow(window.0.hwnd) })
+    }
+
+    fn primary_scale_factor(&self) -> ScaleFactor {
+        ScaleFactor::from_dpi(unsafe { GetDpiForSystem() })
+    }
+
+    fn refresh_rate(&self, window: super::WindowId) -> Option<f32> {
+        let monitor = unsafe { MonitorFromWindow(window.0.hwnd, MONITOR_DEFAULTTONEAREST) };
+
+        let mut info = MONITORINFOEXW {
+            monitorInfo: MONITORINFO {
+                cbSize: std::mem::size_of::<MONITORINFOEXW>() as u32,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        if !unsafe { GetMonitorInfoW(monitor, std::ptr::addr_of_mut!(info).cast()) }.as_bool() {
+            return None;
+        }
+
+        let mut mode = DEVMODEW {
+            dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+            ..Default::default()
+        };
+        let found = unsafe {
+            EnumDisplaySettingsW(
+                PCWSTR(info.szDevice.as_ptr()),
+                ENUM_CURRENT_SETTINGS,
+                &mut mode,
+            )
+        };
+
+        // Frequencies of 0 and 1 stand for the hardware's default.
+        match mode.dmDisplayFrequency {
+            frequency if found.as_bool() && frequency > 1 => Some(frequency as f32),
+            _ => None,
+        }
+    }
+
+    fn prefers_subpixel_text(&self) -> bool {
+        let mut enabled = BOOL(0);
+        let mut smoothing_type = 0u32;
+
+        // Font smoothing must be enabled, and set to ClearType rather than
+        // grayscale.
+        unsafe {
+            SystemParametersInfoW(
+                SPI_GETFONTSMOOTHING,
+                0,
+                (&mut enabled as *mut BOOL).cast(),
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            )
+            .as_bool()
+                && enabled.as_bool()
+                && SystemParametersInfoW(
+                    SPI_GETFONTSMOOTHINGTYPE,
+                    0,
+                    (&mut smoothing_type as *mut u32).cast(),
+                    SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+                )
+                .as_bool()
+                && smoothing_type == FE_FONTSMOOTHINGCLEARTYPE
+        }
+    }
+
+    fn features(&self) -> super::ShellFeatures {
+        super::ShellFeatures {
+            file_drop: true,
+            input_method: true,
+            raw_mouse_input: true,
+            per_monitor_scale: true,
+            accessibility: true,
+        }
+    }
+
+    fn clipboard(&self) -> super::Clipboard {
+        super::Clipboard(Clipboard {
+            shell: Rc::downgrade(self),
+        })
+    }
+
+    fn event_loop_proxy(&self) -> super::EventLoopProxy {
+        super::EventLoopProxy(EventLoopProxy {
+            thread: self.thread,
+            events: self.user_events.clone(),
+        })
+    }
+
+    fn take_user_events(&self) -> Vec<UserEvent> {
+        std::mem::take(&mut *self.user_events.lock().unwrap())
+    }
+
+    fn dropped_files(&self, window: super::WindowId) -> Vec<PathBuf> {
+        match &*self.dropped_files.borrow() {
+            Some((hwnd, paths)) if *hwnd == window.0.hwnd => paths.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn update_accessibility(&self, window: super::WindowId, update: accesskit::TreeUpdate) {
+        let hwnd = window.0.hwnd;
+        let adapter = {
+            let mut accessibility = self.accessibility.borrow_mut();
+            let index = match accessibility.iter().position(|a| a.hwnd == hwnd) {
+                Some(index) => index,
+                None => {
+                    accessibility.push(Accessibility {
+                        hwnd,
+                        tree: None,
+                        adapter: None,
+                        requests: Arc::new(Mutex::new(Vec::new())),
+                    });
+                    accessibility.len() - 1
+                }
+            };
+
+            // Until assistive technology asks for the window, only the latest
+            // tree is kept.
+            match &accessibility[index].adapter {
+                Some(adapter) => adapter.clone(),
+                None => {
+                    accessibility[index].tree = Some(update);
+                    return;
+                }
+            }
+        };
+
+        // Raising the events may send `WM_GETOBJECT` to the window, so
+        // nothing can be borrowed while they are.
+        adapter.update(update).raise();
+    }
+
+    fn take_access_requests(&self, window: super::WindowId) -> Vec<accesskit::ActionRequest> {
+        self.accessibility
+            .borrow()
+            .iter()
+            .find(|a| a.hwnd == window.0.hwnd)
+            .map(|a| std::mem::take(&mut *a.requests.lock().unwrap()))
+            .unwrap_or_default()
+    }
+
+    fn composition(&self, window: super::WindowId) -> Composition {
+        let hwnd = window.0.hwnd;
+        let himc = unsafe { ImmGetContext(hwnd) };
+        if himc.is_invalid() {
+            return Composition::default();
+        }
+
+        let composition =
+            composition_string(himc, GCS_COMPSTR).map_or_else(Composition::default, |text| {
+                // The caret is reported in UTF-16 code units.
+                let caret = unsafe { ImmGetCompositionStringW(himc, GCS_CURSORPOS, null_mut(), 0) };
+                let caret = usize::try_from(caret).unwrap_or(text.len()).min(text.len());
+                Composition {
+                    caret: String::from_utf16_lossy(&text[..caret]).len(),
+                    text: String::from_utf16_lossy(&text),
+                }
+            });
+
+        unsafe { ImmReleaseContext(hwnd, himc) };
+        composition
+    }
+
+    fn set_ime_area(&self, window: super::WindowId, area: PhysicalRect) {
+        let hwnd = window.0.hwnd;
+        let himc = unsafe { ImmGetContext(hwnd) };
+        if himc.is_invalid() {
+            return;
+        }
+
+        let rect = RECT {
+            left: area.left.0.into(),
+            top: area.top.0.into(),
+            right: area.right.0.into(),
+            bottom: area.bottom.0.into(),
+        };
+
+        // The composed text is drawn by the widget, but some input methods
+        // still place their windows relative to the composition window.
+        let composition = COMPOSITIONFORM {
+            dwStyle: CFS_POINT,
+            ptCurrentPos: POINT {
+                x: rect.left,
+                y: rect.top,
+            },
+            rcArea: RECT::default(),
+        };
+
+        // Place the candidate window below the area, moving it elsewhere if
+        // it would cover the area.
+        let candidate = CANDIDATEFORM {
+            dwIndex: 0,
+            dwStyle: CFS_EXCLUDE,
+            ptCurrentPos: POINT {
+                x: rect.left,
+                y: rect.bottom,
+            },
+            rcArea: rect,
+        };
+
+        unsafe {
+            ImmSetCompositionWindow(himc, &composition);
+            ImmSetCandidateWindow(himc, &candidate);
+            ImmReleaseContext(hwnd, himc);
+        }
+    }
+
+    fn set_raw_mouse_input(&self, window: super::WindowId, enabled: bool) {
+        // The mouse in the generic desktop controls usage page. Removing a
+        // registration requires that it not name a window.
+        let device = RAWINPUTDEVICE {
+            usUsagePage: 0x01,
+            usUsage: 0x02,
+            dwFlags: if enabled {
+                RAWINPUTDEVICE_FLAGS(0)
+            } else {
+                RIDEV_REMOVE
+            },
+            hwndTarget: if enabled {
+                window.0.hwnd
+            } else {
+                HWND::default()
+            },
+        };
+
+        unsafe {
+            RegisterRawInputDevices(&[device], std::mem::size_of::<RAWINPUTDEVICE>() as u32);
+        }
+    }
+
+    fn set_cursor(&self, window: super::WindowId, icon: CursorIcon) {
+        let hwnd = window.0.hwnd;
+        let name = match icon {
+            CursorIcon::Arrow => IDC_ARROW,
+            CursorIcon::Text => IDC_IBEAM,
+            CursorIcon::Hand => IDC_HAND,
+            CursorIcon::Move => IDC_SIZEALL,
+            CursorIcon::ResizeHorizontal => IDC_SIZEWE,
+            CursorIcon::ResizeVertical => IDC_SIZENS,
+            CursorIcon::NotAllowed => IDC_NO,
+            CursorIcon::Wait => IDC_WAIT,
+        };
+
+        // System cursors are shared, and don't need to be destroyed.
+        let cursor = match unsafe { LoadCursorW(None, name) } {
+            Ok(cursor) => cursor,
+            Err(_) => return,
+        };
+
+        let mut cursors = self.cursors.borrow_mut();
+        match cursors.iter_mut().find(|(h, _)| *h == hwnd) {
+            Some((_, current)) => *current = cursor,
+            None => cursors.push((hwnd, cursor)),
+        }
+
+        // The system only asks for the cursor again once the mouse moves, so
+        // it is changed immediately as well. Widgets only change the cursor
+        // while it is over their window.
+        if !self.hidden_cursors.borrow().contains(&hwnd) {
+            unsafe { SetCursor(cursor) };
+        }
+    }
+
+    fn set_cursor_visible(&self, window: super::WindowId, visible: bool) {
+        let hwnd = window.0.hwnd;
+        let mut hidden = self.hidden_cursors.borrow_mut();
+        hidden.retain(|h| *h != hwnd);
+
+        if visible {
+            let cursor = self
+                .cursors
+                .borrow()
+                .iter()
+                .find(|(h, _)| *h == hwnd)
+                .map(|(_, cursor)| *cursor);
+            match cursor {
+                Some(cursor) => unsafe { SetCursor(cursor) },
+                None => unsafe { SetCursor(LoadCursorW(None, IDC_ARROW).unwrap_or_default()) },
+            };
+        } else {
+            hidden.push(hwnd);
+            unsafe { SetCursor(HCURSOR::default()) };
+        }
+    }
+
+    fn set_mouse_capture(&self, window: super::WindowId, captured: bool) {
+        let hwnd = window.0.hwnd;
+        unsafe {
+            if captured {
+                SetCapture(hwnd);
+            } else if GetCapture() == hwnd {
+                ReleaseCapture();
+            }
+        }
+    }
+
+    fn confine_cursor(&self, window: super::WindowId, area: Option<PhysicalRect>) {
+        let hwnd = window.0.hwnd;
+        match area {
+            Some(area) => {
+                self.cursor_clip.set(Some((hwnd, area)));
+                clip_cursor(hwnd, area);
+            }
+            None => {
+                if matches!(self.cursor_clip.get(), Some((h, _)) if h == hwnd) {
+                    self.cursor_clip.set(None);
+                    unsafe { ClipCursor(std::ptr::null()) };
+                }
+            }
+        }
+    }
+
+    fn open_file_dialog(&self, window: super::WindowId, dialog: &FileDialog) -> DialogId {
+        self.show_file_dialog(window, dialog, false)
+    }
+
+    fn save_file_dialog(&self, window: super::WindowId, dialog: &FileDialog) -> DialogId {
+        self.show_file_dialog(window, dialog, true)
+    }
+
+    fn take_dialog_result(&self, dialog: DialogId) -> Vec<PathBuf> {
+        let mut results = self.dialog_results.lock().unwrap();
+        match results.iter().position(|(id, _)| *id == dialog.0) {
+            Some(index) => results.swap_remove(index).1,
+            None => Vec::new(),
+        }
+    }
+
+    fn set_timer(&self, window: super::WindowId, duration: Duration, repeating: bool) -> TimerId {
+        let hwnd = window.0.hwnd;
+        let id = self.next_timer.get();
+        self.next_timer.set(id + 1);
+
+        let millis = u32::try_from(duration.as_millis()).unwrap_or(u32::MAX);
+        unsafe { SetTimer(hwnd, id as usize, millis, None) };
+
+        if !repeating {
+            self.one_shot_timers.borrow_mut().push((hwnd, id as usize));
+        }
+
+        TimerId(id)
+    }
+
+    fn cancel_timer(&self, window: super::WindowId, timer: TimerId) {
+        let hwnd = window.0.hwnd;
+        let id = timer.0 as usize;
+        self.one_shot_timers
+            .borrow_mut()
+            .retain(|t| *t != (hwnd, id));
+        unsafe { KillTimer(hwnd, id) };
+    }
+
+    fn hwnd(&self, window: super::WindowId) -> windows::Win32::Foundation::HWND {
+        window.0.hwnd
+    }
+}
+
+pub fn show_error_dialog(title: &str, message: &str) {
+    let title: Vec<u16> = title.encode_utf16().chain(std::iter::once(0)).collect();
+    let message: Vec<u16> = message
+        .replace("\r\n", "\n")
+        .replace('\n', "\r\n")
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+
+    unsafe {
+        MessageBoxW(
+            HWND(0),
+            PCWSTR(message.as_ptr()),
+            PCWSTR(title.as_ptr()),
+            MB_OK | MB_ICONERROR | MB_TASKMODAL | MB_SETFOREGROUND,
+        );
+    }
+}
+
+/// Builds the list of filters for a file dialog, as pairs of names and
+/// patterns separated by nuls and ending with two. Empty if there are no
+/// filters, so that every file is shown.
+fn filter_string(filters: &[FileFilter]) -> Vec<u16> {
+    if filters.is_empty() {
+        return Vec::new();
+    }
+
+    let mut string = String::new();
+    for filter in filters {
+        let patterns: Vec<String> = filter
+            .extensions
+            .iter()
+            .map(|extension| format!("*.{extension}"))
+            .collect();
+        string.push_str(&filter.name);
+        string.push('\0');
+        string.push_str(&patterns.join(";"));
+        string.push('\0');
+    }
+    string.push('\0');
+    string.encode_utf16().collect()
+}
+
+/// Reads the files that a file dialog wrote to `buffer`. One file is written
+/// as its path, and several as their directory followed by their names, each
+/// ending with a nul and the last with two.
+fn file_names(buffer: &[u16]) -> Vec<PathBuf> {
+    let mut parts = buffer
+        .split(|c| *c == 0)
+        .take_while(|part| !part.is_empty())
+        .map(String::from_utf16_lossy);
+
+    let first = match parts.next() {
+        Some(first) => PathBuf::from(first),
+        None => return Vec::new(),
+    };
+
+    let names: Vec<PathBuf> = parts.map(|name| first.join(name)).collect();
+    if names.is_empty() {
+        vec![first]
+    } else {
+        names
+    }
+}
+
+/// The style of Fathom's windows. Windows that can't be resized don't have a
+/// resizable border or a maximize button.
+fn window_style(resizable: bool) -> WINDOW_STYLE {
+    if resizable {
+        WS_OVERLAPPEDWINDOW
+    } else {
+        WINDOW_STYLE(WS_OVERLAPPEDWINDOW.0 & !(WS_THICKFRAME.0 | WS_MAXIMIZEBOX.0))
+    }
+}
+
+/// Describes a tray icon to the taskbar. Its ID is truncated, which is
+/// harmless since IDs only need to be unique among the window's icons.
+fn notify_icon_data(tray: &Tray, tooltip: &str) -> NOTIFYICONDATAW {
+    let mut data = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: tray.hwnd,
+        uID: tray.id as u32,
+        uFlags: NIF_MESSAGE | NIF_ICON | NIF_TIP,
+        uCallbackMessage: UM_TRAY_ICON,
+        hIcon: tray.icon,
+        ..Default::default()
+    };
+
+    // The last code unit is left as the terminator.
+    let capacity = data.szTip.len() - 1;
+    for (dst, src) in data
+        .szTip
+        .iter_mut()
+        .zip(tooltip.encode_utf16().take(capacity))
+    {
+        *dst = src;
+    }
+
+    data
+}
+
+fn delete_tray_icon(tray: &Tray) {
+    let data = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: tray.hwnd,
+        uID: tray.id as u32,
+        ..Default::default()
+    };
+
+    unsafe {
+        Shell_NotifyIconW(NIM_DELETE, &data);
+        DestroyIcon(tray.icon);
+    }
+}
+
+/// The items of a native menu that can be selected, and the accelerators of
+/// those that are enabled.
+#[derive(Default)]
+struct MenuCommands {
+    /// The command of each item is its position plus one, since a command of
+    /// zero means that nothing was selected.
+    items: Vec<MenuItemId>,
+    accelerators: Vec<(Accelerator, MenuItemId)>,
+}
+
+impl MenuCommands {
+    /// Finds the item for a command from a menu made with `create_menu`.
+    fn item(&self, command: i32) -> Option<MenuItemId> {
+        let index = usize::try_from(command).ok()?.checked_sub(1)?;
+        self.items.get(index).copied()
+    }
+}
+
+/// Creates a native menu for `menu`, as a menu bar or a popup, and records
+/// its items in `commands`.
+fn create_menu(menu: &Menu, bar: bool, commands: &mut MenuCommands) -> HMENU {
+    let hmenu = if bar {
+        unsafe { CreateMenu() }
+    } else {
+        unsafe { CreatePopupMenu() }
+    }
+    .unwrap();
+
+    for item in &menu.items {
+        match item {
+            MenuItem::Action {
+                id,
+                label,
+                enabled,
+                checked,
+                accelerator,
+            } => {
+                commands.items.push(*id);
+
+                let mut flags = MF_STRING;
+                if !enabled {
+                    flags |= MF_GRAYED;
+                }
+                if *checked {
+                    flags |= MF_CHECKED;
+                }
+
+                // Text after a tab is aligned to the right of the menu.
+                let label = match accelerator {
+                    Some(accelerator) if *enabled => {
+                        commands.accelerators.push((*accelerator, *id));
+                        format!("{label}\t{accelerator}")
+                    }
+                    Some(accelerator) => format!("{label}\t{accelerator}"),
+                    None => label.clone(),
+                };
+
+                let label: Vec<u16> = label.encode_utf16().chain(Some(0)).collect();
+                unsafe { AppendMenuW(hmenu, flags, commands.items.len(), PCWSTR(label.as_ptr())) };
+            }
+            MenuItem::Separator => unsafe {
+                AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+            },
+            MenuItem::Submenu { label, menu } => {
+                let submenu = create_menu(menu, false, commands);
+                let label: Vec<u16> = label.encode_utf16().chain(Some(0)).collect();
+                unsafe { AppendMenuW(hmenu, MF_POPUP, submenu.0 as usize, PCWSTR(label.as_ptr())) };
+            }
+        }
+    }
+
+    hmenu
+}
+
+/// The window's extended style, such as whether it is layered.
+fn ex_style(hwnd: HWND) -> WINDOW_EX_STYLE {
+    WINDOW_EX_STYLE(unsafe { GetWindowLongPtrW(hwnd, GWL_EXSTYLE) } as u32)
+}
+
+/// The size of the whole window, including its borders and title bar, when
+/// its client area is `extent`.
+fn window_size(hwnd: HWND, extent: PhysicalExtent) -> (i32, i32) {
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: extent.width.0.into(),
+        bottom: extent.height.0.into(),
+    };
+
+    unsafe {
+        let style = WINDOW_STYLE(GetWindowLongPtrW(hwnd, GWL_STYLE) as u32);
+        AdjustWindowRectExForDpi(
+            &mut rect,
+            style,
+            GetMenu(hwnd).0 != 0,
+            ex_style(hwnd),
+            GetDpiForWindow(hwnd),
+        );
+    }
+
+    (rect.right - rect.left, rect.bottom - rect.top)
+}
+
+/// Creates an icon from an `RGB8` or `RGBA8` image, or returns `None` for
+/// other layouts.
+fn create_icon(hinstance: HINSTANCE, image: &PixelBuffer) -> Option<HICON> {
+    let bgra: Vec<u8> = match image.layout() {
+        Layout::RGBA8 => image
+            .bytes()
+            .chunks_exact(4)
+            .flat_map(|p| [p[2], p[1], p[0], p[3]])
+            .collect(),
+        Layout::RGB8 => image
+            .bytes()
+            .chunks_exact(3)
+            .flat_map(|p| [p[2], p[1], p[0], 255])
+            .collect(),
+        Layout::BC1 | Layout::BC7 => return None,
+    };
+
+    let Extent { width, height } = image.extent();
+
+    // The mask is ignored for 32-bit icons, but must still be provided. Each
+    // of its rows is padded to a multiple of 16 bits.
+    let mask = vec![0_u8; (width.0 as usize).div_ceil(16) * 2 * height.0 as usize];
+
+    unsafe {
+        CreateIcon(
+            hinstance,
+            width.0.into(),
+            height.0.into(),
+            1,
+            32,
+            mask.as_ptr(),
+            bgra.as_ptr(),
+        )
+    }
+    .ok()
+}
+
+fn monitor_info(monitor: HMONITOR) -> MONITORINFO {
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    unsafe { GetMonitorInfoW(monitor, &mut info) };
+    info
+}
+
+/// The offset from the workspace coordinates used by `WINDOWPLACEMENT` to
+/// screen coordinates. They differ when the taskbar is at the top or left of
+/// the monitor, except for tool windows.
+fn workspace_offset(hwnd: HWND, monitor: &MONITORINFO) -> (i32, i32) {
+    let ex_style = WINDOW_EX_STYLE(unsafe { GetWindowLongPtrW(hwnd, GWL_EXSTYLE) } as u32);
+
+    if ex_style.0 & WS_EX_TOOLWINDOW.0 != 0 {
+        (0, 0)
+    } else {
+        (
+            monitor.rcWork.left - monitor.rcMonitor.left,
+            monitor.rcWork.top - monitor.rcMonitor.top,
+        )
+    }
+}
+
+fn to_rect(rect: RECT) -> PhysicalRect {
+    PhysicalRect {
+        top: PhysicalPx(rect.top as i16),
+        left: PhysicalPx(rect.left as i16),
+        bottom: PhysicalPx(rect.bottom as i16),
+        right: PhysicalPx(rect.right as i16),
+    }
+}
+
+/// Removes the window's borders and resizes it to cover the monitor that it is
+/// on.
+fn cover_monitor(hwnd: HWND) {
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+
+    unsafe {
+        GetMonitorInfoW(MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST), &mut info);
+        SetWindowLongPtrW(hwnd, GWL_STYLE, (WS_POPUP | WS_VISIBLE).0 as isize);
+
+        let RECT {
+            left,
+            top,
+            right,
+            bottom,
+        } = info.rcMonitor;
+
+        SetWindowPos(
+            hwnd,
+            HWND_TOP,
+            left,
+            top,
+            right - left,
+            bottom - top,
+            SWP_FRAMECHANGED | SWP_NOOWNERZORDER,
+        );
+    }
+}
+
+/// Confines the cursor to `area` of the window, in window coordinates.
+fn clip_cursor(hwnd: HWND, area: PhysicalRect) {
+    let mut top_left = POINT {
+        x: area.left.0.into(),
+        y: area.top.0.into(),
+    };
+    let mut bottom_right = POINT {
+        x: area.right.0.into(),
+        y: area.bottom.0.into(),
+    };
+
+    unsafe {
+        ClientToScreen(hwnd, &mut top_left);
+        ClientToScreen(hwnd, &mut bottom_right);
+        ClipCursor(&RECT {
+            left: top_left.x,
+            top: top_left.y,
+            right: bottom_right.x,
+            bottom: bottom_right.y,
+        });
+    }
+}
+
+#[inline]
+fn dispatch(shell: &Rc<Inner>, events: impl IntoIterator<Item = Event>) {
+    // If the callback is already running, the events were sent by a call it
+    // made to the shell, such as to create a window. They are delivered once
+    // it returns.
+    let mut cb = match shell.event_callback.try_borrow_mut() {
+        Ok(cb) => cb,
+        Err(_) => {
+            shell.event_buffer.borrow_mut().extend(events);
+            return;
+        }
+    };
+
+    // If we don't have a callback yet, the event was sent before
+    // run_event_loop() was called. This happens when windows are created before
+    // the event loop is run.
+    if let Some(callback) = cb.as_mut() {
+        let mut ctrl = EventLoopControl::Poll;
+        let mut send = |event| {
+            callback(event, shell, &mut ctrl);
+            shell.event_mode.set(ctrl);
+
+            if ctrl == EventLoopControl::Exit {
+                // std::mem::drop(cb);
+                // clean_exit(shell);
+                unsafe { PostQuitMessage(0) };
+            }
+        };
+
+        for event in events {
+            send(event);
+        }
+
+        loop {
+            let buffered_events = shell.event_buffer.take();
+            if buffered_events.is_empty() {
+                break;
+            }
+
+            for event in buffered_events {
+                send(event);
+            }
+        }
+    } else {
+        shell.event_buffer.borrow_mut().extend(events);
+    }
+}
+
+fn clean_exit(shell: &Rc<Inner>) -> ! {
+    // Cleanly shut down the event loop by disabling window creation and
+    // destroying any windows that remain.
+    shell.is_shutting_down.set(true);
+
+    let mut cb = shell.event_callback.borrow_mut();
+
+    // If we don't have a callback yet, the event was sent before
+    // run_event_loop() was called. This happens when windows are created before
+    // the event loop is run.
     if let Some(callback) = cb.as_mut() {
         let mut ctrl = EventLoopControl::Poll;
 
@@ -360,6 +2239,36 @@ unsafe extern "system" fn unsafe_wndproc(
                 // reference to Inner.
                 let shell = Rc::from_raw(shell);
                 shell.windows.borrow_mut().retain(|h| *h != hwnd);
+                shell.redraw_requests.borrow_mut().retain(|h| *h != hwnd);
+                shell.cursors.borrow_mut().retain(|(h, _)| *h != hwnd);
+                shell.hidden_cursors.borrow_mut().retain(|h| *h != hwnd);
+                shell
+                    .size_limits
+                    .borrow_mut()
+                    .retain(|(h, _, _)| *h != hwnd);
+                shell.fullscreen.borrow_mut().retain(|f| f.hwnd != hwnd);
+                shell.menu_bars.borrow_mut().retain(|(h, _)| *h != hwnd);
+                shell.accessibility.borrow_mut().retain(|a| a.hwnd != hwnd);
+                shell.tray_icons.borrow_mut().retain(|tray| {
+                    if tray.hwnd == hwnd {
+                        delete_tray_icon(tray);
+                    }
+                    tray.hwnd != hwnd
+                });
+                shell
+                    .one_shot_timers
+                    .borrow_mut()
+                    .retain(|(h, _)| *h != hwnd);
+                shell.icons.borrow_mut().retain(|(h, icon)| {
+                    if *h == hwnd {
+                        DestroyIcon(*icon);
+                    }
+                    *h != hwnd
+                });
+                if matches!(shell.cursor_clip.get(), Some((h, _)) if h == hwnd) {
+                    shell.cursor_clip.set(None);
+                    ClipCursor(std::ptr::null());
+                }
                 wndproc(&shell, hwnd, msg, wparam, lparam);
                 LRESULT(0)
             }
@@ -386,27 +2295,12 @@ fn wndproc(shell: &Rc<Inner>, hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPAR
     let window_id = hwnd.into();
 
     let event = match msg {
-        WM_CREATE => {
-            let rect: RECT = {
-                let mut rect = RECT::default();
-                unsafe {
-                    GetClientRect(hwnd, std::ptr::addr_of_mut!(rect));
-                }
-                rect
-            };
-
-            let extent = Extent {
-                width: Px((rect.right - rect.left).try_into().unwrap()),
-                height: Px((rect.bottom - rect.top).try_into().unwrap()),
-            };
-
-            Event::Window {
-                window_id,
-                event: WindowEvent::Init {
-                    inner_extent: extent,
-                },
-            }
-        }
+        WM_CREATE => Event::Window {
+            window_id,
+            event: WindowEvent::Init {
+                inner_extent: client_extent(hwnd),
+            },
+        },
         WM_DESTROY => Event::Window {
             window_id,
             event: WindowEvent::Destroyed,
@@ -415,13 +2309,21 @@ fn wndproc(shell: &Rc<Inner>, hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPAR
             window_id,
             event: WindowEvent::CloseRequested,
         },
+        WM_ENTERSIZEMOVE => Event::Window {
+            window_id,
+            event: WindowEvent::SizeMoveStarted,
+        },
+        WM_EXITSIZEMOVE => Event::Window {
+            window_id,
+            event: WindowEvent::SizeMoveEnded,
+        },
         WM_MOUSEMOVE => {
-            let x = Px(lparam.0 as i16);
-            let y = Px((lparam.0 >> 16) as i16);
+            let x = PhysicalPx(lparam.0 as i16);
+            let y = PhysicalPx((lparam.0 >> 16) as i16);
             Event::Window {
                 window_id,
                 event: WindowEvent::CursorMoved {
-                    position: Point { x, y },
+                    position: PhysicalPoint { x, y },
                 },
             }
         }
@@ -449,9 +2351,426 @@ fn wndproc(shell: &Rc<Inner>, hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPAR
             window_id,
             event: WindowEvent::MiddleMouseButtonReleased,
         },
+        WM_MOUSEWHEEL => Event::Window {
+            window_id,
+            event: WindowEvent::MouseWheel {
+                x: 0,
+                y: (wparam.0 >> 16) as i16,
+            },
+        },
+        WM_MOUSEHWHEEL => Event::Window {
+            window_id,
+            event: WindowEvent::MouseWheel {
+                x: (wparam.0 >> 16) as i16,
+                y: 0,
+            },
+        },
+        WM_KEYDOWN => {
+            let key = key_from_virtual_key(wparam.0);
+            let modifiers = modifiers();
+
+            let event = match shell.accelerator(hwnd, key, modifiers) {
+                Some(item) => WindowEvent::MenuItemSelected { item },
+                None => WindowEvent::KeyPressed { key, modifiers },
+            };
+
+            Event::Window { window_id, event }
+        }
+        // Keys pressed with Alt are only reported if they are accelerators,
+        // so that the system can still use them to open the menu bar.
+        WM_SYSKEYDOWN => {
+            let key = key_from_virtual_key(wparam.0);
+            match shell.accelerator(hwnd, key, modifiers()) {
+                Some(item) => Event::Window {
+                    window_id,
+                    event: WindowEvent::MenuItemSelected { item },
+                },
+                None => return unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
+            }
+        }
+        // Menus send their commands in the low word, with zero in the high
+        // word.
+        WM_COMMAND if (wparam.0 >> 16) & 0xffff == 0 => {
+            match shell.menu_bar_item(hwnd, (wparam.0 & 0xffff) as i32) {
+                Some(item) => Event::Window {
+                    window_id,
+                    event: WindowEvent::MenuItemSelected { item },
+                },
+                None => return LRESULT(0),
+            }
+        }
+        WM_CHAR => {
+            let unit = wparam.0 as u16;
+
+            let character = if (0xD800..0xDC00).contains(&unit) {
+                shell.high_surrogate.set(Some(unit));
+                return LRESULT(0);
+            } else if let Some(high) = shell.high_surrogate.take() {
+                char::decode_utf16([high, unit]).next().and_then(Result::ok)
+            } else {
+                char::from_u32(u32::from(unit))
+            };
+
+            match character {
+                Some(character) if !character.is_control() => Event::Window {
+                    window_id,
+                    event: WindowEvent::CharacterReceived { character },
+                },
+                _ => return LRESULT(0),
+            }
+        }
+        // Not passed to `DefWindowProcW`, so that the system doesn't show its
+        // own composition window.
+        WM_IME_STARTCOMPOSITION => Event::Window {
+            window_id,
+            event: WindowEvent::CompositionStarted,
+        },
+        WM_IME_ENDCOMPOSITION => Event::Window {
+            window_id,
+            event: WindowEvent::CompositionEnded,
+        },
+        WM_TIMER => {
+            let id = wparam.0;
+            let mut one_shot = shell.one_shot_timers.borrow_mut();
+            if let Some(index) = one_shot.iter().position(|timer| *timer == (hwnd, id)) {
+                one_shot.swap_remove(index);
+                unsafe { KillTimer(hwnd, id) };
+            }
+
+            Event::Window {
+                window_id,
+                event: WindowEvent::Timer {
+                    timer: TimerId(id as u64),
+                },
+            }
+        }
+        UM_TRAY_ICON => {
+            let tray = wparam.0 as u64;
+            let event = match (lparam.0 & 0xffff) as u32 {
+                WM_LBUTTONUP => WindowEvent::TrayIconClicked {
+                    tray: TrayIconId(tray),
+                },
+                WM_RBUTTONUP => match shell.show_tray_menu(hwnd, tray) {
+                    Some(item) => WindowEvent::MenuItemSelected { item },
+                    None => return LRESULT(0),
+                },
+                _ => return LRESULT(0),
+            };
+
+            Event::Window { window_id, event }
+        }
+        UM_FILE_DIALOG_CLOSED => Event::Window {
+            window_id,
+            event: WindowEvent::FileDialogClosed {
+                dialog: DialogId(wparam.0 as u64),
+            },
+        },
+        UM_ACCESS_REQUEST => Event::Window {
+            window_id,
+            event: WindowEvent::AccessibilityRequested,
+        },
         special_return => {
             return match special_return {
                 WM_ERASEBKGND => LRESULT(1),
+                WM_GETOBJECT => {
+                    let result = shell.access_adapter(hwnd).and_then(|adapter| {
+                        adapter.handle_wm_getobject(
+                            accesskit_windows::WPARAM(wparam.0),
+                            accesskit_windows::LPARAM(lparam.0),
+                        )
+                    });
+
+                    match result {
+                        Some(result) => {
+                            let result: accesskit_windows::LRESULT = result.into();
+                            LRESULT(result.0)
+                        }
+                        // Other objects, and windows that haven't been
+                        // described, are left to the system.
+                        None => unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
+                    }
+                }
+                WM_DROPFILES => {
+                    let hdrop = HDROP(wparam.0 as isize);
+
+                    // Long enough for paths beyond `MAX_PATH`.
+                    let mut buffer = vec![0_u16; 32 * 1024];
+                    let count = unsafe { DragQueryFileW(hdrop, u32::MAX, &mut []) };
+                    let paths = (0..count)
+                        .map(|index| {
+                            let len = unsafe { DragQueryFileW(hdrop, index, &mut buffer) };
+                            PathBuf::from(String::from_utf16_lossy(&buffer[..len as usize]))
+                        })
+                        .collect();
+
+                    let mut point = POINT::default();
+                    unsafe {
+                        DragQueryPoint(hdrop, &mut point);
+                        DragFinish(hdrop);
+                    }
+
+                    *shell.dropped_files.borrow_mut() = Some((hwnd, paths));
+                    dispatch(
+                        shell,
+                        std::iter::once(Event::Window {
+                            window_id,
+                            event: WindowEvent::FilesDropped {
+                                position: PhysicalPoint {
+                                    x: PhysicalPx(point.x as i16),
+                                    y: PhysicalPx(point.y as i16),
+                                },
+                            },
+                        }),
+                    );
+                    *shell.dropped_files.borrow_mut() = None;
+
+                    LRESULT(0)
+                }
+                WM_SETCURSOR => {
+                    // Only the client area, so that the borders still show
+                    // the resize cursors.
+                    let cursor = shell
+                        .cursors
+                        .borrow()
+                        .iter()
+                        .find(|(h, _)| *h == hwnd)
+                        .map(|(_, cursor)| *cursor);
+
+                    let is_client = (lparam.0 & 0xFFFF) as u32 == HTCLIENT;
+                    if is_client && shell.hidden_cursors.borrow().contains(&hwnd) {
+                        unsafe { SetCursor(HCURSOR::default()) };
+                        return LRESULT(1);
+                    }
+
+                    match cursor {
+                        Some(cursor) if is_client => {
+                            unsafe { SetCursor(cursor) };
+                            LRESULT(1)
+                        }
+                        _ => unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
+                    }
+                }
+                WM_INPUT => {
+                    let mut input = RAWINPUT::default();
+                    let mut size = std::mem::size_of::<RAWINPUT>() as u32;
+                    let read = unsafe {
+                        GetRawInputData(
+                            HRAWINPUT(lparam.0),
+                            RID_INPUT,
+                            (&mut input as *mut RAWINPUT).cast(),
+                            &mut size,
+                            std::mem::size_of::<RAWINPUTHEADER>() as u32,
+                        )
+                    };
+
+                    if read != u32::MAX && input.header.dwType == RIM_TYPEMOUSE.0 {
+                        let mouse = unsafe { input.data.mouse };
+                        if mouse.usFlags & MOUSE_MOVE_ABSOLUTE == 0
+                            && (mouse.lLastX != 0 || mouse.lLastY != 0)
+                        {
+                            dispatch(
+                                shell,
+                                std::iter::once(Event::Window {
+                                    window_id,
+                                    event: WindowEvent::RawMouseMotion {
+                                        x: mouse.lLastX,
+                                        y: mouse.lLastY,
+                                    },
+                                }),
+                            );
+                        }
+                    }
+
+                    // The system needs to clean up after the message.
+                    unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+                }
+                WM_POINTERDOWN | WM_POINTERUPDATE | WM_POINTERUP | WM_POINTERCAPTURECHANGED => {
+                    let id = (wparam.0 & 0xFFFF) as u32;
+                    let mut kind = POINTER_INPUT_TYPE(0);
+                    let is_touch =
+                        unsafe { GetPointerType(id, &mut kind) }.as_bool() && kind == PT_TOUCH;
+
+                    if is_touch {
+                        // The position is in screen coordinates, except for
+                        // `WM_POINTERCAPTURECHANGED` which has none.
+                        let mut point = POINT {
+                            x: (lparam.0 as i16).into(),
+                            y: ((lparam.0 >> 16) as i16).into(),
+                        };
+                        unsafe { ScreenToClient(hwnd, &mut point) };
+
+                        let phase = match msg {
+                            WM_POINTERDOWN => TouchPhase::Started,
+                            WM_POINTERUPDATE => TouchPhase::Moved,
+                            WM_POINTERUP => TouchPhase::Ended,
+                            _ => TouchPhase::Cancelled,
+                        };
+
+                        dispatch(
+                            shell,
+                            std::iter::once(Event::Window {
+                                window_id,
+                                event: WindowEvent::Touch {
+                                    id,
+                                    phase,
+                                    position: PhysicalPoint {
+                                        x: PhysicalPx(point.x as i16),
+                                        y: PhysicalPx(point.y as i16),
+                                    },
+                                },
+                            }),
+                        );
+                    }
+
+                    // The system turns unhandled touches into mouse input, for
+                    // widgets that only handle the mouse.
+                    unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+                }
+                WM_IME_SETCONTEXT => {
+                    // Widgets draw the text being composed themselves.
+                    let lparam = LPARAM(lparam.0 & !(ISC_SHOWUICOMPOSITIONWINDOW as isize));
+                    unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+                }
+                WM_IME_COMPOSITION => {
+                    // Handling the committed text here rather than in
+                    // `DefWindowProcW` prevents it from also being sent as
+                    // `WM_CHAR` messages.
+                    let flags = lparam.0 as u32;
+                    let mut events = Vec::new();
+
+                    if flags & GCS_RESULTSTR != 0 {
+                        let himc = unsafe { ImmGetContext(hwnd) };
+                        if !himc.is_invalid() {
+                            if let Some(text) = composition_string(himc, GCS_RESULTSTR) {
+                                events.extend(
+                                    char::decode_utf16(text)
+                                        .filter_map(Result::ok)
+                                        .filter(|character| !character.is_control())
+                                        .map(|character| Event::Window {
+                                            window_id,
+                                            event: WindowEvent::CharacterReceived { character },
+                                        }),
+                                );
+                            }
+                            unsafe { ImmReleaseContext(hwnd, himc) };
+                        }
+                    }
+
+                    if flags & GCS_COMPSTR != 0 {
+                        events.push(Event::Window {
+                            window_id,
+                            event: WindowEvent::CompositionUpdated,
+                        });
+                    }
+
+                    dispatch(shell, events);
+                    LRESULT(0)
+                }
+                WM_DPICHANGED => {
+                    // Both halves of `WPARAM` hold the same DPI.
+                    let dpi = (wparam.0 & 0xFFFF) as u32;
+                    dispatch(
+                        shell,
+                        std::iter::once(Event::Window {
+                            window_id,
+                            event: WindowEvent::ScaleFactorChanged { dpi },
+                        }),
+                    );
+
+                    // The system suggests a size that keeps the window's
+                    // contents the same size on the new monitor.
+                    if shell.fullscreen.borrow().iter().any(|f| f.hwnd == hwnd) {
+                        cover_monitor(hwnd);
+                    } else {
+                        let RECT {
+                            left,
+                            top,
+                            right,
+                            bottom,
+                        } = unsafe { *(lparam.0 as *const RECT) };
+
+                        unsafe {
+                            SetWindowPos(
+                                hwnd,
+                                None,
+                                left,
+                                top,
+                                right - left,
+                                bottom - top,
+                                SWP_NOZORDER | SWP_NOACTIVATE,
+                            )
+                        };
+                    }
+
+                    LRESULT(0)
+                }
+                WM_GETMINMAXINFO => {
+                    // Fullscreen windows cover their monitor regardless of
+                    // their limits.
+                    if shell.fullscreen.borrow().iter().any(|f| f.hwnd == hwnd) {
+                        return LRESULT(0);
+                    }
+
+                    let info = lparam.0 as *mut MINMAXINFO;
+                    let (min, max) = shell.size_limits(hwnd);
+
+                    if min != PhysicalExtent::default() {
+                        let (x, y) = window_size(hwnd, min);
+                        unsafe { (*info).ptMinTrackSize = POINT { x, y } };
+                    }
+
+                    if max != PhysicalExtent::MAX {
+                        let (x, y) = window_size(hwnd, max);
+                        unsafe {
+                            (*info).ptMaxTrackSize = POINT { x, y };
+                            (*info).ptMaxSize = POINT { x, y };
+                        }
+                    }
+
+                    LRESULT(0)
+                }
+                UM_UPDATE_FRAME => {
+                    // The borrow must end before the window changes, since
+                    // that sends events to the callback.
+                    let fullscreen = shell
+                        .fullscreen
+                        .borrow()
+                        .iter()
+                        .find(|f| f.hwnd == hwnd)
+                        .copied();
+
+                    match fullscreen {
+                        Some(Fullscreen {
+                            mode: FullscreenMode::Windowed,
+                            placement,
+                            style,
+                            ..
+                        }) => {
+                            shell.fullscreen.borrow_mut().retain(|f| f.hwnd != hwnd);
+                            unsafe {
+                                SetWindowLongPtrW(hwnd, GWL_STYLE, style.0 as isize);
+                                SetWindowPlacement(hwnd, &placement);
+                            }
+                        }
+                        Some(_) => {
+                            cover_monitor(hwnd);
+                            return LRESULT(0);
+                        }
+                        None => {}
+                    }
+
+                    let (min, max) = shell.size_limits(hwnd);
+                    let (width, height) = window_size(hwnd, client_extent(hwnd).clamp(min, max));
+
+                    // The system sizes maximized and minimized windows.
+                    let mut flags = SWP_FRAMECHANGED | SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE;
+                    if unsafe { IsZoomed(hwnd).as_bool() || IsIconic(hwnd).as_bool() } {
+                        flags |= SWP_NOSIZE;
+                    }
+
+                    unsafe { SetWindowPos(hwnd, None, 0, 0, width, height, flags) };
+                    LRESULT(0)
+                }
                 WM_WINDOWPOSCHANGING => {
                     let pos = lparam.0 as *mut WINDOWPOS;
                     // NOTE(straivers): Since we redraw the entire window
@@ -459,32 +2778,67 @@ fn wndproc(shell: &Rc<Inner>, hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPAR
                     unsafe { (*pos).flags |= SWP_NOCOPYBITS };
                     LRESULT(0)
                 }
+                WM_ACTIVATE => {
+                    let is_active = (wparam.0 & 0xFFFF) as u32 != WA_INACTIVE;
+
+                    // The confinement is global, so it is lifted while
+                    // another window is active.
+                    if let Some((h, area)) = shell.cursor_clip.get() {
+                        if h == hwnd {
+                            if is_active {
+                                clip_cursor(hwnd, area);
+                            } else {
+                                unsafe { ClipCursor(std::ptr::null()) };
+                            }
+                        }
+                    }
+
+                    // Assistive technology only follows the focus within the
+                    // active window.
+                    let adapter = shell
+                        .accessibility
+                        .borrow()
+                        .iter()
+                        .find(|a| a.hwnd == hwnd)
+                        .and_then(|a| a.adapter.clone());
+                    if let Some(adapter) = adapter {
+                        adapter.update_window_focus_state(is_active).raise();
+                    }
+                    unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+                }
                 WM_WINDOWPOSCHANGED => {
                     let pos = lparam.0 as *const WINDOWPOS;
 
-                    let (width, height) = unsafe { ((*pos).cx, (*pos).cy) };
-                    let width = width as i16;
-                    let height = height as i16;
+                    if let Some((h, area)) = shell.cursor_clip.get() {
+                        if h == hwnd {
+                            clip_cursor(hwnd, area);
+                        }
+                    }
 
+                    // Moving the window doesn't change its contents.
+                    if unsafe { (*pos).flags }.0 & SWP_NOSIZE.0 != 0 {
+                        return LRESULT(0);
+                    }
+
+                    // `WINDOWPOS` holds the size of the whole window, including
+                    // its borders and title bar.
                     let resize = Event::Window {
                         window_id,
                         event: WindowEvent::Resized {
-                            inner_extent: Extent {
-                                width: Px(width),
-                                height: Px(height),
-                            },
+                            inner_extent: client_extent(hwnd),
                         },
                     };
 
+                    // The windows are copied since the callback may create
+                    // more of them.
+                    let windows = shell.windows.borrow().clone();
                     dispatch(
                         shell,
                         std::iter::once(resize).chain(
-                            shell
-                                .windows
-                                .borrow()
-                                .iter()
+                            windows
+                                .into_iter()
                                 .map(|hwnd| Event::Window {
-                                    window_id: (*hwnd).into(),
+                                    window_id: hwnd.into(),
                                     event: WindowEvent::Repaint,
                                 })
                                 .chain(std::iter::once(Event::RepaintComplete)),
@@ -519,3 +2873,123 @@ fn wndproc(shell: &Rc<Inner>, hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPAR
 
     LRESULT(0)
 }
+
+/// The size of the window's client area, which is zero while it is minimized.
+fn client_extent(hwnd: HWND) -> PhysicalExtent {
+    let mut rect = RECT::default();
+    unsafe {
+        GetClientRect(hwnd, std::ptr::addr_of_mut!(rect));
+    }
+
+    PhysicalExtent {
+        width: PhysicalPx((rect.right - rect.left).try_into().unwrap()),
+        height: PhysicalPx((rect.bottom - rect.top).try_into().unwrap()),
+    }
+}
+
+/// Reads one of the strings of the input method context `himc`, such as the
+/// text being composed (`GCS_COMPSTR`) or the text that was just committed
+/// (`GCS_RESULTSTR`). Returns `None` if the string couldn't be read.
+fn composition_string(himc: HIMC, kind: u32) -> Option<Vec<u16>> {
+    // The length is in bytes, not UTF-16 code units.
+    let len = unsafe { ImmGetCompositionStringW(himc, kind, null_mut(), 0) };
+    let len = u32::try_from(len).ok()?;
+
+    let mut text = vec![0u16; len as usize / std::mem::size_of::<u16>()];
+    let read = unsafe { ImmGetCompositionStringW(himc, kind, text.as_mut_ptr().cast(), len) };
+    if read < 0 {
+        return None;
+    }
+
+    Some(text)
+}
+
+/// Converts a Win32 virtual key code into a [`Key`].
+fn key_from_virtual_key(vk: usize) -> Key {
+    // The letter keys share their codes with the uppercase ASCII letters.
+    const LETTERS: [Key; 26] = [
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+    ];
+
+    // The function keys are contiguous, starting with `VK_F1`.
+    const FUNCTION_KEYS: [Key; 12] = [
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+    ];
+
+    let vk = match u16::try_from(vk) {
+        Ok(vk) => VIRTUAL_KEY(vk),
+        Err(_) => return Key::Unknown,
+    };
+
+    match vk {
+        VK_BACK => Key::Backspace,
+        VK_TAB => Key::Tab,
+        VK_RETURN => Key::Enter,
+        VK_ESCAPE => Key::Escape,
+        VK_SPACE => Key::Space,
+        VK_PRIOR => Key::PageUp,
+        VK_NEXT => Key::PageDown,
+        VK_END => Key::End,
+        VK_HOME => Key::Home,
+        VK_LEFT => Key::Left,
+        VK_UP => Key::Up,
+        VK_RIGHT => Key::Right,
+        VK_DOWN => Key::Down,
+        VK_INSERT => Key::Insert,
+        VK_DELETE => Key::Delete,
+        VIRTUAL_KEY(code @ 0x41..=0x5A) => LETTERS[usize::from(code - 0x41)],
+        VIRTUAL_KEY(code) if (VK_F1.0..=VK_F12.0).contains(&code) => {
+            FUNCTION_KEYS[usize::from(code - VK_F1.0)]
+        }
+        _ => Key::Unknown,
+    }
+}
+
+/// Reads the state of the modifier keys at the time of the message being
+/// processed.
+fn modifiers() -> Modifiers {
+    // The high bit is set if the key is down.
+    let is_down = |vk: VIRTUAL_KEY| unsafe { GetKeyState(i32::from(vk.0)) } < 0;
+
+    Modifiers {
+        shift: is_down(VK_SHIFT),
+        control: is_down(VK_CONTROL),
+        alt: is_down(VK_MENU),
+    }
+}
@@ -0,0 +1,3106 @@
+//! The shell for Linux, using X11.
+//!
+//! Xlib is loaded when the shell is initialized (see the `xlib` module). Some
+//! of the shell's features have no equivalent that every X11 desktop
+//! supports, and are approximated:
+//!
+//! - Menu bars aren't shown, but their accelerators still select their items.
+//! - Tray icons are docked with the XEmbed system tray protocol, and don't
+//!   show their tooltips or menus.
+//! - File dialogs are shown with `zenity`, and choose nothing if it isn't
+//!   installed. So are error dialogs, which are printed to standard error
+//!   instead if it isn't.
+//! - Input methods draw the text being composed themselves, so
+//!   [`Shell::composition`](super::Shell::composition) is always empty and
+//!   [`Shell::set_ime_area`](super::Shell::set_ime_area) is ignored.
+//! - [`FullscreenMode::Exclusive`] is the same as
+//!   [`FullscreenMode::Borderless`].
+//! - Dropped files, touches, and raw mouse motion aren't reported.
+//! - The scale factor is read from the `Xft.dpi` resource when the shell is
+//!   initialized, and is the same for every monitor.
+//! - Errors reported by the X server are printed to standard error. Only
+//!   those caused by creating a window are returned, from
+//!   [`Shell::create_window`](super::Shell::create_window).
+//! - Windows aren't described to assistive technology, so
+//!   [`Shell::update_accessibility`](super::Shell::update_accessibility) is
+//!   ignored.
+
+#[path = "xlib.rs"]
+mod xlib;
+
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{CStr, CString},
+    io::{ErrorKind, Read, Write},
+    os::{
+        raw::{c_char, c_int, c_long, c_uint, c_ulong, c_void},
+        unix::{io::AsRawFd, net::UnixStream},
+    },
+    path::PathBuf,
+    process::Command,
+    ptr::{null, null_mut},
+    rc::{Rc, Weak},
+    sync::{Arc, Mutex, PoisonError},
+    thread::ThreadId,
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::OnceCell;
+
+use crate::{
+    gfx::{
+        geometry::{Extent, PhysicalExtent, PhysicalPoint, PhysicalPx, PhysicalRect, ScaleFactor},
+        pixel_buffer::{Layout, PixelBuffer},
+    },
+    shell::event::{Event, Key, Modifiers, Window as WindowEvent, WHEEL_DELTA},
+};
+
+use super::{
+    dialog::{DialogId, FileDialog, FileFilter},
+    menu::{Accelerator, Menu, MenuItem, MenuItemId},
+    placement::WindowPlacement,
+    Composition, CursorIcon, Error, EventLoopControl, FullscreenMode, TimerId, TrayIcon,
+    TrayIconId, UserEvent, WindowConfig,
+};
+
+/// The size of windows created without one.
+const DEFAULT_EXTENT: PhysicalExtent = PhysicalExtent {
+    width: PhysicalPx(800),
+    height: PhysicalPx(600),
+};
+
+/// The events that Fathom's windows receive. Key releases aren't reported,
+/// but input methods need them to compose text.
+const WINDOW_EVENT_MASK: c_long = xlib::KeyPressMask
+    | xlib::KeyReleaseMask
+    | xlib::ButtonPressMask
+    | xlib::ButtonReleaseMask
+    | xlib::PointerMotionMask
+    | xlib::ExposureMask
+    | xlib::StructureNotifyMask
+    | xlib::FocusChangeMask
+    | xlib::PropertyChangeMask;
+
+/// The events that tray icons receive.
+const TRAY_EVENT_MASK: c_long =
+    xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::ExposureMask;
+
+/// The shortest interval between the firings of a timer, so that repeating
+/// timers with a zero duration don't keep the event loop from waiting.
+const MIN_TIMER_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The longest time that reading the clipboard waits for its owner to answer.
+const CLIPBOARD_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The opcode of `_NET_SYSTEM_TRAY_OPCODE` that asks the tray to embed an
+/// icon.
+const SYSTEM_TRAY_REQUEST_DOCK: c_long = 0;
+
+/// Set in the first item of `_MOTIF_WM_HINTS` when it holds the window's
+/// decorations.
+const MWM_HINTS_DECORATIONS: c_ulong = 1 << 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WindowId {
+    window: xlib::Window,
+}
+
+impl From<xlib::Window> for super::WindowId {
+    fn from(window: xlib::Window) -> Self {
+        super::WindowId(WindowId { window })
+    }
+}
+
+static SHELL_THREAD: OnceCell<ThreadId> = OnceCell::new();
+static RUNNING: OnceCell<bool> = OnceCell::new();
+
+/// The errors that the X server has reported since they were last checked.
+/// Xlib reports them from whichever thread made the request that failed.
+static PROTOCOL_ERRORS: Mutex<Vec<ProtocolError>> = Mutex::new(Vec::new());
+
+/// An error that the X server reported for a request.
+struct ProtocolError {
+    error_code: u8,
+    request_code: u8,
+    minor_code: u8,
+    resource: xlib::XID,
+}
+
+/// Records errors for [`Inner::check_errors`] in place of Xlib's default
+/// handler, which exits the process.
+unsafe extern "C" fn record_protocol_error(
+    _: *mut xlib::Display,
+    event: *mut xlib::XErrorEvent,
+) -> c_int {
+    let event = &*event;
+    PROTOCOL_ERRORS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(ProtocolError {
+            error_code: event.error_code,
+            request_code: event.request_code,
+            minor_code: event.minor_code,
+            resource: event.resourceid,
+        });
+    0
+}
+
+pub struct OsShell {
+    inner: Rc<Inner>,
+}
+
+impl OsShell {
+    pub fn initialize() -> Self {
+        SHELL_THREAD.set(std::thread::current().id()).expect(
+            "Only one instance of the shell may be initialized for the lifetime of the program",
+        );
+
+        let xlib = xlib::Xlib::load().expect("Xlib could not be loaded. Is libX11 installed?");
+
+        // Vulkan drivers may use the connection from their own threads.
+        unsafe { (xlib.XInitThreads)() };
+
+        // Input methods are chosen by the locale and by `XMODIFIERS`.
+        unsafe {
+            libc::setlocale(libc::LC_CTYPE, c"".as_ptr());
+            (xlib.XSetLocaleModifiers)(c"".as_ptr());
+        }
+
+        // Errors are logged as they arrive, and those caused by creating a
+        // window fail `create_window`.
+        unsafe { (xlib.XSetErrorHandler)(Some(record_protocol_error)) };
+
+        let display = unsafe { (xlib.XOpenDisplay)(null()) };
+        assert!(
+            !display.is_null(),
+            "Could not connect to the X server. Is DISPLAY set?"
+        );
+
+        let screen = unsafe { (xlib.XDefaultScreen)(display) };
+        let root = unsafe { (xlib.XRootWindow)(display, screen) };
+        let atoms = Atoms::intern(&xlib, display, screen);
+
+        // Text is still typed without an input method, but only in Latin-1.
+        let im = unsafe { (xlib.XOpenIM)(display, null_mut(), null_mut(), null_mut()) };
+
+        // Owns the clipboard, and receives its contents when it is read.
+        let selection_window = unsafe {
+            (xlib.XCreateWindow)(
+                display,
+                root,
+                0,
+                0,
+                1,
+                1,
+                0,
+                0,
+                xlib::InputOnly,
+                null_mut(),
+                0,
+                &mut xlib::XSetWindowAttributes::default(),
+            )
+        };
+
+        // Proxies and dialog threads wake the event loop by writing to the
+        // socket that it waits on.
+        let (wake, proxy_wake) = UnixStream::pair().expect("the wake socket could not be created");
+        wake.set_nonblocking(true).unwrap();
+        proxy_wake.set_nonblocking(true).unwrap();
+
+        let resources = unsafe {
+            let resources = (xlib.XResourceManagerString)(display);
+            if resources.is_null() {
+                String::new()
+            } else {
+                std::ffi::CStr::from_ptr(resources)
+                    .to_string_lossy()
+                    .into_owned()
+            }
+        };
+
+        let dpi = resource(&resources, "Xft.dpi")
+            .and_then(|dpi| dpi.parse::<f32>().ok())
+            .map_or(96, |dpi| dpi.round() as u32);
+
+        let subpixel_text = matches!(
+            resource(&resources, "Xft.rgba"),
+            Some("rgb" | "bgr" | "vrgb" | "vbgr")
+        );
+
+        Self {
+            inner: Rc::new(Inner {
+                xrandr: xlib::Xrandr::load().ok(),
+                xlib,
+                display,
+                screen,
+                root,
+                atoms,
+                im,
+                selection_window,
+                wake,
+                proxy_wake: Arc::new(proxy_wake),
+                event_buffer: RefCell::new(vec![]),
+                windows: RefCell::new(vec![]),
+                redraw_requests: RefCell::new(vec![]),
+                destroy_requests: RefCell::new(vec![]),
+                extents: RefCell::new(vec![]),
+                minimized: RefCell::new(vec![]),
+                input_contexts: RefCell::new(vec![]),
+                colormaps: RefCell::new(vec![]),
+                font_cursors: RefCell::new(vec![]),
+                cursors: RefCell::new(vec![]),
+                hidden_cursors: RefCell::new(vec![]),
+                blank_cursor: Cell::new(0),
+                size_limits: RefCell::new(vec![]),
+                fixed_size: RefCell::new(vec![]),
+                fullscreen: RefCell::new(vec![]),
+                normal_frames: RefCell::new(vec![]),
+                focused: Cell::new(None),
+                captured: Cell::new(None),
+                cursor_clip: Cell::new(None),
+                next_dialog: Cell::new(0),
+                dialog_results: Arc::new(Mutex::new(Vec::new())),
+                closed_dialogs: Arc::new(Mutex::new(Vec::new())),
+                next_timer: Cell::new(0),
+                timers: RefCell::new(vec![]),
+                next_tray: Cell::new(0),
+                tray_icons: RefCell::new(vec![]),
+                accelerators: RefCell::new(vec![]),
+                clipboard_text: RefCell::new(None),
+                user_events: Arc::new(Mutex::new(Vec::new())),
+                dpi,
+                subpixel_text,
+                is_shutting_down: Cell::new(false),
+                exit_requested: Cell::new(false),
+                event_mode: Cell::new(EventLoopControl::Poll),
+                event_callback: RefCell::new(None),
+            }),
+        }
+    }
+
+    pub fn run_event_loop<F>(&self, callback: F) -> !
+    where
+        F: 'static + FnMut(Event, &dyn super::Shell, &mut EventLoopControl),
+    {
+        RUNNING
+            .set(true)
+            .expect("run_event_loop can only be called once");
+
+        *self.inner.event_callback.borrow_mut() = Some(Box::new(callback));
+
+        {
+            let buffered_events = self.inner.event_buffer.take();
+            dispatch(&self.inner, buffered_events);
+        }
+
+        let shell = &self.inner;
+
+        loop {
+            if shell.exit_requested.get() {
+                break;
+            }
+
+            if shell.event_mode.get() == EventLoopControl::Wait
+                && shell.redraw_requests.borrow().is_empty()
+                && shell.destroy_requests.borrow().is_empty()
+            {
+                shell.wait_for_input();
+            }
+
+            while unsafe { (shell.xlib.XPending)(shell.display) } > 0 {
+                let mut event = xlib::XEvent::default();
+                unsafe { (shell.xlib.XNextEvent)(shell.display, &mut event) };
+                handle_event(shell, &mut event);
+            }
+
+            // Errors from requests that nothing waits on, such as those made
+            // while tearing down a window, are only logged.
+            let _ = shell.check_errors();
+
+            // The bytes only wake the event loop. What woke it is checked
+            // for below.
+            let mut buffer = [0; 64];
+            while matches!((&shell.wake).read(&mut buffer), Ok(n) if n > 0) {}
+
+            fire_timers(shell);
+
+            if !shell.user_events.lock().unwrap().is_empty() {
+                dispatch(shell, std::iter::once(Event::User));
+            }
+
+            let closed_dialogs = std::mem::take(&mut *shell.closed_dialogs.lock().unwrap());
+            let windows = shell.windows.borrow().clone();
+            dispatch(
+                shell,
+                closed_dialogs
+                    .into_iter()
+                    .filter(|(window, _)| windows.contains(window))
+                    .map(|(window, dialog)| Event::Window {
+                        window_id: window.into(),
+                        event: WindowEvent::FileDialogClosed {
+                            dialog: DialogId(dialog),
+                        },
+                    }),
+            );
+
+            for window in shell.destroy_requests.take() {
+                destroy_window(shell, window);
+            }
+
+            // The mode is checked again since the callback may have changed
+            // it while handling events.
+            let repaint = if shell.event_mode.get() == EventLoopControl::Poll {
+                shell.redraw_requests.borrow_mut().clear();
+                shell.windows.borrow().clone()
+            } else {
+                shell.redraw_requests.take()
+            };
+
+            if !repaint.is_empty() {
+                dispatch(
+                    shell,
+                    repaint
+                        .into_iter()
+                        .map(|window| Event::Window {
+                            window_id: window.into(),
+                            event: WindowEvent::Repaint,
+                        })
+                        .chain(std::iter::once(Event::RepaintComplete)),
+                );
+            }
+        }
+
+        clean_exit(&self.inner);
+    }
+}
+
+impl super::Shell for OsShell {
+    fn create_window(&self, config: &WindowConfig) -> Result<super::WindowId, Error> {
+        self.inner.create_window(config)
+    }
+
+    fn destroy_window(&self, window: super::WindowId) {
+        self.inner.destroy_window(window);
+    }
+
+    fn show_window(&self, window: super::WindowId) {
+        self.inner.show_window(window);
+    }
+
+    fn hide_window(&self, window: super::WindowId) {
+        self.inner.hide_window(window);
+    }
+
+    fn request_redraw(&self, window: super::WindowId) {
+        self.inner.request_redraw(window);
+    }
+
+    fn set_title(&self, window: super::WindowId, title: &str) {
+        self.inner.set_title(window, title);
+    }
+
+    fn set_extent(&self, window: super::WindowId, extent: PhysicalExtent) {
+        self.inner.set_extent(window, extent);
+    }
+
+    fn set_position(&self, window: super::WindowId, position: PhysicalPoint) {
+        self.inner.set_position(window, position);
+    }
+
+    fn minimize(&self, window: super::WindowId) {
+        self.inner.minimize(window);
+    }
+
+    fn maximize(&self, window: super::WindowId) {
+        self.inner.maximize(window);
+    }
+
+    fn restore(&self, window: super::WindowId) {
+        self.inner.restore(window);
+    }
+
+    fn window_placement(&self, window: super::WindowId) -> WindowPlacement {
+        self.inner.window_placement(window)
+    }
+
+    fn restore_placement(&self, window: super::WindowId, placement: &WindowPlacement) {
+        self.inner.restore_placement(window, placement);
+    }
+
+    fn set_size_limits(
+        &self,
+        window: super::WindowId,
+        min: Option<PhysicalExtent>,
+        max: Option<PhysicalExtent>,
+    ) {
+        self.inner.set_size_limits(window, min, max);
+    }
+
+    fn set_resizable(&self, window: super::WindowId, resizable: bool) {
+        self.inner.set_resizable(window, resizable);
+    }
+
+    fn set_always_on_top(&self, window: super::WindowId, always_on_top: bool) {
+        self.inner.set_always_on_top(window, always_on_top);
+    }
+
+    fn set_window_icon(&self, window: super::WindowId, icon: Option<&PixelBuffer>) {
+        self.inner.set_window_icon(window, icon);
+    }
+
+    fn fullscreen(&self, window: super::WindowId) -> FullscreenMode {
+        self.inner.fullscreen(window)
+    }
+
+    fn set_fullscreen(&self, window: super::WindowId, mode: FullscreenMode) {
+        self.inner.set_fullscreen(window, mode);
+    }
+
+    fn scale_factor(&self, window: super::WindowId) -> ScaleFactor {
+        self.inner.scale_factor(window)
+    }
+
+    fn primary_scale_factor(&self) -> ScaleFactor {
+        self.inner.primary_scale_factor()
+    }
+
+    fn refresh_rate(&self, window: super::WindowId) -> Option<f32> {
+        self.inner.refresh_rate(window)
+    }
+
+    fn prefers_subpixel_text(&self) -> bool {
+        self.inner.prefers_subpixel_text()
+    }
+
+    fn features(&self) -> super::ShellFeatures {
+        self.inner.features()
+    }
+
+    fn clipboard(&self) -> super::Clipboard {
+        self.inner.clipboard()
+    }
+
+    fn event_loop_proxy(&self) -> super::EventLoopProxy {
+        self.inner.event_loop_proxy()
+    }
+
+    fn take_user_events(&self) -> Vec<UserEvent> {
+        self.inner.take_user_events()
+    }
+
+    fn composition(&self, window: super::WindowId) -> Composition {
+        self.inner.composition(window)
+    }
+
+    fn dropped_files(&self, window: super::WindowId) -> Vec<PathBuf> {
+        self.inner.dropped_files(window)
+    }
+
+    fn update_accessibility(&self, window: super::WindowId, update: accesskit::TreeUpdate) {
+        self.inner.update_accessibility(window, update);
+    }
+
+    fn take_access_requests(&self, window: super::WindowId) -> Vec<accesskit::ActionRequest> {
+        self.inner.take_access_requests(window)
+    }
+
+    fn set_ime_area(&self, window: super::WindowId, area: PhysicalRect) {
+        self.inner.set_ime_area(window, area);
+    }
+
+    fn set_raw_mouse_input(&self, window: super::WindowId, enabled: bool) {
+        self.inner.set_raw_mouse_input(window, enabled);
+    }
+
+    fn set_cursor(&self, window: super::WindowId, icon: CursorIcon) {
+        self.inner.set_cursor(window, icon);
+    }
+
+    fn set_cursor_visible(&self, window: super::WindowId, visible: bool) {
+        self.inner.set_cursor_visible(window, visible);
+    }
+
+    fn set_mouse_capture(&self, window: super::WindowId, captured: bool) {
+        self.inner.set_mouse_capture(window, captured);
+    }
+
+    fn confine_cursor(&self, window: super::WindowId, area: Option<PhysicalRect>) {
+        self.inner.confine_cursor(window, area);
+    }
+
+    fn open_file_dialog(&self, window: super::WindowId, dialog: &FileDialog) -> DialogId {
+        self.inner.open_file_dialog(window, dialog)
+    }
+
+    fn save_file_dialog(&self, window: super::WindowId, dialog: &FileDialog) -> DialogId {
+        self.inner.save_file_dialog(window, dialog)
+    }
+
+    fn take_dialog_result(&self, dialog: DialogId) -> Vec<PathBuf> {
+        self.inner.take_dialog_result(dialog)
+    }
+
+    fn set_timer(&self, window: super::WindowId, duration: Duration, repeating: bool) -> TimerId {
+        self.inner.set_timer(window, duration, repeating)
+    }
+
+    fn cancel_timer(&self, window: super::WindowId, timer: TimerId) {
+        self.inner.cancel_timer(window, timer);
+    }
+
+    fn add_tray_icon(&self, window: super::WindowId, icon: &TrayIcon) -> TrayIconId {
+        self.inner.add_tray_icon(window, icon)
+    }
+
+    fn update_tray_icon(&self, tray: TrayIconId, icon: &TrayIcon) {
+        self.inner.update_tray_icon(tray, icon);
+    }
+
+    fn remove_tray_icon(&self, tray: TrayIconId) {
+        self.inner.remove_tray_icon(tray);
+    }
+
+    fn set_menu_bar(&self, window: super::WindowId, menu: Option<&Menu>) {
+        self.inner.set_menu_bar(window, menu);
+    }
+
+    fn xlib_window(&self, window: super::WindowId) -> (*mut c_void, c_ulong) {
+        self.inner.xlib_window(window)
+    }
+}
+
+#[derive(Clone)]
+pub struct EventLoopProxy {
+    /// The socket that the event loop waits on.
+    wake: Arc<UnixStream>,
+    events: Arc<Mutex<Vec<UserEvent>>>,
+}
+
+impl EventLoopProxy {
+    pub fn send(&self, event: UserEvent) -> Result<(), Error> {
+        self.events.lock().unwrap().push(event);
+
+        // A full socket already holds bytes that will wake the event loop.
+        // Writing fails once the event loop's end has closed, with the event
+        // loop.
+        match (&*self.wake).write(&[0]) {
+            Ok(_) => Ok(()),
+            Err(error) if error.kind() == ErrorKind::WouldBlock => Ok(()),
+            Err(_) => Err(Error::ShuttingDown),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Clipboard {
+    /// Owns the clipboard's text while it is set by the program. Clipboards
+    /// outlive the shell only when they are kept by widgets after the event
+    /// loop ends.
+    shell: Weak<Inner>,
+}
+
+impl Clipboard {
+    pub fn text(&self) -> Option<String> {
+        self.shell.upgrade()?.clipboard_text()
+    }
+
+    pub fn set_text(&self, text: &str) {
+        let shell = match self.shell.upgrade() {
+            Some(shell) => shell,
+            None => return,
+        };
+
+        *shell.clipboard_text.borrow_mut() = Some(text.replace("\r\n", "\n"));
+        unsafe {
+            (shell.xlib.XSetSelectionOwner)(
+                shell.display,
+                shell.atoms.clipboard,
+                shell.selection_window,
+                xlib::CurrentTime,
+            );
+            (shell.xlib.XFlush)(shell.display);
+        }
+    }
+}
+
+/// The atoms used by the shell, interned when it is initialized.
+struct Atoms {
+    wm_protocols: xlib::Atom,
+    wm_delete_window: xlib::Atom,
+    wm_state: xlib::Atom,
+    utf8_string: xlib::Atom,
+    net_wm_name: xlib::Atom,
+    net_wm_state: xlib::Atom,
+    net_wm_state_maximized_vert: xlib::Atom,
+    net_wm_state_maximized_horz: xlib::Atom,
+    net_wm_state_fullscreen: xlib::Atom,
+    net_wm_state_above: xlib::Atom,
+    net_wm_state_skip_taskbar: xlib::Atom,
+    net_wm_window_type: xlib::Atom,
+    net_wm_window_type_normal: xlib::Atom,
+    net_wm_window_type_utility: xlib::Atom,
+    net_wm_icon: xlib::Atom,
+    net_frame_extents: xlib::Atom,
+    motif_wm_hints: xlib::Atom,
+    clipboard: xlib::Atom,
+    targets: xlib::Atom,
+    /// The property of the selection window that the clipboard's contents
+    /// are written to when it is read.
+    selection: xlib::Atom,
+    xembed_info: xlib::Atom,
+    /// The selection owned by the system tray of the shell's screen.
+    net_system_tray: xlib::Atom,
+    net_system_tray_opcode: xlib::Atom,
+}
+
+impl Atoms {
+    fn intern(xlib: &xlib::Xlib, display: *mut xlib::Display, screen: c_int) -> Self {
+        let intern = |name: &str| {
+            let name = CString::new(name).unwrap();
+            unsafe { (xlib.XInternAtom)(display, name.as_ptr(), xlib::False) }
+        };
+
+        Self {
+            wm_protocols: intern("WM_PROTOCOLS"),
+            wm_delete_window: intern("WM_DELETE_WINDOW"),
+            wm_state: intern("WM_STATE"),
+            utf8_string: intern("UTF8_STRING"),
+            net_wm_name: intern("_NET_WM_NAME"),
+            net_wm_state: intern("_NET_WM_STATE"),
+            net_wm_state_maximized_vert: intern("_NET_WM_STATE_MAXIMIZED_VERT"),
+            net_wm_state_maximized_horz: intern("_NET_WM_STATE_MAXIMIZED_HORZ"),
+            net_wm_state_fullscreen: intern("_NET_WM_STATE_FULLSCREEN"),
+            net_wm_state_above: intern("_NET_WM_STATE_ABOVE"),
+            net_wm_state_skip_taskbar: intern("_NET_WM_STATE_SKIP_TASKBAR"),
+            net_wm_window_type: intern("_NET_WM_WINDOW_TYPE"),
+            net_wm_window_type_normal: intern("_NET_WM_WINDOW_TYPE_NORMAL"),
+            net_wm_window_type_utility: intern("_NET_WM_WINDOW_TYPE_UTILITY"),
+            net_wm_icon: intern("_NET_WM_ICON"),
+            net_frame_extents: intern("_NET_FRAME_EXTENTS"),
+            motif_wm_hints: intern("_MOTIF_WM_HINTS"),
+            clipboard: intern("CLIPBOARD"),
+            targets: intern("TARGETS"),
+            selection: intern("FATHOM_SELECTION"),
+            xembed_info: intern("_XEMBED_INFO"),
+            net_system_tray: intern(&format!("_NET_SYSTEM_TRAY_S{screen}")),
+            net_system_tray_opcode: intern("_NET_SYSTEM_TRAY_OPCODE"),
+        }
+    }
+}
+
+/// A fullscreen window. The window manager restores the window when it leaves
+/// fullscreen.
+#[derive(Clone, Copy)]
+struct Fullscreen {
+    window: xlib::Window,
+    mode: FullscreenMode,
+    /// The placement restored with `Shell::restore_placement` while the
+    /// window was fullscreen, which is applied once it leaves fullscreen.
+    placement: Option<WindowPlacement>,
+}
+
+/// A timer started with `Shell::set_timer`.
+struct Timer {
+    id: u64,
+    window: xlib::Window,
+    deadline: Instant,
+    /// How often the timer repeats, or `None` if it stops after firing once.
+    interval: Option<Duration>,
+}
+
+/// An icon added with `Shell::add_tray_icon`.
+struct Tray {
+    id: u64,
+    /// The window that owns the icon.
+    window: xlib::Window,
+    /// The window embedded in the system tray that the icon is drawn in.
+    tray_window: xlib::Window,
+    /// The icon's extent and its pixels as 32-bit ARGB, or `None` for a blank
+    /// icon.
+    icon: Option<(Extent, Vec<u32>)>,
+}
+
+pub(super) struct Inner {
+    xlib: xlib::Xlib,
+    /// Used to find the monitors, or `None` if libXrandr isn't installed and
+    /// the screen is treated as one monitor.
+    xrandr: Option<xlib::Xrandr>,
+    display: *mut xlib::Display,
+    screen: c_int,
+    root: xlib::Window,
+    atoms: Atoms,
+    /// The input method, or null if one couldn't be opened.
+    im: xlib::XIM,
+    /// An invisible window that owns the clipboard while the program has set
+    /// it, and receives its contents when it is read.
+    selection_window: xlib::Window,
+    /// The event loop's end of the socket that wakes it, which is read until
+    /// it is empty each time the event loop runs.
+    wake: UnixStream,
+    /// The other end of the socket, written to by proxies and dialogs.
+    proxy_wake: Arc<UnixStream>,
+    /// Events sent while the event callback couldn't receive them, either
+    /// before the event loop started or while the callback was running.
+    event_buffer: RefCell<Vec<Event>>,
+    /// A simple array used to keep track of every currently open window.
+    windows: RefCell<Vec<xlib::Window>>,
+    /// The windows to repaint the next time the event loop runs.
+    redraw_requests: RefCell<Vec<xlib::Window>>,
+    /// The windows to destroy the next time the event loop runs, once the
+    /// callback has returned.
+    destroy_requests: RefCell<Vec<xlib::Window>>,
+    /// The size of each window, as of the last `ConfigureNotify`.
+    extents: RefCell<Vec<(xlib::Window, PhysicalExtent)>>,
+    minimized: RefCell<Vec<xlib::Window>>,
+    /// The input context of each window, if there is an input method.
+    input_contexts: RefCell<Vec<(xlib::Window, xlib::XIC)>>,
+    /// The colormaps created for transparent windows, which are freed with
+    /// them.
+    colormaps: RefCell<Vec<(xlib::Window, xlib::Colormap)>>,
+    /// The cursors loaded for each icon, which are shared between windows.
+    font_cursors: RefCell<Vec<(CursorIcon, xlib::Cursor)>>,
+    /// The cursor set for each window with [`super::Shell::set_cursor`].
+    /// Windows without one show the default arrow.
+    cursors: RefCell<Vec<(xlib::Window, xlib::Cursor)>>,
+    /// Windows that hide the cursor while the mouse is over them.
+    hidden_cursors: RefCell<Vec<xlib::Window>>,
+    /// An invisible cursor, created the first time a cursor is hidden.
+    blank_cursor: Cell<xlib::Cursor>,
+    /// The smallest and largest extents of each window that has been given
+    /// size limits.
+    size_limits: RefCell<Vec<(xlib::Window, PhysicalExtent, PhysicalExtent)>>,
+    /// Windows that the user can't resize.
+    fixed_size: RefCell<Vec<xlib::Window>>,
+    /// The windows that are fullscreen.
+    fullscreen: RefCell<Vec<Fullscreen>>,
+    /// Where each window was on the screen the last time it wasn't maximized
+    /// or fullscreen, not including its borders and title bar.
+    normal_frames: RefCell<Vec<(xlib::Window, PhysicalRect)>>,
+    /// The window with the keyboard focus.
+    focused: Cell<Option<xlib::Window>>,
+    /// The window that captured the mouse with
+    /// [`super::Shell::set_mouse_capture`].
+    captured: Cell<Option<xlib::Window>>,
+    /// The window that confines the cursor, and the invisible child window
+    /// covering the area it is confined to. The pointer is grabbed with the
+    /// child as its confinement whenever the window is focused.
+    cursor_clip: Cell<Option<(xlib::Window, xlib::Window)>>,
+    next_dialog: Cell<u64>,
+    /// The files chosen in each dialog that has closed, until they are taken.
+    /// Dialogs run on their own threads.
+    #[allow(clippy::type_complexity)]
+    dialog_results: Arc<Mutex<Vec<(u64, Vec<PathBuf>)>>>,
+    /// The dialogs that have closed since the event loop last ran, and the
+    /// windows that own them.
+    closed_dialogs: Arc<Mutex<Vec<(xlib::Window, u64)>>>,
+    next_timer: Cell<u64>,
+    timers: RefCell<Vec<Timer>>,
+    next_tray: Cell<u64>,
+    tray_icons: RefCell<Vec<Tray>>,
+    /// The accelerators of the enabled items in each window's menu bar.
+    #[allow(clippy::type_complexity)]
+    accelerators: RefCell<Vec<(xlib::Window, Vec<(Accelerator, MenuItemId)>)>>,
+    /// The text that the program put on the clipboard, while it owns the
+    /// clipboard.
+    clipboard_text: RefCell<Option<String>>,
+    /// The events sent by proxies, until they are taken.
+    user_events: Arc<Mutex<Vec<UserEvent>>>,
+    dpi: u32,
+    subpixel_text: bool,
+    is_shutting_down: Cell<bool>,
+    /// Set once the callback asks to exit. It is kept apart from `event_mode`,
+    /// which the callback may change again before the event loop checks it.
+    exit_requested: Cell<bool>,
+    event_mode: Cell<EventLoopControl>,
+    #[allow(clippy::type_complexity)]
+    event_callback:
+        RefCell<Option<Box<dyn FnMut(Event, &dyn super::Shell, &mut EventLoopControl)>>>,
+}
+
+impl Inner {
+    /// Logs the errors that the X server reported since the last check,
+    /// returning the first of them.
+    fn check_errors(&self) -> Result<(), Error> {
+        let errors = std::mem::take(
+            &mut *PROTOCOL_ERRORS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+
+        let mut first = None;
+        for error in errors {
+            let mut text = [0 as c_char; 256];
+            let text = unsafe {
+                (self.xlib.XGetErrorText)(
+                    self.display,
+                    c_int::from(error.error_code),
+                    text.as_mut_ptr(),
+                    text.len() as c_int,
+                );
+                CStr::from_ptr(text.as_ptr()).to_string_lossy()
+            };
+
+            let message = format!(
+                "{text} in request {}.{} for resource {:#x}",
+                error.request_code, error.minor_code, error.resource
+            );
+            eprintln!("X11 error: {message}");
+            first.get_or_insert(message);
+        }
+
+        match first {
+            Some(message) => Err(Error::WindowSystem(message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Waits until there are events, a proxy or dialog wakes the event loop,
+    /// or the next timer is due. `XPending` also sends the requests made
+    /// since the event loop last waited.
+    fn wait_for_input(&self) {
+        if unsafe { (self.xlib.XPending)(self.display) } > 0 {
+            return;
+        }
+
+        // Rounded up, so that the timer is due once the wait ends.
+        let timeout = self
+            .timers
+            .borrow()
+            .iter()
+            .map(|timer| timer.deadline)
+            .min()
+            .map_or(-1, |deadline| {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                c_int::try_from(remaining.as_micros().div_ceil(1000)).unwrap_or(c_int::MAX)
+            });
+
+        let mut fds = [
+            libc::pollfd {
+                fd: unsafe { (self.xlib.XConnectionNumber)(self.display) },
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: self.wake.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+
+        unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+    }
+
+    /// Removes everything that the shell knows about a window that is being
+    /// destroyed.
+    fn forget_window(&self, window: xlib::Window) {
+        self.windows.borrow_mut().retain(|w| *w != window);
+        self.redraw_requests.borrow_mut().retain(|w| *w != window);
+        self.extents.borrow_mut().retain(|(w, _)| *w != window);
+        self.minimized.borrow_mut().retain(|w| *w != window);
+        self.cursors.borrow_mut().retain(|(w, _)| *w != window);
+        self.hidden_cursors.borrow_mut().retain(|w| *w != window);
+        self.size_limits
+            .borrow_mut()
+            .retain(|(w, _, _)| *w != window);
+        self.fixed_size.borrow_mut().retain(|w| *w != window);
+        self.fullscreen.borrow_mut().retain(|f| f.window != window);
+        self.normal_frames
+            .borrow_mut()
+            .retain(|(w, _)| *w != window);
+        self.accelerators.borrow_mut().retain(|(w, _)| *w != window);
+        self.timers.borrow_mut().retain(|t| t.window != window);
+        self.tray_icons.borrow_mut().retain(|tray| {
+            if tray.window == window {
+                unsafe { (self.xlib.XDestroyWindow)(self.display, tray.tray_window) };
+            }
+            tray.window != window
+        });
+        self.input_contexts.borrow_mut().retain(|(w, ic)| {
+            if *w == window {
+                unsafe { (self.xlib.XDestroyIC)(*ic) };
+            }
+            *w != window
+        });
+
+        // The confinement's child window is destroyed with the window.
+        if matches!(self.cursor_clip.get(), Some((w, _)) if w == window) {
+            self.cursor_clip.set(None);
+        }
+        if self.captured.get() == Some(window) {
+            self.captured.set(None);
+        }
+        if self.focused.get() == Some(window) {
+            self.focused.set(None);
+        }
+        self.update_pointer_grab();
+    }
+
+    fn input_context(&self, window: xlib::Window) -> Option<xlib::XIC> {
+        self.input_contexts
+            .borrow()
+            .iter()
+            .find(|(w, _)| *w == window)
+            .map(|(_, ic)| *ic)
+    }
+
+    /// The size of the window, not including its borders and title bar.
+    fn extent(&self, window: xlib::Window) -> PhysicalExtent {
+        self.extents
+            .borrow()
+            .iter()
+            .find(|(w, _)| *w == window)
+            .map_or(PhysicalExtent::default(), |(_, extent)| *extent)
+    }
+
+    /// Where the window is on the screen, not including its borders and
+    /// title bar.
+    fn client_rect(&self, window: xlib::Window) -> PhysicalRect {
+        let (mut x, mut y, mut child) = (0, 0, 0);
+        unsafe {
+            (self.xlib.XTranslateCoordinates)(
+                self.display,
+                window,
+                self.root,
+                0,
+                0,
+                &mut x,
+                &mut y,
+                &mut child,
+            )
+        };
+
+        PhysicalRect::new(PhysicalPoint::new(x as i16, y as i16), self.extent(window))
+    }
+
+    /// Where the window was on the screen the last time it wasn't maximized
+    /// or fullscreen, not including its borders and title bar.
+    fn normal_frame(&self, window: xlib::Window) -> PhysicalRect {
+        let saved = self
+            .normal_frames
+            .borrow()
+            .iter()
+            .find(|(w, _)| *w == window)
+            .map(|(_, rect)| *rect);
+
+        match saved {
+            Some(rect) => rect,
+            None => self.client_rect(window),
+        }
+    }
+
+    /// The widths of the borders and title bar that the window manager adds
+    /// to the window, as left, right, top, and bottom. Zero until the window
+    /// manager has decorated the window.
+    fn frame_extents(&self, window: xlib::Window) -> (i16, i16, i16, i16) {
+        match self.property(window, self.atoms.net_frame_extents, xlib::XA_CARDINAL)[..] {
+            [left, right, top, bottom] => (left as i16, right as i16, top as i16, bottom as i16),
+            _ => (0, 0, 0, 0),
+        }
+    }
+
+    /// The bounds of each monitor, with the primary monitor first.
+    fn monitors(&self) -> Vec<PhysicalRect> {
+        let mut monitors = Vec::new();
+
+        if let Some(xrandr) = &self.xrandr {
+            let mut count = 0;
+            let info =
+                unsafe { (xrandr.XRRGetMonitors)(self.display, self.root, xlib::True, &mut count) };
+
+            if !info.is_null() {
+                let info = unsafe { std::slice::from_raw_parts(info, count.max(0) as usize) };
+                let mut sorted: Vec<_> = info.iter().collect();
+                sorted.sort_by_key(|monitor| monitor.primary == xlib::False);
+
+                monitors.extend(sorted.into_iter().map(|monitor| {
+                    PhysicalRect::new(
+                        PhysicalPoint::new(monitor.x as i16, monitor.y as i16),
+                        PhysicalExtent {
+                            width: PhysicalPx(monitor.width as i16),
+                            height: PhysicalPx(monitor.height as i16),
+                        },
+                    )
+                }));
+
+                unsafe { (xrandr.XRRFreeMonitors)(info.as_ptr().cast_mut()) };
+            }
+        }
+
+        if monitors.is_empty() {
+            let (width, height) = unsafe {
+                (
+                    (self.xlib.XDisplayWidth)(self.display, self.screen),
+                    (self.xlib.XDisplayHeight)(self.display, self.screen),
+                )
+            };
+
+            monitors.push(PhysicalRect::new(
+                PhysicalPoint::new(0, 0),
+                PhysicalExtent {
+                    width: PhysicalPx(width as i16),
+                    height: PhysicalPx(height as i16),
+                },
+            ));
+        }
+
+        monitors
+    }
+
+    /// The monitor that the center of `area` is on, or the primary monitor
+    /// if it is off the screen.
+    fn monitor_for(&self, area: PhysicalRect) -> PhysicalRect {
+        let center = PhysicalPoint::new(
+            ((i32::from(area.left.0) + i32::from(area.right.0)) / 2) as i16,
+            ((i32::from(area.top.0) + i32::from(area.bottom.0)) / 2) as i16,
+        );
+
+        let monitors = self.monitors();
+        monitors
+            .iter()
+            .find(|monitor| monitor.contains_point(center))
+            .copied()
+            .unwrap_or(monitors[0])
+    }
+
+    /// Reads a property of the window made of 32-bit items, which Xlib
+    /// returns as `c_ulong`s. Empty if the property isn't set, or isn't of
+    /// type `kind`.
+    fn property(
+        &self,
+        window: xlib::Window,
+        property: xlib::Atom,
+        kind: xlib::Atom,
+    ) -> Vec<c_ulong> {
+        let mut actual_kind = 0;
+        let mut format = 0;
+        let mut count = 0;
+        let mut remaining = 0;
+        let mut data = null_mut();
+
+        let status = unsafe {
+            (self.xlib.XGetWindowProperty)(
+                self.display,
+                window,
+                property,
+                0,
+                1024,
+                xlib::False,
+                kind,
+                &mut actual_kind,
+                &mut format,
+                &mut count,
+                &mut remaining,
+                &mut data,
+            )
+        };
+
+        if status != xlib::Success || data.is_null() {
+            return Vec::new();
+        }
+
+        let items = if format == 32 && actual_kind == kind {
+            unsafe { std::slice::from_raw_parts(data.cast::<c_ulong>(), count as usize) }.to_vec()
+        } else {
+            Vec::new()
+        };
+
+        unsafe { (self.xlib.XFree)(data.cast()) };
+        items
+    }
+
+    /// Replaces a property of the window with 32-bit items, which Xlib takes
+    /// as `c_ulong`s.
+    fn set_property(
+        &self,
+        window: xlib::Window,
+        property: xlib::Atom,
+        kind: xlib::Atom,
+        items: &[c_ulong],
+    ) {
+        unsafe {
+            (self.xlib.XChangeProperty)(
+                self.display,
+                window,
+                property,
+                kind,
+                32,
+                xlib::PropModeReplace,
+                items.as_ptr().cast(),
+                items.len() as c_int,
+            )
+        };
+    }
+
+    fn is_maximized(&self, window: xlib::Window) -> bool {
+        let state = self.property(window, self.atoms.net_wm_state, xlib::XA_ATOM);
+        state.contains(&self.atoms.net_wm_state_maximized_vert)
+            && state.contains(&self.atoms.net_wm_state_maximized_horz)
+    }
+
+    fn is_fullscreen(&self, window: xlib::Window) -> bool {
+        self.fullscreen.borrow().iter().any(|f| f.window == window)
+    }
+
+    /// Asks the window manager to add or remove up to two states, such as
+    /// maximized, from the window's `_NET_WM_STATE`. Pass zero for `second`
+    /// to change only one.
+    fn change_wm_state(
+        &self,
+        window: xlib::Window,
+        add: bool,
+        first: xlib::Atom,
+        second: xlib::Atom,
+    ) {
+        // The last item says that the request comes from a normal program.
+        self.send_client_message(
+            self.root,
+            window,
+            self.atoms.net_wm_state,
+            [c_long::from(add), first as c_long, second as c_long, 1, 0],
+            xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+        );
+    }
+
+    fn send_client_message(
+        &self,
+        destination: xlib::Window,
+        window: xlib::Window,
+        message_type: xlib::Atom,
+        data: [c_long; 5],
+        mask: c_long,
+    ) {
+        let mut event = xlib::XEvent {
+            client_message: xlib::XClientMessageEvent {
+                type_: xlib::ClientMessage,
+                serial: 0,
+                send_event: xlib::True,
+                display: self.display,
+                window,
+                message_type,
+                format: 32,
+                data,
+            },
+        };
+
+        unsafe { (self.xlib.XSendEvent)(self.display, destination, xlib::False, mask, &mut event) };
+    }
+
+    /// The smallest and largest extents that the window can be resized to.
+    fn size_limits(&self, window: xlib::Window) -> (PhysicalExtent, PhysicalExtent) {
+        self.size_limits
+            .borrow()
+            .iter()
+            .find(|(w, _, _)| *w == window)
+            .map_or(
+                (PhysicalExtent::default(), PhysicalExtent::MAX),
+                |(_, min, max)| (*min, *max),
+            )
+    }
+
+    /// Tells the window manager how small and large the user can make the
+    /// window. Windows that can't be resized are held at `extent`.
+    fn update_size_hints(&self, window: xlib::Window, extent: PhysicalExtent) {
+        let (mut min, mut max) = self.size_limits(window);
+        if self.fixed_size.borrow().contains(&window) {
+            min = extent;
+            max = extent;
+        }
+
+        let mut hints = xlib::XSizeHints::default();
+
+        if min != PhysicalExtent::default() {
+            hints.flags |= xlib::PMinSize;
+            hints.min_width = min.width.0.into();
+            hints.min_height = min.height.0.into();
+        }
+
+        if max != PhysicalExtent::MAX {
+            hints.flags |= xlib::PMaxSize;
+            hints.max_width = max.width.0.into();
+            hints.max_height = max.height.0.into();
+        }
+
+        unsafe { (self.xlib.XSetWMNormalHints)(self.display, window, &mut hints) };
+    }
+
+    /// Resizes the window, which must not be maximized or fullscreen, to
+    /// `extent` within its limits.
+    fn resize(&self, window: xlib::Window, extent: PhysicalExtent) {
+        let (min, max) = self.size_limits(window);
+        let extent = extent.clamp(min, max);
+        self.update_size_hints(window, extent);
+
+        unsafe {
+            (self.xlib.XResizeWindow)(
+                self.display,
+                window,
+                extent.width.0.max(1) as c_uint,
+                extent.height.0.max(1) as c_uint,
+            )
+        };
+    }
+
+    /// The cursor for `icon`, loaded from the cursor font the first time it
+    /// is used.
+    fn font_cursor(&self, icon: CursorIcon) -> xlib::Cursor {
+        let loaded = self
+            .font_cursors
+            .borrow()
+            .iter()
+            .find(|(i, _)| *i == icon)
+            .map(|(_, cursor)| *cursor);
+
+        if let Some(cursor) = loaded {
+            return cursor;
+        }
+
+        let shape = match icon {
+            CursorIcon::Arrow => xlib::XC_left_ptr,
+            CursorIcon::Text => xlib::XC_xterm,
+            CursorIcon::Hand => xlib::XC_hand2,
+            CursorIcon::Move => xlib::XC_fleur,
+            CursorIcon::ResizeHorizontal => xlib::XC_sb_h_double_arrow,
+            CursorIcon::ResizeVertical => xlib::XC_sb_v_double_arrow,
+            CursorIcon::NotAllowed => xlib::XC_X_cursor,
+            CursorIcon::Wait => xlib::XC_watch,
+        };
+
+        let cursor = unsafe { (self.xlib.XCreateFontCursor)(self.display, shape) };
+        self.font_cursors.borrow_mut().push((icon, cursor));
+        cursor
+    }
+
+    /// A cursor without any visible pixels, for windows that hide the cursor.
+    fn blank_cursor(&self) -> xlib::Cursor {
+        if self.blank_cursor.get() != 0 {
+            return self.blank_cursor.get();
+        }
+
+        let data = [0 as c_char; 1];
+        let mut color = xlib::XColor::default();
+
+        let cursor = unsafe {
+            let pixmap =
+                (self.xlib.XCreateBitmapFromData)(self.display, self.root, data.as_ptr(), 1, 1);
+            let cursor = (self.xlib.XCreatePixmapCursor)(
+                self.display,
+                pixmap,
+                pixmap,
+                &mut color,
+                &mut color,
+                0,
+                0,
+            );
+            (self.xlib.XFreePixmap)(self.display, pixmap);
+            cursor
+        };
+
+        self.blank_cursor.set(cursor);
+        cursor
+    }
+
+    /// Grabs the pointer for the focused window's confinement or the window
+    /// that captured the mouse, or releases it if there is neither.
+    fn update_pointer_grab(&self) {
+        let confinement = self
+            .cursor_clip
+            .get()
+            .filter(|(window, _)| Some(*window) == self.focused.get());
+
+        let grab = match (confinement, self.captured.get()) {
+            (Some((window, area)), _) => Some((window, area)),
+            (None, Some(window)) => Some((window, 0)),
+            (None, None) => None,
+        };
+
+        // Events over the program's windows are still delivered to them, and
+        // the rest to the window that grabbed the pointer.
+        unsafe {
+            match grab {
+                Some((window, confine_to)) => (self.xlib.XGrabPointer)(
+                    self.display,
+                    window,
+                    xlib::True,
+                    (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::PointerMotionMask)
+                        as c_uint,
+                    xlib::GrabModeAsync,
+                    xlib::GrabModeAsync,
+                    confine_to,
+                    0,
+                    xlib::CurrentTime,
+                ),
+                None => (self.xlib.XUngrabPointer)(self.display, xlib::CurrentTime),
+            }
+        };
+    }
+
+    /// Finds the item in the window's menu bar that the keys select.
+    fn accelerator(
+        &self,
+        window: xlib::Window,
+        key: Key,
+        modifiers: Modifiers,
+    ) -> Option<MenuItemId> {
+        let accelerator = Accelerator::new(key, modifiers);
+        self.accelerators
+            .borrow()
+            .iter()
+            .find(|(w, _)| *w == window)
+            .and_then(|(_, accelerators)| {
+                accelerators
+                    .iter()
+                    .find(|(a, _)| *a == accelerator)
+                    .map(|(_, item)| *item)
+            })
+    }
+
+    /// Reads the text typed by a key press, after the keyboard layout and the
+    /// input method have been applied.
+    fn typed_text(&self, window: xlib::Window, key: &mut xlib::XKeyEvent) -> String {
+        let mut buffer = vec![0_u8; 64];
+
+        let ic = match self.input_context(window) {
+            Some(ic) => ic,
+            None => {
+                // Without an input method, the text is Latin-1.
+                let len = unsafe {
+                    (self.xlib.XLookupString)(
+                        key,
+                        buffer.as_mut_ptr().cast(),
+                        buffer.len() as c_int,
+                        null_mut(),
+                        null_mut(),
+                    )
+                };
+                return buffer[..len.max(0) as usize]
+                    .iter()
+                    .map(|&byte| char::from(byte))
+                    .collect();
+            }
+        };
+
+        let mut status = 0;
+        let mut lookup = |buffer: &mut Vec<u8>, status: &mut c_int| unsafe {
+            (self.xlib.Xutf8LookupString)(
+                ic,
+                key,
+                buffer.as_mut_ptr().cast(),
+                buffer.len() as c_int,
+                null_mut(),
+                status,
+            )
+        };
+
+        let mut len = lookup(&mut buffer, &mut status);
+        if status == xlib::XBufferOverflow {
+            buffer.resize(len.max(0) as usize, 0);
+            len = lookup(&mut buffer, &mut status);
+        }
+
+        if status == xlib::XLookupChars || status == xlib::XLookupBoth {
+            String::from_utf8_lossy(&buffer[..len.max(0) as usize]).into_owned()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Reads the text on the clipboard, waiting for its owner to answer.
+    /// Other events stay queued until the event loop runs again. Transfers
+    /// too large to be sent at once aren't supported.
+    fn clipboard_text(&self) -> Option<String> {
+        let xlib = &self.xlib;
+
+        let owner = unsafe { (xlib.XGetSelectionOwner)(self.display, self.atoms.clipboard) };
+        if owner == 0 {
+            return None;
+        } else if owner == self.selection_window {
+            return self.clipboard_text.borrow().clone();
+        }
+
+        unsafe {
+            (xlib.XConvertSelection)(
+                self.display,
+                self.atoms.clipboard,
+                self.atoms.utf8_string,
+                self.atoms.selection,
+                self.selection_window,
+                xlib::CurrentTime,
+            );
+            (xlib.XFlush)(self.display);
+        }
+
+        let deadline = Instant::now() + CLIPBOARD_TIMEOUT;
+        let mut event = xlib::XEvent::default();
+        while unsafe {
+            (xlib.XCheckTypedWindowEvent)(
+                self.display,
+                self.selection_window,
+                xlib::SelectionNotify,
+                &mut event,
+            )
+        } == xlib::False
+        {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return None;
+            }
+
+            let mut fd = libc::pollfd {
+                fd: unsafe { (xlib.XConnectionNumber)(self.display) },
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let timeout = remaining.as_millis().clamp(1, 10) as c_int;
+            unsafe { libc::poll(&mut fd, 1, timeout) };
+        }
+
+        // The owner couldn't convert the clipboard to text.
+        if unsafe { event.selection.property } == 0 {
+            return None;
+        }
+
+        let mut kind = 0;
+        let mut format = 0;
+        let mut count = 0;
+        let mut remaining = 0;
+        let mut data = null_mut();
+
+        let status = unsafe {
+            (xlib.XGetWindowProperty)(
+                self.display,
+                self.selection_window,
+                self.atoms.selection,
+                0,
+                c_long::MAX / 4,
+                xlib::True,
+                xlib::AnyPropertyType,
+                &mut kind,
+                &mut format,
+                &mut count,
+                &mut remaining,
+                &mut data,
+            )
+        };
+
+        if status != xlib::Success || data.is_null() {
+            return None;
+        }
+
+        let text = if format == 8 {
+            let bytes = unsafe { std::slice::from_raw_parts(data, count as usize) };
+            Some(String::from_utf8_lossy(bytes).replace("\r\n", "\n"))
+        } else {
+            None
+        };
+
+        unsafe { (xlib.XFree)(data.cast()) };
+        text
+    }
+
+    /// Sends the clipboard's text to another program that asked for it, as
+    /// UTF-8 or Latin-1, or tells it which of those it can ask for.
+    fn answer_selection_request(&self, request: &xlib::XSelectionRequestEvent) {
+        let atoms = &self.atoms;
+        let text = self.clipboard_text.borrow();
+
+        // Obsolete programs don't name a property, and expect the target to
+        // be used instead.
+        let property = if request.property == 0 {
+            request.target
+        } else {
+            request.property
+        };
+
+        let change = |kind: xlib::Atom, format: c_int, data: &[u8], count: usize| unsafe {
+            (self.xlib.XChangeProperty)(
+                self.display,
+                request.requestor,
+                property,
+                kind,
+                format,
+                xlib::PropModeReplace,
+                data.as_ptr(),
+                count as c_int,
+            );
+        };
+
+        let answered = match text.as_deref() {
+            Some(_) if request.selection != atoms.clipboard => false,
+            Some(_) if request.target == atoms.targets => {
+                let targets: [c_ulong; 3] = [atoms.targets, atoms.utf8_string, xlib::XA_STRING];
+                let bytes = unsafe {
+                    std::slice::from_raw_parts(
+                        targets.as_ptr().cast::<u8>(),
+                        std::mem::size_of_val(&targets),
+                    )
+                };
+                change(xlib::XA_ATOM, 32, bytes, targets.len());
+                true
+            }
+            Some(text) if request.target == atoms.utf8_string => {
+                change(atoms.utf8_string, 8, text.as_bytes(), text.len());
+                true
+            }
+            Some(text) if request.target == xlib::XA_STRING => {
+                let latin1: Vec<u8> = text
+                    .chars()
+                    .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+                    .collect();
+                change(xlib::XA_STRING, 8, &latin1, latin1.len());
+                true
+            }
+            _ => false,
+        };
+
+        let mut reply = xlib::XEvent {
+            selection: xlib::XSelectionEvent {
+                type_: xlib::SelectionNotify,
+                serial: 0,
+                send_event: xlib::True,
+                display: self.display,
+                requestor: request.requestor,
+                selection: request.selection,
+                target: request.target,
+                property: if answered { property } else { 0 },
+                time: request.time,
+            },
+        };
+
+        unsafe {
+            (self.xlib.XSendEvent)(
+                self.display,
+                request.requestor,
+                xlib::False,
+                xlib::NoEventMask,
+                &mut reply,
+            );
+        }
+    }
+
+    /// Draws a tray icon, scaled to fit the size that the tray gave it.
+    /// Pixels that are mostly transparent show the tray behind them, and the
+    /// rest are drawn opaque.
+    fn draw_tray_icon(&self, tray_window: xlib::Window) {
+        let tray_icons = self.tray_icons.borrow();
+        let (icon_extent, pixels) = match tray_icons
+            .iter()
+            .find(|t| t.tray_window == tray_window)
+            .and_then(|t| t.icon.as_ref())
+        {
+            Some((extent, pixels)) => (*extent, pixels),
+            None => return,
+        };
+
+        let (width, height) = window_size(&self.xlib, self.display, tray_window);
+        let icon_width = icon_extent.width.0.max(0) as usize;
+        let icon_height = icon_extent.height.0.max(0) as usize;
+        if width == 0 || height == 0 || icon_width == 0 || icon_height == 0 {
+            return;
+        }
+
+        unsafe {
+            let gc = (self.xlib.XCreateGC)(self.display, tray_window, 0, null_mut());
+
+            for y in 0..height {
+                for x in 0..width {
+                    let argb =
+                        pixels[(y * icon_height / height) * icon_width + x * icon_width / width];
+                    if argb >> 24 < 128 {
+                        continue;
+                    }
+
+                    // Assumes that the tray uses a 24-bit color visual.
+                    (self.xlib.XSetForeground)(self.display, gc, c_ulong::from(argb & 0x00ff_ffff));
+                    (self.xlib.XFillRectangle)(
+                        self.display,
+                        tray_window,
+                        gc,
+                        x as c_int,
+                        y as c_int,
+                        1,
+                        1,
+                    );
+                }
+            }
+
+            (self.xlib.XFreeGC)(self.display, gc);
+        }
+    }
+
+    /// Shows a file dialog with `zenity` on its own thread, which waits for
+    /// it to close. The event loop is woken once it has, and sends
+    /// `Window::FileDialogClosed` to the owner.
+    fn show_file_dialog(
+        &self,
+        window: super::WindowId,
+        dialog: &FileDialog,
+        save: bool,
+    ) -> DialogId {
+        let id = self.next_dialog.get();
+        self.next_dialog.set(id + 1);
+
+        let mut command = Command::new("zenity");
+        command.arg("--file-selection");
+
+        if save {
+            command.args(["--save", "--confirm-overwrite"]);
+        } else if dialog.multiple {
+            command.args(["--multiple", "--separator=\n"]);
+        }
+
+        if let Some(title) = &dialog.title {
+            command.arg(format!("--title={title}"));
+        }
+
+        if let Some(file_name) = &dialog.file_name {
+            command.arg(format!("--filename={file_name}"));
+        }
+
+        command.args(dialog.filters.iter().map(file_filter_arg));
+
+        let default_extension = dialog
+            .filters
+            .first()
+            .and_then(|filter| filter.extensions.first())
+            .filter(|_| save)
+            .cloned();
+
+        let owner = window.0.window;
+        let results = self.dialog_results.clone();
+        let closed = self.closed_dialogs.clone();
+        let wake = self.proxy_wake.clone();
+
+        std::thread::spawn(move || {
+            // Nothing is chosen if the dialog was cancelled, or if `zenity`
+            // isn't installed.
+            let paths = match command.output() {
+                Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(|line| {
+                        let mut path = PathBuf::from(line);
+                        if let Some(extension) = &default_extension {
+                            if path.extension().is_none() {
+                                path.set_extension(extension);
+                            }
+                        }
+                        path
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+
+            results.lock().unwrap().push((id, paths));
+            closed.lock().unwrap().push((owner, id));
+            let _ = (&*wake).write(&[0]);
+        });
+
+        DialogId(id)
+    }
+}
+
+impl super::Shell for Rc<Inner> {
+    fn create_window(&self, config: &WindowConfig) -> Result<super::WindowId, Error> {
+        if self.is_shutting_down.get() {
+            return Err(Error::ShuttingDown);
+        }
+
+        let xlib = &self.xlib;
+        let extent = config.extent.unwrap_or(DEFAULT_EXTENT);
+
+        let mut attributes = xlib::XSetWindowAttributes {
+            event_mask: WINDOW_EVENT_MASK,
+            ..Default::default()
+        };
+        let mut attribute_mask = xlib::CWBackPixmap | xlib::CWBorderPixel | xlib::CWEventMask;
+
+        // Transparent windows need a visual with an alpha channel, which the
+        // compositor blends with what is behind them. They are drawn as-is
+        // if there isn't one.
+        let mut depth = 0;
+        let mut visual = null_mut();
+        let mut colormap = 0;
+        if config.transparent {
+            let mut info = xlib::XVisualInfo::default();
+            if unsafe {
+                (xlib.XMatchVisualInfo)(self.display, self.screen, 32, xlib::TrueColor, &mut info)
+            } != 0
+            {
+                colormap = unsafe {
+                    (xlib.XCreateColormap)(self.display, self.root, info.visual, xlib::AllocNone)
+                };
+                attributes.colormap = colormap;
+                attribute_mask |= xlib::CWColormap;
+                depth = info.depth;
+                visual = info.visual;
+            }
+        }
+
+        let window = unsafe {
+            (xlib.XCreateWindow)(
+                self.display,
+                self.root,
+                0,
+                0,
+                extent.width.0.max(1) as c_uint,
+                extent.height.0.max(1) as c_uint,
+                0,
+                depth,
+                xlib::InputOutput,
+                visual,
+                attribute_mask,
+                &mut attributes,
+            )
+        };
+
+        // Waits for the server to create the window, so that a visual it
+        // doesn't accept fails here instead of later.
+        unsafe { (xlib.XSync)(self.display, xlib::False) };
+        if let Err(error) = self.check_errors() {
+            unsafe {
+                (xlib.XDestroyWindow)(self.display, window);
+                if colormap != 0 {
+                    (xlib.XFreeColormap)(self.display, colormap);
+                }
+            }
+            return Err(error);
+        }
+
+        // Closing the window is reported instead of the window manager
+        // killing the connection.
+        let mut protocols = [self.atoms.wm_delete_window];
+        unsafe { (xlib.XSetWMProtocols)(self.display, window, protocols.as_mut_ptr(), 1) };
+
+        let window_type = if config.tool_window {
+            self.atoms.net_wm_window_type_utility
+        } else {
+            self.atoms.net_wm_window_type_normal
+        };
+        self.set_property(
+            window,
+            self.atoms.net_wm_window_type,
+            xlib::XA_ATOM,
+            &[window_type],
+        );
+
+        // The window manager reads the initial states when the window is
+        // first shown, and only changes them by request after that.
+        let mut states = Vec::new();
+        if config.always_on_top {
+            states.push(self.atoms.net_wm_state_above);
+        }
+        if config.tool_window {
+            states.push(self.atoms.net_wm_state_skip_taskbar);
+        }
+        if !states.is_empty() {
+            self.set_property(window, self.atoms.net_wm_state, xlib::XA_ATOM, &states);
+        }
+
+        if config.transparent {
+            self.set_property(
+                window,
+                self.atoms.motif_wm_hints,
+                self.atoms.motif_wm_hints,
+                &[MWM_HINTS_DECORATIONS, 0, 0, 0, 0],
+            );
+        }
+
+        if !self.im.is_null() {
+            let style = xlib::XIMPreeditNothing | xlib::XIMStatusNothing;
+            let ic = unsafe {
+                (xlib.XCreateIC)(
+                    self.im,
+                    xlib::XNInputStyle.as_ptr(),
+                    style,
+                    xlib::XNClientWindow.as_ptr(),
+                    window,
+                    xlib::XNFocusWindow.as_ptr(),
+                    window,
+                    null_mut::<c_void>(),
+                )
+            };
+
+            if !ic.is_null() {
+                self.input_contexts.borrow_mut().push((window, ic));
+            }
+        }
+
+        if colormap != 0 {
+            self.colormaps.borrow_mut().push((window, colormap));
+        }
+
+        self.windows.borrow_mut().push(window);
+        self.extents.borrow_mut().push((window, extent));
+        let window_id = super::WindowId(WindowId { window });
+
+        self.set_title(window_id, config.title);
+
+        if config.min_extent.is_some() || config.max_extent.is_some() {
+            self.set_size_limits(window_id, config.min_extent, config.max_extent);
+        }
+
+        if !config.resizable {
+            self.set_resizable(window_id, false);
+        }
+
+        if config.icon.is_some() {
+            self.set_window_icon(window_id, config.icon);
+        }
+
+        if config.menu_bar.is_some() {
+            self.set_menu_bar(window_id, config.menu_bar);
+        }
+
+        unsafe {
+            (xlib.XMapWindow)(self.display, window);
+            (xlib.XFlush)(self.display);
+        }
+
+        dispatch(
+            self,
+            std::iter::once(Event::Window {
+                window_id,
+                event: WindowEvent::Init {
+                    inner_extent: extent,
+                },
+            }),
+        );
+
+        Ok(window_id)
+    }
+
+    fn destroy_window(&self, window: super::WindowId) {
+        let mut requests = self.destroy_requests.borrow_mut();
+        if !requests.contains(&window.0.window) {
+            requests.push(window.0.window);
+        }
+    }
+
+    fn show_window(&self, window: super::WindowId) {
+        unsafe { (self.xlib.XMapWindow)(self.display, window.0.window) };
+    }
+
+    fn hide_window(&self, window: super::WindowId) {
+        unsafe { (self.xlib.XUnmapWindow)(self.display, window.0.window) };
+    }
+
+    fn request_redraw(&self, window: super::WindowId) {
+        let mut requests = self.redraw_requests.borrow_mut();
+        if !requests.contains(&window.0.window) {
+            requests.push(window.0.window);
+        }
+    }
+
+    fn add_tray_icon(&self, window: super::WindowId, icon: &TrayIcon) -> TrayIconId {
+        let id = self.next_tray.get();
+        self.next_tray.set(id + 1);
+
+        // The tray's background shows through the window, and the tray
+        // resizes it to fit.
+        let mut attributes = xlib::XSetWindowAttributes {
+            background_pixmap: xlib::ParentRelative,
+            event_mask: TRAY_EVENT_MASK,
+            ..Default::default()
+        };
+
+        let tray_window = unsafe {
+            (self.xlib.XCreateWindow)(
+                self.display,
+                self.root,
+                0,
+                0,
+                icon.icon.extent().width.0.max(1) as c_uint,
+                icon.icon.extent().height.0.max(1) as c_uint,
+                0,
+                0,
+                xlib::InputOutput,
+                null_mut(),
+                xlib::CWBackPixmap | xlib::CWEventMask,
+                &mut attributes,
+            )
+        };
+
+        // The XEmbed version, and that the tray should show the window.
+        self.set_property(
+            tray_window,
+            self.atoms.xembed_info,
+            self.atoms.xembed_info,
+            &[0, 1],
+        );
+
+        let tray =
+            unsafe { (self.xlib.XGetSelectionOwner)(self.display, self.atoms.net_system_tray) };
+        if tray != 0 {
+            self.send_client_message(
+                tray,
+                tray,
+                self.atoms.net_system_tray_opcode,
+                [
+                    xlib::CurrentTime as c_long,
+                    SYSTEM_TRAY_REQUEST_DOCK,
+                    tray_window as c_long,
+                    0,
+                    0,
+                ],
+                xlib::NoEventMask,
+            );
+        }
+
+        self.tray_icons.borrow_mut().push(Tray {
+            id,
+            window: window.0.window,
+            tray_window,
+            icon: argb_pixels(icon.icon).map(|pixels| (icon.icon.extent(), pixels)),
+        });
+
+        TrayIconId(id)
+    }
+
+    fn update_tray_icon(&self, tray: TrayIconId, icon: &TrayIcon) {
+        let mut tray_icons = self.tray_icons.borrow_mut();
+        if let Some(tray) = tray_icons.iter_mut().find(|t| t.id == tray.0) {
+            tray.icon = argb_pixels(icon.icon).map(|pixels| (icon.icon.extent(), pixels));
+
+            // Clearing the window sends `Expose`, which draws the new icon.
+            unsafe {
+                (self.xlib.XClearArea)(self.display, tray.tray_window, 0, 0, 0, 0, xlib::True)
+            };
+        }
+    }
+
+    fn remove_tray_icon(&self, tray: TrayIconId) {
+        let mut tray_icons = self.tray_icons.borrow_mut();
+        if let Some(index) = tray_icons.iter().position(|t| t.id == tray.0) {
+            let tray = tray_icons.swap_remove(index);
+            unsafe { (self.xlib.XDestroyWindow)(self.display, tray.tray_window) };
+        }
+    }
+
+    fn set_menu_bar(&self, window: super::WindowId, menu: Option<&Menu>) {
+        let window = window.0.window;
+        let mut accelerators = self.accelerators.borrow_mut();
+        accelerators.retain(|(w, _)| *w != window);
+
+        if let Some(menu) = menu {
+            let mut items = Vec::new();
+            collect_accelerators(menu, &mut items);
+            accelerators.push((window, items));
+        }
+    }
+
+    fn set_title(&self, window: super::WindowId, title: &str) {
+        let window = window.0.window;
+
+        // `WM_NAME` is for window managers that don't read `_NET_WM_NAME`,
+        // and can't hold nuls.
+        let name = CString::new(title.replace('\0', "")).unwrap();
+
+        unsafe {
+            (self.xlib.XStoreName)(self.display, window, name.as_ptr());
+            (self.xlib.XChangeProperty)(
+                self.display,
+                window,
+                self.atoms.net_wm_name,
+                self.atoms.utf8_string,
+                8,
+                xlib::PropModeReplace,
+                title.as_ptr(),
+                title.len() as c_int,
+            );
+        }
+    }
+
+    fn set_extent(&self, window: super::WindowId, extent: PhysicalExtent) {
+        let window = window.0.window;
+
+        if self.is_fullscreen(window) {
+            return;
+        }
+
+        // The window manager sizes maximized windows.
+        self.change_wm_state(
+            window,
+            false,
+            self.atoms.net_wm_state_maximized_vert,
+            self.atoms.net_wm_state_maximized_horz,
+        );
+
+        self.resize(window, extent);
+    }
+
+    fn set_position(&self, window: super::WindowId, position: PhysicalPoint) {
+        let window = window.0.window;
+
+        if self.is_fullscreen(window) {
+            return;
+        }
+
+        // The window manager places the top-left of the window's frame at
+        // the position.
+        unsafe {
+            (self.xlib.XMoveWindow)(
+                self.display,
+                window,
+                position.x.0.into(),
+                position.y.0.into(),
+            )
+        };
+    }
+
+    fn minimize(&self, window: super::WindowId) {
+        unsafe { (self.xlib.XIconifyWindow)(self.display, window.0.window, self.screen) };
+    }
+
+    fn maximize(&self, window: super::WindowId) {
+        let window = window.0.window;
+        if !self.is_fullscreen(window) {
+            self.change_wm_state(
+                window,
+                true,
+                self.atoms.net_wm_state_maximized_vert,
+                self.atoms.net_wm_state_maximized_horz,
+            );
+        }
+    }
+
+    fn restore(&self, window: super::WindowId) {
+        let window = window.0.window;
+        if self.minimized.borrow().contains(&window) {
+            unsafe { (self.xlib.XMapRaised)(self.display, window) };
+        } else {
+            self.change_wm_state(
+                window,
+                false,
+                self.atoms.net_wm_state_maximized_vert,
+                self.atoms.net_wm_state_maximized_horz,
+            );
+        }
+    }
+
+    fn window_placement(&self, window: super::WindowId) -> WindowPlacement {
+        let window = window.0.window;
+
+        // Fullscreen windows return to where they were before, or to where
+        // they were placed while fullscreen.
+        let saved = self
+            .fullscreen
+            .borrow()
+            .iter()
+            .find(|f| f.window == window)
+            .and_then(|f| f.placement);
+
+        if let Some(placement) = saved {
+            return placement;
+        }
+
+        let client = self.normal_frame(window);
+        let (left, right, top, bottom) = self.frame_extents(window);
+
+        let position = PhysicalPoint::new(client.left.0 - left, client.top.0 - top);
+        let extent = PhysicalExtent {
+            width: PhysicalPx(client.width().0 + left + right),
+            height: PhysicalPx(client.height().0 + top + bottom),
+        };
+
+        WindowPlacement {
+            position,
+            extent,
+            maximized: self.is_maximized(window),
+            monitor: self.monitor_for(PhysicalRect::new(position, extent)),
+        }
+    }
+
+    fn restore_placement(&self, window: super::WindowId, placement: &WindowPlacement) {
+        let window = window.0.window;
+        let mut position = placement.position;
+        let extent = placement.extent;
+
+        // The monitor was disconnected or rearranged, so the window may not
+        // be visible where it was.
+        let monitor = self.monitor_for(PhysicalRect::new(position, extent));
+        if monitor != placement.monitor {
+            position = PhysicalPoint::new(
+                (monitor.left.0 + (monitor.width().0 - extent.width.0) / 2).max(monitor.left.0),
+                (monitor.top.0 + (monitor.height().0 - extent.height.0) / 2).max(monitor.top.0),
+            );
+        }
+
+        if let Some(entry) = self
+            .fullscreen
+            .borrow_mut()
+            .iter_mut()
+            .find(|f| f.window == window)
+        {
+            entry.placement = Some(WindowPlacement {
+                position,
+                monitor,
+                ..*placement
+            });
+            return;
+        }
+
+        let maximized = [
+            self.atoms.net_wm_state_maximized_vert,
+            self.atoms.net_wm_state_maximized_horz,
+        ];
+
+        self.change_wm_state(window, false, maximized[0], maximized[1]);
+
+        let (left, right, top, bottom) = self.frame_extents(window);
+        let (min, max) = self.size_limits(window);
+        let client = PhysicalExtent {
+            width: PhysicalPx(extent.width.0 - left - right),
+            height: PhysicalPx(extent.height.0 - top - bottom),
+        }
+        .clamp(min, max);
+        self.update_size_hints(window, client);
+
+        unsafe {
+            (self.xlib.XMoveResizeWindow)(
+                self.display,
+                window,
+                position.x.0.into(),
+                position.y.0.into(),
+                client.width.0.max(1) as c_uint,
+                client.height.0.max(1) as c_uint,
+            )
+        };
+
+        if placement.maximized {
+            self.change_wm_state(window, true, maximized[0], maximized[1]);
+        }
+    }
+
+    fn set_size_limits(
+        &self,
+        window: super::WindowId,
+        min: Option<PhysicalExtent>,
+        max: Option<PhysicalExtent>,
+    ) {
+        let window = window.0.window;
+        let mut limits = self.size_limits.borrow_mut();
+        limits.retain(|(w, _, _)| *w != window);
+
+        if min.is_some() || max.is_some() {
+            limits.push((
+                window,
+                min.unwrap_or_default(),
+                max.unwrap_or(PhysicalExtent::MAX),
+            ));
+        }
+
+        std::mem::drop(limits);
+
+        if self.is_fullscreen(window) {
+            self.update_size_hints(window, self.extent(window));
+        } else {
+            self.resize(window, self.extent(window));
+        }
+    }
+
+    fn set_resizable(&self, window: super::WindowId, resizable: bool) {
+        let window = window.0.window;
+        let mut fixed_size = self.fixed_size.borrow_mut();
+        fixed_size.retain(|w| *w != window);
+
+        if !resizable {
+            fixed_size.push(window);
+        }
+
+        std::mem::drop(fixed_size);
+        self.update_size_hints(window, self.extent(window));
+    }
+
+    fn set_always_on_top(&self, window: super::WindowId, always_on_top: bool) {
+        self.change_wm_state(
+            window.0.window,
+            always_on_top,
+            self.atoms.net_wm_state_above,
+            0,
+        );
+    }
+
+    fn fullscreen(&self, window: super::WindowId) -> FullscreenMode {
+        self.fullscreen
+            .borrow()
+            .iter()
+            .find(|f| f.window == window.0.window)
+            .map_or(FullscreenMode::Windowed, |f| f.mode)
+    }
+
+    fn set_fullscreen(&self, window: super::WindowId, mode: FullscreenMode) {
+        let window = window.0.window;
+        let mut fullscreen = self.fullscreen.borrow_mut();
+        let index = fullscreen.iter().position(|f| f.window == window);
+
+        match (index, mode) {
+            (None, FullscreenMode::Windowed) => {}
+            (None, mode) => {
+                fullscreen.push(Fullscreen {
+                    window,
+                    mode,
+                    placement: None,
+                });
+                std::mem::drop(fullscreen);
+                self.change_wm_state(window, true, self.atoms.net_wm_state_fullscreen, 0);
+            }
+            (Some(index), FullscreenMode::Windowed) => {
+                let entry = fullscreen.swap_remove(index);
+                std::mem::drop(fullscreen);
+                self.change_wm_state(window, false, self.atoms.net_wm_state_fullscreen, 0);
+
+                if let Some(placement) = entry.placement {
+                    self.restore_placement(window.into(), &placement);
+                }
+            }
+            (Some(index), mode) => fullscreen[index].mode = mode,
+        }
+    }
+
+    fn set_window_icon(&self, window: super::WindowId, icon: Option<&PixelBuffer>) {
+        let window = window.0.window;
+
+        // The icon's width and height, followed by its pixels.
+        let data = icon.and_then(|icon| {
+            let pixels = argb_pixels(icon)?;
+            let Extent { width, height } = icon.extent();
+            Some(
+                [width.0 as c_ulong, height.0 as c_ulong]
+                    .into_iter()
+                    .chain(pixels.into_iter().map(c_ulong::from))
+                    .collect::<Vec<_>>(),
+            )
+        });
+
+        match data {
+            Some(data) => {
+                self.set_property(window, self.atoms.net_wm_icon, xlib::XA_CARDINAL, &data);
+            }
+            None => unsafe {
+                (self.xlib.XDeleteProperty)(self.display, window, self.atoms.net_wm_icon);
+            },
+        }
+    }
+
+    fn scale_factor(&self, _window: super::WindowId) -> ScaleFactor {
+        ScaleFactor::from_dpi(self.dpi)
+    }
+
+    fn primary_scale_factor(&self) -> ScaleFactor {
+        ScaleFactor::from_dpi(self.dpi)
+    }
+
+    fn refresh_rate(&self, window: super::WindowId) -> Option<f32> {
+        let xrandr = self.xrandr.as_ref()?;
+
+        let config = unsafe { (xrandr.XRRGetScreenInfo)(self.display, window.0.window) };
+        if config.is_null() {
+            return None;
+        }
+
+        let rate = unsafe { (xrandr.XRRConfigCurrentRate)(config) };
+        unsafe { (xrandr.XRRFreeScreenConfigInfo)(config) };
+
+        (rate > 0).then_some(f32::from(rate))
+    }
+
+    fn prefers_subpixel_text(&self) -> bool {
+        self.subpixel_text
+    }
+
+    fn features(&self) -> super::ShellFeatures {
+        // Drag and drop (XDND), input methods (XIM), raw input (XInput2),
+        // per-monitor scales (XRandR) and assistive technology (AT-SPI) each
+        // need a protocol that the shell doesn't speak yet.
+        super::ShellFeatures {
+            file_drop: false,
+            input_method: false,
+            raw_mouse_input: false,
+            per_monitor_scale: false,
+            accessibility: false,
+        }
+    }
+
+    fn clipboard(&self) -> super::Clipboard {
+        super::Clipboard(Clipboard {
+            shell: Rc::downgrade(self),
+        })
+    }
+
+    fn event_loop_proxy(&self) -> super::EventLoopProxy {
+        super::EventLoopProxy(EventLoopProxy {
+            wake: self.proxy_wake.clone(),
+            events: self.user_events.clone(),
+        })
+    }
+
+    fn take_user_events(&self) -> Vec<UserEvent> {
+        std::mem::take(&mut *self.user_events.lock().unwrap())
+    }
+
+    fn dropped_files(&self, _window: super::WindowId) -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    fn update_accessibility(&self, _window: super::WindowId, _update: accesskit::TreeUpdate) {}
+
+    fn take_access_requests(&self, _window: super::WindowId) -> Vec<accesskit::ActionRequest> {
+        Vec::new()
+    }
+
+    fn composition(&self, _window: super::WindowId) -> Composition {
+        Composition::default()
+    }
+
+    fn set_ime_area(&self, _window: super::WindowId, _area: PhysicalRect) {}
+
+    fn set_raw_mouse_input(&self, _window: super::WindowId, _enabled: bool) {}
+
+    fn set_cursor(&self, window: super::WindowId, icon: CursorIcon) {
+        let window = window.0.window;
+        let cursor = self.font_cursor(icon);
+
+        let mut cursors = self.cursors.borrow_mut();
+        match cursors.iter_mut().find(|(w, _)| *w == window) {
+            Some((_, current)) => *current = cursor,
+            None => cursors.push((window, cursor)),
+        }
+
+        if !self.hidden_cursors.borrow().contains(&window) {
+            unsafe { (self.xlib.XDefineCursor)(self.display, window, cursor) };
+        }
+    }
+
+    fn set_cursor_visible(&self, window: super::WindowId, visible: bool) {
+        let window = window.0.window;
+        let mut hidden = self.hidden_cursors.borrow_mut();
+        hidden.retain(|w| *w != window);
+
+        // Windows without a cursor of their own show the root window's.
+        let cursor = if visible {
+            self.cursors
+                .borrow()
+                .iter()
+                .find(|(w, _)| *w == window)
+                .map_or(0, |(_, cursor)| *cursor)
+        } else {
+            hidden.push(window);
+            self.blank_cursor()
+        };
+
+        unsafe { (self.xlib.XDefineCursor)(self.display, window, cursor) };
+    }
+
+    fn set_mouse_capture(&self, window: super::WindowId, captured: bool) {
+        let window = window.0.window;
+        if captured {
+            self.captured.set(Some(window));
+        } else if self.captured.get() == Some(window) {
+            self.captured.set(None);
+        }
+
+        self.update_pointer_grab();
+    }
+
+    fn confine_cursor(&self, window: super::WindowId, area: Option<PhysicalRect>) {
+        let window = window.0.window;
+        let current = self.cursor_clip.get();
+
+        match area {
+            Some(area) => {
+                let (x, y) = (c_int::from(area.left.0), c_int::from(area.top.0));
+                let width = area.width().0.max(1) as c_uint;
+                let height = area.height().0.max(1) as c_uint;
+
+                match current {
+                    Some((w, child)) if w == window => unsafe {
+                        (self.xlib.XMoveResizeWindow)(self.display, child, x, y, width, height);
+                    },
+                    _ => {
+                        if let Some((_, child)) = current {
+                            unsafe { (self.xlib.XDestroyWindow)(self.display, child) };
+                        }
+
+                        // The pointer can only be confined to a window that
+                        // is shown. Events over the child are delivered to
+                        // the window, since the child doesn't select any.
+                        let child = unsafe {
+                            let child = (self.xlib.XCreateWindow)(
+                                self.display,
+                                window,
+                                x,
+                                y,
+                                width,
+                                height,
+                                0,
+                                0,
+                                xlib::InputOnly,
+                                null_mut(),
+                                0,
+                                &mut xlib::XSetWindowAttributes::default(),
+                            );
+                            (self.xlib.XMapWindow)(self.display, child);
+                            child
+                        };
+
+                        self.cursor_clip.set(Some((window, child)));
+                    }
+                }
+            }
+            None => match current {
+                Some((w, child)) if w == window => {
+                    self.cursor_clip.set(None);
+                    unsafe { (self.xlib.XDestroyWindow)(self.display, child) };
+                }
+                _ => return,
+            },
+        }
+
+        self.update_pointer_grab();
+    }
+
+    fn open_file_dialog(&self, window: super::WindowId, dialog: &FileDialog) -> DialogId {
+        self.show_file_dialog(window, dialog, false)
+    }
+
+    fn save_file_dialog(&self, window: super::WindowId, dialog: &FileDialog) -> DialogId {
+        self.show_file_dialog(window, dialog, true)
+    }
+
+    fn take_dialog_result(&self, dialog: DialogId) -> Vec<PathBuf> {
+        let mut results = self.dialog_results.lock().unwrap();
+        match results.iter().position(|(id, _)| *id == dialog.0) {
+            Some(index) => results.swap_remove(index).1,
+            None => Vec::new(),
+        }
+    }
+
+    fn set_timer(&self, window: super::WindowId, duration: Duration, repeating: bool) -> TimerId {
+        let id = self.next_timer.get();
+        self.next_timer.set(id + 1);
+
+        let duration = duration.max(MIN_TIMER_INTERVAL);
+        self.timers.borrow_mut().push(Timer {
+            id,
+            window: window.0.window,
+            deadline: Instant::now() + duration,
+            interval: repeating.then_some(duration),
+        });
+
+        TimerId(id)
+    }
+
+    fn cancel_timer(&self, window: super::WindowId, timer: TimerId) {
+        self.timers
+            .borrow_mut()
+            .retain(|t| t.id != timer.0 || t.window != window.0.window);
+    }
+
+    fn xlib_window(&self, window: super::WindowId) -> (*mut c_void, c_ulong) {
+        (self.display.cast(), window.0.window)
+    }
+}
+
+pub fn show_error_dialog(title: &str, message: &str) {
+    let shown = Command::new("zenity")
+        .args(["--error", "--no-markup"])
+        .arg(format!("--title={title}"))
+        .arg(format!("--text={message}"))
+        .status();
+
+    if shown.is_err() {
+        eprintln!("{title}\n\n{message}");
+    }
+}
+
+/// Records the accelerators of the enabled items in `menu` and its submenus.
+fn collect_accelerators(menu: &Menu, accelerators: &mut Vec<(Accelerator, MenuItemId)>) {
+    for item in &menu.items {
+        match item {
+            MenuItem::Action {
+                id,
+                enabled: true,
+                accelerator: Some(accelerator),
+                ..
+            } => accelerators.push((*accelerator, *id)),
+            MenuItem::Submenu { menu, .. } => collect_accelerators(menu, accelerators),
+            MenuItem::Action { .. } | MenuItem::Separator => {}
+        }
+    }
+}
+
+/// The argument that limits a `zenity` file dialog to a kind of file.
+fn file_filter_arg(filter: &FileFilter) -> String {
+    let patterns: Vec<String> = filter
+        .extensions
+        .iter()
+        .map(|extension| format!("*.{extension}"))
+        .collect();
+    format!("--file-filter={} | {}", filter.name, patterns.join(" "))
+}
+
+/// Finds a resource, such as `Xft.dpi`, in the X server's resource database,
+/// which holds one `name:\tvalue` pair per line.
+fn resource<'a>(resources: &'a str, name: &str) -> Option<&'a str> {
+    resources.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == name).then(|| value.trim())
+    })
+}
+
+/// Converts an `RGB8` or `RGBA8` image to 32-bit ARGB pixels, or returns
+/// `None` for other layouts.
+fn argb_pixels(image: &PixelBuffer) -> Option<Vec<u32>> {
+    match image.layout() {
+        Layout::RGBA8 => Some(
+            image
+                .bytes()
+                .chunks_exact(4)
+                .map(|p| u32::from_be_bytes([p[3], p[0], p[1], p[2]]))
+                .collect(),
+        ),
+        Layout::RGB8 => Some(
+            image
+                .bytes()
+                .chunks_exact(3)
+                .map(|p| u32::from_be_bytes([255, p[0], p[1], p[2]]))
+                .collect(),
+        ),
+        Layout::BC1 | Layout::BC7 => None,
+    }
+}
+
+/// The size of a window, read from the X server.
+fn window_size(
+    xlib: &xlib::Xlib,
+    display: *mut xlib::Display,
+    window: xlib::Window,
+) -> (usize, usize) {
+    let (mut root, mut x, mut y) = (0, 0, 0);
+    let (mut width, mut height, mut border, mut depth) = (0, 0, 0, 0);
+
+    unsafe {
+        (xlib.XGetGeometry)(
+            display,
+            window,
+            &mut root,
+            &mut x,
+            &mut y,
+            &mut width,
+            &mut height,
+            &mut border,
+            &mut depth,
+        )
+    };
+
+    (width as usize, height as usize)
+}
+
+/// Fires the timers that are due. Repeating timers are due again one interval
+/// later.
+fn fire_timers(shell: &Rc<Inner>) {
+    let now = Instant::now();
+    let mut due = Vec::new();
+
+    shell.timers.borrow_mut().retain_mut(|timer| {
+        if timer.deadline > now {
+            return true;
+        }
+
+        due.push(Event::Window {
+            window_id: timer.window.into(),
+            event: WindowEvent::Timer {
+                timer: TimerId(timer.id),
+            },
+        });
+
+        match timer.interval {
+            Some(interval) => {
+                timer.deadline = now + interval;
+                true
+            }
+            None => false,
+        }
+    });
+
+    dispatch(shell, due);
+}
+
+/// Destroys a window that the program asked to destroy, once the callback has
+/// returned.
+fn destroy_window(shell: &Rc<Inner>, window: xlib::Window) {
+    if !shell.windows.borrow().contains(&window) {
+        return;
+    }
+
+    shell.forget_window(window);
+
+    dispatch(
+        shell,
+        std::iter::once(Event::Window {
+            window_id: window.into(),
+            event: WindowEvent::Destroyed,
+        }),
+    );
+
+    unsafe { (shell.xlib.XDestroyWindow)(shell.display, window) };
+
+    let mut colormaps = shell.colormaps.borrow_mut();
+    if let Some(index) = colormaps.iter().position(|(w, _)| *w == window) {
+        let (_, colormap) = colormaps.swap_remove(index);
+        unsafe { (shell.xlib.XFreeColormap)(shell.display, colormap) };
+    }
+}
+
+#[inline]
+fn dispatch(shell: &Rc<Inner>, events: impl IntoIterator<Item = Event>) {
+    // If the callback is already running, the events were sent by a call it
+    // made to the shell, such as to create a window. They are delivered once
+    // it returns.
+    let mut cb = match shell.event_callback.try_borrow_mut() {
+        Ok(cb) => cb,
+        Err(_) => {
+            shell.event_buffer.borrow_mut().extend(events);
+            return;
+        }
+    };
+
+    // If we don't have a callback yet, the event was sent before
+    // run_event_loop() was called. This happens when windows are created before
+    // the event loop is run.
+    if let Some(callback) = cb.as_mut() {
+        let mut ctrl = EventLoopControl::Poll;
+        let mut send = |event| {
+            callback(event, shell, &mut ctrl);
+            shell.event_mode.set(ctrl);
+
+            if ctrl == EventLoopControl::Exit {
+                shell.exit_requested.set(true);
+            }
+        };
+
+        for event in events {
+            send(event);
+        }
+
+        loop {
+            let buffered_events = shell.event_buffer.take();
+            if buffered_events.is_empty() {
+                break;
+            }
+
+            for event in buffered_events {
+                send(event);
+            }
+        }
+    } else {
+        shell.event_buffer.borrow_mut().extend(events);
+    }
+}
+
+fn clean_exit(shell: &Rc<Inner>) -> ! {
+    // Cleanly shut down the event loop by disabling window creation and
+    // destroying any windows that remain.
+    shell.is_shutting_down.set(true);
+
+    let mut cb = shell.event_callback.borrow_mut();
+
+    if let Some(callback) = cb.as_mut() {
+        let mut ctrl = EventLoopControl::Poll;
+
+        for window in shell.windows.borrow_mut().drain(..) {
+            callback(
+                Event::Window {
+                    window_id: window.into(),
+                    event: WindowEvent::Destroyed,
+                },
+                shell,
+                &mut ctrl,
+            );
+        }
+    }
+
+    unsafe {
+        if !shell.im.is_null() {
+            (shell.xlib.XCloseIM)(shell.im);
+        }
+        (shell.xlib.XCloseDisplay)(shell.display);
+    }
+
+    std::process::exit(0);
+}
+
+#[allow(clippy::too_many_lines)]
+fn handle_event(shell: &Rc<Inner>, event: &mut xlib::XEvent) {
+    let xlib = &shell.xlib;
+
+    // Input methods take the key presses that they compose text from.
+    if unsafe { (xlib.XFilterEvent)(event, 0) } != xlib::False {
+        return;
+    }
+
+    let kind = unsafe { event.type_ };
+    let window = unsafe { event.any.window };
+
+    match kind {
+        xlib::SelectionRequest => {
+            shell.answer_selection_request(unsafe { &event.selection_request });
+            return;
+        }
+        xlib::SelectionClear => {
+            shell.clipboard_text.borrow_mut().take();
+            return;
+        }
+        _ => {}
+    }
+
+    let tray = shell
+        .tray_icons
+        .borrow()
+        .iter()
+        .find(|t| t.tray_window == window)
+        .map(|t| (t.id, t.window));
+
+    if let Some((tray, owner)) = tray {
+        match kind {
+            xlib::Expose if unsafe { event.expose.count } == 0 => shell.draw_tray_icon(window),
+            xlib::ButtonRelease if unsafe { event.button.button } == 1 => dispatch(
+                shell,
+                std::iter::once(Event::Window {
+                    window_id: owner.into(),
+                    event: WindowEvent::TrayIconClicked {
+                        tray: TrayIconId(tray),
+                    },
+                }),
+            ),
+            _ => {}
+        }
+        return;
+    }
+
+    if !shell.windows.borrow().contains(&window) {
+        return;
+    }
+
+    let window_id = window.into();
+
+    let event = match kind {
+        xlib::ClientMessage => {
+            let message = unsafe { event.client_message };
+            if message.message_type == shell.atoms.wm_protocols
+                && message.data[0] as xlib::Atom == shell.atoms.wm_delete_window
+            {
+                WindowEvent::CloseRequested
+            } else {
+                return;
+            }
+        }
+        xlib::MotionNotify => {
+            let motion = unsafe { event.motion };
+            WindowEvent::CursorMoved {
+                position: PhysicalPoint::new(motion.x as i16, motion.y as i16),
+            }
+        }
+        xlib::ButtonPress => match unsafe { event.button.button } {
+            1 => WindowEvent::LeftMouseButtonPressed,
+            2 => WindowEvent::MiddleMouseButtonPressed,
+            3 => WindowEvent::RightMouseButtonPressed,
+            // The wheel is reported as buttons, once for each notch.
+            4 => WindowEvent::MouseWheel {
+                x: 0,
+                y: WHEEL_DELTA,
+            },
+            5 => WindowEvent::MouseWheel {
+                x: 0,
+                y: -WHEEL_DELTA,
+            },
+            6 => WindowEvent::MouseWheel {
+                x: -WHEEL_DELTA,
+                y: 0,
+            },
+            7 => WindowEvent::MouseWheel {
+                x: WHEEL_DELTA,
+                y: 0,
+            },
+            _ => return,
+        },
+        xlib::ButtonRelease => match unsafe { event.button.button } {
+            1 => WindowEvent::LeftMouseButtonReleased,
+            2 => WindowEvent::MiddleMouseButtonReleased,
+            3 => WindowEvent::RightMouseButtonReleased,
+            _ => return,
+        },
+        xlib::KeyPress => {
+            let key = unsafe { &mut event.key };
+
+            // Text committed by an input method arrives without a key.
+            let mut events = Vec::new();
+            if key.keycode != 0 {
+                let modifiers = modifiers(key.state);
+                let keysym = unsafe { (xlib.XLookupKeysym)(key, 0) };
+                let key = key_from_keysym(keysym);
+
+                if let Some(item) = shell.accelerator(window, key, modifiers) {
+                    dispatch(
+                        shell,
+                        std::iter::once(Event::Window {
+                            window_id,
+                            event: WindowEvent::MenuItemSelected { item },
+                        }),
+                    );
+                    return;
+                }
+
+                events.push(WindowEvent::KeyPressed { key, modifiers });
+
+                // Keys pressed with Ctrl or Alt are shortcuts rather than
+                // text.
+                if modifiers.control || modifiers.alt {
+                    dispatch(
+                        shell,
+                        events
+                            .into_iter()
+                            .map(|event| Event::Window { window_id, event }),
+                    );
+                    return;
+                }
+            }
+
+            events.extend(
+                shell
+                    .typed_text(window, key)
+                    .chars()
+                    .filter(|character| !character.is_control())
+                    .map(|character| WindowEvent::CharacterReceived { character }),
+            );
+
+            dispatch(
+                shell,
+                events
+                    .into_iter()
+                    .map(|event| Event::Window { window_id, event }),
+            );
+            return;
+        }
+        xlib::FocusIn | xlib::FocusOut => {
+            // Grabbing the keyboard, such as for a menu, doesn't change the
+            // focused window.
+            let focus = unsafe { event.focus_change };
+            if focus.mode == xlib::NotifyGrab || focus.mode == xlib::NotifyUngrab {
+                return;
+            }
+
+            let ic = shell.input_context(window);
+            if kind == xlib::FocusIn {
+                shell.focused.set(Some(window));
+                if let Some(ic) = ic {
+                    unsafe { (xlib.XSetICFocus)(ic) };
+                }
+            } else {
+                if shell.focused.get() == Some(window) {
+                    shell.focused.set(None);
+                }
+                if let Some(ic) = ic {
+                    unsafe { (xlib.XUnsetICFocus)(ic) };
+                }
+            }
+
+            // The confinement is lifted while another window is focused.
+            shell.update_pointer_grab();
+            return;
+        }
+        xlib::Expose => {
+            // The window is repainted all at once, after the last of the
+            // exposed areas.
+            if unsafe { event.expose.count } == 0 {
+                super::Shell::request_redraw(shell, window_id);
+            }
+            return;
+        }
+        xlib::ConfigureNotify => {
+            let configure = unsafe { event.configure };
+
+            if shell.minimized.borrow().contains(&window) {
+                return;
+            }
+
+            if !shell.is_fullscreen(window) && !shell.is_maximized(window) {
+                let rect = shell.client_rect(window);
+                let mut frames = shell.normal_frames.borrow_mut();
+                match frames.iter_mut().find(|(w, _)| *w == window) {
+                    Some((_, frame)) => *frame = rect,
+                    None => frames.push((window, rect)),
+                }
+            }
+
+            let extent = PhysicalExtent {
+                width: PhysicalPx(configure.width as i16),
+                height: PhysicalPx(configure.height as i16),
+            };
+
+            {
+                let mut extents = shell.extents.borrow_mut();
+                match extents.iter_mut().find(|(w, _)| *w == window) {
+                    // Moving the window doesn't change its contents.
+                    Some((_, current)) if *current == extent => return,
+                    Some((_, current)) => *current = extent,
+                    None => extents.push((window, extent)),
+                }
+            }
+
+            // The windows are copied since the callback may create more of
+            // them.
+            let windows = shell.windows.borrow().clone();
+            dispatch(
+                shell,
+                std::iter::once(Event::Window {
+                    window_id,
+                    event: WindowEvent::Resized {
+                        inner_extent: extent,
+                    },
+                })
+                .chain(
+                    windows
+                        .into_iter()
+                        .map(|window| Event::Window {
+                            window_id: window.into(),
+                            event: WindowEvent::Repaint,
+                        })
+                        .chain(std::iter::once(Event::RepaintComplete)),
+                ),
+            );
+            return;
+        }
+        xlib::PropertyNotify => {
+            if unsafe { event.property.atom } != shell.atoms.wm_state {
+                return;
+            }
+
+            // The first item of `WM_STATE` is the state itself.
+            let iconic = shell
+                .property(window, shell.atoms.wm_state, shell.atoms.wm_state)
+                .first()
+                == Some(&(xlib::IconicState as c_ulong));
+
+            let mut minimized = shell.minimized.borrow_mut();
+            if iconic == minimized.contains(&window) {
+                return;
+            }
+
+            let inner_extent = if iconic {
+                minimized.push(window);
+                PhysicalExtent::default()
+            } else {
+                minimized.retain(|w| *w != window);
+                super::Shell::request_redraw(shell, window_id);
+                shell.extent(window)
+            };
+
+            WindowEvent::Resized { inner_extent }
+        }
+        _ => return,
+    };
+
+    dispatch(shell, std::iter::once(Event::Window { window_id, event }));
+}
+
+/// Converts an X11 key symbol, as it is without any modifiers, into a [`Key`].
+fn key_from_keysym(keysym: xlib::KeySym) -> Key {
+    const LETTERS: [Key; 26] = [
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+    ];
+
+    // The function keys are contiguous, starting with `XK_F1`.
+    const FUNCTION_KEYS: [Key; 12] = [
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+    ];
+
+    match keysym {
+        xlib::XK_BackSpace => Key::Backspace,
+        xlib::XK_Tab | xlib::XK_ISO_Left_Tab => Key::Tab,
+        xlib::XK_Return | xlib::XK_KP_Enter => Key::Enter,
+        xlib::XK_Escape => Key::Escape,
+        xlib::XK_space => Key::Space,
+        xlib::XK_Prior => Key::PageUp,
+        xlib::XK_Next => Key::PageDown,
+        xlib::XK_End => Key::End,
+        xlib::XK_Home => Key::Home,
+        xlib::XK_Left => Key::Left,
+        xlib::XK_Up => Key::Up,
+        xlib::XK_Right => Key::Right,
+        xlib::XK_Down => Key::Down,
+        xlib::XK_Insert => Key::Insert,
+        xlib::XK_Delete => Key::Delete,
+        xlib::XK_a..=xlib::XK_z => LETTERS[(keysym - xlib::XK_a) as usize],
+        xlib::XK_A..=xlib::XK_Z => LETTERS[(keysym - xlib::XK_A) as usize],
+        xlib::XK_F1..=xlib::XK_F12 => FUNCTION_KEYS[(keysym - xlib::XK_F1) as usize],
+        _ => Key::Unknown,
+    }
+}
+
+/// Reads the modifier keys from the state of a key event.
+fn modifiers(state: c_uint) -> Modifiers {
+    Modifiers {
+        shift: state & xlib::ShiftMask != 0,
+        control: state & xlib::ControlMask != 0,
+        alt: state & xlib::Mod1Mask != 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        assert_eq!(key_from_keysym(xlib::XK_a), Key::A);
+        assert_eq!(key_from_keysym(xlib::XK_Z), Key::Z);
+        assert_eq!(key_from_keysym(xlib::XK_F12), Key::F12);
+        assert_eq!(key_from_keysym(xlib::XK_ISO_Left_Tab), Key::Tab);
+        assert_eq!(key_from_keysym(0x00e9), Key::Unknown);
+    }
+
+    #[test]
+    fn resources() {
+        let resources = "Xft.antialias:\t1\nXft.dpi:\t144\nXft.rgba:\trgb\n";
+        assert_eq!(resource(resources, "Xft.dpi"), Some("144"));
+        assert_eq!(resource(resources, "Xft.rgba"), Some("rgb"));
+        assert_eq!(resource(resources, "Xft.hinting"), None);
+    }
+}
@@ -0,0 +1,639 @@
+//! The parts of Xlib used by the X11 shell.
+//!
+//! The library is loaded when the shell is initialized rather than linked, so
+//! that programs can be built without the X11 development packages, and so
+//! that they can report a missing X server instead of failing to start.
+
+#![allow(non_snake_case, non_upper_case_globals, clippy::upper_case_acronyms)]
+
+use std::os::raw::{c_char, c_int, c_long, c_short, c_uchar, c_uint, c_ulong, c_ushort, c_void};
+
+use libloading::Library;
+
+#[repr(C)]
+pub struct Display {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct Visual {
+    _private: [u8; 0],
+}
+
+pub type XID = c_ulong;
+pub type Window = XID;
+pub type Drawable = XID;
+pub type Pixmap = XID;
+pub type Cursor = XID;
+pub type Colormap = XID;
+pub type Atom = c_ulong;
+pub type Time = c_ulong;
+pub type KeySym = XID;
+pub type VisualID = c_ulong;
+pub type Bool = c_int;
+pub type Status = c_int;
+pub type XIM = *mut c_void;
+pub type XIC = *mut c_void;
+pub type GC = *mut c_void;
+
+pub const False: Bool = 0;
+pub const True: Bool = 1;
+
+pub const CurrentTime: Time = 0;
+pub const AnyPropertyType: Atom = 0;
+pub const PropModeReplace: c_int = 0;
+pub const Success: c_int = 0;
+
+pub const XA_ATOM: Atom = 4;
+pub const XA_CARDINAL: Atom = 6;
+pub const XA_STRING: Atom = 31;
+
+pub const KeyPress: c_int = 2;
+pub const ButtonPress: c_int = 4;
+pub const ButtonRelease: c_int = 5;
+pub const MotionNotify: c_int = 6;
+pub const FocusIn: c_int = 9;
+pub const FocusOut: c_int = 10;
+pub const Expose: c_int = 12;
+pub const ConfigureNotify: c_int = 22;
+pub const PropertyNotify: c_int = 28;
+pub const SelectionClear: c_int = 29;
+pub const SelectionRequest: c_int = 30;
+pub const SelectionNotify: c_int = 31;
+pub const ClientMessage: c_int = 33;
+
+pub const NoEventMask: c_long = 0;
+pub const KeyPressMask: c_long = 1 << 0;
+pub const KeyReleaseMask: c_long = 1 << 1;
+pub const ButtonPressMask: c_long = 1 << 2;
+pub const ButtonReleaseMask: c_long = 1 << 3;
+pub const PointerMotionMask: c_long = 1 << 6;
+pub const ExposureMask: c_long = 1 << 15;
+pub const StructureNotifyMask: c_long = 1 << 17;
+pub const SubstructureNotifyMask: c_long = 1 << 19;
+pub const SubstructureRedirectMask: c_long = 1 << 20;
+pub const FocusChangeMask: c_long = 1 << 21;
+pub const PropertyChangeMask: c_long = 1 << 22;
+
+pub const ShiftMask: c_uint = 1 << 0;
+pub const ControlMask: c_uint = 1 << 2;
+pub const Mod1Mask: c_uint = 1 << 3;
+
+pub const CWBackPixmap: c_ulong = 1 << 0;
+pub const CWBorderPixel: c_ulong = 1 << 3;
+pub const CWEventMask: c_ulong = 1 << 11;
+pub const CWColormap: c_ulong = 1 << 13;
+
+pub const ParentRelative: Pixmap = 1;
+pub const InputOutput: c_uint = 1;
+pub const InputOnly: c_uint = 2;
+pub const TrueColor: c_int = 4;
+pub const AllocNone: c_int = 0;
+pub const GrabModeAsync: c_int = 1;
+
+pub const PMinSize: c_long = 1 << 4;
+pub const PMaxSize: c_long = 1 << 5;
+
+/// The value of `WM_STATE` while a window is minimized.
+pub const IconicState: c_long = 3;
+
+pub const XIMPreeditNothing: c_ulong = 0x0008;
+pub const XIMStatusNothing: c_ulong = 0x0400;
+pub const XNInputStyle: &[u8] = b"inputStyle\0";
+pub const XNClientWindow: &[u8] = b"clientWindow\0";
+pub const XNFocusWindow: &[u8] = b"focusWindow\0";
+
+pub const XBufferOverflow: c_int = -1;
+pub const XLookupChars: c_int = 2;
+pub const XLookupBoth: c_int = 4;
+
+/// Set in `XFocusChangeEvent::mode` for the focus changes caused by grabbing
+/// and releasing the keyboard, which don't change the active window.
+pub const NotifyGrab: c_int = 1;
+pub const NotifyUngrab: c_int = 2;
+
+pub const XK_space: KeySym = 0x0020;
+pub const XK_A: KeySym = 0x0041;
+pub const XK_Z: KeySym = 0x005a;
+pub const XK_a: KeySym = 0x0061;
+pub const XK_z: KeySym = 0x007a;
+pub const XK_ISO_Left_Tab: KeySym = 0xfe20;
+pub const XK_BackSpace: KeySym = 0xff08;
+pub const XK_Tab: KeySym = 0xff09;
+pub const XK_Return: KeySym = 0xff0d;
+pub const XK_Escape: KeySym = 0xff1b;
+pub const XK_Home: KeySym = 0xff50;
+pub const XK_Left: KeySym = 0xff51;
+pub const XK_Up: KeySym = 0xff52;
+pub const XK_Right: KeySym = 0xff53;
+pub const XK_Down: KeySym = 0xff54;
+pub const XK_Prior: KeySym = 0xff55;
+pub const XK_Next: KeySym = 0xff56;
+pub const XK_End: KeySym = 0xff57;
+pub const XK_Insert: KeySym = 0xff63;
+pub const XK_KP_Enter: KeySym = 0xff8d;
+pub const XK_F1: KeySym = 0xffbe;
+pub const XK_F12: KeySym = 0xffc9;
+pub const XK_Delete: KeySym = 0xffff;
+
+pub const XC_X_cursor: c_uint = 0;
+pub const XC_fleur: c_uint = 52;
+pub const XC_hand2: c_uint = 60;
+pub const XC_left_ptr: c_uint = 68;
+pub const XC_sb_h_double_arrow: c_uint = 108;
+pub const XC_sb_v_double_arrow: c_uint = 116;
+pub const XC_watch: c_uint = 150;
+pub const XC_xterm: c_uint = 152;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XAnyEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XKeyEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub keycode: c_uint,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XButtonEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub button: c_uint,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XMotionEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub is_hint: c_char,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XFocusChangeEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub mode: c_int,
+    pub detail: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XExposeEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub count: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XConfigureEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub event: Window,
+    pub window: Window,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub border_width: c_int,
+    pub above: Window,
+    pub override_redirect: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XPropertyEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub atom: Atom,
+    pub time: Time,
+    pub state: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XSelectionClearEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub selection: Atom,
+    pub time: Time,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XSelectionRequestEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub owner: Window,
+    pub requestor: Window,
+    pub selection: Atom,
+    pub target: Atom,
+    pub property: Atom,
+    pub time: Time,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XSelectionEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub requestor: Window,
+    pub selection: Atom,
+    pub target: Atom,
+    pub property: Atom,
+    pub time: Time,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XClientMessageEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub message_type: Atom,
+    pub format: c_int,
+    /// The message, as the `l` member of the C union. Messages of other
+    /// formats are the same bytes, and aren't used.
+    pub data: [c_long; 5],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XErrorEvent {
+    pub type_: c_int,
+    pub display: *mut Display,
+    pub resourceid: XID,
+    pub serial: c_ulong,
+    pub error_code: c_uchar,
+    pub request_code: c_uchar,
+    pub minor_code: c_uchar,
+}
+
+pub type XErrorHandler = Option<unsafe extern "C" fn(*mut Display, *mut XErrorEvent) -> c_int>;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union XEvent {
+    pub type_: c_int,
+    pub any: XAnyEvent,
+    pub key: XKeyEvent,
+    pub button: XButtonEvent,
+    pub motion: XMotionEvent,
+    pub focus_change: XFocusChangeEvent,
+    pub expose: XExposeEvent,
+    pub configure: XConfigureEvent,
+    pub property: XPropertyEvent,
+    pub selection_clear: XSelectionClearEvent,
+    pub selection_request: XSelectionRequestEvent,
+    pub selection: XSelectionEvent,
+    pub client_message: XClientMessageEvent,
+    pub pad: [c_long; 24],
+}
+
+impl Default for XEvent {
+    fn default() -> Self {
+        Self { pad: [0; 24] }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct XSetWindowAttributes {
+    pub background_pixmap: Pixmap,
+    pub background_pixel: c_ulong,
+    pub border_pixmap: Pixmap,
+    pub border_pixel: c_ulong,
+    pub bit_gravity: c_int,
+    pub win_gravity: c_int,
+    pub backing_store: c_int,
+    pub backing_planes: c_ulong,
+    pub backing_pixel: c_ulong,
+    pub save_under: Bool,
+    pub event_mask: c_long,
+    pub do_not_propagate_mask: c_long,
+    pub override_redirect: Bool,
+    pub colormap: Colormap,
+    pub cursor: Cursor,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct AspectRatio {
+    pub x: c_int,
+    pub y: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct XSizeHints {
+    pub flags: c_long,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub min_width: c_int,
+    pub min_height: c_int,
+    pub max_width: c_int,
+    pub max_height: c_int,
+    pub width_inc: c_int,
+    pub height_inc: c_int,
+    pub min_aspect: AspectRatio,
+    pub max_aspect: AspectRatio,
+    pub base_width: c_int,
+    pub base_height: c_int,
+    pub win_gravity: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XVisualInfo {
+    pub visual: *mut Visual,
+    pub visualid: VisualID,
+    pub screen: c_int,
+    pub depth: c_int,
+    pub class: c_int,
+    pub red_mask: c_ulong,
+    pub green_mask: c_ulong,
+    pub blue_mask: c_ulong,
+    pub colormap_size: c_int,
+    pub bits_per_rgb: c_int,
+}
+
+impl Default for XVisualInfo {
+    fn default() -> Self {
+        Self {
+            visual: std::ptr::null_mut(),
+            visualid: 0,
+            screen: 0,
+            depth: 0,
+            class: 0,
+            red_mask: 0,
+            green_mask: 0,
+            blue_mask: 0,
+            colormap_size: 0,
+            bits_per_rgb: 0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct XColor {
+    pub pixel: c_ulong,
+    pub red: c_ushort,
+    pub green: c_ushort,
+    pub blue: c_ushort,
+    pub flags: c_char,
+    pub pad: c_char,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XRRMonitorInfo {
+    pub name: Atom,
+    pub primary: Bool,
+    pub automatic: Bool,
+    pub noutput: c_int,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub mwidth: c_int,
+    pub mheight: c_int,
+    pub outputs: *mut XID,
+}
+
+/// Declares a table of functions loaded from a shared library, named after
+/// the functions themselves.
+macro_rules! library {
+    ($table:ident, $($file:literal),+; $($name:ident: fn($($arg:ty),* $(; $variadic:tt)?) -> $ret:ty;)*) => {
+        pub struct $table {
+            $(pub $name: unsafe extern "C" fn($($arg),* $(, $variadic)?) -> $ret,)*
+            _library: Library,
+        }
+
+        impl $table {
+            /// Loads the library, trying each of its names in turn.
+            pub fn load() -> Result<Self, libloading::Error> {
+                let mut result = Err(None);
+                for file in [$($file),+] {
+                    match unsafe { Library::new(file) } {
+                        Ok(library) => {
+                            result = Ok(library);
+                            break;
+                        }
+                        Err(error) => result = Err(Some(error)),
+                    }
+                }
+
+                let library = match result {
+                    Ok(library) => library,
+                    Err(error) => return Err(error.unwrap()),
+                };
+
+                // SAFETY: The signatures match the library's headers.
+                unsafe {
+                    Ok(Self {
+                        $($name: *library.get(concat!(stringify!($name), "\0").as_bytes())?,)*
+                        _library: library,
+                    })
+                }
+            }
+        }
+    };
+}
+
+library! {
+    Xlib, "libX11.so.6", "libX11.so";
+    XInitThreads: fn() -> Status;
+    XOpenDisplay: fn(*const c_char) -> *mut Display;
+    XCloseDisplay: fn(*mut Display) -> c_int;
+    XDefaultScreen: fn(*mut Display) -> c_int;
+    XRootWindow: fn(*mut Display, c_int) -> Window;
+    XDisplayWidth: fn(*mut Display, c_int) -> c_int;
+    XDisplayHeight: fn(*mut Display, c_int) -> c_int;
+    XConnectionNumber: fn(*mut Display) -> c_int;
+    XPending: fn(*mut Display) -> c_int;
+    XNextEvent: fn(*mut Display, *mut XEvent) -> c_int;
+    XCheckTypedWindowEvent: fn(*mut Display, Window, c_int, *mut XEvent) -> Bool;
+    XSendEvent: fn(*mut Display, Window, Bool, c_long, *mut XEvent) -> Status;
+    XFlush: fn(*mut Display) -> c_int;
+    XSync: fn(*mut Display, Bool) -> c_int;
+    XSetErrorHandler: fn(XErrorHandler) -> XErrorHandler;
+    XGetErrorText: fn(*mut Display, c_int, *mut c_char, c_int) -> c_int;
+    XFree: fn(*mut c_void) -> c_int;
+    XInternAtom: fn(*mut Display, *const c_char, Bool) -> Atom;
+    XCreateWindow: fn(
+        *mut Display,
+        Window,
+        c_int,
+        c_int,
+        c_uint,
+        c_uint,
+        c_uint,
+        c_int,
+        c_uint,
+        *mut Visual,
+        c_ulong,
+        *mut XSetWindowAttributes
+    ) -> Window;
+    XDestroyWindow: fn(*mut Display, Window) -> c_int;
+    XMapWindow: fn(*mut Display, Window) -> c_int;
+    XMapRaised: fn(*mut Display, Window) -> c_int;
+    XUnmapWindow: fn(*mut Display, Window) -> c_int;
+    XIconifyWindow: fn(*mut Display, Window, c_int) -> Status;
+    XMoveWindow: fn(*mut Display, Window, c_int, c_int) -> c_int;
+    XResizeWindow: fn(*mut Display, Window, c_uint, c_uint) -> c_int;
+    XMoveResizeWindow: fn(*mut Display, Window, c_int, c_int, c_uint, c_uint) -> c_int;
+    XClearArea: fn(*mut Display, Window, c_int, c_int, c_uint, c_uint, Bool) -> c_int;
+    XGetGeometry: fn(
+        *mut Display,
+        Drawable,
+        *mut Window,
+        *mut c_int,
+        *mut c_int,
+        *mut c_uint,
+        *mut c_uint,
+        *mut c_uint,
+        *mut c_uint
+    ) -> Status;
+    XTranslateCoordinates: fn(
+        *mut Display,
+        Window,
+        Window,
+        c_int,
+        c_int,
+        *mut c_int,
+        *mut c_int,
+        *mut Window
+    ) -> Bool;
+    XSetWMProtocols: fn(*mut Display, Window, *mut Atom, c_int) -> Status;
+    XSetWMNormalHints: fn(*mut Display, Window, *mut XSizeHints) -> ();
+    XStoreName: fn(*mut Display, Window, *const c_char) -> c_int;
+    XChangeProperty: fn(*mut Display, Window, Atom, Atom, c_int, c_int, *const c_uchar, c_int) -> c_int;
+    XDeleteProperty: fn(*mut Display, Window, Atom) -> c_int;
+    XGetWindowProperty: fn(
+        *mut Display,
+        Window,
+        Atom,
+        c_long,
+        c_long,
+        Bool,
+        Atom,
+        *mut Atom,
+        *mut c_int,
+        *mut c_ulong,
+        *mut c_ulong,
+        *mut *mut c_uchar
+    ) -> c_int;
+    XMatchVisualInfo: fn(*mut Display, c_int, c_int, c_int, *mut XVisualInfo) -> Status;
+    XCreateColormap: fn(*mut Display, Window, *mut Visual, c_int) -> Colormap;
+    XFreeColormap: fn(*mut Display, Colormap) -> c_int;
+    XCreateFontCursor: fn(*mut Display, c_uint) -> Cursor;
+    XCreateBitmapFromData: fn(*mut Display, Drawable, *const c_char, c_uint, c_uint) -> Pixmap;
+    XCreatePixmapCursor: fn(*mut Display, Pixmap, Pixmap, *mut XColor, *mut XColor, c_uint, c_uint) -> Cursor;
+    XFreePixmap: fn(*mut Display, Pixmap) -> c_int;
+    XDefineCursor: fn(*mut Display, Window, Cursor) -> c_int;
+    XGrabPointer: fn(*mut Display, Window, Bool, c_uint, c_int, c_int, Window, Cursor, Time) -> c_int;
+    XUngrabPointer: fn(*mut Display, Time) -> c_int;
+    XGetSelectionOwner: fn(*mut Display, Atom) -> Window;
+    XSetSelectionOwner: fn(*mut Display, Atom, Window, Time) -> c_int;
+    XConvertSelection: fn(*mut Display, Atom, Atom, Atom, Window, Time) -> c_int;
+    XCreateGC: fn(*mut Display, Drawable, c_ulong, *mut c_void) -> GC;
+    XFreeGC: fn(*mut Display, GC) -> c_int;
+    XSetForeground: fn(*mut Display, GC, c_ulong) -> c_int;
+    XFillRectangle: fn(*mut Display, Drawable, GC, c_int, c_int, c_uint, c_uint) -> c_int;
+    XResourceManagerString: fn(*mut Display) -> *mut c_char;
+    XFilterEvent: fn(*mut XEvent, Window) -> Bool;
+    XLookupKeysym: fn(*mut XKeyEvent, c_int) -> KeySym;
+    XLookupString: fn(*mut XKeyEvent, *mut c_char, c_int, *mut KeySym, *mut c_void) -> c_int;
+    Xutf8LookupString: fn(XIC, *mut XKeyEvent, *mut c_char, c_int, *mut KeySym, *mut c_int) -> c_int;
+    XSetLocaleModifiers: fn(*const c_char) -> *mut c_char;
+    XOpenIM: fn(*mut Display, *mut c_void, *mut c_char, *mut c_char) -> XIM;
+    XCloseIM: fn(XIM) -> Status;
+    XCreateIC: fn(XIM; ...) -> XIC;
+    XDestroyIC: fn(XIC) -> ();
+    XSetICFocus: fn(XIC) -> ();
+    XUnsetICFocus: fn(XIC) -> ();
+}
+
+library! {
+    Xrandr, "libXrandr.so.2", "libXrandr.so";
+    XRRGetMonitors: fn(*mut Display, Window, Bool, *mut c_int) -> *mut XRRMonitorInfo;
+    XRRFreeMonitors: fn(*mut XRRMonitorInfo) -> ();
+    XRRGetScreenInfo: fn(*mut Display, Window) -> *mut c_void;
+    XRRConfigCurrentRate: fn(*mut c_void) -> c_short;
+    XRRFreeScreenConfigInfo: fn(*mut c_void) -> ();
+}
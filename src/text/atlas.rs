@@ -0,0 +1,547 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::{
+    gfx::{
+        color::Color,
+        geometry::{Extent, Offset, Point, Px, Rect, ScaleFactor, Transform},
+        pixel_buffer::{ColorSpace, Layout, PixelBuffer},
+        DrawCommandList, GfxDevice, GlyphAntiAliasing, Image, ImageCopy,
+    },
+    handle_pool::Handle,
+};
+
+use super::{raster::rasterize, FontCollection, FontId, GlyphId, ShapedLine};
+
+/// Identifies a rasterized glyph. Sizes are stored in 1/64ths of a pixel so
+/// that they can be hashed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: FontId,
+    glyph: GlyphId,
+    size: u32,
+    anti_aliasing: GlyphAntiAliasing,
+}
+
+/// A glyph stored in the atlas.
+struct Entry {
+    page: usize,
+    rect: Rect,
+    bearing: Offset,
+    /// The generation in which the glyph was last drawn.
+    last_used: u64,
+    /// A copy of the glyph's pixels, kept so that the glyph can be moved when
+    /// the atlas is compacted without rasterizing it again.
+    pixels: Box<[u8]>,
+    color: bool,
+}
+
+/// Where a glyph can be drawn from.
+#[derive(Clone, Copy)]
+struct Placement {
+    image: Handle<Image>,
+    rect: Rect,
+    bearing: Offset,
+    color: bool,
+}
+
+struct Page {
+    image: Handle<Image>,
+    shelves: Shelves,
+}
+
+impl Page {
+    fn new(gfx: &dyn GfxDevice) -> Result<Self, crate::gfx::Error> {
+        Ok(Self {
+            image: gfx.create_image(GlyphAtlas::PAGE_EXTENT, Layout::RGBA8)?,
+            shelves: Shelves::new(GlyphAtlas::PAGE_EXTENT),
+        })
+    }
+}
+
+/// A row of images. Images are placed left-to-right in the shortest shelf
+/// that they fit in, and new shelves are opened below the last one when none
+/// do.
+struct Shelf {
+    top: Px,
+    height: Px,
+    /// The left edge of the unused part of the shelf.
+    end: Px,
+}
+
+/// Packs images into shelves. Individual images cannot be freed; space is
+/// reclaimed by clearing every shelf and packing the images again.
+struct Shelves {
+    extent: Extent,
+    shelves: Vec<Shelf>,
+    /// The area taken up by images, including padding.
+    used_area: usize,
+}
+
+impl Shelves {
+    /// Space left between images so that they don't bleed into each other
+    /// when sampled.
+    const PADDING: i16 = 1;
+
+    fn new(extent: Extent) -> Self {
+        Self {
+            extent,
+            shelves: Vec::new(),
+            used_area: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.shelves.clear();
+        self.used_area = 0;
+    }
+
+    /// Finds space for an image of `extent`, returning its top-left corner.
+    fn allocate(&mut self, extent: Extent) -> Option<Point> {
+        let width = extent.width + Px(Self::PADDING);
+        let height = extent.height + Px(Self::PADDING);
+
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= height && shelf.end + width <= self.extent.width)
+            .min_by_key(|shelf| shelf.height);
+
+        let shelf = match shelf {
+            Some(shelf) => shelf,
+            None => {
+                let top = self
+                    .shelves
+                    .last()
+                    .map_or(Px(0), |shelf| shelf.top + shelf.height);
+
+                if top + height > self.extent.height || width > self.extent.width {
+                    return None;
+                }
+
+                self.shelves.push(Shelf {
+                    top,
+                    height,
+                    end: Px(0),
+                });
+                self.shelves.last_mut().unwrap()
+            }
+        };
+
+        let point = Point::new(shelf.end, shelf.top);
+        shelf.end += width;
+        self.used_area += Extent { width, height }.area();
+        Some(point)
+    }
+}
+
+/// A cache of rasterized glyphs, stored in a small number of large images so
+/// that text can be drawn with few image switches.
+///
+/// Glyphs are rasterized on first use and uploaded by [`Self::flush`], which
+/// must be called before the command list that draws them is submitted.
+///
+/// The atlas is managed in generations, one per frame, started by
+/// [`Self::begin_frame`]. When the atlas fills up, glyphs that have not been
+/// drawn recently are evicted, least recently used first, and the remaining
+/// glyphs are packed together again. If the glyphs still in use would crowd
+/// the atlas, another page is added, up to [`Self::MAX_PAGES`].
+pub struct GlyphAtlas {
+    pages: Vec<Page>,
+    /// Glyphs without an outline are stored as `None` so that they are not
+    /// rasterized again.
+    entries: HashMap<GlyphKey, Option<Entry>>,
+    /// Glyphs that have been placed but not uploaded yet.
+    pending: Vec<GlyphKey>,
+    generation: u64,
+    /// Set when a glyph could not be placed during the current generation.
+    overflowed: bool,
+}
+
+impl GlyphAtlas {
+    pub const PAGE_EXTENT: Extent = Extent {
+        width: Px(1024),
+        height: Px(1024),
+    };
+
+    pub const MAX_PAGES: usize = 4;
+
+    /// The number of generations after which an unused glyph is evicted when
+    /// the atlas is compacted.
+    const MAX_AGE: u64 = 120;
+
+    /// Compaction is triggered once this fraction of the atlas is in use.
+    const HIGH_WATER: f32 = 0.75;
+
+    /// The fraction of the atlas that may be filled after compaction, leaving
+    /// room for new glyphs.
+    const LOW_WATER: f32 = 0.5;
+
+    /// Creates an atlas with a single page.
+    ///
+    /// ## Errors
+    ///
+    /// Fails if the image for the first page could not be created.
+    pub fn new(gfx: &dyn GfxDevice) -> Result<Self, crate::gfx::Error> {
+        Ok(Self {
+            pages: vec![Page::new(gfx)?],
+            entries: HashMap::new(),
+            pending: Vec::new(),
+            generation: 0,
+            overflowed: false,
+        })
+    }
+
+    /// The number of images used by the atlas.
+    #[must_use]
+    pub fn num_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Checks if every glyph drawn in the current generation was found a
+    /// place. If not, the missing glyphs will be available once the next
+    /// generation begins, and the frame should be drawn again.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        !self.overflowed
+    }
+
+    /// Starts a new generation. This must be called before drawing each frame
+    /// so that the atlas knows which glyphs are in use, and is where the atlas
+    /// is compacted if it is getting full.
+    ///
+    /// ## Errors
+    ///
+    /// Fails if a new page was needed but could not be created.
+    pub fn begin_frame(&mut self, gfx: &dyn GfxDevice) -> Result<(), crate::gfx::Error> {
+        self.generation += 1;
+
+        let used: usize = self.pages.iter().map(|page| page.shelves.used_area).sum();
+        if self.overflowed || used as f32 > self.capacity() as f32 * Self::HIGH_WATER {
+            self.compact(gfx)?;
+        }
+
+        Ok(())
+    }
+
+    /// Uploads every glyph that was placed since the last flush.
+    ///
+    /// ## Errors
+    ///
+    /// Fails if the pixels could not be copied into the atlas.
+    pub fn flush(&mut self, gfx: &dyn GfxDevice) -> Result<(), crate::gfx::Error> {
+        for key in self.pending.drain(..) {
+            let entry = match self.entries.get(&key) {
+                Some(Some(entry)) => entry,
+                _ => continue,
+            };
+
+            let pixels = PixelBuffer::new(
+                Layout::RGBA8,
+                ColorSpace::Linear,
+                entry.rect.extent(),
+                entry.pixels.clone(),
+            );
+
+            gfx.copy_pixels(
+                pixels.view(),
+                self.pages[entry.page].image,
+                &[ImageCopy {
+                    src_rect: Rect::new(Point::zero(), entry.rect.extent()),
+                    dst_location: Offset {
+                        x: entry.rect.left,
+                        y: entry.rect.top,
+                    },
+                }],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Destroys the atlas's images.
+    ///
+    /// ## Errors
+    ///
+    /// Fails if an image is still in use.
+    pub fn destroy(self, gfx: &dyn GfxDevice) -> Result<(), crate::gfx::Error> {
+        for page in self.pages {
+            gfx.destroy_image(page.image)?;
+        }
+        Ok(())
+    }
+
+    /// Draws a line of shaped text with the pen starting at `origin` on the
+    /// baseline. Glyphs are positioned to the nearest pixel. Color glyphs,
+    /// such as emoji, are drawn in their own colors, faded by `color`'s alpha.
+    ///
+    /// Glyphs that don't fit in the atlas are not drawn; see
+    /// [`Self::is_complete`].
+    ///
+    /// `scale` is the scale that the commands are drawn at. Glyphs are
+    /// rasterized and positioned for the physical pixels rather than scaled up
+    /// from logical ones, so that they stay sharp.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_line(
+        &mut self,
+        fonts: &FontCollection,
+        commands: &mut DrawCommandList,
+        line: &ShapedLine,
+        origin: Point,
+        size: f32,
+        scale: ScaleFactor,
+        color: Color,
+        anti_aliasing: GlyphAntiAliasing,
+    ) {
+        let scale = scale.0;
+        let is_scaled = scale != 1.0;
+        if is_scaled {
+            commands.push_transform(&Transform::scale(1.0 / scale, 1.0 / scale));
+        }
+
+        let size = size * scale;
+        let mut pen = f32::from(origin.x) * scale;
+        let baseline = f32::from(origin.y) * scale;
+
+        for run in &line.runs {
+            for glyph in &run.glyphs {
+                let x = Px::from((pen + glyph.offset.x * scale).round());
+                let y = Px::from((baseline + glyph.offset.y * scale).round());
+                pen += glyph.advance * scale;
+
+                let placement =
+                    match self.get_or_insert(fonts, run.font, glyph.glyph, size, anti_aliasing) {
+                        Some(placement) => placement,
+                        None => continue,
+                    };
+
+                let dst = Rect::new(
+                    Point::new(x + placement.bearing.x, y + placement.bearing.y),
+                    placement.rect.extent(),
+                );
+                if placement.color {
+                    commands.draw_color_glyph(placement.image, placement.rect, dst, color.a);
+                } else {
+                    commands.draw_glyph(placement.image, placement.rect, dst, color, anti_aliasing);
+                }
+            }
+        }
+
+        if is_scaled {
+            commands.pop_transform();
+        }
+    }
+
+    /// The total area of every page.
+    fn capacity(&self) -> usize {
+        Self::PAGE_EXTENT.area() * self.pages.len()
+    }
+
+    /// Finds a glyph in the atlas, rasterizing it if it isn't there yet.
+    /// Returns `None` if the glyph has no outline or doesn't fit.
+    fn get_or_insert(
+        &mut self,
+        fonts: &FontCollection,
+        font: FontId,
+        glyph: GlyphId,
+        size: f32,
+        anti_aliasing: GlyphAntiAliasing,
+    ) -> Option<Placement> {
+        let key = GlyphKey {
+            font,
+            glyph,
+            size: (size * 64.0).round() as u32,
+            anti_aliasing,
+        };
+
+        if let Some(entry) = self.entries.get_mut(&key) {
+            let entry = entry.as_mut()?;
+            entry.last_used = self.generation;
+            return Some(Placement {
+                image: self.pages[entry.page].image,
+                rect: entry.rect,
+                bearing: entry.bearing,
+                color: entry.color,
+            });
+        }
+
+        let bitmap = match rasterize(fonts.font(font), glyph, size, anti_aliasing) {
+            Some(bitmap) => bitmap,
+            None => {
+                self.entries.insert(key, None);
+                return None;
+            }
+        };
+
+        // Glyphs that don't fit aren't remembered, so that they are tried
+        // again once the atlas has been compacted.
+        let (page, point) = match place(&mut self.pages, bitmap.extent) {
+            Some(place) => place,
+            None => {
+                self.overflowed = true;
+                return None;
+            }
+        };
+
+        let placement = Placement {
+            image: self.pages[page].image,
+            rect: Rect::new(point, bitmap.extent),
+            bearing: bitmap.bearing,
+            color: bitmap.color,
+        };
+
+        self.entries.insert(
+            key,
+            Some(Entry {
+                page,
+                rect: placement.rect,
+                bearing: placement.bearing,
+                last_used: self.generation,
+                pixels: bitmap.pixels,
+                color: bitmap.color,
+            }),
+        );
+        self.pending.push(key);
+        Some(placement)
+    }
+
+    /// Evicts glyphs that haven't been used recently and packs the rest
+    /// together again, adding pages if the glyphs still in use would crowd
+    /// the atlas.
+    fn compact(&mut self, gfx: &dyn GfxDevice) -> Result<(), crate::gfx::Error> {
+        self.overflowed = false;
+
+        let mut glyphs: Vec<(GlyphKey, u64, usize)> = self
+            .entries
+            .iter()
+            .filter_map(|(key, entry)| {
+                let entry = entry.as_ref()?;
+                Some((*key, entry.last_used, padded_area(entry.rect.extent())))
+            })
+            .collect();
+
+        let recent: usize = glyphs
+            .iter()
+            .filter(|(_, last_used, _)| self.generation - last_used <= Self::MAX_AGE)
+            .map(|(_, _, area)| area)
+            .sum();
+
+        while recent as f32 > self.capacity() as f32 * Self::LOW_WATER
+            && self.pages.len() < Self::MAX_PAGES
+        {
+            self.pages.push(Page::new(gfx)?);
+        }
+
+        let budget = (self.capacity() as f32 * Self::LOW_WATER) as usize;
+        select_survivors(&mut glyphs, self.generation, budget);
+
+        // Tall glyphs are packed first so that shelves are filled evenly.
+        glyphs.sort_by_key(|(key, _, _)| {
+            let entry = self.entries[key].as_ref().unwrap();
+            Reverse(entry.rect.height())
+        });
+
+        let mut old_entries = std::mem::take(&mut self.entries);
+
+        for page in &mut self.pages {
+            page.shelves.clear();
+        }
+        self.pending.clear();
+
+        for (key, _, _) in glyphs {
+            let mut entry = old_entries.remove(&key).flatten().unwrap();
+            if let Some((page, point)) = place(&mut self.pages, entry.rect.extent()) {
+                entry.page = page;
+                entry.rect = Rect::new(point, entry.rect.extent());
+                self.entries.insert(key, Some(entry));
+                self.pending.push(key);
+            }
+        }
+
+        // Glyphs without outlines take up no space, so they are kept.
+        self.entries
+            .extend(old_entries.into_iter().filter(|(_, entry)| entry.is_none()));
+
+        Ok(())
+    }
+}
+
+/// Finds space for an image of `extent` in the first page that has room for
+/// it, returning the page's index and the image's top-left corner.
+fn place(pages: &mut [Page], extent: Extent) -> Option<(usize, Point)> {
+    pages
+        .iter_mut()
+        .enumerate()
+        .find_map(|(index, page)| Some((index, page.shelves.allocate(extent)?)))
+}
+
+fn padded_area(extent: Extent) -> usize {
+    Extent {
+        width: extent.width + Px(Shelves::PADDING),
+        height: extent.height + Px(Shelves::PADDING),
+    }
+    .area()
+}
+
+/// Keeps the most recently used glyphs whose total area fits within `budget`,
+/// discarding any that have not been used for [`GlyphAtlas::MAX_AGE`]
+/// generations. Glyphs are `(key, last used, area)`.
+fn select_survivors<K>(glyphs: &mut Vec<(K, u64, usize)>, generation: u64, budget: usize) {
+    glyphs.retain(|(_, last_used, _)| generation - last_used <= GlyphAtlas::MAX_AGE);
+    glyphs.sort_by_key(|(_, last_used, _)| Reverse(*last_used));
+
+    let mut total = 0;
+    let keep = glyphs
+        .iter()
+        .take_while(|(_, _, area)| {
+            total += area;
+            total <= budget
+        })
+        .count();
+    glyphs.truncate(keep);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shelf_packing() {
+        let mut shelves = Shelves::new(Extent {
+            width: Px(32),
+            height: Px(32),
+        });
+
+        let tall = Extent {
+            width: Px(9),
+            height: Px(15),
+        };
+        let short = Extent {
+            width: Px(9),
+            height: Px(7),
+        };
+
+        // Images go into the shortest shelf that fits them.
+        assert_eq!(shelves.allocate(short), Some(Point::new(Px(0), Px(0))));
+        assert_eq!(shelves.allocate(tall), Some(Point::new(Px(0), Px(8))));
+        assert_eq!(shelves.allocate(short), Some(Point::new(Px(10), Px(0))));
+        assert_eq!(shelves.allocate(tall), Some(Point::new(Px(10), Px(8))));
+        assert_eq!(shelves.used_area, 2 * 10 * 8 + 2 * 10 * 16);
+
+        // The tall shelf fills up, and there's no room for another.
+        assert_eq!(shelves.allocate(tall), Some(Point::new(Px(20), Px(8))));
+        assert_eq!(shelves.allocate(tall), None);
+        assert_eq!(shelves.allocate(short), Some(Point::new(Px(20), Px(0))));
+
+        shelves.clear();
+        assert_eq!(shelves.allocate(tall), Some(Point::new(Px(0), Px(0))));
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let mut glyphs = vec![('a', 10, 4), ('b', 200, 4), ('c', 150, 4), ('d', 199, 4)];
+
+        // 'a' is too old to keep, and only two of the rest fit the budget.
+        select_survivors(&mut glyphs, 200, 9);
+        assert_eq!(glyphs, [('b', 200, 4), ('d', 199, 4)]);
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use super::{
+    font::{describe_file, Font, FontStyle, GlyphId},
+    Error,
+};
+
+/// Families that cover most scripts on a default Windows installation, in the
+/// order that they are tried when a character is missing from a font.
+#[cfg(target_os = "windows")]
+const SYSTEM_FALLBACK_FAMILIES: &[&str] = &[
+    "Segoe UI",
+    "Segoe UI Symbol",
+    "Segoe UI Emoji",
+    "Segoe UI Historic",
+    "Microsoft YaHei",
+    "Yu Gothic",
+    "Malgun Gothic",
+    "Nirmala UI",
+    "Leelawadee UI",
+    "Ebrima",
+    "Gadugi",
+    "Javanese Text",
+    "Myanmar Text",
+    "Mongolian Baiti",
+];
+
+/// Families that cover most scripts on common Linux distributions, in the
+/// order that they are tried when a character is missing from a font.
+#[cfg(target_os = "linux")]
+const SYSTEM_FALLBACK_FAMILIES: &[&str] = &[
+    "DejaVu Sans",
+    "Noto Sans",
+    "Noto Sans CJK SC",
+    "Noto Sans Symbols",
+    "Noto Sans Symbols2",
+    "Noto Color Emoji",
+    "Liberation Sans",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FontId(u32);
+
+/// A contiguous range of text that is drawn with a single font.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FontRun {
+    pub font: FontId,
+    /// The byte range of the run within the text.
+    pub range: Range<usize>,
+}
+
+/// The set of fonts available to the application, along with the order in
+/// which fonts are tried when a character is missing from the requested font.
+///
+/// Fonts can be loaded from files or from memory. System fonts are indexed by
+/// [`Self::load_system_fonts`] but are only loaded once they are requested by
+/// [`Self::query`] or are needed for fallback.
+#[derive(Default)]
+pub struct FontCollection {
+    fonts: Vec<Font>,
+    /// Faces that were found on the system but have not been loaded yet.
+    system_faces: Vec<SystemFace>,
+    /// Fonts to search, in order, for characters that are missing from the
+    /// requested font.
+    fallback: Vec<FontId>,
+}
+
+struct SystemFace {
+    path: PathBuf,
+    index: u32,
+    family: String,
+    style: FontStyle,
+}
+
+impl FontCollection {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every face in the contents of a font file.
+    ///
+    /// ## Errors
+    ///
+    /// Fails if any of the faces could not be parsed. No fonts are added to
+    /// the collection in that case.
+    pub fn load_bytes(&mut self, data: impl Into<Arc<[u8]>>) -> Result<Vec<FontId>, Error> {
+        let data = data.into();
+        let fonts = (0..Font::count_faces(&data)?)
+            .map(|index| Font::from_bytes(data.clone(), index))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(fonts.into_iter().map(|font| self.add(font)).collect())
+    }
+
+    /// Loads every face in the font file at `path`.
+    ///
+    /// ## Errors
+    ///
+    /// Fails if the file could not be read, or for any of the reasons listed
+    /// in [`Self::load_bytes`].
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<Vec<FontId>, Error> {
+        self.load_bytes(std::fs::read(path)?)
+    }
+
+    /// Adds an already-parsed font to the collection.
+    pub fn add(&mut self, font: Font) -> FontId {
+        let id = FontId(u32::try_from(self.fonts.len()).expect("too many fonts"));
+        self.fonts.push(font);
+        id
+    }
+
+    /// Indexes the fonts installed on the system and loads the families used
+    /// for fallback. Files that cannot be read are skipped. Returns the number
+    /// of faces found.
+    ///
+    /// This is currently only implemented on Windows, where it scans the
+    /// system and per-user font directories.
+    pub fn load_system_fonts(&mut self) -> usize {
+        let mut count = 0;
+
+        for directory in system_font_directories() {
+            let entries = match std::fs::read_dir(&directory) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+
+            for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+                if !is_font_file(&path) {
+                    continue;
+                }
+
+                if let Ok(faces) = describe_file(&path) {
+                    count += faces.len();
+                    self.system_faces
+                        .extend(faces.into_iter().map(|(index, family, style)| SystemFace {
+                            path: path.clone(),
+                            index,
+                            family,
+                            style,
+                        }));
+                }
+            }
+        }
+
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        for family in SYSTEM_FALLBACK_FAMILIES {
+            if let Some(font) = self.query(family, FontStyle::REGULAR) {
+                self.push_fallback(font);
+            }
+        }
+
+        count
+    }
+
+    #[must_use]
+    pub fn font(&self, id: FontId) -> &Font {
+        &self.fonts[id.0 as usize]
+    }
+
+    /// Finds the face of `family` whose style is closest to `style`, loading
+    /// it from the system if necessary. Family names are compared without
+    /// regard to case.
+    pub fn query(&mut self, family: &str, style: FontStyle) -> Option<FontId> {
+        let loaded = (0..)
+            .zip(&self.fonts)
+            .filter(|(_, font)| font.family().eq_ignore_ascii_case(family))
+            .map(|(i, font)| (FontId(i), style.distance(&font.style())))
+            .min_by_key(|(_, distance)| *distance);
+
+        let system = self
+            .system_faces
+            .iter()
+            .enumerate()
+            .filter(|(_, face)| face.family.eq_ignore_ascii_case(family))
+            .map(|(i, face)| (i, style.distance(&face.style)))
+            .min_by_key(|(_, distance)| *distance);
+
+        match (loaded, system) {
+            (Some((id, loaded)), Some((_, system))) if loaded <= system => Some(id),
+            (Some((id, _)), None) => Some(id),
+            (_, Some((index, _))) => {
+                // Faces are removed from the index once loaded, or if they
+                // turn out to be unreadable.
+                let face = self.system_faces.swap_remove(index);
+                match Font::from_file(&face.path, face.index) {
+                    Ok(font) => Some(self.add(font)),
+                    Err(_) => self.query(family, style),
+                }
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// Adds a font to the end of the fallback order.
+    pub fn push_fallback(&mut self, font: FontId) {
+        if !self.fallback.contains(&font) {
+            self.fallback.push(font);
+        }
+    }
+
+    /// Replaces the fallback order.
+    pub fn set_fallback(&mut self, fonts: &[FontId]) {
+        self.fallback.clear();
+        self.fallback.extend_from_slice(fonts);
+    }
+
+    /// Finds a font that can draw `c`, trying `font` first, then each font in
+    /// the fallback order, then every other loaded font. Returns `None` if no
+    /// loaded font contains the character.
+    pub fn resolve(&self, font: FontId, c: char) -> Option<(FontId, GlyphId)> {
+        std::iter::once(font)
+            .chain(self.fallback.iter().copied())
+            .chain((0..).take(self.fonts.len()).map(FontId))
+            .find_map(|id| Some((id, self.font(id).glyph_index(c)?)))
+    }
+
+    /// Splits `text` into runs that can each be drawn with a single font,
+    /// preferring `font`. A character stays in the current run if that run's
+    /// font can draw it, so that spaces and punctuation don't break up text in
+    /// a fallback font. Characters that no font can draw are assigned to
+    /// `font`, which will draw them as its missing glyph.
+    #[must_use]
+    pub fn runs(&self, font: FontId, text: &str) -> Vec<FontRun> {
+        let mut runs: Vec<FontRun> = Vec::new();
+
+        for (offset, c) in text.char_indices() {
+            let end = offset + c.len_utf8();
+
+            if let Some(run) = runs.last_mut() {
+                if self.font(run.font).has_glyph(c) {
+                    run.range.end = end;
+                    continue;
+                }
+            }
+
+            let id = self.resolve(font, c).map_or(font, |(id, _)| id);
+            match runs.last_mut() {
+                Some(run) if run.font == id => run.range.end = end,
+                _ => runs.push(FontRun {
+                    font: id,
+                    range: offset..end,
+                }),
+            }
+        }
+
+        runs
+    }
+}
+
+fn is_font_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ["ttf", "otf", "ttc", "otc"]
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e))
+        })
+}
+
+#[cfg(target_os = "windows")]
+fn system_font_directories() -> Vec<PathBuf> {
+    let mut directories = Vec::with_capacity(2);
+
+    if let Some(windows) = std::env::var_os("WINDIR") {
+        directories.push(PathBuf::from(windows).join("Fonts"));
+    }
+
+    if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+        directories.push(PathBuf::from(local).join(r"Microsoft\Windows\Fonts"));
+    }
+
+    directories
+}
+
+/// Fontconfig's default directories and every directory beneath them, since
+/// fonts are usually installed in a subdirectory for each package.
+#[cfg(target_os = "linux")]
+fn system_font_directories() -> Vec<PathBuf> {
+    let mut directories = vec![
+        PathBuf::from("/usr/share/fonts"),
+        PathBuf::from("/usr/local/share/fonts"),
+    ];
+
+    if let Some(home) = std::env::var_os("HOME") {
+        let home = PathBuf::from(home);
+        directories.push(home.join(".local/share/fonts"));
+        directories.push(home.join(".fonts"));
+    }
+
+    let mut next = 0;
+    while next < directories.len() {
+        if let Ok(entries) = std::fs::read_dir(&directories[next]) {
+            directories.extend(
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+                    .map(|entry| entry.path()),
+            );
+        }
+        next += 1;
+    }
+
+    directories
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn system_font_directories() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::test_font;
+
+    #[test]
+    fn font_fallback() {
+        let mut fonts = FontCollection::new();
+        let latin = fonts
+            .load_bytes(test_font::build("Latin", &['a', 'b', ' ']))
+            .unwrap()[0];
+        let cjk = fonts
+            .load_bytes(test_font::build("CJK", &['漢', '字', ' ']))
+            .unwrap()[0];
+        let emoji = fonts
+            .load_bytes(test_font::build("Emoji", &['😀']))
+            .unwrap()[0];
+
+        assert_eq!(fonts.query("latin", FontStyle::BOLD), Some(latin));
+        assert_eq!(fonts.query("Serif", FontStyle::REGULAR), None);
+
+        assert_eq!(fonts.resolve(latin, 'b'), Some((latin, GlyphId(2))));
+        assert_eq!(fonts.resolve(latin, '字'), Some((cjk, GlyphId(2))));
+        assert_eq!(fonts.resolve(latin, 'z'), None);
+
+        // The space stays with the CJK run instead of switching back.
+        let runs = fonts.runs(latin, "ab漢 字😀z");
+        let expected = [(latin, 0..2), (cjk, 2..9), (emoji, 9..13), (latin, 13..14)];
+        assert_eq!(runs.len(), expected.len());
+        for (run, (font, range)) in runs.iter().zip(expected) {
+            assert_eq!((run.font, run.range.clone()), (font, range));
+        }
+
+        fonts.set_fallback(&[emoji]);
+        assert_eq!(fonts.resolve(latin, ' '), Some((latin, GlyphId(3))));
+    }
+}
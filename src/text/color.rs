@@ -0,0 +1,365 @@
+//! Rasterization of color glyphs, such as emoji.
+//!
+//! Two formats are supported: layered glyphs from the `COLR` (version 0) and
+//! `CPAL` tables, where each layer is an outline filled with a color from a
+//! palette, and bitmap glyphs from the `CBDT` or `sbix` tables, where each
+//! glyph is a PNG image drawn at one or more fixed sizes.
+
+use rustybuzz::ttf_parser::{self, RasterImageFormat};
+
+use crate::{
+    gfx::{
+        geometry::{Extent, Offset, PointF, Px},
+        pixel_buffer::Layout,
+    },
+    io::image::decode_png,
+};
+
+use super::{
+    font::{read_u16, read_u32},
+    raster::{bounds, coverage, outline},
+    Font, GlyphBitmap, GlyphId,
+};
+
+/// The palette index that stands for the text color instead of a color from
+/// the palette.
+const FOREGROUND: u16 = 0xFFFF;
+
+/// One layer of a `COLR` glyph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Layer {
+    glyph: GlyphId,
+    palette_index: u16,
+}
+
+/// Draws `glyph` in color if the font has color data for it. Returns `None`
+/// for glyphs that are drawn with the text color.
+pub(super) fn rasterize(
+    font: &Font,
+    face: &rustybuzz::Face,
+    glyph: GlyphId,
+    size: f32,
+) -> Option<GlyphBitmap> {
+    let layers = font.table(b"COLR").and_then(|colr| layers(colr, glyph));
+
+    match layers {
+        Some(layers) => render_layers(font, face, &layers, size),
+        None => render_image(face, glyph, size),
+    }
+}
+
+/// Fills the outline of each layer with its color, from bottom to top.
+///
+/// Layers drawn with the text color are drawn black, since the color of a
+/// glyph is fixed once it has been rasterized.
+fn render_layers(
+    font: &Font,
+    face: &rustybuzz::Face,
+    layers: &[Layer],
+    size: f32,
+) -> Option<GlyphBitmap> {
+    let scale = size / f32::from(font.metrics().units_per_em);
+    let cpal = font.table(b"CPAL");
+
+    let outlines: Vec<_> = layers
+        .iter()
+        .filter_map(|layer| {
+            let color = match layer.palette_index {
+                FOREGROUND => [0, 0, 0, 255],
+                index => cpal.and_then(|cpal| palette_color(cpal, index))?,
+            };
+            Some((outline(face, layer.glyph, scale)?, color))
+        })
+        .collect();
+
+    let all_lines: Vec<_> = outlines
+        .iter()
+        .flat_map(|(lines, _)| lines)
+        .copied()
+        .collect();
+    let (min, max) = bounds(&all_lines)?;
+
+    let left = min.x.floor();
+    let top = min.y.floor();
+    let extent = Extent {
+        width: Px(i16::try_from((max.x.ceil() - left) as i32).ok()?),
+        height: Px(i16::try_from((max.y.ceil() - top) as i32).ok()?),
+    };
+    let (width, height) = (extent.width.0 as usize, extent.height.0 as usize);
+
+    // Layers are blended with premultiplied alpha, then unpremultiplied once
+    // every layer has been drawn.
+    let mut canvas = vec![[0.0f32; 4]; width * height];
+    for (lines, color) in &outlines {
+        let alpha = f32::from(color[3]) / 255.0;
+        let rgb = color.map(|c| f32::from(c) / 255.0 * alpha);
+
+        for (pixel, coverage) in
+            canvas
+                .iter_mut()
+                .zip(coverage(lines, PointF::new(left, top), width, height))
+        {
+            let a = alpha * coverage;
+            for channel in 0..3 {
+                pixel[channel] = rgb[channel] * coverage + pixel[channel] * (1.0 - a);
+            }
+            pixel[3] = a + pixel[3] * (1.0 - a);
+        }
+    }
+
+    let mut pixels = vec![0; width * height * 4].into_boxed_slice();
+    for (out, pixel) in pixels.chunks_exact_mut(4).zip(canvas) {
+        let alpha = pixel[3];
+        if alpha > 0.0 {
+            for channel in 0..3 {
+                out[channel] = (pixel[channel] / alpha * 255.0).round() as u8;
+            }
+            out[3] = (alpha * 255.0).round() as u8;
+        }
+    }
+
+    Some(GlyphBitmap {
+        extent,
+        bearing: Offset {
+            x: Px(left as i16),
+            y: Px(top as i16),
+        },
+        pixels,
+        color: true,
+    })
+}
+
+/// Draws a bitmap glyph, scaling the font's image that is closest in size.
+fn render_image(face: &rustybuzz::Face, glyph: GlyphId, size: f32) -> Option<GlyphBitmap> {
+    let pixels_per_em = size.round().clamp(1.0, f32::from(u16::MAX)) as u16;
+    let image = face.glyph_raster_image(ttf_parser::GlyphId(glyph.0), pixels_per_em)?;
+
+    if image.format != RasterImageFormat::PNG {
+        return None;
+    }
+
+    let buffer = decode_png(image.data).ok()?;
+    let src_extent = buffer.extent();
+    let (src_width, src_height) = (src_extent.width.0 as usize, src_extent.height.0 as usize);
+
+    let rgba: Vec<u8> = match buffer.layout() {
+        Layout::RGBA8 => buffer.bytes().to_vec(),
+        Layout::RGB8 => buffer
+            .bytes()
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        Layout::BC1 | Layout::BC7 => return None,
+    };
+
+    let scale = size / f32::from(image.pixels_per_em.max(1));
+    let width = ((src_width as f32 * scale).round() as usize).max(1);
+    let height = ((src_height as f32 * scale).round() as usize).max(1);
+
+    // Offsets are measured from the bottom-left corner of the image, with
+    // positive `y` pointing up.
+    let left = f32::from(image.x) * scale;
+    let top = -(f32::from(image.y) + f32::from(image.height)) * scale;
+
+    Some(GlyphBitmap {
+        extent: Extent {
+            width: Px(i16::try_from(width).ok()?),
+            height: Px(i16::try_from(height).ok()?),
+        },
+        bearing: Offset {
+            x: Px(left.round() as i16),
+            y: Px(top.round() as i16),
+        },
+        pixels: resample(&rgba, src_width, src_height, width, height).into_boxed_slice(),
+        color: true,
+    })
+}
+
+/// Resizes an RGBA8 image by averaging the pixels that fall within each new
+/// pixel. Colors are weighted by their alpha so that transparent pixels don't
+/// darken the edges of the image.
+fn resample(
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    new_width: usize,
+    new_height: usize,
+) -> Vec<u8> {
+    if (width, height) == (new_width, new_height) {
+        return pixels.to_vec();
+    }
+
+    // The range of source pixels covered by each new pixel along one axis.
+    let span = |i: usize, from: usize, to: usize| {
+        let start = i * from / to;
+        start..((i + 1) * from / to).max(start + 1)
+    };
+
+    let mut out = Vec::with_capacity(new_width * new_height * 4);
+    for y in 0..new_height {
+        let rows = span(y, height, new_height);
+        for x in 0..new_width {
+            let columns = span(x, width, new_width);
+
+            let mut sum = [0u32; 4];
+            let mut count = 0;
+            for row in rows.clone() {
+                for column in columns.clone() {
+                    let pixel = &pixels[(row * width + column) * 4..][..4];
+                    let alpha = u32::from(pixel[3]);
+                    for channel in 0..3 {
+                        sum[channel] += u32::from(pixel[channel]) * alpha;
+                    }
+                    sum[3] += alpha;
+                    count += 1;
+                }
+            }
+
+            // Fully transparent pixels have no color to average.
+            let color = |channel: usize| sum[channel].checked_div(sum[3]).unwrap_or(0) as u8;
+            out.extend([color(0), color(1), color(2), (sum[3] / count) as u8]);
+        }
+    }
+    out
+}
+
+/// Finds the layers of `glyph` in a `COLR` table. Returns `None` if the glyph
+/// has no layers.
+fn layers(colr: &[u8], glyph: GlyphId) -> Option<Vec<Layer>> {
+    let num_base_glyphs = read_u16(colr, 2)? as usize;
+    let base_glyphs = read_u32(colr, 4)? as usize;
+    let layer_records = read_u32(colr, 8)? as usize;
+    let num_layers = read_u16(colr, 12)? as usize;
+
+    // Base glyph records are six bytes each, and sorted by glyph.
+    let (mut low, mut high) = (0, num_base_glyphs);
+    while low < high {
+        let mid = (low + high) / 2;
+        let record = base_glyphs + mid * 6;
+        match read_u16(colr, record)?.cmp(&glyph.0) {
+            std::cmp::Ordering::Less => low = mid + 1,
+            std::cmp::Ordering::Greater => high = mid,
+            std::cmp::Ordering::Equal => {
+                let first = read_u16(colr, record + 2)? as usize;
+                let count = read_u16(colr, record + 4)? as usize;
+                if count == 0 || first + count > num_layers {
+                    return None;
+                }
+
+                return (first..first + count)
+                    .map(|i| {
+                        let layer = layer_records + i * 4;
+                        Some(Layer {
+                            glyph: GlyphId(read_u16(colr, layer)?),
+                            palette_index: read_u16(colr, layer + 2)?,
+                        })
+                    })
+                    .collect();
+            }
+        }
+    }
+
+    None
+}
+
+/// Looks up a color in the first palette of a `CPAL` table, returning it as
+/// RGBA.
+fn palette_color(cpal: &[u8], index: u16) -> Option<[u8; 4]> {
+    let num_entries = read_u16(cpal, 2)?;
+    let num_records = read_u16(cpal, 6)? as usize;
+    let records = read_u32(cpal, 8)? as usize;
+    let first = read_u16(cpal, 12)? as usize;
+
+    if index >= num_entries || first + index as usize >= num_records {
+        return None;
+    }
+
+    // Color records are stored as BGRA.
+    let offset = records + (first + index as usize) * 4;
+    let bgra = cpal.get(offset..offset + 4)?;
+    Some([bgra[2], bgra[1], bgra[0], bgra[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16s(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn colr_layers() {
+        // Glyphs 3 and 7 have layers; base glyph records start at 14 and
+        // layer records at 26.
+        let mut colr = u16s(&[0, 2]);
+        colr.extend(14u32.to_be_bytes());
+        colr.extend(26u32.to_be_bytes());
+        colr.extend(u16s(&[3]));
+        colr.extend(u16s(&[3, 0, 1, 7, 1, 2]));
+        colr.extend(u16s(&[10, 0, 11, 1, 12, FOREGROUND]));
+
+        assert_eq!(
+            layers(&colr, GlyphId(3)),
+            Some(vec![Layer {
+                glyph: GlyphId(10),
+                palette_index: 0
+            }])
+        );
+        assert_eq!(
+            layers(&colr, GlyphId(7)),
+            Some(vec![
+                Layer {
+                    glyph: GlyphId(11),
+                    palette_index: 1
+                },
+                Layer {
+                    glyph: GlyphId(12),
+                    palette_index: FOREGROUND
+                }
+            ])
+        );
+        assert_eq!(layers(&colr, GlyphId(5)), None);
+        assert_eq!(layers(&colr[..20], GlyphId(7)), None);
+    }
+
+    #[test]
+    fn cpal_colors() {
+        // Two palettes of two entries, with records starting at 16.
+        let mut cpal = u16s(&[0, 2, 2, 4]);
+        cpal.extend(16u32.to_be_bytes());
+        cpal.extend(u16s(&[0, 2]));
+        cpal.extend([0, 0, 255, 255, 255, 0, 0, 128, 0, 255, 0, 255, 0, 0, 0, 0]);
+
+        assert_eq!(palette_color(&cpal, 0), Some([255, 0, 0, 255]));
+        assert_eq!(palette_color(&cpal, 1), Some([0, 0, 255, 128]));
+        assert_eq!(palette_color(&cpal, 2), None);
+    }
+
+    #[test]
+    fn resample_image() {
+        // Halving an image averages each 2x2 block, ignoring the colors of
+        // transparent pixels.
+        let pixels = [
+            [255, 0, 0, 255],
+            [0, 0, 0, 0],
+            [255, 0, 0, 255],
+            [0, 0, 0, 0],
+        ]
+        .concat();
+        assert_eq!(resample(&pixels, 2, 2, 1, 1), [255, 0, 0, 127]);
+
+        // Enlarging an image repeats its pixels.
+        let pixels = [[1, 2, 3, 255], [4, 5, 6, 255]].concat();
+        assert_eq!(
+            resample(&pixels, 2, 1, 4, 1),
+            [
+                [1, 2, 3, 255],
+                [1, 2, 3, 255],
+                [4, 5, 6, 255],
+                [4, 5, 6, 255]
+            ]
+            .concat()
+        );
+    }
+}
@@ -0,0 +1,536 @@
+use std::{path::Path, sync::Arc};
+
+use super::Error;
+
+/// The index of a glyph within a font. Glyph 0 is always the font's 'missing
+/// glyph', usually drawn as an empty box.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GlyphId(pub u16);
+
+/// Vertical metrics for a font, in font units. Divide by `units_per_em` and
+/// multiply by the font size to convert to pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FontMetrics {
+    pub units_per_em: u16,
+    /// The distance from the baseline to the top of the tallest glyphs.
+    pub ascender: i16,
+    /// The distance from the baseline to the bottom of the lowest glyphs. This
+    /// is usually negative.
+    pub descender: i16,
+    /// Additional space to put between lines.
+    pub line_gap: i16,
+}
+
+/// The weight and slant of a font face, used to select a face from a family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FontStyle {
+    /// The weight of the face, from 100 (thin) to 900 (black).
+    pub weight: u16,
+    pub italic: bool,
+}
+
+impl FontStyle {
+    pub const REGULAR: Self = Self {
+        weight: 400,
+        italic: false,
+    };
+
+    pub const BOLD: Self = Self {
+        weight: 700,
+        italic: false,
+    };
+
+    /// How far apart two styles are, for picking the closest face to a
+    /// request. A mismatched slant outweighs any difference in weight.
+    #[must_use]
+    pub fn distance(&self, other: &Self) -> u32 {
+        let slant = if self.italic == other.italic { 0 } else { 1000 };
+        u32::from(self.weight.abs_diff(other.weight)) + slant
+    }
+}
+
+impl Default for FontStyle {
+    fn default() -> Self {
+        Self::REGULAR
+    }
+}
+
+/// A single face from a TrueType or OpenType font file. Font files may contain
+/// more than one face, in which case each is loaded separately while sharing
+/// the file's data.
+#[derive(Clone)]
+pub struct Font {
+    data: Arc<[u8]>,
+    /// The index of the face within the font file.
+    index: u32,
+    /// The offset of this face's table directory within `data`.
+    directory: usize,
+    family: String,
+    style: FontStyle,
+    metrics: FontMetrics,
+    num_glyphs: u16,
+    cmap: CharacterMap,
+}
+
+impl Font {
+    /// Parses face `index` from the contents of a font file. Files that are
+    /// not font collections only contain face 0.
+    ///
+    /// ## Errors
+    ///
+    /// Fails if the data is not a valid font, if the face does not exist, or
+    /// if the face does not map Unicode characters to glyphs.
+    pub fn from_bytes(data: impl Into<Arc<[u8]>>, index: u32) -> Result<Self, Error> {
+        let data = data.into();
+        let directory = face_offset(&data, index)?;
+        let tables = TableDirectory::new(&data, directory)?;
+
+        let table = |tag| {
+            tables
+                .find(tag)
+                .and_then(|range| data.get(range))
+                .ok_or(Error::MalformedFont)
+        };
+
+        let head = table(b"head")?;
+        let hhea = table(b"hhea")?;
+        let maxp = table(b"maxp")?;
+        let name = table(b"name")?;
+        let os2 = table(b"OS/2").ok();
+
+        let metrics = FontMetrics {
+            units_per_em: read_u16(head, 18).ok_or(Error::MalformedFont)?,
+            ascender: read_i16(hhea, 4).ok_or(Error::MalformedFont)?,
+            descender: read_i16(hhea, 6).ok_or(Error::MalformedFont)?,
+            line_gap: read_i16(hhea, 8).ok_or(Error::MalformedFont)?,
+        };
+
+        let cmap = {
+            let range = tables.find(b"cmap").ok_or(Error::MalformedFont)?;
+            CharacterMap::new(&data, range.start).ok_or(Error::UnsupportedCharacterMap)?
+        };
+
+        Ok(Self {
+            family: read_family_name(name).ok_or(Error::MalformedFont)?,
+            style: read_style(os2),
+            metrics,
+            num_glyphs: read_u16(maxp, 4).ok_or(Error::MalformedFont)?,
+            cmap,
+            index,
+            directory,
+            data,
+        })
+    }
+
+    /// Reads face `index` from the font file at `path`.
+    ///
+    /// ## Errors
+    ///
+    /// Fails if the file cannot be read, or for any of the reasons listed in
+    /// [`Self::from_bytes`].
+    pub fn from_file(path: impl AsRef<Path>, index: u32) -> Result<Self, Error> {
+        Self::from_bytes(std::fs::read(path)?, index)
+    }
+
+    /// Counts the number of faces in the contents of a font file.
+    ///
+    /// ## Errors
+    ///
+    /// Fails if the data is not a valid font or font collection.
+    pub fn count_faces(data: &[u8]) -> Result<u32, Error> {
+        match read_tag(data, 0) {
+            Some(b"ttcf") => read_u32(data, 8).ok_or(Error::MalformedFont),
+            Some(tag) if is_sfnt_version(tag) => Ok(1),
+            _ => Err(Error::MalformedFont),
+        }
+    }
+
+    /// The contents of the font file that the face was loaded from.
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The index of the face within its font file.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    #[must_use]
+    pub fn family(&self) -> &str {
+        &self.family
+    }
+
+    #[must_use]
+    pub fn style(&self) -> FontStyle {
+        self.style
+    }
+
+    #[must_use]
+    pub fn metrics(&self) -> FontMetrics {
+        self.metrics
+    }
+
+    #[must_use]
+    pub fn num_glyphs(&self) -> u16 {
+        self.num_glyphs
+    }
+
+    /// Looks up the glyph for a character. Returns `None` if the font does not
+    /// contain the character.
+    pub fn glyph_index(&self, c: char) -> Option<GlyphId> {
+        self.cmap
+            .lookup(&self.data, u32::from(c))
+            .filter(|glyph| *glyph != 0 && *glyph < self.num_glyphs)
+            .map(GlyphId)
+    }
+
+    #[must_use]
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.glyph_index(c).is_some()
+    }
+
+    /// Retrieves the raw contents of one of the face's tables.
+    pub fn table(&self, tag: &[u8; 4]) -> Option<&[u8]> {
+        TableDirectory::new(&self.data, self.directory)
+            .ok()?
+            .find(tag)
+            .and_then(|range| self.data.get(range))
+    }
+}
+
+impl std::fmt::Debug for Font {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Font")
+            .field("family", &self.family)
+            .field("style", &self.style)
+            .field("num_glyphs", &self.num_glyphs)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Describes the faces in a font file without loading the whole file, reading
+/// only the tables needed to identify each face.
+///
+/// ## Errors
+///
+/// Fails if the file cannot be read or is not a valid font.
+pub(super) fn describe_file(path: &Path) -> Result<Vec<(u32, String, FontStyle)>, Error> {
+    use std::{
+        fs::File,
+        io::{Read, Seek, SeekFrom},
+    };
+
+    fn read_at(file: &mut File, offset: u64, len: usize) -> Result<Vec<u8>, Error> {
+        let mut buffer = vec![0; len];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    let mut file = File::open(path)?;
+    let header = read_at(&mut file, 0, 12)?;
+    let num_faces = Font::count_faces(&header)?;
+
+    let directories = if read_tag(&header, 0) == Some(b"ttcf") {
+        let offsets = read_at(&mut file, 12, 4 * num_faces as usize)?;
+        (0..num_faces)
+            .map(|i| read_u32(&offsets, 4 * i as usize).ok_or(Error::MalformedFont))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![0]
+    };
+
+    let mut faces = Vec::with_capacity(directories.len());
+    for (index, offset) in (0..).zip(directories) {
+        let num_tables = read_u16(&read_at(&mut file, u64::from(offset) + 4, 2)?, 0)
+            .ok_or(Error::MalformedFont)?;
+        let directory = read_at(&mut file, u64::from(offset), 12 + 16 * num_tables as usize)?;
+        let tables = TableDirectory::new(&directory, 0)?;
+
+        let mut read_table = |tag| match tables.find(tag) {
+            Some(range) => read_at(&mut file, range.start as u64, range.len()).map(Some),
+            None => Ok(None),
+        };
+
+        let name = read_table(b"name")?.ok_or(Error::MalformedFont)?;
+        let os2 = read_table(b"OS/2")?;
+        let family = read_family_name(&name).ok_or(Error::MalformedFont)?;
+        faces.push((index, family, read_style(os2.as_deref())));
+    }
+
+    Ok(faces)
+}
+
+fn is_sfnt_version(tag: &[u8; 4]) -> bool {
+    matches!(tag, [0, 1, 0, 0] | b"OTTO" | b"true")
+}
+
+/// Finds the table directory for a face, handling font collections.
+fn face_offset(data: &[u8], index: u32) -> Result<usize, Error> {
+    let num_faces = Font::count_faces(data)?;
+    if index >= num_faces {
+        return Err(Error::FaceNotFound { index });
+    }
+
+    if read_tag(data, 0) == Some(b"ttcf") {
+        read_u32(data, 12 + 4 * index as usize)
+            .map(|offset| offset as usize)
+            .ok_or(Error::MalformedFont)
+    } else {
+        Ok(0)
+    }
+}
+
+/// The list of tables in a face.
+struct TableDirectory<'a> {
+    records: &'a [u8],
+}
+
+impl<'a> TableDirectory<'a> {
+    fn new(data: &'a [u8], offset: usize) -> Result<Self, Error> {
+        let version = read_tag(data, offset).ok_or(Error::MalformedFont)?;
+        if !is_sfnt_version(version) {
+            return Err(Error::MalformedFont);
+        }
+
+        let num_tables = read_u16(data, offset + 4).ok_or(Error::MalformedFont)? as usize;
+        let records = data
+            .get(offset + 12..offset + 12 + 16 * num_tables)
+            .ok_or(Error::MalformedFont)?;
+
+        Ok(Self { records })
+    }
+
+    /// Finds the byte range of a table within the font file.
+    fn find(&self, tag: &[u8; 4]) -> Option<std::ops::Range<usize>> {
+        self.records.chunks_exact(16).find_map(|record| {
+            if &record[0..4] == tag {
+                let offset = read_u32(record, 8)? as usize;
+                let length = read_u32(record, 12)? as usize;
+                Some(offset..offset.checked_add(length)?)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// The subtable of the `cmap` table used to map Unicode characters to glyphs.
+#[derive(Clone, Copy, Debug)]
+struct CharacterMap {
+    /// The offset of the subtable within the font file.
+    offset: usize,
+    format: u16,
+}
+
+impl CharacterMap {
+    /// Selects the most complete Unicode subtable that we know how to read,
+    /// preferring full-repertoire (format 12) subtables over BMP-only
+    /// (format 4) ones.
+    fn new(data: &[u8], cmap: usize) -> Option<Self> {
+        let num_subtables = read_u16(data, cmap + 2)?;
+
+        (0..num_subtables)
+            .filter_map(|i| {
+                let record = cmap + 4 + 8 * i as usize;
+                let platform = read_u16(data, record)?;
+                let encoding = read_u16(data, record + 2)?;
+                let offset = cmap + read_u32(data, record + 4)? as usize;
+                let format = read_u16(data, offset)?;
+
+                let rank = match (platform, encoding, format) {
+                    (3, 10, 12) | (0, 4 | 6, 12) => 0,
+                    (3, 1, 4) | (0, 3, 4) => 1,
+                    (0, _, 4) => 2,
+                    _ => return None,
+                };
+
+                Some((rank, Self { offset, format }))
+            })
+            .min_by_key(|(rank, _)| *rank)
+            .map(|(_, map)| map)
+    }
+
+    fn lookup(&self, data: &[u8], c: u32) -> Option<u16> {
+        match self.format {
+            4 => self.lookup_format4(data, u16::try_from(c).ok()?),
+            12 => self.lookup_format12(data, c),
+            _ => None,
+        }
+    }
+
+    fn lookup_format4(&self, data: &[u8], c: u16) -> Option<u16> {
+        let seg_count_x2 = read_u16(data, self.offset + 6)? as usize;
+        let end_codes = self.offset + 14;
+        let start_codes = end_codes + seg_count_x2 + 2;
+        let id_deltas = start_codes + seg_count_x2;
+        let id_range_offsets = id_deltas + seg_count_x2;
+
+        // Find the first segment that ends at or after `c`.
+        let (mut lo, mut hi) = (0, seg_count_x2 / 2);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if read_u16(data, end_codes + 2 * mid)? < c {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+
+        let segment = lo;
+        let start = read_u16(data, start_codes + 2 * segment)?;
+        if segment == seg_count_x2 / 2 || c < start {
+            return None;
+        }
+
+        let delta = read_u16(data, id_deltas + 2 * segment)?;
+        let range_offset_position = id_range_offsets + 2 * segment;
+        let range_offset = read_u16(data, range_offset_position)? as usize;
+
+        if range_offset == 0 {
+            Some(c.wrapping_add(delta))
+        } else {
+            let glyph_position = range_offset_position + range_offset + 2 * (c - start) as usize;
+            let glyph = read_u16(data, glyph_position)?;
+            (glyph != 0).then(|| glyph.wrapping_add(delta))
+        }
+    }
+
+    fn lookup_format12(&self, data: &[u8], c: u32) -> Option<u16> {
+        let num_groups = read_u32(data, self.offset + 12)? as usize;
+        let groups = self.offset + 16;
+
+        let (mut lo, mut hi) = (0, num_groups);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let group = groups + 12 * mid;
+            let start = read_u32(data, group)?;
+            let end = read_u32(data, group + 4)?;
+
+            if c < start {
+                hi = mid;
+            } else if c > end {
+                lo = mid + 1;
+            } else {
+                let glyph = read_u32(data, group + 8)? + (c - start);
+                return u16::try_from(glyph).ok();
+            }
+        }
+
+        None
+    }
+}
+
+/// Reads the family name from the `name` table, preferring the typographic
+/// family (which groups more than four styles together) and English names
+/// from the Windows platform.
+fn read_family_name(name: &[u8]) -> Option<String> {
+    let count = read_u16(name, 2)?;
+    let storage = read_u16(name, 4)? as usize;
+
+    let (_, record) = (0..count)
+        .filter_map(|i| {
+            let record = 6 + 12 * i as usize;
+            let platform = read_u16(name, record)?;
+            let language = read_u16(name, record + 4)?;
+            let name_id = read_u16(name, record + 6)?;
+
+            let id_rank = match name_id {
+                16 => 0,
+                1 => 1,
+                _ => return None,
+            };
+
+            let platform_rank = match (platform, language) {
+                (3, 0x409) => 0,
+                (3, _) => 1,
+                (0, _) => 2,
+                (1, 0) => 3,
+                _ => return None,
+            };
+
+            Some(((id_rank, platform_rank), record))
+        })
+        .min_by_key(|(rank, _)| *rank)?;
+
+    let platform = read_u16(name, record)?;
+    let length = read_u16(name, record + 8)? as usize;
+    let offset = storage + read_u16(name, record + 10)? as usize;
+    let bytes = name.get(offset..offset + length)?;
+
+    if platform == 1 {
+        // Mac Roman. Family names are almost always ASCII.
+        Some(bytes.iter().map(|b| char::from(*b)).collect())
+    } else {
+        let units = bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]));
+        char::decode_utf16(units).collect::<Result<_, _>>().ok()
+    }
+}
+
+/// Reads the face's weight and slant from the `OS/2` table, which is optional
+/// in fonts made for Apple platforms.
+fn read_style(os2: Option<&[u8]>) -> FontStyle {
+    let weight = os2.and_then(|t| read_u16(t, 4)).unwrap_or(400);
+    let selection = os2.and_then(|t| read_u16(t, 62)).unwrap_or(0);
+
+    FontStyle {
+        weight,
+        // Bit 0 is ITALIC, bit 9 is OBLIQUE.
+        italic: selection & (1 | 1 << 9) != 0,
+    }
+}
+
+fn read_tag(data: &[u8], offset: usize) -> Option<&[u8; 4]> {
+    data.get(offset..offset + 4)?.try_into().ok()
+}
+
+pub(super) fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Option<i16> {
+    read_u16(data, offset).map(|v| v as i16)
+}
+
+pub(super) fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::test_font;
+
+    #[test]
+    fn parse_font() {
+        let font = Font::from_bytes(test_font::build("Test Sans", &['a', 'b', '😀']), 0).unwrap();
+
+        assert_eq!(font.family(), "Test Sans");
+        assert_eq!(font.style(), FontStyle::REGULAR);
+        assert_eq!(font.num_glyphs(), 4);
+        assert_eq!(font.metrics().units_per_em, 1000);
+        assert_eq!(font.metrics().descender, -200);
+
+        assert_eq!(font.glyph_index('a'), Some(GlyphId(1)));
+        assert_eq!(font.glyph_index('b'), Some(GlyphId(2)));
+        assert_eq!(font.glyph_index('😀'), Some(GlyphId(3)));
+        assert_eq!(font.glyph_index('c'), None);
+
+        assert!(matches!(
+            Font::from_bytes(test_font::build("Test Sans", &[]), 1),
+            Err(Error::FaceNotFound { index: 1 })
+        ));
+        assert!(matches!(
+            Font::from_bytes(vec![0; 12], 0),
+            Err(Error::MalformedFont)
+        ));
+    }
+}
@@ -0,0 +1,312 @@
+use std::ops::Range;
+
+use super::{shape, FontCollection, FontId, ShapedLine};
+
+/// The font and size that text is drawn with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    pub font: FontId,
+    /// The size of the font in pixels per em.
+    pub size: f32,
+}
+
+/// A position between two characters where a caret can be placed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Caret {
+    /// The byte offset of the character after the caret.
+    pub offset: usize,
+    /// The distance from the start of the line to the caret, in pixels.
+    pub x: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct LineMetrics {
+    /// The byte range of the text in the line, excluding the line break and
+    /// any whitespace that the line was wrapped at.
+    pub range: Range<usize>,
+    pub width: f32,
+    /// The distance from the top of the text to the line's baseline.
+    pub baseline: f32,
+    /// The caret positions in the line, sorted by offset. There is one for
+    /// each character boundary, including the end of the line.
+    pub carets: Vec<Caret>,
+    /// The glyphs of the line, ready to be drawn.
+    pub shaped: ShapedLine,
+}
+
+/// The measurements of a block of text, in pixels.
+#[derive(Clone, Debug)]
+pub struct TextMetrics {
+    /// The width of the widest line.
+    pub width: f32,
+    /// The height of every line together.
+    pub height: f32,
+    /// The distance from a baseline to the top of the tallest glyphs.
+    pub ascent: f32,
+    /// The distance from a baseline to the bottom of the lowest glyphs.
+    pub descent: f32,
+    /// The distance between consecutive baselines.
+    pub line_height: f32,
+    pub lines: Vec<LineMetrics>,
+}
+
+impl TextMetrics {
+    /// Finds the caret closest to `x` on `line`.
+    #[must_use]
+    pub fn caret_at(&self, line: usize, x: f32) -> Option<Caret> {
+        self.lines[line]
+            .carets
+            .iter()
+            .min_by(|a, b| (a.x - x).abs().total_cmp(&(b.x - x).abs()))
+            .copied()
+    }
+}
+
+/// Measures `text` as it would be drawn with `style`, without drawing it. The
+/// shaped lines are kept so that the text can be drawn as measured.
+///
+/// Lines are broken at line breaks, and wrapped at whitespace so that no line
+/// is wider than `max_width` unless a single word is. Vertical metrics are
+/// taken from the style's font, and do not account for fallback fonts.
+#[must_use]
+pub fn measure_text(
+    fonts: &FontCollection,
+    text: &str,
+    style: &TextStyle,
+    max_width: Option<f32>,
+) -> TextMetrics {
+    let metrics = fonts.font(style.font).metrics();
+    let scale = style.size / f32::from(metrics.units_per_em);
+    let ascent = f32::from(metrics.ascender) * scale;
+    let descent = -f32::from(metrics.descender) * scale;
+    let line_height = ascent + descent + f32::from(metrics.line_gap) * scale;
+
+    let mut lines = Vec::new();
+    for paragraph in shape(fonts, style.font, style.size, text) {
+        let ranges = match max_width {
+            Some(max_width) if paragraph.width > max_width => {
+                wrap(text, &advances(text, &paragraph), max_width)
+            }
+            _ => vec![paragraph.range.clone()],
+        };
+
+        for range in ranges {
+            let line = if range == paragraph.range {
+                paragraph.clone()
+            } else {
+                shape_range(fonts, style, text, range)
+            };
+
+            lines.push(LineMetrics {
+                range: line.range.clone(),
+                width: line.width,
+                baseline: ascent + lines.len() as f32 * line_height,
+                carets: carets(text, &line),
+                shaped: line,
+            });
+        }
+    }
+
+    TextMetrics {
+        width: lines.iter().map(|line| line.width).fold(0.0, f32::max),
+        height: lines.len() as f32 * line_height,
+        ascent,
+        descent,
+        line_height,
+        lines,
+    }
+}
+
+/// Shapes a range of text within a single paragraph.
+fn shape_range(
+    fonts: &FontCollection,
+    style: &TextStyle,
+    text: &str,
+    range: Range<usize>,
+) -> ShapedLine {
+    let mut line = shape(fonts, style.font, style.size, &text[range.clone()]).swap_remove(0);
+    line.range = range.clone();
+    for run in &mut line.runs {
+        run.range = run.range.start + range.start..run.range.end + range.start;
+        for glyph in &mut run.glyphs {
+            glyph.cluster += range.start;
+        }
+    }
+    line
+}
+
+/// A group of glyphs that were shaped from the same characters.
+struct Cluster {
+    range: Range<usize>,
+    /// The left edge of the cluster, relative to the start of the line.
+    x: f32,
+    width: f32,
+    rtl: bool,
+}
+
+/// Collects the clusters of a line in visual order.
+fn clusters(line: &ShapedLine) -> Vec<Cluster> {
+    let mut clusters: Vec<Cluster> = Vec::new();
+    let mut x = 0.0;
+
+    for run in &line.runs {
+        let first = clusters.len();
+
+        for glyph in &run.glyphs {
+            match clusters[first..].last_mut() {
+                Some(cluster) if cluster.range.start == glyph.cluster => {
+                    cluster.width += glyph.advance;
+                }
+                _ => clusters.push(Cluster {
+                    range: glyph.cluster..glyph.cluster,
+                    x,
+                    width: glyph.advance,
+                    rtl: run.rtl,
+                }),
+            }
+            x += glyph.advance;
+        }
+
+        // Each cluster ends where the next one in logical order begins.
+        let run_clusters = &mut clusters[first..];
+        let mut starts: Vec<usize> = run_clusters.iter().map(|c| c.range.start).collect();
+        starts.sort_unstable();
+        for cluster in run_clusters {
+            cluster.range.end = starts
+                .iter()
+                .find(|&&start| start > cluster.range.start)
+                .copied()
+                .unwrap_or(run.range.end);
+        }
+    }
+
+    clusters
+}
+
+/// Splits each cluster's width evenly between its characters, returning the
+/// offset and advance of every character in the line in logical order.
+fn advances(text: &str, line: &ShapedLine) -> Vec<(usize, f32)> {
+    let mut advances = Vec::new();
+    for cluster in clusters(line) {
+        let chars = text[cluster.range.clone()].char_indices();
+        let count = chars.clone().count().max(1);
+        advances
+            .extend(chars.map(|(i, _)| (cluster.range.start + i, cluster.width / count as f32)));
+    }
+    advances.sort_unstable_by_key(|(offset, _)| *offset);
+    advances
+}
+
+/// Finds the caret position at every character boundary in the line.
+pub(super) fn carets(text: &str, line: &ShapedLine) -> Vec<Caret> {
+    let mut carets = Vec::new();
+    let mut end = None;
+
+    for cluster in clusters(line) {
+        let chars: Vec<usize> = text[cluster.range.clone()]
+            .char_indices()
+            .map(|(i, _)| cluster.range.start + i)
+            .collect();
+        let step = cluster.width / chars.len().max(1) as f32;
+
+        for (i, offset) in chars.into_iter().enumerate() {
+            let distance = step * i as f32;
+            let x = if cluster.rtl {
+                cluster.x + cluster.width - distance
+            } else {
+                cluster.x + distance
+            };
+            carets.push(Caret { offset, x });
+        }
+
+        if cluster.range.end == line.range.end {
+            let x = if cluster.rtl {
+                cluster.x
+            } else {
+                cluster.x + cluster.width
+            };
+            end = Some(x);
+        }
+    }
+
+    carets.sort_unstable_by_key(|caret| caret.offset);
+    carets.push(Caret {
+        offset: line.range.end,
+        x: end.unwrap_or(line.width),
+    });
+    carets
+}
+
+/// Breaks a paragraph into lines no wider than `max_width`, given the advance
+/// of each character. Lines are broken after whitespace, which is left out of
+/// the line. Words wider than `max_width` are put on a line of their own.
+fn wrap(text: &str, advances: &[(usize, f32)], max_width: f32) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+
+    let end_of_text = advances.last().map_or(0, |&(offset, _)| {
+        offset + text[offset..].chars().next().map_or(0, char::len_utf8)
+    });
+
+    let mut start = advances.first().map_or(0, |&(offset, _)| offset);
+    let mut width = 0.0;
+    // The end of the line's content and the start of the next line if the
+    // line is broken at the last whitespace seen.
+    let mut last_break: Option<(usize, usize, f32)> = None;
+    let mut in_whitespace = false;
+    let mut content_end = start;
+
+    for &(offset, advance) in advances {
+        let c = text[offset..].chars().next().unwrap_or(' ');
+
+        if c.is_whitespace() {
+            if !in_whitespace {
+                content_end = offset;
+            }
+            in_whitespace = true;
+            width += advance;
+            continue;
+        }
+
+        if in_whitespace {
+            in_whitespace = false;
+            last_break = Some((content_end, offset, width));
+        }
+
+        width += advance;
+        if width > max_width {
+            if let Some((end, next, width_before)) = last_break.take() {
+                lines.push(start..end);
+                start = next;
+                width -= width_before;
+            }
+        }
+    }
+
+    let end = if in_whitespace {
+        content_end
+    } else {
+        end_of_text
+    };
+    lines.push(start..end);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_lines() {
+        let text = "aa bb  cccccc d";
+        let advances: Vec<_> = text.char_indices().map(|(i, _)| (i, 1.0)).collect();
+
+        assert_eq!(
+            wrap(text, &advances, 100.0),
+            vec![Range { start: 0, end: 15 }]
+        );
+        assert_eq!(wrap(text, &advances, 5.0), [0..5, 7..13, 14..15]);
+
+        // Words that don't fit are left on their own line.
+        assert_eq!(wrap(text, &advances, 2.0), [0..2, 3..5, 7..13, 14..15]);
+    }
+}
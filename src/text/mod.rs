@@ -0,0 +1,103 @@
+pub mod atlas;
+pub mod collection;
+mod color;
+pub mod font;
+pub mod measure;
+pub mod raster;
+pub mod rich;
+pub mod shaping;
+
+pub use atlas::GlyphAtlas;
+pub use collection::{FontCollection, FontId, FontRun};
+pub use font::{Font, FontMetrics, FontStyle, GlyphId};
+pub use measure::{measure_text, Caret, LineMetrics, TextMetrics, TextStyle};
+pub use raster::{rasterize, GlyphBitmap};
+pub use rich::{
+    layout_rich_text, RichLayout, RichLine, RichRun, RichText, TextAttributes, TextSpan,
+};
+pub use shaping::{shape, ShapedGlyph, ShapedLine, ShapedRun};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("the font file could not be read")]
+    Io(#[from] std::io::Error),
+    #[error("the provided bytestream is not a valid TrueType or OpenType font")]
+    MalformedFont,
+    #[error("the font file does not contain a face with index {index}")]
+    FaceNotFound { index: u32 },
+    #[error("the font does not contain a supported character map")]
+    UnsupportedCharacterMap,
+}
+
+/// Builds minimal fonts for tests, since no font files are checked in.
+#[cfg(test)]
+pub(crate) mod test_font {
+    /// Builds a TrueType font named `family` that maps each character in
+    /// `chars` to consecutive glyphs, starting at glyph 1.
+    pub fn build(family: &str, chars: &[char]) -> Vec<u8> {
+        let num_glyphs = u16::try_from(chars.len() + 1).unwrap();
+
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+
+        let mut hhea = vec![0; 36];
+        hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+        hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+        hhea[8..10].copy_from_slice(&100i16.to_be_bytes());
+
+        let mut maxp = vec![0, 0, 0x50, 0];
+        maxp.extend(num_glyphs.to_be_bytes());
+
+        let mut os2 = vec![0; 78];
+        os2[4..6].copy_from_slice(&400u16.to_be_bytes());
+
+        // A format 12 subtable with one group per character.
+        let mut subtable = Vec::new();
+        subtable.extend(12u16.to_be_bytes());
+        subtable.extend(0u16.to_be_bytes());
+        subtable.extend((16 + 12 * chars.len() as u32).to_be_bytes());
+        subtable.extend(0u32.to_be_bytes());
+        subtable.extend((chars.len() as u32).to_be_bytes());
+        let mut sorted = chars.to_vec();
+        sorted.sort_unstable();
+        for c in &sorted {
+            let glyph = chars.iter().position(|x| x == c).unwrap() as u32 + 1;
+            subtable.extend((*c as u32).to_be_bytes());
+            subtable.extend((*c as u32).to_be_bytes());
+            subtable.extend(glyph.to_be_bytes());
+        }
+        let mut cmap = vec![0, 0, 0, 1, 0, 3, 0, 10, 0, 0, 0, 12];
+        cmap.extend(subtable);
+
+        let family: Vec<u8> = family.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        let mut name = vec![0, 0, 0, 1, 0, 18];
+        for field in [3, 1, 0x409, 1, family.len() as u16, 0] {
+            name.extend(field.to_be_bytes());
+        }
+        name.extend(family);
+
+        let tables: [(&[u8; 4], Vec<u8>); 6] = [
+            (b"OS/2", os2),
+            (b"cmap", cmap),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"maxp", maxp),
+            (b"name", name),
+        ];
+
+        let mut font = vec![0, 1, 0, 0, 0, tables.len() as u8, 0, 0, 0, 0, 0, 0];
+        let mut offset = 12 + 16 * tables.len();
+        for (tag, table) in &tables {
+            font.extend(*tag);
+            font.extend(0u32.to_be_bytes());
+            font.extend((offset as u32).to_be_bytes());
+            font.extend((table.len() as u32).to_be_bytes());
+            offset += (table.len() + 3) & !3;
+        }
+        for (_, table) in &tables {
+            font.extend(table);
+            font.resize((font.len() + 3) & !3, 0);
+        }
+        font
+    }
+}
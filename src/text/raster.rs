@@ -0,0 +1,451 @@
+use rustybuzz::ttf_parser::{self, OutlineBuilder};
+
+use crate::gfx::{
+    geometry::{Extent, Offset, PointF, Px},
+    GlyphAntiAliasing,
+};
+
+use super::{color, Font, GlyphId};
+
+/// The weights of the filter applied to subpixel coverage, out of 256. Each
+/// subpixel's coverage is spread over its neighbors to reduce the color
+/// fringes that would otherwise appear on the edges of glyphs.
+const SUBPIXEL_FILTER: [u32; 5] = [8, 77, 86, 77, 8];
+
+/// An image of a single glyph.
+#[derive(Clone, Debug)]
+pub struct GlyphBitmap {
+    pub extent: Extent,
+    /// The offset from the pen position on the baseline to the top-left
+    /// corner of the bitmap.
+    pub bearing: Offset,
+    /// The glyph's coverage as RGBA8 pixels, in rows from top to bottom.
+    /// Grayscale glyphs store the same coverage in every channel. Subpixel
+    /// glyphs store the coverage of each color channel separately, with the
+    /// greatest of the three in the alpha channel.
+    ///
+    /// Color glyphs instead store their colors, with unpremultiplied alpha.
+    pub pixels: Box<[u8]>,
+    /// Whether the glyph is drawn with its own colors instead of the text
+    /// color.
+    pub color: bool,
+}
+
+/// Draws `glyph` from `font` at `size` pixels per em. Color glyphs, such as
+/// emoji, are drawn in color regardless of `anti_aliasing`. Returns `None` if
+/// the glyph has no outline, such as for a space, or if it is too large to
+/// fit in a bitmap.
+#[must_use]
+pub fn rasterize(
+    font: &Font,
+    glyph: GlyphId,
+    size: f32,
+    anti_aliasing: GlyphAntiAliasing,
+) -> Option<GlyphBitmap> {
+    let face = rustybuzz::Face::from_slice(font.data(), font.index())?;
+
+    if let Some(bitmap) = color::rasterize(font, &face, glyph, size) {
+        return Some(bitmap);
+    }
+
+    let scale = size / f32::from(font.metrics().units_per_em);
+    render(&outline(&face, glyph, scale)?, anti_aliasing)
+}
+
+/// Retrieves the outline of a glyph as line segments, scaled by `scale` from
+/// font units to pixels. Returns `None` if the glyph has no outline.
+pub(super) fn outline(
+    face: &rustybuzz::Face,
+    glyph: GlyphId,
+    scale: f32,
+) -> Option<Vec<(PointF, PointF)>> {
+    let mut outline = Outline::new(scale);
+    face.outline_glyph(ttf_parser::GlyphId(glyph.0), &mut outline)?;
+    Some(outline.lines)
+}
+
+/// Finds the smallest and largest coordinates of the lines. Returns `None` if
+/// they enclose no area.
+pub(super) fn bounds(lines: &[(PointF, PointF)]) -> Option<(PointF, PointF)> {
+    let (min, max) = lines.iter().flat_map(|(a, b)| [a, b]).fold(
+        (
+            PointF::new(f32::MAX, f32::MAX),
+            PointF::new(f32::MIN, f32::MIN),
+        ),
+        |(min, max), p| {
+            (
+                PointF::new(min.x.min(p.x), min.y.min(p.y)),
+                PointF::new(max.x.max(p.x), max.y.max(p.y)),
+            )
+        },
+    );
+
+    (min.x < max.x && min.y < max.y).then_some((min, max))
+}
+
+/// Computes the coverage of each pixel in a `width` by `height` area whose
+/// top-left corner is at `origin`, in rows from top to bottom.
+pub(super) fn coverage(
+    lines: &[(PointF, PointF)],
+    origin: PointF,
+    width: usize,
+    height: usize,
+) -> Vec<f32> {
+    let mut canvas = Canvas::new(width, height);
+    for (a, b) in lines {
+        canvas.line(
+            PointF::new(a.x - origin.x, a.y - origin.y),
+            PointF::new(b.x - origin.x, b.y - origin.y),
+        );
+    }
+    canvas.coverage().collect()
+}
+
+/// Fills the area enclosed by `lines` using the nonzero winding rule. Lines
+/// are in pixels relative to the pen position, with positive `y` pointing
+/// down.
+fn render(lines: &[(PointF, PointF)], anti_aliasing: GlyphAntiAliasing) -> Option<GlyphBitmap> {
+    let (min, max) = bounds(lines)?;
+
+    // The subpixel filter spreads coverage into the neighboring pixels.
+    let padding = match anti_aliasing {
+        GlyphAntiAliasing::Grayscale => 0.0,
+        GlyphAntiAliasing::Subpixel => 1.0,
+    };
+
+    let left = (min.x - padding).floor();
+    let top = min.y.floor();
+    let width = (max.x + padding).ceil() - left;
+    let height = max.y.ceil() - top;
+
+    let extent = Extent {
+        width: Px(i16::try_from(width as i32).ok()?),
+        height: Px(i16::try_from(height as i32).ok()?),
+    };
+    let bearing = Offset {
+        x: Px(left as i16),
+        y: Px(top as i16),
+    };
+
+    let (width, height) = (width as usize, height as usize);
+    let mut pixels = vec![0; width * height * 4].into_boxed_slice();
+
+    match anti_aliasing {
+        GlyphAntiAliasing::Grayscale => {
+            let coverage = coverage(lines, PointF::new(left, top), width, height);
+            for (pixel, coverage) in pixels.chunks_exact_mut(4).zip(coverage) {
+                pixel.fill(to_u8(coverage));
+            }
+        }
+        GlyphAntiAliasing::Subpixel => {
+            // Each pixel is rendered as three horizontal subpixels.
+            let mut canvas = Canvas::new(width * 3, height);
+            for (a, b) in lines {
+                canvas.line(
+                    PointF::new((a.x - left) * 3.0, a.y - top),
+                    PointF::new((b.x - left) * 3.0, b.y - top),
+                );
+            }
+
+            let coverage: Vec<u32> = canvas.coverage().map(|c| u32::from(to_u8(c))).collect();
+            for (row, pixels) in coverage
+                .chunks_exact(width * 3)
+                .zip(pixels.chunks_exact_mut(width * 4))
+            {
+                for (x, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+                    for (channel, value) in pixel[..3].iter_mut().enumerate() {
+                        *value = filter(row, x * 3 + channel);
+                    }
+                    pixel[3] = pixel[0].max(pixel[1]).max(pixel[2]);
+                }
+            }
+        }
+    }
+
+    Some(GlyphBitmap {
+        extent,
+        bearing,
+        pixels,
+        color: false,
+    })
+}
+
+/// Applies [`SUBPIXEL_FILTER`] to the subpixel at `index` in `row`.
+fn filter(row: &[u32], index: usize) -> u8 {
+    let sum: u32 = SUBPIXEL_FILTER
+        .iter()
+        .enumerate()
+        .filter_map(|(i, weight)| Some(weight * row.get((index + i).checked_sub(2)?)?))
+        .sum();
+    (sum / 256) as u8
+}
+
+fn to_u8(coverage: f32) -> u8 {
+    (coverage * 255.0).round() as u8
+}
+
+/// Collects a glyph's outline as line segments in pixels, flattening curves
+/// along the way.
+struct Outline {
+    scale: f32,
+    start: PointF,
+    current: PointF,
+    lines: Vec<(PointF, PointF)>,
+}
+
+impl Outline {
+    /// The greatest distance, in pixels, that a flattened curve is allowed to
+    /// stray from the true curve. Small enough that the error is not visible.
+    const TOLERANCE: f32 = 0.1;
+
+    fn new(scale: f32) -> Self {
+        Self {
+            scale,
+            start: PointF::zero(),
+            current: PointF::zero(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Converts font units, where positive `y` points up, to pixels.
+    fn point(&self, x: f32, y: f32) -> PointF {
+        PointF::new(x * self.scale, -y * self.scale)
+    }
+
+    fn push(&mut self, to: PointF) {
+        self.lines.push((self.current, to));
+        self.current = to;
+    }
+
+    /// The number of line segments needed to approximate a curve whose control
+    /// polygon deviates from a straight line by `deviation` pixels.
+    fn segments(deviation: f32) -> usize {
+        ((deviation / Self::TOLERANCE).sqrt().ceil() as usize).clamp(1, 64)
+    }
+}
+
+impl OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.point(x, y);
+        self.current = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let to = self.point(x, y);
+        self.push(to);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let p0 = self.current;
+        let p1 = self.point(x1, y1);
+        let p2 = self.point(x, y);
+
+        let deviation = distance(p0.x - 2.0 * p1.x + p2.x, p0.y - 2.0 * p1.y + p2.y);
+        let n = Self::segments(deviation / 4.0);
+        for i in 1..=n {
+            let t = i as f32 / n as f32;
+            let u = 1.0 - t;
+            self.push(PointF::new(
+                u * u * p0.x + 2.0 * u * t * p1.x + t * t * p2.x,
+                u * u * p0.y + 2.0 * u * t * p1.y + t * t * p2.y,
+            ));
+        }
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let p0 = self.current;
+        let p1 = self.point(x1, y1);
+        let p2 = self.point(x2, y2);
+        let p3 = self.point(x, y);
+
+        let deviation = distance(p0.x - 2.0 * p1.x + p2.x, p0.y - 2.0 * p1.y + p2.y)
+            .max(distance(p1.x - 2.0 * p2.x + p3.x, p1.y - 2.0 * p2.y + p3.y));
+        let n = Self::segments(deviation * 3.0 / 4.0);
+        for i in 1..=n {
+            let t = i as f32 / n as f32;
+            let u = 1.0 - t;
+            let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+            self.push(PointF::new(
+                a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+                a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+            ));
+        }
+    }
+
+    fn close(&mut self) {
+        if self.current != self.start {
+            self.push(self.start);
+        }
+    }
+}
+
+fn distance(x: f32, y: f32) -> f32 {
+    (x * x + y * y).sqrt()
+}
+
+/// Accumulates the signed area covered by each line in the pixels that it
+/// crosses. Summing the accumulated values along each row then gives the
+/// coverage of every pixel, since the contributions of a closed outline's
+/// edges cancel out outside of it.
+struct Canvas {
+    width: usize,
+    height: usize,
+    /// One extra value at the end catches area to the right of the last
+    /// pixel, which is always zero for a closed outline.
+    accumulation: Vec<f32>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            accumulation: vec![0.0; width * height + 2],
+        }
+    }
+
+    /// Adds the area to the right of the line from `p0` to `p1`. Both points
+    /// must be within the canvas.
+    fn line(&mut self, p0: PointF, p1: PointF) {
+        if (p0.y - p1.y).abs() <= f32::EPSILON {
+            return;
+        }
+
+        // Lines are always walked downwards, with the direction deciding
+        // whether they add or remove area.
+        let (direction, p0, p1) = if p0.y < p1.y {
+            (1.0, p0, p1)
+        } else {
+            (-1.0, p1, p0)
+        };
+
+        let dxdy = (p1.x - p0.x) / (p1.y - p0.y);
+        let mut x = p0.x;
+
+        let y0 = p0.y.max(0.0) as usize;
+        let y1 = (p1.y.ceil() as usize).min(self.height);
+
+        for y in y0..y1 {
+            let row = y * self.width;
+            let dy = (y as f32 + 1.0).min(p1.y) - (y as f32).max(p0.y);
+            let x_next = x + dxdy * dy;
+            let d = dy * direction;
+
+            let (x0, x1) = if x < x_next { (x, x_next) } else { (x_next, x) };
+            let x0_floor = x0.floor();
+            let x0i = x0_floor.max(0.0) as usize;
+            let x1_ceil = x1.ceil();
+            let x1i = x1_ceil.max(0.0) as usize;
+
+            if x1i <= x0i + 1 {
+                // The line crosses at most one pixel in this row.
+                let xm = 0.5 * (x + x_next) - x0_floor;
+                self.accumulation[row + x0i] += d - d * xm;
+                self.accumulation[row + x0i + 1] += d * xm;
+            } else {
+                let s = (x1 - x0).recip();
+                let x0f = x0 - x0_floor;
+                let a0 = 0.5 * s * (1.0 - x0f) * (1.0 - x0f);
+                let x1f = x1 - x1_ceil + 1.0;
+                let am = 0.5 * s * x1f * x1f;
+
+                self.accumulation[row + x0i] += d * a0;
+                if x1i == x0i + 2 {
+                    self.accumulation[row + x0i + 1] += d * (1.0 - a0 - am);
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    self.accumulation[row + x0i + 1] += d * (a1 - a0);
+                    for xi in x0i + 2..x1i - 1 {
+                        self.accumulation[row + xi] += d * s;
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                    self.accumulation[row + x1i - 1] += d * (1.0 - a2 - am);
+                }
+                self.accumulation[row + x1i] += d * am;
+            }
+
+            x = x_next;
+        }
+    }
+
+    /// The coverage of each pixel, from 0 to 1, in rows from top to bottom.
+    fn coverage(&self) -> impl Iterator<Item = f32> + '_ {
+        self.accumulation[..self.width * self.height]
+            .iter()
+            .scan(0.0, |sum, value| {
+                *sum += value;
+                Some(f32::min(sum.abs(), 1.0))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(left: f32, top: f32, size: f32) -> Vec<(PointF, PointF)> {
+        let (right, bottom) = (left + size, top + size);
+        vec![
+            (PointF::new(left, top), PointF::new(right, top)),
+            (PointF::new(right, top), PointF::new(right, bottom)),
+            (PointF::new(right, bottom), PointF::new(left, bottom)),
+            (PointF::new(left, bottom), PointF::new(left, top)),
+        ]
+    }
+
+    #[test]
+    fn rasterize_square() {
+        let bitmap = render(&square(1.0, -4.0, 3.0), GlyphAntiAliasing::Grayscale).unwrap();
+        assert_eq!(
+            bitmap.extent,
+            Extent {
+                width: Px(3),
+                height: Px(3)
+            }
+        );
+        assert_eq!(
+            bitmap.bearing,
+            Offset {
+                x: Px(1),
+                y: Px(-4)
+            }
+        );
+        assert!(bitmap.pixels.iter().all(|&value| value == 255));
+
+        // A square that ends halfway through a column covers half of it.
+        let bitmap = render(&square(0.0, 0.0, 1.5), GlyphAntiAliasing::Grayscale).unwrap();
+        assert_eq!(
+            bitmap.extent,
+            Extent {
+                width: Px(2),
+                height: Px(2)
+            }
+        );
+        assert_eq!(
+            &bitmap.pixels[..8],
+            &[255, 255, 255, 255, 128, 128, 128, 128]
+        );
+
+        // Subpixel glyphs are padded for the filter, and only the channels
+        // near the edges are partially covered.
+        let bitmap = render(&square(0.0, 0.0, 3.0), GlyphAntiAliasing::Subpixel).unwrap();
+        assert_eq!(
+            bitmap.extent,
+            Extent {
+                width: Px(5),
+                height: Px(3)
+            }
+        );
+        assert_eq!(
+            bitmap.bearing,
+            Offset {
+                x: Px(-1),
+                y: Px(0)
+            }
+        );
+        let row = &bitmap.pixels[..20];
+        assert_eq!(&row[..4], &[0, 7, 84, 84]);
+        assert_eq!(&row[8..12], &[255, 255, 255, 255]);
+        assert_eq!(&row[16..], &[84, 7, 0, 84]);
+    }
+}
@@ -0,0 +1,544 @@
+use std::ops::Range;
+
+use crate::gfx::color::Color;
+
+use super::{
+    measure::carets, shape, Caret, FontCollection, FontId, FontStyle, ShapedLine, TextStyle,
+};
+
+/// How a span of [`RichText`] differs from the style that the text is drawn
+/// with. Attributes that are `None` are inherited from that style.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextAttributes {
+    pub font: Option<FontId>,
+    /// The size of the font in pixels per em.
+    pub size: Option<f32>,
+    /// The weight of the font, from 100 (thin) to 900 (black). The closest
+    /// face of the font's family is used.
+    pub weight: Option<u16>,
+    pub italic: Option<bool>,
+    pub color: Option<Color>,
+    /// Where the span links to. Links are underlined, and what it means to
+    /// follow one is up to the application.
+    pub link: Option<String>,
+}
+
+impl TextAttributes {
+    #[must_use]
+    pub fn with_font(mut self, font: FontId) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    #[must_use]
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    #[must_use]
+    pub fn with_weight(mut self, weight: u16) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    #[must_use]
+    pub fn with_italic(mut self, italic: bool) -> Self {
+        self.italic = Some(italic);
+        self
+    }
+
+    #[must_use]
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    #[must_use]
+    pub fn with_link(mut self, link: impl Into<String>) -> Self {
+        self.link = Some(link.into());
+        self
+    }
+
+    /// Bold text, with a weight of 700.
+    #[must_use]
+    pub fn bold() -> Self {
+        Self::default().with_weight(700)
+    }
+
+    #[must_use]
+    pub fn italic() -> Self {
+        Self::default().with_italic(true)
+    }
+
+    /// The style that text with these attributes is drawn in, within text
+    /// drawn in `style`. A different weight or slant picks the closest face
+    /// of the font's family, loading it from the system if necessary.
+    #[must_use]
+    pub fn resolve(&self, fonts: &mut FontCollection, style: &TextStyle) -> TextStyle {
+        let font = self.font.unwrap_or(style.font);
+        let font = if self.weight.is_some() || self.italic.is_some() {
+            let face = fonts.font(font);
+            let wanted = FontStyle {
+                weight: self.weight.unwrap_or(face.style().weight),
+                italic: self.italic.unwrap_or(face.style().italic),
+            };
+            let family = face.family().to_owned();
+            fonts.query(&family, wanted).unwrap_or(font)
+        } else {
+            font
+        };
+
+        TextStyle {
+            font,
+            size: self.size.unwrap_or(style.size),
+        }
+    }
+}
+
+/// A part of [`RichText`] with its own attributes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextSpan {
+    /// The byte range of the span in the text.
+    pub range: Range<usize>,
+    pub attributes: TextAttributes,
+}
+
+/// Text made of spans that can each have a different font, size, weight,
+/// color, or link, so that a paragraph with mixed styles can be drawn as one.
+///
+/// ```ignore
+/// let text = RichText::new()
+///     .with_text("Read the ")
+///     .with_span("manual", TextAttributes::default().with_link("help:manual"))
+///     .with_text(" before ")
+///     .with_span("anything else", TextAttributes::bold());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RichText {
+    text: String,
+    /// The spans, in order. Every byte of the text is in exactly one span.
+    spans: Vec<TextSpan>,
+}
+
+impl From<&str> for RichText {
+    fn from(text: &str) -> Self {
+        Self::new().with_text(text)
+    }
+}
+
+impl From<String> for RichText {
+    fn from(text: String) -> Self {
+        Self::new().with_text(&text)
+    }
+}
+
+impl RichText {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds text in the style that the text is drawn with.
+    #[must_use]
+    pub fn with_text(self, text: &str) -> Self {
+        self.with_span(text, TextAttributes::default())
+    }
+
+    #[must_use]
+    pub fn with_span(mut self, text: &str, attributes: TextAttributes) -> Self {
+        self.push(text, attributes);
+        self
+    }
+
+    /// Adds a span to the end of the text, extending the last span instead if
+    /// it has the same attributes.
+    pub fn push(&mut self, text: &str, attributes: TextAttributes) {
+        if text.is_empty() {
+            return;
+        }
+
+        let start = self.text.len();
+        self.text.push_str(text);
+        match self.spans.last_mut() {
+            Some(last) if last.attributes == attributes => last.range.end = self.text.len(),
+            _ => self.spans.push(TextSpan {
+                range: start..self.text.len(),
+                attributes,
+            }),
+        }
+    }
+
+    /// The text of every span together, without attributes.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    #[must_use]
+    pub fn spans(&self) -> &[TextSpan] {
+        &self.spans
+    }
+
+    /// Finds the span that contains the byte at `offset`.
+    #[must_use]
+    pub fn span_at(&self, offset: usize) -> Option<&TextSpan> {
+        self.spans.iter().find(|span| span.range.contains(&offset))
+    }
+}
+
+/// A piece of a line of rich text, shaped in the style of one span.
+#[derive(Clone, Debug)]
+pub struct RichRun {
+    /// The index of the span that the run is part of.
+    pub span: usize,
+    /// The byte range of the run in the text.
+    pub range: Range<usize>,
+    /// The distance from the start of the line to the start of the run.
+    pub x: f32,
+    pub width: f32,
+    pub style: TextStyle,
+    /// The glyphs of the run. Their clusters are relative to the start of the
+    /// run.
+    pub shaped: ShapedLine,
+}
+
+#[derive(Clone, Debug)]
+pub struct RichLine {
+    pub runs: Vec<RichRun>,
+    /// The byte range of the text in the line, excluding the line break.
+    pub range: Range<usize>,
+    /// The width of the line, without the whitespace that it was wrapped at.
+    pub width: f32,
+    /// The distance from the top of the text to the top of the line.
+    pub top: f32,
+    /// The distance from the top of the text to the line's baseline.
+    pub baseline: f32,
+    pub height: f32,
+    /// The caret positions in the line, sorted by offset. There is one for
+    /// each character boundary, including the end of the line.
+    pub carets: Vec<Caret>,
+}
+
+impl RichLine {
+    /// The distance from the start of the line to the caret before `offset`,
+    /// which is clamped to the line.
+    #[must_use]
+    pub fn caret_x(&self, offset: usize) -> f32 {
+        self.carets
+            .iter()
+            .rev()
+            .find(|caret| caret.offset <= offset)
+            .or_else(|| self.carets.first())
+            .map_or(0.0, |caret| caret.x)
+    }
+}
+
+/// The lines of a block of rich text, measured and ready to be drawn.
+#[derive(Clone, Debug, Default)]
+pub struct RichLayout {
+    /// The width of the widest line.
+    pub width: f32,
+    pub height: f32,
+    pub lines: Vec<RichLine>,
+}
+
+impl RichLayout {
+    /// Finds the line at `y` pixels below the top of the text. Points above
+    /// or below the text are on its first or last line.
+    #[must_use]
+    pub fn line_at(&self, y: f32) -> Option<usize> {
+        let last = self.lines.len().checked_sub(1)?;
+        Some(
+            self.lines
+                .iter()
+                .position(|line| y < line.top + line.height)
+                .unwrap_or(last),
+        )
+    }
+
+    /// Finds the line holding the caret before `offset`.
+    #[must_use]
+    pub fn line_of(&self, offset: usize) -> Option<usize> {
+        let last = self.lines.len().checked_sub(1)?;
+        Some(
+            self.lines
+                .iter()
+                .position(|line| offset <= line.range.end)
+                .unwrap_or(last),
+        )
+    }
+
+    /// Finds the caret closest to `x` on `line`.
+    #[must_use]
+    pub fn caret_at(&self, line: usize, x: f32) -> Option<Caret> {
+        self.lines
+            .get(line)?
+            .carets
+            .iter()
+            .min_by(|a, b| (a.x - x).abs().total_cmp(&(b.x - x).abs()))
+            .copied()
+    }
+}
+
+/// A word, or the part of one in a single span, measured for line breaking.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Piece {
+    /// The width of the piece, including the whitespace after it.
+    width: f32,
+    /// The width of the piece without the whitespace after it.
+    content_width: f32,
+    /// The line may be broken after the piece.
+    break_after: bool,
+    /// The paragraph ends after the piece.
+    newline_after: bool,
+}
+
+/// Lays out `text` in lines no wider than `max_width`, unless a single word
+/// is. `styles` has the style of each span, with their fonts already chosen
+/// for their weight and slant.
+///
+/// Lines are broken at line breaks and wrapped at whitespace between words, as
+/// with [`measure_text`](super::measure_text). Each line is as tall as the
+/// tallest style in it, and the runs in a line share its baseline.
+#[must_use]
+pub fn layout_rich_text(
+    fonts: &FontCollection,
+    text: &RichText,
+    styles: &[TextStyle],
+    max_width: Option<f32>,
+) -> RichLayout {
+    let mut pieces = Vec::new();
+    let mut runs = Vec::new();
+
+    for (index, (span, style)) in text.spans.iter().zip(styles).enumerate() {
+        for (range, newline_after) in split_words(&text.text, span.range.clone()) {
+            let run_text = &text.text[range.clone()];
+            let shaped = shape(fonts, style.font, style.size, run_text).swap_remove(0);
+            let content_len = run_text.trim_end().len();
+            let content_width = shaped
+                .runs
+                .iter()
+                .flat_map(|run| &run.glyphs)
+                .filter(|glyph| glyph.cluster < content_len)
+                .map(|glyph| glyph.advance)
+                .sum();
+
+            pieces.push(Piece {
+                width: shaped.width,
+                content_width,
+                break_after: content_len < run_text.len(),
+                newline_after,
+            });
+            runs.push(RichRun {
+                span: index,
+                range,
+                x: 0.0,
+                width: shaped.width,
+                style: *style,
+                shaped,
+            });
+        }
+    }
+
+    let mut layout = RichLayout::default();
+    let mut runs = runs.into_iter();
+    for line in break_lines(&pieces, max_width) {
+        let mut line_runs: Vec<RichRun> = runs.by_ref().take(line.len()).collect();
+
+        let (mut ascent, mut descent, mut gap) = (0.0f32, 0.0f32, 0.0f32);
+        let mut x = 0.0;
+        for run in &mut line_runs {
+            let metrics = fonts.font(run.style.font).metrics();
+            let scale = run.style.size / f32::from(metrics.units_per_em);
+            ascent = ascent.max(f32::from(metrics.ascender) * scale);
+            descent = descent.max(-f32::from(metrics.descender) * scale);
+            gap = gap.max(f32::from(metrics.line_gap) * scale);
+
+            run.x = x;
+            x += run.width;
+        }
+
+        let width = match (line_runs.last(), pieces[line.clone()].last()) {
+            (Some(run), Some(piece)) => run.x + piece.content_width,
+            _ => 0.0,
+        };
+        let range = match (line_runs.first(), line_runs.last()) {
+            (Some(first), Some(last)) => first.range.start..last.range.end,
+            _ => 0..0,
+        };
+        let top = layout.height;
+        layout.lines.push(RichLine {
+            carets: line_carets(&text.text, &line_runs),
+            runs: line_runs,
+            range,
+            width,
+            top,
+            baseline: top + ascent,
+            height: ascent + descent + gap,
+        });
+        layout.width = layout.width.max(width);
+        layout.height += ascent + descent + gap;
+    }
+
+    layout
+}
+
+/// Finds the caret position at every character boundary in a line made of
+/// `runs`, sorted by offset.
+fn line_carets(text: &str, runs: &[RichRun]) -> Vec<Caret> {
+    let mut found: Vec<Caret> = Vec::new();
+    for run in runs {
+        // The caret at the end of each run is the one at the start of the
+        // next.
+        found.pop();
+        found.extend(
+            carets(&text[run.range.clone()], &run.shaped)
+                .into_iter()
+                .map(|caret| Caret {
+                    offset: run.range.start + caret.offset,
+                    x: run.x + caret.x,
+                }),
+        );
+    }
+    found
+}
+
+/// Splits `range` of `text` into words with the whitespace after them, and
+/// at line breaks, which are left out. Returns each piece's range, and whether
+/// a line break follows it.
+fn split_words(text: &str, range: Range<usize>) -> Vec<(Range<usize>, bool)> {
+    let mut pieces = Vec::new();
+    let mut start = range.start;
+    let mut in_whitespace = false;
+
+    for (i, c) in text[range.clone()].char_indices() {
+        let offset = range.start + i;
+        if c == '\n' {
+            pieces.push((start..offset, true));
+            start = offset + 1;
+            in_whitespace = false;
+        } else if c.is_whitespace() {
+            in_whitespace = true;
+        } else if in_whitespace {
+            pieces.push((start..offset, false));
+            start = offset;
+            in_whitespace = false;
+        }
+    }
+
+    if start < range.end {
+        pieces.push((start..range.end, false));
+    }
+    pieces
+}
+
+/// Breaks the pieces of a text into lines no wider than `max_width`, returning
+/// the range of pieces in each line. Lines are only broken after a piece that
+/// allows it, so a word made of several pieces is kept together, and words
+/// wider than `max_width` are put on a line of their own.
+fn break_lines(pieces: &[Piece], max_width: Option<f32>) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut width = 0.0;
+
+    let mut i = 0;
+    while i < pieces.len() {
+        // The word starting at `i`, up to where the line can next be broken.
+        let mut end = i;
+        let mut word_width = 0.0;
+        let mut content_width;
+        loop {
+            let piece = pieces[end];
+            content_width = word_width + piece.content_width;
+            word_width += piece.width;
+            end += 1;
+            if piece.break_after || piece.newline_after || end == pieces.len() {
+                break;
+            }
+        }
+
+        if max_width.is_some_and(|max| start < i && width + content_width > max) {
+            lines.push(start..i);
+            start = i;
+            width = 0.0;
+        }
+        width += word_width;
+
+        if pieces[end - 1].newline_after {
+            lines.push(start..end);
+            start = end;
+            width = 0.0;
+        }
+        i = end;
+    }
+
+    if start < pieces.len() || lines.is_empty() {
+        lines.push(start..pieces.len());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_spans() {
+        let text = RichText::new()
+            .with_text("Read the ")
+            .with_span("manual", TextAttributes::default().with_link("help"))
+            .with_text(" now")
+            .with_text("!");
+
+        assert_eq!(text.text(), "Read the manual now!");
+        let ranges = text
+            .spans()
+            .iter()
+            .map(|span| span.range.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, [0..9, 9..15, 15..20]);
+        assert_eq!(
+            text.span_at(10)
+                .and_then(|span| span.attributes.link.as_deref()),
+            Some("help")
+        );
+    }
+
+    #[test]
+    fn split_and_break() {
+        let text = "one two\n\nthree";
+        assert_eq!(
+            split_words(text, 0..text.len()),
+            [(0..4, false), (4..7, true), (8..8, true), (9..14, false)]
+        );
+        assert_eq!(split_words(text, 2..6), [(2..4, false), (4..6, false)]);
+
+        let word = |width: f32, space: f32, break_after: bool| Piece {
+            width: width + space,
+            content_width: width,
+            break_after,
+            newline_after: false,
+        };
+        // "aa bbcc dd", where "bb" and "cc" are in different spans.
+        let pieces = [
+            word(2.0, 1.0, true),
+            word(2.0, 0.0, false),
+            word(2.0, 1.0, true),
+            word(2.0, 0.0, false),
+        ];
+        assert_eq!(break_lines(&pieces, None), vec![Range { start: 0, end: 4 }]);
+        assert_eq!(break_lines(&pieces, Some(7.0)), [0..3, 3..4]);
+        // A word split across spans stays together.
+        assert_eq!(break_lines(&pieces, Some(5.0)), [0..1, 1..3, 3..4]);
+        assert_eq!(break_lines(&pieces, Some(1.0)), [0..1, 1..3, 3..4]);
+
+        let mut paragraphs = pieces;
+        paragraphs[0].newline_after = true;
+        assert_eq!(break_lines(&paragraphs, None), [0..1, 1..4]);
+        assert_eq!(
+            break_lines(&[], Some(10.0)),
+            vec![Range { start: 0, end: 0 }]
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::ops::Range;
+
+use rustybuzz::{Direction, UnicodeBuffer};
+use unicode_bidi::{BidiInfo, ParagraphInfo};
+
+use crate::gfx::geometry::PointF;
+
+use super::{FontCollection, FontId, GlyphId};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapedGlyph {
+    pub glyph: GlyphId,
+    /// The byte offset in the text of the first character that produced this
+    /// glyph. Glyphs that were produced from the same characters, such as a
+    /// ligature's components or a base character and its marks, share a
+    /// cluster.
+    pub cluster: usize,
+    /// How far to move the pen after drawing the glyph, in pixels.
+    pub advance: f32,
+    /// Where to draw the glyph relative to the pen, in pixels. Positive `y`
+    /// points down.
+    pub offset: PointF,
+}
+
+/// A sequence of glyphs that are drawn with one font in one direction.
+#[derive(Clone, Debug)]
+pub struct ShapedRun {
+    pub font: FontId,
+    /// The byte range of the text that the run was shaped from.
+    pub range: Range<usize>,
+    pub rtl: bool,
+    /// The glyphs in the run, in visual (left-to-right) order.
+    pub glyphs: Vec<ShapedGlyph>,
+    /// The sum of the advances of every glyph, in pixels.
+    pub width: f32,
+}
+
+/// A line of shaped text, ready to be positioned by the layout engine.
+#[derive(Clone, Debug, Default)]
+pub struct ShapedLine {
+    /// The byte range of the text that the line was shaped from, excluding the
+    /// line break.
+    pub range: Range<usize>,
+    /// The runs in the line, in visual (left-to-right) order.
+    pub runs: Vec<ShapedRun>,
+    /// The sum of the widths of every run, in pixels.
+    pub width: f32,
+}
+
+/// Shapes `text` at `size` pixels per em, preferring `font` and falling back to
+/// other fonts in the collection for missing characters.
+///
+/// Each paragraph of the text is shaped into its own line. Bidirectional text
+/// is split into runs with the Unicode Bidirectional Algorithm and reordered
+/// for display, and each run is shaped so that ligatures, kerning, and the
+/// contextual forms of scripts such as Arabic and Devanagari are applied.
+#[must_use]
+pub fn shape(fonts: &FontCollection, font: FontId, size: f32, text: &str) -> Vec<ShapedLine> {
+    let bidi = BidiInfo::new(text, None);
+
+    let mut lines = Vec::with_capacity(bidi.paragraphs.len() + 1);
+    for paragraph in &bidi.paragraphs {
+        let range = trim_line_break(text, paragraph.range.clone());
+        lines.push(shape_line(fonts, font, size, &bidi, paragraph, range));
+    }
+
+    // Text that ends with a line break (or is empty) has an empty last line,
+    // which the bidi algorithm doesn't report as a paragraph.
+    if text.is_empty() || lines.last().is_some_and(|l| l.range.end < text.len()) {
+        lines.push(ShapedLine {
+            range: text.len()..text.len(),
+            ..ShapedLine::default()
+        });
+    }
+
+    lines
+}
+
+fn shape_line(
+    fonts: &FontCollection,
+    font: FontId,
+    size: f32,
+    bidi: &BidiInfo,
+    paragraph: &ParagraphInfo,
+    range: Range<usize>,
+) -> ShapedLine {
+    let mut line = ShapedLine {
+        range: range.clone(),
+        ..ShapedLine::default()
+    };
+
+    if range.is_empty() {
+        return line;
+    }
+
+    let (_, level_runs) = bidi.visual_runs(paragraph, range);
+    for level_run in level_runs {
+        let rtl = bidi.levels[level_run.start].is_rtl();
+
+        // Font runs are in logical order, so they must be reversed to display
+        // right-to-left text.
+        let mut font_runs = fonts.runs(font, &bidi.text[level_run.clone()]);
+        if rtl {
+            font_runs.reverse();
+        }
+
+        for run in font_runs {
+            let range = level_run.start + run.range.start..level_run.start + run.range.end;
+            let run = shape_run(fonts, run.font, size, bidi.text, range, rtl);
+            line.width += run.width;
+            line.runs.push(run);
+        }
+    }
+
+    line
+}
+
+fn shape_run(
+    fonts: &FontCollection,
+    font_id: FontId,
+    size: f32,
+    text: &str,
+    range: Range<usize>,
+    rtl: bool,
+) -> ShapedRun {
+    let font = fonts.font(font_id);
+
+    let mut run = ShapedRun {
+        font: font_id,
+        range: range.clone(),
+        rtl,
+        glyphs: Vec::new(),
+        width: 0.0,
+    };
+
+    // The font was already parsed when it was loaded, so this only fails if
+    // the shaper is stricter about the font's tables. The run is left empty
+    // in that case.
+    let face = match rustybuzz::Face::from_slice(font.data(), font.index()) {
+        Some(face) => face,
+        None => return run,
+    };
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(&text[range.clone()]);
+    buffer.set_direction(if rtl {
+        Direction::RightToLeft
+    } else {
+        Direction::LeftToRight
+    });
+    buffer.guess_segment_properties();
+
+    let output = rustybuzz::shape(&face, &[], buffer);
+
+    // Positions are reported in font units.
+    let scale = size / f32::from(font.metrics().units_per_em);
+
+    run.glyphs.extend(
+        output
+            .glyph_infos()
+            .iter()
+            .zip(output.glyph_positions())
+            .map(|(info, position)| ShapedGlyph {
+                glyph: GlyphId(info.glyph_id as u16),
+                cluster: range.start + info.cluster as usize,
+                advance: position.x_advance as f32 * scale,
+                offset: PointF::new(
+                    position.x_offset as f32 * scale,
+                    -position.y_offset as f32 * scale,
+                ),
+            }),
+    );
+
+    run.width = run.glyphs.iter().map(|glyph| glyph.advance).sum();
+    run
+}
+
+/// Removes the paragraph separator from the end of a paragraph's range.
+fn trim_line_break(text: &str, mut range: Range<usize>) -> Range<usize> {
+    let paragraph = &text[range.clone()];
+
+    if paragraph.ends_with("\r\n") {
+        range.end -= 2;
+    } else if let Some(c) = paragraph.chars().next_back() {
+        if matches!(c, '\n' | '\r' | '\u{1c}'..='\u{1e}' | '\u{85}' | '\u{2029}') {
+            range.end -= c.len_utf8();
+        }
+    }
+
+    range
+}